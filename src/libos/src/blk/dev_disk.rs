@@ -7,56 +7,73 @@ use crate::util::sgx::get_autokey;
 use core::any::Any;
 use rcore_fs::vfs::{self, FileType, INode, Metadata, Timespec};
 use std::path::PathBuf;
+use std::untrusted::path::PathEx;
 use sworndisk_v2::{AeadKey, SwornDisk, BLOCK_SIZE};
 
 lazy_static! {
-    pub static ref SWORNDISK: RwLock<Option<Arc<SwornDisk<RawDisk>>>> = { RwLock::new(None) };
-    pub static ref SWORNDISK_METADATA: RwLock<SwornDiskMeta> =
-        { RwLock::new(SwornDiskMeta::default()) };
+    /// All the SwornDisks that have been set up. The index of a SwornDisk is the
+    /// number in its device name.
+    static ref SWORNDISKS: RwLock<Vec<SwornDiskSlot>> = RwLock::new(Vec::new());
 }
 
-pub const DEV_SWORNDISK: &str = "sworndisk";
+/// The prefix of the device names of the SwornDisks, i.e., sworndisk0, sworndisk1, ...
+pub const DEV_SWORNDISK_PREFIX: &str = "sworndisk";
+/// The device name of the only SwornDisk before multiple ones were supported, which is
+/// kept as an alias of sworndisk0.
+pub const DEV_SWORNDISK_ALIAS: &str = "sworndisk";
+
+// The name of the image file in the image dir of a SwornDisk
+const SWORNDISK_IMAGE_NAME: &str = "sworndisk.image";
+
+struct SwornDiskSlot {
+    meta: SwornDiskMeta,
+    // Opened on the first use
+    disk: Option<Arc<SwornDisk<RawDisk>>>,
+}
 
 /// Block device wrapper.
 pub struct DevDisk {
     disk: Arc<dyn BlockDevice>,
+    index: usize,
 }
 
 impl DevDisk {
     pub fn open_or_create(name: &str) -> Result<Self> {
-        let disk: Arc<dyn BlockDevice> = match name {
-            // Currently only support SwornDisk
-            DEV_SWORNDISK => {
-                let mut sworndisk_opt = SWORNDISK.write().unwrap();
-                if let Some(sworndisk) = sworndisk_opt.as_ref() {
-                    sworndisk.clone()
-                } else {
-                    let metadata = SWORNDISK_METADATA.read().unwrap();
-                    if !metadata.is_setup {
-                        return_errno!(EINVAL, "SwornDisk not set up");
-                    }
-                    let total_blocks = metadata.size / BLOCK_SIZE;
-                    let image_path = {
-                        let mut path = metadata.image_dir.clone();
-                        path.push("sworndisk.image");
-                        path
-                    };
-                    let raw_disk =
-                        RawDisk::open_or_create(total_blocks, image_path.to_str().unwrap())?;
-                    let root_key = metadata.root_key;
-
-                    let sworndisk = Arc::new(
-                        SwornDisk::open(raw_disk.clone(), root_key, None).unwrap_or_else(|_e| {
-                            SwornDisk::create(raw_disk, root_key, None).unwrap()
-                        }),
-                    );
-                    sworndisk_opt.insert(sworndisk.clone());
-                    sworndisk
-                }
-            }
-            _ => return_errno!(EINVAL, "Unrecognized block device"),
+        // Currently only support SwornDisk
+        let index =
+            sworndisk_index(name).ok_or_else(|| errno!(EINVAL, "Unrecognized block device"))?;
+        let mut sworndisks = SWORNDISKS.write().unwrap();
+        let slot = sworndisks
+            .get_mut(index)
+            .ok_or_else(|| errno!(EINVAL, "SwornDisk not set up"))?;
+        let disk: Arc<dyn BlockDevice> = if let Some(sworndisk) = slot.disk.as_ref() {
+            sworndisk.clone()
+        } else {
+            let metadata = &slot.meta;
+            let total_blocks = metadata.size / BLOCK_SIZE;
+            let image_path = metadata.image_dir.join(SWORNDISK_IMAGE_NAME);
+            // A disk is created only if there is no image. Failing to open an existing
+            // image, e.g., with a wrong key, must not wipe it.
+            let is_new = !image_path.exists();
+            let raw_disk = RawDisk::open_or_create(total_blocks, image_path.to_str().unwrap())?;
+            let root_key = AeadKey::from(metadata.root_key);
+
+            let sworndisk = if is_new {
+                SwornDisk::create(raw_disk, root_key, None)
+                    .map_err(|_| errno!(EIO, "failed to create SwornDisk"))?
+            } else {
+                SwornDisk::open(raw_disk, root_key, None).map_err(|_| {
+                    errno!(
+                        EACCES,
+                        "failed to open SwornDisk, the key may be wrong or the image is corrupted"
+                    )
+                })?
+            };
+            let sworndisk = Arc::new(sworndisk);
+            slot.disk.insert(sworndisk.clone());
+            sworndisk
         };
-        Ok(Self { disk })
+        Ok(Self { disk, index })
     }
 
     pub fn disk(&self) -> Arc<dyn BlockDevice> {
@@ -90,7 +107,7 @@ impl INode for DevDisk {
     fn metadata(&self) -> vfs::Result<Metadata> {
         Ok(Metadata {
             dev: 0,
-            inode: 0xfe23_1d08,
+            inode: 0xfe23_1d08 + self.index,
             size: self.disk.total_bytes(),
             blk_size: BLOCK_SIZE,
             blocks: self.disk.total_blocks(),
@@ -133,50 +150,85 @@ fn rw_args_block_aligned(offset: usize, buf_len: usize) -> bool {
 #[derive(Debug)]
 pub struct SwornDiskMeta {
     size: usize,
-    root_key: AeadKey,
+    root_key: sgx_key_128bit_t,
     image_dir: PathBuf,
-    is_setup: bool,
-}
-
-impl Default for SwornDiskMeta {
-    fn default() -> Self {
-        Self {
-            size: 0,
-            root_key: AeadKey::default(),
-            image_dir: PathBuf::from("run"),
-            is_setup: false,
-        }
-    }
 }
 
 impl SwornDiskMeta {
+    /// Set up a SwornDisk whose image is put in the given dir, and return its device
+    /// name. If a SwornDisk has been set up with the same image dir, it is returned
+    /// as long as the given size and key, if any, are the same.
     pub fn setup(
-        disk_size: u64,
+        disk_size: Option<u64>,
         user_key: &Option<sgx_key_128bit_t>,
         source_path: Option<&PathBuf>,
-    ) -> Result<()> {
-        let mut metadata = SWORNDISK_METADATA.write().unwrap();
-        if metadata.is_setup {
-            return_errno!(EEXIST, "SwornDisk already set up");
-        };
+    ) -> Result<String> {
+        let image_dir = source_path.cloned().unwrap_or_else(|| PathBuf::from("run"));
+
+        let mut sworndisks = SWORNDISKS.write().unwrap();
+        let existing_slot = sworndisks
+            .iter()
+            .position(|slot| slot.meta.image_dir == image_dir);
+        if let Some(index) = existing_slot {
+            let metadata = &sworndisks[index].meta;
+            if disk_size.map_or(false, |size| size as usize != metadata.size) {
+                return_errno!(EBUSY, "SwornDisk already set up with another size");
+            }
+            if user_key.map_or(false, |key| key != metadata.root_key) {
+                return_errno!(EBUSY, "SwornDisk already set up with another key");
+            }
+            return Ok(sworndisk_name(index));
+        }
+
+        let disk_size =
+            disk_size.ok_or_else(|| errno!(EINVAL, "Disk size is expected for SwornDisk"))?;
         if disk_size < (5 * GB) as _ {
             return_errno!(EINVAL, "Disk size too small for SwornDisk");
         };
-        metadata.size = disk_size as _;
-        if let Some(source_path) = source_path {
-            metadata.image_dir = source_path.clone();
-        }
         let root_key = if let Some(user_key) = user_key {
             *user_key
         } else {
-            get_autokey(&metadata.image_dir)?
+            get_autokey(&image_dir)?
         };
-        metadata.root_key = AeadKey::from(root_key);
-        metadata.is_setup = true;
-        Ok(())
+        sworndisks.push(SwornDiskSlot {
+            meta: SwornDiskMeta {
+                size: disk_size as _,
+                root_key,
+                image_dir,
+            },
+            disk: None,
+        });
+        Ok(sworndisk_name(sworndisks.len() - 1))
+    }
+
+    /// The image dir of a SwornDisk that has been set up.
+    pub fn image_dir_of(name: &str) -> Result<PathBuf> {
+        let sworndisks = SWORNDISKS.read().unwrap();
+        let slot = sworndisk_index(name)
+            .and_then(|index| sworndisks.get(index))
+            .ok_or_else(|| errno!(ENODEV, "SwornDisk not set up"))?;
+        Ok(slot.meta.image_dir.clone())
     }
 
-    pub fn is_setup() -> bool {
-        SWORNDISK_METADATA.read().unwrap().is_setup
+    /// The device names of all the SwornDisks that have been set up.
+    pub fn all_names() -> Vec<String> {
+        let num_sworndisks = SWORNDISKS.read().unwrap().len();
+        (0..num_sworndisks).map(sworndisk_name).collect()
+    }
+}
+
+fn sworndisk_name(index: usize) -> String {
+    format!("{}{}", DEV_SWORNDISK_PREFIX, index)
+}
+
+fn sworndisk_index(name: &str) -> Option<usize> {
+    if name == DEV_SWORNDISK_ALIAS {
+        return Some(0);
+    }
+    let index = name.strip_prefix(DEV_SWORNDISK_PREFIX)?;
+    // Reject the names like "sworndisk01"
+    if index.len() > 1 && index.starts_with('0') {
+        return None;
     }
+    index.parse().ok()
 }
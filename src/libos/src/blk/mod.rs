@@ -2,7 +2,7 @@
 mod dev_disk;
 mod raw_disk;
 
-pub use self::dev_disk::{DevDisk, SwornDiskMeta, DEV_SWORNDISK};
+pub use self::dev_disk::{DevDisk, SwornDiskMeta, DEV_SWORNDISK_ALIAS, DEV_SWORNDISK_PREFIX};
 pub use self::raw_disk::RawDisk;
 
 pub use ext2_rs::{Bid, BlockDevice, BlockDeviceExt};
@@ -101,6 +101,24 @@ pub fn parse_key(key_str: &str) -> Result<sgx_key_128bit_t> {
     Ok(key)
 }
 
+/// Parse a UUID in the format of "xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx".
+pub fn parse_uuid(uuid_str: &str) -> Result<[u8; 16]> {
+    let group_lens: Vec<usize> = uuid_str.split("-").map(|group| group.len()).collect();
+    if group_lens != [8, 4, 4, 4, 12] {
+        return_errno!(EINVAL, "The length or format of UUID string is invalid");
+    }
+
+    let hex_str = uuid_str.replace("-", "");
+    let mut uuid = [0u8; 16];
+    for (byte_i, byte) in uuid.iter_mut().enumerate() {
+        let byte_str = hex_str
+            .get(byte_i * 2..byte_i * 2 + 2)
+            .ok_or_else(|| errno!(EINVAL, "The UUID string is invalid"))?;
+        *byte = u8::from_str_radix(byte_str, 16).map_err(|e| errno!(e))?;
+    }
+    Ok(uuid)
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub resource_limits: ConfigResourceLimits,
@@ -108,6 +126,7 @@ pub struct Config {
     pub env: ConfigEnv,
     pub app: Vec<ConfigApp>,
     pub feature: ConfigFeature,
+    pub network: ConfigNetwork,
 }
 
 #[derive(Debug)]
@@ -151,6 +170,60 @@ pub struct ConfigFeature {
     pub io_uring: u32,
     pub enable_edmm: bool,
     pub enable_posix_shm: bool,
+    pub enable_loopback_in_enclave: bool,
+}
+
+/// The network policy, which decides whether the app may communicate with an address.
+///
+/// The rules are matched in order, and the action of the first matching rule is taken.
+/// If no rule matches, the default action is taken.
+#[derive(Clone, Debug)]
+pub struct ConfigNetwork {
+    pub default_action: ConfigNetworkAction,
+    pub rules: Vec<ConfigNetworkRule>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigNetworkAction {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigNetworkDirection {
+    // Connecting or sending to a remote address
+    Egress,
+    // Binding a local address, or accepting a connection from a remote address
+    Ingress,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigNetworkProtocol {
+    Tcp,
+    Udp,
+    Unix,
+}
+
+/// A rule of the network policy. A field of `None` matches anything.
+#[derive(Clone, Debug)]
+pub struct ConfigNetworkRule {
+    pub action: ConfigNetworkAction,
+    pub direction: Option<ConfigNetworkDirection>,
+    pub protocol: Option<ConfigNetworkProtocol>,
+    pub cidr: Option<ConfigCidr>,
+    // The inclusive range of ports
+    pub ports: Option<(u16, u16)>,
+    // The path of a unix socket, which matches the paths with the prefix if it ends
+    // with `*`. An abstract address is written as `@name`.
+    pub path: Option<String>,
+}
+
+/// A block of IP addresses. IPv4 addresses are stored as IPv4-mapped IPv6 addresses, so
+/// that an IPv4 block also matches the mapped addresses used by IPv6 sockets.
+#[derive(Clone, Copy, Debug)]
+pub struct ConfigCidr {
+    pub ip: [u8; 16],
+    pub prefix_len: u32,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -183,6 +256,18 @@ impl ConfigMountFsType {
         };
         Ok(type_)
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigMountFsType::TYPE_SEFS => "sefs",
+            ConfigMountFsType::TYPE_HOSTFS => "hostfs",
+            ConfigMountFsType::TYPE_RAMFS => "ramfs",
+            ConfigMountFsType::TYPE_UNIONFS => "unionfs",
+            ConfigMountFsType::TYPE_DEVFS => "devfs",
+            ConfigMountFsType::TYPE_PROCFS => "procfs",
+            ConfigMountFsType::TYPE_EXT2 => "ext2",
+        }
+    }
 }
 
 #[derive(Clone, Default, Debug)]
@@ -192,7 +277,21 @@ pub struct ConfigMountOptions {
     pub temporary: bool,
     pub cache_size: Option<u64>,
     pub disk_size: Option<u64>,
+    /// The key of the SwornDisk of an Ext2
+    pub key: Option<sgx_key_128bit_t>,
     pub index: u32,
+    pub mkfs: ConfigMkfsOptions,
+}
+
+/// The parameters to format the disk of an Ext2, which are used only if the disk
+/// has not been formatted.
+#[derive(Clone, Default, Debug)]
+pub struct ConfigMkfsOptions {
+    pub block_size: Option<usize>,
+    pub inodes_count: Option<u32>,
+    pub reserved_ratio: Option<u32>,
+    pub label: Option<String>,
+    pub uuid: Option<[u8; 16]>,
 }
 
 impl Config {
@@ -208,6 +307,7 @@ impl Config {
             app
         };
         let feature = ConfigFeature::from_input(&input.feature)?;
+        let network = ConfigNetwork::from_input(&input.network)?;
 
         if input.disable_log {
             log::set_max_level(LevelFilter::Off);
@@ -229,6 +329,7 @@ impl Config {
             env,
             app,
             feature,
+            network,
         })
     }
 
@@ -314,10 +415,150 @@ impl ConfigFeature {
             io_uring: input.io_uring,
             enable_edmm: input.enable_edmm,
             enable_posix_shm: input.enable_posix_shm,
+            enable_loopback_in_enclave: input.enable_loopback_in_enclave,
+        })
+    }
+}
+
+impl ConfigNetwork {
+    fn from_input(input: &InputConfigNetwork) -> Result<ConfigNetwork> {
+        let default_action = ConfigNetworkAction::from_input(&input.default_action)?;
+        let rules = {
+            let mut rules = Vec::new();
+            for input_rule in &input.rules {
+                rules.push(ConfigNetworkRule::from_input(input_rule)?);
+            }
+            rules
+        };
+        Ok(ConfigNetwork {
+            default_action,
+            rules,
+        })
+    }
+}
+
+impl ConfigNetworkAction {
+    fn from_input(input: &str) -> Result<ConfigNetworkAction> {
+        Ok(match input {
+            "allow" => ConfigNetworkAction::Allow,
+            "deny" => ConfigNetworkAction::Deny,
+            _ => return_errno!(EINVAL, "Unsupported network policy action"),
+        })
+    }
+}
+
+impl ConfigNetworkDirection {
+    fn from_input(input: &str) -> Result<Option<ConfigNetworkDirection>> {
+        Ok(match input {
+            "egress" => Some(ConfigNetworkDirection::Egress),
+            "ingress" => Some(ConfigNetworkDirection::Ingress),
+            "any" => None,
+            _ => return_errno!(EINVAL, "Unsupported network policy direction"),
+        })
+    }
+}
+
+impl ConfigNetworkProtocol {
+    fn from_input(input: &str) -> Result<Option<ConfigNetworkProtocol>> {
+        Ok(match input {
+            "tcp" => Some(ConfigNetworkProtocol::Tcp),
+            "udp" => Some(ConfigNetworkProtocol::Udp),
+            "unix" => Some(ConfigNetworkProtocol::Unix),
+            "any" => None,
+            _ => return_errno!(EINVAL, "Unsupported network policy protocol"),
+        })
+    }
+}
+
+impl ConfigNetworkRule {
+    fn from_input(input: &InputConfigNetworkRule) -> Result<ConfigNetworkRule> {
+        let action = ConfigNetworkAction::from_input(&input.action)?;
+        let direction = match input.direction.as_ref() {
+            Some(direction) => ConfigNetworkDirection::from_input(direction)?,
+            None => None,
+        };
+        let protocol = match input.protocol.as_ref() {
+            Some(protocol) => ConfigNetworkProtocol::from_input(protocol)?,
+            None => None,
+        };
+        let cidr = match input.cidr.as_ref() {
+            Some(cidr) => Some(parse_cidr(cidr)?),
+            None => None,
+        };
+        let ports = match input.ports.as_ref() {
+            Some(ports) => Some(parse_port_range(ports)?),
+            None => None,
+        };
+
+        // A rule matches either internet addresses or unix socket paths
+        let is_inet_rule = cidr.is_some()
+            || ports.is_some()
+            || protocol == Some(ConfigNetworkProtocol::Tcp)
+            || protocol == Some(ConfigNetworkProtocol::Udp);
+        let is_unix_rule = input.path.is_some() || protocol == Some(ConfigNetworkProtocol::Unix);
+        if is_inet_rule && is_unix_rule {
+            return_errno!(
+                EINVAL,
+                "Network policy rule mixes unix paths and IP addresses"
+            );
+        }
+
+        Ok(ConfigNetworkRule {
+            action,
+            direction,
+            protocol,
+            cidr,
+            ports,
+            path: input.path.clone(),
         })
     }
 }
 
+// Parse a block of IP addresses like "10.0.0.0/8" or "fe80::/10". A single address
+// without the prefix length is also accepted.
+fn parse_cidr(cidr_str: &str) -> Result<ConfigCidr> {
+    let (ip_str, prefix_len_str) = match cidr_str.trim().split_once('/') {
+        Some((ip_str, prefix_len_str)) => (ip_str, Some(prefix_len_str)),
+        None => (cidr_str.trim(), None),
+    };
+    let (ip, max_prefix_len) = match ip_str.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ipv4)) => (ipv4.to_ipv6_mapped().octets(), 32),
+        Ok(std::net::IpAddr::V6(ipv6)) => (ipv6.octets(), 128),
+        Err(_) => return_errno!(EINVAL, "Invalid IP address in CIDR"),
+    };
+    let prefix_len = match prefix_len_str {
+        Some(prefix_len_str) => match prefix_len_str.parse::<u32>() {
+            Ok(prefix_len) if prefix_len <= max_prefix_len => prefix_len,
+            _ => return_errno!(EINVAL, "Invalid prefix length in CIDR"),
+        },
+        None => max_prefix_len,
+    };
+    // The prefix length of an IPv4 block counts the mapped prefix
+    let prefix_len = prefix_len + (128 - max_prefix_len);
+    Ok(ConfigCidr { ip, prefix_len })
+}
+
+// Parse a port like "443", or an inclusive range of ports like "8000-8080"
+fn parse_port_range(ports_str: &str) -> Result<(u16, u16)> {
+    let parse_port = |port_str: &str| {
+        port_str
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| errno!(EINVAL, "Invalid port"))
+    };
+    let (start, end) = match ports_str.split_once('-') {
+        Some((start_str, end_str)) => (parse_port(start_str)?, parse_port(end_str)?),
+        None => {
+            let port = parse_port(ports_str)?;
+            (port, port)
+        }
+    };
+    if start > end {
+        return_errno!(EINVAL, "Invalid port range");
+    }
+    Ok((start, end))
+}
+
 impl ConfigMount {
     fn from_input(input: &InputConfigMount) -> Result<ConfigMount> {
         let type_ = ConfigMountFsType::from_input(input.type_.as_str())?;
@@ -371,18 +612,43 @@ impl ConfigMountOptions {
         } else {
             None
         };
+        let key = if input.key.is_some() {
+            Some(parse_key(input.key.as_ref().unwrap())?)
+        } else {
+            None
+        };
+        let mkfs = ConfigMkfsOptions::from_input(&input.mkfs)?;
         Ok(ConfigMountOptions {
             mac,
             layers,
             temporary: input.temporary,
             cache_size,
             disk_size,
+            key,
             index: input.index,
+            mkfs,
         })
     }
 }
 
-fn parse_memory_size(mem_str: &str) -> Result<usize> {
+impl ConfigMkfsOptions {
+    fn from_input(input: &InputConfigMkfsOptions) -> Result<ConfigMkfsOptions> {
+        let uuid = if let Some(uuid_str) = input.uuid.as_ref() {
+            Some(parse_uuid(uuid_str)?)
+        } else {
+            None
+        };
+        Ok(ConfigMkfsOptions {
+            block_size: input.block_size,
+            inodes_count: input.inodes_count,
+            reserved_ratio: input.reserved_ratio,
+            label: input.label.clone(),
+            uuid,
+        })
+    }
+}
+
+pub fn parse_memory_size(mem_str: &str) -> Result<usize> {
     const UNIT2FACTOR: [(&str, usize); 5] = [
         ("KB", 1024),
         ("MB", 1024 * 1024),
@@ -422,6 +688,8 @@ struct InputConfig {
     pub app: Vec<InputConfigApp>,
     #[serde(default)]
     pub feature: InputConfigFeature,
+    #[serde(default)]
+    pub network: InputConfigNetwork,
 }
 
 #[derive(Deserialize, Debug)]
@@ -529,7 +797,26 @@ struct InputConfigMountOptions {
     #[serde(default)]
     pub disk_size: Option<String>,
     #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
     pub index: u32,
+    #[serde(default)]
+    pub mkfs: InputConfigMkfsOptions,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct InputConfigMkfsOptions {
+    #[serde(default)]
+    pub block_size: Option<usize>,
+    #[serde(default)]
+    pub inodes_count: Option<u32>,
+    #[serde(default)]
+    pub reserved_ratio: Option<u32>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -556,6 +843,8 @@ struct InputConfigFeature {
     pub enable_edmm: bool,
     #[serde(default)]
     pub enable_posix_shm: bool,
+    #[serde(default)]
+    pub enable_loopback_in_enclave: bool,
 }
 
 impl Default for InputConfigFeature {
@@ -566,10 +855,52 @@ impl Default for InputConfigFeature {
             io_uring: 0,
             enable_edmm: false,
             enable_posix_shm: false,
+            enable_loopback_in_enclave: false,
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InputConfigNetwork {
+    #[serde(default = "InputConfigNetwork::get_default_action")]
+    pub default_action: String,
+    #[serde(default)]
+    pub rules: Vec<InputConfigNetworkRule>,
+}
+
+impl InputConfigNetwork {
+    // Allow all for compatibility
+    fn get_default_action() -> String {
+        "allow".to_string()
+    }
+}
+
+impl Default for InputConfigNetwork {
+    fn default() -> InputConfigNetwork {
+        InputConfigNetwork {
+            default_action: InputConfigNetwork::get_default_action(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InputConfigNetworkRule {
+    pub action: String,
+    #[serde(default)]
+    pub direction: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub cidr: Option<String>,
+    #[serde(default)]
+    pub ports: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 #[allow(non_camel_case_types)]
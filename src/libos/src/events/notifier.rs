@@ -45,6 +45,13 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
         subscribers.retain(|subscriber| !Weak::ptr_eq(&subscriber.observer, observer));
     }
 
+    /// Move all registered observers, along with their filters and metadata, to another
+    /// notifier.
+    pub fn move_observers_to(&self, other: &Self) {
+        let mut subscribers = self.subscribers.lock();
+        other.subscribers.lock().extend(subscribers.drain(..));
+    }
+
     /// Broadcast an event to all registered observers.
     pub fn broadcast(&self, event: &E) {
         let subscribers = self.subscribers.lock();
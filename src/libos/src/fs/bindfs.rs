@@ -0,0 +1,31 @@
+use super::*;
+use rcore_fs::vfs;
+
+/// A file system whose root is a directory (or a file) of another file system, which
+/// is mounted to make a bind mount.
+///
+/// If the root is an inode of a `MountFS`, the mounts under it are also visible
+/// through the bind mount, i.e., the bind mount is recursive.
+pub struct BindFS {
+    root: Arc<dyn INode>,
+}
+
+impl BindFS {
+    pub fn new(root: Arc<dyn INode>) -> Arc<Self> {
+        Arc::new(Self { root })
+    }
+}
+
+impl FileSystem for BindFS {
+    fn sync(&self) -> vfs::Result<()> {
+        self.root.fs().sync()
+    }
+
+    fn root_inode(&self) -> Arc<dyn INode> {
+        self.root.clone()
+    }
+
+    fn info(&self) -> vfs::FsInfo {
+        self.root.fs().info()
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Weak;
+use std::time::Duration;
 
 use ringbuf::{Consumer as RbConsumer, Producer as RbProducer, RingBuffer};
 
@@ -185,9 +186,12 @@ macro_rules! impl_end_point_type {
 
 // Just like a normal loop, except that a waiter queue (as well as a waiter)
 // is used to avoid busy loop. This macro is used in the push/pop implementation
-// below.
+// below. The loop ends with ETIMEDOUT if the optional timeout expires.
 macro_rules! waiter_loop {
     ($loop_body: block, $waiter_queue: expr) => {
+        waiter_loop!($loop_body, $waiter_queue, None)
+    };
+    ($loop_body: block, $waiter_queue: expr, $timeout: expr) => {
         // Try without creating a waiter. This saves some CPU cycles if the
         // first attempt succeeds.
         {
@@ -197,6 +201,7 @@ macro_rules! waiter_loop {
         // The main loop
         let waiter = Waiter::new();
         let waiter_queue = $waiter_queue;
+        let mut timeout: Option<Duration> = $timeout;
         loop {
             waiter_queue.reset_and_enqueue(&waiter);
 
@@ -204,7 +209,7 @@ macro_rules! waiter_loop {
                 $loop_body
             }
 
-            waiter.wait(None)?;
+            waiter.wait_mut(timeout.as_mut())?;
         }
     };
 }
@@ -291,6 +296,15 @@ impl<I: Copy> Producer<I> {
     }
 
     pub fn push_slices(&self, item_slices: &[&[I]]) -> Result<usize> {
+        self.push_slices_with_timeout(item_slices, None)
+    }
+
+    /// Push the slices of items, blocking at most for the timeout if it is given.
+    pub fn push_slices_with_timeout(
+        &self,
+        item_slices: &[&[I]],
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
         let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
         if len == 0 {
             return Ok(0);
@@ -324,7 +338,8 @@ impl<I: Copy> Producer<I> {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            timeout
         );
     }
 }
@@ -436,6 +451,15 @@ impl<I: Copy> Consumer<I> {
     }
 
     pub fn pop_slices(&self, item_slices: &mut [&mut [I]]) -> Result<usize> {
+        self.pop_slices_with_timeout(item_slices, None)
+    }
+
+    /// Pop the slices of items, blocking at most for the timeout if it is given.
+    pub fn pop_slices_with_timeout(
+        &self,
+        item_slices: &mut [&mut [I]],
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
         let len: usize = item_slices.iter().map(|slice| slice.len()).sum();
         if len == 0 {
             return Ok(0);
@@ -472,7 +496,8 @@ impl<I: Copy> Consumer<I> {
                     return_errno!(EAGAIN, "try again later");
                 }
             },
-            self.observer.waiter_queue()
+            self.observer.waiter_queue(),
+            timeout
         );
     }
 }
@@ -7,22 +7,30 @@ use rcore_fs_mountfs::MountFS;
 use rcore_fs_ramfs::RamFS;
 
 use self::dev_fd::DevFd;
+use self::dev_mqueue::DevMqueue;
 use self::dev_null::DevNull;
 use self::dev_random::DevRandom;
 use self::dev_sgx::DevSgx;
 use self::dev_shm::DevShm;
 use self::dev_zero::DevZero;
-use blk::DevDisk;
+use blk::{DevDisk, SwornDiskMeta, DEV_SWORNDISK_ALIAS, DEV_SWORNDISK_PREFIX};
+use std::sync::Weak;
 
 mod dev_fd;
+mod dev_mqueue;
 mod dev_null;
 mod dev_random;
 mod dev_sgx;
 mod dev_shm;
 mod dev_zero;
 
+lazy_static! {
+    /// The DevFS that is initialized last, where the block devices set up later are added
+    static ref LATEST_DEVFS: RwLock<Weak<DevFS>> = RwLock::new(Weak::new());
+}
+
 /// API to initialize the DevFS
-pub fn init_devfs(disk_options: &[DevDiskOption]) -> Result<Arc<MountFS>> {
+pub fn init_devfs() -> Result<Arc<MountFS>> {
     let devfs = DevFS::new();
     let dev_null = Arc::new(DevNull) as _;
     devfs.add("null", dev_null)?;
@@ -36,13 +44,14 @@ pub fn init_devfs(disk_options: &[DevDiskOption]) -> Result<Arc<MountFS>> {
     devfs.add("sgx", dev_sgx)?;
     let dev_shm = Arc::new(DevShm) as _;
     devfs.add("shm", dev_shm)?;
+    let dev_mqueue = Arc::new(DevMqueue) as _;
+    devfs.add("mqueue", dev_mqueue)?;
     let dev_fd = Arc::new(DevFd) as _;
     devfs.add("fd", dev_fd);
-    for disk_option in disk_options {
-        let disk_name = &disk_option.name;
-        let dev_disk = Arc::new(DevDisk::open_or_create(disk_name)?);
-        devfs.add(disk_name, dev_disk)?;
+    for disk_name in SwornDiskMeta::all_names() {
+        add_disk_to(&devfs, &disk_name)?;
     }
+    *LATEST_DEVFS.write().unwrap() = Arc::downgrade(&devfs);
     let mountable_devfs = MountFS::new(devfs);
     // Mount the ramfs at '/shm'
     let ramfs = RamFS::new();
@@ -52,17 +61,37 @@ pub fn init_devfs(disk_options: &[DevDiskOption]) -> Result<Arc<MountFS>> {
         &Path::new("/shm"),
         true,
     )?;
+    // Mount the mqueue fs at '/mqueue'
+    let mqueue_fs = Arc::clone(&crate::ipc::MQUEUE_FS);
+    mount_fs_at(
+        mqueue_fs,
+        &mountable_devfs.root_inode(),
+        &Path::new("/mqueue"),
+        true,
+    )?;
     // TODO: Add stdio(stdin, stdout, stderr) into DevFS
     Ok(mountable_devfs)
 }
 
-/// Options of block device under the DevFS.
-pub struct DevDiskOption {
-    name: &'static str,
+/// Add a block device that is set up after the DevFS is initialized.
+pub fn add_disk(disk_name: &str) -> Result<()> {
+    let devfs = match LATEST_DEVFS.read().unwrap().upgrade() {
+        Some(devfs) => devfs,
+        // No DevFS is mounted
+        None => return Ok(()),
+    };
+    if devfs.root_inode().find(disk_name).is_ok() {
+        return Ok(());
+    }
+    add_disk_to(&devfs, disk_name)
 }
 
-impl DevDiskOption {
-    pub fn new(name: &'static str) -> Self {
-        Self { name }
+fn add_disk_to(devfs: &Arc<DevFS>, disk_name: &str) -> Result<()> {
+    let dev_disk: Arc<dyn INode> = Arc::new(DevDisk::open_or_create(disk_name)?);
+    devfs.add(disk_name, dev_disk.clone())?;
+    // The first SwornDisk is also known by its old name
+    if disk_name == format!("{}0", DEV_SWORNDISK_PREFIX) {
+        devfs.add(DEV_SWORNDISK_ALIAS, dev_disk)?;
     }
+    Ok(())
 }
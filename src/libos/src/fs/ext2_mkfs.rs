@@ -0,0 +1,439 @@
+//! A native formatter of Ext2.
+//!
+//! Like `mke2fs -t ext2`, it writes the superblocks, the group descriptors, the
+//! bitmaps, the inode tables, and the root and lost+found directories onto the
+//! block device directly, so no user-space tool is required to set up an Ext2.
+use super::*;
+use crate::blk::{BlockDevice, BlockDeviceExt};
+use crate::config::ConfigMkfsOptions;
+use crate::misc::get_random;
+
+const EXT2_MAGIC: u16 = 0xEF53;
+// The superblock of the first group is always at the byte offset 1024
+const SUPERBLOCK_OFFSET: usize = 1024;
+const SUPERBLOCK_SIZE: usize = 1024;
+const GROUP_DESC_SIZE: usize = 32;
+const INODE_SIZE: usize = 128;
+
+const ROOT_INO: u32 = 2;
+// The first non-reserved inode, which is used by lost+found
+const FIRST_INO: u32 = 11;
+
+// The layout of Ext2 allows the block sizes of 1024 and 2048 too, but ext2-rs accesses
+// an Ext2 in the blocks of the block device
+const SUPPORTED_BLOCK_SIZE: usize = crate::blk::BLOCK_SIZE;
+const DEFAULT_BYTES_PER_INODE: u64 = 16384;
+const DEFAULT_RESERVED_RATIO: u32 = 5;
+const MAX_RESERVED_RATIO: u32 = 50;
+const MAX_LABEL_LEN: usize = 16;
+// Like mke2fs, the last group is dropped if it has so few data blocks
+const MIN_DATA_BLOCKS_OF_LAST_GROUP: u32 = 50;
+
+const FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+const FEATURE_RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+
+const EXT2_FT_DIR: u8 = 2;
+const S_IFDIR: u16 = 0o040000;
+
+/// Format the block device as an empty Ext2.
+pub fn format_ext2(disk: &Arc<dyn BlockDevice>, options: &ConfigMkfsOptions) -> Result<()> {
+    debug!("format Ext2: options: {:?}", options);
+
+    let label = options.label.as_deref().unwrap_or("");
+    if label.len() > MAX_LABEL_LEN {
+        return_errno!(EINVAL, "the label is too long");
+    }
+    let reserved_ratio = options.reserved_ratio.unwrap_or(DEFAULT_RESERVED_RATIO);
+    if reserved_ratio > MAX_RESERVED_RATIO {
+        return_errno!(EINVAL, "the ratio of reserved blocks is too large");
+    }
+    let uuid = match options.uuid {
+        Some(uuid) => uuid,
+        None => {
+            let mut uuid = [0u8; 16];
+            get_random(&mut uuid)?;
+            // A random UUID of version 4
+            uuid[6] = (uuid[6] & 0x0f) | 0x40;
+            uuid[8] = (uuid[8] & 0x3f) | 0x80;
+            uuid
+        }
+    };
+
+    let layout = Layout::new(disk.total_bytes(), options)?;
+    let now = time::do_gettimeofday().sec as u32;
+    let formatter = Formatter {
+        disk,
+        layout,
+        reserved_blocks: (layout.blocks_count as u64 * reserved_ratio as u64 / 100) as u32,
+        uuid,
+        label,
+        now,
+    };
+    formatter.format()
+}
+
+/// The layout of the block groups.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    block_size: usize,
+    blocks_count: u32,
+    first_data_block: u32,
+    blocks_per_group: u32,
+    inodes_per_group: u32,
+    groups_count: u32,
+    gdt_blocks: u32,
+    inode_table_blocks: u32,
+}
+
+impl Layout {
+    fn new(total_bytes: usize, options: &ConfigMkfsOptions) -> Result<Self> {
+        let block_size = options.block_size.unwrap_or(SUPPORTED_BLOCK_SIZE);
+        if block_size != SUPPORTED_BLOCK_SIZE {
+            return_errno!(EINVAL, "the block size must be 4096");
+        }
+        let first_data_block = if block_size == 1024 { 1 } else { 0 };
+        let blocks_per_group = (block_size * 8) as u32;
+        let inodes_per_block = (block_size / INODE_SIZE) as u32;
+        let inodes_count = match options.inodes_count {
+            // The inodes up to lost+found are always there
+            Some(inodes_count) if inodes_count <= FIRST_INO => {
+                return_errno!(EINVAL, "inodes_count is too small");
+            }
+            Some(inodes_count) => inodes_count as u64,
+            None => total_bytes as u64 / DEFAULT_BYTES_PER_INODE,
+        };
+
+        let mut blocks_count = (total_bytes / block_size).min(u32::MAX as usize) as u32;
+        loop {
+            if blocks_count <= first_data_block {
+                return_errno!(ENOSPC, "the disk is too small for Ext2");
+            }
+            let groups_count = div_round_up(blocks_count - first_data_block, blocks_per_group);
+            // The inodes of a group fill whole blocks of the inode table, and are
+            // no more than the bits of the inode bitmap
+            let inodes_per_group = {
+                let inodes_per_group =
+                    div_round_up_u64(inodes_count, groups_count as u64).max(FIRST_INO as u64 + 1);
+                if inodes_per_group > blocks_per_group as u64 {
+                    return_errno!(EINVAL, "inodes_count is too large for the disk");
+                }
+                align_up(inodes_per_group as usize, inodes_per_block.max(8) as usize) as u32
+            };
+            if inodes_per_group as u64 * groups_count as u64 > u32::MAX as u64 {
+                return_errno!(EINVAL, "too many inodes");
+            }
+            let layout = Self {
+                block_size,
+                blocks_count,
+                first_data_block,
+                blocks_per_group,
+                inodes_per_group,
+                groups_count,
+                gdt_blocks: div_round_up(groups_count * GROUP_DESC_SIZE as u32, block_size as u32),
+                inode_table_blocks: inodes_per_group / inodes_per_block,
+            };
+
+            let last_group = groups_count - 1;
+            let min_blocks = layout.overhead_blocks(last_group)
+                + if last_group == 0 { 2 } else { 0 }
+                + MIN_DATA_BLOCKS_OF_LAST_GROUP;
+            if layout.group_blocks(last_group) >= min_blocks {
+                return Ok(layout);
+            }
+            if last_group == 0 {
+                return_errno!(ENOSPC, "the disk is too small for Ext2");
+            }
+            blocks_count = layout.group_first_block(last_group);
+        }
+    }
+
+    fn group_first_block(&self, group: u32) -> u32 {
+        self.first_data_block + group * self.blocks_per_group
+    }
+
+    fn group_blocks(&self, group: u32) -> u32 {
+        (self.blocks_count - self.group_first_block(group)).min(self.blocks_per_group)
+    }
+
+    // With the sparse_super feature, only the groups 0, 1 and the powers of 3, 5
+    // and 7 have the backups of the superblock and the group descriptors
+    fn has_super(&self, group: u32) -> bool {
+        fn is_power_of(mut n: u32, base: u32) -> bool {
+            while n > 1 && n % base == 0 {
+                n /= base;
+            }
+            n == 1
+        }
+        group <= 1 || is_power_of(group, 3) || is_power_of(group, 5) || is_power_of(group, 7)
+    }
+
+    fn block_bitmap(&self, group: u32) -> u32 {
+        let mut block = self.group_first_block(group);
+        if self.has_super(group) {
+            block += 1 + self.gdt_blocks;
+        }
+        block
+    }
+
+    fn inode_bitmap(&self, group: u32) -> u32 {
+        self.block_bitmap(group) + 1
+    }
+
+    fn inode_table(&self, group: u32) -> u32 {
+        self.inode_bitmap(group) + 1
+    }
+
+    // The num of the blocks used by the metadata of a group
+    fn overhead_blocks(&self, group: u32) -> u32 {
+        self.inode_table(group) + self.inode_table_blocks - self.group_first_block(group)
+    }
+
+    fn inodes_count(&self) -> u32 {
+        self.inodes_per_group * self.groups_count
+    }
+
+    fn offset_of(&self, block: u32) -> usize {
+        block as usize * self.block_size
+    }
+}
+
+struct Formatter<'a> {
+    disk: &'a Arc<dyn BlockDevice>,
+    layout: Layout,
+    reserved_blocks: u32,
+    uuid: [u8; 16],
+    label: &'a str,
+    now: u32,
+}
+
+impl Formatter<'_> {
+    fn format(&self) -> Result<()> {
+        let layout = &self.layout;
+        // The root and lost+found directories take the first two data blocks
+        let root_dir_block = layout.inode_table(0) + layout.inode_table_blocks;
+        let lost_found_block = root_dir_block + 1;
+
+        let mut group_descs = Vec::with_capacity(layout.groups_count as usize);
+        for group in 0..layout.groups_count {
+            let used_data_blocks = if group == 0 { 2 } else { 0 };
+            let used_inodes = if group == 0 { FIRST_INO } else { 0 };
+            self.write_bitmap(
+                layout.block_bitmap(group),
+                layout.overhead_blocks(group) + used_data_blocks,
+                layout.group_blocks(group),
+            )?;
+            self.write_bitmap(
+                layout.inode_bitmap(group),
+                used_inodes,
+                layout.inodes_per_group,
+            )?;
+            self.zero_blocks(layout.inode_table(group), layout.inode_table_blocks)?;
+
+            group_descs.push(GroupDesc {
+                block_bitmap: layout.block_bitmap(group),
+                inode_bitmap: layout.inode_bitmap(group),
+                inode_table: layout.inode_table(group),
+                free_blocks: layout.group_blocks(group)
+                    - layout.overhead_blocks(group)
+                    - used_data_blocks,
+                free_inodes: layout.inodes_per_group - used_inodes,
+                used_dirs: if group == 0 { 2 } else { 0 },
+            });
+        }
+
+        self.write_dir(
+            ROOT_INO,
+            root_dir_block,
+            0o755,
+            3,
+            &[(ROOT_INO, "."), (ROOT_INO, ".."), (FIRST_INO, "lost+found")],
+        )?;
+        self.write_dir(
+            FIRST_INO,
+            lost_found_block,
+            0o700,
+            2,
+            &[(FIRST_INO, "."), (ROOT_INO, "..")],
+        )?;
+
+        // The superblock and group descriptors are written at last, so that the
+        // disk is not recognized as an Ext2 if the formatting fails halfway
+        let gdt = {
+            let mut gdt = vec![0u8; layout.offset_of(layout.gdt_blocks)];
+            for (i, group_desc) in group_descs.iter().enumerate() {
+                group_desc.write_to(&mut gdt[i * GROUP_DESC_SIZE..(i + 1) * GROUP_DESC_SIZE]);
+            }
+            gdt
+        };
+        let free_blocks: u32 = group_descs.iter().map(|desc| desc.free_blocks).sum();
+        let free_inodes: u32 = group_descs.iter().map(|desc| desc.free_inodes).sum();
+        for group in (0..layout.groups_count).filter(|group| layout.has_super(*group)) {
+            let first_block = layout.group_first_block(group);
+            self.disk
+                .write_bytes(layout.offset_of(first_block + 1), &gdt)?;
+
+            let superblock = self.superblock(group, free_blocks, free_inodes);
+            let superblock_offset = if group == 0 {
+                SUPERBLOCK_OFFSET
+            } else {
+                layout.offset_of(first_block)
+            };
+            self.disk.write_bytes(superblock_offset, &superblock)?;
+        }
+
+        self.disk.sync()?;
+        Ok(())
+    }
+
+    // Write a bitmap whose first bits are set for the used objects. The bits beyond
+    // the objects of the group are also set.
+    fn write_bitmap(&self, block: u32, used: u32, total: u32) -> Result<()> {
+        let mut bitmap = vec![0u8; self.layout.block_size];
+        let set_bit = |bitmap: &mut [u8], bit: usize| bitmap[bit / 8] |= 1 << (bit % 8);
+        for bit in 0..used as usize {
+            set_bit(&mut bitmap, bit);
+        }
+        for bit in total as usize..self.layout.block_size * 8 {
+            set_bit(&mut bitmap, bit);
+        }
+        self.disk
+            .write_bytes(self.layout.offset_of(block), &bitmap)?;
+        Ok(())
+    }
+
+    fn zero_blocks(&self, block: u32, count: u32) -> Result<()> {
+        const ZERO_BUF_SIZE: usize = 256 * 1024;
+        let zeros = vec![0u8; ZERO_BUF_SIZE];
+        let mut offset = self.layout.offset_of(block);
+        let end = self.layout.offset_of(block + count);
+        while offset < end {
+            let len = (end - offset).min(ZERO_BUF_SIZE);
+            self.disk.write_bytes(offset, &zeros[..len])?;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    // Write a directory of one block with the given entries
+    fn write_dir(
+        &self,
+        ino: u32,
+        block: u32,
+        perm: u16,
+        links: u16,
+        entries: &[(u32, &str)],
+    ) -> Result<()> {
+        let block_size = self.layout.block_size;
+        let mut dir_block = vec![0u8; block_size];
+        let mut offset = 0;
+        for (i, (entry_ino, name)) in entries.iter().enumerate() {
+            // The last entry takes the rest of the block
+            let rec_len = if i == entries.len() - 1 {
+                block_size - offset
+            } else {
+                align_up(8 + name.len(), 4)
+            };
+            let entry = &mut dir_block[offset..offset + rec_len];
+            entry[0..4].copy_from_slice(&entry_ino.to_le_bytes());
+            entry[4..6].copy_from_slice(&(rec_len as u16).to_le_bytes());
+            entry[6] = name.len() as u8;
+            entry[7] = EXT2_FT_DIR;
+            entry[8..8 + name.len()].copy_from_slice(name.as_bytes());
+            offset += rec_len;
+        }
+        self.disk
+            .write_bytes(self.layout.offset_of(block), &dir_block)?;
+
+        let mut inode = [0u8; INODE_SIZE];
+        inode[0..2].copy_from_slice(&(S_IFDIR | perm).to_le_bytes());
+        inode[4..8].copy_from_slice(&(block_size as u32).to_le_bytes());
+        for time_offset in [8, 12, 16] {
+            inode[time_offset..time_offset + 4].copy_from_slice(&self.now.to_le_bytes());
+        }
+        inode[26..28].copy_from_slice(&links.to_le_bytes());
+        // The num of 512-byte sectors
+        inode[28..32].copy_from_slice(&((block_size / 512) as u32).to_le_bytes());
+        inode[40..44].copy_from_slice(&block.to_le_bytes());
+        // The inodes are numbered from 1, and both directories are in the first group
+        let inode_offset =
+            self.layout.offset_of(self.layout.inode_table(0)) + (ino as usize - 1) * INODE_SIZE;
+        self.disk.write_bytes(inode_offset, &inode)?;
+        Ok(())
+    }
+
+    fn superblock(&self, group: u32, free_blocks: u32, free_inodes: u32) -> Vec<u8> {
+        let layout = &self.layout;
+        let mut sb = vec![0u8; SUPERBLOCK_SIZE];
+        let put_u16 = |sb: &mut [u8], offset: usize, val: u16| {
+            sb[offset..offset + 2].copy_from_slice(&val.to_le_bytes())
+        };
+        put_u16(&mut sb, 52, 0); // s_mnt_count
+        put_u16(&mut sb, 54, 0xffff); // s_max_mnt_count, i.e., -1
+        put_u16(&mut sb, 56, EXT2_MAGIC);
+        put_u16(&mut sb, 58, 1); // s_state, i.e., cleanly unmounted
+        put_u16(&mut sb, 60, 1); // s_errors, i.e., continue
+        put_u16(&mut sb, 88, INODE_SIZE as u16);
+        put_u16(&mut sb, 90, group as u16); // s_block_group_nr
+
+        let put_u32 = |sb: &mut [u8], offset: usize, val: u32| {
+            sb[offset..offset + 4].copy_from_slice(&val.to_le_bytes())
+        };
+        put_u32(&mut sb, 0, layout.inodes_count());
+        put_u32(&mut sb, 4, layout.blocks_count);
+        put_u32(&mut sb, 8, self.reserved_blocks);
+        put_u32(&mut sb, 12, free_blocks);
+        put_u32(&mut sb, 16, free_inodes);
+        put_u32(&mut sb, 20, layout.first_data_block);
+        // s_log_block_size and s_log_frag_size
+        let log_block_size = layout.block_size.trailing_zeros() - 10;
+        put_u32(&mut sb, 24, log_block_size);
+        put_u32(&mut sb, 28, log_block_size);
+        put_u32(&mut sb, 32, layout.blocks_per_group);
+        put_u32(&mut sb, 36, layout.blocks_per_group);
+        put_u32(&mut sb, 40, layout.inodes_per_group);
+        put_u32(&mut sb, 48, self.now); // s_wtime
+        put_u32(&mut sb, 64, self.now); // s_lastcheck
+        put_u32(&mut sb, 76, 1); // s_rev_level, i.e., dynamic
+        put_u32(&mut sb, 84, FIRST_INO);
+        put_u32(&mut sb, 96, FEATURE_INCOMPAT_FILETYPE);
+        put_u32(
+            &mut sb,
+            100,
+            FEATURE_RO_COMPAT_SPARSE_SUPER | FEATURE_RO_COMPAT_LARGE_FILE,
+        );
+        put_u32(&mut sb, 264, self.now); // s_mkfs_time
+
+        sb[104..120].copy_from_slice(&self.uuid);
+        sb[120..120 + self.label.len()].copy_from_slice(self.label.as_bytes());
+        sb
+    }
+}
+
+struct GroupDesc {
+    block_bitmap: u32,
+    inode_bitmap: u32,
+    inode_table: u32,
+    free_blocks: u32,
+    free_inodes: u32,
+    used_dirs: u16,
+}
+
+impl GroupDesc {
+    fn write_to(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.block_bitmap.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.inode_bitmap.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.inode_table.to_le_bytes());
+        buf[12..14].copy_from_slice(&(self.free_blocks as u16).to_le_bytes());
+        buf[14..16].copy_from_slice(&(self.free_inodes as u16).to_le_bytes());
+        buf[16..18].copy_from_slice(&self.used_dirs.to_le_bytes());
+    }
+}
+
+fn div_round_up(n: u32, d: u32) -> u32 {
+    div_round_up_u64(n as u64, d as u64) as u32
+}
+
+fn div_round_up_u64(n: u64, d: u64) -> u64 {
+    (n + d - 1) / d
+}
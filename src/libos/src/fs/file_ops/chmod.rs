@@ -75,6 +75,7 @@ pub fn do_fchmodat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
         let fs = current.fs().read().unwrap();
         fs.lookup_inode(&path)?
     };
+    inode.check_mount_writable()?;
     let mut info = inode.metadata()?;
     info.mode = mode.bits();
     inode.set_metadata(&info)?;
@@ -85,6 +86,9 @@ pub fn do_fchmod(fd: FileDesc, mode: FileMode) -> Result<()> {
     debug!("fchmod: fd: {}, mode: {:#o}", fd, mode);
 
     let file_ref = current!().file(fd)?;
+    if let Ok(inode_file) = file_ref.as_inode_file() {
+        inode_file.inode().check_mount_writable()?;
+    }
     let mut info = file_ref.metadata()?;
     info.mode = mode.bits();
     file_ref.set_metadata(&info)?;
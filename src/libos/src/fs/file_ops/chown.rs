@@ -30,6 +30,7 @@ pub fn do_fchownat(fs_path: &FsPath, uid: i32, gid: i32, flags: ChownFlags) -> R
             fs.lookup_inode(&path)?
         }
     };
+    inode.check_mount_writable()?;
     let mut info = inode.metadata()?;
     if let Some(uid) = uid {
         info.uid = uid as usize;
@@ -52,6 +53,9 @@ pub fn do_fchown(fd: FileDesc, uid: i32, gid: i32) -> Result<()> {
     }
 
     let file_ref = current!().file(fd)?;
+    if let Ok(inode_file) = file_ref.as_inode_file() {
+        inode_file.inode().check_mount_writable()?;
+    }
     let mut info = file_ref.metadata()?;
     if let Some(uid) = uid {
         info.uid = uid as usize;
@@ -8,8 +8,8 @@ use super::*;
 
 use self::builtin::*;
 pub use self::builtin::{
-    GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, GetWinSize, IfConf, IoctlCmd, SetNonBlocking,
-    SetWinSize, TcGets, TcSets,
+    GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, GetWinSize, IfConf, IfReq, IoctlCmd,
+    SetNonBlocking, SetWinSize, TcGets, TcSets,
 };
 pub use self::non_builtin::{NonBuiltinIoctlCmd, StructuredIoctlArgType, StructuredIoctlNum};
 use crate::util::mem_util::from_user;
@@ -30,6 +30,9 @@ pub fn do_linkat(old_fs_path: &FsPath, new_fs_path: &FsPath, flags: LinkFlags) -
         let new_dir_inode = fs.lookup_inode(new_dir_path)?;
         (inode, new_dir_inode)
     };
+    new_dir_inode.check_mount_writable()?;
     new_dir_inode.link(new_file_name, &inode)?;
+    inotify::notify_inode(&inode, InotifyMask::IN_ATTRIB);
+    inotify::notify_child(&new_dir_inode, InotifyMask::IN_CREATE, new_file_name, 0);
     Ok(())
 }
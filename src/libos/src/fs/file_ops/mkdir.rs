@@ -16,7 +16,14 @@ pub fn do_mkdirat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
     if !inode.allow_write()? {
         return_errno!(EPERM, "dir cannot be written");
     }
+    inode.check_mount_writable()?;
     let masked_mode = mode & !current.process().umask();
     inode.create(file_name, FileType::Dir, masked_mode.bits())?;
+    inotify::notify_child(
+        &inode,
+        InotifyMask::IN_CREATE | InotifyMask::IN_ISDIR,
+        file_name,
+        0,
+    );
     Ok(())
 }
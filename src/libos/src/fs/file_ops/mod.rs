@@ -15,8 +15,8 @@ pub use self::fsync::{do_fdatasync, do_fsync};
 pub use self::getdents::{do_getdents, do_getdents64};
 pub use self::ioctl::{
     do_ioctl, occlum_ocall_ioctl, BuiltinIoctlNum, GetIfConf, GetIfReqWithRawCmd, GetReadBufLen,
-    GetWinSize, IfConf, IoctlCmd, IoctlRawCmd, NonBuiltinIoctlCmd, SetNonBlocking, SetWinSize,
-    StructuredIoctlArgType, StructuredIoctlNum, TcGets, TcSets,
+    GetWinSize, IfConf, IfReq, IoctlCmd, IoctlRawCmd, NonBuiltinIoctlCmd, SetNonBlocking,
+    SetWinSize, StructuredIoctlArgType, StructuredIoctlNum, TcGets, TcSets,
 };
 pub use self::link::{do_linkat, LinkFlags};
 pub use self::lseek::do_lseek;
@@ -34,6 +34,10 @@ pub use self::utimes::{
     do_utimes_fd, do_utimes_path, get_utimes, utimbuf_t, Utime, UtimeFlags, UTIME_OMIT,
 };
 pub use self::write::{do_pwrite, do_pwritev, do_write, do_writev};
+pub use self::xattr::{
+    do_getxattr, do_listxattr, do_removexattr, do_setxattr, keep_xattrs_in_upper_layer,
+    persist_xattrs_in, XattrFlags, XattrTarget,
+};
 
 mod access;
 mod chmod;
@@ -62,3 +66,4 @@ mod truncate;
 mod unlink;
 mod utimes;
 mod write;
+mod xattr;
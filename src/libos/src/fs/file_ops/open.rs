@@ -13,12 +13,24 @@ pub fn do_openat(fs_path: &FsPath, flags: u32, mode: FileMode) -> Result<FileDes
     let fs = current.fs().read().unwrap();
     let masked_mode = mode & !current.process().umask();
 
+    let creation_flags = CreationFlags::from_bits_truncate(flags);
+    // Check whether the file is to be created only if the creation is to be reported
+    let is_created =
+        inotify::is_watched() && creation_flags.can_create() && fs.lookup_inode(&path).is_err();
+
     let file_ref: Arc<dyn File> = fs.open_file(&path, flags, masked_mode)?;
+    if let Ok(inode_file) = file_ref.as_inode_file() {
+        if is_created {
+            inotify::notify_parent(inode_file.parent(), InotifyMask::IN_CREATE);
+        }
+        inotify::notify_file_event(
+            inode_file.inode(),
+            inode_file.parent(),
+            InotifyMask::IN_OPEN,
+        );
+    }
 
-    let fd = {
-        let creation_flags = CreationFlags::from_bits_truncate(flags);
-        current.add_file(file_ref, creation_flags.must_close_on_spawn())
-    };
+    let fd = current.add_file(file_ref, creation_flags.must_close_on_spawn());
 
     debug!("openat: result fd: {:?}", fd);
     Ok(fd)
@@ -2,14 +2,20 @@ use super::*;
 
 pub fn do_read(fd: FileDesc, buf: &mut [u8]) -> Result<usize> {
     debug!("read: fd: {}", fd);
-    let file_ref = current!().file(fd)?;
-    file_ref.read(buf)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.read(buf)?;
+    current.process().io_stats().add_read(len);
+    Ok(len)
 }
 
 pub fn do_readv(fd: FileDesc, bufs: &mut [&mut [u8]]) -> Result<usize> {
     debug!("readv: fd: {}", fd);
-    let file_ref = current!().file(fd)?;
-    file_ref.readv(bufs)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.readv(bufs)?;
+    current.process().io_stats().add_read(len);
+    Ok(len)
 }
 
 pub fn do_pread(fd: FileDesc, buf: &mut [u8], offset: off_t) -> Result<usize> {
@@ -17,12 +23,18 @@ pub fn do_pread(fd: FileDesc, buf: &mut [u8], offset: off_t) -> Result<usize> {
     if offset < 0 {
         return_errno!(EINVAL, "the offset is negative");
     }
-    let file_ref = current!().file(fd)?;
-    file_ref.read_at(offset as usize, buf)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.read_at(offset as usize, buf)?;
+    current.process().io_stats().add_read(len);
+    Ok(len)
 }
 
 pub fn do_preadv(fd: FileDesc, bufs: &mut [&mut [u8]], offset: off_t) -> Result<usize> {
     debug!("preadv: fd: {}, offset {}", fd, offset);
-    let file_ref = current!().file(fd)?;
-    file_ref.preadv(bufs, offset as usize)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.preadv(bufs, offset as usize)?;
+    current.process().io_stats().add_read(len);
+    Ok(len)
 }
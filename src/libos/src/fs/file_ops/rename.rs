@@ -24,18 +24,58 @@ pub fn do_renameat(old_fs_path: &FsPath, new_fs_path: &FsPath) -> Result<()> {
     let (new_dir_path, new_file_name) = split_path(&newpath.trim_end_matches('/'));
     let old_dir_inode = fs.lookup_inode(old_dir_path)?;
     let new_dir_inode = fs.lookup_inode(new_dir_path)?;
-    let old_file_mode = {
-        let old_file_inode = old_dir_inode.find(old_file_name)?;
+    old_dir_inode.check_mount_writable()?;
+    new_dir_inode.check_mount_writable()?;
+    let old_file_inode = old_dir_inode.find(old_file_name)?;
+    let (old_file_ino, old_file_type, old_file_mode) = {
         let metadata = old_file_inode.metadata()?;
         // oldpath is directory, the old_file_inode should be directory
         if oldpath.ends_with("/") && metadata.type_ != FileType::Dir {
             return_errno!(ENOTDIR, "old path is not a directory");
         }
-        FileMode::from_bits_truncate(metadata.mode)
+        (
+            metadata.inode,
+            metadata.type_,
+            FileMode::from_bits_truncate(metadata.mode),
+        )
     };
     if old_file_mode.has_sticky_bit() {
         warn!("ignoring the sticky bit");
     }
+    // The target to be replaced, if any
+    let replaced_inode = new_dir_inode
+        .find(new_file_name)
+        .ok()
+        .filter(|inode| inode.metadata().map(|m| m.inode).ok() != Some(old_file_ino));
+    let xattrs_holder = replaced_inode
+        .as_ref()
+        .and_then(|inode| super::xattr::find_xattrs_holder(&new_dir_inode, new_file_name, inode));
     old_dir_inode.move_(old_file_name, &new_dir_inode, new_file_name)?;
+
+    let cookie = inotify::new_move_cookie();
+    let isdir_mask = if old_file_type == FileType::Dir {
+        InotifyMask::IN_ISDIR
+    } else {
+        InotifyMask::empty()
+    };
+    inotify::notify_child(
+        &old_dir_inode,
+        InotifyMask::IN_MOVED_FROM | isdir_mask,
+        old_file_name,
+        cookie,
+    );
+    inotify::notify_child(
+        &new_dir_inode,
+        InotifyMask::IN_MOVED_TO | isdir_mask,
+        new_file_name,
+        cookie,
+    );
+    inotify::notify_inode(&old_file_inode, InotifyMask::IN_MOVE_SELF);
+    if let Some(replaced_inode) = replaced_inode {
+        inotify::notify_removed(&replaced_inode);
+    }
+    if let Some(xattrs_holder) = xattrs_holder {
+        super::xattr::release_xattrs_if_removed(&xattrs_holder);
+    }
     Ok(())
 }
@@ -9,10 +9,22 @@ pub fn do_rmdir(path: &str) -> Result<()> {
         let fs = current.fs().read().unwrap();
         fs.lookup_inode(dir_path)?
     };
+    dir_inode.check_mount_writable()?;
     let file_inode = dir_inode.find(file_name)?;
     if file_inode.metadata()?.type_ != FileType::Dir {
         return_errno!(ENOTDIR, "rmdir on not directory");
     }
+    let xattrs_holder = super::xattr::find_xattrs_holder(&dir_inode, file_name, &file_inode);
     dir_inode.unlink(file_name)?;
+    inotify::notify_child(
+        &dir_inode,
+        InotifyMask::IN_DELETE | InotifyMask::IN_ISDIR,
+        file_name,
+        0,
+    );
+    inotify::notify_removed(&file_inode);
+    if let Some(xattrs_holder) = xattrs_holder {
+        super::xattr::release_xattrs_if_removed(&xattrs_holder);
+    }
     Ok(())
 }
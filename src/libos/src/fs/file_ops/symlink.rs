@@ -47,9 +47,11 @@ pub fn do_symlinkat(target: &str, link_path: &FsPath) -> Result<usize> {
     if !dir_inode.allow_write()? {
         return_errno!(EPERM, "symlink cannot be created");
     }
+    dir_inode.check_mount_writable()?;
     let link_inode = dir_inode.create(link_name, FileType::SymLink, 0o0777)?;
     let data = target.as_bytes();
     link_inode.resize(data.len())?;
     link_inode.write_at(0, data)?;
+    inotify::notify_child(&dir_inode, InotifyMask::IN_CREATE, link_name, 0);
     Ok(0)
 }
@@ -2,12 +2,20 @@ use super::*;
 
 pub fn do_truncate(path: &str, len: usize) -> Result<()> {
     debug!("truncate: path: {:?}, len: {}", path, len);
-    let inode = {
+    let (inode, parent) = {
         let current = current!();
         let fs = current.fs().read().unwrap();
-        fs.lookup_inode(&path)?
+        let inode = fs.lookup_inode(&path)?;
+        let parent = if inotify::is_watched() {
+            fs.lookup_parent(path)
+        } else {
+            None
+        };
+        (inode, parent)
     };
+    inode.check_mount_writable()?;
     inode.resize(len)?;
+    inotify::notify_file_event(&inode, parent.as_ref(), InotifyMask::IN_MODIFY);
     Ok(())
 }
 
@@ -15,5 +23,6 @@ pub fn do_ftruncate(fd: FileDesc, len: usize) -> Result<()> {
     debug!("ftruncate: fd: {}, len: {}", fd, len);
     let file_ref = current!().file(fd)?;
     file_ref.set_len(len as u64)?;
+    inotify::notify_file(&file_ref, InotifyMask::IN_MODIFY);
     Ok(())
 }
@@ -16,6 +16,7 @@ fn do_unlink(path: &str) -> Result<()> {
         let fs = current.fs().read().unwrap();
         fs.lookup_inode(dir_path)?
     };
+    dir_inode.check_mount_writable()?;
     let file_inode = dir_inode.find(file_name)?;
     let metadata = file_inode.metadata()?;
     if metadata.type_ == FileType::Dir {
@@ -25,7 +26,13 @@ fn do_unlink(path: &str) -> Result<()> {
     if file_mode.has_sticky_bit() {
         warn!("ignoring the sticky bit");
     }
+    let xattrs_holder = super::xattr::find_xattrs_holder(&dir_inode, file_name, &file_inode);
     dir_inode.unlink(file_name)?;
+    inotify::notify_child(&dir_inode, InotifyMask::IN_DELETE, file_name, 0);
+    inotify::notify_removed(&file_inode);
+    if let Some(xattrs_holder) = xattrs_holder {
+        super::xattr::release_xattrs_if_removed(&xattrs_holder);
+    }
     Ok(())
 }
 
@@ -94,6 +94,9 @@ pub fn do_utimes_fd(fd: FileDesc, atime: Utime, mtime: Utime, flags: i32) -> Res
     }
 
     let file_ref = current!().file(fd)?;
+    if let Ok(inode_file) = file_ref.as_inode_file() {
+        inode_file.inode().check_mount_writable()?;
+    }
     let mut info = file_ref.metadata()?;
     if let Utime::UTIME(atime) = atime {
         info.atime = atime;
@@ -126,6 +129,7 @@ pub fn do_utimes_path(
             fs.lookup_inode(&path)?
         }
     };
+    inode.check_mount_writable()?;
     let mut info = inode.metadata()?;
     if let Utime::UTIME(atime) = atime {
         info.atime = atime;
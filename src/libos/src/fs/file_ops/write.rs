@@ -2,14 +2,26 @@ use super::*;
 
 pub fn do_write(fd: FileDesc, buf: &[u8]) -> Result<usize> {
     debug!("write: fd: {}", fd);
-    let file_ref = current!().file(fd)?;
-    file_ref.write(buf)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.write(buf)?;
+    current.process().io_stats().add_write(len);
+    if len > 0 {
+        inotify::notify_file(&file_ref, InotifyMask::IN_MODIFY);
+    }
+    Ok(len)
 }
 
 pub fn do_writev(fd: FileDesc, bufs: &[&[u8]]) -> Result<usize> {
     debug!("writev: fd: {}", fd);
-    let file_ref = current!().file(fd)?;
-    file_ref.writev(bufs)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.writev(bufs)?;
+    current.process().io_stats().add_write(len);
+    if len > 0 {
+        inotify::notify_file(&file_ref, InotifyMask::IN_MODIFY);
+    }
+    Ok(len)
 }
 
 pub fn do_pwrite(fd: FileDesc, buf: &[u8], offset: off_t) -> Result<usize> {
@@ -17,12 +29,24 @@ pub fn do_pwrite(fd: FileDesc, buf: &[u8], offset: off_t) -> Result<usize> {
     if offset < 0 {
         return_errno!(EINVAL, "the offset is negative");
     }
-    let file_ref = current!().file(fd)?;
-    file_ref.write_at(offset as usize, buf)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.write_at(offset as usize, buf)?;
+    current.process().io_stats().add_write(len);
+    if len > 0 {
+        inotify::notify_file(&file_ref, InotifyMask::IN_MODIFY);
+    }
+    Ok(len)
 }
 
 pub fn do_pwritev(fd: FileDesc, bufs: &[&[u8]], offset: off_t) -> Result<usize> {
     debug!("pwritev: fd: {}, offset {}", fd, offset);
-    let file_ref = current!().file(fd)?;
-    file_ref.pwritev(bufs, offset as usize)
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let len = file_ref.pwritev(bufs, offset as usize)?;
+    current.process().io_stats().add_write(len);
+    if len > 0 {
+        inotify::notify_file(&file_ref, InotifyMask::IN_MODIFY);
+    }
+    Ok(len)
 }
@@ -0,0 +1,681 @@
+use super::*;
+use rcore_fs_mountfs::MNode;
+use rcore_fs_sefs::dev::File as DevFile;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Weak;
+
+// The max length of an attribute name, same with XATTR_NAME_MAX on Linux
+const XATTR_NAME_MAX: usize = 255;
+// The max size of an attribute value, same with XATTR_SIZE_MAX on Linux
+const XATTR_SIZE_MAX: usize = 65536;
+// The max size of the name list, same with XATTR_LIST_MAX on Linux
+const XATTR_LIST_MAX: usize = 65536;
+// The max total size of the names and values of an inode. Like ext2, which keeps all
+// the attributes of an inode in one block, an inode cannot hold unlimited attributes.
+const XATTRS_SIZE_PER_INODE_MAX: usize = 65536;
+// The max total size of the names and values of a file system, which bounds the memory
+// taken by the attributes and the size of the file where they are persisted
+const XATTRS_SIZE_PER_FS_MAX: usize = 1024 * 1024;
+
+// Only the namespaces whose attributes are simply stored are supported. The system
+// namespace, e.g., the POSIX ACLs, is not.
+const XATTR_NAMESPACES: [&str; 3] = ["user.", "trusted.", "security."];
+
+bitflags! {
+    pub struct XattrFlags: i32 {
+        /// Fail if the attribute already exists
+        const XATTR_CREATE = 0x1;
+        /// Fail if the attribute does not exist
+        const XATTR_REPLACE = 0x2;
+    }
+}
+
+/// The file whose extended attributes are accessed.
+#[derive(Debug)]
+pub enum XattrTarget<'a> {
+    /// The path, following the symlink, i.e., setxattr and friends.
+    Path(&'a FsPath<'a>),
+    /// The path, not following the symlink, i.e., lsetxattr and friends.
+    LinkPath(&'a FsPath<'a>),
+    /// The file descriptor, i.e., fsetxattr and friends.
+    Fd(FileDesc),
+}
+
+pub fn do_setxattr(
+    target: &XattrTarget,
+    name: &str,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    debug!(
+        "setxattr: target: {:?}, name: {:?}, size: {}, flags: {:?}",
+        target,
+        name,
+        value.len(),
+        flags
+    );
+
+    check_name(name)?;
+    if value.len() > XATTR_SIZE_MAX {
+        return_errno!(E2BIG, "the value is too large");
+    }
+
+    let inode = target.lookup_inode()?;
+    if !allow_xattr(&inode, name)? {
+        return_errno!(EPERM, "user attributes are only for files and directories");
+    }
+    if !inode.allow_write()? {
+        return_errno!(EACCES, "the file is not writable");
+    }
+    inode.check_mount_writable()?;
+
+    let holder = target
+        .lookup_xattrs_holder(&inode, true)?
+        .ok_or_else(|| errno!(ENOENT, "the file is not copied up"))?;
+    let ino = holder.metadata()?.inode;
+    let mut stores = XATTR_STORES.lock().unwrap();
+    let store = get_or_create_store(&mut stores, &holder);
+    store.set(ino, name, value, flags)
+}
+
+/// Get the value of an attribute. If the buffer is empty, only the size of the value
+/// is returned.
+pub fn do_getxattr(target: &XattrTarget, name: &str, value: &mut [u8]) -> Result<usize> {
+    debug!(
+        "getxattr: target: {:?}, name: {:?}, size: {}",
+        target,
+        name,
+        value.len()
+    );
+
+    check_name(name)?;
+    let inode = target.lookup_inode()?;
+    if !allow_xattr(&inode, name)? {
+        return_errno!(ENODATA, "the attribute does not exist");
+    }
+
+    let holder = target
+        .lookup_xattrs_holder(&inode, false)?
+        .ok_or_else(|| errno!(ENODATA, "the attribute does not exist"))?;
+    let ino = holder.metadata()?.inode;
+    let stores = XATTR_STORES.lock().unwrap();
+    let attr_value = get_store(&stores, &holder)
+        .and_then(|store| store.inodes.get(&ino))
+        .and_then(|xattrs| xattrs.attrs.get(name))
+        .ok_or_else(|| errno!(ENODATA, "the attribute does not exist"))?;
+    if value.is_empty() {
+        return Ok(attr_value.len());
+    }
+    if value.len() < attr_value.len() {
+        return_errno!(ERANGE, "the buffer is too small");
+    }
+    value[..attr_value.len()].copy_from_slice(attr_value);
+    Ok(attr_value.len())
+}
+
+/// Get the names of all the attributes, each of which is null-terminated. If the buffer
+/// is empty, only the size of the names is returned.
+pub fn do_listxattr(target: &XattrTarget, list: &mut [u8]) -> Result<usize> {
+    debug!("listxattr: target: {:?}, size: {}", target, list.len());
+
+    let inode = target.lookup_inode()?;
+    let is_user_allowed = allow_xattr(&inode, "user.")?;
+    let names: Vec<u8> = match target.lookup_xattrs_holder(&inode, false)? {
+        Some(holder) => {
+            let ino = holder.metadata()?.inode;
+            let stores = XATTR_STORES.lock().unwrap();
+            match get_store(&stores, &holder).and_then(|store| store.inodes.get(&ino)) {
+                Some(xattrs) => xattrs
+                    .attrs
+                    .keys()
+                    .filter(|name| is_user_allowed || !name.starts_with("user."))
+                    .flat_map(|name| name.bytes().chain(std::iter::once(0)))
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+        None => Vec::new(),
+    };
+
+    if names.len() > XATTR_LIST_MAX {
+        return_errno!(E2BIG, "the name list is too large");
+    }
+    if list.is_empty() {
+        return Ok(names.len());
+    }
+    if list.len() < names.len() {
+        return_errno!(ERANGE, "the buffer is too small");
+    }
+    list[..names.len()].copy_from_slice(&names);
+    Ok(names.len())
+}
+
+pub fn do_removexattr(target: &XattrTarget, name: &str) -> Result<()> {
+    debug!("removexattr: target: {:?}, name: {:?}", target, name);
+
+    check_name(name)?;
+    let inode = target.lookup_inode()?;
+    if !allow_xattr(&inode, name)? {
+        return_errno!(EPERM, "user attributes are only for files and directories");
+    }
+    if !inode.allow_write()? {
+        return_errno!(EACCES, "the file is not writable");
+    }
+    inode.check_mount_writable()?;
+
+    let holder = target
+        .lookup_xattrs_holder(&inode, false)?
+        .ok_or_else(|| errno!(ENODATA, "the attribute does not exist"))?;
+    let ino = holder.metadata()?.inode;
+    let mut stores = XATTR_STORES.lock().unwrap();
+    let store = get_or_create_store(&mut stores, &holder);
+    store.remove(ino, name)
+}
+
+/// Find the inode that keeps the attributes of the file in the directory. It must be
+/// found before the file is removed, since a file of unionfs is found by its path, and
+/// given to `release_xattrs_if_removed` after that.
+pub fn find_xattrs_holder(
+    dir_inode: &Arc<dyn INode>,
+    name: &str,
+    inode: &Arc<dyn INode>,
+) -> Option<Arc<dyn INode>> {
+    xattrs_holder(inode, Some((dir_inode, name)), false).unwrap_or_else(|e| {
+        warn!("failed to find the attributes of a file: {:?}", e);
+        None
+    })
+}
+
+/// Release the attributes kept by an inode, which is found by `find_xattrs_holder`,
+/// once it has been removed from the file system, so that they are not inherited by a
+/// new inode with the same inode number.
+pub fn release_xattrs_if_removed(inode: &Arc<dyn INode>) {
+    let (is_removed, ino) = match inode.metadata() {
+        Ok(metadata) => (
+            metadata.nlinks == 0 || metadata.type_ == FileType::Dir,
+            metadata.inode,
+        ),
+        Err(_) => return,
+    };
+    if !is_removed {
+        return;
+    }
+    let mut stores = XATTR_STORES.lock().unwrap();
+    let store = get_or_create_store(&mut stores, inode);
+    if let Err(e) = store.release(ino) {
+        warn!(
+            "failed to release the attributes of a removed inode: {:?}",
+            e
+        );
+    }
+}
+
+/// Keep the attributes of the inodes of the file system in the file, where the
+/// attributes saved before are loaded from. It must be called once the file system
+/// is opened and before any of its attributes is accessed.
+///
+/// The file system must number the inodes stably on the disk, e.g., SEFS.
+pub fn persist_xattrs_in(fs: &Arc<dyn FileSystem>, file: Box<dyn DevFile>) -> Result<()> {
+    let store = XattrStore::load(fs, file)?;
+    let mut stores = XATTR_STORES.lock().unwrap();
+    stores.retain(|_, store| store.is_alive());
+    stores.insert(fs_id(fs), store);
+    Ok(())
+}
+
+/// Keep the attributes of the files of the unionfs in their copies in the upper layer,
+/// so that they are persisted with the upper layer, e.g., by `persist_xattrs_in`. A
+/// file is copied up once its attributes are modified.
+pub fn keep_xattrs_in_upper_layer(unionfs: &Arc<dyn FileSystem>, upper: &Arc<dyn FileSystem>) {
+    let mut upper_layers = UPPER_LAYERS.lock().unwrap();
+    upper_layers.retain(|_, (unionfs, _)| unionfs.strong_count() > 0);
+    upper_layers.insert(
+        fs_id(unionfs),
+        (Arc::downgrade(unionfs), Arc::downgrade(upper)),
+    );
+}
+
+impl<'a> XattrTarget<'a> {
+    fn lookup_inode(&self) -> Result<Arc<dyn INode>> {
+        let inode = match self {
+            Self::Path(fs_path) => {
+                let path = fs_path.to_abs_path()?;
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                fs.lookup_inode(&path)?
+            }
+            Self::LinkPath(fs_path) => {
+                let path = fs_path.to_abs_path()?;
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                fs.lookup_inode_no_follow(&path)?
+            }
+            Self::Fd(fd) => {
+                let file_ref = current!().file(*fd)?;
+                let inode_file = file_ref
+                    .as_inode_file()
+                    .map_err(|_| errno!(EOPNOTSUPP, "not an inode file"))?;
+                inode_file.inode().clone()
+            }
+        };
+        Ok(inode)
+    }
+
+    fn lookup_xattrs_holder(
+        &self,
+        inode: &Arc<dyn INode>,
+        copy_up: bool,
+    ) -> Result<Option<Arc<dyn INode>>> {
+        let parent = self.lookup_parent(inode)?;
+        let parent = parent
+            .as_ref()
+            .map(|(dir_inode, name)| (dir_inode, name.as_str()));
+        xattrs_holder(inode, parent, copy_up)
+    }
+
+    // The parent directory and the name of the file, which are only needed by the
+    // files of unionfs that are not directories, see `xattrs_holder`
+    fn lookup_parent(&self, inode: &Arc<dyn INode>) -> Result<Option<(Arc<dyn INode>, String)>> {
+        let metadata = inode.metadata()?;
+        if upper_layer_of(inode).is_none() || metadata.type_ == FileType::Dir {
+            return Ok(None);
+        }
+
+        let current = current!();
+        let fs = current.fs().read().unwrap();
+        let path = match self {
+            Self::Path(fs_path) => fs.lookup_real_path(&fs_path.to_abs_path()?)?,
+            Self::LinkPath(fs_path) => fs_path.to_abs_path()?,
+            Self::Fd(fd) => current.file(*fd)?.as_inode_file()?.abs_path().to_owned(),
+        };
+        let (dir_path, file_name) = split_path(&path);
+        let file_name = file_name.trim_end_matches('/');
+        let dir_inode = fs.lookup_inode(dir_path)?;
+        // The file opened may have been renamed or removed
+        if dir_inode.find(file_name)?.metadata()?.inode != metadata.inode {
+            return_errno!(ENOENT, "the file is not found by its path");
+        }
+        Ok(Some((dir_inode, file_name.to_owned())))
+    }
+}
+
+// The inode that keeps the attributes of the file, which is the file itself unless it
+// belongs to a unionfs. The files of unionfs keep them in their copies in the upper
+// layer, which are found by the paths of the files, so the parent directory and the
+// name are needed unless the file is a directory.
+//
+// If `copy_up` is true, the file is copied up. Otherwise, `None` is returned if the
+// file has no copy, i.e., no attributes.
+fn xattrs_holder(
+    inode: &Arc<dyn INode>,
+    parent: Option<(&Arc<dyn INode>, &str)>,
+    copy_up: bool,
+) -> Result<Option<Arc<dyn INode>>> {
+    let upper_fs = match upper_layer_of(inode) {
+        Some(upper_fs) => upper_fs,
+        None => return Ok(Some(inode.clone())),
+    };
+    if copy_up {
+        // Modifying the metadata of a file of unionfs copies it up
+        let metadata = inode.metadata()?;
+        inode.set_metadata(&metadata)?;
+    } else {
+        // Avoid walking the directories if no file has attributes, e.g., on unlink
+        let stores = XATTR_STORES.lock().unwrap();
+        let has_xattrs = stores
+            .get(&fs_id(&upper_fs))
+            .map_or(false, |store| store.is_alive() && !store.inodes.is_empty());
+        if !has_xattrs {
+            return Ok(None);
+        }
+    }
+
+    let path = path_in_fs(inode, parent)?;
+    match upper_fs.root_inode().lookup(&path) {
+        Ok(upper_inode) => Ok(Some(upper_inode)),
+        Err(FsError::EntryNotFound) if !copy_up => Ok(None),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+fn upper_layer_of(inode: &Arc<dyn INode>) -> Option<Arc<dyn FileSystem>> {
+    let upper_layers = UPPER_LAYERS.lock().unwrap();
+    let (unionfs, upper) = upper_layers.get(&fs_id(&inode.underlying_fs()))?;
+    if unionfs.strong_count() == 0 {
+        return None;
+    }
+    upper.upgrade()
+}
+
+// The path of the file from the root of the file system beneath the mounts. Since a
+// file does not know its parent directory, the directory and the name are needed
+// unless the file is a directory.
+fn path_in_fs(inode: &Arc<dyn INode>, parent: Option<(&Arc<dyn INode>, &str)>) -> Result<String> {
+    let (mut dir_inode, mut names) = match parent {
+        Some((dir_inode, name)) => (unwrap_mounts(dir_inode), vec![name.to_owned()]),
+        None => (unwrap_mounts(inode), Vec::new()),
+    };
+    let root_ino = dir_inode.fs().root_inode().metadata()?.inode;
+    loop {
+        let ino = dir_inode.metadata()?.inode;
+        if ino == root_ino {
+            break;
+        }
+        let parent_inode = dir_inode.find("..")?;
+        names.push(name_in_dir(&parent_inode, ino)?);
+        dir_inode = parent_inode;
+    }
+    names.reverse();
+    Ok(format!("/{}", names.join("/")))
+}
+
+fn name_in_dir(dir_inode: &Arc<dyn INode>, ino: usize) -> Result<String> {
+    for id in 0.. {
+        let name = dir_inode
+            .get_entry(id)
+            .map_err(|_| errno!(ENOENT, "the directory is not found in its parent"))?;
+        if name == "." || name == ".." {
+            continue;
+        }
+        if dir_inode.find(&name)?.metadata()?.inode == ino {
+            return Ok(name);
+        }
+    }
+    unreachable!()
+}
+
+fn unwrap_mounts(inode: &Arc<dyn INode>) -> Arc<dyn INode> {
+    match inode.downcast_ref::<MNode>() {
+        Some(mnode) => unwrap_mounts(&mnode.inode),
+        None => inode.clone(),
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return_errno!(ERANGE, "invalid attribute name length");
+    }
+    let namespace = XATTR_NAMESPACES
+        .iter()
+        .find(|namespace| name.starts_with(*namespace))
+        .ok_or_else(|| errno!(EOPNOTSUPP, "the attribute namespace is not supported"))?;
+    if name.len() == namespace.len() {
+        return_errno!(EINVAL, "the attribute name is empty");
+    }
+    Ok(())
+}
+
+// Like Linux, the user attributes are only allowed on regular files and directories,
+// since the permission bits of other types of files have different meanings.
+fn allow_xattr(inode: &Arc<dyn INode>, name: &str) -> Result<bool> {
+    if !name.starts_with("user.") {
+        return Ok(true);
+    }
+    let type_ = inode.metadata()?.type_;
+    Ok(type_ == FileType::File || type_ == FileType::Dir)
+}
+
+/// The extended attributes of the inodes of a file system, which are indexed by the
+/// inode numbers.
+///
+/// The attributes are kept by the LibOS. They are attached to the file system beneath
+/// the mounts, so a file has the same attributes through all its bind mounts. The
+/// inode numbers of unionfs are not stable across restarts, so the attributes of a
+/// unionfs are kept in the store of its upper layer instead, see `xattrs_holder`.
+///
+/// Only the attributes of the file systems given a file by `persist_xattrs_in`, i.e.,
+/// SEFS and the upper SEFS layers of unionfs, survive restarts. The others are kept in
+/// memory: the on-disk format of ext2 has room for the attributes (the block referred
+/// by `i_file_acl`), but ext2-rs does not access it yet.
+struct XattrStore {
+    fs: Weak<dyn FileSystem>,
+    inodes: HashMap<usize, Xattrs>,
+    // The total size of the names and values of all the inodes
+    size: usize,
+    file: Option<Box<dyn DevFile>>,
+}
+
+#[derive(Default)]
+struct Xattrs {
+    attrs: BTreeMap<String, Vec<u8>>,
+    // The total size of the names and values
+    size: usize,
+}
+
+impl XattrStore {
+    fn new(fs: &Arc<dyn FileSystem>) -> Self {
+        Self {
+            fs: Arc::downgrade(fs),
+            inodes: HashMap::new(),
+            size: 0,
+            file: None,
+        }
+    }
+
+    fn load(fs: &Arc<dyn FileSystem>, file: Box<dyn DevFile>) -> Result<Self> {
+        let mut store = Self::new(fs);
+        let mut header = [0u8; 8];
+        let payload = if read_file_at(file.as_ref(), &mut header, 0)? < header.len() {
+            // A new file
+            Vec::new()
+        } else {
+            let len = u64::from_le_bytes(header) as usize;
+            if len > XATTRS_SIZE_PER_FS_MAX * 2 {
+                return_errno!(EINVAL, "the xattrs file is corrupted");
+            }
+            let mut payload = vec![0u8; len];
+            if read_file_at(file.as_ref(), &mut payload, header.len())? < len {
+                return_errno!(EINVAL, "the xattrs file is truncated");
+            }
+            payload
+        };
+
+        let mut reader = PayloadReader(&payload);
+        while !reader.0.is_empty() {
+            let ino = reader.read_u64()? as usize;
+            let mut xattrs = Xattrs::default();
+            for _ in 0..reader.read_u32()? {
+                let name = String::from_utf8(reader.read_bytes()?.to_vec())
+                    .map_err(|_| errno!(EINVAL, "the xattrs file is corrupted"))?;
+                let value = reader.read_bytes()?.to_vec();
+                xattrs.size += name.len() + value.len();
+                xattrs.attrs.insert(name, value);
+            }
+            store.size += xattrs.size;
+            store.inodes.insert(ino, xattrs);
+        }
+        store.file = Some(file);
+        Ok(store)
+    }
+
+    fn is_alive(&self) -> bool {
+        self.fs.strong_count() > 0
+    }
+
+    fn set(&mut self, ino: usize, name: &str, value: &[u8], flags: XattrFlags) -> Result<()> {
+        let xattrs = self.inodes.entry(ino).or_default();
+        let old_value_len = match xattrs.attrs.get(name) {
+            Some(_) if flags.contains(XattrFlags::XATTR_CREATE) => {
+                return_errno!(EEXIST, "the attribute already exists");
+            }
+            None if flags.contains(XattrFlags::XATTR_REPLACE) => {
+                return_errno!(ENODATA, "the attribute does not exist");
+            }
+            Some(old_value) => Some(old_value.len()),
+            None => None,
+        };
+        let new_size = match old_value_len {
+            Some(len) => xattrs.size - len + value.len(),
+            None => xattrs.size + name.len() + value.len(),
+        };
+        let new_store_size = self.size - xattrs.size + new_size;
+        if new_size > XATTRS_SIZE_PER_INODE_MAX || new_store_size > XATTRS_SIZE_PER_FS_MAX {
+            if xattrs.attrs.is_empty() {
+                self.inodes.remove(&ino);
+            }
+            return_errno!(ENOSPC, "no space for the attribute");
+        }
+
+        let old_value = xattrs.attrs.insert(name.to_owned(), value.to_vec());
+        let old_size = std::mem::replace(&mut xattrs.size, new_size);
+        let old_store_size = std::mem::replace(&mut self.size, new_store_size);
+        if let Err(e) = self.save() {
+            // Keep the attributes in memory the same with those in the file
+            let xattrs = self.inodes.get_mut(&ino).unwrap();
+            match old_value {
+                Some(old_value) => {
+                    xattrs.attrs.insert(name.to_owned(), old_value);
+                }
+                None => {
+                    xattrs.attrs.remove(name);
+                }
+            }
+            xattrs.size = old_size;
+            if xattrs.attrs.is_empty() {
+                self.inodes.remove(&ino);
+            }
+            self.size = old_store_size;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, ino: usize, name: &str) -> Result<()> {
+        let xattrs = self
+            .inodes
+            .get_mut(&ino)
+            .ok_or_else(|| errno!(ENODATA, "the attribute does not exist"))?;
+        let value = xattrs
+            .attrs
+            .remove(name)
+            .ok_or_else(|| errno!(ENODATA, "the attribute does not exist"))?;
+        xattrs.size -= name.len() + value.len();
+        self.size -= name.len() + value.len();
+        if xattrs.attrs.is_empty() {
+            self.inodes.remove(&ino);
+        }
+        self.save()
+    }
+
+    fn release(&mut self, ino: usize) -> Result<()> {
+        if let Some(xattrs) = self.inodes.remove(&ino) {
+            self.size -= xattrs.size;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // Write all the attributes to the file, if any.
+    //
+    // The file is prefixed with the length of the payload, since the file of SEFS
+    // cannot be shrunk. The payload is a sequence of inodes, each of which is the inode
+    // number (u64), the number of the attributes (u32), and the attributes, each of
+    // which is the name and the value prefixed with their lengths (u32).
+    fn save(&self) -> Result<()> {
+        let file = match self.file.as_ref() {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let mut buf = vec![0u8; 8];
+        for (ino, xattrs) in self.inodes.iter() {
+            buf.extend_from_slice(&(*ino as u64).to_le_bytes());
+            buf.extend_from_slice(&(xattrs.attrs.len() as u32).to_le_bytes());
+            for (name, value) in xattrs.attrs.iter() {
+                buf.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                buf.extend_from_slice(name.as_bytes());
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buf.extend_from_slice(value);
+            }
+        }
+        let payload_len = (buf.len() - 8) as u64;
+        buf[..8].copy_from_slice(&payload_len.to_le_bytes());
+
+        let mut offset = 0;
+        while offset < buf.len() {
+            offset += file
+                .write_at(&buf[offset..], offset)
+                .map_err(|_| errno!(EIO, "failed to write the xattrs file"))?;
+        }
+        file.flush()
+            .map_err(|_| errno!(EIO, "failed to flush the xattrs file"))?;
+        Ok(())
+    }
+}
+
+struct PayloadReader<'a>(&'a [u8]);
+
+impl<'a> PayloadReader<'a> {
+    fn read_u64(&mut self) -> Result<u64> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    // Read the bytes prefixed with their length
+    fn read_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return_errno!(EINVAL, "the xattrs file is corrupted");
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+}
+
+fn read_file_at(file: &dyn DevFile, buf: &mut [u8], offset: usize) -> Result<usize> {
+    let mut read_len = 0;
+    while read_len < buf.len() {
+        let len = file
+            .read_at(&mut buf[read_len..], offset + read_len)
+            .map_err(|_| errno!(EIO, "failed to read the xattrs file"))?;
+        if len == 0 {
+            break;
+        }
+        read_len += len;
+    }
+    Ok(read_len)
+}
+
+fn fs_id(fs: &Arc<dyn FileSystem>) -> usize {
+    Arc::as_ptr(fs) as *const u8 as usize
+}
+
+fn get_store<'a>(
+    stores: &'a HashMap<usize, XattrStore>,
+    inode: &Arc<dyn INode>,
+) -> Option<&'a XattrStore> {
+    let fs = inode.underlying_fs();
+    stores.get(&fs_id(&fs)).filter(|store| store.is_alive())
+}
+
+fn get_or_create_store<'a>(
+    stores: &'a mut HashMap<usize, XattrStore>,
+    inode: &Arc<dyn INode>,
+) -> &'a mut XattrStore {
+    let fs = inode.underlying_fs();
+    // The attributes of the file systems that have gone, whose addresses may be reused
+    stores.retain(|_, store| store.is_alive());
+    stores
+        .entry(fs_id(&fs))
+        .or_insert_with(|| XattrStore::new(&fs))
+}
+
+lazy_static! {
+    /// The attributes of the file systems, indexed by the addresses of the file systems.
+    static ref XATTR_STORES: SgxMutex<HashMap<usize, XattrStore>> = SgxMutex::new(HashMap::new());
+    /// The upper layers of the unionfses, indexed by the addresses of the unionfses.
+    static ref UPPER_LAYERS: SgxMutex<HashMap<usize, UpperLayer>> = SgxMutex::new(HashMap::new());
+}
+
+// The unionfs and its upper layer
+type UpperLayer = (Weak<dyn FileSystem>, Weak<dyn FileSystem>);
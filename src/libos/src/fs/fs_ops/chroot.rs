@@ -0,0 +1,18 @@
+use super::*;
+
+pub fn do_chroot(path: &str) -> Result<()> {
+    debug!("chroot: path: {:?}", path);
+
+    let current = current!();
+    let inode = {
+        let fs = current.fs().read().unwrap();
+        fs.lookup_inode(path)?
+    };
+    let info = inode.metadata()?;
+    if info.type_ != FileType::Dir {
+        return_errno!(ENOTDIR, "root must be directory");
+    }
+
+    current.fs().write().unwrap().set_root(path)?;
+    Ok(())
+}
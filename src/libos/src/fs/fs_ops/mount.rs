@@ -1,13 +1,22 @@
-use config::{parse_key, parse_mac, ConfigMount, ConfigMountFsType, ConfigMountOptions};
+use blk::SwornDiskMeta;
+use config::{
+    parse_key, parse_mac, parse_memory_size, ConfigMkfsOptions, ConfigMount, ConfigMountFsType,
+    ConfigMountOptions,
+};
 use rcore_fs_mountfs::MNode;
 use rootfs::SEFS_MANAGER;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Once;
 use util::host_file_util::{write_host_file, HostFile};
 use util::mem_util::from_user;
 
-use super::rootfs::{mount_nonroot_fs_according_to, open_root_fs_according_to, umount_nonroot_fs};
+use super::mount_table::MOUNT_TABLE;
+use super::rootfs::{
+    bind_mount, lookup_mount_root, mount_nonroot_fs_according_to, move_mount,
+    open_root_fs_according_to, pivot_root, set_mount_flags, umount_nonroot_fs,
+};
 use super::*;
+use crate::process::{table, FsViewRef};
 
 lazy_static! {
     static ref MOUNT_ONCE: Once = Once::new();
@@ -26,6 +35,7 @@ pub fn do_mount_rootfs(
     let mount_config = &user_app_config.mount;
 
     SEFS_MANAGER.clear();
+    MOUNT_TABLE.write().unwrap().clear();
     let new_rootfs = open_root_fs_according_to(mount_config, user_key)?;
     let root_inode = new_rootfs.root_inode();
     MOUNT_ONCE.call_once(|| {
@@ -62,26 +72,10 @@ pub fn do_mount(
         source, target, flags, options
     );
 
-    let target = if target == "/" {
+    if target == "/" {
         return_errno!(EPERM, "can not mount on root");
-    } else if target.len() > 0 && target.as_bytes()[0] == b'/' {
-        PathBuf::from(target)
-    } else {
-        let thread = current!();
-        let fs = thread.fs().read().unwrap();
-        PathBuf::from(fs.convert_to_abs_path(target))
-    };
-
-    if flags.contains(MountFlags::MS_REMOUNT)
-        || flags.contains(MountFlags::MS_BIND)
-        || flags.contains(MountFlags::MS_SHARED)
-        || flags.contains(MountFlags::MS_PRIVATE)
-        || flags.contains(MountFlags::MS_SLAVE)
-        || flags.contains(MountFlags::MS_UNBINDABLE)
-        || flags.contains(MountFlags::MS_MOVE)
-    {
-        return_errno!(EINVAL, "Only support to create a new mount");
     }
+    let target = PathBuf::from(to_global_path(target));
 
     let (mount_configs, user_key) = match options {
         MountOptions::UnionFS(unionfs_options) => {
@@ -141,14 +135,26 @@ pub fn do_mount(
             };
             (vec![mc], None)
         }
-        MountOptions::Ext2 => {
+        MountOptions::Ext2(ext2_options) => {
+            // The disk is given by either its image dir or its device name
+            let image_dir = match ext2_options.dir {
+                Some(dir) => dir,
+                None => {
+                    let disk_name = source.strip_prefix("/dev/").unwrap_or(source);
+                    SwornDiskMeta::image_dir_of(disk_name)?
+                }
+            };
             let mc = ConfigMount {
                 type_: ConfigMountFsType::TYPE_EXT2,
                 target,
-                source: None,
-                options: Default::default(),
+                source: Some(image_dir),
+                options: ConfigMountOptions {
+                    disk_size: ext2_options.disk_size,
+                    mkfs: ext2_options.mkfs,
+                    ..Default::default()
+                },
             };
-            (vec![mc], None)
+            (vec![mc], ext2_options.key)
         }
     };
 
@@ -159,21 +165,136 @@ pub fn do_mount(
 
     let follow_symlink = !flags.contains(MountFlags::MS_NOSYMFOLLOW);
     mount_nonroot_fs_according_to(&root_inode, &mount_configs, &user_key, follow_symlink)?;
+    set_mount_flags(&root_inode, &mount_configs[0].target, follow_symlink, flags)?;
     Ok(())
 }
 
-pub fn do_umount(target: &str, flags: UmountFlags) -> Result<()> {
-    debug!("umount: target: {}, flags: {:?}", target, flags);
+/// Change the per-mount flags of an existing mount, e.g., make it read-only.
+pub fn do_remount(target: &str, flags: MountFlags) -> Result<()> {
+    debug!("remount: target: {}, flags: {:?}", target, flags);
 
-    let target = if target == "/" {
-        return_errno!(EPERM, "cannot umount rootfs");
-    } else if target.len() > 0 && target.as_bytes()[0] == b'/' {
-        target.to_owned()
-    } else {
+    let target = PathBuf::from(to_global_path(target));
+    let root_inode = ROOT_FS.read().unwrap().root_inode();
+    let follow_symlink = !flags.contains(MountFlags::MS_NOSYMFOLLOW);
+    if flags.contains(MountFlags::MS_RDONLY) {
+        // Flush the dirty data before the file system becomes read-only
+        let mount_root = lookup_mount_root(&root_inode, &target, follow_symlink)?;
+        mount_root.fs().sync()?;
+    }
+    set_mount_flags(&root_inode, &target, follow_symlink, flags)
+}
+
+/// Make the file at the source visible at the target, with or without the mounts
+/// under the source.
+pub fn do_bind_mount(source: &str, target: &str, flags: MountFlags) -> Result<()> {
+    debug!(
+        "bind mount: source: {}, target: {}, flags: {:?}",
+        source, target, flags
+    );
+
+    let source = PathBuf::from(to_global_path(source));
+    let target = to_global_path(target);
+    if target == "/" {
+        return_errno!(EPERM, "can not mount on root");
+    }
+    let target = PathBuf::from(target);
+
+    let root_inode = ROOT_FS.read().unwrap().root_inode();
+    let recursive = flags.contains(MountFlags::MS_REC);
+    let follow_symlink = !flags.contains(MountFlags::MS_NOSYMFOLLOW);
+    // Like Linux, the other flags are ignored, which can be changed by remount
+    bind_mount(&root_inode, &source, &target, recursive, follow_symlink)
+}
+
+/// Move an existing mount to the target.
+pub fn do_move_mount(source: &str, target: &str, flags: MountFlags) -> Result<()> {
+    debug!(
+        "move mount: source: {}, target: {}, flags: {:?}",
+        source, target, flags
+    );
+
+    let source = PathBuf::from(to_global_path(source));
+    let target = PathBuf::from(to_global_path(target));
+    if source == Path::new("/") {
+        return_errno!(EINVAL, "cannot move the root");
+    }
+    if target.starts_with(&source) {
+        return_errno!(EINVAL, "cannot move a mount to the place under itself");
+    }
+
+    let rootfs = ROOT_FS.read().unwrap();
+    SEFS_MANAGER.sync_all()?;
+    let root_inode = rootfs.root_inode();
+    drop(rootfs);
+
+    let follow_symlink = !flags.contains(MountFlags::MS_NOSYMFOLLOW);
+    move_mount(&root_inode, &source, &target, follow_symlink)
+}
+
+/// Change the propagation type of a mount. Since there is only one mount namespace,
+/// all mounts are private and the propagation type makes no difference.
+pub fn do_change_propagation(target: &str, flags: MountFlags) -> Result<()> {
+    debug!("change propagation: target: {}, flags: {:?}", target, flags);
+
+    let propagation_flags = flags & MountFlags::propagation_flags();
+    if propagation_flags.bits().count_ones() != 1 {
+        return_errno!(EINVAL, "only one propagation type can be given");
+    }
+    let target = PathBuf::from(to_global_path(target));
+    let root_inode = ROOT_FS.read().unwrap().root_inode();
+    lookup_mount_root(&root_inode, &target, true)?;
+    Ok(())
+}
+
+/// Make the mount at `new_root` the root mount, and move the old root mount to
+/// `put_old`, which must be under `new_root`.
+pub fn do_pivot_root(new_root: &str, put_old: &str) -> Result<()> {
+    debug!("pivot_root: new_root: {}, put_old: {}", new_root, put_old);
+
+    let (new_root, put_old) = {
         let thread = current!();
         let fs = thread.fs().read().unwrap();
-        fs.convert_to_abs_path(target)
+        if fs.root() != "/" {
+            return_errno!(EINVAL, "the root of the process is not the root mount");
+        }
+        (
+            fs.convert_to_global_path(new_root),
+            fs.convert_to_global_path(put_old),
+        )
     };
+    if new_root == "/" {
+        return_errno!(EBUSY, "new_root is already the root mount");
+    }
+
+    pivot_root(Path::new(&new_root), Path::new(&put_old))?;
+
+    // Like Linux, the processes still work in the same directories under the new root.
+    // The threads may share the same `FsView`, which must be updated only once.
+    let mut fs_views: Vec<FsViewRef> = Vec::new();
+    for thread in table::get_all_threads() {
+        let fs = thread.fs();
+        if fs_views.iter().any(|fs_view| Arc::ptr_eq(fs_view, fs)) {
+            continue;
+        }
+        fs.write().unwrap().pivot_root(&new_root);
+        fs_views.push(fs.clone());
+    }
+    Ok(())
+}
+
+fn to_global_path(path: &str) -> String {
+    let thread = current!();
+    let fs = thread.fs().read().unwrap();
+    fs.convert_to_global_path(path)
+}
+
+pub fn do_umount(target: &str, flags: UmountFlags) -> Result<()> {
+    debug!("umount: target: {}, flags: {:?}", target, flags);
+
+    if target == "/" {
+        return_errno!(EPERM, "cannot umount rootfs");
+    }
+    let target = to_global_path(target);
 
     let mut rootfs = ROOT_FS.write().unwrap();
     // XXX: There may be redundant sync with `MNode::umount()`
@@ -221,13 +342,32 @@ bitflags! {
     }
 }
 
+impl MountFlags {
+    /// The flags that belong to a mount rather than a file system
+    pub fn per_mount_flags() -> Self {
+        Self::MS_RDONLY
+            | Self::MS_NOSUID
+            | Self::MS_NODEV
+            | Self::MS_NOEXEC
+            | Self::MS_NOSYMFOLLOW
+            | Self::MS_NOATIME
+            | Self::MS_NODIRATIME
+            | Self::MS_RELATIME
+            | Self::MS_STRICTATIME
+    }
+
+    pub fn propagation_flags() -> Self {
+        Self::MS_SHARED | Self::MS_PRIVATE | Self::MS_SLAVE | Self::MS_UNBINDABLE
+    }
+}
+
 #[derive(Debug)]
 pub enum MountOptions {
     UnionFS(UnionFSMountOptions),
     SEFS(SEFSMountOptions),
     HostFS(PathBuf),
     RamFS,
-    Ext2,
+    Ext2(Ext2MountOptions),
 }
 
 impl MountOptions {
@@ -266,7 +406,18 @@ impl MountOptions {
                 Self::HostFS(dir)
             }
             ConfigMountFsType::TYPE_RAMFS => Self::RamFS,
-            ConfigMountFsType::TYPE_EXT2 => Self::Ext2,
+            ConfigMountFsType::TYPE_EXT2 => {
+                // The options are optional if the disk is given by the source
+                let ext2_mount_options = if options.is_null() {
+                    Ext2MountOptions::default()
+                } else {
+                    let options = from_user::clone_cstring_safely(options)?
+                        .to_string_lossy()
+                        .into_owned();
+                    Ext2MountOptions::from_input(options.as_str())?
+                };
+                Self::Ext2(ext2_mount_options)
+            }
             _ => {
                 return_errno!(EINVAL, "unsupported fs type");
             }
@@ -338,6 +489,60 @@ impl SEFSMountOptions {
     }
 }
 
+#[derive(Debug, Default)]
+pub struct Ext2MountOptions {
+    dir: Option<PathBuf>,
+    disk_size: Option<u64>,
+    key: Option<sgx_key_128bit_t>,
+    // Used only if the disk has not been formatted
+    mkfs: ConfigMkfsOptions,
+}
+
+impl Ext2MountOptions {
+    pub fn from_input(input: &str) -> Result<Self> {
+        let options: Vec<&str> = input.split(",").collect();
+
+        let dir = options
+            .iter()
+            .find_map(|s| s.strip_prefix("dir="))
+            .map(PathBuf::from);
+        let disk_size = match options.iter().find_map(|s| s.strip_prefix("disk_size=")) {
+            Some(size_str) => Some(parse_memory_size(size_str)? as u64),
+            None => None,
+        };
+        let key = match options.iter().find_map(|s| s.strip_prefix("key=")) {
+            Some(key_str) => Some(parse_key(key_str)?),
+            None => None,
+        };
+        let mkfs = ConfigMkfsOptions {
+            block_size: match options.iter().find_map(|s| s.strip_prefix("block_size=")) {
+                Some(size_str) => Some(
+                    size_str
+                        .parse()
+                        .map_err(|_| errno!(EINVAL, "invalid block_size"))?,
+                ),
+                None => None,
+            },
+            inodes_count: match options.iter().find_map(|s| s.strip_prefix("inodes_count=")) {
+                Some(count_str) => Some(
+                    count_str
+                        .parse()
+                        .map_err(|_| errno!(EINVAL, "invalid inodes_count"))?,
+                ),
+                None => None,
+            },
+            ..Default::default()
+        };
+
+        Ok(Self {
+            dir,
+            disk_size,
+            key,
+            mkfs,
+        })
+    }
+}
+
 bitflags! {
     pub struct UmountFlags: u32 {
         const MNT_FORCE = 1;
@@ -1,8 +1,11 @@
 /// Present a per-process view of FS.
+use super::inotify::FileParent;
 use super::*;
 
 #[derive(Debug, Clone)]
 pub struct FsView {
+    /// The root directory, which is a normalized absolute path in the mount tree.
+    /// All the paths of the process, including the cwd, are relative to it.
     root: String,
     cwd: String,
 }
@@ -24,6 +27,34 @@ impl FsView {
         &self.cwd
     }
 
+    /// Change the root directory to the path, which is given in the current root.
+    ///
+    /// Unlike Linux, the cwd never stays outside of the root. It is moved to the new
+    /// root if it is not under the new root.
+    pub fn set_root(&mut self, path: &str) -> Result<()> {
+        if path.len() == 0 {
+            return_errno!(EINVAL, "empty path");
+        }
+
+        let root = self.convert_to_global_path(path);
+        let cwd = self.convert_to_global_path(self.cwd());
+        self.cwd = strip_root(&cwd, &root).unwrap_or_else(|| String::from("/"));
+        self.root = root;
+        Ok(())
+    }
+
+    /// Update the paths after the mount at `new_root` becomes the root mount, so that
+    /// they still refer to the same directories if they are under `new_root`.
+    pub fn pivot_root(&mut self, new_root: &str) {
+        if self.root == "/" {
+            if let Some(cwd) = strip_root(&normalize_path(&self.cwd), new_root) {
+                self.cwd = cwd;
+            }
+        } else if let Some(root) = strip_root(&self.root, new_root) {
+            self.root = root;
+        }
+    }
+
     /// Set the current working directory.
     pub fn set_cwd(&mut self, path: &str) -> Result<()> {
         if path.len() == 0 {
@@ -46,6 +77,8 @@ impl FsView {
     /// Open a file on the process. But DO NOT add it to file table.
     pub fn open_file(&self, path: &str, flags: u32, mode: FileMode) -> Result<Arc<dyn File>> {
         let creation_flags = CreationFlags::from_bits_truncate(flags);
+        // The directory where the file is created
+        let mut created_in = None;
         let inode = if creation_flags.no_follow_symlink() {
             match self.lookup_inode_no_follow(path) {
                 Ok(inode) => {
@@ -79,7 +112,10 @@ impl FsView {
                     if !dir_inode.allow_write()? {
                         return_errno!(EPERM, "file cannot be created");
                     }
-                    dir_inode.create(file_name, FileType::File, mode.bits())?
+                    dir_inode.check_mount_writable()?;
+                    let inode = dir_inode.create(file_name, FileType::File, mode.bits())?;
+                    created_in = FileParent::new(&dir_inode, file_name).ok();
+                    inode
                 }
                 Err(e) => return Err(e),
             }
@@ -115,13 +151,29 @@ impl FsView {
                     if !dir_inode.allow_write()? {
                         return_errno!(EPERM, "file cannot be created");
                     }
-                    dir_inode.create(file_name, FileType::File, mode.bits())?
+                    dir_inode.check_mount_writable()?;
+                    let inode = dir_inode.create(file_name, FileType::File, mode.bits())?;
+                    created_in = FileParent::new(&dir_inode, file_name).ok();
+                    inode
                 }
                 Err(e) => return Err(e),
             }
         };
+        let parent = created_in.or_else(|| self.lookup_parent(path));
         let abs_path = self.convert_to_abs_path(&path);
-        Ok(Arc::new(INodeFile::open(inode, &abs_path, flags)?))
+        Ok(Arc::new(INodeFile::open(inode, &abs_path, parent, flags)?))
+    }
+
+    /// Lookup the directory where the file of the path is found, which is looked up
+    /// once a file is opened, so that the inotify events of the file are reported to
+    /// the directory without looking it up again.
+    pub fn lookup_parent(&self, path: &str) -> Option<FileParent> {
+        let (dir_path, file_name) = split_path(path);
+        if matches!(file_name.trim_end_matches('/'), "." | "..") {
+            return None;
+        }
+        let dir_inode = self.lookup_inode(dir_path).ok()?;
+        FileParent::new(&dir_inode, file_name).ok()
     }
 
     /// Recursively lookup the real path of giving path, dereference symlinks
@@ -168,6 +220,9 @@ impl FsView {
             self.cwd(),
             path
         );
+        if self.is_chrooted() {
+            return self.lookup_inode_in_root(path, false);
+        }
         let (dir_path, file_name) = split_path(&path);
         let inode = if file_name.ends_with("/") {
             self.lookup_inode(path)?
@@ -181,6 +236,9 @@ impl FsView {
     /// Lookup INode from the cwd of the process, dereference symlink
     pub fn lookup_inode(&self, path: &str) -> Result<Arc<dyn INode>> {
         debug!("lookup_inode: cwd: {:?}, path: {:?}", self.cwd(), path);
+        if self.is_chrooted() {
+            return self.lookup_inode_in_root(path, true);
+        }
         if path.len() > 0 && path.as_bytes()[0] == b'/' {
             // absolute path
             let abs_path = path.trim_start_matches('/');
@@ -203,6 +261,156 @@ impl FsView {
         }
     }
 
+    /// Lookup INode from the root of the process, in which ".." of the root is the root
+    /// itself. The symlinks are also resolved in the root.
+    fn lookup_inode_in_root(&self, path: &str, follow_last: bool) -> Result<Arc<dyn INode>> {
+        let root_inode = ROOT_FS
+            .read()
+            .unwrap()
+            .root_inode()
+            .lookup_follow(self.root.trim_start_matches('/'), MAX_SYMLINKS)?;
+        let abs_path = self.convert_to_abs_path(path);
+        let follow_last = follow_last || abs_path.ends_with("/");
+
+        // The inodes from the root to the current one
+        let mut inodes = vec![root_inode];
+        let mut names: VecDeque<String> = abs_path.split('/').map(String::from).collect();
+        let mut follow_times = MAX_SYMLINKS;
+        while let Some(name) = names.pop_front() {
+            match name.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    if inodes.len() > 1 {
+                        inodes.pop();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let dir_inode = inodes.last().unwrap();
+            if dir_inode.metadata()?.type_ != FileType::Dir {
+                return_errno!(ENOTDIR, "not a directory");
+            }
+            let inode = dir_inode.find(&name)?;
+            let is_last = names.iter().all(|name| name.is_empty());
+            if inode.metadata()?.type_ == FileType::SymLink && (follow_last || !is_last) {
+                if follow_times == 0 {
+                    return_errno!(ELOOP, "too many symlinks");
+                }
+                follow_times -= 1;
+
+                let mut content = vec![0u8; PATH_MAX];
+                let len = inode.read_at(0, &mut content)?;
+                let link_path = std::str::from_utf8(&content[..len])
+                    .map_err(|_| errno!(ENOENT, "invalid symlink content"))?;
+                if link_path.starts_with('/') {
+                    inodes.truncate(1);
+                }
+                for name in link_path.split('/').rev() {
+                    names.push_front(name.to_owned());
+                }
+            } else {
+                inodes.push(inode);
+            }
+        }
+        Ok(inodes.pop().unwrap())
+    }
+
+    fn is_chrooted(&self) -> bool {
+        self.root != "/"
+    }
+
+    /// Convert the path of the process to the normalized absolute path in the mount tree.
+    pub fn convert_to_global_path(&self, path: &str) -> String {
+        let path_in_root = normalize_path(&self.convert_to_abs_path(path));
+        if !self.is_chrooted() {
+            path_in_root
+        } else if path_in_root == "/" {
+            self.root.clone()
+        } else {
+            self.root.clone() + &path_in_root
+        }
+    }
+
+    /// Convert the path of the process to the absolute path in the mount tree with all
+    /// the symlinks resolved in the root, like realpath(3). Unlike realpath(3), the
+    /// components that don't exist are kept as they are.
+    pub fn resolve_global_path(&self, path: &str) -> Result<String> {
+        let root_inode = ROOT_FS
+            .read()
+            .unwrap()
+            .root_inode()
+            .lookup_follow(self.root.trim_start_matches('/'), MAX_SYMLINKS)?;
+        let abs_path = self.convert_to_abs_path(path);
+
+        // The names from the root to the current one, and their inodes, which are `None`
+        // since a name doesn't exist
+        let mut resolved_names: Vec<String> = Vec::new();
+        let mut inodes = vec![Some(root_inode)];
+        let mut names: VecDeque<String> = abs_path.split('/').map(String::from).collect();
+        let mut follow_times = MAX_SYMLINKS;
+        while let Some(name) = names.pop_front() {
+            match name.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    if resolved_names.pop().is_some() {
+                        inodes.pop();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let inode = match inodes.last().unwrap() {
+                Some(dir_inode) if dir_inode.metadata()?.type_ == FileType::Dir => {
+                    dir_inode.find(&name).ok()
+                }
+                _ => None,
+            };
+            match inode {
+                Some(inode) if inode.metadata()?.type_ == FileType::SymLink => {
+                    if follow_times == 0 {
+                        return_errno!(ELOOP, "too many symlinks");
+                    }
+                    follow_times -= 1;
+
+                    let mut content = vec![0u8; PATH_MAX];
+                    let len = inode.read_at(0, &mut content)?;
+                    let link_path = std::str::from_utf8(&content[..len])
+                        .map_err(|_| errno!(ENOENT, "invalid symlink content"))?;
+                    if link_path.starts_with('/') {
+                        resolved_names.clear();
+                        inodes.truncate(1);
+                    }
+                    for name in link_path.split('/').rev() {
+                        names.push_front(name.to_owned());
+                    }
+                }
+                inode => {
+                    resolved_names.push(name);
+                    inodes.push(inode);
+                }
+            }
+        }
+
+        let path_in_root = String::from("/") + &resolved_names.join("/");
+        Ok(if !self.is_chrooted() {
+            path_in_root
+        } else if path_in_root == "/" {
+            self.root.clone()
+        } else {
+            self.root.clone() + &path_in_root
+        })
+    }
+
+    /// Convert the absolute path in the mount tree to the path of the process, or
+    /// return `None` if the path is not under the root of the process.
+    pub fn convert_from_global_path(&self, path: &str) -> Option<String> {
+        if !self.is_chrooted() {
+            return Some(path.to_owned());
+        }
+        strip_root(&normalize_path(path), &self.root)
+    }
+
     /// Convert the path to be absolute
     pub fn convert_to_abs_path(&self, path: &str) -> String {
         debug!(
@@ -232,3 +440,31 @@ impl Default for FsView {
         Self { root, cwd }
     }
 }
+
+/// Normalize the absolute path lexically, i.e., remove ".", ".." and redundant slashes.
+/// Like the root directory, ".." of "/" is "/" itself.
+fn normalize_path(path: &str) -> String {
+    let mut names = Vec::new();
+    for name in path.split('/') {
+        match name {
+            "" | "." => {}
+            ".." => {
+                names.pop();
+            }
+            _ => names.push(name),
+        }
+    }
+    String::from("/") + &names.join("/")
+}
+
+/// Get the path relative to the root if the normalized path is under the root.
+fn strip_root(path: &str, root: &str) -> Option<String> {
+    if root == "/" {
+        return Some(path.to_owned());
+    }
+    match path.strip_prefix(root) {
+        Some("") => Some(String::from("/")),
+        Some(rest) if rest.starts_with('/') => Some(rest.to_owned()),
+        _ => None,
+    }
+}
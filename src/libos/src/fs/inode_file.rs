@@ -1,16 +1,25 @@
+use super::fs_ops::MountFlags;
+use super::inotify::FileParent;
+use super::mount_table::{MountWriter, MOUNT_TABLE};
 use super::*;
 use crate::fs::IoctlCmd;
 use crate::net::PollEventFlags;
 use crate::process::do_getuid;
 use rcore_fs::vfs::FallocateMode;
+use rcore_fs_mountfs::MNode;
 use rcore_fs_sefs::dev::SefsMac;
 
 pub struct INodeFile {
     inode: Arc<dyn INode>,
     abs_path: String,
+    // The parent directory where the file is opened, which receives the inotify events
+    // of the file
+    parent: Option<FileParent>,
     offset: SgxMutex<usize>,
     access_mode: AccessMode,
     status_flags: RwLock<StatusFlags>,
+    // Keep the mount from being remounted read-only if the file is writable
+    _mount_writer: Option<MountWriter>,
 }
 
 impl File for INodeFile {
@@ -306,7 +315,12 @@ impl INodeFile {
         &self.inode
     }
 
-    pub fn open(inode: Arc<dyn INode>, abs_path: &str, flags: u32) -> Result<Self> {
+    pub fn open(
+        inode: Arc<dyn INode>,
+        abs_path: &str,
+        parent: Option<FileParent>,
+        flags: u32,
+    ) -> Result<Self> {
         let access_mode = AccessMode::from_u32(flags)?;
         if (access_mode.readable() && !inode.allow_read()?) {
             return_errno!(EACCES, "File not readable");
@@ -314,6 +328,11 @@ impl INodeFile {
         if (access_mode.writable() && !inode.allow_write()?) {
             return_errno!(EACCES, "File not writable");
         }
+        let mount_writer = if access_mode.writable() {
+            inode.add_mount_writer()?
+        } else {
+            None
+        };
         if access_mode.writable() && inode.metadata()?.type_ == FileType::Dir {
             return_errno!(EISDIR, "Directory cannot be open to write");
         }
@@ -329,9 +348,11 @@ impl INodeFile {
         Ok(INodeFile {
             inode,
             abs_path: abs_path.to_owned(),
+            parent,
             offset: SgxMutex::new(0),
             access_mode,
             status_flags: RwLock::new(status_flags),
+            _mount_writer: mount_writer,
         })
     }
 
@@ -339,6 +360,10 @@ impl INodeFile {
         &self.abs_path
     }
 
+    pub fn parent(&self) -> Option<&FileParent> {
+        self.parent.as_ref()
+    }
+
     fn check_advisory_lock_with_access_mode(&self, lock: &RangeLock) -> Result<()> {
         match lock.type_() {
             RangeLockType::F_RDLCK => {
@@ -410,7 +435,14 @@ impl INodeFile {
 
 impl Drop for INodeFile {
     fn drop(&mut self) {
-        self.unlock_flock()
+        self.unlock_flock();
+
+        let mask = if self.access_mode.writable() {
+            InotifyMask::IN_CLOSE_WRITE
+        } else {
+            InotifyMask::IN_CLOSE_NOWRITE
+        };
+        inotify::notify_file_event(&self.inode, self.parent.as_ref(), mask);
     }
 }
 
@@ -431,6 +463,10 @@ pub trait INodeExt {
     fn read_as_vec(&self) -> Result<Vec<u8>>;
     fn allow_write(&self) -> Result<bool>;
     fn allow_read(&self) -> Result<bool>;
+    fn mount_flags(&self) -> MountFlags;
+    fn check_mount_writable(&self) -> Result<()>;
+    fn add_mount_writer(&self) -> Result<Option<MountWriter>>;
+    fn underlying_fs(&self) -> Arc<dyn FileSystem>;
 }
 
 impl INodeExt for dyn INode {
@@ -466,6 +502,64 @@ impl INodeExt for dyn INode {
         let file_mode = FileMode::from_bits_truncate(info.mode);
         Ok(file_mode.is_readable())
     }
+
+    /// The flags of the mount where the inode is looked up.
+    fn mount_flags(&self) -> MountFlags {
+        MOUNT_TABLE
+            .read()
+            .unwrap()
+            .get(self)
+            .map_or(MountFlags::empty(), |mount| mount.flags())
+    }
+
+    fn check_mount_writable(&self) -> Result<()> {
+        if self.mount_flags().contains(MountFlags::MS_RDONLY) {
+            return_errno!(EROFS, "the file system is mounted read-only");
+        }
+        Ok(())
+    }
+
+    /// Check that the mount is writable and count a writer of it, which makes
+    /// remounting it read-only fail with EBUSY until the writer is dropped.
+    fn add_mount_writer(&self) -> Result<Option<MountWriter>> {
+        let mount_table = MOUNT_TABLE.read().unwrap();
+        let mount = match mount_table.get(self) {
+            Some(mount) => mount,
+            None => return Ok(None),
+        };
+        if mount.flags().contains(MountFlags::MS_RDONLY) {
+            return_errno!(EROFS, "the file system is mounted read-only");
+        }
+        Ok(Some(mount.add_writer()))
+    }
+
+    /// The file system that the inode belongs to beneath the mounts, which is the same
+    /// no matter through which mount, e.g., a bind mount, the inode is looked up.
+    fn underlying_fs(&self) -> Arc<dyn FileSystem> {
+        match self.downcast_ref::<MNode>() {
+            Some(mnode) => mnode.inode.underlying_fs(),
+            None => self.fs(),
+        }
+    }
+}
+
+/// The identity of an inode, which is the same no matter how the inode is looked up.
+///
+/// Some file systems create a new inode object on each lookup, so the pointer of the
+/// inode object cannot be used as its identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct INodeKey {
+    fs_id: usize,
+    ino: usize,
+}
+
+impl INodeKey {
+    pub fn new(inode: &Arc<dyn INode>) -> Result<Self> {
+        Ok(Self {
+            fs_id: Arc::as_ptr(&inode.underlying_fs()) as *const u8 as usize,
+            ino: inode.metadata()?.inode,
+        })
+    }
 }
 
 pub trait AsINodeFile {
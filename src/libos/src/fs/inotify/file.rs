@@ -0,0 +1,124 @@
+use super::inotify::Inotify;
+use super::*;
+
+/// The file of an inotify instance created by inotify_init.
+pub struct InotifyFile {
+    inotify: Arc<Inotify>,
+    status_flags: RwLock<StatusFlags>,
+}
+
+impl InotifyFile {
+    pub fn new(flags: InotifyFlags) -> Self {
+        let status_flags = if flags.contains(InotifyFlags::IN_NONBLOCK) {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        };
+        Self {
+            inotify: Arc::new(Inotify::new()),
+            status_flags: RwLock::new(status_flags),
+        }
+    }
+
+    /// Add a watch on an inode, or modify the existing one. Returns the watch descriptor.
+    pub fn add_watch(&self, inode: &Arc<dyn INode>, mask: InotifyMask) -> Result<i32> {
+        if mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE) {
+            return_errno!(EINVAL, "IN_MASK_ADD and IN_MASK_CREATE are exclusive");
+        }
+        if (mask & InotifyMask::IN_ALL_EVENTS).is_empty() {
+            return_errno!(EINVAL, "no event to watch");
+        }
+        watch::add_watch(&self.inotify, inode, mask)
+    }
+
+    pub fn remove_watch(&self, wd: i32) -> Result<()> {
+        watch::remove_watch(&self.inotify, wd)
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+}
+
+impl File for InotifyFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.inotify.read(buf, self.is_nonblocking())
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        // The events cannot be split into multiple buffers
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
+    }
+
+    fn ioctl(&self, cmd: &mut dyn IoctlCmd) -> Result<()> {
+        match_ioctl_cmd_auto_error!(cmd, {
+            cmd : GetReadBufLen => {
+                let bytes_to_read = self.inotify.bytes_to_read().min(std::i32::MAX as usize) as i32;
+                cmd.set_output(bytes_to_read as _);
+            },
+        });
+        Ok(())
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDONLY)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(*self.status_flags.read().unwrap())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        // Only O_NONBLOCK is meaningful for an inotify instance
+        status_flags.set(
+            StatusFlags::O_NONBLOCK,
+            new_status_flags.contains(StatusFlags::O_NONBLOCK),
+        );
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.inotify.pollee().poll(IoEvents::all(), None)
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.inotify.pollee().notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Drop for InotifyFile {
+    fn drop(&mut self) {
+        watch::remove_all_watches(&self.inotify);
+    }
+}
+
+impl Debug for InotifyFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InotifyFile")
+            .field("status_flags", &self.status_flags)
+            .finish()
+    }
+}
+
+pub trait AsInotify {
+    fn as_inotify(&self) -> Result<&InotifyFile>;
+}
+
+impl AsInotify for FileRef {
+    fn as_inotify(&self) -> Result<&InotifyFile> {
+        self.as_any()
+            .downcast_ref::<InotifyFile>()
+            .ok_or_else(|| errno!(EINVAL, "not an inotify file"))
+    }
+}
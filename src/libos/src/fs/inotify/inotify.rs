@@ -0,0 +1,195 @@
+use super::*;
+use crate::events::{Pollee, Waiter, WaiterQueue};
+use std::collections::HashMap;
+
+// The max num of queued events of an inotify instance, same with
+// /proc/sys/fs/inotify/max_queued_events on Linux
+const MAX_QUEUED_EVENTS: usize = 16384;
+// The max num of watches of an inotify instance, same with
+// /proc/sys/fs/inotify/max_user_watches on most Linux distributions
+const MAX_WATCHES: usize = 8192;
+// The size of struct inotify_event without the name
+const EVENT_HEADER_LEN: usize = 16;
+
+/// An event to be read from an inotify instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    name: Option<String>,
+}
+
+impl InotifyEvent {
+    pub fn new(wd: i32, mask: InotifyMask, cookie: u32, name: Option<&str>) -> Self {
+        Self {
+            wd,
+            mask,
+            cookie,
+            name: name.map(|name| name.to_owned()),
+        }
+    }
+
+    /// The length of the event in the buffer of read.
+    fn len(&self) -> usize {
+        EVENT_HEADER_LEN + self.name_len()
+    }
+
+    // Like Linux, the name is null-terminated and padded to align the next event
+    fn name_len(&self) -> usize {
+        match &self.name {
+            Some(name) => align_up(name.len() + 1, EVENT_HEADER_LEN),
+            None => 0,
+        }
+    }
+
+    fn write_to(&self, buf: &mut [u8]) {
+        let name_len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.bits().to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+
+        let name_buf = &mut buf[EVENT_HEADER_LEN..EVENT_HEADER_LEN + name_len];
+        name_buf.fill(0);
+        if let Some(name) = &self.name {
+            name_buf[..name.len()].copy_from_slice(name.as_bytes());
+        }
+    }
+}
+
+/// An inotify instance, which holds the watches and the queue of the events.
+///
+/// The watches are also registered in the global watch table, through which the events
+/// are queued.
+pub struct Inotify {
+    inner: SgxMutex<Inner>,
+    read_waiters: WaiterQueue,
+    pollee: Pollee,
+}
+
+struct Inner {
+    events: VecDeque<InotifyEvent>,
+    // The total length of the queued events
+    num_bytes: usize,
+    // The watched inodes of the watch descriptors
+    watches: HashMap<i32, INodeKey>,
+    next_wd: i32,
+}
+
+impl Inotify {
+    pub fn new() -> Self {
+        Self {
+            inner: SgxMutex::new(Inner {
+                events: VecDeque::new(),
+                num_bytes: 0,
+                watches: HashMap::new(),
+                next_wd: 1,
+            }),
+            read_waiters: WaiterQueue::new(),
+            pollee: Pollee::new(IoEvents::empty()),
+        }
+    }
+
+    pub fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+
+    /// Queue an event. Like Linux, an event identical to the last queued one is merged,
+    /// and an IN_Q_OVERFLOW event is queued if the queue is full.
+    pub fn push_event(&self, event: InotifyEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.events.back() == Some(&event) {
+            return;
+        }
+
+        let event = if inner.events.len() < MAX_QUEUED_EVENTS - 1 {
+            event
+        } else if inner.events.len() == MAX_QUEUED_EVENTS - 1 {
+            InotifyEvent::new(-1, InotifyMask::IN_Q_OVERFLOW, 0, None)
+        } else {
+            return;
+        };
+        inner.num_bytes += event.len();
+        inner.events.push_back(event);
+        self.pollee.add_events(IoEvents::IN);
+        drop(inner);
+
+        self.read_waiters.dequeue_and_wake_all();
+    }
+
+    /// Read as many events as the buffer can hold, blocking if there is no event.
+    pub fn read(&self, buf: &mut [u8], nonblocking: bool) -> Result<usize> {
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if !inner.events.is_empty() {
+                let mut read_len = 0;
+                while let Some(event) = inner.events.front() {
+                    let event_len = event.len();
+                    if read_len + event_len > buf.len() {
+                        break;
+                    }
+                    event.write_to(&mut buf[read_len..read_len + event_len]);
+                    read_len += event_len;
+                    inner.num_bytes -= event_len;
+                    inner.events.pop_front();
+                }
+                if read_len == 0 {
+                    return_errno!(EINVAL, "the buffer is too small for the next event");
+                }
+                if inner.events.is_empty() {
+                    self.pollee.del_events(IoEvents::IN);
+                }
+                return Ok(read_len);
+            }
+
+            if nonblocking {
+                return_errno!(EAGAIN, "no event is available");
+            }
+            self.read_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait(None)?;
+        }
+    }
+
+    /// The total length of the queued events.
+    pub fn bytes_to_read(&self) -> usize {
+        self.inner.lock().unwrap().num_bytes
+    }
+
+    /// Find the watch descriptor of an inode.
+    pub fn find_wd(&self, key: &INodeKey) -> Option<i32> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .watches
+            .iter()
+            .find(|(_, watched_key)| *watched_key == key)
+            .map(|(wd, _)| *wd)
+    }
+
+    /// Allocate a watch descriptor for an inode.
+    pub fn add_wd(&self, key: INodeKey) -> Result<i32> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.watches.len() >= MAX_WATCHES {
+            return_errno!(ENOSPC, "too many watches");
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(wd, key);
+        Ok(wd)
+    }
+
+    /// Release a watch descriptor, and queue an IN_IGNORED event for it.
+    pub fn remove_wd(&self, wd: i32) -> Option<INodeKey> {
+        let key = self.inner.lock().unwrap().watches.remove(&wd)?;
+        self.push_event(InotifyEvent::new(wd, InotifyMask::IN_IGNORED, 0, None));
+        Some(key)
+    }
+
+    pub fn watched_inodes(&self) -> Vec<(i32, INodeKey)> {
+        let inner = self.inner.lock().unwrap();
+        inner.watches.iter().map(|(wd, key)| (*wd, *key)).collect()
+    }
+}
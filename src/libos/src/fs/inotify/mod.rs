@@ -0,0 +1,82 @@
+//! The inotify API to monitor the events of the file systems.
+//!
+//! The events are generated by the file operations in `fs/file_ops`, so they work for
+//! all the mounted file systems, e.g., SEFS, unionfs, ramfs and hostfs. The changes
+//! made outside of the LibOS, e.g., by the host to a hostfs, are not reported.
+
+use super::*;
+
+pub use self::file::{AsInotify, InotifyFile};
+pub use self::watch::{
+    is_watched, new_move_cookie, notify_child, notify_file, notify_file_event, notify_inode,
+    notify_parent, notify_removed, FileParent,
+};
+
+mod file;
+mod inotify;
+mod watch;
+
+bitflags! {
+    pub struct InotifyFlags: i32 {
+        /// Non-blocking
+        const IN_NONBLOCK = 1 << 11;
+        /// Close on exec
+        const IN_CLOEXEC  = 1 << 19;
+    }
+}
+
+bitflags! {
+    pub struct InotifyMask: u32 {
+        /// File was accessed
+        const IN_ACCESS        = 0x00000001;
+        /// File was modified
+        const IN_MODIFY        = 0x00000002;
+        /// Metadata changed
+        const IN_ATTRIB        = 0x00000004;
+        /// Writable file was closed
+        const IN_CLOSE_WRITE   = 0x00000008;
+        /// Unwritable file was closed
+        const IN_CLOSE_NOWRITE = 0x00000010;
+        /// File was opened
+        const IN_OPEN          = 0x00000020;
+        /// File was moved from X
+        const IN_MOVED_FROM    = 0x00000040;
+        /// File was moved to Y
+        const IN_MOVED_TO      = 0x00000080;
+        /// Subfile was created
+        const IN_CREATE        = 0x00000100;
+        /// Subfile was deleted
+        const IN_DELETE        = 0x00000200;
+        /// Self was deleted
+        const IN_DELETE_SELF   = 0x00000400;
+        /// Self was moved
+        const IN_MOVE_SELF     = 0x00000800;
+
+        /// Backing fs was unmounted
+        const IN_UNMOUNT       = 0x00002000;
+        /// Event queue overflowed
+        const IN_Q_OVERFLOW    = 0x00004000;
+        /// File was ignored, i.e., the watch was removed
+        const IN_IGNORED       = 0x00008000;
+
+        /// Only watch the path if it is a directory
+        const IN_ONLYDIR       = 0x01000000;
+        /// Do not follow a symlink
+        const IN_DONT_FOLLOW   = 0x02000000;
+        /// Exclude the events on the unlinked children
+        const IN_EXCL_UNLINK   = 0x04000000;
+        /// Only create the watch, not modify an existing one
+        const IN_MASK_CREATE   = 0x10000000;
+        /// Add to the mask of an existing watch
+        const IN_MASK_ADD      = 0x20000000;
+        /// Event occurred against a directory
+        const IN_ISDIR         = 0x40000000;
+        /// Only send the event once
+        const IN_ONESHOT       = 0x80000000;
+
+        const IN_CLOSE = Self::IN_CLOSE_WRITE.bits | Self::IN_CLOSE_NOWRITE.bits;
+        const IN_MOVE = Self::IN_MOVED_FROM.bits | Self::IN_MOVED_TO.bits;
+        /// All the events that can be watched
+        const IN_ALL_EVENTS = 0x00000fff;
+    }
+}
@@ -0,0 +1,226 @@
+use super::inotify::{Inotify, InotifyEvent};
+use super::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Weak;
+
+/// A watch of an inotify instance on an inode.
+struct Watch {
+    inotify: Weak<Inotify>,
+    wd: i32,
+    mask: InotifyMask,
+}
+
+impl Watch {
+    fn belongs_to(&self, inotify: &Inotify) -> bool {
+        std::ptr::eq(self.inotify.as_ptr(), inotify)
+    }
+}
+
+lazy_static! {
+    /// The watches of all the inotify instances, indexed by the watched inodes.
+    static ref WATCH_TABLE: SgxMutex<HashMap<INodeKey, Vec<Watch>>> =
+        SgxMutex::new(HashMap::new());
+}
+
+// The num of watches in the table, which makes the file operations skip generating
+// events quickly if nothing is watched
+static NUM_WATCHES: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_MOVE_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Add a watch on an inode, or modify the existing one.
+pub(super) fn add_watch(
+    inotify: &Arc<Inotify>,
+    inode: &Arc<dyn INode>,
+    mask: InotifyMask,
+) -> Result<i32> {
+    let key = INodeKey::new(inode)?;
+    let events = mask & (InotifyMask::IN_ALL_EVENTS | InotifyMask::IN_ONESHOT);
+
+    let mut table = WATCH_TABLE.lock().unwrap();
+    let existing_watch = table
+        .get_mut(&key)
+        .and_then(|watches| watches.iter_mut().find(|watch| watch.belongs_to(inotify)));
+    if let Some(watch) = existing_watch {
+        if mask.contains(InotifyMask::IN_MASK_CREATE) {
+            return_errno!(EEXIST, "the inode is already watched");
+        }
+        if mask.contains(InotifyMask::IN_MASK_ADD) {
+            watch.mask |= events;
+        } else {
+            watch.mask = events;
+        }
+        return Ok(watch.wd);
+    }
+
+    let wd = inotify.add_wd(key)?;
+    table.entry(key).or_default().push(Watch {
+        inotify: Arc::downgrade(inotify),
+        wd,
+        mask: events,
+    });
+    NUM_WATCHES.fetch_add(1, Ordering::Relaxed);
+    Ok(wd)
+}
+
+/// Remove a watch, and queue an IN_IGNORED event for it.
+pub(super) fn remove_watch(inotify: &Inotify, wd: i32) -> Result<()> {
+    let mut table = WATCH_TABLE.lock().unwrap();
+    let key = inotify
+        .remove_wd(wd)
+        .ok_or_else(|| errno!(EINVAL, "invalid watch descriptor"))?;
+    remove_from_table(&mut table, &key, |watch| watch.belongs_to(inotify));
+    Ok(())
+}
+
+/// Remove all the watches of an inotify instance which is being closed.
+pub(super) fn remove_all_watches(inotify: &Inotify) {
+    let mut table = WATCH_TABLE.lock().unwrap();
+    for (_, key) in inotify.watched_inodes() {
+        remove_from_table(&mut table, &key, |watch| watch.belongs_to(inotify));
+    }
+}
+
+fn remove_from_table(
+    table: &mut HashMap<INodeKey, Vec<Watch>>,
+    key: &INodeKey,
+    mut f: impl FnMut(&Watch) -> bool,
+) {
+    if let Some(watches) = table.get_mut(key) {
+        let old_len = watches.len();
+        watches.retain(|watch| !f(watch));
+        NUM_WATCHES.fetch_sub(old_len - watches.len(), Ordering::Relaxed);
+        if watches.is_empty() {
+            table.remove(key);
+        }
+    }
+}
+
+/// Queue an event to the watches of an inode.
+fn notify(key: &INodeKey, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+    let mut table = WATCH_TABLE.lock().unwrap();
+    let is_self_removed = mask.contains(InotifyMask::IN_DELETE_SELF);
+    remove_from_table(&mut table, key, |watch| {
+        let inotify = match watch.inotify.upgrade() {
+            Some(inotify) => inotify,
+            // The inotify instance is being closed
+            None => return true,
+        };
+
+        let events = mask & watch.mask & InotifyMask::IN_ALL_EVENTS;
+        if !events.is_empty() {
+            let event_mask = events | (mask & InotifyMask::IN_ISDIR);
+            inotify.push_event(InotifyEvent::new(watch.wd, event_mask, cookie, name));
+        }
+
+        // A one-shot watch is removed after the first event, and all the watches are
+        // removed if the inode is deleted
+        let should_remove =
+            (!events.is_empty() && watch.mask.contains(InotifyMask::IN_ONESHOT)) || is_self_removed;
+        if should_remove {
+            inotify.remove_wd(watch.wd);
+        }
+        should_remove
+    });
+}
+
+/// Whether any inode is watched, i.e., the events need to be generated.
+pub fn is_watched() -> bool {
+    NUM_WATCHES.load(Ordering::Relaxed) > 0
+}
+
+/// Generate an event on an inode itself.
+pub fn notify_inode(inode: &Arc<dyn INode>, mask: InotifyMask) {
+    if !is_watched() {
+        return;
+    }
+    if let Ok(key) = INodeKey::new(inode) {
+        notify(&key, mask, 0, None);
+    }
+}
+
+/// Generate an event on a directory for its child.
+pub fn notify_child(dir_inode: &Arc<dyn INode>, mask: InotifyMask, name: &str, cookie: u32) {
+    if !is_watched() {
+        return;
+    }
+    if let Ok(key) = INodeKey::new(dir_inode) {
+        notify(&key, mask, cookie, Some(name));
+    }
+}
+
+/// The directory where a file is found and the name of the file in it, to which the
+/// events of the file are also reported.
+#[derive(Debug, Clone)]
+pub struct FileParent {
+    dir: INodeKey,
+    name: String,
+}
+
+impl FileParent {
+    pub fn new(dir_inode: &Arc<dyn INode>, name: &str) -> Result<Self> {
+        Ok(Self {
+            dir: INodeKey::new(dir_inode)?,
+            name: name.trim_end_matches('/').to_owned(),
+        })
+    }
+}
+
+/// Generate an event on a file, which is reported to the watches of both the file and
+/// its parent directory, if the parent is known.
+pub fn notify_file_event(inode: &Arc<dyn INode>, parent: Option<&FileParent>, mask: InotifyMask) {
+    if !is_watched() {
+        return;
+    }
+    let mask = match inode.metadata() {
+        Ok(metadata) if metadata.type_ == FileType::Dir => mask | InotifyMask::IN_ISDIR,
+        Ok(_) => mask,
+        Err(_) => return,
+    };
+    notify_inode(inode, mask);
+
+    if let Some(parent) = parent {
+        notify(&parent.dir, mask, 0, Some(&parent.name));
+    }
+}
+
+/// Generate an event on the parent directory of a file for the file, e.g., IN_CREATE.
+pub fn notify_parent(parent: Option<&FileParent>, mask: InotifyMask) {
+    if !is_watched() {
+        return;
+    }
+    if let Some(parent) = parent {
+        notify(&parent.dir, mask, 0, Some(&parent.name));
+    }
+}
+
+/// Generate an event on an opened file. Nothing happens if it is not an inode file.
+pub fn notify_file(file_ref: &FileRef, mask: InotifyMask) {
+    if !is_watched() {
+        return;
+    }
+    if let Ok(inode_file) = file_ref.as_inode_file() {
+        notify_file_event(inode_file.inode(), inode_file.parent(), mask);
+    }
+}
+
+/// Generate the events of an inode that has been removed from the file system, after
+/// which its watches are removed.
+pub fn notify_removed(inode: &Arc<dyn INode>) {
+    if !is_watched() {
+        return;
+    }
+    let is_removed = match inode.metadata() {
+        Ok(metadata) => metadata.nlinks == 0 || metadata.type_ == FileType::Dir,
+        Err(_) => false,
+    };
+    if is_removed {
+        notify_inode(inode, InotifyMask::IN_DELETE_SELF);
+    }
+}
+
+/// Allocate a cookie to relate the IN_MOVED_FROM and IN_MOVED_TO events of a rename.
+pub fn new_move_cookie() -> u32 {
+    NEXT_MOVE_COOKIE.fetch_add(1, Ordering::Relaxed)
+}
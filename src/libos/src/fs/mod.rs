@@ -19,15 +19,16 @@ pub use self::events::{AtomicIoEvents, IoEvents, IoNotifier};
 pub use self::file::{File, FileRef};
 pub use self::file_ops::{
     occlum_ocall_ioctl, utimbuf_t, AccessMode, BuiltinIoctlNum, CreationFlags, FallocateFlags,
-    FileMode, GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, GetWinSize, IfConf, IoctlCmd,
+    FileMode, GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, GetWinSize, IfConf, IfReq, IoctlCmd,
     IoctlRawCmd, NonBuiltinIoctlCmd, SetNonBlocking, SetWinSize, Stat, StatusFlags,
     StructuredIoctlArgType, StructuredIoctlNum, TcGets, TcSets, STATUS_FLAGS_MASK,
 };
 pub use self::file_table::{FileDesc, FileTable, FileTableEvent, FileTableNotifier};
-pub use self::fs_ops::Statfs;
+pub use self::fs_ops::{MountFlags, Statfs};
 pub use self::fs_view::FsView;
 pub use self::host_fd::HostFd;
-pub use self::inode_file::{AsINodeFile, INodeExt, INodeFile};
+pub use self::inode_file::{AsINodeFile, INodeExt, INodeFile, INodeKey};
+pub use self::inotify::{AsInotify, InotifyFile, InotifyFlags, InotifyMask};
 pub use self::locks::flock::{Flock, FlockList, FlockOps, FlockType};
 pub use self::locks::range_lock::{
     FileRange, RangeLock, RangeLockBuilder, RangeLockList, RangeLockType, OFFSET_MAX,
@@ -38,10 +39,12 @@ pub use self::stdio::{HostStdioFds, StdinFile, StdoutFile};
 pub use self::syscalls::*;
 pub use self::timer_file::{AsTimer, TimerCreationFlags, TimerFile};
 
+mod bindfs;
 pub mod channel;
 mod dev_fs;
 mod event_file;
 mod events;
+mod ext2_mkfs;
 mod file;
 mod file_ops;
 mod file_table;
@@ -50,7 +53,9 @@ mod fs_view;
 mod host_fd;
 mod hostfs;
 mod inode_file;
+mod inotify;
 mod locks;
+mod mount_table;
 mod pipe;
 mod procfs;
 mod rootfs;
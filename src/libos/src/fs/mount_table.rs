@@ -0,0 +1,218 @@
+//! The table of the mounts.
+//!
+//! A `MountFS` only knows where the file systems are mounted on it. The table records
+//! what else a mount has, e.g., its flags, which are checked by the file operations,
+//! and its source, which is shown in /proc/mounts.
+use super::fs_ops::MountFlags;
+use super::*;
+use rcore_fs_mountfs::MountFS;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+lazy_static! {
+    /// All the mounts, including the root.
+    pub static ref MOUNT_TABLE: RwLock<MountTable> = RwLock::new(MountTable::new());
+}
+
+/// What is mounted.
+#[derive(Debug, Clone)]
+pub struct MountSource {
+    /// The device or the host path, e.g., "/dev/sworndisk0" and "./run/mnt", or the
+    /// name of the file system if there is no such thing
+    pub name: String,
+    pub fs_type: String,
+    /// The path in the file system which is the root of the mount, which is not "/"
+    /// only for the bind mounts
+    pub root: PathBuf,
+}
+
+impl MountSource {
+    pub fn new(name: &str, fs_type: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            fs_type: fs_type.to_owned(),
+            root: PathBuf::from("/"),
+        }
+    }
+}
+
+/// A mount of a file system.
+#[derive(Clone)]
+pub struct Mount {
+    id: usize,
+    parent_id: usize,
+    mount_fs: Arc<MountFS>,
+    fs: Arc<dyn FileSystem>,
+    source: MountSource,
+    target: PathBuf,
+    flags: MountFlags,
+    // The number of the files opened for writing through the mount
+    writers: Arc<AtomicUsize>,
+}
+
+impl Mount {
+    /// The unique ID of the mount.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The ID of the mount where it is mounted on. The root mount is its own parent.
+    pub fn parent_id(&self) -> usize {
+        self.parent_id
+    }
+
+    /// The mounted file system, i.e., the one wrapped by the `MountFS`.
+    pub fn fs(&self) -> &Arc<dyn FileSystem> {
+        &self.fs
+    }
+
+    pub fn source(&self) -> &MountSource {
+        &self.source
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    pub fn flags(&self) -> MountFlags {
+        self.flags
+    }
+
+    pub fn set_flags(&mut self, flags: MountFlags) {
+        self.flags = flags & MountFlags::per_mount_flags();
+    }
+
+    /// Count a file opened for writing through the mount, until the returned
+    /// `MountWriter` is dropped.
+    pub fn add_writer(&self) -> MountWriter {
+        self.writers.fetch_add(1, Ordering::Relaxed);
+        MountWriter(self.writers.clone())
+    }
+
+    pub fn has_writers(&self) -> bool {
+        self.writers.load(Ordering::Relaxed) > 0
+    }
+
+    fn fs_id(&self) -> usize {
+        Arc::as_ptr(&self.mount_fs) as *const u8 as usize
+    }
+}
+
+/// A file opened for writing through a mount, which keeps the mount from becoming
+/// read-only. A shared file mapping holds the file, so it is counted too.
+#[derive(Debug)]
+pub struct MountWriter(Arc<AtomicUsize>);
+
+impl Drop for MountWriter {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct MountTable {
+    mounts: Vec<Mount>,
+    next_id: usize,
+}
+
+impl MountTable {
+    pub fn new() -> Self {
+        Self {
+            mounts: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Add a mount on the mount point, or the root mount if there is no mount point.
+    pub fn add(
+        &mut self,
+        mount_fs: &Arc<MountFS>,
+        fs: Arc<dyn FileSystem>,
+        mount_point: Option<&dyn INode>,
+        target: &Path,
+        source: MountSource,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let parent_id = mount_point
+            .and_then(|mount_point| self.get(mount_point))
+            .map_or(id, |parent| parent.id);
+        self.mounts.push(Mount {
+            id,
+            parent_id,
+            mount_fs: mount_fs.clone(),
+            fs,
+            source,
+            target: target.to_path_buf(),
+            flags: MountFlags::empty(),
+            writers: Arc::new(AtomicUsize::new(0)),
+        });
+    }
+
+    /// Make the mount the new root, which has been removed from the table. The old root
+    /// is mounted at `put_old` in the new root through `old_root_fs`, with the mounts
+    /// under it.
+    pub fn pivot_root(
+        &mut self,
+        mut new_root: Mount,
+        new_root_fs: &Arc<MountFS>,
+        old_root_fs: &Arc<MountFS>,
+        put_old: &Path,
+    ) {
+        for mount in self.mounts.iter_mut() {
+            if mount.parent_id == mount.id {
+                mount.parent_id = new_root.id;
+                mount.mount_fs = old_root_fs.clone();
+            }
+            let path_in_old_root = mount.target.strip_prefix("/").unwrap();
+            mount.target = if path_in_old_root.as_os_str().is_empty() {
+                put_old.to_path_buf()
+            } else {
+                put_old.join(path_in_old_root)
+            };
+        }
+        new_root.parent_id = new_root.id;
+        new_root.mount_fs = new_root_fs.clone();
+        new_root.target = PathBuf::from("/");
+        self.mounts.insert(0, new_root);
+    }
+
+    /// Remove the mount of an inode. The mounts under it are also removed, since
+    /// they are umounted together.
+    pub fn remove(&mut self, inode: &dyn INode) -> Option<Mount> {
+        let idx = self.position(inode)?;
+        let mount = self.mounts.remove(idx);
+        self.mounts
+            .retain(|submount| !submount.target.starts_with(&mount.target));
+        Some(mount)
+    }
+
+    /// The mount of an inode, which is looked up through the `MountFS`.
+    pub fn get(&self, inode: &dyn INode) -> Option<&Mount> {
+        self.position(inode).map(|idx| &self.mounts[idx])
+    }
+
+    pub fn get_mut(&mut self, inode: &dyn INode) -> Option<&mut Mount> {
+        self.position(inode).map(move |idx| &mut self.mounts[idx])
+    }
+
+    /// Whether there are mounts under the mount.
+    pub fn has_submounts(&self, mount: &Mount) -> bool {
+        self.mounts
+            .iter()
+            .any(|submount| submount.id != mount.id && submount.target.starts_with(&mount.target))
+    }
+
+    /// All the mounts in the order of mounting.
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    pub fn clear(&mut self) {
+        self.mounts.clear();
+    }
+
+    fn position(&self, inode: &dyn INode) -> Option<usize> {
+        let fs_id = Arc::as_ptr(&inode.fs()) as *const u8 as usize;
+        self.mounts.iter().position(|mount| mount.fs_id() == fs_id)
+    }
+}
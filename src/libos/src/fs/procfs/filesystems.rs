@@ -0,0 +1,31 @@
+use super::*;
+
+pub struct FilesystemsINode;
+
+// The file systems which can be mounted, and whether a device is needed for them
+const FILESYSTEMS: &[(&str, bool)] = &[
+    ("sefs", false),
+    ("hostfs", false),
+    ("ramfs", false),
+    ("unionfs", false),
+    ("devfs", false),
+    ("procfs", false),
+    ("ext2", true),
+];
+
+impl FilesystemsINode {
+    pub fn new() -> Arc<dyn INode> {
+        Arc::new(File::new(Self))
+    }
+}
+
+impl ProcINode for FilesystemsINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let mut result = String::new();
+        for (name, requires_dev) in FILESYSTEMS {
+            let nodev = if *requires_dev { "" } else { "nodev" };
+            result.push_str(&format!("{}\t{}\n", nodev, name));
+        }
+        Ok(result.into_bytes())
+    }
+}
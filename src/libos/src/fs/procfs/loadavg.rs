@@ -0,0 +1,37 @@
+use super::*;
+use crate::process::{ThreadId, ThreadStatus};
+
+pub struct LoadAvgINode;
+
+impl LoadAvgINode {
+    pub fn new() -> Arc<dyn INode> {
+        Arc::new(File::new(Self))
+    }
+}
+
+impl ProcINode for LoadAvgINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let threads: Vec<_> = get_all_processes()
+            .iter()
+            .flat_map(|process| process.threads())
+            .collect();
+        // Whether a running thread is blocked is not tracked by the scheduler, so all
+        // the running threads are counted as runnable. And without the history, the
+        // load averages are the number of the runnable threads at present.
+        let num_runnable = threads
+            .iter()
+            .filter(|thread| thread.status() == ThreadStatus::Running)
+            .count();
+        let load = num_runnable as f64;
+        Ok(format!(
+            "{:.2} {:.2} {:.2} {}/{} {}\n",
+            load,
+            load,
+            load,
+            num_runnable,
+            threads.len(),
+            ThreadId::last_allocated()
+        )
+        .into_bytes())
+    }
+}
@@ -6,18 +6,30 @@ use crate::process::pid_t;
 use crate::process::table::get_all_processes;
 
 use self::cpuinfo::CpuInfoINode;
+use self::filesystems::FilesystemsINode;
+use self::loadavg::LoadAvgINode;
 use self::meminfo::MemInfoINode;
+use self::mounts::MountsSymINode;
 use self::pid::LockedPidDirINode;
 use self::proc_inode::{Dir, DirProcINode, File, ProcINode, SymLink};
 use self::self_::SelfSymINode;
 use self::stat::StatINode;
+use self::sys::LockedSysDirINode;
+use self::uptime::UptimeINode;
+use self::version::VersionINode;
 
 mod cpuinfo;
+mod filesystems;
+mod loadavg;
 mod meminfo;
+mod mounts;
 mod pid;
 mod proc_inode;
 mod self_;
 mod stat;
+mod sys;
+mod uptime;
+mod version;
 
 // Same with the procfs on Linux
 const PROC_SUPER_MAGIC: usize = 0x9fa0;
@@ -113,6 +125,24 @@ impl LockedProcRootINode {
         let stat_inode = StatINode::new();
         file.non_volatile_entries
             .insert(String::from("stat"), stat_inode);
+        let mounts_inode = MountsSymINode::new();
+        file.non_volatile_entries
+            .insert(String::from("mounts"), mounts_inode);
+        let filesystems_inode = FilesystemsINode::new();
+        file.non_volatile_entries
+            .insert(String::from("filesystems"), filesystems_inode);
+        let uptime_inode = UptimeINode::new();
+        file.non_volatile_entries
+            .insert(String::from("uptime"), uptime_inode);
+        let loadavg_inode = LoadAvgINode::new();
+        file.non_volatile_entries
+            .insert(String::from("loadavg"), loadavg_inode);
+        let version_inode = VersionINode::new();
+        file.non_volatile_entries
+            .insert(String::from("version"), version_inode);
+        let sys_inode = LockedSysDirINode::new(file.this.clone());
+        file.non_volatile_entries
+            .insert(String::from("sys"), sys_inode);
     }
 }
 
@@ -0,0 +1,16 @@
+use super::*;
+
+/// The symbolic link of /proc/mounts, which points to /proc/self/mounts like Linux.
+pub struct MountsSymINode;
+
+impl MountsSymINode {
+    pub fn new() -> Arc<dyn INode> {
+        Arc::new(SymLink::new(Self))
+    }
+}
+
+impl ProcINode for MountsSymINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        Ok(b"self/mounts".to_vec())
+    }
+}
@@ -1,19 +1,38 @@
 use super::*;
+use crate::process::ThreadName;
 
-pub struct ProcCommINode(ProcessRef);
+pub struct ProcCommINode(ProcThread);
 
 impl ProcCommINode {
     pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
-        Arc::new(File::new(Self(Arc::clone(process_ref))))
+        let inner = Self(ProcThread::Main(Arc::clone(process_ref)));
+        Arc::new(File::new_writable(inner))
+    }
+
+    pub fn new_for_thread(thread_ref: &ThreadRef) -> Arc<dyn INode> {
+        let inner = Self(ProcThread::Task(Arc::clone(thread_ref)));
+        Arc::new(File::new_writable(inner))
     }
 }
 
 impl ProcINode for ProcCommINode {
     fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
-        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
-        let mut comm = main_thread.name().as_c_str().to_bytes().to_vec();
+        let thread = self.0.thread()?;
+        let mut comm = thread.name().as_c_str().to_bytes().to_vec();
         // Add '\n' at the end to make the result same with Linux
         comm.push(b'\n');
         Ok(comm)
     }
+
+    fn write_data_in_bytes(&self, data: &[u8]) -> vfs::Result<()> {
+        let thread = self.0.thread()?;
+        // The trailing '\n' is added by the commands like echo, which is not a part of the name
+        let name = data.strip_suffix(b"\n").unwrap_or(data);
+        // Like PR_SET_NAME, the name is truncated if it is too long
+        let mut buf = [0; ThreadName::max_len()];
+        let len = name.len().min(ThreadName::max_len() - 1);
+        buf[..len].copy_from_slice(&name[..len]);
+        thread.set_name(ThreadName::from_slice(&buf));
+        Ok(())
+    }
 }
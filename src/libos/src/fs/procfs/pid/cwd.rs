@@ -11,7 +11,17 @@ impl ProcCwdSymINode {
 impl ProcINode for ProcCwdSymINode {
     fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
         let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
-        let fs = main_thread.fs().read().unwrap();
-        Ok(fs.cwd().to_owned().into_bytes())
+        let path = {
+            let fs = main_thread.fs().read().unwrap();
+            fs.convert_to_global_path(fs.cwd())
+        };
+        // Like Linux, the path is shown in the root of the reader
+        let path = current!()
+            .fs()
+            .read()
+            .unwrap()
+            .convert_from_global_path(&path)
+            .ok_or(FsError::EntryNotFound)?;
+        Ok(path.into_bytes())
     }
 }
@@ -0,0 +1,26 @@
+use super::*;
+
+pub struct ProcEnvironINode(ProcessRef);
+
+impl ProcEnvironINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcEnvironINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        if let ProcessStatus::Zombie = self.0.status() {
+            return Ok(Vec::new());
+        }
+        // Null-terminated strings of the initial environment, same with Linux
+        let environ = self
+            .0
+            .environ()
+            .iter()
+            .flat_map(|env| env.as_bytes_with_nul())
+            .cloned()
+            .collect();
+        Ok(environ)
+    }
+}
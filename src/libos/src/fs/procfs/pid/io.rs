@@ -0,0 +1,31 @@
+use super::*;
+
+pub struct ProcIoINode(ProcessRef);
+
+impl ProcIoINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcIoINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let io_stats = self.0.io_stats();
+        // The bytes fetched from or sent to the storage are not tracked, since the
+        // I/O to the host is done by the file systems of the LibOS.
+        Ok(format!(
+            "rchar: {}\n\
+             wchar: {}\n\
+             syscr: {}\n\
+             syscw: {}\n\
+             read_bytes: 0\n\
+             write_bytes: 0\n\
+             cancelled_write_bytes: 0\n",
+            io_stats.rchar(),
+            io_stats.wchar(),
+            io_stats.syscr(),
+            io_stats.syscw(),
+        )
+        .into_bytes())
+    }
+}
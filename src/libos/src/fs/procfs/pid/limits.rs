@@ -0,0 +1,62 @@
+use super::*;
+use crate::misc::resource_t;
+
+pub struct ProcLimitsINode(ProcessRef);
+
+impl ProcLimitsINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+// The names and units of the resource limits in the order of resource_t
+const LIMITS: [(&str, &str); 16] = [
+    ("Max cpu time", "seconds"),
+    ("Max file size", "bytes"),
+    ("Max data size", "bytes"),
+    ("Max stack size", "bytes"),
+    ("Max core file size", "bytes"),
+    ("Max resident set", "bytes"),
+    ("Max processes", "processes"),
+    ("Max open files", "files"),
+    ("Max locked memory", "bytes"),
+    ("Max address space", "bytes"),
+    ("Max file locks", "locks"),
+    ("Max pending signals", "signals"),
+    ("Max msgqueue size", "bytes"),
+    ("Max nice priority", ""),
+    ("Max realtime priority", ""),
+    ("Max realtime timeout", "us"),
+];
+
+impl ProcINode for ProcLimitsINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let rlimits = *main_thread.rlimits().lock().unwrap();
+
+        let mut result = format!(
+            "{:<25} {:<20} {:<20} {:<10}\n",
+            "Limit", "Soft Limit", "Hard Limit", "Units"
+        );
+        for (idx, (name, unit)) in LIMITS.iter().enumerate() {
+            let rlimit = rlimits.get(resource_t::from_u32(idx as u32).unwrap());
+            result.push_str(&format!(
+                "{:<25} {:<20} {:<20} {:<10}\n",
+                name,
+                limit_to_string(rlimit.get_cur()),
+                limit_to_string(rlimit.get_max()),
+                unit
+            ));
+        }
+        Ok(result.into_bytes())
+    }
+}
+
+fn limit_to_string(limit: u64) -> String {
+    // RLIM_INFINITY
+    if limit == u64::max_value() {
+        String::from("unlimited")
+    } else {
+        limit.to_string()
+    }
+}
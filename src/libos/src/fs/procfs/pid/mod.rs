@@ -1,24 +1,65 @@
 use super::*;
 use crate::process::table::get_process;
-use crate::process::{ProcessRef, ProcessStatus};
+use crate::process::{ProcessRef, ProcessStatus, ThreadRef};
 
 use self::cmdline::ProcCmdlineINode;
 use self::comm::ProcCommINode;
 use self::cwd::ProcCwdSymINode;
+use self::environ::ProcEnvironINode;
 use self::exe::ProcExeSymINode;
 use self::fd::LockedProcFdDirINode;
+use self::io::ProcIoINode;
+use self::limits::ProcLimitsINode;
 use self::maps::ProcMapsINode;
+use self::mountinfo::ProcMountInfoINode;
+use self::mounts::ProcMountsINode;
 use self::root::ProcRootSymINode;
 use self::stat::ProcStatINode;
+use self::statm::ProcStatmINode;
+use self::status::ProcStatusINode;
+use self::task::LockedProcTaskDirINode;
 
 mod cmdline;
 mod comm;
 mod cwd;
+mod environ;
 mod exe;
 mod fd;
+mod io;
+mod limits;
 mod maps;
+mod mountinfo;
+mod mounts;
 mod root;
 mod stat;
+mod statm;
+mod status;
+mod task;
+
+/// The thread whose information is shown by an inode, which is the main thread for
+/// /proc/[pid], or the specific thread for /proc/[pid]/task/[tid].
+enum ProcThread {
+    Main(ProcessRef),
+    Task(ThreadRef),
+}
+
+impl ProcThread {
+    fn process(&self) -> &ProcessRef {
+        match self {
+            ProcThread::Main(process_ref) => process_ref,
+            ProcThread::Task(thread_ref) => thread_ref.process(),
+        }
+    }
+
+    fn thread(&self) -> vfs::Result<ThreadRef> {
+        match self {
+            ProcThread::Main(process_ref) => {
+                process_ref.main_thread().ok_or(FsError::EntryNotFound)
+            }
+            ProcThread::Task(thread_ref) => Ok(Arc::clone(thread_ref)),
+        }
+    }
+}
 
 pub struct LockedPidDirINode(RwLock<PidDirINode>);
 
@@ -65,6 +106,28 @@ impl LockedPidDirINode {
         // maps
         let maps_inode = ProcMapsINode::new(&file.process_ref);
         file.entries.insert(String::from("maps"), maps_inode);
+        // status
+        let status_inode = ProcStatusINode::new(&file.process_ref);
+        file.entries.insert(String::from("status"), status_inode);
+        // statm
+        let statm_inode = ProcStatmINode::new(&file.process_ref);
+        file.entries.insert(String::from("statm"), statm_inode);
+        // environ
+        let environ_inode = ProcEnvironINode::new(&file.process_ref);
+        file.entries.insert(String::from("environ"), environ_inode);
+        // limits
+        let limits_inode = ProcLimitsINode::new(&file.process_ref);
+        file.entries.insert(String::from("limits"), limits_inode);
+        // io
+        let io_inode = ProcIoINode::new(&file.process_ref);
+        file.entries.insert(String::from("io"), io_inode);
+        // mounts
+        let mounts_inode = ProcMountsINode::new(&file.process_ref);
+        file.entries.insert(String::from("mounts"), mounts_inode);
+        // mountinfo
+        let mountinfo_inode = ProcMountInfoINode::new(&file.process_ref);
+        file.entries
+            .insert(String::from("mountinfo"), mountinfo_inode);
 
         Ok(())
     }
@@ -81,13 +144,18 @@ impl DirProcINode for LockedPidDirINode {
         }
         // The 'fd' entry holds 1 Arc of LockedPidDirINode, so the LockedPidDirINode
         // ifself will hold 2 Arcs. This makes it cannot be dropped automatically.
-        // We initialize the 'fd' here to avoid this.
+        // We initialize the 'fd' here to avoid this. So is the 'task' entry.
         // TODO:: Try to find a better solution.
         if name == "fd" {
             let fd_inode =
                 LockedProcFdDirINode::new(&file.process_ref, file.this.upgrade().unwrap());
             return Ok(fd_inode);
         }
+        if name == "task" {
+            let task_inode =
+                LockedProcTaskDirINode::new(&file.process_ref, file.this.upgrade().unwrap());
+            return Ok(task_inode);
+        }
 
         if let Some(inode) = file.entries.get(name) {
             Ok(Arc::clone(inode))
@@ -106,6 +174,8 @@ impl DirProcINode for LockedPidDirINode {
                     Ok(name.to_owned())
                 } else if i == file.entries.len() + 2 {
                     Ok(String::from("fd"))
+                } else if i == file.entries.len() + 3 {
+                    Ok(String::from("task"))
                 } else {
                     Err(FsError::EntryNotFound)
                 }
@@ -142,6 +212,17 @@ impl DirProcINode for LockedPidDirINode {
                     );
                 }
 
+                // The task entry
+                if *offset == 3 + file.entries.len() {
+                    rcore_fs::visit_entry!(
+                        &mut visitor,
+                        "task",
+                        PROC_INO as u64,
+                        vfs::FileType::Dir,
+                        &mut offset
+                    );
+                }
+
                 Ok(())
             };
 
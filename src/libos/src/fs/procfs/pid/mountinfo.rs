@@ -0,0 +1,42 @@
+use super::mounts::{escape, mount_options, visible_mounts};
+use super::*;
+use crate::fs::MountFlags;
+
+pub struct ProcMountInfoINode(ProcessRef);
+
+impl ProcMountInfoINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcMountInfoINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let mut result = String::new();
+        for (mount, view) in visible_mounts(&self.0)? {
+            let source = mount.source();
+            // The flags of a file system are those of its mount, since a file system is
+            // not shared by the mounts except the bind mounts
+            let super_options = if mount.flags().contains(MountFlags::MS_RDONLY) {
+                "ro"
+            } else {
+                "rw"
+            };
+            // There are no device numbers for the mounts, so the mount ID is used as the
+            // minor number. And there are no optional fields since the mounts are private.
+            result.push_str(&format!(
+                "{} {} 0:{} {} {} {} - {} {} {}\n",
+                mount.id(),
+                mount.parent_id(),
+                mount.id(),
+                escape(&view.root.to_string_lossy()),
+                escape(&view.target.to_string_lossy()),
+                mount_options(mount.flags()),
+                escape(&source.fs_type),
+                escape(&source.name),
+                super_options,
+            ));
+        }
+        Ok(result.into_bytes())
+    }
+}
@@ -0,0 +1,119 @@
+use super::*;
+use crate::fs::mount_table::{Mount, MOUNT_TABLE};
+use crate::fs::MountFlags;
+use std::path::{Path, PathBuf};
+
+pub struct ProcMountsINode(ProcessRef);
+
+impl ProcMountsINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcMountsINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let mut result = String::new();
+        for (mount, view) in visible_mounts(&self.0)? {
+            let source = mount.source();
+            result.push_str(&format!(
+                "{} {} {} {} 0 0\n",
+                escape(&source.name),
+                escape(&view.target.to_string_lossy()),
+                escape(&source.fs_type),
+                mount_options(mount.flags()),
+            ));
+        }
+        Ok(result.into_bytes())
+    }
+}
+
+/// How a mount is seen by a process.
+pub(super) struct MountView {
+    /// The path where the mount is in the root of the process
+    pub target: PathBuf,
+    /// The path in the file system which is the root of the mount as seen by the process
+    pub root: PathBuf,
+}
+
+/// The mounts visible in the root of the process, like Linux.
+///
+/// The mounts outside the root are not shown, and the mount where the root is in is
+/// shown at "/", with its root moved to the root of the process.
+pub(super) fn visible_mounts(process: &ProcessRef) -> vfs::Result<Vec<(Mount, MountView)>> {
+    let main_thread = process.main_thread().ok_or(FsError::EntryNotFound)?;
+    let process_root = PathBuf::from(main_thread.fs().read().unwrap().root());
+
+    let mount_table = MOUNT_TABLE.read().unwrap();
+    // The last mount wins since it covers the earlier ones at the same place
+    let root_mount_id = mount_table
+        .mounts()
+        .iter()
+        .rev()
+        .find(|mount| process_root.starts_with(mount.target()))
+        .map(|mount| mount.id());
+
+    let mut visible_mounts = Vec::new();
+    for mount in mount_table.mounts() {
+        let path_in_root = match process_root_relative(mount.target(), &process_root) {
+            Some(path_in_root) => path_in_root,
+            None if Some(mount.id()) == root_mount_id => {
+                let path_in_mount = process_root.strip_prefix(mount.target()).unwrap();
+                let view = MountView {
+                    target: PathBuf::from("/"),
+                    root: mount.source().root.join(path_in_mount),
+                };
+                visible_mounts.push((mount.clone(), view));
+                continue;
+            }
+            None => continue,
+        };
+        let view = MountView {
+            target: path_in_root,
+            root: mount.source().root.clone(),
+        };
+        visible_mounts.push((mount.clone(), view));
+    }
+    Ok(visible_mounts)
+}
+
+// The path in the process root, or `None` if the path is not under the root.
+fn process_root_relative(path: &Path, process_root: &Path) -> Option<PathBuf> {
+    let path_in_root = path.strip_prefix(process_root).ok()?;
+    Some(Path::new("/").join(path_in_root))
+}
+
+/// The options of a mount in the format of /proc/mounts, e.g., "rw,nosuid,noexec".
+pub(super) fn mount_options(flags: MountFlags) -> String {
+    let mut options = vec![if flags.contains(MountFlags::MS_RDONLY) {
+        "ro"
+    } else {
+        "rw"
+    }];
+    let flag_options = [
+        (MountFlags::MS_NOSUID, "nosuid"),
+        (MountFlags::MS_NODEV, "nodev"),
+        (MountFlags::MS_NOEXEC, "noexec"),
+        (MountFlags::MS_NOATIME, "noatime"),
+        (MountFlags::MS_NODIRATIME, "nodiratime"),
+        (MountFlags::MS_RELATIME, "relatime"),
+    ];
+    for &(flag, option) in flag_options.iter() {
+        if flags.contains(flag) {
+            options.push(option);
+        }
+    }
+    options.join(",")
+}
+
+/// Escape the characters which are used as separators, in the same way with Linux.
+pub(super) fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            ' ' | '\t' | '\n' | '\\' => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -11,7 +11,17 @@ impl ProcRootSymINode {
 impl ProcINode for ProcRootSymINode {
     fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
         let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
-        let fs = main_thread.fs().read().unwrap();
-        Ok(fs.root().to_owned().into_bytes())
+        let path = {
+            let fs = main_thread.fs().read().unwrap();
+            fs.root().to_owned()
+        };
+        // Like Linux, the path is shown in the root of the reader
+        let path = current!()
+            .fs()
+            .read()
+            .unwrap()
+            .convert_from_global_path(&path)
+            .ok_or(FsError::EntryNotFound)?;
+        Ok(path.into_bytes())
     }
 }
@@ -1,29 +1,34 @@
 use super::*;
 
-pub struct ProcStatINode(ProcessRef);
+pub struct ProcStatINode(ProcThread);
 
 impl ProcStatINode {
     pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
-        Arc::new(File::new(Self(Arc::clone(process_ref))))
+        Arc::new(File::new(Self(ProcThread::Main(Arc::clone(process_ref)))))
+    }
+
+    pub fn new_for_thread(thread_ref: &ThreadRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(ProcThread::Task(Arc::clone(thread_ref)))))
     }
 }
 
 impl ProcINode for ProcStatINode {
     fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
-        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let process = self.0.process();
+        let thread = self.0.thread()?;
 
         // Get the process status information, some fields are filled with the
         // dummy value 0, while some fields are denies to access with value 0.
         // TODO: Fill in the dummy fields with meaningful values
-        let pid = main_thread.tid();
-        let comm = String::from_utf8(main_thread.name().as_c_str().to_bytes().to_vec()).unwrap();
-        let state = match self.0.status() {
+        let pid = thread.tid();
+        let comm = String::from_utf8(thread.name().as_c_str().to_bytes().to_vec()).unwrap();
+        let state = match process.status() {
             ProcessStatus::Running => "R",
             ProcessStatus::Stopped => "T",
             ProcessStatus::Zombie => "Z",
         };
-        let ppid = self.0.parent().pid();
-        let pgrp = self.0.pgid();
+        let ppid = process.parent().pid();
+        let pgrp = process.pgid();
         let session = pgrp.clone();
         let tty_nr = 0;
         let tpgid = pgrp.clone();
@@ -37,12 +42,12 @@ impl ProcINode for ProcStatINode {
         let cutime = 0;
         let cstime = 0;
         // Convert [19,-20] to [39,0].
-        let priority = main_thread.nice().read().unwrap().to_raw_val() + 20;
-        let nice = main_thread.nice().read().unwrap().to_raw_val();
-        let num_threads = self.0.threads().len();
+        let priority = thread.nice().read().unwrap().to_raw_val() + 20;
+        let nice = thread.nice().read().unwrap().to_raw_val();
+        let num_threads = process.threads().len();
         let itrealvalue = 0;
-        let starttime = self.0.start_time();
-        let vsize = main_thread.vm().get_process_range().size();
+        let starttime = process.start_time();
+        let vsize = thread.vm().get_process_range().size();
         let rss = 0;
         let rsslim = 0;
         let startcode = 0;
@@ -0,0 +1,30 @@
+use super::*;
+use crate::vm::PAGE_SIZE;
+
+pub struct ProcStatmINode(ProcessRef);
+
+impl ProcStatmINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcStatmINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let usage = main_thread.vm().get_mem_usage(self.0.pid());
+
+        // All the fields are measured in pages. Like Linux, the lib and dt fields are
+        // always 0.
+        let to_pages = |size: usize| size / PAGE_SIZE;
+        Ok(format!(
+            "{} {} {} {} 0 {} 0\n",
+            to_pages(usage.total),
+            to_pages(usage.resident),
+            to_pages(usage.shared),
+            to_pages(usage.exe),
+            to_pages(usage.data + usage.stack),
+        )
+        .into_bytes())
+    }
+}
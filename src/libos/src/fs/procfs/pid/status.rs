@@ -0,0 +1,121 @@
+use super::*;
+use crate::misc::resource_t;
+use crate::signal::{SigAction, SigSet};
+
+pub struct ProcStatusINode(ProcThread);
+
+impl ProcStatusINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(ProcThread::Main(Arc::clone(process_ref)))))
+    }
+
+    pub fn new_for_thread(thread_ref: &ThreadRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(ProcThread::Task(Arc::clone(thread_ref)))))
+    }
+}
+
+const KB: usize = 1024;
+
+impl ProcINode for ProcStatusINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let process = self.0.process();
+        let thread = self.0.thread()?;
+
+        let name = String::from_utf8_lossy(thread.name().as_c_str().to_bytes()).into_owned();
+        let state = match process.status() {
+            ProcessStatus::Running => "R (running)",
+            ProcessStatus::Stopped => "T (stopped)",
+            ProcessStatus::Zombie => "Z (zombie)",
+        };
+        let pid = process.pid();
+        let tid = thread.tid();
+        let ppid = process.parent().pid();
+        let fd_size = thread.files().lock().table().len();
+        let usage = thread.vm().get_mem_usage(pid);
+        let num_threads = process.threads().len();
+
+        // Signals
+        let (queued_sigs, shared_pending) = {
+            let sig_queues = process.sig_queues().read().unwrap();
+            (sig_queues.len(), sig_queues.pending())
+        };
+        let (thread_pending, blocked) = {
+            let sig_queues = thread.sig_queues().read().unwrap();
+            let blocked = *thread.sig_mask().read().unwrap();
+            (sig_queues.pending(), blocked)
+        };
+        let (ignored, caught) = {
+            let mut ignored = SigSet::new_empty();
+            let mut caught = SigSet::new_empty();
+            for (signum, action) in process.sig_dispositions().read().unwrap().iter() {
+                match action {
+                    SigAction::Dfl => {}
+                    SigAction::Ign => ignored += signum,
+                    SigAction::User { .. } => caught += signum,
+                }
+            }
+            (ignored, caught)
+        };
+        let sig_pending_limit = thread
+            .rlimits()
+            .lock()
+            .unwrap()
+            .get(resource_t::RLIMIT_SIGPENDING)
+            .get_cur();
+
+        // There are no users or groups in the LibOS, so all the IDs are 0
+        Ok(format!(
+            "Name:\t{}\n\
+             Umask:\t{:04o}\n\
+             State:\t{}\n\
+             Tgid:\t{}\n\
+             Ngid:\t0\n\
+             Pid:\t{}\n\
+             PPid:\t{}\n\
+             TracerPid:\t0\n\
+             Uid:\t0\t0\t0\t0\n\
+             Gid:\t0\t0\t0\t0\n\
+             FDSize:\t{}\n\
+             Groups:\t\n\
+             VmSize:\t{:>8} kB\n\
+             VmLck:\t{:>8} kB\n\
+             VmRSS:\t{:>8} kB\n\
+             VmData:\t{:>8} kB\n\
+             VmStk:\t{:>8} kB\n\
+             VmExe:\t{:>8} kB\n\
+             VmLib:\t{:>8} kB\n\
+             VmSwap:\t{:>8} kB\n\
+             Threads:\t{}\n\
+             SigQ:\t{}/{}\n\
+             SigPnd:\t{:016x}\n\
+             ShdPnd:\t{:016x}\n\
+             SigBlk:\t{:016x}\n\
+             SigIgn:\t{:016x}\n\
+             SigCgt:\t{:016x}\n",
+            name,
+            process.umask().bits(),
+            state,
+            pid,
+            tid,
+            ppid,
+            fd_size,
+            usage.total / KB,
+            0,
+            usage.resident / KB,
+            usage.data / KB,
+            usage.stack / KB,
+            usage.exe / KB,
+            usage.lib / KB,
+            0,
+            num_threads,
+            queued_sigs,
+            sig_pending_limit,
+            thread_pending.as_u64(),
+            shared_pending.as_u64(),
+            blocked.as_u64(),
+            ignored.as_u64(),
+            caught.as_u64(),
+        )
+        .into_bytes())
+    }
+}
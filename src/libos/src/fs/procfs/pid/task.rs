@@ -0,0 +1,188 @@
+use super::*;
+
+/// The directory of /proc/[pid]/task, which has an entry for each thread.
+pub struct LockedProcTaskDirINode(RwLock<ProcTaskDirINode>);
+
+struct ProcTaskDirINode {
+    process_ref: ProcessRef,
+    this: Weak<Dir<LockedProcTaskDirINode>>,
+    parent: Arc<dyn INode>,
+}
+
+impl LockedProcTaskDirINode {
+    pub fn new(process_ref: &ProcessRef, parent: Arc<dyn INode>) -> Arc<dyn INode> {
+        let inode = Arc::new(Dir::new(Self(RwLock::new(ProcTaskDirINode {
+            process_ref: Arc::clone(process_ref),
+            this: Weak::default(),
+            parent: Arc::clone(&parent),
+        }))));
+        inode.inner().0.write().unwrap().this = Arc::downgrade(&inode);
+        inode
+    }
+}
+
+impl DirProcINode for LockedProcTaskDirINode {
+    fn find(&self, name: &str) -> vfs::Result<Arc<dyn INode>> {
+        let file = self.0.read().unwrap();
+        if name == "." {
+            return Ok(file.this.upgrade().unwrap());
+        }
+        if name == ".." {
+            return Ok(Arc::clone(&file.parent));
+        }
+        let tid = name.parse::<pid_t>().map_err(|_| FsError::EntryNotFound)?;
+        let thread_ref = file
+            .process_ref
+            .threads()
+            .into_iter()
+            .find(|thread| thread.tid() == tid)
+            .ok_or(FsError::EntryNotFound)?;
+        let tid_inode = LockedTidDirINode::new(&thread_ref, file.this.upgrade().unwrap());
+        Ok(tid_inode)
+    }
+
+    fn get_entry(&self, id: usize) -> vfs::Result<String> {
+        match id {
+            0 => Ok(String::from(".")),
+            1 => Ok(String::from("..")),
+            i => {
+                let file = self.0.read().unwrap();
+                let threads = file.process_ref.threads();
+                let thread = threads.iter().nth(i - 2).ok_or(FsError::EntryNotFound)?;
+                Ok(thread.tid().to_string())
+            }
+        }
+    }
+
+    fn iterate_entries(
+        &self,
+        offset: usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> vfs::Result<usize> {
+        let file = self.0.read().unwrap();
+
+        let try_iterate =
+            |mut offset: &mut usize, mut visitor: &mut dyn DirentVisitor| -> vfs::Result<()> {
+                // The two special entries
+                visit_first_two_entries!(&mut visitor, &file, &mut offset);
+
+                // The tid entries
+                let start_offset = *offset;
+                for thread in file.process_ref.threads().iter().skip(start_offset - 2) {
+                    rcore_fs::visit_entry!(
+                        &mut visitor,
+                        &thread.tid().to_string(),
+                        PROC_INO,
+                        vfs::FileType::Dir,
+                        &mut offset
+                    );
+                }
+
+                Ok(())
+            };
+
+        let mut iterate_offset = offset;
+        match try_iterate(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+}
+
+/// The directory of /proc/[pid]/task/[tid], which shows the information of the thread.
+struct LockedTidDirINode(RwLock<TidDirINode>);
+
+struct TidDirINode {
+    this: Weak<Dir<LockedTidDirINode>>,
+    parent: Arc<dyn INode>,
+    entries: HashMap<String, Arc<dyn INode>>,
+}
+
+impl LockedTidDirINode {
+    fn new(thread_ref: &ThreadRef, parent: Arc<dyn INode>) -> Arc<dyn INode> {
+        let inode = Arc::new(Dir::new(Self(RwLock::new(TidDirINode {
+            this: Weak::default(),
+            parent: Arc::clone(&parent),
+            entries: HashMap::new(),
+        }))));
+        inode.inner().0.write().unwrap().this = Arc::downgrade(&inode);
+        inode.inner().init_entries(thread_ref);
+        inode
+    }
+
+    fn init_entries(&self, thread_ref: &ThreadRef) {
+        let mut file = self.0.write().unwrap();
+        // stat
+        let stat_inode = ProcStatINode::new_for_thread(thread_ref);
+        file.entries.insert(String::from("stat"), stat_inode);
+        // comm
+        let comm_inode = ProcCommINode::new_for_thread(thread_ref);
+        file.entries.insert(String::from("comm"), comm_inode);
+        // status
+        let status_inode = ProcStatusINode::new_for_thread(thread_ref);
+        file.entries.insert(String::from("status"), status_inode);
+        // cmdline, which is the same for all the threads
+        let cmdline_inode = ProcCmdlineINode::new(thread_ref.process());
+        file.entries.insert(String::from("cmdline"), cmdline_inode);
+    }
+}
+
+impl DirProcINode for LockedTidDirINode {
+    fn find(&self, name: &str) -> vfs::Result<Arc<dyn INode>> {
+        let file = self.0.read().unwrap();
+        if name == "." {
+            return Ok(file.this.upgrade().unwrap());
+        }
+        if name == ".." {
+            return Ok(Arc::clone(&file.parent));
+        }
+        if let Some(inode) = file.entries.get(name) {
+            Ok(Arc::clone(inode))
+        } else {
+            Err(FsError::EntryNotFound)
+        }
+    }
+
+    fn get_entry(&self, id: usize) -> vfs::Result<String> {
+        match id {
+            0 => Ok(String::from(".")),
+            1 => Ok(String::from("..")),
+            i => {
+                let file = self.0.read().unwrap();
+                if let Some(name) = file.entries.keys().nth(i - 2) {
+                    Ok(name.to_owned())
+                } else {
+                    Err(FsError::EntryNotFound)
+                }
+            }
+        }
+    }
+
+    fn iterate_entries(
+        &self,
+        offset: usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> vfs::Result<usize> {
+        let file = self.0.read().unwrap();
+
+        let try_iterate =
+            |mut offset: &mut usize, mut visitor: &mut dyn DirentVisitor| -> vfs::Result<()> {
+                // The two special entries
+                visit_first_two_entries!(&mut visitor, &file, &mut offset);
+
+                // The normal entries
+                let start_offset = *offset;
+                for (name, child) in file.entries.iter().skip(start_offset - 2) {
+                    rcore_fs::visit_inode_entry!(&mut visitor, name, child, &mut offset);
+                }
+
+                Ok(())
+            };
+
+        let mut iterate_offset = offset;
+        match try_iterate(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+}
@@ -2,11 +2,23 @@ use super::*;
 
 pub struct File<T: ProcINode> {
     inner: T,
+    writable: bool,
 }
 
 impl<T: ProcINode> File<T> {
     pub fn new(inner: T) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            writable: false,
+        }
+    }
+
+    /// New a file which can be written by the owner, e.g., /proc/[pid]/comm.
+    pub fn new_writable(inner: T) -> Self {
+        Self {
+            inner,
+            writable: true,
+        }
     }
 
     pub fn inner(&self) -> &T {
@@ -29,7 +41,7 @@ where
             mtime: Timespec { sec: 0, nsec: 0 },
             ctime: Timespec { sec: 0, nsec: 0 },
             type_: vfs::FileType::File,
-            mode: 0o444,
+            mode: if self.writable { 0o644 } else { 0o444 },
             nlinks: 1,
             uid: 0,
             gid: 0,
@@ -37,5 +49,14 @@ where
         })
     }
 
+    fn resize(&self, _len: usize) -> vfs::Result<()> {
+        // Truncating takes no effect, so that the file can be opened with O_TRUNC
+        if self.writable {
+            Ok(())
+        } else {
+            Err(vfs::FsError::PermError)
+        }
+    }
+
     impl_inode_for_file_or_symlink!();
 }
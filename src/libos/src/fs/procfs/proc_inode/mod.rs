@@ -10,6 +10,11 @@ mod symlink;
 
 pub trait ProcINode {
     fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>>;
+
+    /// Handle the data written to the inode, which is read-only by default.
+    fn write_data_in_bytes(&self, _data: &[u8]) -> vfs::Result<()> {
+        Err(vfs::FsError::PermError)
+    }
 }
 
 pub trait DirProcINode {
@@ -32,7 +37,9 @@ macro_rules! impl_inode_for_file_or_symlink {
         }
 
         fn write_at(&self, offset: usize, buf: &[u8]) -> vfs::Result<usize> {
-            Err(vfs::FsError::PermError)
+            // Like Linux, the offset is ignored and the whole data is written at once
+            self.inner().write_data_in_bytes(buf)?;
+            Ok(buf.len())
         }
 
         fn set_metadata(&self, metadata: &Metadata) -> vfs::Result<()> {
@@ -0,0 +1,154 @@
+use super::*;
+use crate::misc::sysctl::{Sysctl, SYSCTLS};
+
+/// A directory under /proc/sys, whose entries are the kernel parameters or the
+/// subdirectories. The directories are built from the paths of the parameters.
+pub struct LockedSysDirINode(RwLock<SysDirINode>);
+
+struct SysDirINode {
+    entries: HashMap<String, Arc<dyn INode>>,
+    this: Weak<Dir<LockedSysDirINode>>,
+    parent: Weak<dyn INode>,
+}
+
+impl LockedSysDirINode {
+    /// New the /proc/sys directory with all the kernel parameters.
+    pub fn new(parent: Weak<dyn INode>) -> Arc<dyn INode> {
+        let sysctls: Vec<(&str, &'static Sysctl)> =
+            SYSCTLS.iter().map(|sysctl| (sysctl.path, sysctl)).collect();
+        Self::new_with_sysctls(parent, &sysctls)
+    }
+
+    // The paths of the parameters are relative to this directory
+    fn new_with_sysctls(
+        parent: Weak<dyn INode>,
+        sysctls: &[(&str, &'static Sysctl)],
+    ) -> Arc<dyn INode> {
+        let inode = Arc::new(Dir::new(Self(RwLock::new(SysDirINode {
+            entries: HashMap::new(),
+            this: Weak::default(),
+            parent,
+        }))));
+        inode.inner().0.write().unwrap().this = Arc::downgrade(&inode);
+
+        // Group the parameters by the first components of the paths
+        let mut subdirs: HashMap<&str, Vec<(&str, &'static Sysctl)>> = HashMap::new();
+        let mut entries: HashMap<String, Arc<dyn INode>> = HashMap::new();
+        for &(path, sysctl) in sysctls {
+            match path.split_once('/') {
+                Some((dir_name, sub_path)) => {
+                    subdirs
+                        .entry(dir_name)
+                        .or_default()
+                        .push((sub_path, sysctl));
+                }
+                None => {
+                    entries.insert(path.to_owned(), SysctlINode::new(sysctl));
+                }
+            }
+        }
+        let this: Arc<dyn INode> = inode.clone();
+        for (dir_name, sub_sysctls) in subdirs {
+            let subdir = Self::new_with_sysctls(Arc::downgrade(&this), &sub_sysctls);
+            entries.insert(dir_name.to_owned(), subdir);
+        }
+        inode.inner().0.write().unwrap().entries = entries;
+        inode
+    }
+}
+
+impl DirProcINode for LockedSysDirINode {
+    fn find(&self, name: &str) -> vfs::Result<Arc<dyn INode>> {
+        let file = self.0.read().unwrap();
+        if name == "." {
+            return Ok(file.this.upgrade().unwrap());
+        }
+        if name == ".." {
+            return file.parent.upgrade().ok_or(FsError::EntryNotFound);
+        }
+        if let Some(inode) = file.entries.get(name) {
+            Ok(Arc::clone(inode))
+        } else {
+            Err(FsError::EntryNotFound)
+        }
+    }
+
+    fn get_entry(&self, id: usize) -> vfs::Result<String> {
+        match id {
+            0 => Ok(String::from(".")),
+            1 => Ok(String::from("..")),
+            i => {
+                let file = self.0.read().unwrap();
+                if let Some(name) = file.entries.keys().nth(i - 2) {
+                    Ok(name.to_owned())
+                } else {
+                    Err(FsError::EntryNotFound)
+                }
+            }
+        }
+    }
+
+    fn iterate_entries(
+        &self,
+        offset: usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> vfs::Result<usize> {
+        let file = self.0.read().unwrap();
+
+        let try_iterate =
+            |mut offset: &mut usize, mut visitor: &mut dyn DirentVisitor| -> vfs::Result<()> {
+                // The two special entries
+                if *offset == 0 {
+                    let this_inode = file.this.upgrade().unwrap();
+                    rcore_fs::visit_inode_entry!(&mut visitor, ".", &this_inode, &mut offset);
+                }
+                if *offset == 1 {
+                    let parent_inode = file.parent.upgrade().ok_or(FsError::EntryNotFound)?;
+                    rcore_fs::visit_inode_entry!(&mut visitor, "..", &parent_inode, &mut offset);
+                }
+
+                // The parameters and the subdirectories
+                let start_offset = *offset;
+                for (name, child) in file.entries.iter().skip(start_offset - 2) {
+                    rcore_fs::visit_inode_entry!(&mut visitor, name, child, &mut offset);
+                }
+
+                Ok(())
+            };
+
+        let mut iterate_offset = offset;
+        match try_iterate(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+}
+
+/// A kernel parameter under /proc/sys.
+struct SysctlINode(&'static Sysctl);
+
+impl SysctlINode {
+    fn new(sysctl: &'static Sysctl) -> Arc<dyn INode> {
+        if sysctl.is_writable() {
+            Arc::new(File::new_writable(Self(sysctl)))
+        } else {
+            Arc::new(File::new(Self(sysctl)))
+        }
+    }
+}
+
+impl ProcINode for SysctlINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let mut value = self.0.get();
+        value.push('\n');
+        Ok(value.into_bytes())
+    }
+
+    fn write_data_in_bytes(&self, data: &[u8]) -> vfs::Result<()> {
+        let value = std::str::from_utf8(data).map_err(|_| FsError::InvalidParam)?;
+        self.0.set(value).map_err(|e| {
+            warn!("failed to set {}: {}", self.0.path, e);
+            FsError::InvalidParam
+        })
+    }
+}
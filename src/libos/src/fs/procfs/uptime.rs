@@ -0,0 +1,17 @@
+use super::*;
+
+pub struct UptimeINode;
+
+impl UptimeINode {
+    pub fn new() -> Arc<dyn INode> {
+        Arc::new(File::new(Self))
+    }
+}
+
+impl ProcINode for UptimeINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let uptime = crate::time::up_time::get().unwrap().as_secs_f64();
+        // The idle time of the CPUs is unknown in the LibOS, so it is always 0
+        Ok(format!("{:.2} 0.00\n", uptime).into_bytes())
+    }
+}
@@ -0,0 +1,22 @@
+use super::*;
+use crate::misc::uname;
+
+pub struct VersionINode;
+
+impl VersionINode {
+    pub fn new() -> Arc<dyn INode> {
+        Arc::new(File::new(Self))
+    }
+}
+
+impl ProcINode for VersionINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        Ok(format!(
+            "{} version {} {}\n",
+            uname::sysname(),
+            uname::release(),
+            uname::version()
+        )
+        .into_bytes())
+    }
+}
@@ -1,15 +1,18 @@
+use super::bindfs::BindFS;
 use super::dev_fs;
+use super::ext2_mkfs;
+use super::file_ops::{keep_xattrs_in_upper_layer, persist_xattrs_in};
+use super::fs_ops::MountFlags;
 use super::hostfs::HostFS;
+use super::mount_table::{MountSource, MOUNT_TABLE};
 use super::procfs::ProcFS;
 use super::sefs::{SgxStorage, SgxUuidProvider};
 use super::*;
-use crate::blk::{DevDisk, SwornDiskMeta, DEV_SWORNDISK};
-use crate::config::{ConfigApp, ConfigMountFsType};
+use crate::blk::{DevDisk, SwornDiskMeta};
+use crate::config::{ConfigApp, ConfigMkfsOptions, ConfigMountFsType};
 use crate::ctor::dtor;
-use crate::fs::dev_fs::DevDiskOption;
 use crate::time::OcclumTimeProvider;
 
-use alloc::ffi::CString;
 use alloc::sync::Weak;
 use ext2_rs::{BlockDevice, Ext2};
 use rcore_fs_mountfs::{MNode, MountFS};
@@ -17,7 +20,7 @@ use rcore_fs_ramfs::RamFS;
 use rcore_fs_sefs::dev::*;
 use rcore_fs_sefs::SEFS;
 use rcore_fs_unionfs::UnionFS;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::untrusted::path::PathEx;
 
 lazy_static! {
@@ -38,6 +41,10 @@ lazy_static! {
     };
     /// All mounted SEFSes
     pub static ref SEFS_MANAGER: GlobalSefsManager = GlobalSefsManager::new();
+    /// The opened Ext2 instances, indexed by the names of their disks. Mounting a
+    /// disk again shares the same instance, which keeps the disk consistent.
+    static ref EXT2_INSTANCES: SgxMutex<HashMap<String, Weak<Ext2>>> =
+        SgxMutex::new(HashMap::new());
 }
 
 pub fn open_root_fs_according_to(
@@ -76,8 +83,21 @@ pub fn open_root_fs_according_to(
     let root_container_sefs =
         open_or_create_sefs_according_to(&root_container_sefs_mount_config, user_key)?;
     // create UnionFS
-    let root_unionfs = UnionFS::new(vec![root_container_sefs, root_image_sefs])?;
-    let root_mountable_unionfs = MountFS::new(root_unionfs);
+    let root_unionfs = UnionFS::new(vec![root_container_sefs.clone(), root_image_sefs])?;
+    persist_xattrs_of_unionfs(
+        &root_unionfs,
+        &root_container_sefs,
+        &root_container_sefs_mount_config,
+        user_key,
+    )?;
+    let root_mountable_unionfs = MountFS::new(root_unionfs.clone());
+    MOUNT_TABLE.write().unwrap().add(
+        &root_mountable_unionfs,
+        root_unionfs,
+        None,
+        Path::new("/"),
+        MountSource::new("unionfs", "unionfs"),
+    );
     Ok(root_mountable_unionfs)
 }
 
@@ -99,21 +119,202 @@ pub fn umount_nonroot_fs(
     };
 
     mount_dir.downcast_ref::<MNode>().unwrap().umount()?;
+    MOUNT_TABLE.write().unwrap().remove(mount_dir.as_ref());
     Ok(())
 }
 
+/// Bind the file at the source path to the target path. If it is recursive, the mounts
+/// under the source are also bound.
+pub fn bind_mount(
+    root: &Arc<dyn INode>,
+    source: &Path,
+    target: &Path,
+    recursive: bool,
+    follow_symlink: bool,
+) -> Result<()> {
+    let source_path = source
+        .to_str()
+        .ok_or_else(|| errno!(EINVAL, "invalid path"))?;
+    let source_inode = root.lookup_follow(source_path, MAX_SYMLINKS)?;
+    let (mount_source, flags) = {
+        let mount_table = MOUNT_TABLE.read().unwrap();
+        let source_mount = mount_table
+            .get(source_inode.as_ref())
+            .ok_or_else(|| errno!(EINVAL, "the source is not in the mount tree"))?;
+        // The same source with the source mount, but with the root at the source file
+        let mut mount_source = source_mount.source().clone();
+        if let Ok(path_in_mount) = source.strip_prefix(source_mount.target()) {
+            mount_source.root.push(path_in_mount);
+        }
+        (mount_source, source_mount.flags())
+    };
+    let bind_root = if recursive {
+        source_inode
+    } else {
+        // The inode beneath the `MountFS`, through which the mounts are not visible
+        source_inode.downcast_ref::<MNode>().unwrap().inode.clone()
+    };
+    mount_and_record_fs_at(
+        BindFS::new(bind_root),
+        root,
+        target,
+        follow_symlink,
+        mount_source,
+    )?;
+    // Like Linux, the new mount has the same flags with the source mount
+    set_mount_flags(root, target, follow_symlink, flags)
+}
+
+/// Move the mount at the source path to the target path.
+pub fn move_mount(
+    root: &Arc<dyn INode>,
+    source: &Path,
+    target: &Path,
+    follow_symlink: bool,
+) -> Result<()> {
+    let mount_root = lookup_mount_root(root, source, true)?;
+    let mount = {
+        let mount_table = MOUNT_TABLE.read().unwrap();
+        let mount = mount_table
+            .get(mount_root.as_ref())
+            .ok_or_else(|| errno!(EINVAL, "not a mount point"))?;
+        // The mounts on the MountFS cannot be carried to the new place
+        if mount_table.has_submounts(mount) {
+            return_errno!(EBUSY, "the mount to be moved has submounts");
+        }
+        mount.clone()
+    };
+
+    umount_nonroot_fs(root, source.to_str().unwrap(), true)?;
+    let mount_source = mount.source().clone();
+    if let Err(e) = mount_and_record_fs_at(
+        mount.fs().clone(),
+        root,
+        target,
+        follow_symlink,
+        mount_source.clone(),
+    ) {
+        // Put the mount back to where it was
+        mount_and_record_fs_at(mount.fs().clone(), root, mount.target(), true, mount_source)?;
+        set_mount_flags(root, mount.target(), true, mount.flags())?;
+        return Err(e);
+    }
+    set_mount_flags(root, target, follow_symlink, mount.flags())
+}
+
+/// Make the mount at `new_root` the root mount, and mount the old root at `put_old`,
+/// which must be under `new_root`. The paths are normalized absolute paths.
+pub fn pivot_root(new_root: &Path, put_old: &Path) -> Result<()> {
+    let put_old_in_new_root = put_old
+        .strip_prefix(new_root)
+        .ok()
+        .filter(|path| !path.as_os_str().is_empty())
+        .ok_or_else(|| errno!(EINVAL, "put_old is not under new_root"))?;
+
+    let mut rootfs = ROOT_FS.write().unwrap();
+    SEFS_MANAGER.sync_all()?;
+    let old_root = rootfs.root_inode();
+    let new_root_inode = lookup_mount_root(&old_root, new_root, true)?;
+    let new_root_mount = {
+        let mount_table = MOUNT_TABLE.read().unwrap();
+        let mount = mount_table
+            .get(new_root_inode.as_ref())
+            .ok_or_else(|| errno!(EINVAL, "new_root is not a mount point"))?;
+        // The mounts on the MountFS cannot be carried to the new root
+        if mount_table.has_submounts(mount) {
+            return_errno!(EBUSY, "new_root has submounts");
+        }
+        mount.clone()
+    };
+    let put_old_path = put_old_in_new_root
+        .to_str()
+        .ok_or_else(|| errno!(EINVAL, "invalid path"))?;
+    if new_root_inode
+        .lookup_follow(put_old_path, MAX_SYMLINKS)?
+        .metadata()?
+        .type_
+        != FileType::Dir
+    {
+        return_errno!(ENOTDIR, "put_old is not a directory");
+    }
+
+    umount_nonroot_fs(&old_root, new_root.to_str().unwrap(), true)?;
+    let new_rootfs = MountFS::new(new_root_mount.fs().clone());
+    let (_, old_root_fs) = do_mount_fs_at(
+        rootfs.clone(),
+        &new_rootfs.root_inode(),
+        put_old_in_new_root,
+        true,
+    )?;
+    MOUNT_TABLE.write().unwrap().pivot_root(
+        new_root_mount,
+        &new_rootfs,
+        &old_root_fs,
+        &Path::new("/").join(put_old_in_new_root),
+    );
+    *rootfs = new_rootfs;
+    Ok(())
+}
+
+/// Set the per-mount flags of the mount at the path.
+pub fn set_mount_flags(
+    root: &Arc<dyn INode>,
+    path: &Path,
+    follow_symlink: bool,
+    flags: MountFlags,
+) -> Result<()> {
+    let mount_root = lookup_mount_root(root, path, follow_symlink)?;
+    let mut mount_table = MOUNT_TABLE.write().unwrap();
+    let mount = mount_table
+        .get_mut(mount_root.as_ref())
+        .ok_or_else(|| errno!(EINVAL, "not a mount point"))?;
+    // As Linux, a mount with files opened for writing cannot become read-only
+    if flags.contains(MountFlags::MS_RDONLY)
+        && !mount.flags().contains(MountFlags::MS_RDONLY)
+        && mount.has_writers()
+    {
+        return_errno!(EBUSY, "the mount has files opened for writing");
+    }
+    mount.set_flags(flags);
+    Ok(())
+}
+
+/// Lookup the root inode of the mount at the path.
+pub fn lookup_mount_root(
+    root: &Arc<dyn INode>,
+    path: &Path,
+    follow_symlink: bool,
+) -> Result<Arc<dyn INode>> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| errno!(EINVAL, "invalid path"))?;
+    let (dir_path, file_name) = split_path(path);
+    let inode = if follow_symlink || file_name.ends_with("/") {
+        root.lookup_follow(path, MAX_SYMLINKS)?
+    } else {
+        root.lookup_follow(dir_path, MAX_SYMLINKS)?
+            .lookup(file_name)?
+    };
+    if inode.fs().root_inode().metadata()?.inode != inode.metadata()?.inode {
+        return_errno!(EINVAL, "not a mount point");
+    }
+    Ok(inode)
+}
+
 pub fn mount_nonroot_fs_according_to(
     root: &Arc<dyn INode>,
     mount_configs: &Vec<ConfigMount>,
     user_key: &Option<sgx_key_128bit_t>,
     follow_symlink: bool,
 ) -> Result<()> {
-    let mc_ext2_opt = mount_configs
+    // Setup disk metadata first for all the Ext2 mounts
+    let mut ext2_mounts = Vec::new();
+    for mc in mount_configs
         .iter()
-        .find(|mc| mc.type_ == ConfigMountFsType::TYPE_EXT2);
-    if let Some(mc) = mc_ext2_opt {
-        // Setup disk metadata first if enabled Ext2
-        setup_disk_meta_for_ext2(mc, user_key)?;
+        .filter(|mc| mc.type_ == ConfigMountFsType::TYPE_EXT2)
+    {
+        let disk_name = setup_disk_meta_for_ext2(mc, user_key)?;
+        ext2_mounts.push((mc, disk_name));
     }
 
     for mc in mount_configs {
@@ -129,7 +330,14 @@ pub fn mount_nonroot_fs_according_to(
         match mc.type_ {
             TYPE_SEFS => {
                 let sefs = open_or_create_sefs_according_to(&mc, user_key)?;
-                mount_fs_at(sefs, root, &mc.target, follow_symlink)?;
+                persist_xattrs_of_sefs(&sefs, &mc, user_key)?;
+                mount_and_record_fs_at(
+                    sefs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_HOSTFS => {
                 let source_path =
@@ -147,24 +355,43 @@ pub fn mount_nonroot_fs_according_to(
                 }
 
                 let hostfs = HostFS::new(source_path.unwrap());
-                mount_fs_at(hostfs, root, &mc.target, follow_symlink)?;
+                mount_and_record_fs_at(
+                    hostfs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_RAMFS => {
                 let ramfs = RamFS::new();
-                mount_fs_at(ramfs, root, &mc.target, follow_symlink)?;
+                mount_and_record_fs_at(
+                    ramfs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_DEVFS => {
-                let disk_options = if mc_ext2_opt.is_some() {
-                    vec![DevDiskOption::new(DEV_SWORNDISK)]
-                } else {
-                    vec![]
-                };
-                let devfs = dev_fs::init_devfs(&disk_options)?;
-                mount_fs_at(devfs, root, &mc.target, follow_symlink)?;
+                let devfs = dev_fs::init_devfs()?;
+                mount_and_record_fs_at(
+                    devfs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_PROCFS => {
                 let procfs = ProcFS::new();
-                mount_fs_at(procfs, root, &mc.target, follow_symlink)?;
+                mount_and_record_fs_at(
+                    procfs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_UNIONFS => {
                 let layer_mcs = mc
@@ -183,13 +410,26 @@ pub fn mount_nonroot_fs_according_to(
                         let image_sefs = open_or_create_sefs_according_to(image_fs_mc, user_key)?;
                         let container_sefs =
                             open_or_create_sefs_according_to(container_fs_mc, user_key)?;
-                        UnionFS::new(vec![container_sefs, image_sefs])?
+                        let unionfs = UnionFS::new(vec![container_sefs.clone(), image_sefs])?;
+                        persist_xattrs_of_unionfs(
+                            &unionfs,
+                            &container_sefs,
+                            container_fs_mc,
+                            user_key,
+                        )?;
+                        unionfs
                     }
                     (_, _) => {
                         return_errno!(EINVAL, "Unsupported fs type inside unionfs");
                     }
                 };
-                mount_fs_at(unionfs, root, &mc.target, follow_symlink)?;
+                mount_and_record_fs_at(
+                    unionfs,
+                    root,
+                    &mc.target,
+                    follow_symlink,
+                    mount_source_of(mc),
+                )?;
             }
             TYPE_EXT2 => {
                 // Leave mounting the Ext2 to the final step
@@ -198,9 +438,12 @@ pub fn mount_nonroot_fs_according_to(
         }
     }
 
-    if let Some(mc) = mc_ext2_opt {
-        let ext2 = open_ext2()?;
-        mount_fs_at(ext2, root, &mc.target, follow_symlink)?;
+    for (mc, disk_name) in ext2_mounts {
+        // The disk may be set up after the DevFS is mounted
+        dev_fs::add_disk(&disk_name)?;
+        let ext2 = open_ext2(&disk_name, &mc.options.mkfs)?;
+        let mount_source = MountSource::new(&format!("/dev/{}", disk_name), "ext2");
+        mount_and_record_fs_at(ext2, root, &mc.target, follow_symlink, mount_source)?;
     }
     Ok(())
 }
@@ -211,6 +454,16 @@ pub fn mount_fs_at(
     path: &Path,
     follow_symlink: bool,
 ) -> Result<()> {
+    do_mount_fs_at(fs, parent_inode, path, follow_symlink)?;
+    Ok(())
+}
+
+fn do_mount_fs_at(
+    fs: Arc<dyn FileSystem>,
+    parent_inode: &Arc<dyn INode>,
+    path: &Path,
+    follow_symlink: bool,
+) -> Result<(Arc<dyn INode>, Arc<MountFS>)> {
     let path = path
         .to_str()
         .ok_or_else(|| errno!(EINVAL, "invalid path"))?;
@@ -226,10 +479,40 @@ pub fn mount_fs_at(
                 .lookup(file_name)?
         }
     };
-    mount_dir.downcast_ref::<MNode>().unwrap().mount(fs)?;
+    let mount_fs = mount_dir.downcast_ref::<MNode>().unwrap().mount(fs)?;
+    Ok((mount_dir, mount_fs))
+}
+
+/// Mount the file system at the path in the mount tree, and record it in the mount table.
+fn mount_and_record_fs_at(
+    fs: Arc<dyn FileSystem>,
+    root: &Arc<dyn INode>,
+    path: &Path,
+    follow_symlink: bool,
+    source: MountSource,
+) -> Result<()> {
+    let (mount_point, mount_fs) = do_mount_fs_at(fs.clone(), root, path, follow_symlink)?;
+    MOUNT_TABLE.write().unwrap().add(
+        &mount_fs,
+        fs,
+        Some(mount_point.as_ref()),
+        &Path::new("/").join(path),
+        source,
+    );
     Ok(())
 }
 
+fn mount_source_of(mc: &ConfigMount) -> MountSource {
+    use self::ConfigMountFsType::*;
+    let fs_type = mc.type_.as_str();
+    match (&mc.type_, &mc.source) {
+        (TYPE_SEFS | TYPE_HOSTFS, Some(source)) => {
+            MountSource::new(&source.to_string_lossy(), fs_type)
+        }
+        _ => MountSource::new(fs_type, fs_type),
+    }
+}
+
 fn open_or_create_sefs_according_to(
     mc: &ConfigMount,
     user_key: &Option<sgx_key_128bit_t>,
@@ -297,45 +580,87 @@ fn open_or_create_sefs_according_to(
     Ok(sefs)
 }
 
-fn open_ext2() -> Result<Arc<Ext2>> {
-    debug_assert!(SwornDiskMeta::is_setup());
+/// Keep the extended attributes of the SEFS in a file beside the files of the SEFS.
+fn persist_xattrs_of_sefs(
+    sefs: &Arc<SEFS>,
+    mc: &ConfigMount,
+    user_key: &Option<sgx_key_128bit_t>,
+) -> Result<()> {
+    // The attributes of a temporary SEFS are gone with it, and an integrity protected
+    // SEFS is an image, which is not modified
+    if mc.options.temporary || mc.options.mac.is_some() {
+        return Ok(());
+    }
+
+    const XATTRS_FILE_ID: &str = "xattrs";
+    let source_path = mc.source.as_ref().unwrap();
+    let storage = SgxStorage::new(source_path, user_key, &None, mc.options.cache_size)?;
+    let file = if source_path.join(XATTRS_FILE_ID).exists() {
+        storage.open(XATTRS_FILE_ID)
+    } else {
+        storage.create(XATTRS_FILE_ID)
+    }
+    .map_err(|_| errno!(EIO, "failed to open the xattrs file of SEFS"))?;
+    let fs: Arc<dyn FileSystem> = sefs.clone();
+    persist_xattrs_in(&fs, file)
+}
 
-    let sworndisk = DevDisk::open_or_create(DEV_SWORNDISK)?.disk();
+/// Keep the extended attributes of the unionfs in its container SEFS, where they are
+/// persisted.
+fn persist_xattrs_of_unionfs(
+    unionfs: &Arc<UnionFS>,
+    container_sefs: &Arc<SEFS>,
+    container_mc: &ConfigMount,
+    user_key: &Option<sgx_key_128bit_t>,
+) -> Result<()> {
+    persist_xattrs_of_sefs(container_sefs, container_mc, user_key)?;
+    let unionfs: Arc<dyn FileSystem> = unionfs.clone();
+    let container_fs: Arc<dyn FileSystem> = container_sefs.clone();
+    keep_xattrs_in_upper_layer(&unionfs, &container_fs);
+    Ok(())
+}
+
+fn open_ext2(disk_name: &str, mkfs_options: &ConfigMkfsOptions) -> Result<Arc<Ext2>> {
+    let mut ext2_instances = EXT2_INSTANCES.lock().unwrap();
+    ext2_instances.retain(|_, ext2| ext2.upgrade().is_some());
+    if let Some(ext2) = ext2_instances
+        .get(disk_name)
+        .and_then(|ext2| ext2.upgrade())
+    {
+        return Ok(ext2);
+    }
+
+    let sworndisk = DevDisk::open_or_create(disk_name)?.disk();
     let ext2 = match Ext2::open(sworndisk, Arc::new(OcclumTimeProvider)) {
         Err(e) if e == ext2_rs::FsError::WrongFs => {
-            let sworndisk = format_disk_for_ext2()?;
+            let sworndisk = format_disk_for_ext2(disk_name, mkfs_options)?;
             Ext2::open(sworndisk, Arc::new(OcclumTimeProvider))?
         }
         res => res?,
     };
+    ext2_instances.insert(disk_name.to_owned(), Arc::downgrade(&ext2));
     Ok(ext2)
 }
 
-fn format_disk_for_ext2() -> Result<Arc<dyn BlockDevice>> {
-    // Format the SwornDisk using 'mke2fs' tool for Ext2
-    let path = PathBuf::from("/sbin/mke2fs");
-    let argv = vec![
-        CString::new("mke2fs").unwrap(),
-        CString::new("-q").unwrap(),
-        CString::new("-t").unwrap(),
-        CString::new("ext2").unwrap(),
-        CString::new("/dev/".to_owned() + DEV_SWORNDISK).unwrap(),
-    ];
-    let pid = process::do_spawn(&path.to_str().unwrap(), &argv, &[], &[], None, &current!())?;
-    let _ = process::do_wait4(pid as _, core::ptr::null_mut(), 0)?;
-
-    let sworndisk = DevDisk::open_or_create(DEV_SWORNDISK)?.disk();
+fn format_disk_for_ext2(
+    disk_name: &str,
+    mkfs_options: &ConfigMkfsOptions,
+) -> Result<Arc<dyn BlockDevice>> {
+    let sworndisk = DevDisk::open_or_create(disk_name)?.disk();
+    ext2_mkfs::format_ext2(&sworndisk, mkfs_options)?;
     Ok(sworndisk)
 }
 
-fn setup_disk_meta_for_ext2(mc: &ConfigMount, user_key: &Option<sgx_key_128bit_t>) -> Result<()> {
+/// Set up the SwornDisk of an Ext2 mount, and return the device name of the disk.
+fn setup_disk_meta_for_ext2(
+    mc: &ConfigMount,
+    user_key: &Option<sgx_key_128bit_t>,
+) -> Result<String> {
     debug_assert_eq!(mc.type_, ConfigMountFsType::TYPE_EXT2);
-    let disk_size = mc.options.disk_size;
-    if disk_size.is_none() {
-        return_errno!(EINVAL, "Disk size is expected for Ext2");
-    }
     let source_path = mc.source.as_ref();
-    SwornDiskMeta::setup(disk_size.unwrap(), user_key, source_path)
+    // The key given to the mount takes precedence
+    let key = mc.options.key.or(*user_key);
+    SwornDiskMeta::setup(mc.options.disk_size, &key, source_path)
 }
 
 /// Manage all mounted SEFSes globally.
@@ -2,7 +2,8 @@ use super::event_file::EventCreationFlags;
 use super::file_ops;
 use super::file_ops::{
     get_abs_path_by_fd, get_utimes, AccessibilityCheckMode, ChownFlags, FcntlCmd, FsPath,
-    LinkFlags, StatFlags, UnlinkFlags, Utime, UtimeFlags, AT_FDCWD, UTIME_OMIT,
+    LinkFlags, StatFlags, UnlinkFlags, Utime, UtimeFlags, XattrFlags, XattrTarget, AT_FDCWD,
+    UTIME_OMIT,
 };
 use super::fs_ops;
 use super::fs_ops::{MountFlags, MountOptions, UmountFlags};
@@ -39,6 +40,55 @@ pub fn do_eventfd2(init_val: u32, flags: i32) -> Result<isize> {
     Ok(fd as isize)
 }
 
+pub fn do_inotify_init() -> Result<isize> {
+    do_inotify_init1(0)
+}
+
+pub fn do_inotify_init1(flags: i32) -> Result<isize> {
+    debug!("inotify_init1: flags: {}", flags);
+
+    let flags = InotifyFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    let file_ref: Arc<dyn File> = Arc::new(InotifyFile::new(flags));
+    let fd = current!().add_file(file_ref, flags.contains(InotifyFlags::IN_CLOEXEC));
+    Ok(fd as isize)
+}
+
+pub fn do_inotify_add_watch(fd: FileDesc, path: *const i8, mask: u32) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let mask = InotifyMask::from_bits(mask).ok_or_else(|| errno!(EINVAL, "invalid mask"))?;
+    debug!(
+        "inotify_add_watch: fd: {}, path: {:?}, mask: {:?}",
+        fd, path, mask
+    );
+
+    let file_ref = current!().file(fd)?;
+    let inotify_file = file_ref.as_inotify()?;
+    let inode = {
+        let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+        let abs_path = fs_path.to_abs_path()?;
+        let current = current!();
+        let fs = current.fs().read().unwrap();
+        if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+            fs.lookup_inode_no_follow(&abs_path)?
+        } else {
+            fs.lookup_inode(&abs_path)?
+        }
+    };
+    if mask.contains(InotifyMask::IN_ONLYDIR) && inode.metadata()?.type_ != FileType::Dir {
+        return_errno!(ENOTDIR, "the path is not a directory");
+    }
+    let wd = inotify_file.add_watch(&inode, mask)?;
+    Ok(wd as isize)
+}
+
+pub fn do_inotify_rm_watch(fd: FileDesc, wd: i32) -> Result<isize> {
+    debug!("inotify_rm_watch: fd: {}, wd: {}", fd, wd);
+
+    let file_ref = current!().file(fd)?;
+    file_ref.as_inotify()?.remove_watch(wd)?;
+    Ok(0)
+}
+
 pub fn do_timerfd_create(clockid: clockid_t, flags: i32) -> Result<isize> {
     debug!("timerfd: clockid {}, flags {} ", clockid, flags);
 
@@ -443,6 +493,19 @@ pub fn do_fchdir(fd: FileDesc) -> Result<isize> {
     Ok(0)
 }
 
+pub fn do_chroot(path: *const i8) -> Result<isize> {
+    let path = from_user::clone_cstring_safely(path)?
+        .to_string_lossy()
+        .into_owned();
+    if path.is_empty() {
+        return_errno!(ENOENT, "path is an empty string");
+    } else if path.len() > PATH_MAX {
+        return_errno!(ENAMETOOLONG, "path name too long");
+    }
+    fs_ops::do_chroot(&path)?;
+    Ok(0)
+}
+
 pub fn do_getcwd(buf_ptr: *mut u8, size: usize) -> Result<isize> {
     let buf = {
         from_user::check_mut_array(buf_ptr, size)?;
@@ -641,6 +704,153 @@ pub fn do_lchown(path: *const i8, uid: i32, gid: i32) -> Result<isize> {
     )
 }
 
+pub fn do_setxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_setxattr_wrapper(&XattrTarget::Path(&fs_path), name, value, size, flags)
+}
+
+pub fn do_lsetxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_setxattr_wrapper(&XattrTarget::LinkPath(&fs_path), name, value, size, flags)
+}
+
+pub fn do_fsetxattr(
+    fd: FileDesc,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    self::do_setxattr_wrapper(&XattrTarget::Fd(fd), name, value, size, flags)
+}
+
+fn do_setxattr_wrapper(
+    target: &XattrTarget,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let name = clone_string_safely(name)?;
+    let value = if size == 0 {
+        &[]
+    } else {
+        from_user::check_array(value, size)?;
+        unsafe { std::slice::from_raw_parts(value, size) }
+    };
+    let flags = XattrFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    file_ops::do_setxattr(target, &name, value, flags)?;
+    Ok(0)
+}
+
+pub fn do_getxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_getxattr_wrapper(&XattrTarget::Path(&fs_path), name, value, size)
+}
+
+pub fn do_lgetxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *mut u8,
+    size: usize,
+) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_getxattr_wrapper(&XattrTarget::LinkPath(&fs_path), name, value, size)
+}
+
+pub fn do_fgetxattr(fd: FileDesc, name: *const i8, value: *mut u8, size: usize) -> Result<isize> {
+    self::do_getxattr_wrapper(&XattrTarget::Fd(fd), name, value, size)
+}
+
+fn do_getxattr_wrapper(
+    target: &XattrTarget,
+    name: *const i8,
+    value: *mut u8,
+    size: usize,
+) -> Result<isize> {
+    let name = clone_string_safely(name)?;
+    let value = if size == 0 {
+        &mut []
+    } else {
+        from_user::check_mut_array(value, size)?;
+        unsafe { std::slice::from_raw_parts_mut(value, size) }
+    };
+    let len = file_ops::do_getxattr(target, &name, value)?;
+    Ok(len as isize)
+}
+
+pub fn do_listxattr(path: *const i8, list: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_listxattr_wrapper(&XattrTarget::Path(&fs_path), list, size)
+}
+
+pub fn do_llistxattr(path: *const i8, list: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    self::do_listxattr_wrapper(&XattrTarget::LinkPath(&fs_path), list, size)
+}
+
+pub fn do_flistxattr(fd: FileDesc, list: *mut u8, size: usize) -> Result<isize> {
+    self::do_listxattr_wrapper(&XattrTarget::Fd(fd), list, size)
+}
+
+fn do_listxattr_wrapper(target: &XattrTarget, list: *mut u8, size: usize) -> Result<isize> {
+    let list = if size == 0 {
+        &mut []
+    } else {
+        from_user::check_mut_array(list, size)?;
+        unsafe { std::slice::from_raw_parts_mut(list, size) }
+    };
+    let len = file_ops::do_listxattr(target, list)?;
+    Ok(len as isize)
+}
+
+pub fn do_removexattr(path: *const i8, name: *const i8) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    let name = clone_string_safely(name)?;
+    file_ops::do_removexattr(&XattrTarget::Path(&fs_path), &name)?;
+    Ok(0)
+}
+
+pub fn do_lremovexattr(path: *const i8, name: *const i8) -> Result<isize> {
+    let path = clone_string_safely(path)?;
+    let fs_path = FsPath::new(&path, AT_FDCWD, false)?;
+    let name = clone_string_safely(name)?;
+    file_ops::do_removexattr(&XattrTarget::LinkPath(&fs_path), &name)?;
+    Ok(0)
+}
+
+pub fn do_fremovexattr(fd: FileDesc, name: *const i8) -> Result<isize> {
+    let name = clone_string_safely(name)?;
+    file_ops::do_removexattr(&XattrTarget::Fd(fd), &name)?;
+    Ok(0)
+}
+
+fn clone_string_safely(ptr: *const i8) -> Result<String> {
+    let string = from_user::clone_cstring_safely(ptr)?
+        .to_string_lossy()
+        .into_owned();
+    Ok(string)
+}
+
 pub fn do_sendfile(
     out_fd: FileDesc,
     in_fd: FileDesc,
@@ -749,9 +959,6 @@ pub fn do_mount(
     flags: u32,
     options: *const i8,
 ) -> Result<isize> {
-    let source = from_user::clone_cstring_safely(source)?
-        .to_string_lossy()
-        .into_owned();
     let target = from_user::clone_cstring_safely(target)?
         .to_string_lossy()
         .into_owned();
@@ -761,6 +968,28 @@ pub fn do_mount(
         return_errno!(ENAMETOOLONG, "target name too long");
     }
     let flags = MountFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+
+    // Like Linux, the source, fs type and options are ignored if not needed
+    if flags.contains(MountFlags::MS_REMOUNT) {
+        fs_ops::do_remount(&target, flags)?;
+        return Ok(0);
+    }
+    if flags.intersects(MountFlags::propagation_flags()) {
+        fs_ops::do_change_propagation(&target, flags)?;
+        return Ok(0);
+    }
+    let source = from_user::clone_cstring_safely(source)?
+        .to_string_lossy()
+        .into_owned();
+    if flags.contains(MountFlags::MS_BIND) {
+        fs_ops::do_bind_mount(&source, &target, flags)?;
+        return Ok(0);
+    }
+    if flags.contains(MountFlags::MS_MOVE) {
+        fs_ops::do_move_mount(&source, &target, flags)?;
+        return Ok(0);
+    }
+
     let mount_options = {
         let fs_type = {
             let fs_type = from_user::clone_cstring_safely(fs_type)?
@@ -785,6 +1014,23 @@ pub fn do_umount(target: *const i8, flags: u32) -> Result<isize> {
     Ok(0)
 }
 
+pub fn do_pivot_root(new_root: *const i8, put_old: *const i8) -> Result<isize> {
+    let new_root = from_user::clone_cstring_safely(new_root)?
+        .to_string_lossy()
+        .into_owned();
+    let put_old = from_user::clone_cstring_safely(put_old)?
+        .to_string_lossy()
+        .into_owned();
+    if new_root.is_empty() || put_old.is_empty() {
+        return_errno!(ENOENT, "path is an empty string");
+    } else if new_root.len() > PATH_MAX || put_old.len() > PATH_MAX {
+        return_errno!(ENAMETOOLONG, "path name too long");
+    }
+
+    fs_ops::do_pivot_root(&new_root, &put_old)?;
+    Ok(0)
+}
+
 pub fn do_flock(fd: FileDesc, operation: i32) -> Result<isize> {
     let flock_ops = FlockOps::from_i32(operation)?;
 
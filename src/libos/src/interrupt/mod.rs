@@ -37,10 +37,19 @@ pub fn do_handle_interrupt(
 
 /// Broadcast interrupts to threads by sending POSIX signals.
 pub fn broadcast_interrupts() -> Result<usize> {
+    // Fire the in-enclave timers that have expired, so that the threads that
+    // are interested in the resulting signals can be interrupted right away.
+    crate::time::timer::handle_expired_timers();
+
     let should_interrupt_thread = |thread: &&ThreadRef| -> bool {
         if thread.process().is_forced_to_exit() || thread.is_forced_to_stop() {
             return true;
         }
+        // The threads report their CPU time upon the interrupts, so that the
+        // CPU-time timers can expire even if the threads make no system calls
+        if thread.process().cpu_clock().has_armed_timers() {
+            return true;
+        }
 
         let interested = !*thread.sig_mask().read().unwrap();
         // In the nightly-2022-10-22 Rust compiler, this expression holds two nested read locks.
@@ -1,7 +1,17 @@
 use super::*;
 
+mod mqueue;
+mod msg;
+mod sem;
 mod shm;
 mod syscalls;
 
+pub use self::mqueue::{mq_attr_t, MQUEUE_FS};
+pub use self::msg::{msqid_ds_t, SYSTEM_V_MSG_MANAGER};
+pub use self::sem::{sembuf_t, semid_ds_t, SYSTEM_V_SEM_MANAGER};
 pub use self::shm::{key_t, shmids_t, SYSTEM_V_SHM_MANAGER};
-pub use self::syscalls::{do_shmat, do_shmctl, do_shmdt, do_shmget};
+pub use self::syscalls::{
+    do_mq_getsetattr, do_mq_notify, do_mq_open, do_mq_timedreceive, do_mq_timedsend, do_mq_unlink,
+    do_msgctl, do_msgget, do_msgrcv, do_msgsnd, do_semctl, do_semget, do_semop, do_semtimedop,
+    do_shmat, do_shmctl, do_shmdt, do_shmget,
+};
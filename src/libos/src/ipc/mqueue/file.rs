@@ -0,0 +1,77 @@
+use super::*;
+
+use super::mq::PosixMq;
+use crate::fs::{IoEvents, IoNotifier, StatusFlags};
+
+/// The file of a POSIX message queue opened by mq_open.
+#[derive(Debug)]
+pub struct MqueueFile {
+    mq: Arc<PosixMq>,
+    access_mode: AccessMode,
+    status_flags: RwLock<StatusFlags>,
+}
+
+impl MqueueFile {
+    pub fn new(mq: Arc<PosixMq>, access_mode: AccessMode, status_flags: StatusFlags) -> Self {
+        Self {
+            mq,
+            access_mode,
+            status_flags: RwLock::new(status_flags),
+        }
+    }
+
+    pub fn mq(&self) -> &Arc<PosixMq> {
+        &self.mq
+    }
+
+    pub fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+}
+
+impl File for MqueueFile {
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(self.access_mode)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(*self.status_flags.read().unwrap())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        // Only O_NONBLOCK is meaningful for a message queue
+        status_flags.set(
+            StatusFlags::O_NONBLOCK,
+            new_status_flags.contains(StatusFlags::O_NONBLOCK),
+        );
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.mq.pollee().poll(IoEvents::all(), None)
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.mq.pollee().notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub trait AsMqueue {
+    fn as_mqueue(&self) -> Result<&MqueueFile>;
+}
+
+impl AsMqueue for FileRef {
+    fn as_mqueue(&self) -> Result<&MqueueFile> {
+        self.as_any()
+            .downcast_ref::<MqueueFile>()
+            .ok_or_else(|| errno!(EBADF, "not a message queue"))
+    }
+}
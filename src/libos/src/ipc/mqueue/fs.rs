@@ -0,0 +1,259 @@
+use super::*;
+
+use std::collections::BTreeMap;
+use std::sync::Weak;
+
+use super::mq::{mq_attr_t, PosixMq};
+
+// Same with the mqueue file system on Linux
+const MQUEUE_MAGIC: usize = 0x19800202;
+// The max length of a queue name
+const NAME_MAX: usize = 255;
+
+lazy_static! {
+    /// The global namespace of POSIX message queues.
+    pub static ref MQUEUE_FS: Arc<MqueueFS> = MqueueFS::new();
+}
+
+/// The mqueue file system, which has a flat root directory containing all
+/// the message queues.
+pub struct MqueueFS {
+    root: Arc<MqueueRootINode>,
+}
+
+impl MqueueFS {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            root: MqueueRootINode::new(),
+        })
+    }
+
+    /// Open the queue of `name`, or create it if `flags` contains O_CREAT.
+    ///
+    /// Like Linux, the permissions are only checked against `access_mode` when
+    /// an existing queue is opened.
+    pub fn open(
+        &self,
+        name: &str,
+        access_mode: AccessMode,
+        flags: CreationFlags,
+        mode: FileMode,
+        attr: Option<&mq_attr_t>,
+    ) -> Result<Arc<PosixMq>> {
+        check_name(name)?;
+        let mut queues = self.root.queues.write().unwrap();
+        if let Some(mq) = queues.get(name) {
+            if flags.can_create() && flags.is_exclusive() {
+                return_errno!(EEXIST, "the message queue already exists");
+            }
+            mq.check_perm(access_mode)?;
+            return Ok(mq.clone());
+        }
+        if !flags.can_create() {
+            return_errno!(ENOENT, "the message queue does not exist");
+        }
+
+        let mq = Arc::new(PosixMq::new(mode.bits(), attr)?);
+        queues.insert(name.to_string(), mq.clone());
+        Ok(mq)
+    }
+
+    /// Remove the queue of `name`. The queue is destroyed once all the
+    /// descriptors referring to it are closed.
+    pub fn unlink(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        self.root
+            .queues
+            .write()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| errno!(ENOENT, "the message queue does not exist"))?;
+        Ok(())
+    }
+}
+
+// The name passed to the syscalls has no leading slash, which is stripped by libc
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return_errno!(ENOENT, "empty name");
+    }
+    if name.len() > NAME_MAX {
+        return_errno!(ENAMETOOLONG, "name too long");
+    }
+    if name.contains('/') || name == "." || name == ".." {
+        return_errno!(EACCES, "invalid name");
+    }
+    Ok(())
+}
+
+impl FileSystem for MqueueFS {
+    fn sync(&self) -> vfs::Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn INode> {
+        self.root.clone()
+    }
+
+    fn info(&self) -> vfs::FsInfo {
+        vfs::FsInfo {
+            magic: MQUEUE_MAGIC,
+            bsize: 4096,
+            frsize: 4096,
+            blocks: 0,
+            bfree: 0,
+            bavail: 0,
+            files: 0,
+            ffree: 0,
+            namemax: NAME_MAX,
+        }
+    }
+}
+
+struct MqueueRootINode {
+    queues: RwLock<BTreeMap<String, Arc<PosixMq>>>,
+    this: Weak<MqueueRootINode>,
+}
+
+impl MqueueRootINode {
+    fn new() -> Arc<Self> {
+        let root = Arc::new(Self {
+            queues: RwLock::new(BTreeMap::new()),
+            this: Weak::default(),
+        });
+        let weak = Arc::downgrade(&root);
+        let ptr = Arc::into_raw(root) as *mut Self;
+        unsafe {
+            (*ptr).this = weak;
+        }
+        unsafe { Arc::from_raw(ptr) }
+    }
+}
+
+impl INode for MqueueRootINode {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> vfs::Result<usize> {
+        Err(FsError::NotFile)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> vfs::Result<usize> {
+        Err(FsError::NotFile)
+    }
+
+    fn metadata(&self) -> vfs::Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 1,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Dir,
+            mode: 0o1777,
+            nlinks: 2,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+
+    fn set_metadata(&self, metadata: &Metadata) -> vfs::Result<()> {
+        Err(FsError::PermError)
+    }
+
+    fn sync_all(&self) -> vfs::Result<()> {
+        Ok(())
+    }
+
+    fn sync_data(&self) -> vfs::Result<()> {
+        Ok(())
+    }
+
+    // Support creating a queue with the default attributes by open(2)
+    fn create(&self, name: &str, type_: FileType, mode: u16) -> vfs::Result<Arc<dyn INode>> {
+        if type_ != FileType::File {
+            return Err(FsError::PermError);
+        }
+        let mut queues = self.queues.write().unwrap();
+        if queues.contains_key(name) {
+            return Err(FsError::EntryExist);
+        }
+        let mq = Arc::new(PosixMq::new(mode, None).map_err(|_| FsError::InvalidParam)?);
+        queues.insert(name.to_string(), mq.clone());
+        Ok(mq)
+    }
+
+    fn unlink(&self, name: &str) -> vfs::Result<()> {
+        self.queues
+            .write()
+            .unwrap()
+            .remove(name)
+            .ok_or(FsError::EntryNotFound)?;
+        Ok(())
+    }
+
+    fn find(&self, name: &str) -> vfs::Result<Arc<dyn INode>> {
+        if name == "." || name == ".." {
+            return Ok(self.this.upgrade().unwrap());
+        }
+        self.queues
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|mq| mq.clone() as Arc<dyn INode>)
+            .ok_or(FsError::EntryNotFound)
+    }
+
+    fn get_entry(&self, id: usize) -> vfs::Result<String> {
+        match id {
+            0 => Ok(String::from(".")),
+            1 => Ok(String::from("..")),
+            i => self
+                .queues
+                .read()
+                .unwrap()
+                .keys()
+                .nth(i - 2)
+                .cloned()
+                .ok_or(FsError::EntryNotFound),
+        }
+    }
+
+    fn iterate_entries(
+        &self,
+        offset: usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> vfs::Result<usize> {
+        let try_iterate =
+            |mut offset: &mut usize, mut visitor: &mut dyn DirentVisitor| -> vfs::Result<()> {
+                // The two special entries
+                if *offset == 0 {
+                    let this_inode = self.this.upgrade().unwrap();
+                    rcore_fs::visit_inode_entry!(&mut visitor, ".", &this_inode, &mut offset);
+                }
+                if *offset == 1 {
+                    let parent_inode = self.this.upgrade().unwrap();
+                    rcore_fs::visit_inode_entry!(&mut visitor, "..", &parent_inode, &mut offset);
+                }
+
+                // The queues
+                let start_offset = *offset;
+                let queues = self.queues.read().unwrap();
+                for (name, mq) in queues.iter().skip(start_offset - 2) {
+                    rcore_fs::visit_inode_entry!(&mut visitor, name, mq, &mut offset);
+                }
+                Ok(())
+            };
+
+        let mut iterate_offset = offset;
+        match try_iterate(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
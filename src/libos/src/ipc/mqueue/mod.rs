@@ -0,0 +1,22 @@
+//! POSIX message queues, i.e., mq_open(3) and friends.
+//!
+//! All the message queues are kept in the global mqueue file system, which is
+//! mounted at `/dev/mqueue`. A queue opened by mq_open is a file descriptor
+//! that can be monitored with poll, select and epoll.
+
+use super::*;
+
+use crate::fs::{AccessMode, CreationFlags, FileMode};
+use rcore_fs::vfs::{
+    self, DirentVisitor, FileSystem, FileType, FsError, INode, Metadata, Timespec,
+};
+use std::any::Any;
+use std::time::Duration;
+
+mod file;
+mod fs;
+mod mq;
+
+pub use self::file::{AsMqueue, MqueueFile};
+pub use self::fs::{MqueueFS, MQUEUE_FS};
+pub use self::mq::{mq_attr_t, PosixMq};
@@ -0,0 +1,429 @@
+use super::*;
+
+use crate::events::{Pollee, Waiter, WaiterQueue};
+use crate::fs::IoEvents;
+use crate::process::{do_getegid, do_geteuid, gid_t, uid_t, Process};
+use crate::signal::{sigval_t, SigNum, UserSignal, UserSignalKind, SIGEV_NONE, SIGEV_SIGNAL};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Weak;
+
+// The priorities of messages are in [0, MQ_PRIO_MAX)
+pub const MQ_PRIO_MAX: u32 = 32768;
+// The default attributes of a queue created without attributes
+const DFLT_MAXMSG: usize = 10;
+const DFLT_MSGSIZE: usize = 8192;
+// The upper limits of the attributes, same with the hard limits of Linux
+const HARD_MSGMAX: usize = 65536;
+const HARD_MSGSIZEMAX: usize = 16 * 1024 * 1024;
+
+/// The attributes of a message queue (see mq_getattr(3)).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct mq_attr_t {
+    pub mq_flags: i64,
+    pub mq_maxmsg: i64,
+    pub mq_msgsize: i64,
+    pub mq_curmsgs: i64,
+    __reserved: [i64; 4],
+}
+
+/// A POSIX message queue.
+pub struct PosixMq {
+    ino: usize,
+    maxmsg: usize,
+    msgsize: usize,
+    inner: SgxMutex<PosixMqInner>,
+    // Threads waiting for the queue to become non-full
+    send_waiters: WaiterQueue,
+    // Threads waiting for the queue to become non-empty
+    recv_waiters: WaiterQueue,
+    pollee: Pollee,
+}
+
+struct PosixMqInner {
+    msgs: BinaryHeap<Message>,
+    // The sequence number of the next message, which keeps messages of the
+    // same priority in FIFO order
+    next_seq: u64,
+    num_bytes: usize,
+    notification: Option<MqNotification>,
+    mode: u16,
+    uid: uid_t,
+    gid: gid_t,
+    ctime: i64,
+    mtime: i64,
+}
+
+#[derive(Debug)]
+struct Message {
+    prio: u32,
+    seq: u64,
+    data: Vec<u8>,
+}
+
+// The message of the highest priority is the greatest one. Among the messages
+// of the same priority, the oldest one is the greatest one.
+impl Ord for Message {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.prio
+            .cmp(&other.prio)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Message {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Message {}
+
+/// The process registered by mq_notify to be notified of the arrival of a
+/// message on an empty queue.
+struct MqNotification {
+    pid: pid_t,
+    process: Weak<Process>,
+    // `None` means SIGEV_NONE, i.e., registration without notification
+    signal: Option<(SigNum, sigval_t)>,
+}
+
+// Safety. The signal value is an opaque value that is never dereferenced by the LibOS.
+unsafe impl Send for MqNotification {}
+unsafe impl Sync for MqNotification {}
+
+impl MqNotification {
+    fn is_stale(&self) -> bool {
+        match self.process.upgrade() {
+            Some(process) => !process.status().is_alive(),
+            None => true,
+        }
+    }
+
+    fn notify(self, sender_pid: pid_t, sender_uid: uid_t) {
+        let (signum, value) = match self.signal {
+            Some(signal) => signal,
+            None => return,
+        };
+        let process = match self.process.upgrade() {
+            Some(process) if process.status().is_alive() => process,
+            _ => return,
+        };
+        let signal = Box::new(UserSignal::new(
+            signum,
+            UserSignalKind::Mesgq(value),
+            sender_pid,
+            sender_uid,
+        ));
+        process.sig_queues().write().unwrap().enqueue(signal);
+    }
+}
+
+// The inode number 1 is reserved for the root directory
+static NEXT_INO: AtomicUsize = AtomicUsize::new(2);
+
+impl PosixMq {
+    pub fn new(mode: u16, attr: Option<&mq_attr_t>) -> Result<Self> {
+        let (maxmsg, msgsize) = match attr {
+            Some(attr) => {
+                if attr.mq_maxmsg <= 0 || attr.mq_maxmsg as usize > HARD_MSGMAX {
+                    return_errno!(EINVAL, "invalid mq_maxmsg");
+                }
+                if attr.mq_msgsize <= 0 || attr.mq_msgsize as usize > HARD_MSGSIZEMAX {
+                    return_errno!(EINVAL, "invalid mq_msgsize");
+                }
+                (attr.mq_maxmsg as usize, attr.mq_msgsize as usize)
+            }
+            None => (DFLT_MAXMSG, DFLT_MSGSIZE),
+        };
+
+        let now = crate::time::do_gettimeofday().sec();
+        let inner = PosixMqInner {
+            msgs: BinaryHeap::new(),
+            next_seq: 0,
+            num_bytes: 0,
+            notification: None,
+            mode,
+            uid: do_geteuid().unwrap() as uid_t,
+            gid: do_getegid().unwrap() as gid_t,
+            ctime: now,
+            mtime: now,
+        };
+        Ok(Self {
+            ino: NEXT_INO.fetch_add(1, AtomicOrdering::Relaxed),
+            maxmsg,
+            msgsize,
+            inner: SgxMutex::new(inner),
+            send_waiters: WaiterQueue::new(),
+            recv_waiters: WaiterQueue::new(),
+            pollee: Pollee::new(IoEvents::OUT),
+        })
+    }
+
+    /// Check the permissions of the owner to open the queue, like faccessat.
+    pub fn check_perm(&self, access_mode: AccessMode) -> Result<()> {
+        let owner_mode = self.inner.lock().unwrap().mode >> 6;
+        if access_mode.readable() && owner_mode & 0o4 == 0 {
+            return_errno!(EACCES, "the message queue is not readable");
+        }
+        if access_mode.writable() && owner_mode & 0o2 == 0 {
+            return_errno!(EACCES, "the message queue is not writable");
+        }
+        Ok(())
+    }
+
+    pub fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+
+    pub fn attr(&self) -> mq_attr_t {
+        let inner = self.inner.lock().unwrap();
+        mq_attr_t {
+            mq_maxmsg: self.maxmsg as i64,
+            mq_msgsize: self.msgsize as i64,
+            mq_curmsgs: inner.msgs.len() as i64,
+            ..Default::default()
+        }
+    }
+
+    /// Send a message of priority `prio`.
+    ///
+    /// If the queue is full, the caller is blocked until there is room for the
+    /// message or the relative `timeout` expires.
+    pub fn send(
+        &self,
+        data: &[u8],
+        prio: u32,
+        nonblocking: bool,
+        mut timeout: Option<Duration>,
+    ) -> Result<()> {
+        if data.len() > self.msgsize {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+        if prio >= MQ_PRIO_MAX {
+            return_errno!(EINVAL, "invalid message priority");
+        }
+
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.msgs.len() < self.maxmsg {
+                let was_empty = inner.msgs.is_empty();
+                let seq = inner.next_seq;
+                inner.next_seq += 1;
+                inner.num_bytes += data.len();
+                inner.msgs.push(Message {
+                    prio,
+                    seq,
+                    data: data.to_vec(),
+                });
+                inner.mtime = crate::time::do_gettimeofday().sec();
+
+                // The notification is only sent if no thread is blocked in
+                // receiving, and the registration is removed once notified
+                let notification = if was_empty && self.recv_waiters.is_empty() {
+                    inner.notification.take()
+                } else {
+                    None
+                };
+                // Update the events with the lock held to keep them consistent
+                self.pollee.add_events(IoEvents::IN);
+                if inner.msgs.len() == self.maxmsg {
+                    self.pollee.del_events(IoEvents::OUT);
+                }
+                drop(inner);
+
+                self.recv_waiters.dequeue_and_wake_all();
+                if let Some(notification) = notification {
+                    let current = current!();
+                    notification.notify(current.process().pid(), do_geteuid()? as uid_t);
+                }
+                return Ok(());
+            }
+
+            if nonblocking {
+                return_errno!(EAGAIN, "the message queue is full");
+            }
+            self.send_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait_mut(timeout.as_mut())?;
+        }
+    }
+
+    /// Receive the oldest message of the highest priority.
+    ///
+    /// If the queue is empty, the caller is blocked until a message arrives or
+    /// the relative `timeout` expires.
+    pub fn receive(
+        &self,
+        buf_len: usize,
+        nonblocking: bool,
+        mut timeout: Option<Duration>,
+    ) -> Result<(Vec<u8>, u32)> {
+        if buf_len < self.msgsize {
+            return_errno!(EMSGSIZE, "the buffer is smaller than mq_msgsize");
+        }
+
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(msg) = inner.msgs.pop() {
+                inner.num_bytes -= msg.data.len();
+                inner.mtime = crate::time::do_gettimeofday().sec();
+                self.pollee.add_events(IoEvents::OUT);
+                if inner.msgs.is_empty() {
+                    self.pollee.del_events(IoEvents::IN);
+                }
+                drop(inner);
+
+                self.send_waiters.dequeue_and_wake_all();
+                return Ok((msg.data, msg.prio));
+            }
+
+            if nonblocking {
+                return_errno!(EAGAIN, "the message queue is empty");
+            }
+            self.recv_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait_mut(timeout.as_mut())?;
+        }
+    }
+
+    /// Register the current process to be notified with `signal` when a
+    /// message arrives on the empty queue.
+    ///
+    /// A `signal` of `None` means SIGEV_NONE.
+    pub fn register_notification(&self, signal: Option<(SigNum, sigval_t)>) -> Result<()> {
+        let current = current!();
+        let process = current.process();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(notification) = &inner.notification {
+            if notification.pid != process.pid() && !notification.is_stale() {
+                return_errno!(EBUSY, "another process has registered for notification");
+            }
+        }
+        inner.notification = Some(MqNotification {
+            pid: process.pid(),
+            process: Arc::downgrade(process),
+            signal,
+        });
+        Ok(())
+    }
+
+    /// Remove the registration of the current process, if any.
+    pub fn unregister_notification(&self) -> Result<()> {
+        let pid = current!().process().pid();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(notification) = &inner.notification {
+            if notification.pid == pid {
+                inner.notification = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// The status of the queue shown by reading the queue file under the
+    /// mqueue file system, in the same format as Linux.
+    pub fn status(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let (notify, signo, notify_pid) = match &inner.notification {
+            Some(notification) if !notification.is_stale() => match notification.signal {
+                Some((signum, _)) => (SIGEV_SIGNAL, signum.as_u8() as i32, notification.pid),
+                None => (SIGEV_NONE, 0, notification.pid),
+            },
+            _ => (0, 0, 0),
+        };
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            inner.num_bytes, notify, signo, notify_pid
+        )
+    }
+}
+
+// The queue is also the inode of the mqueue file system
+impl INode for PosixMq {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> vfs::Result<usize> {
+        let status = self.status();
+        let data = status.as_bytes();
+        let start = data.len().min(offset);
+        let end = data.len().min(offset + buf.len());
+        let len = end - start;
+        buf[0..len].copy_from_slice(&data[start..end]);
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> vfs::Result<usize> {
+        Err(FsError::PermError)
+    }
+
+    fn metadata(&self) -> vfs::Result<Metadata> {
+        let inner = self.inner.lock().unwrap();
+        Ok(Metadata {
+            dev: 0,
+            inode: self.ino,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec {
+                sec: inner.mtime,
+                nsec: 0,
+            },
+            mtime: Timespec {
+                sec: inner.mtime,
+                nsec: 0,
+            },
+            ctime: Timespec {
+                sec: inner.ctime,
+                nsec: 0,
+            },
+            type_: FileType::File,
+            mode: inner.mode,
+            nlinks: 1,
+            uid: inner.uid as usize,
+            gid: inner.gid as usize,
+            rdev: 0,
+        })
+    }
+
+    fn set_metadata(&self, metadata: &Metadata) -> vfs::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.mode = metadata.mode;
+        inner.uid = metadata.uid as uid_t;
+        inner.gid = metadata.gid as gid_t;
+        inner.ctime = crate::time::do_gettimeofday().sec();
+        Ok(())
+    }
+
+    fn sync_all(&self) -> vfs::Result<()> {
+        Ok(())
+    }
+
+    fn sync_data(&self) -> vfs::Result<()> {
+        Ok(())
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl std::fmt::Debug for PosixMq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PosixMq")
+            .field("ino", &self.ino)
+            .field("maxmsg", &self.maxmsg)
+            .field("msgsize", &self.msgsize)
+            .finish()
+    }
+}
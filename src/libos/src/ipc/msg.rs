@@ -0,0 +1,400 @@
+use super::*;
+
+use super::shm::{ipc_perm_t, key_t, IPC_PRIVATE};
+use crate::events::{Waiter, WaiterQueue};
+use crate::fs::FileMode;
+use crate::process::{do_getegid, do_geteuid, gid_t, uid_t};
+use crate::time::{do_gettimeofday, time_t};
+use std::collections::{HashMap, VecDeque};
+
+pub type MsqId = u32;
+
+// max size of a message (bytes)
+const MSGMAX: usize = 8192;
+// default max size of a message queue (bytes)
+const MSGMNB: usize = 16384;
+// max num of message queues system wide,
+// also indicates the max msqid - 1 in Occlum
+const MSGMNI: MsqId = 4096;
+
+// For msgflg in msgget()
+const IPC_CREAT: i32 = 0o1000;
+const IPC_EXCL: i32 = 0o2000;
+
+bitflags! {
+    /// The flags of msgsnd() and msgrcv().
+    pub struct MsgFlags: i32 {
+        const IPC_NOWAIT = 0o4000;
+        const MSG_NOERROR = 0o10000;
+        const MSG_EXCEPT = 0o20000;
+        const MSG_COPY = 0o40000;
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct msqid_ds_t {
+    msg_perm: ipc_perm_t,
+    msg_stime: time_t,
+    msg_rtime: time_t,
+    msg_ctime: time_t,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: pid_t,
+    msg_lrpid: pid_t,
+    unused1: u64,
+    unused2: u64,
+}
+
+#[derive(Debug)]
+struct Message {
+    mtype: i64,
+    mtext: Vec<u8>,
+}
+
+struct MsgQueue {
+    msqid: MsqId,
+    key: key_t,
+    inner: SgxMutex<MsgQueueInner>,
+    // The threads waiting for free space to send a message
+    send_waiters: WaiterQueue,
+    // The threads waiting for a message to receive
+    recv_waiters: WaiterQueue,
+}
+
+#[derive(Debug)]
+struct MsgQueueInner {
+    uid: uid_t,
+    gid: gid_t,
+    cuid: uid_t,
+    cgid: gid_t,
+    mode: FileMode,
+
+    msg_stime: time_t,
+    msg_rtime: time_t,
+    msg_ctime: time_t,
+
+    msg_lspid: pid_t,
+    msg_lrpid: pid_t,
+
+    msgs: VecDeque<Message>,
+    // The total size of the messages in the queue (bytes)
+    msg_cbytes: usize,
+    // The max size of the queue (bytes)
+    msg_qbytes: usize,
+    is_removed: bool,
+}
+
+impl MsgQueue {
+    fn new(msqid: MsqId, key: key_t, mode: FileMode) -> Self {
+        let inner = MsgQueueInner {
+            uid: do_geteuid().unwrap() as u32,
+            gid: do_getegid().unwrap() as u32,
+            cuid: do_geteuid().unwrap() as u32,
+            cgid: do_getegid().unwrap() as u32,
+            mode,
+            msg_stime: 0,
+            msg_rtime: 0,
+            msg_ctime: MsgManager::current_time(),
+            msg_lspid: 0,
+            msg_lrpid: 0,
+            msgs: VecDeque::new(),
+            msg_cbytes: 0,
+            msg_qbytes: MSGMNB,
+            is_removed: false,
+        };
+        MsgQueue {
+            msqid,
+            key,
+            inner: SgxMutex::new(inner),
+            send_waiters: WaiterQueue::new(),
+            recv_waiters: WaiterQueue::new(),
+        }
+    }
+
+    fn check_perm(&self) -> Result<()> {
+        // TODO: Add permission control
+        Ok(())
+    }
+
+    fn send(&self, mtype: i64, mtext: Vec<u8>, flags: MsgFlags) -> Result<()> {
+        let pid = current!().process().pid();
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_removed {
+                return_errno!(EIDRM, "the message queue is removed");
+            }
+
+            // Like Linux, the num of messages is also limited by msg_qbytes
+            // so that a queue cannot be filled with zero-sized messages.
+            let has_space = inner.msg_cbytes + mtext.len() <= inner.msg_qbytes
+                && inner.msgs.len() + 1 <= inner.msg_qbytes;
+            if has_space {
+                inner.msg_cbytes += mtext.len();
+                inner.msgs.push_back(Message { mtype, mtext });
+                inner.msg_lspid = pid;
+                inner.msg_stime = MsgManager::current_time();
+                drop(inner);
+
+                self.recv_waiters.dequeue_and_wake_all();
+                return Ok(());
+            }
+
+            if flags.contains(MsgFlags::IPC_NOWAIT) {
+                return_errno!(EAGAIN, "the message queue is full");
+            }
+            self.send_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait(None)?;
+        }
+    }
+
+    fn recv(&self, max_size: usize, msgtyp: i64, flags: MsgFlags) -> Result<(i64, Vec<u8>)> {
+        let pid = current!().process().pid();
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_removed {
+                return_errno!(EIDRM, "the message queue is removed");
+            }
+
+            if let Some(idx) = inner.find_msg(msgtyp, flags) {
+                let msg_size = inner.msgs[idx].mtext.len();
+                if msg_size > max_size && !flags.contains(MsgFlags::MSG_NOERROR) {
+                    return_errno!(E2BIG, "the message is too large to receive");
+                }
+
+                let mut msg = inner.msgs.remove(idx).unwrap();
+                inner.msg_cbytes -= msg_size;
+                inner.msg_lrpid = pid;
+                inner.msg_rtime = MsgManager::current_time();
+                drop(inner);
+
+                self.send_waiters.dequeue_and_wake_all();
+                // With MSG_NOERROR, the message is truncated
+                msg.mtext.truncate(max_size);
+                return Ok((msg.mtype, msg.mtext));
+            }
+
+            if flags.contains(MsgFlags::IPC_NOWAIT) {
+                return_errno!(ENOMSG, "no message of the requested type");
+            }
+            self.recv_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait(None)?;
+        }
+    }
+}
+
+impl MsgQueueInner {
+    // Find the message to receive according to msgtyp (see msgrcv(2)).
+    fn find_msg(&self, msgtyp: i64, flags: MsgFlags) -> Option<usize> {
+        if msgtyp == 0 {
+            return if self.msgs.is_empty() { None } else { Some(0) };
+        }
+        if msgtyp > 0 {
+            let is_except = flags.contains(MsgFlags::MSG_EXCEPT);
+            return self
+                .msgs
+                .iter()
+                .position(|msg| (msg.mtype == msgtyp) != is_except);
+        }
+
+        // The first message with the lowest type less than or equal to |msgtyp|
+        let max_type = msgtyp.checked_neg().unwrap_or(i64::MAX);
+        self.msgs
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg.mtype <= max_type)
+            .min_by_key(|(idx, msg)| (msg.mtype, *idx))
+            .map(|(idx, _)| idx)
+    }
+}
+
+lazy_static! {
+    pub static ref SYSTEM_V_MSG_MANAGER: MsgManager = MsgManager::new();
+}
+
+pub struct MsgManager {
+    msg_queues: RwLock<HashMap<MsqId, Arc<MsgQueue>>>,
+    last_alloc_id: SgxMutex<MsqId>,
+}
+
+impl MsgManager {
+    fn new() -> Self {
+        MsgManager {
+            msg_queues: RwLock::new(HashMap::new()),
+            last_alloc_id: SgxMutex::new(MSGMNI - 1),
+        }
+    }
+
+    fn current_time() -> time_t {
+        do_gettimeofday().sec()
+    }
+
+    // Always return next free id for msqid
+    fn get_new_msqid(&self, msg_queues: &HashMap<MsqId, Arc<MsgQueue>>) -> Result<MsqId> {
+        if msg_queues.len() >= MSGMNI as usize {
+            return_errno!(ENOSPC, "all possible message queue IDs have been taken");
+        }
+        let mut last_alloc_id = self.last_alloc_id.lock().unwrap();
+        let mut id = *last_alloc_id + 1;
+        loop {
+            if id == MSGMNI {
+                id = 0;
+            }
+            if !msg_queues.contains_key(&id) {
+                break;
+            }
+            id += 1;
+        }
+        *last_alloc_id = id;
+        Ok(id)
+    }
+
+    fn get_msg_queue(&self, msqid: MsqId) -> Result<Arc<MsgQueue>> {
+        self.msg_queues
+            .read()
+            .unwrap()
+            .get(&msqid)
+            .cloned()
+            .ok_or_else(|| errno!(EINVAL, "cannot find message queue by msqid"))
+    }
+
+    pub fn do_msgget(&self, key: key_t, msgflg: i32) -> Result<MsqId> {
+        debug!("do_msgget: key: {:?}, msgflg: {:#o}", key, msgflg);
+        let mode = FileMode::from_bits_truncate(msgflg as u16 & 0o777);
+
+        let mut msg_queues = self.msg_queues.write().unwrap();
+        let msg_queue = if key == IPC_PRIVATE {
+            None
+        } else {
+            msg_queues.values().find(|&msg_queue| msg_queue.key == key)
+        };
+        let msqid = if let Some(msg_queue) = msg_queue {
+            if msgflg & IPC_CREAT != 0 && msgflg & IPC_EXCL != 0 {
+                return_errno!(EEXIST, "the message queue already exists for given key");
+            }
+            msg_queue.check_perm()?;
+            msg_queue.msqid
+        } else {
+            if key != IPC_PRIVATE && msgflg & IPC_CREAT == 0 {
+                return_errno!(ENOENT, "no message queue exists for given key");
+            }
+            let msqid = self.get_new_msqid(&msg_queues)?;
+            let msg_queue = MsgQueue::new(msqid, key, mode);
+            msg_queues.insert(msqid, Arc::new(msg_queue));
+            msqid
+        };
+        Ok(msqid)
+    }
+
+    pub fn do_msgsnd(&self, msqid: MsqId, mtype: i64, mtext: &[u8], flags: MsgFlags) -> Result<()> {
+        debug!(
+            "do_msgsnd: msqid: {:?}, mtype: {:?}, msgsz: {:?}, flags: {:?}",
+            msqid,
+            mtype,
+            mtext.len(),
+            flags
+        );
+        if mtype <= 0 {
+            return_errno!(EINVAL, "mtype must be positive");
+        }
+        if mtext.len() > MSGMAX {
+            return_errno!(EINVAL, "the message is too large");
+        }
+
+        let msg_queue = self.get_msg_queue(msqid)?;
+        msg_queue.check_perm()?;
+        msg_queue.send(mtype, mtext.to_vec(), flags)
+    }
+
+    pub fn do_msgrcv(
+        &self,
+        msqid: MsqId,
+        msgsz: usize,
+        msgtyp: i64,
+        flags: MsgFlags,
+    ) -> Result<(i64, Vec<u8>)> {
+        debug!(
+            "do_msgrcv: msqid: {:?}, msgsz: {:?}, msgtyp: {:?}, flags: {:?}",
+            msqid, msgsz, msgtyp, flags
+        );
+        if flags.contains(MsgFlags::MSG_COPY) {
+            return_errno!(ENOSYS, "MSG_COPY is not supported");
+        }
+
+        let msg_queue = self.get_msg_queue(msqid)?;
+        msg_queue.check_perm()?;
+        msg_queue.recv(msgsz, msgtyp, flags)
+    }
+
+    pub fn do_msgctl_rmid(&self, msqid: MsqId) -> Result<()> {
+        debug!("do_msgctl_rmid: msqid: {:?}", msqid);
+        let msg_queue = self
+            .msg_queues
+            .write()
+            .unwrap()
+            .remove(&msqid)
+            .ok_or_else(|| errno!(EINVAL, "cannot find message queue by msqid"))?;
+        msg_queue.inner.lock().unwrap().is_removed = true;
+        // Wake up all waiters, which will fail with EIDRM
+        msg_queue.send_waiters.dequeue_and_wake_all();
+        msg_queue.recv_waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    pub fn do_msgctl_stat(&self, msqid: MsqId) -> Result<msqid_ds_t> {
+        let msg_queue = self.get_msg_queue(msqid)?;
+        msg_queue.check_perm()?;
+        let inner = msg_queue.inner.lock().unwrap();
+        let msg_perm = ipc_perm_t {
+            key: msg_queue.key,
+            uid: inner.uid,
+            gid: inner.gid,
+            cuid: inner.cuid,
+            cgid: inner.cgid,
+            mode: inner.mode.bits(),
+            pad1: 0,
+            seq: 0,
+            pad2: 0,
+            unused1: 0,
+            unused2: 0,
+        };
+        Ok(msqid_ds_t {
+            msg_perm,
+            msg_stime: inner.msg_stime,
+            msg_rtime: inner.msg_rtime,
+            msg_ctime: inner.msg_ctime,
+            msg_cbytes: inner.msg_cbytes as u64,
+            msg_qnum: inner.msgs.len() as u64,
+            msg_qbytes: inner.msg_qbytes as u64,
+            msg_lspid: inner.msg_lspid,
+            msg_lrpid: inner.msg_lrpid,
+            unused1: 0,
+            unused2: 0,
+        })
+    }
+
+    pub fn do_msgctl_set(&self, msqid: MsqId, buf: &msqid_ds_t) -> Result<()> {
+        debug!("do_msgctl_set: msqid: {:?}, buf: {:?}", msqid, buf);
+        let msg_queue = self.get_msg_queue(msqid)?;
+        msg_queue.check_perm()?;
+        let mut inner = msg_queue.inner.lock().unwrap();
+        inner.uid = buf.msg_perm.uid;
+        inner.gid = buf.msg_perm.gid;
+        inner.mode = FileMode::from_bits_truncate(buf.msg_perm.mode & 0o777);
+        inner.msg_qbytes = buf.msg_qbytes as usize;
+        inner.msg_ctime = MsgManager::current_time();
+        drop(inner);
+
+        // The queue may become larger
+        msg_queue.send_waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+}
@@ -0,0 +1,537 @@
+use super::*;
+
+use super::shm::{ipc_perm_t, key_t, CmdId, IPC_PRIVATE};
+use crate::events::{Waiter, WaiterQueue};
+use crate::fs::FileMode;
+use crate::process::{do_getegid, do_geteuid, gid_t, uid_t};
+use crate::time::{do_gettimeofday, time_t};
+use std::collections::HashMap;
+
+pub type SemId = u32;
+
+// max num of semaphores per set
+const SEMMSL: usize = 32000;
+// max num of semaphore sets system wide,
+// also indicates the max semid - 1 in Occlum
+const SEMMNI: SemId = 4096;
+// max num of ops per semop call
+const SEMOPM: usize = 500;
+// max value of a semaphore
+const SEMVMX: i32 = 32767;
+// max value of the adjustment on exit of a semaphore
+const SEMAEM: i32 = SEMVMX;
+
+// For semflg in semget()
+const IPC_CREAT: i32 = 0o1000;
+const IPC_EXCL: i32 = 0o2000;
+
+// For cmd in semctl()
+pub(super) const GETPID: CmdId = 11;
+pub(super) const GETVAL: CmdId = 12;
+pub(super) const GETALL: CmdId = 13;
+pub(super) const GETNCNT: CmdId = 14;
+pub(super) const GETZCNT: CmdId = 15;
+pub(super) const SETVAL: CmdId = 16;
+pub(super) const SETALL: CmdId = 17;
+// The flag that some libc implementations add to cmd for the new version of semid_ds
+pub(super) const IPC_64: CmdId = 0x100;
+
+// For sem_flg in sembuf
+const IPC_NOWAIT: i16 = 0o4000;
+const SEM_UNDO: i16 = 0x1000;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct semid_ds_t {
+    sem_perm: ipc_perm_t,
+    sem_otime: time_t,
+    unused1: u64,
+    sem_ctime: time_t,
+    unused2: u64,
+    sem_nsems: u64,
+    unused3: u64,
+    unused4: u64,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct sembuf_t {
+    sem_num: u16,
+    sem_op: i16,
+    sem_flg: i16,
+}
+
+#[derive(Debug, Default)]
+struct Semaphore {
+    val: i32,
+    // The pid of the process that last operated on the semaphore
+    pid: pid_t,
+    // The num of processes waiting for the value to increase
+    ncnt: usize,
+    // The num of processes waiting for the value to become zero
+    zcnt: usize,
+}
+
+struct SemSet {
+    semid: SemId,
+    key: key_t,
+    inner: SgxMutex<SemSetInner>,
+    waiters: WaiterQueue,
+}
+
+#[derive(Debug)]
+struct SemSetInner {
+    uid: uid_t,
+    gid: gid_t,
+    cuid: uid_t,
+    cgid: gid_t,
+    mode: FileMode,
+
+    sem_otime: time_t,
+    sem_ctime: time_t,
+
+    sems: Vec<Semaphore>,
+    // The adjustments to be applied on process exit for SEM_UNDO operations
+    undos: HashMap<pid_t, Vec<i32>>,
+    is_removed: bool,
+}
+
+// The reason why a semop call cannot complete right now
+enum SemopBlocked {
+    // The semaphore with the index is waiting for its value to increase
+    Decrease(usize),
+    // The semaphore with the index is waiting for its value to become zero
+    Zero(usize),
+}
+
+impl SemSet {
+    fn new(semid: SemId, key: key_t, nsems: usize, mode: FileMode) -> Self {
+        let mut sems = Vec::with_capacity(nsems);
+        sems.resize_with(nsems, Default::default);
+        let inner = SemSetInner {
+            uid: do_geteuid().unwrap() as u32,
+            gid: do_getegid().unwrap() as u32,
+            cuid: do_geteuid().unwrap() as u32,
+            cgid: do_getegid().unwrap() as u32,
+            mode,
+            sem_otime: 0,
+            sem_ctime: SemManager::current_time(),
+            sems,
+            undos: HashMap::new(),
+            is_removed: false,
+        };
+        SemSet {
+            semid,
+            key,
+            inner: SgxMutex::new(inner),
+            waiters: WaiterQueue::new(),
+        }
+    }
+
+    fn nsems(&self) -> usize {
+        self.inner.lock().unwrap().sems.len()
+    }
+
+    fn check_perm(&self) -> Result<()> {
+        // TODO: Add permission control
+        Ok(())
+    }
+
+    fn semop(&self, sops: &[sembuf_t], mut timeout: Option<Duration>) -> Result<()> {
+        let pid = current!().process().pid();
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_removed {
+                return_errno!(EIDRM, "the semaphore set is removed");
+            }
+
+            let blocked = match inner.try_semop(sops, pid)? {
+                Some(blocked) => blocked,
+                None => {
+                    drop(inner);
+                    self.waiters.dequeue_and_wake_all();
+                    return Ok(());
+                }
+            };
+            inner.inc_wait_count(&blocked);
+            self.waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            let res = waiter.wait_mut(timeout.as_mut());
+            self.inner.lock().unwrap().dec_wait_count(&blocked);
+            if let Err(e) = res {
+                if e.errno() == Errno::ETIMEDOUT {
+                    return_errno!(EAGAIN, "the time limit expires");
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+impl SemSetInner {
+    // Perform all the operations atomically.
+    //
+    // Returns the reason why the operations cannot be performed without blocking,
+    // or `None` if the operations are performed.
+    fn try_semop(&mut self, sops: &[sembuf_t], pid: pid_t) -> Result<Option<SemopBlocked>> {
+        let mut vals: Vec<i32> = self.sems.iter().map(|sem| sem.val).collect();
+        for sop in sops {
+            let idx = sop.sem_num as usize;
+            let val = &mut vals[idx];
+            let blocked = match sop.sem_op {
+                0 if *val != 0 => Some(SemopBlocked::Zero(idx)),
+                op if op < 0 && *val < -(op as i32) => Some(SemopBlocked::Decrease(idx)),
+                op => {
+                    *val += op as i32;
+                    if *val > SEMVMX {
+                        return_errno!(ERANGE, "the semaphore value is too large");
+                    }
+                    None
+                }
+            };
+            if let Some(blocked) = blocked {
+                if sop.sem_flg & IPC_NOWAIT != 0 {
+                    return_errno!(EAGAIN, "the operation would block");
+                }
+                return Ok(Some(blocked));
+            }
+        }
+
+        // Record the adjustments for SEM_UNDO operations
+        let nsems = self.sems.len();
+        if sops.iter().any(|sop| sop.sem_flg & SEM_UNDO != 0) {
+            let mut adjs = self
+                .undos
+                .get(&pid)
+                .cloned()
+                .unwrap_or_else(|| vec![0; nsems]);
+            for sop in sops.iter().filter(|sop| sop.sem_flg & SEM_UNDO != 0) {
+                let adj = &mut adjs[sop.sem_num as usize];
+                *adj -= sop.sem_op as i32;
+                if *adj < -(SEMAEM + 1) || *adj > SEMAEM {
+                    return_errno!(ERANGE, "the adjustment is out of range");
+                }
+            }
+            self.undos.insert(pid, adjs);
+        }
+
+        for (sem, val) in self.sems.iter_mut().zip(vals.into_iter()) {
+            sem.val = val;
+        }
+        for sop in sops {
+            self.sems[sop.sem_num as usize].pid = pid;
+        }
+        self.sem_otime = SemManager::current_time();
+        Ok(None)
+    }
+
+    fn inc_wait_count(&mut self, blocked: &SemopBlocked) {
+        match *blocked {
+            SemopBlocked::Decrease(idx) => self.sems[idx].ncnt += 1,
+            SemopBlocked::Zero(idx) => self.sems[idx].zcnt += 1,
+        }
+    }
+
+    fn dec_wait_count(&mut self, blocked: &SemopBlocked) {
+        match *blocked {
+            SemopBlocked::Decrease(idx) => self.sems[idx].ncnt -= 1,
+            SemopBlocked::Zero(idx) => self.sems[idx].zcnt -= 1,
+        }
+    }
+
+    fn get_sem(&self, semnum: i32) -> Result<&Semaphore> {
+        self.sems
+            .get(semnum as usize)
+            .filter(|_| semnum >= 0)
+            .ok_or_else(|| errno!(EINVAL, "invalid semnum"))
+    }
+
+    fn set_val(&mut self, semnum: usize, val: i32, pid: pid_t) {
+        self.sems[semnum].val = val;
+        self.sems[semnum].pid = pid;
+        // The adjustments of the semaphore are cleared in all processes
+        for adjs in self.undos.values_mut() {
+            adjs[semnum] = 0;
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref SYSTEM_V_SEM_MANAGER: SemManager = SemManager::new();
+}
+
+pub struct SemManager {
+    sem_sets: RwLock<HashMap<SemId, Arc<SemSet>>>,
+    last_alloc_id: SgxMutex<SemId>,
+}
+
+impl SemManager {
+    fn new() -> Self {
+        SemManager {
+            sem_sets: RwLock::new(HashMap::new()),
+            last_alloc_id: SgxMutex::new(SEMMNI - 1),
+        }
+    }
+
+    fn current_time() -> time_t {
+        do_gettimeofday().sec()
+    }
+
+    // Always return next free id for semid
+    fn get_new_semid(&self, sem_sets: &HashMap<SemId, Arc<SemSet>>) -> Result<SemId> {
+        if sem_sets.len() >= SEMMNI as usize {
+            return_errno!(ENOSPC, "all possible semaphore set IDs have been taken");
+        }
+        let mut last_alloc_id = self.last_alloc_id.lock().unwrap();
+        let mut id = *last_alloc_id + 1;
+        loop {
+            if id == SEMMNI {
+                id = 0;
+            }
+            if !sem_sets.contains_key(&id) {
+                break;
+            }
+            id += 1;
+        }
+        *last_alloc_id = id;
+        Ok(id)
+    }
+
+    fn get_sem_set(&self, semid: SemId) -> Result<Arc<SemSet>> {
+        self.sem_sets
+            .read()
+            .unwrap()
+            .get(&semid)
+            .cloned()
+            .ok_or_else(|| errno!(EINVAL, "cannot find semaphore set by semid"))
+    }
+
+    pub fn do_semget(&self, key: key_t, nsems: i32, semflg: i32) -> Result<SemId> {
+        debug!(
+            "do_semget: key: {:?}, nsems: {:?}, semflg: {:#o}",
+            key, nsems, semflg
+        );
+        if nsems < 0 || nsems as usize > SEMMSL {
+            return_errno!(EINVAL, "invalid nsems");
+        }
+        let nsems = nsems as usize;
+        let mode = FileMode::from_bits_truncate(semflg as u16 & 0o777);
+
+        let mut sem_sets = self.sem_sets.write().unwrap();
+        let sem_set = if key == IPC_PRIVATE {
+            None
+        } else {
+            sem_sets.values().find(|&sem_set| sem_set.key == key)
+        };
+        let semid = if let Some(sem_set) = sem_set {
+            if semflg & IPC_CREAT != 0 && semflg & IPC_EXCL != 0 {
+                return_errno!(EEXIST, "the semaphore set already exists for given key");
+            }
+            if nsems > sem_set.nsems() {
+                return_errno!(EINVAL, "nsems is larger than that of the semaphore set");
+            }
+            sem_set.check_perm()?;
+            sem_set.semid
+        } else {
+            if key != IPC_PRIVATE && semflg & IPC_CREAT == 0 {
+                return_errno!(ENOENT, "no semaphore set exists for given key");
+            }
+            if nsems == 0 {
+                return_errno!(EINVAL, "nsems must be positive to create a semaphore set");
+            }
+            let semid = self.get_new_semid(&sem_sets)?;
+            let sem_set = SemSet::new(semid, key, nsems, mode);
+            sem_sets.insert(semid, Arc::new(sem_set));
+            semid
+        };
+        Ok(semid)
+    }
+
+    pub fn do_semtimedop(
+        &self,
+        semid: SemId,
+        sops: &[sembuf_t],
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        debug!(
+            "do_semtimedop: semid: {:?}, sops: {:?}, timeout: {:?}",
+            semid, sops, timeout
+        );
+        if sops.len() == 0 {
+            return_errno!(EINVAL, "nsops must be positive");
+        }
+        if sops.len() > SEMOPM {
+            return_errno!(E2BIG, "too many operations");
+        }
+
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let nsems = sem_set.nsems();
+        if sops.iter().any(|sop| sop.sem_num as usize >= nsems) {
+            return_errno!(EFBIG, "sem_num is out of range");
+        }
+        sem_set.semop(sops, timeout)
+    }
+
+    pub fn do_semctl_rmid(&self, semid: SemId) -> Result<()> {
+        debug!("do_semctl_rmid: semid: {:?}", semid);
+        let sem_set = self
+            .sem_sets
+            .write()
+            .unwrap()
+            .remove(&semid)
+            .ok_or_else(|| errno!(EINVAL, "cannot find semaphore set by semid"))?;
+        sem_set.inner.lock().unwrap().is_removed = true;
+        // Wake up all waiters, which will fail with EIDRM
+        sem_set.waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    pub fn do_semctl_stat(&self, semid: SemId) -> Result<semid_ds_t> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        let sem_perm = ipc_perm_t {
+            key: sem_set.key,
+            uid: inner.uid,
+            gid: inner.gid,
+            cuid: inner.cuid,
+            cgid: inner.cgid,
+            mode: inner.mode.bits(),
+            pad1: 0,
+            seq: 0,
+            pad2: 0,
+            unused1: 0,
+            unused2: 0,
+        };
+        Ok(semid_ds_t {
+            sem_perm,
+            sem_otime: inner.sem_otime,
+            unused1: 0,
+            sem_ctime: inner.sem_ctime,
+            unused2: 0,
+            sem_nsems: inner.sems.len() as u64,
+            unused3: 0,
+            unused4: 0,
+        })
+    }
+
+    pub fn do_semctl_set(&self, semid: SemId, buf: &semid_ds_t) -> Result<()> {
+        debug!("do_semctl_set: semid: {:?}, buf: {:?}", semid, buf);
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let mut inner = sem_set.inner.lock().unwrap();
+        inner.uid = buf.sem_perm.uid;
+        inner.gid = buf.sem_perm.gid;
+        inner.mode = FileMode::from_bits_truncate(buf.sem_perm.mode & 0o777);
+        inner.sem_ctime = SemManager::current_time();
+        Ok(())
+    }
+
+    pub fn do_semctl_getval(&self, semid: SemId, semnum: i32) -> Result<i32> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        Ok(inner.get_sem(semnum)?.val)
+    }
+
+    pub fn do_semctl_getpid(&self, semid: SemId, semnum: i32) -> Result<pid_t> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        Ok(inner.get_sem(semnum)?.pid)
+    }
+
+    pub fn do_semctl_getncnt(&self, semid: SemId, semnum: i32) -> Result<usize> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        Ok(inner.get_sem(semnum)?.ncnt)
+    }
+
+    pub fn do_semctl_getzcnt(&self, semid: SemId, semnum: i32) -> Result<usize> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        Ok(inner.get_sem(semnum)?.zcnt)
+    }
+
+    pub fn do_semctl_getall(&self, semid: SemId) -> Result<Vec<u16>> {
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let inner = sem_set.inner.lock().unwrap();
+        Ok(inner.sems.iter().map(|sem| sem.val as u16).collect())
+    }
+
+    pub fn do_semctl_setval(&self, semid: SemId, semnum: i32, val: i32) -> Result<()> {
+        debug!(
+            "do_semctl_setval: semid: {:?}, semnum: {:?}, val: {:?}",
+            semid, semnum, val
+        );
+        if val < 0 || val > SEMVMX {
+            return_errno!(ERANGE, "the semaphore value is out of range");
+        }
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let pid = current!().process().pid();
+        let mut inner = sem_set.inner.lock().unwrap();
+        inner.get_sem(semnum)?;
+        inner.set_val(semnum as usize, val, pid);
+        inner.sem_ctime = SemManager::current_time();
+        drop(inner);
+
+        sem_set.waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    pub fn do_semctl_setall(&self, semid: SemId, vals: &[u16]) -> Result<()> {
+        debug!("do_semctl_setall: semid: {:?}, vals: {:?}", semid, vals);
+        if vals.iter().any(|&val| val as i32 > SEMVMX) {
+            return_errno!(ERANGE, "the semaphore value is out of range");
+        }
+        let sem_set = self.get_sem_set(semid)?;
+        sem_set.check_perm()?;
+        let pid = current!().process().pid();
+        let mut inner = sem_set.inner.lock().unwrap();
+        for (semnum, &val) in vals.iter().enumerate().take(inner.sems.len()) {
+            inner.set_val(semnum, val as i32, pid);
+        }
+        inner.sem_ctime = SemManager::current_time();
+        drop(inner);
+
+        sem_set.waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    /// Get the num of semaphores of a semaphore set.
+    pub fn nsems(&self, semid: SemId) -> Result<usize> {
+        let sem_set = self.get_sem_set(semid)?;
+        Ok(sem_set.nsems())
+    }
+
+    /// Apply the adjustments of SEM_UNDO operations of the exiting process.
+    pub fn undo_when_process_exit(&self, pid: pid_t) {
+        let sem_sets: Vec<Arc<SemSet>> = self.sem_sets.read().unwrap().values().cloned().collect();
+        for sem_set in sem_sets {
+            let mut inner = sem_set.inner.lock().unwrap();
+            let adjs = match inner.undos.remove(&pid) {
+                Some(adjs) => adjs,
+                None => continue,
+            };
+            for (sem, adj) in inner.sems.iter_mut().zip(adjs.into_iter()) {
+                if adj == 0 {
+                    continue;
+                }
+                // The value is clamped to the valid range as Linux does
+                sem.val = (sem.val + adj).max(0).min(SEMVMX);
+                sem.pid = pid;
+            }
+            drop(inner);
+            sem_set.waiters.dequeue_and_wake_all();
+        }
+    }
+}
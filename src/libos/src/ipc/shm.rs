@@ -22,12 +22,12 @@ const SHMMAX: usize = (usize::MAX - (1_usize << 24));
 // also indicates the max shmid - 1 in Occlum
 const SHMMNI: ShmId = 4096;
 
-const IPC_PRIVATE: key_t = 0;
+pub(super) const IPC_PRIVATE: key_t = 0;
 
 // For cmd in shmctl()
-const IPC_RMID: CmdId = 0;
-const IPC_SET: CmdId = 1;
-const IPC_STAT: CmdId = 2;
+pub(super) const IPC_RMID: CmdId = 0;
+pub(super) const IPC_SET: CmdId = 1;
+pub(super) const IPC_STAT: CmdId = 2;
 const IPC_INFO: CmdId = 3;
 const SHM_LOCK: CmdId = 11;
 const SHM_UNLOCK: CmdId = 12;
@@ -38,18 +38,18 @@ const SHM_STAT_ANY: CmdId = 15;
 #[allow(non_camel_case_types)]
 #[derive(Debug)]
 #[repr(C)]
-struct ipc_perm_t {
-    key: key_t,
-    uid: uid_t,
-    gid: gid_t,
-    cuid: uid_t,
-    cgid: gid_t,
-    mode: u16,
-    pad1: u16,
-    seq: u16,
-    pad2: u16,
-    unused1: u64,
-    unused2: u64,
+pub(super) struct ipc_perm_t {
+    pub(super) key: key_t,
+    pub(super) uid: uid_t,
+    pub(super) gid: gid_t,
+    pub(super) cuid: uid_t,
+    pub(super) cgid: gid_t,
+    pub(super) mode: u16,
+    pub(super) pad1: u16,
+    pub(super) seq: u16,
+    pub(super) pad2: u16,
+    pub(super) unused1: u64,
+    pub(super) unused2: u64,
 }
 
 #[allow(non_camel_case_types)]
@@ -2,7 +2,19 @@ use super::*;
 
 use util::mem_util::from_user;
 
-use super::shm::{shmids_t, CmdId, ShmFlags, ShmId, SYSTEM_V_SHM_MANAGER};
+use super::mqueue::{mq_attr_t, AsMqueue, MqueueFile, MQUEUE_FS};
+use super::msg::{msqid_ds_t, MsgFlags, MsqId, SYSTEM_V_MSG_MANAGER};
+use super::sem::{
+    sembuf_t, semid_ds_t, SemId, GETALL, GETNCNT, GETPID, GETVAL, GETZCNT, IPC_64, SETALL, SETVAL,
+    SYSTEM_V_SEM_MANAGER,
+};
+use super::shm::{
+    shmids_t, CmdId, ShmFlags, ShmId, IPC_RMID, IPC_SET, IPC_STAT, SYSTEM_V_SHM_MANAGER,
+};
+use crate::fs::{AccessMode, CreationFlags, FileMode, StatusFlags};
+use crate::signal::{sigevent_t, SIGEV_NONE, SIGEV_SIGNAL};
+use crate::time::{do_clock_gettime, timespec_t, ClockId};
+use std::time::Duration;
 
 pub fn do_shmget(key: key_t, size: size_t, shmflg: i32) -> Result<isize> {
     let shmflg =
@@ -34,3 +46,323 @@ pub fn do_shmctl(shmid: i32, cmd: i32, buf_u: *mut shmids_t) -> Result<isize> {
     SYSTEM_V_SHM_MANAGER.do_shmctl(shmid as ShmId, cmd as CmdId, buf)?;
     Ok(0)
 }
+
+pub fn do_semget(key: key_t, nsems: i32, semflg: i32) -> Result<isize> {
+    let semid = SYSTEM_V_SEM_MANAGER.do_semget(key, nsems, semflg)?;
+    Ok(semid as isize)
+}
+
+pub fn do_semop(semid: i32, sops_u: *const sembuf_t, nsops: size_t) -> Result<isize> {
+    do_semtimedop(semid, sops_u, nsops, std::ptr::null())
+}
+
+pub fn do_semtimedop(
+    semid: i32,
+    sops_u: *const sembuf_t,
+    nsops: size_t,
+    timeout_u: *const timespec_t,
+) -> Result<isize> {
+    let sops = {
+        from_user::check_array(sops_u, nsops)?;
+        unsafe { std::slice::from_raw_parts(sops_u, nsops) }
+    };
+    let timeout = if !timeout_u.is_null() {
+        from_user::check_ptr(timeout_u)?;
+        let timeout = timespec_t::from_raw_ptr(timeout_u)?;
+        Some(timeout.as_duration())
+    } else {
+        None
+    };
+    SYSTEM_V_SEM_MANAGER.do_semtimedop(semid as SemId, sops, timeout)?;
+    Ok(0)
+}
+
+pub fn do_semctl(semid: i32, semnum: i32, cmd: i32, arg: u64) -> Result<isize> {
+    let semid = semid as SemId;
+    let cmd = cmd as CmdId & !IPC_64;
+    let ret = match cmd {
+        IPC_RMID => {
+            SYSTEM_V_SEM_MANAGER.do_semctl_rmid(semid)?;
+            0
+        }
+        IPC_STAT => {
+            let buf_u = arg as *mut semid_ds_t;
+            from_user::check_mut_ptr(buf_u)?;
+            let semid_ds = SYSTEM_V_SEM_MANAGER.do_semctl_stat(semid)?;
+            unsafe {
+                *buf_u = semid_ds;
+            }
+            0
+        }
+        IPC_SET => {
+            let buf_u = arg as *const semid_ds_t;
+            from_user::check_ptr(buf_u)?;
+            SYSTEM_V_SEM_MANAGER.do_semctl_set(semid, unsafe { &*buf_u })?;
+            0
+        }
+        GETVAL => SYSTEM_V_SEM_MANAGER.do_semctl_getval(semid, semnum)? as isize,
+        GETPID => SYSTEM_V_SEM_MANAGER.do_semctl_getpid(semid, semnum)? as isize,
+        GETNCNT => SYSTEM_V_SEM_MANAGER.do_semctl_getncnt(semid, semnum)? as isize,
+        GETZCNT => SYSTEM_V_SEM_MANAGER.do_semctl_getzcnt(semid, semnum)? as isize,
+        GETALL => {
+            let vals = SYSTEM_V_SEM_MANAGER.do_semctl_getall(semid)?;
+            let array_u = arg as *mut u16;
+            from_user::check_mut_array(array_u, vals.len())?;
+            let array = unsafe { std::slice::from_raw_parts_mut(array_u, vals.len()) };
+            array.copy_from_slice(&vals);
+            0
+        }
+        SETVAL => {
+            // The value is passed in the int member of union semun
+            let val = arg as i32;
+            SYSTEM_V_SEM_MANAGER.do_semctl_setval(semid, semnum, val)?;
+            0
+        }
+        SETALL => {
+            let nsems = SYSTEM_V_SEM_MANAGER.nsems(semid)?;
+            let array_u = arg as *const u16;
+            from_user::check_array(array_u, nsems)?;
+            let array = unsafe { std::slice::from_raw_parts(array_u, nsems) };
+            SYSTEM_V_SEM_MANAGER.do_semctl_setall(semid, array)?;
+            0
+        }
+        _ => return_errno!(EINVAL, "unimplemented cmd"),
+    };
+    Ok(ret)
+}
+
+pub fn do_msgget(key: key_t, msgflg: i32) -> Result<isize> {
+    let msqid = SYSTEM_V_MSG_MANAGER.do_msgget(key, msgflg)?;
+    Ok(msqid as isize)
+}
+
+pub fn do_msgsnd(msqid: i32, msgp: *const u8, msgsz: size_t, msgflg: i32) -> Result<isize> {
+    if (msgsz as isize) < 0 {
+        return_errno!(EINVAL, "invalid msgsz");
+    }
+    let flags = MsgFlags::from_bits_truncate(msgflg);
+    // The message buffer starts with a long mtype, followed by the message text
+    let (mtype, mtext) = {
+        let mtype_len = std::mem::size_of::<i64>();
+        from_user::check_array(msgp, mtype_len + msgsz)?;
+        let mtype = unsafe { std::ptr::read_unaligned(msgp as *const i64) };
+        let mtext = unsafe { std::slice::from_raw_parts(msgp.add(mtype_len), msgsz) };
+        (mtype, mtext)
+    };
+    SYSTEM_V_MSG_MANAGER.do_msgsnd(msqid as MsqId, mtype, mtext, flags)?;
+    Ok(0)
+}
+
+pub fn do_msgrcv(
+    msqid: i32,
+    msgp: *mut u8,
+    msgsz: size_t,
+    msgtyp: i64,
+    msgflg: i32,
+) -> Result<isize> {
+    if (msgsz as isize) < 0 {
+        return_errno!(EINVAL, "invalid msgsz");
+    }
+    let flags = MsgFlags::from_bits_truncate(msgflg);
+    let mtype_len = std::mem::size_of::<i64>();
+    // Check the buffer before a message is dequeued, so that no message is lost
+    from_user::check_mut_array(msgp, mtype_len + msgsz)?;
+
+    let (mtype, mtext) = SYSTEM_V_MSG_MANAGER.do_msgrcv(msqid as MsqId, msgsz, msgtyp, flags)?;
+    unsafe {
+        std::ptr::write_unaligned(msgp as *mut i64, mtype);
+        let buf = std::slice::from_raw_parts_mut(msgp.add(mtype_len), mtext.len());
+        buf.copy_from_slice(&mtext);
+    }
+    Ok(mtext.len() as isize)
+}
+
+pub fn do_msgctl(msqid: i32, cmd: i32, buf_u: *mut msqid_ds_t) -> Result<isize> {
+    let msqid = msqid as MsqId;
+    let cmd = cmd as CmdId & !IPC_64;
+    match cmd {
+        IPC_RMID => SYSTEM_V_MSG_MANAGER.do_msgctl_rmid(msqid)?,
+        IPC_STAT => {
+            from_user::check_mut_ptr(buf_u)?;
+            let msqid_ds = SYSTEM_V_MSG_MANAGER.do_msgctl_stat(msqid)?;
+            unsafe {
+                *buf_u = msqid_ds;
+            }
+        }
+        IPC_SET => {
+            from_user::check_ptr(buf_u)?;
+            SYSTEM_V_MSG_MANAGER.do_msgctl_set(msqid, unsafe { &*buf_u })?;
+        }
+        _ => return_errno!(EINVAL, "unimplemented cmd"),
+    }
+    Ok(0)
+}
+
+pub fn do_mq_open(
+    name: *const i8,
+    oflag: u32,
+    mode: u16,
+    attr_u: *const mq_attr_t,
+) -> Result<isize> {
+    let name = from_user::clone_cstring_safely(name)?
+        .to_string_lossy()
+        .into_owned();
+    let access_mode = AccessMode::from_u32(oflag)?;
+    let creation_flags = CreationFlags::from_bits_truncate(oflag);
+    let status_flags = StatusFlags::from_bits_truncate(oflag) & StatusFlags::O_NONBLOCK;
+    let attr = if creation_flags.can_create() && !attr_u.is_null() {
+        from_user::check_ptr(attr_u)?;
+        Some(unsafe { &*attr_u })
+    } else {
+        None
+    };
+    debug!(
+        "mq_open: name: {:?}, oflag: {:#o}, mode: {:#o}, attr: {:?}",
+        name, oflag, mode, attr
+    );
+
+    let current = current!();
+    let mode = FileMode::from_bits_truncate(mode) & !current.process().umask();
+    let mq = MQUEUE_FS.open(&name, access_mode, creation_flags, mode, attr)?;
+
+    let file_ref: Arc<dyn File> = Arc::new(MqueueFile::new(mq, access_mode, status_flags));
+    let fd = current.add_file(file_ref, creation_flags.must_close_on_spawn());
+    Ok(fd as isize)
+}
+
+pub fn do_mq_unlink(name: *const i8) -> Result<isize> {
+    let name = from_user::clone_cstring_safely(name)?
+        .to_string_lossy()
+        .into_owned();
+    debug!("mq_unlink: name: {:?}", name);
+    MQUEUE_FS.unlink(&name)?;
+    Ok(0)
+}
+
+pub fn do_mq_timedsend(
+    mqdes: FileDesc,
+    msg_ptr: *const u8,
+    msg_len: size_t,
+    msg_prio: u32,
+    abs_timeout_u: *const timespec_t,
+) -> Result<isize> {
+    let msg = {
+        from_user::check_array(msg_ptr, msg_len)?;
+        unsafe { std::slice::from_raw_parts(msg_ptr, msg_len) }
+    };
+    let timeout = timeout_from_abs_timespec(abs_timeout_u)?;
+
+    let file_ref = current!().file(mqdes)?;
+    let mq_file = file_ref.as_mqueue()?;
+    if !mq_file.access_mode()?.writable() {
+        return_errno!(EBADF, "the message queue is not opened for writing");
+    }
+    mq_file
+        .mq()
+        .send(msg, msg_prio, mq_file.is_nonblocking(), timeout)?;
+    Ok(0)
+}
+
+pub fn do_mq_timedreceive(
+    mqdes: FileDesc,
+    msg_ptr: *mut u8,
+    msg_len: size_t,
+    msg_prio_u: *mut u32,
+    abs_timeout_u: *const timespec_t,
+) -> Result<isize> {
+    // Check the buffers before a message is dequeued, so that no message is lost
+    from_user::check_mut_array(msg_ptr, msg_len)?;
+    if !msg_prio_u.is_null() {
+        from_user::check_mut_ptr(msg_prio_u)?;
+    }
+    let timeout = timeout_from_abs_timespec(abs_timeout_u)?;
+
+    let file_ref = current!().file(mqdes)?;
+    let mq_file = file_ref.as_mqueue()?;
+    if !mq_file.access_mode()?.readable() {
+        return_errno!(EBADF, "the message queue is not opened for reading");
+    }
+    let (msg, prio) = mq_file
+        .mq()
+        .receive(msg_len, mq_file.is_nonblocking(), timeout)?;
+    unsafe {
+        let buf = std::slice::from_raw_parts_mut(msg_ptr, msg.len());
+        buf.copy_from_slice(&msg);
+        if !msg_prio_u.is_null() {
+            *msg_prio_u = prio;
+        }
+    }
+    Ok(msg.len() as isize)
+}
+
+// Convert an absolute timeout against CLOCK_REALTIME to a relative one
+fn timeout_from_abs_timespec(abs_timeout_u: *const timespec_t) -> Result<Option<Duration>> {
+    if abs_timeout_u.is_null() {
+        return Ok(None);
+    }
+    from_user::check_ptr(abs_timeout_u)?;
+    let abs_timeout = timespec_t::from_raw_ptr(abs_timeout_u)?.as_duration();
+    let now = do_clock_gettime(ClockId::CLOCK_REALTIME)?.as_duration();
+    Ok(Some(abs_timeout.checked_sub(now).unwrap_or_default()))
+}
+
+pub fn do_mq_notify(mqdes: FileDesc, sevp_u: *const sigevent_t) -> Result<isize> {
+    let file_ref = current!().file(mqdes)?;
+    let mq = file_ref.as_mqueue()?.mq();
+    if sevp_u.is_null() {
+        mq.unregister_notification()?;
+        return Ok(0);
+    }
+
+    from_user::check_ptr(sevp_u)?;
+    let sevp = unsafe { &*sevp_u };
+    debug!("mq_notify: mqdes: {}, sevp: {:?}", mqdes, sevp);
+    let signal = match sevp.sigev_notify {
+        SIGEV_NONE => None,
+        SIGEV_SIGNAL => {
+            let signum = sevp.signum()?;
+            Some((signum, sevp.sigev_value))
+        }
+        // SIGEV_THREAD is implemented by libc with netlink sockets
+        _ => return_errno!(EINVAL, "unsupported notification method"),
+    };
+    mq.register_notification(signal)?;
+    Ok(0)
+}
+
+pub fn do_mq_getsetattr(
+    mqdes: FileDesc,
+    new_attr_u: *const mq_attr_t,
+    old_attr_u: *mut mq_attr_t,
+) -> Result<isize> {
+    let new_attr = if !new_attr_u.is_null() {
+        from_user::check_ptr(new_attr_u)?;
+        Some(unsafe { &*new_attr_u })
+    } else {
+        None
+    };
+    if !old_attr_u.is_null() {
+        from_user::check_mut_ptr(old_attr_u)?;
+    }
+
+    let file_ref = current!().file(mqdes)?;
+    let mq_file = file_ref.as_mqueue()?;
+    let status_flags = mq_file.status_flags()?;
+    if !old_attr_u.is_null() {
+        let mut old_attr = mq_file.mq().attr();
+        old_attr.mq_flags = (status_flags & StatusFlags::O_NONBLOCK).bits() as i64;
+        unsafe {
+            *old_attr_u = old_attr;
+        }
+    }
+    // Only O_NONBLOCK can be changed, and the other attributes are ignored
+    if let Some(new_attr) = new_attr {
+        let mut new_status_flags = status_flags;
+        new_status_flags.set(
+            StatusFlags::O_NONBLOCK,
+            new_attr.mq_flags & StatusFlags::O_NONBLOCK.bits() as i64 != 0,
+        );
+        mq_file.set_status_flags(new_status_flags)?;
+    }
+    Ok(0)
+}
@@ -5,7 +5,9 @@ mod coverage;
 mod random;
 mod rlimit;
 mod sysinfo;
-mod uname;
+
+pub mod sysctl;
+pub mod uname;
 
 pub use self::random::{do_getrandom, get_random, RandFlags};
 pub use self::rlimit::{do_prlimit, resource_t, rlimit_t, ResourceLimits};
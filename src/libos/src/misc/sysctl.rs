@@ -0,0 +1,133 @@
+//! The kernel parameters, which are shown and tuned through the files under /proc/sys.
+//!
+//! Most of the parameters are read-only, and they are provided because some programs
+//! read them at startup. The writable ones take effect at runtime.
+
+use super::uname;
+use super::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A kernel parameter.
+pub struct Sysctl {
+    /// The path under /proc/sys, e.g., "kernel/pid_max".
+    pub path: &'static str,
+    get: fn() -> String,
+    set: Option<fn(&str) -> Result<()>>,
+}
+
+impl Sysctl {
+    pub fn get(&self) -> String {
+        (self.get)()
+    }
+
+    /// Set the parameter with the value, which is trimmed like Linux.
+    pub fn set(&self, value: &str) -> Result<()> {
+        let set = self
+            .set
+            .ok_or_else(|| errno!(EPERM, "the parameter is read-only"))?;
+        set(value.trim())
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.set.is_some()
+    }
+}
+
+/// All the kernel parameters.
+pub static SYSCTLS: &[Sysctl] = &[
+    Sysctl {
+        path: "kernel/ostype",
+        get: || uname::sysname().to_owned(),
+        set: None,
+    },
+    Sysctl {
+        path: "kernel/osrelease",
+        get: || uname::release().to_owned(),
+        set: None,
+    },
+    Sysctl {
+        path: "kernel/version",
+        get: || uname::version().to_owned(),
+        set: None,
+    },
+    Sysctl {
+        path: "kernel/hostname",
+        get: uname::nodename,
+        set: Some(set_hostname),
+    },
+    Sysctl {
+        path: "kernel/pid_max",
+        get: || pid_max().to_string(),
+        set: Some(set_pid_max),
+    },
+    Sysctl {
+        path: "vm/overcommit_memory",
+        // The memory is always committed on demand, like the heuristic overcommit
+        get: || String::from("0"),
+        set: None,
+    },
+    Sysctl {
+        path: "vm/max_map_count",
+        get: || String::from("65530"),
+        set: None,
+    },
+    Sysctl {
+        path: "net/core/somaxconn",
+        get: || somaxconn().to_string(),
+        set: Some(set_somaxconn),
+    },
+];
+
+// Unlike Linux, whose default pid_max is 32768 and limit is 2^22 on 64-bit, the
+// PIDs are allocated in the whole range of u32 by default, as they always have been
+const PID_MAX_DEFAULT: u32 = u32::MAX;
+// The PIDs must be greater than PID_MAX_MIN, since some of them are reserved on Linux
+const PID_MAX_MIN: u32 = 301;
+// The same with Linux
+const SOMAXCONN_DEFAULT: u32 = 4096;
+
+static PID_MAX: AtomicU32 = AtomicU32::new(PID_MAX_DEFAULT);
+static SOMAXCONN: AtomicU32 = AtomicU32::new(SOMAXCONN_DEFAULT);
+
+/// The maximum value of PIDs (exclusive), i.e., kernel.pid_max.
+pub fn pid_max() -> u32 {
+    PID_MAX.load(Ordering::Relaxed)
+}
+
+/// The maximum backlog of the listening sockets, i.e., net.core.somaxconn.
+pub fn somaxconn() -> u32 {
+    SOMAXCONN.load(Ordering::Relaxed)
+}
+
+fn set_pid_max(value: &str) -> Result<()> {
+    let pid_max = parse_u32(value)?;
+    if pid_max < PID_MAX_MIN {
+        return_errno!(EINVAL, "pid_max is out of range");
+    }
+    PID_MAX.store(pid_max, Ordering::Relaxed);
+    Ok(())
+}
+
+fn set_somaxconn(value: &str) -> Result<()> {
+    let somaxconn = parse_u32(value)?;
+    if somaxconn > i32::max_value() as u32 {
+        return_errno!(EINVAL, "somaxconn is out of range");
+    }
+    SOMAXCONN.store(somaxconn, Ordering::Relaxed);
+    Ok(())
+}
+
+fn set_hostname(value: &str) -> Result<()> {
+    // The length is limited by the field in `utsname_t`
+    if value.len() > 64 || value.contains('\0') {
+        return_errno!(EINVAL, "invalid hostname");
+    }
+    uname::init_nodename(value);
+    Ok(())
+}
+
+fn parse_u32(value: &str) -> Result<u32> {
+    value
+        .parse::<u32>()
+        .map_err(|_| errno!(EINVAL, "not a valid number"))
+}
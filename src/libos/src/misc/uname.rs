@@ -48,6 +48,22 @@ fn copy_from_cstr_to_u8_array(src: &CStr, dst: &mut [u8]) {
     dst[len] = 0;
 }
 
+pub fn sysname() -> &'static str {
+    SYSNAME.to_str().unwrap()
+}
+
+pub fn release() -> &'static str {
+    RELEASE.to_str().unwrap()
+}
+
+pub fn version() -> &'static str {
+    VERSION.to_str().unwrap()
+}
+
+pub fn nodename() -> String {
+    NODENAME.read().unwrap().to_string_lossy().into_owned()
+}
+
 pub fn init_nodename(nodename_str: &str) {
     let nodename_cstr = CString::new(nodename_str).unwrap();
     let mut nodename = NODENAME.write().unwrap();
@@ -293,8 +293,8 @@ impl EpollFile {
             let weak_observer = self.weak_self.clone() as Weak<dyn Observer<_>>;
             notifier.unregister(&weak_observer);
 
-            if ep_entry.file.host_fd().is_some() {
-                self.host_file_epoller.del_file(&ep_entry.file);
+            if let Some(host_fd) = ep_entry.host_fd {
+                self.host_file_epoller.del_file(host_fd);
             }
         }
         Ok(())
@@ -510,6 +510,9 @@ impl AsEpollFile for FileRef {
 struct EpollEntry {
     fd: FileDesc,
     file: FileRef,
+    // The host fd of the file when it is added, which is kept even if the file is taken
+    // over by a file in the LibOS later
+    host_fd: Option<FileDesc>,
     inner: SgxMutex<EpollEntryInner>,
     // Whether the entry is in the ready list
     is_ready: AtomicBool,
@@ -522,9 +525,11 @@ impl EpollEntry {
         let is_ready = Default::default();
         let is_deleted = Default::default();
         let inner = SgxMutex::new(EpollEntryInner { event, flags });
+        let host_fd = file.host_fd().map(|host_fd| host_fd.to_raw());
         Self {
             fd,
             file,
+            host_fd,
             inner,
             is_ready,
             is_deleted,
@@ -7,16 +7,19 @@ pub use self::io_multiplexing::{
     PollEvent, PollEventFlags, PollFd, THREAD_NOTIFIERS,
 };
 pub use self::socket::{
-    mmsghdr, socketpair, unix_socket, Addr, AnyAddr, AsUnixSocket, Domain, GetAcceptConnCmd,
-    GetDomainCmd, GetErrorCmd, GetOutputAsBytes, GetPeerNameCmd, GetRecvBufSizeCmd,
+    is_loopback_addr, mmsghdr, socketpair, unix_gc, unix_socket, Addr, AnyAddr, AsInetSocket,
+    AsLoopbackSocket, AsNetlinkSocket, AsUnixDatagram, AsUnixSocket, Domain, GetAcceptConnCmd,
+    GetDomainCmd, GetErrorCmd, GetOutputAsBytes, GetPassCredCmd, GetPeerNameCmd, GetRecvBufSizeCmd,
     GetRecvTimeoutCmd, GetSendBufSizeCmd, GetSendTimeoutCmd, GetSockOptRawCmd, GetTypeCmd,
-    HostSocket, HostSocketType, Iovs, IovsMut, RecvFlags, SendFlags, SetRecvBufSizeCmd,
-    SetRecvTimeoutCmd, SetSendBufSizeCmd, SetSendTimeoutCmd, SetSockOptRawCmd, Shutdown,
-    SliceAsLibcIovec, SockAddr, SockOptName, SocketFile, SocketType, UnixAddr, UringSocketType,
+    HostSocket, HostSocketType, InetSocket, Iovs, IovsMut, LoopbackSocket, NetlinkSocket,
+    RecvFlags, SendFlags, SetPassCredCmd, SetRecvBufSizeCmd, SetRecvTimeoutCmd, SetSendBufSizeCmd,
+    SetSendTimeoutCmd, SetSockOptRawCmd, Shutdown, SliceAsLibcIovec, SockAddr, SockOptName,
+    SocketFile, SocketType, UnixAddr, UringSocketType,
 };
 pub use self::syscalls::*;
 
 mod io_multiplexing;
+mod policy;
 mod socket;
 mod syscalls;
 
@@ -0,0 +1,261 @@
+//! The network policy given by Occlum.json, which restricts the addresses that the app
+//! may connect, bind, send to or accept connections from, so that a compromised app can
+//! not talk to arbitrary hosts. It is enforced for all kinds of sockets before the
+//! requests reach the sockets, and the denied attempts are logged for audit. The
+//! datagrams received from the denied sources are dropped after they are received.
+//! The addresses of the network interfaces that the app can't use at all are hidden from
+//! the netlink dumps.
+
+use super::*;
+use crate::config::{
+    ConfigCidr, ConfigNetwork, ConfigNetworkAction, ConfigNetworkProtocol, ConfigNetworkRule,
+    LIBOS_CONFIG,
+};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+pub use crate::config::ConfigNetworkDirection as Direction;
+
+/// Check whether the socket may talk to the address in the direction. The operation is
+/// only used for the audit log.
+pub fn check_network_policy(
+    file_ref: &FileRef,
+    direction: Direction,
+    addr: &AnyAddr,
+    op: &str,
+) -> Result<()> {
+    let policy = &LIBOS_CONFIG.network;
+    // Skip the querying of the socket if the policy allows everything
+    if policy.rules.is_empty() && policy.default_action == ConfigNetworkAction::Allow {
+        return Ok(());
+    }
+
+    let target = match Target::from_addr(addr)? {
+        Some(target) => target,
+        // Unnamed or unspecified addresses are not restricted
+        None => return Ok(()),
+    };
+    let protocol = protocol_of(file_ref, &target)?;
+    let rule = policy
+        .rules
+        .iter()
+        .find(|rule| is_rule_matched(rule, direction, protocol, &target));
+    let action = match rule {
+        Some(rule) => rule.action,
+        None => policy.default_action,
+    };
+    if action == ConfigNetworkAction::Allow {
+        return Ok(());
+    }
+
+    // The audit log is kept by release enclaves, which only keep the error logs
+    let pid = current!().process().pid();
+    match rule {
+        Some(rule) => error!(
+            "network policy: {} with {:?} by process {} is denied by rule {:?}",
+            op, target, pid, rule
+        ),
+        None => error!(
+            "network policy: {} with {:?} by process {} is denied by default",
+            op, target, pid
+        ),
+    }
+    return_errno!(EPERM, "the address is denied by the network policy");
+}
+
+/// Check whether the socket may receive a datagram from the source address. A denied
+/// datagram is logged, and should be dropped by the caller. The data of stream sockets
+/// is always allowed, whose peers are checked when connecting or accepting.
+pub fn is_datagram_allowed(file_ref: &FileRef, src_addr: Option<&AnyAddr>, op: &str) -> bool {
+    let policy = &LIBOS_CONFIG.network;
+    let src_addr = match src_addr {
+        Some(src_addr) => src_addr,
+        None => return true,
+    };
+    if policy.rules.is_empty() && policy.default_action == ConfigNetworkAction::Allow {
+        return true;
+    }
+
+    let is_datagram = if file_ref.as_unix_datagram().is_ok() {
+        true
+    } else if file_ref.as_unix_socket().is_ok() {
+        false
+    } else {
+        matches!(socket_type_of(file_ref), Ok(Some(SocketType::DGRAM)))
+    };
+    !is_datagram || check_network_policy(file_ref, Direction::Ingress, src_addr, op).is_ok()
+}
+
+/// Check whether an address of the network interfaces may be shown to the app, e.g., by
+/// the netlink dumps. The address is hidden if the app can neither bind nor reach it,
+/// i.e., it is denied in both directions for all the protocols and ports.
+pub fn is_interface_addr_visible(ip: &IpAddr) -> bool {
+    let policy = &LIBOS_CONFIG.network;
+    if policy.rules.is_empty() && policy.default_action == ConfigNetworkAction::Allow {
+        return true;
+    }
+
+    let ip = match ip {
+        IpAddr::V4(ipv4_addr) => ipv4_addr.to_ipv6_mapped().octets(),
+        IpAddr::V6(ipv6_addr) => ipv6_addr.octets(),
+    };
+    is_ip_ever_allowed(policy, &ip, Direction::Ingress)
+        || is_ip_ever_allowed(policy, &ip, Direction::Egress)
+}
+
+// Whether the IP is allowed in the direction for some protocol and port, i.e., a rule
+// allowing it comes before any rule denying it for all the protocols and ports
+fn is_ip_ever_allowed(policy: &ConfigNetwork, ip: &[u8; 16], direction: Direction) -> bool {
+    for rule in policy.rules.iter() {
+        if rule.direction.is_some() && rule.direction != Some(direction) {
+            continue;
+        }
+        if rule.path.is_some() || rule.protocol == Some(ConfigNetworkProtocol::Unix) {
+            continue;
+        }
+        if !rule.cidr.map_or(true, |cidr| is_in_cidr(ip, &cidr)) {
+            continue;
+        }
+        match rule.action {
+            ConfigNetworkAction::Allow => return true,
+            ConfigNetworkAction::Deny if rule.protocol.is_none() && rule.ports.is_none() => {
+                return false
+            }
+            ConfigNetworkAction::Deny => {}
+        }
+    }
+    policy.default_action == ConfigNetworkAction::Allow
+}
+
+/// The address to check against the rules.
+#[derive(Debug)]
+enum Target {
+    // An IP address, where IPv4 addresses are mapped to IPv6 ones, and the port
+    Inet([u8; 16], u16),
+    // The path of a unix socket in the mount tree, or `@name` for an abstract address
+    Unix(String),
+}
+
+impl Target {
+    fn from_addr(addr: &AnyAddr) -> Result<Option<Self>> {
+        Ok(match addr {
+            AnyAddr::Ipv4(ipv4_addr) => {
+                let [a, b, c, d] = *ipv4_addr.ip().octets();
+                let ip = std::net::Ipv4Addr::new(a, b, c, d).to_ipv6_mapped();
+                Some(Target::Inet(ip.octets(), ipv4_addr.port()))
+            }
+            AnyAddr::Ipv6(ipv6_addr) => {
+                Some(Target::Inet(ipv6_addr.ip().octets(), ipv6_addr.port()))
+            }
+            // The path is resolved, so that a path like `//a`, `/./a`, `/b/../a`, a symlink
+            // to `/a` or `/a` in a chroot jail can't bypass the rules of `/a`
+            AnyAddr::Unix(UnixAddr::File(_, unix_path)) => {
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                Some(Target::Unix(fs.resolve_global_path(&unix_path.absolute())?))
+            }
+            AnyAddr::Unix(UnixAddr::Abstract(name)) => Some(Target::Unix(format!("@{}", name))),
+            AnyAddr::Unix(UnixAddr::Unnamed) | AnyAddr::Unspec => None,
+            // The addresses of host sockets
+            AnyAddr::Raw(raw_addr) => {
+                let (c_addr, c_addr_len) = raw_addr.to_c_storage();
+                match AnyAddr::from_c_storage(&c_addr, c_addr_len) {
+                    Ok(AnyAddr::Raw(_)) | Err(_) => None,
+                    Ok(addr) => Self::from_addr(&addr)?,
+                }
+            }
+        })
+    }
+}
+
+// The protocol of the socket, or `None` for the protocols that rules can't specify, e.g.,
+// raw sockets, which are only matched by the rules of any protocol
+fn protocol_of(file_ref: &FileRef, target: &Target) -> Result<Option<ConfigNetworkProtocol>> {
+    if let Target::Unix(_) = target {
+        return Ok(Some(ConfigNetworkProtocol::Unix));
+    }
+
+    Ok(match socket_type_of(file_ref)? {
+        Some(SocketType::STREAM) => Some(ConfigNetworkProtocol::Tcp),
+        Some(SocketType::DGRAM) => Some(ConfigNetworkProtocol::Udp),
+        _ => None,
+    })
+}
+
+// The type of an internet socket, or `None` for the other sockets
+fn socket_type_of(file_ref: &FileRef) -> Result<Option<SocketType>> {
+    let socket_type = if let Ok(uring_socket) = file_ref.as_uring_socket() {
+        uring_socket.get_type()
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.get_type()
+    } else if let Ok(host_socket) = file_ref.as_host_socket() {
+        let mut cmd = GetSockOptRawCmd::new(
+            libc::SOL_SOCKET,
+            SockOptName::SO_TYPE.into(),
+            std::mem::size_of::<i32>() as u32,
+        );
+        host_socket.ioctl(&mut cmd)?;
+        let socket_type = match cmd.output() {
+            Some(optval) if optval.len() == std::mem::size_of::<i32>() => {
+                i32::from_ne_bytes([optval[0], optval[1], optval[2], optval[3]])
+            }
+            _ => return_errno!(EINVAL, "invalid socket type"),
+        };
+        match SocketType::try_from(socket_type) {
+            Ok(socket_type) => socket_type,
+            Err(_) => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(socket_type))
+}
+
+fn is_rule_matched(
+    rule: &ConfigNetworkRule,
+    direction: Direction,
+    protocol: Option<ConfigNetworkProtocol>,
+    target: &Target,
+) -> bool {
+    if rule.direction.is_some() && rule.direction != Some(direction) {
+        return false;
+    }
+    if rule.protocol.is_some() && rule.protocol != protocol {
+        return false;
+    }
+
+    match target {
+        Target::Inet(ip, port) => {
+            if rule.path.is_some() {
+                return false;
+            }
+            let is_cidr_matched = rule.cidr.map_or(true, |cidr| is_in_cidr(ip, &cidr));
+            let is_port_matched = rule
+                .ports
+                .map_or(true, |(start, end)| start <= *port && *port <= end);
+            is_cidr_matched && is_port_matched
+        }
+        Target::Unix(path) => {
+            if rule.cidr.is_some() || rule.ports.is_some() {
+                return false;
+            }
+            match rule.path.as_ref() {
+                Some(rule_path) => match rule_path.strip_suffix('*') {
+                    Some(prefix) => path.starts_with(prefix),
+                    None => path == rule_path,
+                },
+                None => true,
+            }
+        }
+    }
+}
+
+fn is_in_cidr(ip: &[u8; 16], cidr: &ConfigCidr) -> bool {
+    let ip = u128::from_be_bytes(*ip);
+    let cidr_ip = u128::from_be_bytes(cidr.ip);
+    let mask = match cidr.prefix_len {
+        0 => 0,
+        prefix_len => u128::MAX << (128 - prefix_len),
+    };
+    ip & mask == cidr_ip & mask
+}
@@ -165,7 +165,8 @@ pub trait HostSocketType {
 
 impl HostSocketType for FileRef {
     fn as_host_socket(&self) -> Result<&HostSocket> {
-        self.as_any()
+        active_socket(self)
+            .as_any()
             .downcast_ref::<HostSocket>()
             .ok_or_else(|| errno!(EBADF, "not a host socket"))
     }
@@ -0,0 +1,293 @@
+use super::port_space::{
+    addr_of_domain, is_loopback_addr, is_same_addr, source_addr, with_zero_port, Binding,
+    PORT_SPACE,
+};
+use super::*;
+use fs::{IoEvents, IoNotifier};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// The max size of the payload of a UDP datagram
+const MAX_IPV4_PAYLOAD_LEN: usize = 65507;
+const MAX_IPV6_PAYLOAD_LEN: usize = 65527;
+
+/// SOCK_DGRAM loopback socket, i.e., a UDP socket that exchanges datagrams with the
+/// sockets in the same enclave.
+///
+/// Every socket owns a queue of received datagrams, which is registered in the port space
+/// once the socket is bound, so that the sending sockets push the datagrams to it directly.
+/// Like UDP, a datagram sent to a port that no socket is bound to is silently dropped.
+pub struct Datagram {
+    domain: Domain,
+    receiver: Arc<MsgQueue>,
+    inner: SgxMutex<Inner>,
+    nonblocking: AtomicBool,
+}
+
+struct Inner {
+    addr: Option<AnyAddr>,
+    // The default destination, from which the datagrams are received exclusively
+    peer_addr: Option<AnyAddr>,
+    is_write_shutdown: bool,
+}
+
+impl Datagram {
+    pub fn new(domain: Domain, nonblocking: bool) -> Self {
+        Self {
+            domain,
+            receiver: Arc::new(MsgQueue::new()),
+            inner: SgxMutex::new(Inner {
+                addr: None,
+                peer_addr: None,
+                is_write_shutdown: false,
+            }),
+            nonblocking: AtomicBool::new(nonblocking),
+        }
+    }
+
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
+    pub fn addr(&self) -> Option<AnyAddr> {
+        self.inner()
+            .addr
+            .as_ref()
+            .map(|addr| addr_of_domain(addr, self.domain))
+    }
+
+    pub fn peer_addr(&self) -> Result<AnyAddr> {
+        match &self.inner().peer_addr {
+            Some(peer_addr) => Ok(addr_of_domain(peer_addr, self.domain)),
+            None => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    pub fn bind(&self, addr: &AnyAddr) -> Result<()> {
+        let mut inner = self.inner();
+        if inner.addr.is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+        self.bind_locked(&mut inner, addr)
+    }
+
+    fn bind_locked(&self, inner: &mut Inner, addr: &AnyAddr) -> Result<()> {
+        let mut addr = addr.clone();
+        PORT_SPACE.bind(
+            SocketType::DGRAM,
+            &mut addr,
+            Binding::Datagram(self.receiver.clone()),
+        )?;
+        inner.addr = Some(addr);
+        Ok(())
+    }
+
+    /// Whether the address is reachable by a loopback datagram socket.
+    pub fn is_connectable(addr: &AnyAddr) -> bool {
+        PORT_SPACE.is_bound(SocketType::DGRAM, addr)
+    }
+
+    // Connecting a datagram socket only sets the default destination, which can be
+    // changed by connecting again, or reset by connecting to AF_UNSPEC address.
+    pub fn connect(&self, addr: &AnyAddr) -> Result<()> {
+        let mut inner = self.inner();
+        if addr.is_unspec() {
+            inner.peer_addr = None;
+            return Ok(());
+        }
+        if !is_loopback_addr(addr) {
+            return_errno!(ENETUNREACH, "only loopback addresses are reachable");
+        }
+
+        // An unbound socket is bound to an ephemeral port of the remote address
+        if inner.addr.is_none() {
+            self.bind_locked(&mut inner, &with_zero_port(addr))?;
+        }
+        inner.peer_addr = Some(addr.clone());
+        Ok(())
+    }
+
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        addr: Option<&AnyAddr>,
+        flags: SendFlags,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let unsupported_flags = flags - (SendFlags::MSG_DONTWAIT | SendFlags::MSG_NOSIGNAL);
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let data = bufs.concat();
+        let max_len = match self.domain {
+            Domain::INET6 => MAX_IPV6_PAYLOAD_LEN,
+            _ => MAX_IPV4_PAYLOAD_LEN,
+        };
+        if data.len() > max_len {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+
+        let (dest_addr, self_addr) = {
+            let mut inner = self.inner();
+            if inner.is_write_shutdown {
+                return_errno!(EPIPE, "the socket is shut down for writing");
+            }
+
+            let dest_addr = match (addr, &inner.peer_addr) {
+                (Some(addr), _) => addr.clone(),
+                (None, Some(peer_addr)) => peer_addr.clone(),
+                (None, None) => return_errno!(EDESTADDRREQ, "the destination address is required"),
+            };
+            if !is_loopback_addr(&dest_addr) {
+                return_errno!(ENETUNREACH, "only loopback addresses are reachable");
+            }
+
+            if inner.addr.is_none() {
+                self.bind_locked(&mut inner, &with_zero_port(&dest_addr))?;
+            }
+            let self_addr = source_addr(inner.addr.as_ref().unwrap(), &dest_addr);
+            (dest_addr, self_addr)
+        };
+
+        let data_len = data.len();
+        let dest_queue = match PORT_SPACE.get(SocketType::DGRAM, &dest_addr) {
+            Some(Binding::Datagram(queue)) => queue,
+            // The peer of a connected socket is unreachable
+            _ if addr.is_none() => {
+                return_errno!(ECONNREFUSED, "no socket is bound to the remote address")
+            }
+            _ => return Ok(data_len),
+        };
+        let msg = Message {
+            data,
+            addr: self_addr,
+            ancillary: Ancillary::default(),
+        };
+
+        let nonblocking = self.nonblocking() || flags.contains(SendFlags::MSG_DONTWAIT);
+        match dest_queue.push(msg, nonblocking, timeout) {
+            // The datagram to a closed socket is dropped
+            Err(e) if e.errno() == EPIPE => Ok(data_len),
+            res => res.map(|_| data_len),
+        }
+    }
+
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        timeout: Option<Duration>,
+    ) -> Result<(usize, Option<AnyAddr>, MsgFlags)> {
+        let unsupported_flags =
+            flags - (RecvFlags::MSG_PEEK | RecvFlags::MSG_TRUNC | RecvFlags::MSG_DONTWAIT);
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let peek = flags.contains(RecvFlags::MSG_PEEK);
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        let msg = loop {
+            let msg = match self.receiver.pop(peek, nonblocking, timeout)? {
+                Some(msg) => msg,
+                // Shut down for reading
+                None => return Ok((0, None, MsgFlags::empty())),
+            };
+
+            // A connected socket only receives the datagrams from its peer
+            let is_from_peer = match &self.inner().peer_addr {
+                Some(peer_addr) => is_same_addr(peer_addr, &msg.addr),
+                None => true,
+            };
+            if is_from_peer {
+                break msg;
+            }
+            if peek {
+                self.receiver.pop(false, true, None)?;
+            }
+        };
+
+        let mut msg_flags = MsgFlags::empty();
+        let mut copied_len = 0;
+        for buf in bufs.iter_mut() {
+            let len = buf.len().min(msg.data.len() - copied_len);
+            buf[..len].copy_from_slice(&msg.data[copied_len..copied_len + len]);
+            copied_len += len;
+        }
+        if copied_len < msg.data.len() {
+            msg_flags |= MsgFlags::MSG_TRUNC;
+        }
+
+        let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+            msg.data.len()
+        } else {
+            copied_len
+        };
+        let addr = addr_of_domain(&msg.addr, self.domain);
+        Ok((data_len, Some(addr), msg_flags))
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        let mut inner = self.inner();
+        if inner.peer_addr.is_none() {
+            return_errno!(ENOTCONN, "The socket is not connected.");
+        }
+
+        if how.should_shut_read() {
+            self.receiver.close(false);
+        }
+        if how.should_shut_write() {
+            inner.is_write_shutdown = true;
+        }
+        Ok(())
+    }
+
+    pub fn poll(&self) -> IoEvents {
+        let mut events = self.receiver.pollee().poll(IoEvents::all(), None);
+        if !self.inner().is_write_shutdown {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+
+    pub fn notifier(&self) -> &IoNotifier {
+        self.receiver.pollee().notifier()
+    }
+
+    /// The length of the next datagram, like Linux
+    pub fn bytes_to_read(&self) -> usize {
+        self.receiver.next_msg_len()
+    }
+
+    pub fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl Debug for Datagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Datagram")
+            .field("addr", &self.addr())
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for Datagram {
+    fn drop(&mut self) {
+        if let Some(addr) = self.inner().addr.as_ref() {
+            PORT_SPACE.unbind(SocketType::DGRAM, addr);
+        }
+        // The senders holding the queue drop the datagrams from now on
+        self.receiver.close(true);
+    }
+}
@@ -0,0 +1,447 @@
+use super::port_space::is_wildcard_addr;
+use super::socket_file::timeout_of;
+use super::*;
+use crate::events::Observer;
+use crate::fs::{AccessMode, File, HostFd, IoEvents, IoNotifier, IoctlCmd, StatusFlags};
+use crate::net::io_multiplexing::EventMonitorBuilder;
+use crate::net::socket::sockopt::SockOptName;
+use rcore_fs::vfs::Metadata;
+use spin::Once;
+use std::any::Any;
+use std::io::SeekFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+
+/// A TCP or UDP socket, which is taken over by a loopback socket once it is going to
+/// talk to a loopback address in the enclave.
+///
+/// The take-over happens inside the file, so that it is seen by all the fds referring to
+/// the file, e.g., the duplicated fds and the fds inherited by the child processes. The
+/// socket taken over is kept and bound on the host, which reserves the port of the
+/// loopback socket.
+///
+/// A socket bound to a wildcard address keeps talking through the host, along with a
+/// shadow loopback socket bound to the same address and port, through which it accepts
+/// the connections and receives the datagrams from the loopback sockets in the enclave.
+#[derive(Debug)]
+pub struct InetSocket {
+    socket: FileRef,
+    domain: Domain,
+    socket_type: SocketType,
+    loopback: Once<LoopbackSocket>,
+    shadow: Once<Shadow>,
+    // Serializes the take-overs
+    take_over_lock: SgxMutex<()>,
+}
+
+#[derive(Debug)]
+struct Shadow {
+    // Never blocks, so that the socket on the host can be waited for at the same time
+    socket: Arc<LoopbackSocket>,
+    // Relays the incoming events of the shadow socket to the socket on the host
+    _relay: Arc<EventRelay>,
+    // Whether the socket is connected to a remote address, so that the datagrams from
+    // the enclave are not received
+    is_detached: AtomicBool,
+}
+
+pub trait AsInetSocket {
+    fn as_inet_socket(&self) -> Result<&InetSocket>;
+}
+
+impl AsInetSocket for FileRef {
+    fn as_inet_socket(&self) -> Result<&InetSocket> {
+        self.as_any()
+            .downcast_ref::<InetSocket>()
+            .ok_or_else(|| errno!(ENOTSOCK, "not an internet socket"))
+    }
+}
+
+/// The socket that serves the operations on the file, which sees through an
+/// `InetSocket` to the socket that it is or is taken over by.
+pub fn active_socket(file_ref: &FileRef) -> &dyn File {
+    match file_ref.as_inet_socket() {
+        Ok(inet_socket) => inet_socket.active(),
+        Err(_) => file_ref.as_ref(),
+    }
+}
+
+impl InetSocket {
+    /// Whether a socket of the domain, type and protocol can be taken over.
+    pub fn is_supported(domain: Domain, socket_type: SocketType, protocol: i32) -> bool {
+        let is_inet = domain == Domain::INET || domain == Domain::INET6;
+        let is_tcp_or_udp = match socket_type {
+            SocketType::STREAM => protocol == 0 || protocol == libc::IPPROTO_TCP,
+            SocketType::DGRAM => protocol == 0 || protocol == libc::IPPROTO_UDP,
+            _ => false,
+        };
+        is_inet && is_tcp_or_udp
+    }
+
+    pub fn new(socket: FileRef, domain: Domain, socket_type: SocketType) -> Self {
+        Self {
+            socket,
+            domain,
+            socket_type,
+            loopback: Once::new(),
+            shadow: Once::new(),
+            take_over_lock: SgxMutex::new(()),
+        }
+    }
+
+    /// The loopback socket that takes over the socket, if any.
+    pub fn loopback_socket(&self) -> Option<&LoopbackSocket> {
+        self.loopback.get()
+    }
+
+    /// Take over the socket to bind a loopback address, or bind the socket to a wildcard
+    /// address along with a shadow socket. Returns whether the socket is bound.
+    pub fn take_over_to_bind(&self, addr: &AnyAddr) -> Result<bool> {
+        if is_wildcard_addr(addr) {
+            self.bind_with_shadow(addr)?;
+            return Ok(true);
+        }
+        if !is_loopback_addr(addr) {
+            return Ok(false);
+        }
+        self.take_over(addr, true)
+    }
+
+    /// Take over the socket to reach a loopback address, which is bound in the enclave
+    /// by a socket of one of the types. Returns whether the socket is taken over.
+    pub fn take_over_to_reach(&self, addr: &AnyAddr, socket_types: &[SocketType]) -> Result<bool> {
+        if !socket_types.contains(&self.socket_type)
+            || !LoopbackSocket::is_connectable(self.socket_type, addr)
+        {
+            return Ok(false);
+        }
+        self.take_over(addr, false)
+    }
+
+    // Take over the socket by a loopback socket bound to the address, or to an ephemeral
+    // port of the IP of the address if `to_bind` is false
+    fn take_over(&self, addr: &AnyAddr, to_bind: bool) -> Result<bool> {
+        let _guard = self.take_over_lock.lock().unwrap();
+        // A socket already bound outside the enclave is never taken over
+        if self.loopback.get().is_some() || self.host_addr().is_some() {
+            return Ok(false);
+        }
+
+        // Reserve the port on the host, so that it can't be taken by the host processes
+        let mut host_addr = addr.clone();
+        if !to_bind {
+            set_port(&mut host_addr, 0);
+        }
+        self.bind_on_host(&host_addr)?;
+        let reserved_addr = self
+            .host_addr()
+            .ok_or_else(|| errno!(EADDRNOTAVAIL, "no port is reserved on the host"))?;
+
+        let nonblocking = self
+            .socket
+            .status_flags()?
+            .contains(StatusFlags::O_NONBLOCK);
+        let loopback_socket = LoopbackSocket::new(self.domain, self.socket_type, nonblocking)?;
+        loopback_socket.copy_options_from(&self.socket);
+        loopback_socket.bind(&reserved_addr)?;
+        let loopback_socket = self.loopback.call_once(|| loopback_socket);
+
+        // The observers of the socket, e.g., the epoll files monitoring it, go on with
+        // observing the loopback socket
+        if let (Some(notifier), Some(loopback_notifier)) =
+            (self.socket.notifier(), loopback_socket.notifier())
+        {
+            notifier.move_observers_to(loopback_notifier);
+            loopback_notifier.broadcast(&loopback_socket.poll_new());
+        }
+        Ok(true)
+    }
+
+    // Bind the wildcard address on the host, and the same address and port in the
+    // enclave by the shadow socket. The conflicts of the ports are checked by the host.
+    fn bind_with_shadow(&self, addr: &AnyAddr) -> Result<()> {
+        let _guard = self.take_over_lock.lock().unwrap();
+        if self.loopback.get().is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+        self.bind_on_host(addr)?;
+        let bound_addr = match self.host_addr() {
+            Some(bound_addr) => bound_addr,
+            None => return Ok(()),
+        };
+
+        let shadow_socket = LoopbackSocket::new(self.domain, self.socket_type, true)?;
+        shadow_socket.copy_options_from(&self.socket);
+        if let Err(e) = shadow_socket.bind(&bound_addr) {
+            warn!("{:?} can't be reached in the enclave: {:?}", bound_addr, e);
+            return Ok(());
+        }
+        let shadow_socket = Arc::new(shadow_socket);
+        let relay = Arc::new(EventRelay {
+            socket: self.socket.clone(),
+        });
+        shadow_socket.notifier().unwrap().register(
+            Arc::downgrade(&relay) as Weak<dyn Observer<_>>,
+            Some(IoEvents::IN),
+            None,
+        );
+        self.shadow.call_once(|| Shadow {
+            socket: shadow_socket,
+            _relay: relay,
+            is_detached: AtomicBool::new(false),
+        });
+        Ok(())
+    }
+
+    /// Listen on the shadow socket as well, if any.
+    pub fn listen_shadow(&self, backlog: i32) -> Result<()> {
+        match self.shadow.get() {
+            Some(shadow) if shadow.socket.get_type() == SocketType::STREAM => {
+                // The accepted sockets inherit the options set after the bind
+                shadow.socket.copy_options_from(&self.socket);
+                shadow.socket.listen(backlog)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Connect the shadow datagram socket as well, if any. Connected to a remote address,
+    /// the socket no longer receives the datagrams from the enclave.
+    pub fn connect_shadow(&self, addr: &AnyAddr) -> Result<()> {
+        let shadow = match self.shadow.get() {
+            Some(shadow) if shadow.socket.get_type() == SocketType::DGRAM => shadow,
+            _ => return Ok(()),
+        };
+        let is_remote = !addr.is_unspec() && !is_loopback_addr(addr);
+        if is_remote {
+            shadow.socket.connect(&AnyAddr::Unspec)?;
+        } else {
+            shadow.socket.connect(addr)?;
+        }
+        shadow.is_detached.store(is_remote, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The shadow socket to send to the address, or to the connected peer if the address
+    /// is `None`, if the destination is bound by a loopback datagram socket.
+    pub fn shadow_to_send(&self, addr: Option<&AnyAddr>) -> Option<&LoopbackSocket> {
+        let shadow = self.shadow.get()?;
+        if shadow.socket.get_type() != SocketType::DGRAM {
+            return None;
+        }
+        let dest_addr = match addr {
+            Some(addr) => addr.clone(),
+            None => shadow.socket.peer_addr().ok()?,
+        };
+        if LoopbackSocket::is_connectable(SocketType::DGRAM, &dest_addr) {
+            Some(&*shadow.socket)
+        } else {
+            None
+        }
+    }
+
+    /// Accept a connection or receive data, from the shadow socket if it has any incoming,
+    /// or from the socket on the host otherwise. `op` is given the shadow socket, or `None`
+    /// for the socket on the host. Unless the socket is non-blocking or `dontwait` is
+    /// true, both sockets are waited for until one of them has any incoming.
+    pub fn recv_on_either<T>(
+        &self,
+        dontwait: bool,
+        mut op: impl FnMut(Option<&LoopbackSocket>) -> Result<T>,
+    ) -> Result<T> {
+        let shadow = match self.shadow.get() {
+            Some(shadow) if shadow.is_receiving() => &shadow.socket,
+            _ => return op(None),
+        };
+        match op(Some(shadow.as_ref())) {
+            Err(e) if e.errno() == EAGAIN => (),
+            res => return res,
+        }
+        let nonblocking = self
+            .socket
+            .status_flags()?
+            .contains(StatusFlags::O_NONBLOCK);
+        if dontwait || nonblocking {
+            return op(None);
+        }
+
+        let mut monitor = {
+            let mut builder = EventMonitorBuilder::new(2);
+            builder.add_file(self.socket.clone(), IoEvents::IN);
+            builder.add_file(shadow.clone(), IoEvents::IN);
+            builder.build()
+        };
+        let mut timeout = timeout_of(&self.socket, SockOptName::SO_RCVTIMEO_OLD)
+            .filter(|timeout| !timeout.is_zero());
+        loop {
+            monitor.reset_events();
+            match op(Some(shadow.as_ref())) {
+                Err(e) if e.errno() == EAGAIN => (),
+                res => return res,
+            }
+            if self
+                .socket
+                .poll_new()
+                .intersects(IoEvents::IN | IoEvents::ERR)
+            {
+                return op(None);
+            }
+
+            monitor
+                .wait_events(timeout.as_mut())
+                .map_err(|e| match e.errno() {
+                    ETIMEDOUT => errno!(EAGAIN, "nothing is incoming before timeout"),
+                    _ => e,
+                })?;
+        }
+    }
+
+    fn bind_on_host(&self, addr: &AnyAddr) -> Result<()> {
+        if let Ok(uring_socket) = self.socket.as_uring_socket() {
+            uring_socket.bind(addr)
+        } else if let Ok(host_socket) = self.socket.as_host_socket() {
+            host_socket.bind(&addr.to_raw())
+        } else {
+            return_errno!(ENOTSOCK, "not a socket on the host");
+        }
+    }
+
+    // The address bound on the host, or `None` if the socket is unbound
+    fn host_addr(&self) -> Option<AnyAddr> {
+        let addr = if let Ok(uring_socket) = self.socket.as_uring_socket() {
+            uring_socket.addr().ok()?
+        } else {
+            let raw_addr = self.socket.as_host_socket().ok()?.addr().ok()?;
+            let (c_addr, c_addr_len) = raw_addr.to_c_storage();
+            AnyAddr::from_c_storage(&c_addr, c_addr_len).ok()?
+        };
+        let port = match &addr {
+            AnyAddr::Ipv4(ipv4_addr) => ipv4_addr.port(),
+            AnyAddr::Ipv6(ipv6_addr) => ipv6_addr.port(),
+            _ => 0,
+        };
+        if port != 0 {
+            Some(addr)
+        } else {
+            None
+        }
+    }
+
+    fn active(&self) -> &dyn File {
+        match self.loopback.get() {
+            Some(loopback_socket) => loopback_socket,
+            None => self.socket.as_ref(),
+        }
+    }
+}
+
+fn set_port(addr: &mut AnyAddr, port: u16) {
+    match addr {
+        AnyAddr::Ipv4(ipv4_addr) => ipv4_addr.set_port(port),
+        AnyAddr::Ipv6(ipv6_addr) => ipv6_addr.set_port(port),
+        _ => (),
+    }
+}
+
+impl File for InetSocket {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.active().read(buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.active().write(buf)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        self.active().read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        self.active().write_at(offset, buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        self.active().readv(bufs)
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.active().writev(bufs)
+    }
+
+    fn seek(&self, pos: SeekFrom) -> Result<off_t> {
+        self.active().seek(pos)
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        self.active().metadata()
+    }
+
+    fn ioctl(&self, cmd: &mut dyn IoctlCmd) -> Result<()> {
+        self.active().ioctl(cmd)
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        self.active().access_mode()
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        self.active().status_flags()
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        self.active().set_status_flags(new_status_flags)
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        let events = self.active().poll_new();
+        match self.shadow.get() {
+            Some(shadow) if shadow.is_receiving() => {
+                events | (shadow.socket.poll_new() & IoEvents::IN)
+            }
+            _ => events,
+        }
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        self.active().notifier()
+    }
+
+    fn host_fd(&self) -> Option<&HostFd> {
+        self.active().host_fd()
+    }
+
+    fn update_host_events(&self, ready: &IoEvents, mask: &IoEvents, trigger_notifier: bool) {
+        // The host events are always of the socket on the host, even if it is taken over
+        self.socket
+            .update_host_events(ready, mask, trigger_notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Shadow {
+    // Whether the shadow socket accepts the connections or receives the datagrams
+    fn is_receiving(&self) -> bool {
+        match self.socket.get_type() {
+            SocketType::STREAM => self.socket.is_listening(),
+            _ => !self.is_detached.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// An observer of the shadow socket, which relays the events to the observers of the
+// socket on the host, e.g., the epoll files monitoring it
+#[derive(Debug)]
+struct EventRelay {
+    socket: FileRef,
+}
+
+impl Observer<IoEvents> for EventRelay {
+    fn on_event(&self, events: &IoEvents, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
+        if let Some(notifier) = self.socket.notifier() {
+            notifier.broadcast(events);
+        }
+    }
+}
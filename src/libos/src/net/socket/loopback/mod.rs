@@ -0,0 +1,22 @@
+//! In-enclave loopback TCP and UDP sockets.
+//!
+//! When the `enable_loopback_in_enclave` feature is on, the sockets in the same enclave
+//! talking over a loopback address, e.g., 127.0.0.1, exchange data through in-enclave
+//! channels like unix sockets, rather than through the host kernel. A TCP or UDP socket
+//! is taken over by a loopback socket once it is bound to a loopback address, or it
+//! connects to a loopback address bound in the enclave. A socket bound to a wildcard
+//! address, e.g., 0.0.0.0, is also reachable from the loopback sockets in the enclave.
+use super::unix::{
+    end_pair, Ancillary, Endpoint, Message, MsgQueue, RelayNotifier, DEFAULT_BUF_SIZE,
+};
+use super::*;
+
+mod datagram;
+mod inet_socket;
+mod port_space;
+mod socket_file;
+mod stream;
+
+pub use self::inet_socket::{active_socket, AsInetSocket, InetSocket};
+pub use self::port_space::is_loopback_addr;
+pub use self::socket_file::{AsLoopbackSocket, LoopbackSocket};
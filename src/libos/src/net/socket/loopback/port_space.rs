@@ -0,0 +1,271 @@
+use super::stream::Listener;
+use super::*;
+use std::collections::btree_map::BTreeMap;
+
+lazy_static! {
+    pub(super) static ref PORT_SPACE: PortSpace = PortSpace::new();
+}
+
+// The range of ephemeral ports, same with /proc/sys/net/ipv4/ip_local_port_range on most
+// Linux distributions
+const EPHEMERAL_PORT_START: u16 = 32768;
+const EPHEMERAL_PORT_END: u16 = 60999;
+
+/// The key of a loopback or wildcard address in the port space.
+///
+/// IPv4 addresses are stored as IPv4-mapped IPv6 addresses, so that an IPv6 socket can
+/// reach an IPv4 socket by `::ffff:127.0.0.1`, like a dual-stack Linux host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PortKey {
+    ip: [u8; 16],
+    port: u16,
+}
+
+impl PortKey {
+    pub fn from_addr(addr: &AnyAddr) -> Result<Self> {
+        let (ip, port) = match addr {
+            AnyAddr::Ipv4(ipv4_addr) => {
+                let mut ip = [0; 16];
+                ip[10] = 0xff;
+                ip[11] = 0xff;
+                ip[12..].copy_from_slice(ipv4_addr.ip().octets());
+                (ip, ipv4_addr.port())
+            }
+            AnyAddr::Ipv6(ipv6_addr) => (ipv6_addr.ip().octets(), ipv6_addr.port()),
+            _ => return_errno!(EAFNOSUPPORT, "not an internet address"),
+        };
+        Ok(Self { ip, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    fn with_port(&self, port: u16) -> Self {
+        Self { ip: self.ip, port }
+    }
+
+    fn is_wildcard(&self) -> bool {
+        self.ip == IPV6_WILDCARD_IP || self.ip == IPV4_WILDCARD_IP
+    }
+
+    // The keys to look up in order. A loopback address is reached by the sockets bound to
+    // the wildcard addresses of the same port, if none is bound to the address itself.
+    fn lookup_keys(&self) -> Vec<Self> {
+        let mut keys = vec![*self];
+        if self.is_wildcard() {
+            return keys;
+        }
+        if self.ip[..12] == IPV4_WILDCARD_IP[..12] {
+            keys.push(Self {
+                ip: IPV4_WILDCARD_IP,
+                port: self.port,
+            });
+        }
+        keys.push(Self {
+            ip: IPV6_WILDCARD_IP,
+            port: self.port,
+        });
+        keys
+    }
+}
+
+// `::` and `::ffff:0.0.0.0`, i.e., `0.0.0.0` in the port space
+const IPV6_WILDCARD_IP: [u8; 16] = [0; 16];
+const IPV4_WILDCARD_IP: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 0, 0];
+
+/// What is bound to a loopback address.
+#[derive(Clone)]
+pub(super) enum Binding {
+    // A stream socket is bound to the address, but it is not listening
+    Bound,
+    // A listening stream socket
+    Listener(Arc<Listener>),
+    // A datagram socket, which can be reached by its receiving queue
+    Datagram(Arc<MsgQueue>),
+}
+
+/// The ports of the loopback addresses that are bound by the sockets in the enclave.
+/// Stream and datagram sockets have separated port spaces, like TCP and UDP.
+pub struct PortSpace {
+    stream: SgxMutex<BTreeMap<PortKey, Binding>>,
+    datagram: SgxMutex<BTreeMap<PortKey, Binding>>,
+    next_ephemeral_port: SgxMutex<u16>,
+}
+
+impl PortSpace {
+    pub fn new() -> Self {
+        Self {
+            stream: SgxMutex::new(BTreeMap::new()),
+            datagram: SgxMutex::new(BTreeMap::new()),
+            next_ephemeral_port: SgxMutex::new(EPHEMERAL_PORT_START),
+        }
+    }
+
+    /// Bind the address. If the port is zero, an ephemeral port is allocated and filled
+    /// in the address.
+    pub(super) fn bind(
+        &self,
+        type_: SocketType,
+        addr: &mut AnyAddr,
+        binding: Binding,
+    ) -> Result<()> {
+        let key = PortKey::from_addr(addr)?;
+        let mut space = self.get_space(type_);
+        let key = if key.port() == 0 {
+            self.alloc_ephemeral_port(&space, &key)?
+        } else if space.contains_key(&key) {
+            return_errno!(EADDRINUSE, "the addr is already bound");
+        } else {
+            key
+        };
+
+        space.insert(key, binding);
+        set_port(addr, key.port());
+        Ok(())
+    }
+
+    fn alloc_ephemeral_port(
+        &self,
+        space: &BTreeMap<PortKey, Binding>,
+        key: &PortKey,
+    ) -> Result<PortKey> {
+        let mut next_port = self.next_ephemeral_port.lock().unwrap();
+        let num_ports = EPHEMERAL_PORT_END - EPHEMERAL_PORT_START + 1;
+        for _ in 0..num_ports {
+            let port = *next_port;
+            *next_port = if port == EPHEMERAL_PORT_END {
+                EPHEMERAL_PORT_START
+            } else {
+                port + 1
+            };
+
+            let key = key.with_port(port);
+            if !space.contains_key(&key) {
+                return Ok(key);
+            }
+        }
+        return_errno!(EADDRNOTAVAIL, "no ephemeral port is available");
+    }
+
+    // Turn a bound address into a listening one
+    pub(super) fn listen(&self, addr: &AnyAddr, listener: Arc<Listener>) -> Result<()> {
+        let key = PortKey::from_addr(addr)?;
+        let mut space = self.get_space(SocketType::STREAM);
+        match space.get(&key) {
+            Some(Binding::Bound) => {
+                space.insert(key, Binding::Listener(listener));
+                Ok(())
+            }
+            Some(_) => return_errno!(EINVAL, "the socket is already listened"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
+        }
+    }
+
+    /// Get what is bound to the address, or to the wildcard address of the same port if
+    /// the address is not bound.
+    pub(super) fn get(&self, type_: SocketType, addr: &AnyAddr) -> Option<Binding> {
+        let key = PortKey::from_addr(addr).ok()?;
+        let space = self.get_space(type_);
+        key.lookup_keys()
+            .iter()
+            .find_map(|key| space.get(key))
+            .cloned()
+    }
+
+    pub fn is_bound(&self, type_: SocketType, addr: &AnyAddr) -> bool {
+        self.get(type_, addr).is_some()
+    }
+
+    pub fn unbind(&self, type_: SocketType, addr: &AnyAddr) {
+        if let Ok(key) = PortKey::from_addr(addr) {
+            self.get_space(type_).remove(&key);
+        }
+    }
+
+    fn get_space(&self, type_: SocketType) -> SgxMutexGuard<'_, BTreeMap<PortKey, Binding>> {
+        match type_ {
+            SocketType::STREAM => self.stream.lock().unwrap(),
+            _ => self.datagram.lock().unwrap(),
+        }
+    }
+}
+
+/// Whether the address is a loopback address, i.e., 127.0.0.0/8, `::1` or an
+/// IPv4-mapped IPv6 address of the former.
+pub fn is_loopback_addr(addr: &AnyAddr) -> bool {
+    match addr {
+        AnyAddr::Ipv4(ipv4_addr) => ipv4_addr.ip().octets()[0] == 127,
+        AnyAddr::Ipv6(ipv6_addr) => {
+            let ip = ipv6_addr.ip();
+            let octets = ip.octets();
+            let is_v4_mapped =
+                octets[..10].iter().all(|b| *b == 0) && octets[10] == 0xff && octets[11] == 0xff;
+            ip.is_loopback() || (is_v4_mapped && octets[12] == 127)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the address is a wildcard address, i.e., `0.0.0.0`, `::` or `::ffff:0.0.0.0`.
+pub fn is_wildcard_addr(addr: &AnyAddr) -> bool {
+    match PortKey::from_addr(addr) {
+        Ok(key) => key.is_wildcard(),
+        Err(_) => false,
+    }
+}
+
+/// The source address of the data sent by a socket bound to the address. A socket bound
+/// to a wildcard address sends from the IP of the destination, i.e., a loopback address.
+pub fn source_addr(addr: &AnyAddr, dest_addr: &AnyAddr) -> AnyAddr {
+    match PortKey::from_addr(addr) {
+        Ok(key) if key.is_wildcard() => {
+            let mut source_addr = dest_addr.clone();
+            set_port(&mut source_addr, key.port());
+            source_addr
+        }
+        _ => addr.clone(),
+    }
+}
+
+/// Convert an address to the one of the given domain, so that a socket always sees
+/// the addresses of its own family. IPv4 addresses are mapped to IPv6 ones for IPv6
+/// sockets, and vice versa.
+pub fn addr_of_domain(addr: &AnyAddr, domain: Domain) -> AnyAddr {
+    match (addr, domain) {
+        (AnyAddr::Ipv4(ipv4_addr), Domain::INET6) => {
+            let [a, b, c, d] = *ipv4_addr.ip().octets();
+            let ip = std::net::Ipv4Addr::new(a, b, c, d).to_ipv6_mapped();
+            AnyAddr::Ipv6(Ipv6SocketAddr::new(ip, ipv4_addr.port(), 0, 0))
+        }
+        (AnyAddr::Ipv6(ipv6_addr), Domain::INET) => {
+            let octets = ipv6_addr.ip().octets();
+            let ip = Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]);
+            AnyAddr::Ipv4(Ipv4SocketAddr::new(ip, ipv6_addr.port()))
+        }
+        _ => addr.clone(),
+    }
+}
+
+/// Whether two addresses are the same loopback address and port.
+pub fn is_same_addr(addr: &AnyAddr, other: &AnyAddr) -> bool {
+    match (PortKey::from_addr(addr), PortKey::from_addr(other)) {
+        (Ok(key), Ok(other_key)) => key == other_key,
+        _ => false,
+    }
+}
+
+/// The same address with the port of zero, which is used to autobind an ephemeral port.
+pub fn with_zero_port(addr: &AnyAddr) -> AnyAddr {
+    let mut addr = addr.clone();
+    set_port(&mut addr, 0);
+    addr
+}
+
+fn set_port(addr: &mut AnyAddr, port: u16) {
+    match addr {
+        AnyAddr::Ipv4(ipv4_addr) => ipv4_addr.set_port(port),
+        AnyAddr::Ipv6(ipv6_addr) => ipv6_addr.set_port(port),
+        _ => {}
+    }
+}
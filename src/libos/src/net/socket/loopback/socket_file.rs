@@ -0,0 +1,578 @@
+use super::datagram::Datagram;
+use super::port_space::is_wildcard_addr;
+use super::stream::Stream;
+use super::*;
+use crate::fs::{
+    AccessMode, File, GetReadBufLen, IoEvents, IoNotifier, IoctlCmd, SetNonBlocking, StatusFlags,
+};
+use crate::net::socket::sockopt::{
+    timeout_to_timeval, timeval, timeval_to_timeout, AddrStorage, GetAcceptConnCmd, GetDomainCmd,
+    GetErrorCmd, GetPeerNameCmd, GetRecvBufSizeCmd, GetRecvTimeoutCmd, GetSendBufSizeCmd,
+    GetSendTimeoutCmd, GetSockOptRawCmd, GetTypeCmd, SetRecvBufSizeCmd, SetRecvTimeoutCmd,
+    SetSendBufSizeCmd, SetSendTimeoutCmd, SetSockOptRawCmd, SockOptName,
+};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+use std::collections::btree_map::BTreeMap;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// An INET or INET6 socket bound or connected to a loopback address in the enclave.
+///
+/// The data exchanged by loopback sockets never leaves the enclave. Such a socket takes
+/// over an `InetSocket` when it is bound to a loopback address, or connected to a
+/// loopback address that is bound by another loopback socket. Consequently, a loopback
+/// socket can neither reach nor be reached by the sockets outside the enclave, though its
+/// port is reserved on the host by the socket taken over.
+#[derive(Debug)]
+pub struct LoopbackSocket {
+    socket: AnySocket,
+    options: SgxMutex<SockOptions>,
+    // The pending error, which is reported by the next operation or SO_ERROR
+    errno: SgxMutex<Option<Errno>>,
+}
+
+#[derive(Debug)]
+enum AnySocket {
+    Stream(Stream),
+    Datagram(Datagram),
+}
+
+/// The socket options, which are only recorded since there is no host socket, except
+/// that the timeouts interrupt the blocking operations.
+#[derive(Debug, Clone, Default)]
+struct SockOptions {
+    raw: BTreeMap<(i32, i32), Vec<u8>>,
+    send_timeout: Option<Duration>,
+    recv_timeout: Option<Duration>,
+    send_buf_size: Option<usize>,
+    recv_buf_size: Option<usize>,
+}
+
+pub trait AsLoopbackSocket {
+    fn as_loopback_socket(&self) -> Result<&LoopbackSocket>;
+}
+
+impl AsLoopbackSocket for FileRef {
+    fn as_loopback_socket(&self) -> Result<&LoopbackSocket> {
+        active_socket(self)
+            .as_any()
+            .downcast_ref::<LoopbackSocket>()
+            .ok_or_else(|| errno!(ENOTSOCK, "not a loopback socket"))
+    }
+}
+
+impl LoopbackSocket {
+    pub fn new(domain: Domain, socket_type: SocketType, nonblocking: bool) -> Result<Self> {
+        if domain != Domain::INET && domain != Domain::INET6 {
+            return_errno!(EAFNOSUPPORT, "not an internet socket");
+        }
+        let socket = match socket_type {
+            SocketType::STREAM => AnySocket::Stream(Stream::new(domain, nonblocking)),
+            SocketType::DGRAM => AnySocket::Datagram(Datagram::new(domain, nonblocking)),
+            _ => return_errno!(ESOCKTNOSUPPORT, "socket type not supported"),
+        };
+        Ok(Self {
+            socket,
+            options: SgxMutex::new(SockOptions::default()),
+            errno: SgxMutex::new(None),
+        })
+    }
+
+    /// Copy the options of the socket to be taken over, which are set before the take-over.
+    pub(super) fn copy_options_from(&self, socket: &FileRef) {
+        let mut raw_options = COPIED_SOCKET_OPTIONS
+            .iter()
+            .map(|optname| (libc::SOL_SOCKET, (*optname).into()))
+            .collect::<Vec<(i32, i32)>>();
+        if self.get_type() == SocketType::STREAM {
+            raw_options.push((libc::IPPROTO_TCP, TCP_NODELAY));
+        }
+
+        let mut options = self.options();
+        for (level, optname) in raw_options {
+            let mut cmd = GetSockOptRawCmd::new(level, optname, std::mem::size_of::<i32>() as u32);
+            if socket.ioctl(&mut cmd).is_err() {
+                continue;
+            }
+            if let Some(optval) = cmd.output() {
+                options.raw.insert((level, optname), optval.to_vec());
+            }
+        }
+        options.recv_timeout = timeout_of(socket, SockOptName::SO_RCVTIMEO_OLD);
+        options.send_timeout = timeout_of(socket, SockOptName::SO_SNDTIMEO_OLD);
+    }
+
+    /// Whether the address is bound by a loopback socket of the type, which can be
+    /// connected to.
+    pub fn is_connectable(socket_type: SocketType, addr: &AnyAddr) -> bool {
+        is_loopback_addr(addr)
+            && match socket_type {
+                SocketType::STREAM => Stream::is_connectable(addr),
+                SocketType::DGRAM => Datagram::is_connectable(addr),
+                _ => false,
+            }
+    }
+
+    pub fn domain(&self) -> Domain {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.domain(),
+            AnySocket::Datagram(datagram) => datagram.domain(),
+        }
+    }
+
+    pub fn get_type(&self) -> SocketType {
+        match &self.socket {
+            AnySocket::Stream(_) => SocketType::STREAM,
+            AnySocket::Datagram(_) => SocketType::DGRAM,
+        }
+    }
+
+    pub fn bind(&self, addr: &AnyAddr) -> Result<()> {
+        self.check_addr(addr)?;
+        // A wildcard address is only bound by the shadow socket of an `InetSocket`
+        if !is_loopback_addr(addr) && !is_wildcard_addr(addr) {
+            return_errno!(EADDRNOTAVAIL, "only loopback addresses can be bound");
+        }
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.bind(addr),
+            AnySocket::Datagram(datagram) => datagram.bind(addr),
+        }
+    }
+
+    pub fn listen(&self, backlog: i32) -> Result<()> {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.listen(backlog),
+            AnySocket::Datagram(_) => {
+                return_errno!(EOPNOTSUPP, "The socket is not of a listen supported type")
+            }
+        }
+    }
+
+    pub fn connect(&self, addr: &AnyAddr) -> Result<()> {
+        match &self.socket {
+            AnySocket::Stream(stream) => {
+                self.check_addr(addr)?;
+                if let Some(errno) = self.errno() {
+                    return_errno!(errno, "the previous connection failed");
+                }
+                match stream.connect(addr) {
+                    // Like a non-blocking TCP connect, the refusal is reported later
+                    Err(e) if e.errno() == ECONNREFUSED && stream.nonblocking() => {
+                        self.set_errno(ECONNREFUSED);
+                        return_errno!(EINPROGRESS, "the connection is in progress");
+                    }
+                    res => res,
+                }
+            }
+            AnySocket::Datagram(datagram) => {
+                if !addr.is_unspec() {
+                    self.check_addr(addr)?;
+                }
+                datagram.connect(addr)
+            }
+        }
+    }
+
+    pub fn accept(&self, nonblocking: bool) -> Result<(Self, AnyAddr)> {
+        let (accepted_stream, peer_addr) = match &self.socket {
+            AnySocket::Stream(stream) => stream.accept(nonblocking, self.recv_timeout())?,
+            AnySocket::Datagram(_) => {
+                return_errno!(EOPNOTSUPP, "The socket is not of a accept supported type")
+            }
+        };
+        // The options are inherited from the listening socket
+        let accepted_socket = Self {
+            socket: AnySocket::Stream(accepted_stream),
+            options: SgxMutex::new(self.options().clone()),
+            errno: SgxMutex::new(None),
+        };
+        Ok((accepted_socket, peer_addr))
+    }
+
+    pub fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<AnyAddr>)> {
+        let (bytes_recv, addr_recv, _, _) = self.recvmsg(&mut [buf], flags, None)?;
+        Ok((bytes_recv, addr_recv))
+    }
+
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        _control: Option<&mut [u8]>,
+    ) -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
+        if let Some(errno) = self.errno() {
+            return_errno!(errno, "an error is pending on the socket");
+        }
+
+        // No control message is supported
+        let timeout = self.recv_timeout();
+        Ok(match &self.socket {
+            AnySocket::Stream(stream) => {
+                let bytes_recv = stream.recvmsg(bufs, flags, timeout)?;
+                (bytes_recv, None, MsgFlags::empty(), 0)
+            }
+            AnySocket::Datagram(datagram) => {
+                let (bytes_recv, addr_recv, msg_flags) = datagram.recvmsg(bufs, flags, timeout)?;
+                (bytes_recv, addr_recv, msg_flags, 0)
+            }
+        })
+    }
+
+    pub fn sendto(&self, buf: &[u8], addr: Option<AnyAddr>, flags: SendFlags) -> Result<usize> {
+        self.sendmsg(&[buf], addr, flags, None)
+    }
+
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        addr: Option<AnyAddr>,
+        flags: SendFlags,
+        control: Option<&[u8]>,
+    ) -> Result<usize> {
+        if control.is_some() {
+            warn!("control message is not supported");
+        }
+        if let Some(errno) = self.errno() {
+            return_errno!(errno, "an error is pending on the socket");
+        }
+
+        let timeout = self.send_timeout();
+        let res = match &self.socket {
+            // The destination address of a connected stream socket is ignored, like Linux
+            AnySocket::Stream(stream) => stream.sendmsg(bufs, flags, timeout),
+            AnySocket::Datagram(datagram) => {
+                if let Some(addr) = addr.as_ref() {
+                    self.check_addr(addr)?;
+                }
+                match datagram.sendmsg(bufs, addr.as_ref(), flags, timeout) {
+                    // Like UDP, the datagram is sent, and the unreachable peer is reported
+                    // by the next operation
+                    Err(e) if e.errno() == ECONNREFUSED => {
+                        self.set_errno(ECONNREFUSED);
+                        Ok(bufs.iter().map(|buf| buf.len()).sum())
+                    }
+                    res => res,
+                }
+            }
+        };
+        if res.has_errno(EPIPE) && !flags.contains(SendFlags::MSG_NOSIGNAL) {
+            crate::signal::do_tkill(current!().tid(), crate::signal::SIGPIPE.as_u8() as i32);
+        }
+
+        res
+    }
+
+    pub fn addr(&self) -> Result<AnyAddr> {
+        let addr = match &self.socket {
+            AnySocket::Stream(stream) => stream.addr(),
+            AnySocket::Datagram(datagram) => datagram.addr(),
+        };
+        Ok(addr.unwrap_or_else(|| match self.domain() {
+            Domain::INET6 => AnyAddr::Ipv6(Ipv6SocketAddr::default()),
+            _ => AnyAddr::Ipv4(Ipv4SocketAddr::default()),
+        }))
+    }
+
+    pub fn peer_addr(&self) -> Result<AnyAddr> {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.peer_addr(),
+            AnySocket::Datagram(datagram) => datagram.peer_addr(),
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.shutdown(how),
+            AnySocket::Datagram(datagram) => datagram.shutdown(how),
+        }
+    }
+
+    pub fn nonblocking(&self) -> bool {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.nonblocking(),
+            AnySocket::Datagram(datagram) => datagram.nonblocking(),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.set_nonblocking(nonblocking),
+            AnySocket::Datagram(datagram) => datagram.set_nonblocking(nonblocking),
+        }
+    }
+
+    fn poll(&self) -> IoEvents {
+        let mut events = match &self.socket {
+            AnySocket::Stream(stream) => stream.poll(),
+            AnySocket::Datagram(datagram) => datagram.poll(),
+        };
+        if self.errno.lock().unwrap().is_some() {
+            events |= IoEvents::ERR;
+        }
+        events
+    }
+
+    // Take the pending error
+    fn errno(&self) -> Option<Errno> {
+        self.errno.lock().unwrap().take()
+    }
+
+    fn set_errno(&self, errno: Errno) {
+        *self.errno.lock().unwrap() = Some(errno);
+        if let Some(notifier) = self.notifier() {
+            notifier.broadcast(&IoEvents::ERR);
+        }
+    }
+
+    fn bytes_to_read(&self) -> usize {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.bytes_to_read(),
+            AnySocket::Datagram(datagram) => datagram.bytes_to_read(),
+        }
+    }
+
+    pub fn is_listening(&self) -> bool {
+        match &self.socket {
+            AnySocket::Stream(stream) => stream.is_listening(),
+            AnySocket::Datagram(_) => false,
+        }
+    }
+
+    // The address family must be the same with the socket
+    fn check_addr(&self, addr: &AnyAddr) -> Result<()> {
+        match (self.domain(), addr) {
+            (Domain::INET, AnyAddr::Ipv4(_)) | (Domain::INET6, AnyAddr::Ipv6(_)) => Ok(()),
+            (Domain::INET6, _) => return_errno!(EINVAL, "invalid address family"),
+            _ => return_errno!(EAFNOSUPPORT, "invalid address family"),
+        }
+    }
+
+    // The value of an option that is never set
+    fn default_raw_option(&self, level: i32, optname: i32) -> Vec<u8> {
+        let value = match (level, SockOptName::try_from(optname)) {
+            (libc::SOL_SOCKET, Ok(SockOptName::SO_SNDBUF))
+            | (libc::SOL_SOCKET, Ok(SockOptName::SO_RCVBUF)) => DEFAULT_BUF_SIZE as i32,
+            (libc::SOL_SOCKET, Ok(SockOptName::SO_PROTOCOL)) => match self.get_type() {
+                SocketType::STREAM => libc::IPPROTO_TCP,
+                _ => libc::IPPROTO_UDP,
+            },
+            _ => 0,
+        };
+        value.to_ne_bytes().to_vec()
+    }
+
+    // A zero timeout means blocking forever
+    fn send_timeout(&self) -> Option<Duration> {
+        self.options()
+            .send_timeout
+            .filter(|timeout| !timeout.is_zero())
+    }
+
+    fn recv_timeout(&self) -> Option<Duration> {
+        self.options()
+            .recv_timeout
+            .filter(|timeout| !timeout.is_zero())
+    }
+
+    fn options(&self) -> SgxMutexGuard<'_, SockOptions> {
+        self.options.lock().unwrap()
+    }
+}
+
+// The socket-level options copied from the socket taken over
+const COPIED_SOCKET_OPTIONS: [SockOptName; 6] = [
+    SockOptName::SO_REUSEADDR,
+    SockOptName::SO_REUSEPORT,
+    SockOptName::SO_KEEPALIVE,
+    SockOptName::SO_BROADCAST,
+    SockOptName::SO_SNDBUF,
+    SockOptName::SO_RCVBUF,
+];
+
+// Same as TCP_NODELAY of Linux, at the level of IPPROTO_TCP
+const TCP_NODELAY: i32 = 1;
+
+// The timeouts of a uring socket are maintained in the enclave, while those of a host
+// socket are the options of the host socket
+pub(super) fn timeout_of(socket: &FileRef, optname: SockOptName) -> Option<Duration> {
+    let value = match optname {
+        SockOptName::SO_RCVTIMEO_OLD => {
+            let mut cmd = GetRecvTimeoutCmd::new(());
+            socket.ioctl(&mut cmd).ok().and_then(|_| cmd.take_output())
+        }
+        _ => {
+            let mut cmd = GetSendTimeoutCmd::new(());
+            socket.ioctl(&mut cmd).ok().and_then(|_| cmd.take_output())
+        }
+    };
+    let value = value.or_else(|| {
+        let optlen = std::mem::size_of::<timeval>();
+        let mut cmd = GetSockOptRawCmd::new(libc::SOL_SOCKET, optname.into(), optlen as u32);
+        socket.ioctl(&mut cmd).ok()?;
+        match cmd.output() {
+            Some(optval) if optval.len() == optlen => {
+                Some(unsafe { std::ptr::read_unaligned(optval.as_ptr() as *const timeval) })
+            }
+            _ => None,
+        }
+    })?;
+    timeval_to_timeout(&value)
+}
+
+impl File for LoopbackSocket {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recvfrom(buf, RecvFlags::empty())
+            .map(|(bytes_recv, _)| bytes_recv)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.sendto(buf, None, SendFlags::empty())
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        self.recvmsg(bufs, RecvFlags::empty(), None)
+            .map(|(bytes_recv, ..)| bytes_recv)
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.sendmsg(bufs, None, SendFlags::empty(), None)
+    }
+
+    fn ioctl(&self, cmd: &mut dyn IoctlCmd) -> Result<()> {
+        match_ioctl_cmd_auto_error!(cmd, {
+            cmd : GetSockOptRawCmd => {
+                let (level, optname) = (cmd.level(), cmd.optname());
+                let optval = match self.options().raw.get(&(level, optname)) {
+                    Some(optval) => optval.clone(),
+                    None => self.default_raw_option(level, optname),
+                };
+                cmd.set_output(&optval)?;
+            },
+            cmd : SetSockOptRawCmd => {
+                let key = (cmd.level(), cmd.optname());
+                self.options().raw.insert(key, cmd.optval().to_vec());
+            },
+            cmd : SetRecvTimeoutCmd => {
+                self.options().recv_timeout = Some(*cmd.input());
+            },
+            cmd : SetSendTimeoutCmd => {
+                self.options().send_timeout = Some(*cmd.input());
+            },
+            cmd : GetRecvTimeoutCmd => {
+                let timeval = timeout_to_timeval(self.options().recv_timeout);
+                cmd.set_output(timeval);
+            },
+            cmd : GetSendTimeoutCmd => {
+                let timeval = timeout_to_timeval(self.options().send_timeout);
+                cmd.set_output(timeval);
+            },
+            cmd : SetSendBufSizeCmd => {
+                self.options().send_buf_size = Some(*cmd.input());
+            },
+            cmd : SetRecvBufSizeCmd => {
+                self.options().recv_buf_size = Some(*cmd.input());
+            },
+            cmd : GetSendBufSizeCmd => {
+                let buf_size = self.options().send_buf_size.unwrap_or(DEFAULT_BUF_SIZE);
+                cmd.set_output(buf_size);
+            },
+            cmd : GetRecvBufSizeCmd => {
+                let buf_size = self.options().recv_buf_size.unwrap_or(DEFAULT_BUF_SIZE);
+                cmd.set_output(buf_size);
+            },
+            cmd : GetAcceptConnCmd => {
+                cmd.set_output(self.is_listening() as _);
+            },
+            cmd : GetDomainCmd => {
+                cmd.set_output(self.domain() as _);
+            },
+            cmd : GetPeerNameCmd => {
+                let peer = self.peer_addr()?;
+                cmd.set_output(AddrStorage(peer.to_c_storage()));
+            },
+            cmd : GetErrorCmd => {
+                let error: i32 = self.errno().map(|errno| errno as i32).unwrap_or(0);
+                cmd.set_output(error);
+            },
+            cmd : GetTypeCmd => {
+                cmd.set_output(self.get_type() as _);
+            },
+            cmd : SetNonBlocking => {
+                self.set_nonblocking(*cmd.input() != 0);
+            },
+            cmd : GetReadBufLen => {
+                let bytes_to_read = self.bytes_to_read().min(std::i32::MAX as usize) as i32;
+                cmd.set_output(bytes_to_read as _);
+            }
+        });
+        Ok(())
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        if self.nonblocking() {
+            Ok(StatusFlags::O_NONBLOCK)
+        } else {
+            Ok(StatusFlags::empty())
+        }
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        let nonblocking = new_status_flags.contains(StatusFlags::O_NONBLOCK);
+        self.set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.poll()
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(match &self.socket {
+            AnySocket::Stream(stream) => stream.notifier(),
+            AnySocket::Datagram(datagram) => datagram.notifier(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
@@ -0,0 +1,407 @@
+use super::port_space::{addr_of_domain, with_zero_port, Binding, PORT_SPACE};
+use super::*;
+use events::{Waiter, WaiterQueue};
+use fs::{IoEvents, IoNotifier};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// SOCK_STREAM loopback socket, i.e., a TCP socket whose peer is in the same enclave.
+///
+/// Like a unix stream socket, it has three statuses: unconnected, listening and connected.
+/// A connection is a pair of endpoints, each of which owns an in-enclave channel for the
+/// data it receives. The connection is established immediately if the listener is not
+/// full, so connect never blocks.
+pub struct Stream {
+    domain: Domain,
+    inner: SgxMutex<Inner>,
+    // Relays the events of the endpoint, or the incoming connections of the listener
+    notifier: Arc<RelayNotifier>,
+    nonblocking: AtomicBool,
+}
+
+struct Inner {
+    addr: Option<AnyAddr>,
+    // Whether the port is bound by this socket, rather than shared with the listener
+    is_bound: bool,
+    status: Status,
+}
+
+enum Status {
+    Idle,
+    Listening(Arc<Listener>),
+    Connected(Connection),
+}
+
+#[derive(Clone)]
+struct Connection {
+    endpoint: Endpoint,
+    peer_addr: AnyAddr,
+}
+
+impl Stream {
+    pub fn new(domain: Domain, nonblocking: bool) -> Self {
+        Self::new_with_inner(
+            domain,
+            Inner {
+                addr: None,
+                is_bound: false,
+                status: Status::Idle,
+            },
+            nonblocking,
+        )
+    }
+
+    fn new_with_inner(domain: Domain, inner: Inner, nonblocking: bool) -> Self {
+        let notifier = Arc::new(RelayNotifier::new());
+        if let Status::Connected(connection) = &inner.status {
+            connection.endpoint.set_nonblocking(nonblocking);
+            notifier.observe_endpoint(&connection.endpoint);
+        }
+
+        Self {
+            domain,
+            inner: SgxMutex::new(inner),
+            notifier,
+            nonblocking: AtomicBool::new(nonblocking),
+        }
+    }
+
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
+    pub fn addr(&self) -> Option<AnyAddr> {
+        self.inner()
+            .addr
+            .as_ref()
+            .map(|addr| addr_of_domain(addr, self.domain))
+    }
+
+    pub fn peer_addr(&self) -> Result<AnyAddr> {
+        match &self.inner().status {
+            Status::Connected(connection) => Ok(addr_of_domain(&connection.peer_addr, self.domain)),
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    pub fn bind(&self, addr: &AnyAddr) -> Result<()> {
+        let mut inner = self.inner();
+        if inner.addr.is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+
+        let mut addr = addr.clone();
+        PORT_SPACE.bind(SocketType::STREAM, &mut addr, Binding::Bound)?;
+        inner.addr = Some(addr);
+        inner.is_bound = true;
+        Ok(())
+    }
+
+    pub fn listen(&self, backlog: i32) -> Result<()> {
+        if backlog < 0 {
+            return_errno!(EINVAL, "negative backlog is not supported");
+        }
+        let capacity = backlog as usize;
+
+        let mut inner = self.inner();
+        match &inner.status {
+            Status::Idle => {
+                let addr = match &inner.addr {
+                    Some(addr) => addr.clone(),
+                    None => return_errno!(EINVAL, "the socket is not bound"),
+                };
+                let listener = Arc::new(Listener::new(capacity, self.notifier.clone()));
+                PORT_SPACE.listen(&addr, listener.clone())?;
+                inner.status = Status::Listening(listener);
+            }
+            Status::Connected(_) => return_errno!(EINVAL, "the socket is already connected"),
+            Status::Listening(listener) => listener.resize(capacity),
+        }
+        Ok(())
+    }
+
+    /// Whether the address is reachable by connecting a loopback stream socket.
+    pub fn is_connectable(addr: &AnyAddr) -> bool {
+        PORT_SPACE.is_bound(SocketType::STREAM, addr)
+    }
+
+    pub fn connect(&self, addr: &AnyAddr) -> Result<()> {
+        debug!("connect to {:?}", addr);
+
+        let mut inner = self.inner();
+        match &inner.status {
+            Status::Idle => {}
+            Status::Connected(_) => return_errno!(EISCONN, "already connected"),
+            Status::Listening(_) => return_errno!(EINVAL, "invalid socket for connect"),
+        }
+
+        let listener = match PORT_SPACE.get(SocketType::STREAM, addr) {
+            Some(Binding::Listener(listener)) => listener,
+            _ => return_errno!(ECONNREFUSED, "no one's listening on the remote address"),
+        };
+
+        // An unbound socket is bound to an ephemeral port of the remote address
+        let is_autobound = inner.addr.is_none();
+        if is_autobound {
+            let mut self_addr = with_zero_port(addr);
+            PORT_SPACE.bind(SocketType::STREAM, &mut self_addr, Binding::Bound)?;
+            inner.addr = Some(self_addr);
+            inner.is_bound = true;
+        }
+        let self_addr = inner.addr.clone().unwrap();
+
+        let (end_self, end_incoming) = end_pair(self.nonblocking())?;
+        let incoming = Incoming {
+            endpoint: end_incoming,
+            addr: addr.clone(),
+            peer_addr: self_addr,
+        };
+        if let Err(e) = listener.push(incoming) {
+            if is_autobound {
+                PORT_SPACE.unbind(SocketType::STREAM, inner.addr.as_ref().unwrap());
+                inner.addr = None;
+                inner.is_bound = false;
+            }
+            return Err(e);
+        }
+
+        self.notifier.observe_endpoint(&end_self);
+        inner.status = Status::Connected(Connection {
+            endpoint: end_self,
+            peer_addr: addr.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn accept(&self, nonblocking: bool, timeout: Option<Duration>) -> Result<(Self, AnyAddr)> {
+        let listener = match &self.inner().status {
+            Status::Listening(listener) => listener.clone(),
+            _ => return_errno!(EINVAL, "the socket is not listening"),
+        };
+
+        let incoming = listener.pop(self.nonblocking(), timeout)?;
+        let peer_addr = addr_of_domain(&incoming.peer_addr, self.domain);
+        debug!("accept socket from {:?}", peer_addr);
+
+        let inner = Inner {
+            addr: Some(incoming.addr),
+            is_bound: false,
+            status: Status::Connected(Connection {
+                endpoint: incoming.endpoint,
+                peer_addr: incoming.peer_addr,
+            }),
+        };
+        Ok((
+            Self::new_with_inner(self.domain, inner, nonblocking),
+            peer_addr,
+        ))
+    }
+
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        flags: SendFlags,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let unsupported_flags = flags - SendFlags::MSG_NOSIGNAL;
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        self.endpoint()?.sendmsg(bufs, None, timeout)
+    }
+
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        if !flags.is_empty() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        self.endpoint()?
+            .recvmsg(bufs, timeout)
+            .map(|(bytes_recv, _)| bytes_recv)
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.endpoint()?.shutdown(how)
+    }
+
+    pub fn poll(&self) -> IoEvents {
+        match &self.inner().status {
+            // linux return value
+            Status::Idle => IoEvents::OUT | IoEvents::HUP,
+            Status::Listening(listener) => listener.poll(),
+            Status::Connected(connection) => connection.endpoint.poll(),
+        }
+    }
+
+    pub fn notifier(&self) -> &IoNotifier {
+        self.notifier.notifier()
+    }
+
+    pub fn bytes_to_read(&self) -> usize {
+        match &self.inner().status {
+            Status::Connected(connection) => connection.endpoint.bytes_to_read(),
+            _ => 0,
+        }
+    }
+
+    pub fn is_listening(&self) -> bool {
+        matches!(self.inner().status, Status::Listening(_))
+    }
+
+    pub fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        let inner = self.inner();
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        if let Status::Connected(connection) = &inner.status {
+            connection.endpoint.set_nonblocking(nonblocking);
+        }
+    }
+
+    // The connected status will not be changed any more. Use clone to release the mutex
+    // lock early.
+    fn endpoint(&self) -> Result<Endpoint> {
+        match &self.inner().status {
+            Status::Connected(connection) => Ok(connection.endpoint.clone()),
+            _ => return_errno!(ENOTCONN, "unconnected socket"),
+        }
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl Debug for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream")
+            .field("addr", &self.addr())
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        if inner.is_bound {
+            PORT_SPACE.unbind(SocketType::STREAM, inner.addr.as_ref().unwrap());
+        }
+
+        if let Status::Listening(listener) = &inner.status {
+            // Refuse the pending connections and wake up the accepting threads
+            listener.shutdown();
+        }
+    }
+}
+
+/// A connection to be accepted, whose endpoint is created by the connecting socket.
+pub struct Incoming {
+    endpoint: Endpoint,
+    addr: AnyAddr,
+    peer_addr: AnyAddr,
+}
+
+/// The incoming connections of a listening loopback stream socket.
+pub struct Listener {
+    inner: SgxMutex<ListenerInner>,
+    accept_waiters: WaiterQueue,
+    notifier: Arc<RelayNotifier>,
+}
+
+struct ListenerInner {
+    incoming: VecDeque<Incoming>,
+    capacity: usize,
+    is_shutdown: bool,
+}
+
+impl Listener {
+    pub fn new(capacity: usize, notifier: Arc<RelayNotifier>) -> Self {
+        Self {
+            inner: SgxMutex::new(ListenerInner {
+                incoming: VecDeque::new(),
+                capacity,
+                is_shutdown: false,
+            }),
+            accept_waiters: WaiterQueue::new(),
+            notifier,
+        }
+    }
+
+    pub fn push(&self, incoming: Incoming) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        // Like Linux, one more connection than the capacity is allowed
+        if inner.is_shutdown || inner.incoming.len() > inner.capacity {
+            return_errno!(ECONNREFUSED, "the backlog is full");
+        }
+        inner.incoming.push_back(incoming);
+        drop(inner);
+
+        self.notifier.notifier().broadcast(&IoEvents::IN);
+        self.accept_waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    /// Pop an incoming connection. The blocking pop fails with EAGAIN once the timeout
+    /// expires.
+    pub fn pop(&self, nonblocking: bool, timeout: Option<Duration>) -> Result<Incoming> {
+        let mut timeout = timeout;
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(incoming) = inner.incoming.pop_front() {
+                return Ok(incoming);
+            }
+
+            if inner.is_shutdown {
+                return_errno!(EINVAL, "the socket is not listening");
+            }
+            if nonblocking {
+                return_errno!(EAGAIN, "no connection is incoming");
+            }
+            self.accept_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter
+                .wait_mut(timeout.as_mut())
+                .map_err(|e| match e.errno() {
+                    ETIMEDOUT => errno!(EAGAIN, "no connection is incoming before timeout"),
+                    _ => e,
+                })?;
+        }
+    }
+
+    pub fn resize(&self, capacity: usize) {
+        self.inner.lock().unwrap().capacity = capacity;
+    }
+
+    pub fn poll(&self) -> IoEvents {
+        if self.inner.lock().unwrap().incoming.is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+
+    /// Refuse all the pending and future connections.
+    pub fn shutdown(&self) {
+        let pending = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.is_shutdown = true;
+            std::mem::take(&mut inner.incoming)
+        };
+        // The connecting sockets get EOF when the pending endpoints are dropped
+        drop(pending);
+
+        self.accept_waiters.dequeue_and_wake_all();
+    }
+}
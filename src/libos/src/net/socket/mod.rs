@@ -1,22 +1,29 @@
 use super::*;
 
 mod host;
+mod loopback;
+mod netlink;
 mod sockopt;
 mod unix;
 mod uring;
 mod util;
 
 pub use self::host::{HostSocket, HostSocketType};
-pub use self::unix::{socketpair, unix_socket, AsUnixSocket};
+pub use self::loopback::{
+    active_socket, is_loopback_addr, AsInetSocket, AsLoopbackSocket, InetSocket, LoopbackSocket,
+};
+pub use self::netlink::{AsNetlinkSocket, NetlinkSocket, NETLINK_ROUTE};
+pub use self::unix::{socketpair, unix_gc, unix_socket, AsUnixDatagram, AsUnixSocket};
 pub use self::util::{
-    mmsghdr, Addr, AnyAddr, CMessages, CSockAddr, CmsgData, Domain, Iovs, IovsMut, Ipv4Addr,
-    Ipv4SocketAddr, Ipv6SocketAddr, MsgFlags, RecvFlags, SendFlags, Shutdown, SliceAsLibcIovec,
-    SockAddr, SocketFlags, SocketProtocol, SocketType, UnixAddr,
+    mmsghdr, ucred_t, Addr, AnyAddr, CMessages, CMessagesWriter, CSockAddr, CmsgData, Domain, Iovs,
+    IovsMut, Ipv4Addr, Ipv4SocketAddr, Ipv6SocketAddr, MsgFlags, RecvFlags, SendFlags, Shutdown,
+    SliceAsLibcIovec, SockAddr, SocketFlags, SocketProtocol, SocketType, UnixAddr, SOL_TLS,
+    TLS_GET_RECORD_TYPE, TLS_SET_RECORD_TYPE,
 };
 pub use sockopt::{
-    GetAcceptConnCmd, GetDomainCmd, GetErrorCmd, GetOutputAsBytes, GetPeerNameCmd,
+    GetAcceptConnCmd, GetDomainCmd, GetErrorCmd, GetOutputAsBytes, GetPassCredCmd, GetPeerNameCmd,
     GetRecvBufSizeCmd, GetRecvTimeoutCmd, GetSendBufSizeCmd, GetSendTimeoutCmd, GetSockOptRawCmd,
-    GetTypeCmd, SetRecvBufSizeCmd, SetRecvTimeoutCmd, SetSendBufSizeCmd, SetSendTimeoutCmd,
-    SetSockOptRawCmd, SockOptName,
+    GetTypeCmd, SetPassCredCmd, SetRecvBufSizeCmd, SetRecvTimeoutCmd, SetSendBufSizeCmd,
+    SetSendTimeoutCmd, SetSockOptRawCmd, SockOptName,
 };
 pub use uring::{socket_file::SocketFile, UringSocketType};
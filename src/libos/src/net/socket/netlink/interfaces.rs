@@ -0,0 +1,238 @@
+use super::*;
+use crate::fs::{GetIfConf, GetIfReqWithRawCmd, IfReq};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::untrusted::fs;
+
+const SIOCGIFFLAGS: u32 = 0x8913;
+const SIOCGIFBRDADDR: u32 = 0x8919;
+const SIOCGIFNETMASK: u32 = 0x891B;
+const SIOCGIFMTU: u32 = 0x8921;
+const SIOCGIFHWADDR: u32 = 0x8927;
+const SIOCGIFINDEX: u32 = 0x8933;
+
+pub const IFF_BROADCAST: u32 = 0x2;
+pub const IFF_LOOPBACK: u32 = 0x8;
+pub const IFF_RUNNING: u32 = 0x40;
+pub const IFA_F_PERMANENT: u8 = 0x80;
+
+// The scopes of the IPv6 addresses in /proc/net/if_inet6
+const IPV6_ADDR_LOOPBACK: u32 = 0x10;
+const IPV6_ADDR_LINKLOCAL: u32 = 0x20;
+const IPV6_ADDR_SITELOCAL: u32 = 0x40;
+
+/// A network interface of the host.
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub index: u32,
+    pub name: String,
+    pub flags: u32,
+    pub mtu: u32,
+    // The ARPHRD_* type of the hardware
+    pub hw_type: u16,
+    pub hw_addr: [u8; 6],
+    pub addrs: Vec<InterfaceAddr>,
+}
+
+/// An IPv4 or IPv6 address of a network interface.
+#[derive(Debug, Clone)]
+pub struct InterfaceAddr {
+    // The name of the interface, or its alias like `eth0:1`
+    pub label: String,
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+    // Only IPv4 addresses have broadcast addresses
+    pub broadcast: Option<[u8; 4]>,
+    pub scope: AddrScope,
+    // The IFA_F_* flags
+    pub flags: u8,
+}
+
+/// The scope of an address, i.e., where the address is valid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddrScope {
+    Universe,
+    Site,
+    Link,
+    Host,
+}
+
+impl Interface {
+    pub fn is_loopback(&self) -> bool {
+        self.flags & IFF_LOOPBACK != 0
+    }
+}
+
+/// Take a snapshot of the network interfaces of the host.
+///
+/// The interfaces and their IPv4 addresses are queried by the SIOCGIFCONF and SIOCGIFxxx
+/// ioctl commands that serve the sockets, so the answers are consistent with those of
+/// the ioctls. SIOCGIFCONF only knows IPv4 addresses, so the IPv6 addresses are read from
+/// /proc/net/if_inet6 of the host. Both are untrusted like the answers of the ioctls.
+pub fn host_interfaces() -> Result<Vec<Interface>> {
+    let host_socket = query_socket()?;
+    let host_fd = host_socket.raw_host_fd();
+
+    // Get the length of the list first
+    let mut get_ifconf = GetIfConf::IfConfLen(0);
+    get_ifconf.execute(host_fd)?;
+    let mut get_ifconf = GetIfConf::IfConfBuf(vec![0; get_ifconf.len()]);
+    get_ifconf.execute(host_fd)?;
+
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let if_reqs = get_ifconf
+        .as_slice()
+        .unwrap()
+        .chunks_exact(std::mem::size_of::<IfReq>());
+    for if_req_bytes in if_reqs {
+        let if_req = unsafe { std::ptr::read_unaligned(if_req_bytes.as_ptr() as *const IfReq) };
+        let label = name_of(&if_req);
+        // The aliases share the link of the interface
+        let name = label.split(':').next().unwrap().to_string();
+        let interface = match interfaces
+            .iter()
+            .position(|interface| interface.name == name)
+        {
+            Some(pos) => &mut interfaces[pos],
+            None => {
+                interfaces.push(query_interface(host_fd, name)?);
+                interfaces.last_mut().unwrap()
+            }
+        };
+
+        let netmask = sockaddr_in_ip(&get_ifreq(host_fd, SIOCGIFNETMASK, &label)?);
+        let broadcast = if interface.flags & IFF_BROADCAST != 0 {
+            Some(sockaddr_in_ip(&get_ifreq(host_fd, SIOCGIFBRDADDR, &label)?))
+        } else {
+            None
+        };
+        let scope = if interface.is_loopback() {
+            AddrScope::Host
+        } else {
+            AddrScope::Universe
+        };
+        interface.addrs.push(InterfaceAddr {
+            addr: IpAddr::V4(Ipv4Addr::from(sockaddr_in_ip(&if_req))),
+            prefix_len: u32::from_be_bytes(netmask).count_ones() as u8,
+            broadcast,
+            scope,
+            flags: IFA_F_PERMANENT,
+            label,
+        });
+    }
+
+    // The host may have IPv6 disabled, where the file is absent
+    let if_inet6 = fs::read_to_string("/proc/net/if_inet6").unwrap_or_default();
+    for line in if_inet6.lines() {
+        let (name, addr) = match parse_if_inet6_line(line) {
+            Some(name_and_addr) => name_and_addr,
+            None => {
+                warn!("invalid line in /proc/net/if_inet6: {}", line);
+                continue;
+            }
+        };
+        // The interfaces with only IPv6 addresses are unknown to SIOCGIFCONF
+        let interface = match interfaces
+            .iter()
+            .position(|interface| interface.name == name)
+        {
+            Some(pos) => &mut interfaces[pos],
+            None => {
+                interfaces.push(query_interface(host_fd, name)?);
+                interfaces.last_mut().unwrap()
+            }
+        };
+        interface.addrs.push(addr);
+    }
+    Ok(interfaces)
+}
+
+// Parse a line of /proc/net/if_inet6 like
+// `fe800000000000000000000000000001 02 40 20 80     eth0`, i.e., the address, the index,
+// the prefix length, the scope and the flags in hex, and the name of the interface
+fn parse_if_inet6_line(line: &str) -> Option<(String, InterfaceAddr)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 6 || fields[0].len() != 32 {
+        return None;
+    }
+    let addr = u128::from_str_radix(fields[0], 16).ok()?;
+    let prefix_len = u8::from_str_radix(fields[2], 16).ok()?;
+    let scope = match u32::from_str_radix(fields[3], 16).ok()? {
+        IPV6_ADDR_LOOPBACK => AddrScope::Host,
+        IPV6_ADDR_LINKLOCAL => AddrScope::Link,
+        IPV6_ADDR_SITELOCAL => AddrScope::Site,
+        _ => AddrScope::Universe,
+    };
+    // Only the lower bits fit in ifa_flags
+    let flags = u32::from_str_radix(fields[4], 16).ok()? as u8;
+    let name = fields[5].to_string();
+    if prefix_len > 128 {
+        return None;
+    }
+    let addr = InterfaceAddr {
+        label: name.clone(),
+        addr: IpAddr::V6(Ipv6Addr::from(addr)),
+        prefix_len,
+        broadcast: None,
+        scope,
+        flags,
+    };
+    Some((name, addr))
+}
+
+/// Create a host socket to query the network interfaces by ioctls.
+pub fn query_socket() -> Result<HostSocket> {
+    HostSocket::new(Domain::INET, SocketType::DGRAM, SocketFlags::empty(), 0)
+}
+
+fn query_interface(host_fd: FileDesc, name: String) -> Result<Interface> {
+    let index = ifreq_i32(&get_ifreq(host_fd, SIOCGIFINDEX, &name)?) as u32;
+    let flags = ifreq_i32(&get_ifreq(host_fd, SIOCGIFFLAGS, &name)?) as u16 as u32;
+    let mtu = ifreq_i32(&get_ifreq(host_fd, SIOCGIFMTU, &name)?) as u32;
+    // The hardware address is a sockaddr, whose family is the type of the hardware
+    let hw_req = get_ifreq(host_fd, SIOCGIFHWADDR, &name)?;
+    let hw_type = u16::from_ne_bytes([hw_req.ifr_union[0], hw_req.ifr_union[1]]);
+    let mut hw_addr = [0; 6];
+    hw_addr.copy_from_slice(&hw_req.ifr_union[2..8]);
+    Ok(Interface {
+        index,
+        name,
+        flags,
+        mtu,
+        hw_type,
+        hw_addr,
+        addrs: Vec::new(),
+    })
+}
+
+fn get_ifreq(host_fd: FileDesc, cmd: u32, name: &str) -> Result<IfReq> {
+    let mut if_req = IfReq::default();
+    // Leave room for the null terminator
+    let name_len = name.len().min(if_req.ifr_name.len() - 1);
+    if_req.ifr_name[..name_len].copy_from_slice(&name.as_bytes()[..name_len]);
+
+    let mut get_ifreq = GetIfReqWithRawCmd::new(cmd, if_req);
+    get_ifreq.execute(host_fd)?;
+    Ok(*get_ifreq.output().unwrap())
+}
+
+fn name_of(if_req: &IfReq) -> String {
+    let name_len = if_req
+        .ifr_name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(if_req.ifr_name.len());
+    String::from_utf8_lossy(&if_req.ifr_name[..name_len]).into_owned()
+}
+
+fn ifreq_i32(if_req: &IfReq) -> i32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&if_req.ifr_union[..4]);
+    i32::from_ne_bytes(bytes)
+}
+
+// The IP address of the sockaddr_in in the request
+fn sockaddr_in_ip(if_req: &IfReq) -> [u8; 4] {
+    let mut ip = [0; 4];
+    ip.copy_from_slice(&if_req.ifr_union[4..8]);
+    ip
+}
@@ -0,0 +1,442 @@
+//! The messages of rtnetlink, i.e., the requests to NETLINK_ROUTE and their answers.
+use super::interfaces::{
+    host_interfaces, AddrScope, Interface, InterfaceAddr, IFF_BROADCAST, IFF_RUNNING,
+};
+use super::*;
+use crate::net::policy::is_interface_addr_visible;
+use std::mem::size_of;
+use std::net::IpAddr;
+
+// The types of the messages
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const RTM_BASE: u16 = 16;
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+const RTM_NEWADDR: u16 = 20;
+const RTM_GETADDR: u16 = 22;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_GETROUTE: u16 = 26;
+
+// The flags of the messages
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_MULTI: u16 = 0x2;
+const NLM_F_ACK: u16 = 0x4;
+const NLM_F_DUMP: u16 = 0x300;
+
+// The types of the attributes
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_BROADCAST: u16 = 2;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFLA_OPERSTATE: u16 = 16;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const IFA_LABEL: u16 = 3;
+const IFA_BROADCAST: u16 = 4;
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_PREFSRC: u16 = 7;
+const RTA_TABLE: u16 = 15;
+
+const AF_UNSPEC: u8 = 0;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+const ARPHRD_NONE: u16 = 0xfffe;
+const IF_OPER_DOWN: u8 = 2;
+const IF_OPER_UP: u8 = 6;
+const RT_SCOPE_UNIVERSE: u8 = 0;
+const RT_SCOPE_SITE: u8 = 200;
+const RT_SCOPE_LINK: u8 = 253;
+const RT_SCOPE_HOST: u8 = 254;
+const RT_TABLE_MAIN: u8 = 254;
+const RTPROT_KERNEL: u8 = 2;
+const RTN_UNICAST: u8 = 1;
+
+// The max length of a datagram of a dump, which fits in the buffer of one page used by
+// libc to receive the answers
+const MAX_DUMP_DATAGRAM_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct nlmsghdr {
+    nlmsg_len: u32,
+    nlmsg_type: u16,
+    nlmsg_flags: u16,
+    nlmsg_seq: u32,
+    nlmsg_pid: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct ifinfomsg {
+    ifi_family: u8,
+    ifi_pad: u8,
+    ifi_type: u16,
+    ifi_index: i32,
+    ifi_flags: u32,
+    ifi_change: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct ifaddrmsg {
+    ifa_family: u8,
+    ifa_prefixlen: u8,
+    ifa_flags: u8,
+    ifa_scope: u8,
+    ifa_index: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct rtmsg {
+    rtm_family: u8,
+    rtm_dst_len: u8,
+    rtm_src_len: u8,
+    rtm_tos: u8,
+    rtm_table: u8,
+    rtm_protocol: u8,
+    rtm_scope: u8,
+    rtm_type: u8,
+    rtm_flags: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct rtattr {
+    rta_len: u16,
+    rta_type: u16,
+}
+
+/// Handle the requests in a message sent to the kernel, returning the datagrams of the
+/// answers to be received by the socket of the port ID.
+pub fn handle_requests(data: &[u8], port_id: u32) -> Vec<Vec<u8>> {
+    let mut answers = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = read_struct::<nlmsghdr>(&data[offset..]) {
+        let msg_len = header.nlmsg_len as usize;
+        // The malformed messages are ignored, like Linux
+        if msg_len < size_of::<nlmsghdr>() || msg_len > data.len() - offset {
+            break;
+        }
+        let msg = &data[offset..offset + msg_len];
+        answers.extend(handle_request(&header, msg, port_id));
+        offset = (offset + align(msg_len)).min(data.len());
+    }
+    answers
+}
+
+fn handle_request(header: &nlmsghdr, msg: &[u8], port_id: u32) -> Vec<Vec<u8>> {
+    // Only the requests are answered, while the control messages are ignored
+    if header.nlmsg_flags & NLM_F_REQUEST == 0 || header.nlmsg_type < RTM_BASE {
+        return Vec::new();
+    }
+
+    let payload = &msg[size_of::<nlmsghdr>()..];
+    let is_dump = header.nlmsg_flags & NLM_F_DUMP == NLM_F_DUMP;
+    let answer_to = AnswerTo {
+        seq: header.nlmsg_seq,
+        port_id,
+    };
+    let res = match header.nlmsg_type {
+        RTM_GETLINK | RTM_GETADDR | RTM_GETROUTE if is_dump => {
+            dump(header.nlmsg_type, payload, &answer_to)
+        }
+        RTM_GETLINK => get_link(payload, &answer_to).map(|link_msg| {
+            let mut answers = vec![link_msg];
+            if header.nlmsg_flags & NLM_F_ACK != 0 {
+                answers.push(error_msg(0, msg, &answer_to));
+            }
+            answers
+        }),
+        RTM_GETADDR | RTM_GETROUTE => Err(errno!(EOPNOTSUPP, "only dumps are supported")),
+        _ => Err(errno!(EOPNOTSUPP, "the request is not supported")),
+    };
+    match res {
+        Ok(answers) => answers,
+        Err(e) => {
+            warn!("failed to answer the netlink request: {:?}", e);
+            vec![error_msg(e.errno() as i32, msg, &answer_to)]
+        }
+    }
+}
+
+// The header fields of the answers to a request
+struct AnswerTo {
+    seq: u32,
+    port_id: u32,
+}
+
+// Dump the links, addresses or routes, which are packed into datagrams and end with a
+// NLMSG_DONE message
+fn dump(msg_type: u16, payload: &[u8], answer_to: &AnswerTo) -> Result<Vec<Vec<u8>>> {
+    // Either ifinfomsg, ifaddrmsg, rtmsg or rtgenmsg starts with the address family
+    let family = payload.first().copied().unwrap_or(AF_UNSPEC);
+    let interfaces = host_interfaces()?;
+
+    let mut msgs = Vec::new();
+    for interface in interfaces.iter() {
+        if msg_type == RTM_GETLINK {
+            msgs.push(link_msg(interface, NLM_F_MULTI, answer_to));
+            continue;
+        }
+        // The addresses that the app can't use are hidden by the network policy, and so
+        // are the routes to their subnets
+        let addrs = interface.addrs.iter().filter(|addr| {
+            (family == AF_UNSPEC || family == family_of(addr))
+                && is_interface_addr_visible(&addr.addr)
+        });
+        for addr in addrs {
+            match msg_type {
+                RTM_GETADDR => msgs.push(addr_msg(interface, addr, answer_to)),
+                // The routes to the loopback addresses are not in the main table
+                _ if addr.scope == AddrScope::Host => {}
+                _ => msgs.push(route_msg(interface, addr, answer_to)),
+            }
+        }
+    }
+    let mut done_msg = MsgBuilder::new(NLMSG_DONE, NLM_F_MULTI, answer_to);
+    done_msg.push(&0_i32);
+    msgs.push(done_msg.finish());
+
+    let mut datagrams: Vec<Vec<u8>> = Vec::new();
+    for msg in msgs {
+        match datagrams.last_mut() {
+            Some(datagram) if datagram.len() + msg.len() <= MAX_DUMP_DATAGRAM_LEN => {
+                datagram.extend_from_slice(&msg);
+            }
+            _ => datagrams.push(msg),
+        }
+    }
+    Ok(datagrams)
+}
+
+// Get the link of the index or the name given by the request
+fn get_link(payload: &[u8], answer_to: &AnswerTo) -> Result<Vec<u8>> {
+    let ifi = read_struct::<ifinfomsg>(payload)
+        .ok_or_else(|| errno!(EINVAL, "the request is too short"))?;
+    let name = if ifi.ifi_index == 0 {
+        let attrs = &payload[align(size_of::<ifinfomsg>()).min(payload.len())..];
+        let name = find_attr(attrs, IFLA_IFNAME)
+            .ok_or_else(|| errno!(EINVAL, "neither the index nor the name is given"))?;
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Some(String::from_utf8_lossy(&name[..name_len]).into_owned())
+    } else {
+        None
+    };
+
+    let interfaces = host_interfaces()?;
+    let interface = interfaces
+        .iter()
+        .find(|interface| match name.as_ref() {
+            Some(name) => &interface.name == name,
+            None => interface.index == ifi.ifi_index as u32,
+        })
+        .ok_or_else(|| errno!(ENODEV, "no such device"))?;
+    Ok(link_msg(interface, 0, answer_to))
+}
+
+fn link_msg(interface: &Interface, flags: u16, answer_to: &AnswerTo) -> Vec<u8> {
+    let mut builder = MsgBuilder::new(RTM_NEWLINK, flags, answer_to);
+    builder.push(&ifinfomsg {
+        ifi_family: AF_UNSPEC,
+        ifi_type: interface.hw_type,
+        ifi_index: interface.index as i32,
+        ifi_flags: interface.flags,
+        ..Default::default()
+    });
+    builder.push_attr(IFLA_IFNAME, &c_string(&interface.name));
+    builder.push_attr(IFLA_MTU, &interface.mtu.to_ne_bytes());
+    if interface.hw_type != ARPHRD_NONE {
+        builder.push_attr(IFLA_ADDRESS, &interface.hw_addr);
+    }
+    if interface.flags & IFF_BROADCAST != 0 {
+        builder.push_attr(IFLA_BROADCAST, &[0xff; 6]);
+    }
+    let operstate = if interface.flags & IFF_RUNNING != 0 {
+        IF_OPER_UP
+    } else {
+        IF_OPER_DOWN
+    };
+    builder.push_attr(IFLA_OPERSTATE, &[operstate]);
+    builder.finish()
+}
+
+fn addr_msg(interface: &Interface, addr: &InterfaceAddr, answer_to: &AnswerTo) -> Vec<u8> {
+    let mut builder = MsgBuilder::new(RTM_NEWADDR, NLM_F_MULTI, answer_to);
+    builder.push(&ifaddrmsg {
+        ifa_family: family_of(addr),
+        ifa_prefixlen: addr.prefix_len,
+        ifa_flags: addr.flags,
+        ifa_scope: match addr.scope {
+            AddrScope::Universe => RT_SCOPE_UNIVERSE,
+            AddrScope::Site => RT_SCOPE_SITE,
+            AddrScope::Link => RT_SCOPE_LINK,
+            AddrScope::Host => RT_SCOPE_HOST,
+        },
+        ifa_index: interface.index,
+    });
+    let ip = ip_bytes(&addr.addr);
+    builder.push_attr(IFA_ADDRESS, &ip);
+    // Like Linux, only IPv4 addresses have both the local and the peer addresses
+    if let IpAddr::V4(_) = addr.addr {
+        builder.push_attr(IFA_LOCAL, &ip);
+        builder.push_attr(IFA_LABEL, &c_string(&addr.label));
+    }
+    if let Some(broadcast) = addr.broadcast.as_ref() {
+        builder.push_attr(IFA_BROADCAST, broadcast);
+    }
+    builder.finish()
+}
+
+// The route to the subnet of an address
+fn route_msg(interface: &Interface, addr: &InterfaceAddr, answer_to: &AnswerTo) -> Vec<u8> {
+    let (dst, scope) = match addr.addr {
+        IpAddr::V4(ipv4_addr) => {
+            let mask = match addr.prefix_len {
+                0 => 0,
+                prefix_len => u32::MAX << (32 - prefix_len.min(32) as u32),
+            };
+            let dst = (u32::from(ipv4_addr) & mask).to_be_bytes().to_vec();
+            (dst, RT_SCOPE_LINK)
+        }
+        IpAddr::V6(ipv6_addr) => {
+            let mask = match addr.prefix_len {
+                0 => 0,
+                prefix_len => u128::MAX << (128 - prefix_len.min(128) as u32),
+            };
+            let dst = (u128::from(ipv6_addr) & mask).to_be_bytes().to_vec();
+            (dst, RT_SCOPE_UNIVERSE)
+        }
+    };
+
+    let mut builder = MsgBuilder::new(RTM_NEWROUTE, NLM_F_MULTI, answer_to);
+    builder.push(&rtmsg {
+        rtm_family: family_of(addr),
+        rtm_dst_len: addr.prefix_len,
+        rtm_table: RT_TABLE_MAIN,
+        rtm_protocol: RTPROT_KERNEL,
+        rtm_scope: scope,
+        rtm_type: RTN_UNICAST,
+        ..Default::default()
+    });
+    builder.push_attr(RTA_TABLE, &(RT_TABLE_MAIN as u32).to_ne_bytes());
+    builder.push_attr(RTA_DST, &dst);
+    // Like Linux, only the IPv4 routes have the preferred source addresses
+    if let IpAddr::V4(_) = addr.addr {
+        builder.push_attr(RTA_PREFSRC, &ip_bytes(&addr.addr));
+    }
+    builder.push_attr(RTA_OIF, &interface.index.to_ne_bytes());
+    builder.finish()
+}
+
+fn family_of(addr: &InterfaceAddr) -> u8 {
+    match addr.addr {
+        IpAddr::V4(_) => AF_INET,
+        IpAddr::V6(_) => AF_INET6,
+    }
+}
+
+fn ip_bytes(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ipv4_addr) => ipv4_addr.octets().to_vec(),
+        IpAddr::V6(ipv6_addr) => ipv6_addr.octets().to_vec(),
+    }
+}
+
+// The error of a request, or the acknowledgement if the error is zero. Like Linux, the
+// request is echoed in the error, while only its header is echoed in the acknowledgement.
+fn error_msg(errno: i32, request: &[u8], answer_to: &AnswerTo) -> Vec<u8> {
+    let mut builder = MsgBuilder::new(NLMSG_ERROR, 0, answer_to);
+    builder.push(&(-errno));
+    if errno == 0 {
+        builder.push_bytes(&request[..size_of::<nlmsghdr>()]);
+    } else {
+        builder.push_bytes(request);
+    }
+    builder.finish()
+}
+
+/// The builder of a message, which appends the header, the family-specific header and
+/// the attributes in order.
+struct MsgBuilder {
+    buf: Vec<u8>,
+}
+
+impl MsgBuilder {
+    fn new(msg_type: u16, flags: u16, answer_to: &AnswerTo) -> Self {
+        let mut builder = Self { buf: Vec::new() };
+        builder.push(&nlmsghdr {
+            nlmsg_len: 0,
+            nlmsg_type: msg_type,
+            nlmsg_flags: flags,
+            nlmsg_seq: answer_to.seq,
+            nlmsg_pid: answer_to.port_id,
+        });
+        builder
+    }
+
+    fn push<T: Copy>(&mut self, value: &T) {
+        let bytes =
+            unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        self.push_bytes(bytes);
+    }
+
+    fn push_attr(&mut self, attr_type: u16, payload: &[u8]) {
+        self.push(&rtattr {
+            rta_len: (size_of::<rtattr>() + payload.len()) as u16,
+            rta_type: attr_type,
+        });
+        self.push_bytes(payload);
+    }
+
+    // Every part is aligned to 4 bytes
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(align(self.buf.len()), 0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let msg_len = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&msg_len.to_ne_bytes());
+        self.buf
+    }
+}
+
+// Find the payload of the attribute of the type
+fn find_attr(mut attrs: &[u8], attr_type: u16) -> Option<&[u8]> {
+    while let Some(attr) = read_struct::<rtattr>(attrs) {
+        let attr_len = attr.rta_len as usize;
+        if attr_len < size_of::<rtattr>() || attr_len > attrs.len() {
+            return None;
+        }
+        if attr.rta_type == attr_type {
+            return Some(&attrs[size_of::<rtattr>()..attr_len]);
+        }
+        attrs = &attrs[align(attr_len).min(attrs.len())..];
+    }
+    None
+}
+
+fn read_struct<T: Copy>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() < size_of::<T>() {
+        return None;
+    }
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+fn c_string(s: &str) -> Vec<u8> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
@@ -0,0 +1,17 @@
+//! In-enclave NETLINK_ROUTE sockets.
+//!
+//! The rtnetlink requests to dump the links, addresses and routes are answered in the
+//! enclave with the network interfaces of the host, which are queried by the same ioctls
+//! as SIOCGIFCONF and SIOCGIFxxx of the sockets, plus the IPv6 addresses of the host.
+//! The addresses hidden by the network policy are left out of the dumps. The sockets of
+//! the other netlink protocols are still host sockets.
+use super::unix::{Ancillary, Message, MsgQueue, DEFAULT_BUF_SIZE};
+use super::*;
+
+mod interfaces;
+mod message;
+mod socket_file;
+
+pub use self::socket_file::{AsNetlinkSocket, NetlinkSocket};
+
+pub const NETLINK_ROUTE: i32 = 0;
@@ -0,0 +1,454 @@
+use super::interfaces::query_socket;
+use super::message::handle_requests;
+use super::*;
+use crate::fs::{
+    AccessMode, File, GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, IoEvents, IoNotifier, IoctlCmd,
+    SetNonBlocking, StatusFlags,
+};
+use crate::net::socket::sockopt::{
+    timeout_to_timeval, AddrStorage, GetAcceptConnCmd, GetDomainCmd, GetErrorCmd, GetPeerNameCmd,
+    GetRecvTimeoutCmd, GetSendTimeoutCmd, GetSockOptRawCmd, GetTypeCmd, SetRecvTimeoutCmd,
+    SetSendTimeoutCmd, SetSockOptRawCmd, SockOptName,
+};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+use std::collections::btree_map::BTreeMap;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+lazy_static! {
+    // The port IDs bound by the netlink sockets
+    static ref PORT_IDS: SgxMutex<BTreeSet<u32>> = SgxMutex::new(BTreeSet::new());
+}
+
+// The first port ID assigned to the sockets that can't use the process ID, like Linux
+const AUTOBIND_PORT_ID_START: i32 = -4096;
+
+/// A NETLINK_ROUTE socket, whose requests are answered in the enclave.
+///
+/// The answers are queued on sending the requests, so they can be received without
+/// blocking, like Linux. The multicast groups are only recorded, since no notification
+/// of the changes of the network is sent.
+pub struct NetlinkSocket {
+    socket_type: SocketType,
+    receiver: MsgQueue,
+    inner: SgxMutex<Inner>,
+    nonblocking: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    port_id: Option<u32>,
+    groups: u32,
+    // The options are only recorded since there is no host socket
+    raw_options: BTreeMap<(i32, i32), Vec<u8>>,
+    send_timeout: Option<Duration>,
+    recv_timeout: Option<Duration>,
+}
+
+/// The address of a netlink socket, i.e., `struct sockaddr_nl`.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+struct NetlinkAddr {
+    family: u16,
+    pad: u16,
+    port_id: u32,
+    groups: u32,
+}
+
+impl NetlinkAddr {
+    fn new(port_id: u32, groups: u32) -> Self {
+        Self {
+            family: Domain::NETLINK as u16,
+            pad: 0,
+            port_id,
+            groups,
+        }
+    }
+
+    // The address of the kernel
+    fn kernel() -> Self {
+        Self::new(0, 0)
+    }
+
+    fn from_any_addr(addr: &AnyAddr) -> Result<Self> {
+        let (c_addr, c_addr_len) = addr.to_c_storage();
+        if c_addr.ss_family != Domain::NETLINK as u16 {
+            return_errno!(EINVAL, "not a netlink address");
+        }
+        if c_addr_len < std::mem::size_of::<Self>() {
+            return_errno!(EINVAL, "the address is too short");
+        }
+        Ok(unsafe { std::ptr::read_unaligned(&c_addr as *const _ as *const Self) })
+    }
+
+    fn to_any_addr(&self) -> AnyAddr {
+        let mut c_addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        unsafe {
+            std::ptr::write_unaligned(&mut c_addr as *mut _ as *mut Self, *self);
+        }
+        AnyAddr::Raw(SockAddr::from_c_storage(
+            &c_addr,
+            std::mem::size_of::<Self>(),
+        ))
+    }
+}
+
+pub trait AsNetlinkSocket {
+    fn as_netlink_socket(&self) -> Result<&NetlinkSocket>;
+}
+
+impl AsNetlinkSocket for FileRef {
+    fn as_netlink_socket(&self) -> Result<&NetlinkSocket> {
+        self.as_any()
+            .downcast_ref::<NetlinkSocket>()
+            .ok_or_else(|| errno!(ENOTSOCK, "not a netlink socket"))
+    }
+}
+
+impl NetlinkSocket {
+    pub fn new(socket_type: SocketType, flags: SocketFlags) -> Result<Self> {
+        if socket_type != SocketType::RAW && socket_type != SocketType::DGRAM {
+            return_errno!(ESOCKTNOSUPPORT, "socket type not supported");
+        }
+        Ok(Self {
+            socket_type,
+            receiver: MsgQueue::new(),
+            inner: SgxMutex::new(Inner::default()),
+            nonblocking: AtomicBool::new(flags.contains(SocketFlags::SOCK_NONBLOCK)),
+        })
+    }
+
+    pub fn get_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    /// Bind the socket to a port ID, or a unique one if the port ID is zero.
+    pub fn bind(&self, addr: &AnyAddr) -> Result<()> {
+        let nl_addr = NetlinkAddr::from_any_addr(addr)?;
+        let mut inner = self.inner();
+        match inner.port_id {
+            Some(port_id) if nl_addr.port_id != 0 && nl_addr.port_id != port_id => {
+                return_errno!(EINVAL, "the socket is already bound")
+            }
+            Some(_) => {}
+            None if nl_addr.port_id == 0 => inner.port_id = Some(autobind()),
+            None => {
+                let mut port_ids = PORT_IDS.lock().unwrap();
+                if !port_ids.insert(nl_addr.port_id) {
+                    return_errno!(EADDRINUSE, "the port ID is in use");
+                }
+                inner.port_id = Some(nl_addr.port_id);
+            }
+        }
+        inner.groups = nl_addr.groups;
+        Ok(())
+    }
+
+    /// Only the kernel can be connected to, which is the default destination anyway.
+    pub fn connect(&self, addr: &AnyAddr) -> Result<()> {
+        if addr.is_unspec() {
+            return Ok(());
+        }
+        Self::check_dest_addr(addr)
+    }
+
+    pub fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<AnyAddr>)> {
+        let (bytes_recv, addr_recv, _, _) = self.recvmsg(&mut [buf], flags, None)?;
+        Ok((bytes_recv, addr_recv))
+    }
+
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        _control: Option<&mut [u8]>,
+    ) -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
+        let unsupported_flags =
+            flags - (RecvFlags::MSG_PEEK | RecvFlags::MSG_TRUNC | RecvFlags::MSG_DONTWAIT);
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let peek = flags.contains(RecvFlags::MSG_PEEK);
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        let msg = match self.receiver.pop(peek, nonblocking, None)? {
+            Some(msg) => msg,
+            None => return Ok((0, None, MsgFlags::empty(), 0)),
+        };
+
+        let mut msg_flags = MsgFlags::empty();
+        let mut copied_len = 0;
+        for buf in bufs.iter_mut() {
+            let len = buf.len().min(msg.data.len() - copied_len);
+            buf[..len].copy_from_slice(&msg.data[copied_len..copied_len + len]);
+            copied_len += len;
+        }
+        if copied_len < msg.data.len() {
+            msg_flags |= MsgFlags::MSG_TRUNC;
+        }
+
+        let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+            msg.data.len()
+        } else {
+            copied_len
+        };
+        // No control message is supported
+        Ok((data_len, Some(msg.addr), msg_flags, 0))
+    }
+
+    pub fn sendto(&self, buf: &[u8], addr: Option<AnyAddr>, flags: SendFlags) -> Result<usize> {
+        self.sendmsg(&[buf], addr, flags, None)
+    }
+
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        addr: Option<AnyAddr>,
+        flags: SendFlags,
+        control: Option<&[u8]>,
+    ) -> Result<usize> {
+        if control.is_some() {
+            warn!("control message is not supported");
+        }
+        if let Some(addr) = addr.as_ref() {
+            Self::check_dest_addr(addr)?;
+        }
+
+        let data = bufs.concat();
+        let port_id = {
+            let mut inner = self.inner();
+            *inner.port_id.get_or_insert_with(autobind)
+        };
+        for answer in handle_requests(&data, port_id) {
+            let msg = Message {
+                data: answer,
+                addr: NetlinkAddr::kernel().to_any_addr(),
+                ancillary: Ancillary::default(),
+            };
+            self.receiver
+                .push(msg, true, None)
+                .map_err(|_| errno!(ENOBUFS, "the receiving queue is full"))?;
+        }
+        Ok(data.len())
+    }
+
+    pub fn addr(&self) -> AnyAddr {
+        let inner = self.inner();
+        NetlinkAddr::new(inner.port_id.unwrap_or(0), inner.groups).to_any_addr()
+    }
+
+    pub fn peer_addr(&self) -> AnyAddr {
+        NetlinkAddr::kernel().to_any_addr()
+    }
+
+    pub fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn check_dest_addr(addr: &AnyAddr) -> Result<()> {
+        if NetlinkAddr::from_any_addr(addr)?.port_id != 0 {
+            return_errno!(ECONNREFUSED, "only the kernel can be talked to");
+        }
+        Ok(())
+    }
+
+    // The value of an option that is never set
+    fn default_raw_option(&self, level: i32, optname: i32) -> Vec<u8> {
+        let value = match (level, SockOptName::try_from(optname)) {
+            (libc::SOL_SOCKET, Ok(SockOptName::SO_SNDBUF))
+            | (libc::SOL_SOCKET, Ok(SockOptName::SO_RCVBUF)) => DEFAULT_BUF_SIZE as i32,
+            (libc::SOL_SOCKET, Ok(SockOptName::SO_PROTOCOL)) => NETLINK_ROUTE,
+            _ => 0,
+        };
+        value.to_ne_bytes().to_vec()
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+}
+
+// Assign a unique port ID, which is the process ID if it is not used yet
+fn autobind() -> u32 {
+    let mut port_ids = PORT_IDS.lock().unwrap();
+    let mut port_id = current!().process().pid() as u32;
+    if port_ids.contains(&port_id) {
+        port_id = AUTOBIND_PORT_ID_START as u32;
+        while port_ids.contains(&port_id) {
+            port_id = port_id.wrapping_sub(1);
+        }
+    }
+    port_ids.insert(port_id);
+    port_id
+}
+
+impl Debug for NetlinkSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NetlinkSocket")
+            .field("socket_type", &self.socket_type)
+            .field("port_id", &self.inner().port_id)
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        if let Some(port_id) = self.inner().port_id {
+            PORT_IDS.lock().unwrap().remove(&port_id);
+        }
+    }
+}
+
+impl File for NetlinkSocket {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recvfrom(buf, RecvFlags::empty())
+            .map(|(bytes_recv, _)| bytes_recv)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.sendto(buf, None, SendFlags::empty())
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        self.recvmsg(bufs, RecvFlags::empty(), None)
+            .map(|(bytes_recv, ..)| bytes_recv)
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.sendmsg(bufs, None, SendFlags::empty(), None)
+    }
+
+    fn ioctl(&self, cmd: &mut dyn IoctlCmd) -> Result<()> {
+        match_ioctl_cmd_auto_error!(cmd, {
+            cmd : GetSockOptRawCmd => {
+                let (level, optname) = (cmd.level(), cmd.optname());
+                let optval = match self.inner().raw_options.get(&(level, optname)) {
+                    Some(optval) => optval.clone(),
+                    None => self.default_raw_option(level, optname),
+                };
+                cmd.set_output(&optval)?;
+            },
+            cmd : SetSockOptRawCmd => {
+                let key = (cmd.level(), cmd.optname());
+                self.inner().raw_options.insert(key, cmd.optval().to_vec());
+            },
+            cmd : SetRecvTimeoutCmd => {
+                self.inner().recv_timeout = Some(*cmd.input());
+            },
+            cmd : SetSendTimeoutCmd => {
+                self.inner().send_timeout = Some(*cmd.input());
+            },
+            cmd : GetRecvTimeoutCmd => {
+                let timeval = timeout_to_timeval(self.inner().recv_timeout);
+                cmd.set_output(timeval);
+            },
+            cmd : GetSendTimeoutCmd => {
+                let timeval = timeout_to_timeval(self.inner().send_timeout);
+                cmd.set_output(timeval);
+            },
+            cmd : GetAcceptConnCmd => {
+                cmd.set_output(0);
+            },
+            cmd : GetDomainCmd => {
+                cmd.set_output(Domain::NETLINK as _);
+            },
+            cmd : GetPeerNameCmd => {
+                cmd.set_output(AddrStorage(self.peer_addr().to_c_storage()));
+            },
+            cmd : GetErrorCmd => {
+                // The errors are always reported by the operations directly
+                cmd.set_output(0);
+            },
+            cmd : GetTypeCmd => {
+                cmd.set_output(self.get_type() as _);
+            },
+            cmd : SetNonBlocking => {
+                self.set_nonblocking(*cmd.input() != 0);
+            },
+            cmd : GetReadBufLen => {
+                let bytes_to_read = self.receiver.next_msg_len().min(std::i32::MAX as usize);
+                cmd.set_output(bytes_to_read as _);
+            },
+            // The interfaces are queried by a host socket, like the netlink requests
+            cmd : GetIfReqWithRawCmd => {
+                cmd.execute(query_socket()?.raw_host_fd())?;
+            },
+            cmd : GetIfConf => {
+                cmd.execute(query_socket()?.raw_host_fd())?;
+            }
+        });
+        Ok(())
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        if self.nonblocking() {
+            Ok(StatusFlags::O_NONBLOCK)
+        } else {
+            Ok(StatusFlags::empty())
+        }
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        let nonblocking = new_status_flags.contains(StatusFlags::O_NONBLOCK);
+        self.set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.receiver.pollee().poll(IoEvents::all(), None) | IoEvents::OUT
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.receiver.pollee().notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
@@ -34,6 +34,26 @@ impl GetSockOptRawCmd {
         Ok(())
     }
 
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    pub fn optname(&self) -> i32 {
+        self.optname
+    }
+
+    /// Set the output by the option value maintained in the enclave, which is truncated
+    /// if the buffer is too small.
+    pub fn set_output(&mut self, optval: &[u8]) -> Result<()> {
+        if self.optlen.is_some() {
+            return_errno!(EINVAL, "can not execute twice");
+        }
+        let optlen = optval.len().min(self.optval.len());
+        self.optval[..optlen].copy_from_slice(&optval[..optlen]);
+        self.optlen = Some(optlen as u32);
+        Ok(())
+    }
+
     pub fn output(&self) -> Option<&[u8]> {
         self.optlen
             .map(|opt_len| &self.optval[..(opt_len as usize)])
@@ -1,7 +1,7 @@
 use super::{GetRecvTimeoutCmd, GetSendTimeoutCmd};
 
 use super::{
-    GetAcceptConnCmd, GetDomainCmd, GetErrorCmd, GetPeerNameCmd, GetRecvBufSizeCmd,
+    GetAcceptConnCmd, GetDomainCmd, GetErrorCmd, GetPassCredCmd, GetPeerNameCmd, GetRecvBufSizeCmd,
     GetSendBufSizeCmd, GetSockOptRawCmd, GetTypeCmd,
 };
 
@@ -43,6 +43,14 @@ impl GetOutputAsBytes for GetAcceptConnCmd {
     }
 }
 
+impl GetOutputAsBytes for GetPassCredCmd {
+    fn get_output_as_bytes(&self) -> Option<&[u8]> {
+        self.output().map(|val_ref| unsafe {
+            std::slice::from_raw_parts(val_ref as *const _ as *const u8, std::mem::size_of::<i32>())
+        })
+    }
+}
+
 impl GetOutputAsBytes for GetPeerNameCmd {
     fn get_output_as_bytes(&self) -> Option<&[u8]> {
         self.output().map(|val_ref| unsafe {
@@ -6,6 +6,7 @@ mod get_output;
 mod get_peername;
 mod get_sockbuf;
 mod get_type;
+mod passcred;
 mod set;
 mod set_sockbuf;
 mod timeout;
@@ -18,10 +19,12 @@ pub use get_output::*;
 pub use get_peername::{AddrStorage, GetPeerNameCmd};
 pub use get_sockbuf::{GetRecvBufSizeCmd, GetSendBufSizeCmd};
 pub use get_type::GetTypeCmd;
+pub use passcred::{GetPassCredCmd, SetPassCredCmd};
 pub use set::{setsockopt_by_host, SetSockOptRawCmd};
 pub use set_sockbuf::{SetRecvBufSizeCmd, SetSendBufSizeCmd};
 pub use timeout::{
-    timeout_to_timeval, GetRecvTimeoutCmd, GetSendTimeoutCmd, SetRecvTimeoutCmd, SetSendTimeoutCmd,
+    timeout_to_timeval, timeval, timeval_to_timeout, GetRecvTimeoutCmd, GetSendTimeoutCmd,
+    SetRecvTimeoutCmd, SetSendTimeoutCmd,
 };
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -0,0 +1,7 @@
+crate::impl_ioctl_cmd! {
+    pub struct SetPassCredCmd<Input=bool, Output=()> {}
+}
+
+crate::impl_ioctl_cmd! {
+    pub struct GetPassCredCmd<Input=(), Output=i32> {}
+}
@@ -19,6 +19,18 @@ impl<'a> SetSockOptRawCmd<'a> {
         }
     }
 
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    pub fn optname(&self) -> i32 {
+        self.optname
+    }
+
+    pub fn optval(&self) -> &[u8] {
+        self.optval
+    }
+
     pub fn execute(&mut self, fd: FileDesc) -> Result<()> {
         setsockopt_by_host(fd, self.level, self.optname, &self.optval)?;
         Ok(())
@@ -33,6 +33,15 @@ pub fn timeout_to_timeval(timeout: Option<Duration>) -> timeval {
     }
 }
 
+pub fn timeval_to_timeout(timeval: &timeval) -> Option<Duration> {
+    if timeval.sec <= 0 && timeval.usec <= 0 {
+        return None;
+    }
+    let sec = timeval.sec.max(0) as u64;
+    let usec = timeval.usec.max(0) as u64;
+    Some(Duration::from_secs(sec) + Duration::from_micros(usec))
+}
+
 // Same as libc::timeval
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
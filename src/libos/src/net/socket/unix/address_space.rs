@@ -0,0 +1,253 @@
+use super::datagram::{Backlog, MsgQueue};
+use super::stream::{Endpoint, Listener, RelayNotifier};
+use super::*;
+use fs::{CreationFlags, FileMode};
+use std::collections::btree_map::BTreeMap;
+
+lazy_static! {
+    pub(super) static ref ADDRESS_SPACE: AddressSpace = AddressSpace::new();
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub enum AddressSpaceKey {
+    FileKey(usize),
+    AbstrKey(String),
+}
+
+impl AddressSpaceKey {
+    pub fn from_inode(inode: usize) -> Self {
+        AddressSpaceKey::FileKey(inode)
+    }
+
+    pub fn from_path(path: String) -> Self {
+        AddressSpaceKey::AbstrKey(path)
+    }
+}
+
+/// What is bound to an address. All kinds of unix sockets share the same address space.
+pub(super) enum Binding {
+    // A socket is bound to the address, but it can't be reached yet
+    Bound,
+    // A listening stream socket
+    StreamListener(Arc<Listener>),
+    // A listening seqpacket socket
+    SeqpacketListener(Arc<Backlog>),
+    // A datagram socket, which can be reached by its receiving queue
+    Datagram(Arc<MsgQueue>),
+}
+
+pub struct AddressSpace {
+    // For "file", use inode number as "key" instead of path string so that listeners can still
+    // be reached even if the socket file is moved or renamed.
+    file: SgxMutex<BTreeMap<AddressSpaceKey, Binding>>,
+    abstr: SgxMutex<BTreeMap<AddressSpaceKey, Binding>>,
+}
+
+impl AddressSpace {
+    pub fn new() -> Self {
+        Self {
+            file: SgxMutex::new(BTreeMap::new()),
+            abstr: SgxMutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Create the corresponding socket file of a file address and fill the address
+    /// with the inode number of the file.
+    pub fn create_socket_file(addr: &mut UnixAddr) -> Result<()> {
+        if let UnixAddr::File(inode_num, path) = addr {
+            let corresponding_inode_num = {
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                let file_ref = fs.open_file(
+                    path.path_str(),
+                    CreationFlags::O_CREAT.bits(),
+                    FileMode::from_bits(0o777).unwrap(),
+                )?;
+                file_ref.metadata()?.inode
+            };
+            *inode_num = Some(corresponding_inode_num);
+        }
+        Ok(())
+    }
+
+    pub fn add_binder(&self, addr: &UnixAddr) -> Result<()> {
+        self.add_binding(addr, Binding::Bound)
+    }
+
+    pub(super) fn add_datagram(&self, addr: &UnixAddr, queue: Arc<MsgQueue>) -> Result<()> {
+        self.add_binding(addr, Binding::Datagram(queue))
+    }
+
+    fn add_binding(&self, addr: &UnixAddr, binding: Binding) -> Result<()> {
+        let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "can't find socket file"))?;
+        let mut space = self.get_space(addr)?;
+        if space.contains_key(&key) {
+            return_errno!(EADDRINUSE, "the addr is already bound");
+        } else {
+            space.insert(key, binding);
+            Ok(())
+        }
+    }
+
+    pub(super) fn add_listener(
+        &self,
+        addr: &UnixAddr,
+        capacity: usize,
+        nonblocking: bool,
+        notifier: Arc<RelayNotifier>,
+    ) -> Result<()> {
+        self.replace_bound(
+            addr,
+            Binding::StreamListener(Arc::new(Listener::new(capacity, nonblocking, notifier)?)),
+        )
+    }
+
+    pub(super) fn add_seqpacket_listener(
+        &self,
+        addr: &UnixAddr,
+        backlog: Arc<Backlog>,
+    ) -> Result<()> {
+        self.replace_bound(addr, Binding::SeqpacketListener(backlog))
+    }
+
+    // Turn a bound address into a listening one
+    fn replace_bound(&self, addr: &UnixAddr, binding: Binding) -> Result<()> {
+        let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "the socket is not bound"))?;
+        let mut space = self.get_space(addr)?;
+
+        match space.get(&key) {
+            Some(Binding::Bound) => {
+                space.insert(key, binding);
+                Ok(())
+            }
+            Some(_) => return_errno!(EINVAL, "the socket is already listened"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
+        }
+    }
+
+    pub fn resize_listener(&self, addr: &UnixAddr, capacity: usize) -> Result<()> {
+        let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "the socket is not bound"))?;
+        let mut space = self.get_space(addr)?;
+
+        match space.get(&key) {
+            Some(Binding::StreamListener(listener)) => {
+                listener.resize(capacity);
+                Ok(())
+            }
+            Some(Binding::SeqpacketListener(backlog)) => {
+                backlog.resize(capacity);
+                Ok(())
+            }
+            Some(_) => return_errno!(EINVAL, "the socket is not listening"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
+        }
+    }
+
+    pub fn push_incoming(&self, addr: &UnixAddr, sock: Endpoint) -> Result<()> {
+        self.get_listener_ref(addr)
+            .ok_or_else(|| errno!(ECONNREFUSED, "no one's listening on the remote address"))?
+            .push_incoming(sock)
+    }
+
+    pub fn pop_incoming(&self, addr: &UnixAddr) -> Result<Endpoint> {
+        self.get_listener_ref(addr)
+            .ok_or_else(|| errno!(EINVAL, "the socket is not listening"))?
+            .pop_incoming()
+            .ok_or_else(|| errno!(EAGAIN, "No connection is incoming"))
+    }
+
+    pub fn get_listener_ref(&self, addr: &UnixAddr) -> Option<Arc<Listener>> {
+        self.get_binding(addr, |binding| match binding {
+            Binding::StreamListener(listener) => Some(listener.clone()),
+            _ => None,
+        })
+        .flatten()
+    }
+
+    pub(super) fn get_seqpacket_listener(&self, addr: &UnixAddr) -> Result<Arc<Backlog>> {
+        match self.get_binding(addr, |binding| match binding {
+            Binding::SeqpacketListener(backlog) => Ok(backlog.clone()),
+            Binding::StreamListener(_) | Binding::Datagram(_) => {
+                return_errno!(
+                    EPROTOTYPE,
+                    "the socket type of the remote address mismatches"
+                )
+            }
+            Binding::Bound => {
+                return_errno!(ECONNREFUSED, "no one's listening on the remote address")
+            }
+        }) {
+            Some(res) => res,
+            None => return_errno!(ECONNREFUSED, "no one's listening on the remote address"),
+        }
+    }
+
+    pub(super) fn get_datagram(&self, addr: &UnixAddr) -> Result<Arc<MsgQueue>> {
+        match self.get_binding(addr, |binding| match binding {
+            Binding::Datagram(queue) => Ok(queue.clone()),
+            _ => return_errno!(
+                EPROTOTYPE,
+                "the socket type of the remote address mismatches"
+            ),
+        }) {
+            Some(res) => res,
+            None => return_errno!(ECONNREFUSED, "no socket is bound to the remote address"),
+        }
+    }
+
+    fn get_binding<T, F>(&self, addr: &UnixAddr, f: F) -> Option<T>
+    where
+        F: FnOnce(&Binding) -> T,
+    {
+        let key = Self::get_key(addr)?;
+        let space = self.get_space(addr).unwrap();
+        space.get(&key).map(f)
+    }
+
+    pub fn remove_addr(&self, addr: &UnixAddr) {
+        let key = Self::get_key(addr);
+        if let Some(key) = key {
+            let mut space = self.get_space(addr).unwrap();
+            space.remove(&key);
+        } else {
+            warn!("address space key not exit: {:?}", addr);
+        }
+    }
+
+    fn get_space(
+        &self,
+        addr: &UnixAddr,
+    ) -> Result<SgxMutexGuard<'_, BTreeMap<AddressSpaceKey, Binding>>> {
+        match addr {
+            UnixAddr::File(_, _) => Ok(self.file.lock().unwrap()),
+            UnixAddr::Abstract(_) => Ok(self.abstr.lock().unwrap()),
+            UnixAddr::Unnamed => return_errno!(EINVAL, "can't get path name for unnamed socket"),
+        }
+    }
+
+    fn get_key(addr: &UnixAddr) -> Option<AddressSpaceKey> {
+        trace!("addr = {:?}", addr);
+        match addr {
+            UnixAddr::File(inode_num, unix_path) if inode_num.is_some() => {
+                Some(AddressSpaceKey::from_inode(inode_num.unwrap()))
+            }
+            UnixAddr::File(_, unix_path) => {
+                let inode = {
+                    let file_path = unix_path.absolute();
+                    let current = current!();
+                    let fs = current.fs().read().unwrap();
+                    fs.lookup_inode(&file_path)
+                };
+                if let Ok(inode) = inode {
+                    Some(AddressSpaceKey::from_inode(inode.metadata().unwrap().inode))
+                } else {
+                    None
+                }
+            }
+            UnixAddr::Abstract(path) => Some(AddressSpaceKey::from_path(
+                addr.path_str().unwrap().to_string(),
+            )),
+            UnixAddr::Unnamed => None,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::gc::InflightFile;
+use super::*;
+use net::socket::{ucred_t, CMessages, CMessagesWriter, CmsgData};
+
+// The max num of file descriptors passed in one message, same with SCM_MAX_FD on Linux
+const MAX_FDS_PER_MSG: usize = 253;
+
+/// The ancillary data sent along with a message, i.e., the files passed with SCM_RIGHTS
+/// and the credentials passed with SCM_CREDENTIALS.
+///
+/// The files are held by the message, so they can be received even if the sender has
+/// closed them or exited.
+#[derive(Clone, Default)]
+pub struct Ancillary {
+    files: Vec<InflightFile>,
+    cred: Option<ucred_t>,
+}
+
+impl Ancillary {
+    /// Parse the control messages of sendmsg.
+    pub fn from_control(control: &[u8]) -> Result<Self> {
+        // CMessages takes a mutable buffer
+        let mut control = control.to_vec();
+        let current = current!();
+        let mut ancillary = Self::default();
+        for cmsg in CMessages::from_bytes(&mut control) {
+            match cmsg {
+                CmsgData::ScmRights(scm_rights) => {
+                    for fd in scm_rights.iter_fds() {
+                        ancillary.files.push(InflightFile::new(current.file(fd)?));
+                    }
+                }
+                CmsgData::ScmCredentials(cred) => {
+                    // Any uid and gid is allowed since the sender is root. But the pid
+                    // must be valid.
+                    if cred.pid != current.process().pid()
+                        && process::table::get_process(cred.pid).is_err()
+                    {
+                        return_errno!(ESRCH, "no such process");
+                    }
+                    ancillary.cred = Some(cred);
+                }
+                // Only meaningful to kTLS sockets
+                CmsgData::TlsRecordType(_) => {}
+            }
+        }
+
+        if ancillary.files.len() > MAX_FDS_PER_MSG {
+            return_errno!(EINVAL, "too many file descriptors");
+        }
+        Ok(ancillary)
+    }
+
+    /// The identities of the unix sockets among the files.
+    pub fn unix_socket_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.files.iter().filter_map(|file| file.unix_socket_id())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.cred.is_none()
+    }
+
+    pub fn cred(&self) -> Option<ucred_t> {
+        self.cred
+    }
+
+    pub fn set_cred(&mut self, cred: ucred_t) {
+        self.cred = Some(cred);
+    }
+
+    /// Merge the ancillary data of a later message, whose credentials are ignored.
+    pub fn merge(&mut self, other: Self) {
+        self.files.extend(other.files);
+        if self.cred.is_none() {
+            self.cred = other.cred;
+        }
+    }
+
+    /// Fill the control messages of recvmsg and install the received files into the
+    /// file table of the current process. The credentials are only reported if the
+    /// receiving socket has SO_PASSCRED set.
+    ///
+    /// Returns the flags and the length of the control messages. Like Linux, the files
+    /// that do not fit in the control buffer are discarded and MSG_CTRUNC is reported.
+    pub fn receive(
+        self,
+        control: Option<&mut [u8]>,
+        passcred: bool,
+        close_on_spawn: bool,
+    ) -> (MsgFlags, usize) {
+        let mut writer = CMessagesWriter::new(control.unwrap_or(&mut []));
+
+        if passcred {
+            let cred = self.cred.unwrap_or_else(ucred_t::unknown);
+            writer.push(libc::SOL_SOCKET, libc::SCM_CREDENTIALS, cred.as_bytes());
+        }
+
+        if !self.files.is_empty() {
+            let max_fds = writer.data_space() / std::mem::size_of::<FileDesc>();
+            if self.files.len() > max_fds {
+                writer.set_truncated();
+            }
+
+            let current = current!();
+            let fds_bytes: Vec<u8> = self
+                .files
+                .into_iter()
+                .take(max_fds)
+                .flat_map(|file| {
+                    current
+                        .add_file(file.file().clone(), close_on_spawn)
+                        .to_ne_bytes()
+                })
+                .collect();
+            if !fds_bytes.is_empty() {
+                writer.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, &fds_bytes);
+            }
+        }
+
+        let msg_flags = if writer.is_truncated() {
+            MsgFlags::MSG_CTRUNC
+        } else {
+            MsgFlags::empty()
+        };
+        (msg_flags, writer.len())
+    }
+}
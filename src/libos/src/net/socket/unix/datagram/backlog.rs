@@ -0,0 +1,118 @@
+use super::datagram::Datagram;
+use super::msg_queue::MsgQueue;
+use super::*;
+use events::{Waiter, WaiterQueue};
+use fs::IoEvents;
+
+/// The incoming connections of a listening seqpacket socket.
+///
+/// A connection is established by the connecting socket, which creates the socket to be
+/// accepted and pushes it into the backlog. The events of the listening socket are
+/// reported through the pollee of its own queue.
+pub struct Backlog {
+    inner: SgxMutex<BacklogInner>,
+    accept_waiters: WaiterQueue,
+    listener_queue: Arc<MsgQueue>,
+}
+
+struct BacklogInner {
+    incoming: VecDeque<Datagram>,
+    capacity: usize,
+    is_shutdown: bool,
+}
+
+impl Backlog {
+    pub fn new(capacity: usize, listener_queue: Arc<MsgQueue>) -> Self {
+        Self {
+            inner: SgxMutex::new(BacklogInner {
+                incoming: VecDeque::new(),
+                capacity,
+                is_shutdown: false,
+            }),
+            accept_waiters: WaiterQueue::new(),
+            listener_queue,
+        }
+    }
+
+    pub fn push(&self, sock: Datagram) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        // Like Linux, one more connection than the capacity is allowed
+        if inner.is_shutdown || inner.incoming.len() > inner.capacity {
+            // Drop the socket out of the lock
+            drop(inner);
+            sock.abort_connect();
+            drop(sock);
+            return_errno!(ECONNREFUSED, "the backlog is full");
+        }
+
+        inner.incoming.push_back(sock);
+        self.listener_queue.pollee().add_events(IoEvents::IN);
+        drop(inner);
+
+        self.accept_waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    pub fn pop(&self, nonblocking: bool) -> Result<Datagram> {
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(sock) = inner.incoming.pop_front() {
+                if inner.incoming.is_empty() {
+                    self.listener_queue.pollee().del_events(IoEvents::IN);
+                }
+                return Ok(sock);
+            }
+
+            if inner.is_shutdown {
+                return_errno!(EINVAL, "the socket is not listening");
+            }
+            if nonblocking {
+                return_errno!(EAGAIN, "no connection is incoming");
+            }
+            self.accept_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            waiter.wait(None)?;
+        }
+    }
+
+    pub fn resize(&self, capacity: usize) {
+        self.inner.lock().unwrap().capacity = capacity;
+    }
+
+    /// The identities of the unix sockets in flight to the pending connections.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .incoming
+            .iter()
+            .flat_map(|sock| sock.receiver().inflight_sockets())
+            .collect()
+    }
+
+    /// Discard the messages to the pending connections.
+    pub fn discard_msgs(&self) {
+        let queues: Vec<Arc<MsgQueue>> = {
+            let inner = self.inner.lock().unwrap();
+            let queues = inner.incoming.iter().map(|sock| sock.receiver().clone());
+            queues.collect()
+        };
+        for queue in queues {
+            queue.discard_msgs();
+        }
+    }
+
+    /// Refuse all the pending and future connections.
+    pub fn shutdown(&self) {
+        let pending_socks = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.is_shutdown = true;
+            std::mem::take(&mut inner.incoming)
+        };
+        // The connecting sockets get EOF when the pending sockets are dropped
+        drop(pending_socks);
+
+        self.accept_waiters.dequeue_and_wake_all();
+    }
+}
@@ -0,0 +1,535 @@
+use super::address_space::{AddressSpace, ADDRESS_SPACE};
+use super::ancillary::Ancillary;
+use super::backlog::Backlog;
+use super::msg_queue::{Message, MsgQueue};
+use super::*;
+use fs::IoEvents;
+use net::socket::ucred_t;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// SOCK_DGRAM and SOCK_SEQPACKET Unix socket.
+///
+/// Both types preserve message boundaries. Every socket owns a queue of received messages,
+/// to which the messages are pushed by the sending sockets directly. A datagram socket is
+/// reachable by its queue registered in the address space once it is bound, so that it can
+/// receive messages from sockets that are neither bound nor connected. A seqpacket socket is
+/// connection-oriented like a stream socket: it has three statuses, i.e., unconnected,
+/// listening and connected, and it can only exchange messages with its connected peer.
+pub struct Datagram {
+    type_: SocketType,
+    receiver: Arc<MsgQueue>,
+    inner: SgxMutex<Inner>,
+    nonblocking: AtomicBool,
+    passcred: AtomicBool,
+}
+
+struct Inner {
+    addr: Option<UnixAddr>,
+    // Whether the address is bound by this socket, rather than inherited from the listener
+    is_bound: bool,
+    is_write_shutdown: bool,
+    status: Status,
+    send_timeout: Option<Duration>,
+    recv_timeout: Option<Duration>,
+}
+
+enum Status {
+    Idle,
+    Listening(Arc<Backlog>),
+    Connected(Peer),
+}
+
+#[derive(Clone)]
+struct Peer {
+    queue: Arc<MsgQueue>,
+    addr: Option<UnixAddr>,
+}
+
+impl Datagram {
+    pub fn new(type_: SocketType, flags: SocketFlags) -> Self {
+        debug_assert!(type_ == SocketType::DGRAM || type_ == SocketType::SEQPACKET);
+        Self::new_with_status(
+            type_,
+            Arc::new(MsgQueue::new()),
+            None,
+            Status::Idle,
+            flags.contains(SocketFlags::SOCK_NONBLOCK),
+        )
+    }
+
+    fn new_with_status(
+        type_: SocketType,
+        receiver: Arc<MsgQueue>,
+        addr: Option<UnixAddr>,
+        status: Status,
+        nonblocking: bool,
+    ) -> Self {
+        Self {
+            type_,
+            receiver,
+            inner: SgxMutex::new(Inner {
+                addr,
+                is_bound: false,
+                is_write_shutdown: false,
+                status,
+                send_timeout: None,
+                recv_timeout: None,
+            }),
+            nonblocking: AtomicBool::new(nonblocking),
+            passcred: AtomicBool::new(false),
+        }
+    }
+
+    pub fn socketpair(type_: SocketType, flags: SocketFlags) -> Result<(Self, Self)> {
+        let nonblocking = flags.contains(SocketFlags::SOCK_NONBLOCK);
+        let queue_a = Arc::new(MsgQueue::new());
+        let queue_b = Arc::new(MsgQueue::new());
+        queue_a.add_writer(&queue_b);
+        queue_b.add_writer(&queue_a);
+        let peer_of = |queue: &Arc<MsgQueue>| {
+            Status::Connected(Peer {
+                queue: queue.clone(),
+                addr: None,
+            })
+        };
+
+        let socket_a =
+            Self::new_with_status(type_, queue_a.clone(), None, peer_of(&queue_b), nonblocking);
+        let socket_b = Self::new_with_status(type_, queue_b, None, peer_of(&queue_a), nonblocking);
+        Ok((socket_a, socket_b))
+    }
+
+    pub fn socket_type(&self) -> SocketType {
+        self.type_
+    }
+
+    pub fn addr(&self) -> UnixAddr {
+        self.inner().addr.clone().unwrap_or(UnixAddr::Unnamed)
+    }
+
+    pub fn peer_addr(&self) -> Result<UnixAddr> {
+        match &self.inner().status {
+            Status::Connected(peer) => Ok(peer.addr.clone().unwrap_or(UnixAddr::Unnamed)),
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    pub fn bind(&self, addr: &UnixAddr) -> Result<()> {
+        let mut unix_addr = addr.clone();
+        let addr = &mut unix_addr;
+
+        let mut inner = self.inner();
+        if inner.addr.is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+
+        // create the corresponding file in the fs and fill Addr with its inode
+        AddressSpace::create_socket_file(addr)?;
+        match self.type_ {
+            SocketType::DGRAM => ADDRESS_SPACE.add_datagram(addr, self.receiver.clone())?,
+            _ => ADDRESS_SPACE.add_binder(addr)?,
+        }
+
+        inner.addr = Some(addr.clone());
+        inner.is_bound = true;
+        Ok(())
+    }
+
+    pub fn listen(&self, backlog: i32) -> Result<()> {
+        if self.type_ != SocketType::SEQPACKET {
+            return_errno!(EOPNOTSUPP, "listen is not supported by datagram socket");
+        }
+        if backlog < 0 {
+            return_errno!(EINVAL, "negative backlog is not supported");
+        }
+        // Like Linux, the backlog is silently truncated to net.core.somaxconn
+        let capacity = (backlog as u32).min(misc::sysctl::somaxconn()) as usize;
+
+        let mut inner = self.inner();
+        match &inner.status {
+            Status::Idle => {
+                let addr = match &inner.addr {
+                    Some(addr) => addr.clone(),
+                    None => return_errno!(EINVAL, "the socket is not bound"),
+                };
+                let backlog = Arc::new(Backlog::new(capacity, self.receiver.clone()));
+                ADDRESS_SPACE.add_seqpacket_listener(&addr, backlog.clone())?;
+                inner.status = Status::Listening(backlog);
+            }
+            Status::Connected(_) => return_errno!(EINVAL, "the socket is already connected"),
+            // Modify the capacity of the backlog
+            Status::Listening(backlog) => backlog.resize(capacity),
+        }
+
+        Ok(())
+    }
+
+    /// Connect to a socket. The connection of a seqpacket socket is established immediately
+    /// if the listener is not full, so it will never block.
+    pub fn connect(&self, addr: &UnixAddr) -> Result<()> {
+        debug!("connect to {:?}", addr);
+
+        match self.type_ {
+            SocketType::DGRAM => self.connect_datagram(addr),
+            _ => self.connect_seqpacket(addr),
+        }
+    }
+
+    // Connecting a datagram socket sets the default destination, which can be changed by
+    // connecting again, and the only socket that messages are accepted from.
+    fn connect_datagram(&self, addr: &UnixAddr) -> Result<()> {
+        let queue = ADDRESS_SPACE.get_datagram(addr)?;
+        queue.add_writer(&self.receiver);
+        self.receiver.set_peer(Some(&queue));
+        self.inner().status = Status::Connected(Peer {
+            queue,
+            addr: Some(addr.clone()),
+        });
+        Ok(())
+    }
+
+    /// Dissolve the association of a datagram socket, i.e., connect to AF_UNSPEC address.
+    pub fn disconnect(&self) -> Result<()> {
+        if self.type_ != SocketType::DGRAM {
+            return_errno!(EINVAL, "invalid address family for connect");
+        }
+        self.receiver.set_peer(None);
+        self.inner().status = Status::Idle;
+        Ok(())
+    }
+
+    fn connect_seqpacket(&self, addr: &UnixAddr) -> Result<()> {
+        let mut inner = self.inner();
+        match &inner.status {
+            Status::Idle => {}
+            Status::Connected(_) => return_errno!(EISCONN, "already connected"),
+            Status::Listening(_) => return_errno!(EINVAL, "invalid socket for connect"),
+        }
+        if inner.addr.as_ref() == Some(addr) {
+            return_errno!(EINVAL, "self connect is not supported");
+        }
+
+        let backlog = ADDRESS_SPACE.get_seqpacket_listener(addr)?;
+        let incoming_sock = Self::new_with_status(
+            self.type_,
+            Arc::new(MsgQueue::new()),
+            Some(addr.clone()),
+            Status::Connected(Peer {
+                queue: self.receiver.clone(),
+                addr: inner.addr.clone(),
+            }),
+            false,
+        );
+        let incoming_queue = incoming_sock.receiver.clone();
+        backlog.push(incoming_sock)?;
+        incoming_queue.add_writer(&self.receiver);
+        self.receiver.add_writer(&incoming_queue);
+
+        inner.status = Status::Connected(Peer {
+            queue: incoming_queue,
+            addr: Some(addr.clone()),
+        });
+        Ok(())
+    }
+
+    pub fn accept(&self, flags: SocketFlags) -> Result<(Self, Option<UnixAddr>)> {
+        let backlog = match &self.inner().status {
+            Status::Listening(backlog) => backlog.clone(),
+            _ => return_errno!(EINVAL, "the socket is not listening"),
+        };
+
+        let sock = backlog.pop(self.nonblocking())?;
+        sock.set_nonblocking(flags.contains(SocketFlags::SOCK_NONBLOCK));
+        // SO_PASSCRED is inherited from the listener
+        sock.set_passcred(self.passcred());
+        let peer_addr = sock.peer_addr().ok();
+
+        debug!("accept socket from {:?}", peer_addr);
+
+        Ok((sock, peer_addr))
+    }
+
+    pub fn sendto(&self, buf: &[u8], flags: SendFlags, addr: Option<&UnixAddr>) -> Result<usize> {
+        self.sendmsg(&[buf], flags, addr, None)
+    }
+
+    pub fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<UnixAddr>)> {
+        let (data_len, addr, _, _) = self.recvmsg(&mut [buf], flags, None)?;
+        let addr = match addr {
+            Some(AnyAddr::Unix(unix_addr)) => Some(unix_addr),
+            _ => None,
+        };
+        Ok((data_len, addr))
+    }
+
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        flags: SendFlags,
+        addr: Option<&UnixAddr>,
+        control: Option<&[u8]>,
+    ) -> Result<usize> {
+        let unsupported_flags = flags - (SendFlags::MSG_DONTWAIT | SendFlags::MSG_NOSIGNAL);
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let (dest_queue, self_addr) = {
+            let inner = self.inner();
+            if inner.is_write_shutdown {
+                return_errno!(EPIPE, "the socket is shut down for writing");
+            }
+
+            let dest_queue = match (self.type_, addr, &inner.status) {
+                (SocketType::DGRAM, Some(addr), _) => ADDRESS_SPACE.get_datagram(addr)?,
+                // The destination address of a seqpacket socket is ignored, like Linux
+                (_, _, Status::Connected(peer)) => peer.queue.clone(),
+                (SocketType::DGRAM, None, _) => {
+                    return_errno!(ENOTCONN, "the destination address is required")
+                }
+                (_, _, _) => return_errno!(ENOTCONN, "the socket is not connected"),
+            };
+            (dest_queue, inner.addr.clone())
+        };
+        // Like Linux, a datagram socket connected to another socket refuses the others
+        if self.type_ == SocketType::DGRAM && !dest_queue.accepts_from(&self.receiver) {
+            return_errno!(EPERM, "the destination is connected to another socket");
+        }
+
+        let mut ancillary = match control {
+            Some(control) => Ancillary::from_control(control)?,
+            None => Ancillary::default(),
+        };
+        // The credentials of the sender are always sent, which are reported to the
+        // receiver with SO_PASSCRED set
+        if ancillary.cred().is_none() {
+            ancillary.set_cred(ucred_t::current());
+        }
+        let data = bufs.concat();
+        let data_len = data.len();
+        let msg = Message {
+            data,
+            addr: AnyAddr::Unix(self_addr.unwrap_or(UnixAddr::Unnamed)),
+            ancillary,
+        };
+
+        let nonblocking = self.nonblocking() || flags.contains(SendFlags::MSG_DONTWAIT);
+        let timeout = self.inner().send_timeout;
+        dest_queue
+            .push(msg, nonblocking, timeout)
+            .map_err(|e| match e.errno() {
+                // The peer of a datagram socket is gone
+                EPIPE if self.type_ == SocketType::DGRAM => {
+                    errno!(ECONNREFUSED, "the destination socket is closed")
+                }
+                _ => e,
+            })?;
+        Ok(data_len)
+    }
+
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        control: Option<&mut [u8]>,
+    ) -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
+        let unsupported_flags = flags
+            - (RecvFlags::MSG_PEEK
+                | RecvFlags::MSG_TRUNC
+                | RecvFlags::MSG_DONTWAIT
+                | RecvFlags::MSG_CMSG_CLOEXEC);
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
+        }
+
+        let timeout = {
+            let inner = self.inner();
+            if let Status::Listening(_) = &inner.status {
+                return_errno!(ENOTCONN, "the socket is listening");
+            }
+            inner.recv_timeout
+        };
+
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        let msg = match self
+            .receiver
+            .pop(flags.contains(RecvFlags::MSG_PEEK), nonblocking, timeout)?
+        {
+            Some(msg) => msg,
+            // End of file
+            None => return Ok((0, None, MsgFlags::empty(), 0)),
+        };
+
+        let mut msg_flags = MsgFlags::empty();
+        let mut copied_len = 0;
+        for buf in bufs.iter_mut() {
+            let len = buf.len().min(msg.data.len() - copied_len);
+            buf[..len].copy_from_slice(&msg.data[copied_len..copied_len + len]);
+            copied_len += len;
+        }
+        if copied_len < msg.data.len() {
+            msg_flags |= MsgFlags::MSG_TRUNC;
+        }
+
+        let (ancillary_flags, control_len) = msg.ancillary.receive(
+            control,
+            self.passcred(),
+            flags.contains(RecvFlags::MSG_CMSG_CLOEXEC),
+        );
+        msg_flags |= ancillary_flags;
+
+        let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+            msg.data.len()
+        } else {
+            copied_len
+        };
+        Ok((data_len, Some(msg.addr), msg_flags, control_len))
+    }
+
+    /// perform shutdown on the socket.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        let mut inner = self.inner();
+        let peer_queue = match &inner.status {
+            Status::Connected(peer) => Some(peer.queue.clone()),
+            _ if self.type_ == SocketType::DGRAM => None,
+            _ => return_errno!(ENOTCONN, "The socket is not connected."),
+        };
+
+        if how.should_shut_read() {
+            self.receiver.close(false);
+        }
+        if how.should_shut_write() {
+            inner.is_write_shutdown = true;
+            // The peer of a seqpacket socket gets EOF after receiving all the messages
+            if self.type_ == SocketType::SEQPACKET {
+                peer_queue.unwrap().set_eof();
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn poll_events(&self) -> IoEvents {
+        let inner = self.inner();
+        match &inner.status {
+            // linux return value
+            Status::Idle if self.type_ == SocketType::SEQPACKET => IoEvents::OUT | IoEvents::HUP,
+            Status::Listening(_) => self.receiver.pollee().poll(IoEvents::IN, None),
+            _ => {
+                let mut events = self.receiver.pollee().poll(IoEvents::all(), None);
+                // A connected socket is writable only if its peer has room. The peer
+                // notifies this socket when it has.
+                let is_writable = match &inner.status {
+                    Status::Connected(peer) => peer.queue.is_writable(),
+                    _ => true,
+                };
+                if !inner.is_write_shutdown && is_writable {
+                    events |= IoEvents::OUT;
+                }
+                events
+            }
+        }
+    }
+
+    pub(super) fn receiver(&self) -> &Arc<MsgQueue> {
+        &self.receiver
+    }
+
+    /// The identities of the unix sockets in flight to this socket, including those to
+    /// the connections that are not accepted yet.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
+        let mut ids = self.receiver.inflight_sockets();
+        if let Status::Listening(backlog) = &self.inner().status {
+            ids.extend(backlog.inflight_sockets());
+        }
+        ids
+    }
+
+    /// Discard the messages to this socket and to the connections that are not accepted
+    /// yet, which can never be received.
+    pub fn discard_inflight_files(&self) {
+        self.receiver.discard_msgs();
+        let backlog = match &self.inner().status {
+            Status::Listening(backlog) => backlog.clone(),
+            _ => return,
+        };
+        backlog.discard_msgs();
+    }
+
+    /// Forget the peer of a connecting socket that is refused by the listener, so that
+    /// the connecting socket is not affected when this socket is dropped.
+    pub(super) fn abort_connect(&self) {
+        self.inner().status = Status::Idle;
+    }
+
+    pub(super) fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    pub(super) fn send_timeout(&self) -> Option<Duration> {
+        self.inner().send_timeout
+    }
+
+    pub(super) fn set_send_timeout(&self, timeout: Duration) {
+        // A zero timeout means blocking forever
+        self.inner().send_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+    }
+
+    pub(super) fn recv_timeout(&self) -> Option<Duration> {
+        self.inner().recv_timeout
+    }
+
+    pub(super) fn set_recv_timeout(&self, timeout: Duration) {
+        self.inner().recv_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+    }
+
+    pub(super) fn passcred(&self) -> bool {
+        self.passcred.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_passcred(&self, passcred: bool) {
+        self.passcred.store(passcred, Ordering::Relaxed);
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl Debug for Datagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Datagram")
+            .field("type", &self.type_)
+            .field("addr", &self.addr())
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for Datagram {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        if inner.is_bound {
+            ADDRESS_SPACE.remove_addr(inner.addr.as_ref().unwrap());
+        }
+
+        match &inner.status {
+            Status::Listening(backlog) => {
+                // refuse the connecting sockets and wake up the accepting threads
+                backlog.shutdown();
+            }
+            Status::Connected(peer) if self.type_ == SocketType::SEQPACKET => {
+                peer.queue.set_eof();
+            }
+            _ => {}
+        }
+
+        // The senders holding the queue get errors from now on
+        self.receiver.close(true);
+    }
+}
@@ -0,0 +1,129 @@
+use super::datagram::Datagram;
+use super::*;
+use fs::{AccessMode, File, IoEvents, IoNotifier, StatusFlags};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+
+use crate::fs::{GetReadBufLen, IoctlCmd, SetNonBlocking};
+use crate::net::socket::sockopt::timeout_to_timeval;
+use crate::net::{
+    GetPassCredCmd, GetRecvTimeoutCmd, GetSendTimeoutCmd, SetPassCredCmd, SetRecvTimeoutCmd,
+    SetSendTimeoutCmd,
+};
+
+impl File for Datagram {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recvfrom(buf, RecvFlags::empty())
+            .map(|(data_len, _)| data_len)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.sendto(buf, SendFlags::empty(), None)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        self.recvmsg(bufs, RecvFlags::empty(), None)
+            .map(|(data_len, ..)| data_len)
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.sendmsg(bufs, SendFlags::empty(), None, None)
+    }
+
+    fn ioctl(&self, cmd: &mut dyn IoctlCmd) -> Result<()> {
+        match_ioctl_cmd_auto_error!(cmd, {
+            cmd : GetReadBufLen => {
+                // The length of the next message, like Linux
+                let bytes_to_read = self.receiver().next_msg_len().min(std::i32::MAX as usize) as i32;
+                cmd.set_output(bytes_to_read as _);
+            },
+            cmd : SetNonBlocking => {
+                let nonblocking = cmd.input();
+                self.set_nonblocking(*nonblocking != 0);
+            },
+            cmd : SetPassCredCmd => {
+                self.set_passcred(*cmd.input());
+            },
+            cmd : GetPassCredCmd => {
+                cmd.set_output(self.passcred() as i32);
+            },
+            cmd : SetRecvTimeoutCmd => {
+                self.set_recv_timeout(*cmd.input());
+            },
+            cmd : SetSendTimeoutCmd => {
+                self.set_send_timeout(*cmd.input());
+            },
+            cmd : GetRecvTimeoutCmd => {
+                cmd.set_output(timeout_to_timeval(self.recv_timeout()));
+            },
+            cmd : GetSendTimeoutCmd => {
+                cmd.set_output(timeout_to_timeval(self.send_timeout()));
+            }
+        });
+        Ok(())
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        if self.nonblocking() {
+            Ok(StatusFlags::O_NONBLOCK)
+        } else {
+            Ok(StatusFlags::empty())
+        }
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        let nonblocking = new_status_flags.contains(StatusFlags::O_NONBLOCK);
+        self.set_nonblocking(nonblocking);
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.poll_events()
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.receiver().pollee().notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
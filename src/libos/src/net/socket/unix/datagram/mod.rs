@@ -0,0 +1,11 @@
+use super::stream::DEFAULT_BUF_SIZE;
+use super::*;
+
+mod backlog;
+mod datagram;
+mod file;
+mod msg_queue;
+
+pub use self::backlog::Backlog;
+pub use self::datagram::Datagram;
+pub use self::msg_queue::{Message, MsgQueue};
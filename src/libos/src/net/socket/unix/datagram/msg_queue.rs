@@ -0,0 +1,267 @@
+use super::ancillary::Ancillary;
+use super::*;
+use events::{Pollee, Waiter, WaiterQueue};
+use fs::IoEvents;
+use std::sync::Weak;
+use std::time::Duration;
+
+// The max num of messages in a queue, same with /proc/sys/net/unix/max_dgram_qlen on
+// most Linux distributions
+const MAX_QUEUE_LEN: usize = 512;
+
+/// A message with its sender address and ancillary data.
+#[derive(Clone)]
+pub struct Message {
+    pub data: Vec<u8>,
+    pub addr: AnyAddr,
+    pub ancillary: Ancillary,
+}
+
+/// The queue of received messages of a datagram or seqpacket unix socket, or a loopback
+/// UDP socket.
+///
+/// The events of the socket are maintained by the pollee of its queue, except for
+/// `IoEvents::OUT`, which depends on the queue of the peer. The queues of the connected
+/// peers are notified with `IoEvents::OUT` when this queue has more room.
+pub struct MsgQueue {
+    inner: SgxMutex<MsgQueueInner>,
+    send_waiters: WaiterQueue,
+    recv_waiters: WaiterQueue,
+    pollee: Pollee,
+    writers: SgxMutex<Vec<Weak<MsgQueue>>>,
+    // The queue of the peer that a datagram socket is connected to
+    peer: SgxMutex<Option<Weak<MsgQueue>>>,
+}
+
+struct MsgQueueInner {
+    msgs: VecDeque<Message>,
+    num_bytes: usize,
+    // No more messages can be pushed, i.e., the owner socket is closed or shut down
+    // for reading
+    is_closed: bool,
+    // No more messages will be pushed, i.e., the peer of a seqpacket socket is closed
+    // or shut down for writing
+    is_eof: bool,
+}
+
+impl MsgQueue {
+    pub fn new() -> Self {
+        Self {
+            inner: SgxMutex::new(MsgQueueInner {
+                msgs: VecDeque::new(),
+                num_bytes: 0,
+                is_closed: false,
+                is_eof: false,
+            }),
+            send_waiters: WaiterQueue::new(),
+            recv_waiters: WaiterQueue::new(),
+            pollee: Pollee::new(IoEvents::empty()),
+            writers: SgxMutex::new(Vec::new()),
+            peer: SgxMutex::new(None),
+        }
+    }
+
+    pub fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+
+    /// Register the queue of a socket that is connected to the owner of this queue, so
+    /// that the socket is notified with `IoEvents::OUT` when this queue has more room.
+    pub fn add_writer(&self, writer: &Arc<MsgQueue>) {
+        let mut writers = self.writers.lock().unwrap();
+        writers.retain(|writer| writer.strong_count() > 0);
+        writers.push(Arc::downgrade(writer));
+    }
+
+    /// Set the queue of the peer that the owner of this queue is connected to.
+    pub fn set_peer(&self, peer: Option<&Arc<MsgQueue>>) {
+        *self.peer.lock().unwrap() = peer.map(Arc::downgrade);
+    }
+
+    /// Whether messages from the owner of the given queue are accepted, i.e., the owner
+    /// of this queue is not connected, or is connected to the sender.
+    pub fn accepts_from(&self, sender: &Arc<MsgQueue>) -> bool {
+        match &*self.peer.lock().unwrap() {
+            Some(peer) => std::ptr::eq(peer.as_ptr(), Arc::as_ptr(sender)),
+            None => true,
+        }
+    }
+
+    /// Whether a message can be pushed without blocking.
+    pub fn is_writable(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.is_closed || inner.has_space(0)
+    }
+
+    /// The identities of the unix sockets passed with the queued messages.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .msgs
+            .iter()
+            .flat_map(|msg| msg.ancillary.unix_socket_ids())
+            .collect()
+    }
+
+    /// Push a message, blocking if the queue is full until the timeout expires.
+    pub fn push(&self, msg: Message, nonblocking: bool, timeout: Option<Duration>) -> Result<()> {
+        if msg.data.len() > DEFAULT_BUF_SIZE {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+
+        let mut timeout = timeout;
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_closed {
+                return_errno!(EPIPE, "the receiving socket is closed");
+            }
+
+            if inner.has_space(msg.data.len()) {
+                inner.num_bytes += msg.data.len();
+                inner.msgs.push_back(msg);
+                self.pollee.add_events(IoEvents::IN);
+                drop(inner);
+
+                self.recv_waiters.dequeue_and_wake_all();
+                return Ok(());
+            }
+
+            if nonblocking {
+                return_errno!(EAGAIN, "the receiving queue is full");
+            }
+            self.send_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            wait_with_timeout(&waiter, timeout.as_mut())?;
+        }
+    }
+
+    /// Pop the first message, blocking if the queue is empty until the timeout expires.
+    ///
+    /// Returns `None` if there will never be any messages. With `peek`, the message
+    /// is kept in the queue.
+    pub fn pop(
+        &self,
+        peek: bool,
+        nonblocking: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Message>> {
+        let mut timeout = timeout;
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if peek {
+                if let Some(msg) = inner.msgs.front() {
+                    return Ok(Some(msg.clone()));
+                }
+            } else if let Some(msg) = inner.msgs.pop_front() {
+                inner.num_bytes -= msg.data.len();
+                if inner.msgs.is_empty() && !inner.is_eof && !inner.is_closed {
+                    self.pollee.del_events(IoEvents::IN);
+                }
+                drop(inner);
+
+                self.send_waiters.dequeue_and_wake_all();
+                self.notify_writers();
+                return Ok(Some(msg));
+            }
+
+            if inner.is_eof || inner.is_closed {
+                return Ok(None);
+            }
+            if nonblocking {
+                return_errno!(EAGAIN, "no message is available");
+            }
+            self.recv_waiters.reset_and_enqueue(&waiter);
+            drop(inner);
+
+            wait_with_timeout(&waiter, timeout.as_mut())?;
+        }
+    }
+
+    /// The length of the first message.
+    pub fn next_msg_len(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.msgs.front().map(|msg| msg.data.len()).unwrap_or(0)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().is_closed
+    }
+
+    /// Stop receiving messages. The messages that are already queued can still be
+    /// received, unless `discard` is true.
+    pub fn close(&self, discard: bool) {
+        let discarded_msgs = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.is_closed = true;
+            self.pollee.add_events(IoEvents::IN | IoEvents::RDHUP);
+            if discard {
+                inner.num_bytes = 0;
+                std::mem::take(&mut inner.msgs)
+            } else {
+                VecDeque::new()
+            }
+        };
+        // The files passed with the messages are released out of the lock
+        drop(discarded_msgs);
+
+        self.send_waiters.dequeue_and_wake_all();
+        self.recv_waiters.dequeue_and_wake_all();
+        // The writers get errors from now on
+        self.notify_writers();
+    }
+
+    /// Discard the queued messages, whose files are released out of the lock.
+    pub fn discard_msgs(&self) {
+        let discarded_msgs = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.num_bytes = 0;
+            if !inner.is_eof && !inner.is_closed {
+                self.pollee.del_events(IoEvents::IN);
+            }
+            std::mem::take(&mut inner.msgs)
+        };
+        drop(discarded_msgs);
+
+        self.send_waiters.dequeue_and_wake_all();
+        self.notify_writers();
+    }
+
+    /// Mark that no more messages will arrive.
+    pub fn set_eof(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.is_eof = true;
+        self.pollee
+            .add_events(IoEvents::IN | IoEvents::RDHUP | IoEvents::HUP);
+        drop(inner);
+
+        self.recv_waiters.dequeue_and_wake_all();
+    }
+
+    fn notify_writers(&self) {
+        let writers: Vec<Arc<MsgQueue>> = {
+            let writers = self.writers.lock().unwrap();
+            writers.iter().filter_map(|writer| writer.upgrade()).collect()
+        };
+        // `IoEvents::OUT` is not kept by the pollee, so only the pollers are notified
+        for writer in writers {
+            writer.pollee.notifier().broadcast(&IoEvents::OUT);
+        }
+    }
+}
+
+impl MsgQueueInner {
+    fn has_space(&self, len: usize) -> bool {
+        self.num_bytes + len <= DEFAULT_BUF_SIZE && self.msgs.len() < MAX_QUEUE_LEN
+    }
+}
+
+// Like Linux, a blocking socket operation that times out fails with EAGAIN
+fn wait_with_timeout(waiter: &Waiter, timeout: Option<&mut Duration>) -> Result<()> {
+    waiter.wait_mut(timeout).map_err(|e| match e.errno() {
+        ETIMEDOUT => errno!(EAGAIN, "the socket operation timed out"),
+        _ => e,
+    })
+}
@@ -0,0 +1,153 @@
+use super::*;
+use std::sync::Weak;
+
+lazy_static! {
+    // The unix sockets in flight, indexed by their identities
+    static ref INFLIGHT_SOCKETS: SgxMutex<HashMap<usize, InflightSocket>> =
+        SgxMutex::new(HashMap::new());
+}
+
+struct InflightSocket {
+    file: Weak<dyn File>,
+    // The num of `InflightFile`s holding the socket
+    count: usize,
+}
+
+/// A file passed with SCM_RIGHTS, which is in flight until it is received.
+///
+/// The unix sockets in flight are tracked so that the ones that are only reachable from
+/// the receiving queues of each other can be released by `unix_gc`.
+pub struct InflightFile {
+    file: FileRef,
+    is_unix_socket: bool,
+}
+
+impl InflightFile {
+    pub fn new(file: FileRef) -> Self {
+        let is_unix_socket = file.as_unix_datagram().is_ok() || file.as_unix_socket().is_ok();
+        if is_unix_socket {
+            let mut inflight_sockets = INFLIGHT_SOCKETS.lock().unwrap();
+            inflight_sockets
+                .entry(file_id(&file))
+                .or_insert_with(|| InflightSocket {
+                    file: Arc::downgrade(&file),
+                    count: 0,
+                })
+                .count += 1;
+        }
+        Self {
+            file,
+            is_unix_socket,
+        }
+    }
+
+    pub fn file(&self) -> &FileRef {
+        &self.file
+    }
+
+    /// The identity of the file if it is a unix socket.
+    pub fn unix_socket_id(&self) -> Option<usize> {
+        self.is_unix_socket.then(|| file_id(&self.file))
+    }
+}
+
+impl Clone for InflightFile {
+    fn clone(&self) -> Self {
+        Self::new(self.file.clone())
+    }
+}
+
+impl Drop for InflightFile {
+    fn drop(&mut self) {
+        if !self.is_unix_socket {
+            return;
+        }
+        let id = file_id(&self.file);
+        let mut inflight_sockets = INFLIGHT_SOCKETS.lock().unwrap();
+        let socket = inflight_sockets.get_mut(&id).unwrap();
+        socket.count -= 1;
+        if socket.count == 0 {
+            inflight_sockets.remove(&id);
+        }
+    }
+}
+
+/// Release the unix sockets that can never be received, like the function of the same
+/// name in Linux.
+///
+/// A unix socket in flight is held by the message carrying it, so the sockets passed to
+/// each other, e.g., over themselves, would never be released. A socket is a candidate if
+/// all its references are from the files in flight. The candidates that are held by the
+/// queues of other sockets are alive, so are the candidates reachable from them through
+/// the queues. The queues of the remaining candidates can never be read, so the messages
+/// in them are discarded, which releases the candidates.
+pub fn unix_gc() {
+    let mut candidates: HashMap<usize, (FileRef, usize)> = {
+        let inflight_sockets = INFLIGHT_SOCKETS.lock().unwrap();
+        inflight_sockets
+            .iter()
+            .filter_map(|(id, socket)| {
+                let file = socket.file.upgrade()?;
+                // Exclude the reference that is just upgraded
+                if Arc::strong_count(&file) - 1 == socket.count {
+                    Some((*id, (file, socket.count)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+    if candidates.is_empty() {
+        return;
+    }
+
+    // Count the references from the queues of the candidates. What remains are the
+    // references from outside.
+    let children: HashMap<usize, Vec<usize>> = candidates
+        .iter()
+        .map(|(id, (file, _))| (*id, inflight_sockets_of(file)))
+        .collect();
+    for child_id in children.values().flatten() {
+        if let Some((_, count)) = candidates.get_mut(child_id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    let mut alive: Vec<usize> = candidates
+        .iter()
+        .filter(|(_, (_, count))| *count > 0)
+        .map(|(id, _)| *id)
+        .collect();
+    while let Some(id) = alive.pop() {
+        if candidates.remove(&id).is_some() {
+            alive.extend(&children[&id]);
+        }
+    }
+
+    for (file, _) in candidates.values() {
+        debug!("release the unix socket in flight: {:?}", file);
+        if let Ok(socket) = file.as_unix_datagram() {
+            socket.discard_inflight_files();
+        } else if let Ok(socket) = file.as_unix_socket() {
+            socket.discard_inflight_files();
+        }
+    }
+    // The candidates are released when the last references, i.e., `candidates`, are
+    // dropped
+}
+
+// The identities of the unix sockets in flight to the socket, including those to its
+// connections that are not accepted yet
+fn inflight_sockets_of(file: &FileRef) -> Vec<usize> {
+    if let Ok(socket) = file.as_unix_datagram() {
+        socket.inflight_sockets()
+    } else if let Ok(socket) = file.as_unix_socket() {
+        socket.inflight_sockets()
+    } else {
+        Vec::new()
+    }
+}
+
+fn file_id(file: &FileRef) -> usize {
+    Arc::as_ptr(file) as *const u8 as usize
+}
@@ -1,9 +1,12 @@
+use super::ancillary::Ancillary;
 use super::*;
 use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::{Channel, Consumer, Producer};
 use fs::{IoEvents, IoNotifier};
+use net::socket::ucred_t;
 use std::any::Any;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 pub type Endpoint = Arc<Inner>;
 
@@ -11,20 +14,27 @@ pub type Endpoint = Arc<Inner>;
 pub fn end_pair(nonblocking: bool) -> Result<(Endpoint, Endpoint)> {
     let (pro_a, con_a) = Channel::new(DEFAULT_BUF_SIZE)?.split();
     let (pro_b, con_b) = Channel::new(DEFAULT_BUF_SIZE)?.split();
+    let ancillary_a = Arc::new(AncillaryQueue::new());
+    let ancillary_b = Arc::new(AncillaryQueue::new());
+    let cred = ucred_t::current();
 
     let mut end_a = Arc::new(Inner {
         addr: RwLock::new(None),
         reader: con_a,
         writer: pro_b,
+        recv_ancillary: ancillary_a.clone(),
+        send_ancillary: ancillary_b.clone(),
         peer: Weak::default(),
-        ancillary: RwLock::new(None),
+        cred: RwLock::new(cred),
     });
     let end_b = Arc::new(Inner {
         addr: RwLock::new(None),
         reader: con_b,
         writer: pro_a,
+        recv_ancillary: ancillary_b,
+        send_ancillary: ancillary_a,
         peer: Arc::downgrade(&end_a),
-        ancillary: RwLock::new(None),
+        cred: RwLock::new(cred),
     });
 
     unsafe {
@@ -42,8 +52,11 @@ pub struct Inner {
     addr: RwLock<Option<UnixAddr>>,
     reader: Consumer<u8>,
     writer: Producer<u8>,
+    recv_ancillary: Arc<AncillaryQueue>,
+    send_ancillary: Arc<AncillaryQueue>,
     peer: Weak<Self>,
-    ancillary: RwLock<Option<Ancillary>>,
+    // The credentials of the process owning this end when the connection is established
+    cred: RwLock<ucred_t>,
 }
 
 impl Inner {
@@ -71,19 +84,137 @@ impl Inner {
         cons_nonblocking
     }
     pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
-        self.reader.pop_slice(buf)
+        self.readv(&mut [buf])
     }
 
     pub fn write(&self, buf: &[u8]) -> Result<usize> {
-        self.writer.push_slice(buf)
+        self.writev(&[buf])
     }
 
     pub fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
-        self.reader.pop_slices(bufs)
+        self.recvmsg(bufs, None).map(|(len, _)| len)
     }
 
     pub fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        self.writer.push_slices(bufs)
+        self.sendmsg(bufs, None, None)
+    }
+
+    /// Write the data along with the ancillary data, which is attached to the first byte
+    /// of the data. A blocking write fails with EAGAIN once the timeout expires.
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        ancillary: Option<Ancillary>,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let queue = &self.send_ancillary;
+        // Hold the lock during writing to keep the offsets consistent with the data
+        let mut send_offset = queue.send_offset.lock().unwrap();
+
+        // Push the ancillary data before writing, so that it is always available when
+        // the data is read
+        let has_ancillary = ancillary.is_some();
+        if let Some(ancillary) = ancillary {
+            queue
+                .items
+                .lock()
+                .unwrap()
+                .push_back((*send_offset, ancillary));
+        }
+
+        match self
+            .writer
+            .push_slices_with_timeout(bufs, timeout)
+            .map_err(timeout_to_eagain)
+        {
+            Ok(len) if len > 0 => {
+                *send_offset += len;
+                Ok(len)
+            }
+            res => {
+                // Nothing is written, so the ancillary data can not have been received
+                if has_ancillary {
+                    queue.items.lock().unwrap().pop_back();
+                }
+                res
+            }
+        }
+    }
+
+    /// Read the data and the ancillary data attached to it. Like Linux, a read does not
+    /// go across the data sent with different ancillary data. A blocking read fails with
+    /// EAGAIN once the timeout expires.
+    pub fn recvmsg(
+        &self,
+        bufs: &mut [&mut [u8]],
+        timeout: Option<Duration>,
+    ) -> Result<(usize, Option<Ancillary>)> {
+        let queue = &self.recv_ancillary;
+        let mut recv_offset = queue.recv_offset.lock().unwrap();
+
+        let limit = queue
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(offset, _)| *offset)
+            .find(|offset| *offset > *recv_offset)
+            .map(|offset| offset - *recv_offset);
+        let len = match limit {
+            Some(limit) => {
+                let mut limited_bufs = Vec::with_capacity(bufs.len());
+                let mut remaining = limit;
+                for buf in bufs.iter_mut() {
+                    if remaining == 0 {
+                        break;
+                    }
+                    let len = buf.len().min(remaining);
+                    limited_bufs.push(&mut buf[..len]);
+                    remaining -= len;
+                }
+                self.reader
+                    .pop_slices_with_timeout(&mut limited_bufs, timeout)
+                    .map_err(timeout_to_eagain)?
+            }
+            None => self
+                .reader
+                .pop_slices_with_timeout(bufs, timeout)
+                .map_err(timeout_to_eagain)?,
+        };
+
+        // More ancillary data may arrive during blocking. Take all that is attached to
+        // the data read.
+        let mut ancillary: Option<Ancillary> = None;
+        let mut items = queue.items.lock().unwrap();
+        while let Some((offset, _)) = items.front() {
+            if *offset >= *recv_offset + len {
+                break;
+            }
+            let (_, item) = items.pop_front().unwrap();
+            match ancillary.as_mut() {
+                Some(ancillary) => ancillary.merge(item),
+                None => ancillary = Some(item),
+            }
+        }
+        *recv_offset += len;
+
+        Ok((len, ancillary))
+    }
+
+    /// The identities of the unix sockets sent to this end but not received.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
+        let items = self.recv_ancillary.items.lock().unwrap();
+        items
+            .iter()
+            .flat_map(|(_, ancillary)| ancillary.unix_socket_ids())
+            .collect()
+    }
+
+    /// Discard the ancillary data sent to this end, whose files are released out of
+    /// the lock.
+    pub fn discard_ancillary(&self) {
+        let items = std::mem::take(&mut *self.recv_ancillary.items.lock().unwrap());
+        drop(items);
     }
 
     pub fn bytes_to_read(&self) -> usize {
@@ -122,16 +253,16 @@ impl Inner {
         events
     }
 
-    pub fn ancillary(&self) -> Option<Ancillary> {
-        self.ancillary.read().unwrap().clone()
+    pub fn cred(&self) -> ucred_t {
+        *self.cred.read().unwrap()
     }
 
-    pub fn set_ancillary(&self, ancillary: Ancillary) {
-        self.ancillary.write().unwrap().insert(ancillary);
+    pub fn set_cred(&self, cred: ucred_t) {
+        *self.cred.write().unwrap() = cred;
     }
 
-    pub fn peer_ancillary(&self) -> Option<Ancillary> {
-        self.peer.upgrade().map(|end| end.ancillary()).flatten()
+    pub fn peer_cred(&self) -> Option<ucred_t> {
+        self.peer.upgrade().map(|end| end.cred())
     }
 
     pub(self) fn register_relay_notifier(&self, observer: &Arc<RelayNotifier>) {
@@ -153,15 +284,38 @@ impl Inner {
     }
 }
 
-/// Ancillary data of connected unix socket's sent/received control message.
-#[derive(Clone, Debug)]
-pub struct Ancillary {
-    pub(super) tid: pid_t, // currently store tid to locate file table
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Release the files that are sent to this end but not received
+        self.discard_ancillary();
+    }
+}
+
+// Like Linux, a socket operation reports EAGAIN when it times out
+fn timeout_to_eagain(e: Error) -> Error {
+    match e.errno() {
+        ETIMEDOUT => errno!(EAGAIN, "the socket operation timed out"),
+        _ => e,
+    }
+}
+
+/// The ancillary data sent in one direction of a connection. Each item is attached to a
+/// byte of the data, which is identified by its offset in the stream.
+struct AncillaryQueue {
+    items: SgxMutex<VecDeque<(usize, Ancillary)>>,
+    // The num of bytes written, whose lock is held by the writer during writing
+    send_offset: SgxMutex<usize>,
+    // The num of bytes read, whose lock is held by the reader during reading
+    recv_offset: SgxMutex<usize>,
 }
 
-impl Ancillary {
-    pub fn tid(&self) -> pid_t {
-        self.tid
+impl AncillaryQueue {
+    fn new() -> Self {
+        Self {
+            items: SgxMutex::new(VecDeque::new()),
+            send_offset: SgxMutex::new(0),
+            recv_offset: SgxMutex::new(0),
+        }
     }
 }
 
@@ -171,7 +325,7 @@ pub const DEFAULT_BUF_SIZE: usize = 208 * 1024;
 
 /// An observer used to observe both reader and writer of the endpoint. It also contains a
 /// notifier that relays the notification of the endpoint.
-pub(super) struct RelayNotifier {
+pub struct RelayNotifier {
     notifier: IoNotifier,
     endpoint: SgxMutex<Option<Endpoint>>,
 }
@@ -6,6 +6,7 @@ use rcore_fs::vfs::{FileType, Metadata, Timespec};
 use std::any::Any;
 
 use crate::fs::{GetReadBufLen, IoctlCmd, SetNonBlocking};
+use crate::net::{GetPassCredCmd, SetPassCredCmd};
 
 impl File for Stream {
     fn read(&self, buf: &mut [u8]) -> Result<usize> {
@@ -71,6 +72,12 @@ impl File for Stream {
             cmd : SetNonBlocking => {
                 let nonblocking = cmd.input();
                 self.set_nonblocking(*nonblocking != 0);
+            },
+            cmd : SetPassCredCmd => {
+                self.set_passcred(*cmd.input());
+            },
+            cmd : GetPassCredCmd => {
+                cmd.set_output(self.passcred() as i32);
             }
         });
         Ok(())
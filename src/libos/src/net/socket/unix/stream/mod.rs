@@ -1,8 +1,8 @@
 use super::*;
 
-mod address_space;
 mod endpoint;
 mod file;
 mod stream;
 
-pub use stream::Stream;
+pub use endpoint::{end_pair, Endpoint, RelayNotifier, DEFAULT_BUF_SIZE};
+pub use stream::{Listener, Stream};
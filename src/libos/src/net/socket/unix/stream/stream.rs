@@ -1,14 +1,14 @@
-use super::address_space::ADDRESS_SPACE;
-use super::endpoint::{end_pair, Ancillary, Endpoint, RelayNotifier};
+use super::address_space::{AddressSpace, ADDRESS_SPACE};
+use super::ancillary::Ancillary;
+use super::endpoint::{self, end_pair, Endpoint, RelayNotifier};
 use super::*;
 use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::Channel;
 use fs::IoEvents;
-use fs::{CreationFlags, FileMode};
-use net::socket::{CMessages, CmsgData};
+use net::socket::ucred_t;
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 /// SOCK_STREAM Unix socket. It has three statuses: unconnected, listening and connected.  When a
 /// socket is created, it is in unconnected status.  It will transfer to listening after listen is
@@ -20,6 +20,7 @@ pub struct Stream {
     // Use the internal notifier of RelayNotifier as the notifier of stream socket. It relays the
     // events of the endpoint, too.
     pub(super) notifier: Arc<RelayNotifier>,
+    passcred: AtomicBool,
 }
 
 impl Stream {
@@ -29,6 +30,7 @@ impl Stream {
                 flags.contains(SocketFlags::SOCK_NONBLOCK),
             ))),
             notifier: Arc::new(RelayNotifier::new()),
+            passcred: AtomicBool::new(false),
         }
     }
 
@@ -43,11 +45,13 @@ impl Stream {
         let socket_a = Self {
             inner: SgxMutex::new(Status::Connected(end_a)),
             notifier: notifier_a,
+            passcred: AtomicBool::new(false),
         };
 
         let socket_b = Self {
             inner: SgxMutex::new(Status::Connected(end_b)),
             notifier: notifier_b,
+            passcred: AtomicBool::new(false),
         };
 
         Ok((socket_a, socket_b))
@@ -75,21 +79,8 @@ impl Stream {
     pub fn bind(&self, addr: &UnixAddr) -> Result<()> {
         let mut unix_addr = addr.clone();
         let addr = &mut unix_addr;
-
-        if let UnixAddr::File(inode_num, path) = addr {
-            // create the corresponding file in the fs and fill Addr with its inode
-            let corresponding_inode_num = {
-                let current = current!();
-                let fs = current.fs().read().unwrap();
-                let file_ref = fs.open_file(
-                    path.path_str(),
-                    CreationFlags::O_CREAT.bits(),
-                    FileMode::from_bits(0o777).unwrap(),
-                )?;
-                file_ref.metadata()?.inode
-            };
-            *inode_num = Some(corresponding_inode_num);
-        }
+        // create the corresponding file in the fs and fill Addr with its inode
+        AddressSpace::create_socket_file(addr)?;
 
         match &mut *self.inner() {
             Status::Idle(ref mut info) => {
@@ -166,9 +157,6 @@ impl Stream {
                 if let Some(self_addr) = self_addr_opt {
                     end_self.set_addr(self_addr);
                 }
-                end_self.set_ancillary(Ancillary {
-                    tid: current!().tid(),
-                });
 
                 ADDRESS_SPACE
                     .push_incoming(addr, end_incoming)
@@ -198,9 +186,8 @@ impl Stream {
             Status::Listening(addr) => {
                 let endpoint = ADDRESS_SPACE.pop_incoming(&addr)?;
                 endpoint.set_nonblocking(flags.contains(SocketFlags::SOCK_NONBLOCK));
-                endpoint.set_ancillary(Ancillary {
-                    tid: current!().tid(),
-                });
+                // The end is created by the connecting process
+                endpoint.set_cred(ucred_t::current());
                 let notifier = Arc::new(RelayNotifier::new());
                 notifier.observe_endpoint(&endpoint);
 
@@ -212,6 +199,8 @@ impl Stream {
                     Self {
                         inner: SgxMutex::new(Status::Connected(endpoint)),
                         notifier: notifier,
+                        // SO_PASSCRED is inherited from the listener
+                        passcred: AtomicBool::new(self.passcred()),
                     },
                     peer_addr,
                 ))
@@ -245,12 +234,17 @@ impl Stream {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let data_len = self.writev(bufs)?;
-        if let Some(msg_control) = control {
-            self.write(msg_control)?;
-        }
+        let endpoint = match &*self.inner() {
+            Status::Connected(endpoint) => endpoint.clone(),
+            _ => return_errno!(ENOTCONN, "unconnected socket"),
+        };
 
-        Ok(data_len)
+        let ancillary = match control {
+            Some(msg_control) => Some(Ancillary::from_control(msg_control)?),
+            None => None,
+        }
+        .filter(|ancillary| !ancillary.is_empty());
+        endpoint.sendmsg(bufs, ancillary, None)
     }
 
     pub fn recvmsg(
@@ -259,39 +253,33 @@ impl Stream {
         flags: RecvFlags,
         control: Option<&mut [u8]>,
     ) -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
-        if !flags.is_empty() {
-            warn!("unsupported flags: {:?}", flags);
+        let unsupported_flags = flags - RecvFlags::MSG_CMSG_CLOEXEC;
+        if !unsupported_flags.is_empty() {
+            warn!("unsupported flags: {:?}", unsupported_flags);
         }
 
-        let data_len = self.readv(bufs)?;
-
-        // For stream socket, the msg_name is ignored. And other fields are not supported.
-        let control_len = if let Some(msg_control) = control {
-            let control_len = self.read(msg_control)?;
-
-            // For each control message that contains file descriptors (SOL_SOCKET and SCM_RIGHTS),
-            // reassign each fd in the message in receive end.
-            for cmsg in CMessages::from_bytes(msg_control) {
-                if let CmsgData::ScmRights(mut scm_rights) = cmsg {
-                    let send_tid = self.peer_ancillary().unwrap().tid();
-                    scm_rights.iter_and_reassign_fds(|send_fd| {
-                        let ipc_file = process::table::get_thread(send_tid)
-                            .unwrap()
-                            .files()
-                            .lock()
-                            .get(send_fd)
-                            .unwrap();
-                        current!().add_file(ipc_file.clone(), false)
-                    })
-                }
-                // Unix credentials need not to be handled here
-            }
-            control_len
-        } else {
-            0
+        let endpoint = match &*self.inner() {
+            Status::Connected(endpoint) => endpoint.clone(),
+            _ => return_errno!(ENOTCONN, "unconnected socket"),
         };
 
-        Ok((data_len, None, MsgFlags::empty(), control_len))
+        let (data_len, ancillary) = endpoint.recvmsg(bufs, None)?;
+
+        // For stream socket, the msg_name is ignored.
+        let mut ancillary = ancillary.unwrap_or_default();
+        if ancillary.cred().is_none() {
+            // The credentials of the peer when the connection is established
+            if let Some(cred) = endpoint.peer_cred() {
+                ancillary.set_cred(cred);
+            }
+        }
+        let (msg_flags, control_len) = ancillary.receive(
+            control,
+            self.passcred(),
+            flags.contains(RecvFlags::MSG_CMSG_CLOEXEC),
+        );
+
+        Ok((data_len, None, msg_flags, control_len))
     }
 
     /// perform shutdown on the socket.
@@ -326,26 +314,41 @@ impl Stream {
         self.inner.lock().unwrap()
     }
 
-    fn ancillary(&self) -> Option<Ancillary> {
+    /// The identities of the unix sockets in flight to this socket, including those to
+    /// the connections that are not accepted yet.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
         match &*self.inner() {
-            Status::Idle(_) => None,
-            Status::Listening(_) => None,
-            Status::Connected(endpoint) => endpoint.ancillary(),
+            Status::Connected(endpoint) => endpoint.inflight_sockets(),
+            Status::Listening(addr) => ADDRESS_SPACE
+                .get_listener_ref(addr)
+                .map(|listener| listener.inflight_sockets())
+                .unwrap_or_default(),
+            Status::Idle(_) => Vec::new(),
         }
     }
 
-    fn peer_ancillary(&self) -> Option<Ancillary> {
-        if let Status::Connected(endpoint) = &*self.inner() {
-            endpoint.peer_ancillary()
-        } else {
-            None
+    /// Discard the ancillary data sent to this socket and to the connections that are
+    /// not accepted yet, which can never be received.
+    pub fn discard_inflight_files(&self) {
+        // The files are released out of the lock
+        let status = (*self.inner()).clone();
+        match &status {
+            Status::Connected(endpoint) => endpoint.discard_ancillary(),
+            Status::Listening(addr) => {
+                if let Some(listener) = ADDRESS_SPACE.get_listener_ref(addr) {
+                    listener.discard_ancillary();
+                }
+            }
+            Status::Idle(_) => {}
         }
     }
 
-    fn set_ancillary(&self, ancillary: Ancillary) {
-        if let Status::Connected(endpoint) = &*self.inner() {
-            endpoint.set_ancillary(ancillary)
-        }
+    pub(super) fn passcred(&self) -> bool {
+        self.passcred.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_passcred(&self, passcred: bool) {
+        self.passcred.store(passcred, Ordering::Relaxed);
     }
 }
 
@@ -426,14 +429,13 @@ impl Info {
 pub struct Listener {
     channel: RwLock<Channel<Endpoint>>,
     notifier: Arc<RelayNotifier>,
+    // The incoming connections in the channel, which are tracked for the garbage
+    // collector of the unix sockets in flight
+    incoming: SgxMutex<Vec<Weak<endpoint::Inner>>>,
 }
 
 impl Listener {
-    pub(super) fn new(
-        capacity: usize,
-        nonblocking: bool,
-        notifier: Arc<RelayNotifier>,
-    ) -> Result<Self> {
+    pub fn new(capacity: usize, nonblocking: bool, notifier: Arc<RelayNotifier>) -> Result<Self> {
         let channel = Channel::new(capacity)?;
         channel.producer().set_nonblocking(true);
         channel.consumer().set_nonblocking(nonblocking);
@@ -441,6 +443,7 @@ impl Listener {
         Ok(Self {
             channel: RwLock::new(channel),
             notifier,
+            incoming: SgxMutex::new(Vec::new()),
         })
     }
 
@@ -472,13 +475,51 @@ impl Listener {
     }
 
     pub fn push_incoming(&self, stream_socket: Endpoint) -> Result<()> {
+        // Track the connection before it can be accepted
+        let mut incoming = self.incoming.lock().unwrap();
+        // The connections dropped by resize are gone
+        incoming.retain(|socket| socket.strong_count() > 0);
+        incoming.push(Arc::downgrade(&stream_socket));
+
         let channel = self.channel.read().unwrap();
-        channel.push(stream_socket)
+        channel.push(stream_socket).map_err(|e| {
+            incoming.pop();
+            e
+        })
     }
 
     pub fn pop_incoming(&self) -> Option<Endpoint> {
-        let channel = self.channel.read().unwrap();
-        channel.pop().ok().flatten()
+        let stream_socket = {
+            let channel = self.channel.read().unwrap();
+            channel.pop().ok().flatten()?
+        };
+
+        let mut incoming = self.incoming.lock().unwrap();
+        incoming.retain(|socket| !std::ptr::eq(socket.as_ptr(), Arc::as_ptr(&stream_socket)));
+        Some(stream_socket)
+    }
+
+    /// The identities of the unix sockets in flight to the incoming connections.
+    pub fn inflight_sockets(&self) -> Vec<usize> {
+        self.incoming_sockets()
+            .iter()
+            .flat_map(|socket| socket.inflight_sockets())
+            .collect()
+    }
+
+    /// Discard the ancillary data sent to the incoming connections.
+    pub fn discard_ancillary(&self) {
+        for socket in self.incoming_sockets() {
+            socket.discard_ancillary();
+        }
+    }
+
+    fn incoming_sockets(&self) -> Vec<Endpoint> {
+        let incoming = self.incoming.lock().unwrap();
+        incoming
+            .iter()
+            .filter_map(|socket| socket.upgrade())
+            .collect()
     }
 
     pub fn remaining(&self) -> usize {
@@ -237,6 +237,11 @@ impl<A: Addr, R: Runtime> DatagramSocket<A, R> {
         self.receiver.recvmsg(bufs, flags, control)
     }
 
+    /// Prepare to receive up to `batch_size` datagrams at once, e.g., for recvmmsg.
+    pub fn reserve_recv_batch(&self, batch_size: usize) {
+        self.receiver.reserve_recv_batch(batch_size)
+    }
+
     pub fn write(&self, buf: &[u8]) -> Result<usize> {
         self.writev(&[buf])
     }
@@ -18,3 +18,5 @@ use crate::net::socket::sockopt::{
 
 const MAX_BUF_SIZE: usize = 64 * 1024;
 const OPTMEM_MAX: usize = 64 * 1024;
+// The max number of async recvs in flight for a socket
+const MAX_RECV_BATCH: usize = 8;
@@ -85,47 +85,50 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
             return_errno!(EINVAL, "the socket flags is not supported");
         }
 
-        // Mark the socket as non-readable since Datagram uses single packet
+        // Mark the socket as non-readable since Datagram uses single packet. It is
+        // marked as readable again if more packets have been received.
         self.common.pollee().del_events(Events::IN);
 
-        let mut recv_bytes = 0;
-        let mut msg_flags = MsgFlags::empty();
-        let recv_addr = inner.get_packet_addr();
-        let msg_controllen = inner.control_len.unwrap_or(0);
         let user_controllen = control.as_ref().map_or(0, |buf| buf.len());
-
-        // Copy ancillary data from control buffer
         if user_controllen > super::OPTMEM_MAX {
             return_errno!(EINVAL, "invalid msg control length");
         }
 
-        if user_controllen < msg_controllen {
-            msg_flags = msg_flags | MsgFlags::MSG_CTRUNC
-        }
+        // Take the packets in the order of their arrival
+        if let Some(&slot_idx) = inner.ready_slots.front() {
+            let slot = &inner.slots[slot_idx];
+            let mut msg_flags = MsgFlags::empty();
+            let recv_addr = slot.get_packet_addr();
+            let recv_len = slot.recv_len.unwrap();
+            let msg_controllen = slot.control_len.unwrap_or(0);
+
+            // Copy ancillary data from control buffer
+            if user_controllen < msg_controllen {
+                msg_flags = msg_flags | MsgFlags::MSG_CTRUNC
+            }
 
-        if msg_controllen > 0 {
-            let copied_bytes = msg_controllen.min(user_controllen);
-            control
-                .as_mut()
-                .map(|buf| buf[..copied_bytes].copy_from_slice(&inner.msg_control[..copied_bytes]));
-        }
+            if msg_controllen > 0 {
+                let copied_bytes = msg_controllen.min(user_controllen);
+                control.as_mut().map(|buf| {
+                    buf[..copied_bytes].copy_from_slice(&slot.msg_control[..copied_bytes])
+                });
+            }
 
-        // Copy data from the recv buffer to the bufs
-        let copied_bytes = inner.try_copy_buf(bufs);
-        if let Some(copied_bytes) = copied_bytes {
+            // Copy data from the recv buffer to the bufs
+            let copied_bytes = slot.copy_buf(bufs);
             let bufs_len: usize = bufs.iter().map(|buf| buf.len()).sum();
 
             // If user provided buffer length is smaller than kernel received datagram length,
             // discard the datagram and set MsgFlags::MSG_TRUNC in returned msg_flags.
-            if bufs_len < inner.recv_len().unwrap() {
+            if bufs_len < recv_len {
                 // update msg.msg_flags to MSG_TRUNC
                 msg_flags = msg_flags | MsgFlags::MSG_TRUNC
             };
 
             // If user provided flags contain MSG_TRUNC, the return received length should be
             // kernel receiver buffer length, vice versa should return truly copied bytes length.
-            recv_bytes = if flags.contains(RecvFlags::MSG_TRUNC) {
-                inner.recv_len().unwrap()
+            let recv_bytes = if flags.contains(RecvFlags::MSG_TRUNC) {
+                recv_len
             } else {
                 copied_bytes
             };
@@ -133,16 +136,16 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
             // When flags contain MSG_PEEK and there is data in socket recv buffer,
             // it is unnecessary to send blocking recv request (do_recv) to fetch data
             // from iouring buffer, which may flush the data in recv buffer.
-            // When flags don't contain MSG_PEEK or there is no available data,
+            // When flags don't contain MSG_PEEK, the slot of the packet is released and
             // it is time to send blocking request to iouring for notifying events.
             if !flags.contains(RecvFlags::MSG_PEEK) {
+                inner.ready_slots.pop_front();
+                inner.slots[slot_idx].clear();
                 self.do_recv(&mut inner);
             }
-            return Ok((recv_bytes, recv_addr, msg_flags, msg_controllen));
-        }
-
-        // In some situantions of MSG_ERRQUEUE, users only require control buffer but setting iovec length to zero.
-        if msg_controllen > 0 {
+            if !inner.ready_slots.is_empty() {
+                self.common.pollee().add_events(Events::IN);
+            }
             return Ok((recv_bytes, recv_addr, msg_flags, msg_controllen));
         }
 
@@ -161,7 +164,7 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
             {
                 return_errno!(Errno::EWOULDBLOCK, "the socket recv has been shutdown");
             } else {
-                return Ok((0, None, msg_flags, 0));
+                return Ok((0, None, MsgFlags::empty(), 0));
             }
         }
 
@@ -169,27 +172,34 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
         return_errno!(EAGAIN, "try recv again");
     }
 
+    /// Submit an async recv for every idle slot.
     fn do_recv(self: &Arc<Self>, inner: &mut MutexGuard<Inner>) {
-        if inner.io_handle.is_some() || self.common.is_closed() {
+        if self.common.is_closed() {
             return;
         }
-        // Clear recv_len and error
-        inner.recv_len.take();
-        inner.control_len.take();
-        inner.error.take();
 
         if inner.is_shutdown {
             info!("do_recv early return, the socket recv has been shutdown");
             return;
         }
 
+        for slot_idx in 0..inner.slots.len() {
+            if inner.slots[slot_idx].is_idle() {
+                self.do_recv_in_slot(inner, slot_idx);
+            }
+        }
+    }
+
+    fn do_recv_in_slot(self: &Arc<Self>, inner: &mut MutexGuard<Inner>, slot_idx: usize) {
         let receiver = self.clone();
         // Init the callback invoked upon the completion of the async recv
         let complete_fn = move |retval: i32| {
             let mut inner = receiver.inner.lock();
+            let inner = &mut *inner;
+            let slot = &mut inner.slots[slot_idx];
 
             // Release the handle to the async recv
-            inner.io_handle.take();
+            slot.io_handle.take();
 
             // Handle error
             if retval < 0 {
@@ -208,10 +218,9 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
             }
 
             // Handle the normal case of a successful read
-            inner.recv_len = Some(retval as usize);
-
-            let control_len = inner.req.msg.msg_controllen;
-            inner.control_len = Some(control_len);
+            slot.recv_len = Some(retval as usize);
+            slot.control_len = Some(slot.req.msg.msg_controllen);
+            inner.ready_slots.push_back(slot_idx);
 
             receiver.common.pollee().add_events(Events::IN);
 
@@ -219,13 +228,14 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
         };
 
         // Generate the async recv request
-        let msghdr_ptr = inner.new_recv_req();
+        let slot = &mut inner.slots[slot_idx];
+        let msghdr_ptr = slot.new_recv_req();
 
         // Submit the async recv to io_uring
         let io_uring = self.common.io_uring();
         let host_fd = Fd(self.common.host_fd() as _);
         let handle = unsafe { io_uring.recvmsg(host_fd, msghdr_ptr, 0, complete_fn) };
-        inner.io_handle.replace(handle);
+        slot.io_handle.replace(handle);
     }
 
     pub fn initiate_async_recv(self: &Arc<Self>) {
@@ -233,15 +243,43 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
         self.do_recv(&mut inner);
     }
 
+    /// Keep up to `batch_size` async recvs in flight, so that the packets arriving
+    /// in a burst are harvested by one enclave transition.
+    ///
+    /// The batch never shrinks, since the users of recvmmsg tend to call it repeatedly.
+    pub fn reserve_recv_batch(self: &Arc<Self>, batch_size: usize) {
+        let batch_size = batch_size.min(super::MAX_RECV_BATCH);
+        let mut inner = self.inner.lock();
+        if inner.slots.len() >= batch_size {
+            return;
+        }
+
+        while inner.slots.len() < batch_size {
+            inner.slots.push(RecvSlot::new());
+        }
+        // Do not start receiving on behalf of a socket that is not bound yet. The new
+        // slots are submitted by the next do_recv then.
+        if inner.slots.iter().any(|slot| !slot.is_idle()) {
+            self.do_recv(&mut inner);
+        }
+    }
+
     pub fn cancel_recv_requests(&self) {
         {
             let inner = self.inner.lock();
-            if let Some(io_handle) = &inner.io_handle {
-                let io_uring = self.common.io_uring();
-                unsafe { io_uring.cancel(io_handle) };
-            } else {
+            let io_handles: Vec<&IoHandle> = inner
+                .slots
+                .iter()
+                .filter_map(|slot| slot.io_handle.as_ref())
+                .collect();
+            if io_handles.is_empty() {
                 return;
             }
+
+            let io_uring = self.common.io_uring();
+            for io_handle in io_handles {
+                unsafe { io_uring.cancel(io_handle) };
+            }
         }
 
         // wait for the cancel to complete
@@ -252,7 +290,7 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
         loop {
             let pending_request_exist = {
                 let inner = self.inner.lock();
-                inner.io_handle.is_some()
+                inner.slots.iter().any(|slot| slot.io_handle.is_some())
             };
 
             if pending_request_exist {
@@ -282,43 +320,71 @@ impl<A: Addr, R: Runtime> Receiver<A, R> {
 
     pub fn ready_len(&self) -> usize {
         let inner = self.inner.lock();
-        inner.recv_len().unwrap_or(0)
+        inner
+            .ready_slots
+            .front()
+            .and_then(|&slot_idx| inner.slots[slot_idx].recv_len)
+            .unwrap_or(0)
     }
 }
 
 struct Inner {
+    // A socket starts with one slot, which is enough for one recv at a time.
+    // More slots are added by reserve_recv_batch for recvmmsg.
+    slots: Vec<RecvSlot>,
+    // The indexes of the slots that hold received packets, in the order of completion
+    ready_slots: VecDeque<usize>,
+    error: Option<Errno>,
+    is_shutdown: bool,
+}
+
+unsafe impl Send for Inner {}
+
+impl Inner {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![RecvSlot::new()],
+            ready_slots: VecDeque::new(),
+            error: None,
+            is_shutdown: false,
+        }
+    }
+}
+
+/// The buffers of one async recv.
+struct RecvSlot {
     recv_buf: UntrustedBox<[u8]>,
     // Datagram sockets in various domains permit zero-length datagrams.
     // Hence the recv_len might be 0.
     recv_len: Option<usize>,
-    // When the recv_buf content length is greater than user buffer,
-    // store the offset for the recv_buf for read loop
-    recv_buf_offset: usize,
     msg_control: UntrustedBox<[u8]>,
     control_len: Option<usize>,
     req: UntrustedBox<RecvReq>,
     io_handle: Option<IoHandle>,
-    error: Option<Errno>,
-    is_shutdown: bool,
 }
 
-unsafe impl Send for Inner {}
-
-impl Inner {
+impl RecvSlot {
     pub fn new() -> Self {
         Self {
             recv_buf: UntrustedBox::new_uninit_slice(super::MAX_BUF_SIZE),
             recv_len: None,
-            recv_buf_offset: 0,
             msg_control: UntrustedBox::new_uninit_slice(super::OPTMEM_MAX),
             control_len: None,
             req: UntrustedBox::new_uninit(),
             io_handle: None,
-            error: None,
-            is_shutdown: false,
         }
     }
 
+    /// Whether the slot neither has an async recv in flight nor holds a packet.
+    pub fn is_idle(&self) -> bool {
+        self.io_handle.is_none() && self.recv_len.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.recv_len.take();
+        self.control_len.take();
+    }
+
     pub fn new_recv_req(&mut self) -> *mut libc::msghdr {
         let iovec = libc::iovec {
             iov_base: self.recv_buf.as_mut_ptr() as _,
@@ -342,26 +408,21 @@ impl Inner {
         msghdr_ptr
     }
 
-    pub fn try_copy_buf(&self, bufs: &mut [&mut [u8]]) -> Option<usize> {
-        self.recv_len.map(|recv_len| {
-            let mut copy_len = 0;
-            for buf in bufs {
-                let recv_buf = &self.recv_buf[copy_len..recv_len];
-                if buf.len() <= recv_buf.len() {
-                    buf.copy_from_slice(&recv_buf[..buf.len()]);
-                    copy_len += buf.len();
-                } else {
-                    buf[..recv_buf.len()].copy_from_slice(&recv_buf[..]);
-                    copy_len += recv_buf.len();
-                    break;
-                }
+    pub fn copy_buf(&self, bufs: &mut [&mut [u8]]) -> usize {
+        let recv_len = self.recv_len.unwrap_or(0);
+        let mut copy_len = 0;
+        for buf in bufs {
+            let recv_buf = &self.recv_buf[copy_len..recv_len];
+            if buf.len() <= recv_buf.len() {
+                buf.copy_from_slice(&recv_buf[..buf.len()]);
+                copy_len += buf.len();
+            } else {
+                buf[..recv_buf.len()].copy_from_slice(&recv_buf[..]);
+                copy_len += recv_buf.len();
+                break;
             }
-            copy_len
-        })
-    }
-
-    pub fn recv_len(&self) -> Option<usize> {
-        self.recv_len
+        }
+        copy_len
     }
 
     /// Return the addr of the received packet if udp socket is not connected.
@@ -4,7 +4,7 @@ use crate::net::socket::{MsgFlags, SocketProtocol};
 
 use self::impls::{Ipv4Stream, Ipv6Stream};
 use crate::fs::{AccessMode, IoEvents, IoNotifier, IoctlCmd, StatusFlags};
-use crate::net::socket::{AnyAddr, Ipv4SocketAddr, Ipv6SocketAddr};
+use crate::net::socket::{active_socket, AnyAddr, Ipv4SocketAddr, Ipv6SocketAddr};
 use crate::prelude::*;
 
 #[derive(Debug)]
@@ -39,7 +39,8 @@ pub trait UringSocketType {
 
 impl UringSocketType for FileRef {
     fn as_uring_socket(&self) -> Result<&SocketFile> {
-        self.as_any()
+        active_socket(self)
+            .as_any()
             .downcast_ref::<SocketFile>()
             .ok_or_else(|| errno!(ENOTSOCK, "not a uring socket"))
     }
@@ -277,24 +278,25 @@ impl SocketFile {
         flags: RecvFlags,
         control: Option<&mut [u8]>,
     ) -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
-        // TODO: support msg_flags and msg_control
         Ok(match &self.socket {
             AnySocket::Ipv4Stream(ipv4_stream) => {
-                let (bytes_recv, addr_recv, msg_flags) = ipv4_stream.recvmsg(bufs, flags)?;
+                let (bytes_recv, addr_recv, msg_flags, msg_controllen) =
+                    ipv4_stream.recvmsg(bufs, flags, control)?;
                 (
                     bytes_recv,
                     addr_recv.map(|addr| AnyAddr::Ipv4(addr)),
                     msg_flags,
-                    0,
+                    msg_controllen,
                 )
             }
             AnySocket::Ipv6Stream(ipv6_stream) => {
-                let (bytes_recv, addr_recv, msg_flags) = ipv6_stream.recvmsg(bufs, flags)?;
+                let (bytes_recv, addr_recv, msg_flags, msg_controllen) =
+                    ipv6_stream.recvmsg(bufs, flags, control)?;
                 (
                     bytes_recv,
                     addr_recv.map(|addr| AnyAddr::Ipv6(addr)),
                     msg_flags,
-                    0,
+                    msg_controllen,
                 )
             }
             AnySocket::Ipv4Datagram(ipv4_datagram) => {
@@ -323,6 +325,16 @@ impl SocketFile {
         })
     }
 
+    /// Prepare to receive up to `batch_size` messages at once. Only the datagram
+    /// sockets receive in batches.
+    pub fn reserve_recv_batch(&self, batch_size: usize) {
+        match &self.socket {
+            AnySocket::Ipv4Datagram(ipv4_datagram) => ipv4_datagram.reserve_recv_batch(batch_size),
+            AnySocket::Ipv6Datagram(ipv6_datagram) => ipv6_datagram.reserve_recv_batch(batch_size),
+            _ => {}
+        }
+    }
+
     pub fn sendto(&self, buf: &[u8], addr: Option<AnyAddr>, flags: SendFlags) -> Result<usize> {
         self.sendmsg(&[buf], addr, flags, None)
     }
@@ -335,8 +347,8 @@ impl SocketFile {
         control: Option<&[u8]>,
     ) -> Result<usize> {
         let res = match &self.socket {
-            AnySocket::Ipv4Stream(ipv4_stream) => ipv4_stream.sendmsg(bufs, flags),
-            AnySocket::Ipv6Stream(ipv6_stream) => ipv6_stream.sendmsg(bufs, flags),
+            AnySocket::Ipv4Stream(ipv4_stream) => ipv4_stream.sendmsg(bufs, flags, control),
+            AnySocket::Ipv6Stream(ipv6_stream) => ipv6_stream.sendmsg(bufs, flags, control),
             AnySocket::Ipv4Datagram(ipv4_datagram) => {
                 let ip_addr = if let Some(addr) = addr.as_ref() {
                     Some(addr.to_ipv4()?)
@@ -6,7 +6,7 @@ use core::time::Duration;
 
 use atomic::Ordering;
 
-use self::states::{ConnectedStream, ConnectingStream, InitStream, ListenerStream};
+use self::states::{is_tls_option, ConnectedStream, ConnectingStream, InitStream, ListenerStream};
 use crate::events::Observer;
 use crate::fs::{
     GetIfConf, GetIfReqWithRawCmd, GetReadBufLen, IoEvents, IoNotifier, IoctlCmd, SetNonBlocking,
@@ -18,7 +18,7 @@ use crate::net::socket::uring::runtime::Runtime;
 use crate::prelude::*;
 
 use crate::events::Poller;
-use crate::net::socket::{sockopt::*, MsgFlags};
+use crate::net::socket::{sockopt::*, MsgFlags, SOL_TLS};
 
 lazy_static! {
     pub static ref SEND_BUF_SIZE: AtomicUsize = AtomicUsize::new(2565 * 1024 + 1); // Default Linux send buffer size is 2.5MB.
@@ -227,7 +227,7 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
     }
 
     pub fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
-        let ret = self.recvmsg(bufs, RecvFlags::empty())?;
+        let ret = self.recvmsg(bufs, RecvFlags::empty(), None)?;
         Ok(ret.0)
     }
 
@@ -240,7 +240,8 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
         &self,
         buf: &mut [&mut [u8]],
         flags: RecvFlags,
-    ) -> Result<(usize, Option<A>, MsgFlags)> {
+        control: Option<&mut [u8]>,
+    ) -> Result<(usize, Option<A>, MsgFlags, usize)> {
         let connected_stream = {
             let mut state = self.state.write().unwrap();
             match &*state {
@@ -261,8 +262,9 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
             }
         };
 
-        let recv_len = connected_stream.recvmsg(buf, flags)?;
-        Ok((recv_len, None, MsgFlags::empty()))
+        let (recv_len, msg_flags, msg_controllen) =
+            connected_stream.recvmsg(buf, flags, control)?;
+        Ok((recv_len, None, msg_flags, msg_controllen))
     }
 
     pub fn write(&self, buf: &[u8]) -> Result<usize> {
@@ -270,10 +272,15 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
     }
 
     pub fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        self.sendmsg(bufs, SendFlags::empty())
+        self.sendmsg(bufs, SendFlags::empty(), None)
     }
 
-    pub fn sendmsg(&self, bufs: &[&[u8]], flags: SendFlags) -> Result<usize> {
+    pub fn sendmsg(
+        &self,
+        bufs: &[&[u8]],
+        flags: SendFlags,
+        control: Option<&[u8]>,
+    ) -> Result<usize> {
         let connected_stream = {
             let mut state = self.state.write().unwrap();
             match &*state {
@@ -294,7 +301,7 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
             }
         };
 
-        connected_stream.sendmsg(bufs, flags)
+        connected_stream.sendmsg(bufs, flags, control)
     }
 
     pub fn poll(&self, mask: IoEvents, poller: Option<&Poller>) -> IoEvents {
@@ -367,10 +374,19 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
         drop(state);
         crate::match_ioctl_cmd_mut!(&mut *cmd, {
             cmd: GetSockOptRawCmd => {
-                cmd.execute(self.host_fd())?;
+                if is_tls_option(cmd.level(), cmd.optname()) {
+                    let optval = self.get_tls_option(cmd.level(), cmd.optname())?;
+                    cmd.set_output(&optval)?;
+                } else {
+                    cmd.execute(self.host_fd())?;
+                }
             },
             cmd: SetSockOptRawCmd => {
-                cmd.execute(self.host_fd())?;
+                if is_tls_option(cmd.level(), cmd.optname()) {
+                    self.set_tls_option(cmd.level(), cmd.optname(), cmd.optval())?;
+                } else {
+                    cmd.execute(self.host_fd())?;
+                }
             },
             cmd: SetRecvTimeoutCmd => {
                 self.set_recv_timeout(*cmd.input());
@@ -451,6 +467,27 @@ impl<A: Addr, R: Runtime> StreamSocket<A, R> {
         Ok(())
     }
 
+    // The TLS options are handled in the enclave, so the host never sees the plaintext
+    fn set_tls_option(&self, level: i32, optname: i32, optval: &[u8]) -> Result<()> {
+        let state = self.state.read().unwrap();
+        match &*state {
+            State::Connected(connected_stream) => {
+                connected_stream.set_tls_option(level, optname, optval)
+            }
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn get_tls_option(&self, level: i32, optname: i32) -> Result<Vec<u8>> {
+        let state = self.state.read().unwrap();
+        match &*state {
+            State::Connected(connected_stream) => connected_stream.get_tls_option(level, optname),
+            // No upper layer protocol is attached
+            _ if level != SOL_TLS => Ok(Vec::new()),
+            _ => return_errno!(ENOPROTOOPT, "the TLS upper layer protocol is not set"),
+        }
+    }
+
     fn set_kernel_send_buf_size(&self, buf_size: usize) {
         let buf_size = (128 * 1024 + 1).max(buf_size);
         let state = self.state.read().unwrap();
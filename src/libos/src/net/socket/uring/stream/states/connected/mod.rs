@@ -1,4 +1,5 @@
 use atomic::Ordering;
+use core::sync::atomic::AtomicBool;
 
 use self::recv::Receiver;
 use self::send::Sender;
@@ -10,11 +11,16 @@ use crate::prelude::*;
 
 mod recv;
 mod send;
+mod tls;
+
+pub use self::tls::is_tls_option;
 
 pub struct ConnectedStream<A: Addr + 'static, R: Runtime> {
     common: Arc<Common<A, R>>,
     sender: Sender,
     receiver: Receiver,
+    // Whether the TLS upper layer protocol is attached by TCP_ULP
+    is_tls_ulp: AtomicBool,
 }
 
 impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
@@ -30,6 +36,7 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
             common,
             sender,
             receiver,
+            is_tls_ulp: AtomicBool::new(false),
         });
 
         // Start async recv requests right as early as possible to support poll and
@@ -8,9 +8,11 @@ use atomic::Ordering;
 use io_uring_callback::{Fd, IoHandle};
 use sgx_untrusted_alloc::{MaybeUntrusted, UntrustedBox};
 
+use super::tls::{TlsRx, TLS_HEADER_LEN, TLS_RECORD_TYPE_DATA};
 use super::ConnectedStream;
 use crate::net::socket::uring::runtime::Runtime;
 use crate::net::socket::uring::stream::RECV_BUF_SIZE;
+use crate::net::socket::{CMessagesWriter, SOL_TLS, TLS_GET_RECORD_TYPE};
 use crate::prelude::*;
 use crate::untrusted::UntrustedCircularBuf;
 use crate::util::sync::{Mutex, MutexGuard};
@@ -19,7 +21,35 @@ use crate::events::Poller;
 use crate::fs::IoEvents as Events;
 
 impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
-    pub fn recvmsg(self: &Arc<Self>, bufs: &mut [&mut [u8]], flags: RecvFlags) -> Result<usize> {
+    pub fn recvmsg(
+        self: &Arc<Self>,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        control: Option<&mut [u8]>,
+    ) -> Result<(usize, MsgFlags, usize)> {
+        let mut writer = CMessagesWriter::new(control.unwrap_or(&mut []));
+        // The content type of the TLS records received
+        let mut record_type = None;
+        let can_report_type = writer.data_space() >= 1;
+        let bytes_recv = self.do_recvmsg(bufs, flags, &mut record_type, can_report_type)?;
+
+        let mut msg_flags = MsgFlags::empty();
+        if let Some(record_type) = record_type {
+            writer.push(SOL_TLS, TLS_GET_RECORD_TYPE, &[record_type]);
+            if writer.is_truncated() {
+                msg_flags |= MsgFlags::MSG_CTRUNC;
+            }
+        }
+        Ok((bytes_recv, msg_flags, writer.len()))
+    }
+
+    fn do_recvmsg(
+        self: &Arc<Self>,
+        bufs: &mut [&mut [u8]],
+        flags: RecvFlags,
+        record_type: &mut Option<u8>,
+        can_report_type: bool,
+    ) -> Result<usize> {
         let total_len: usize = bufs.iter().map(|buf| buf.len()).sum();
         if total_len == 0 {
             return Ok(0);
@@ -35,13 +65,25 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
         let mut timeout = self.common.recv_timeout();
         loop {
             // Attempt to read
-            let res = self.try_recvmsg(bufs, flags, iov_buffer_index, iov_buffer_offset);
+            let res = self.try_recvmsg(
+                bufs,
+                flags,
+                iov_buffer_index,
+                iov_buffer_offset,
+                record_type,
+                can_report_type,
+            );
 
             match res {
                 Ok((received_size, index, offset)) => {
                     total_received += received_size;
 
-                    if !flags.contains(RecvFlags::MSG_WAITALL) || total_received == total_len {
+                    // Nothing more can be received at the end of file, or at the boundary
+                    // of TLS records of different content types
+                    if !flags.contains(RecvFlags::MSG_WAITALL)
+                        || total_received == total_len
+                        || received_size == 0
+                    {
                         return Ok(total_received);
                     } else {
                         // save the index and offset for the next round
@@ -94,6 +136,8 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
         flags: RecvFlags,
         iov_buffer_index: usize,
         iov_buffer_offset: usize,
+        record_type: &mut Option<u8>,
+        can_report_type: bool,
     ) -> Result<(usize, usize, usize)> {
         let mut inner = self.receiver.inner.lock();
 
@@ -104,7 +148,15 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
             return_errno!(EINVAL, "flags not supported");
         }
 
-        let res = {
+        let res = if inner.tls_rx.is_some() {
+            inner.consume_records(
+                bufs,
+                iov_buffer_index,
+                iov_buffer_offset,
+                record_type,
+                can_report_type,
+            )?
+        } else {
             let mut total_consumed = 0;
             let mut iov_buffer_index = iov_buffer_index;
             let mut iov_buffer_offset = iov_buffer_offset;
@@ -142,7 +194,7 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
             return Ok(res);
         }
 
-        if inner.recv_buf.is_empty() {
+        if !inner.has_data_to_recv() {
             // Mark the socket as non-readable
             self.common.pollee().del_events(Events::IN);
         }
@@ -290,7 +342,10 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
 
     pub fn bytes_to_consume(self: &Arc<Self>) -> usize {
         let inner = self.receiver.inner.lock();
-        inner.recv_buf.consumable()
+        match &inner.tls_rx {
+            Some(tls_rx) => tls_rx.plaintext().len(),
+            None => inner.recv_buf.consumable(),
+        }
     }
 
     // This function will try to update the kernel recv buf size.
@@ -331,6 +386,23 @@ impl Receiver {
     pub fn need_update(&self) -> bool {
         self.need_update.load(Ordering::Relaxed)
     }
+
+    pub fn set_tls_rx(&self, tls_rx: TlsRx) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if inner.tls_rx.is_some() {
+            return_errno!(EBUSY, "the TLS keys are already set");
+        }
+        inner.tls_rx = Some(tls_rx);
+        Ok(())
+    }
+
+    pub fn tls_crypto_info(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.lock();
+        inner
+            .tls_rx
+            .as_ref()
+            .map(|tls_rx| tls_rx.crypto_info().as_bytes().to_vec())
+    }
 }
 
 impl std::fmt::Debug for Receiver {
@@ -348,6 +420,8 @@ struct Inner {
     is_shutdown: bool,
     end_of_file: bool,
     fatal: Option<Errno>,
+    // The data are decrypted from TLS records if set
+    tls_rx: Option<TlsRx>,
 }
 
 // Safety. `RecvReq` does not implement `Send`. But since all pointers in `RecvReq`
@@ -365,6 +439,7 @@ impl Inner {
             is_shutdown: false,
             end_of_file: false,
             fatal: None,
+            tls_rx: None,
         }
     }
 
@@ -374,6 +449,119 @@ impl Inner {
         self.recv_buf = new_recv_buf;
     }
 
+    // Whether there are data to receive, or an error of TLS records to report
+    fn has_data_to_recv(&mut self) -> bool {
+        let tls_rx = match &self.tls_rx {
+            Some(tls_rx) => tls_rx,
+            None => return !self.recv_buf.is_empty(),
+        };
+        if !tls_rx.plaintext().is_empty() || tls_rx.is_broken() {
+            return true;
+        }
+
+        let mut header = [0; TLS_HEADER_LEN];
+        if self.recv_buf.peek(&mut header) < TLS_HEADER_LEN {
+            return false;
+        }
+        let record_len = TLS_HEADER_LEN + u16::from_be_bytes([header[3], header[4]]) as usize;
+        // A record that never fits in the buffer is reported as an error
+        self.recv_buf.consumable() >= record_len || record_len > self.recv_buf.capacity()
+    }
+
+    /// Receives the decrypted data of TLS records to the bufs.
+    ///
+    /// Like Linux, the data of records of different content types are never received
+    /// together, and a record of a type other than application data is received only
+    /// if the type can be reported by a control message.
+    fn consume_records(
+        &mut self,
+        bufs: &mut [&mut [u8]],
+        iov_buffer_index: usize,
+        iov_buffer_offset: usize,
+        record_type: &mut Option<u8>,
+        can_report_type: bool,
+    ) -> Result<(usize, usize, usize)> {
+        let mut total_consumed = 0;
+        let mut iov_buffer_index = iov_buffer_index;
+        let mut iov_buffer_offset = iov_buffer_offset;
+
+        loop {
+            if self.tls_rx.as_ref().unwrap().plaintext().is_empty() {
+                match self.decrypt_next_record() {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    // Report the error in the next receive
+                    Err(_) if total_consumed > 0 => break,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let tls_rx = self.tls_rx.as_mut().unwrap();
+            let this_type = tls_rx.record_type();
+            if record_type.map_or(false, |record_type| record_type != this_type) {
+                break;
+            }
+            if this_type != TLS_RECORD_TYPE_DATA && !can_report_type {
+                if total_consumed > 0 {
+                    break;
+                }
+                return_errno!(EIO, "no control buffer to report the record type");
+            }
+            *record_type = Some(this_type);
+
+            while iov_buffer_index < bufs.len() && !tls_rx.plaintext().is_empty() {
+                let buf = &mut bufs[iov_buffer_index][iov_buffer_offset..];
+                let plaintext = tls_rx.plaintext();
+                let this_consumed = buf.len().min(plaintext.len());
+                buf[..this_consumed].copy_from_slice(&plaintext[..this_consumed]);
+                tls_rx.consume_plaintext(this_consumed);
+                total_consumed += this_consumed;
+
+                if this_consumed < buf.len() {
+                    iov_buffer_offset += this_consumed;
+                } else {
+                    iov_buffer_index += 1;
+                    iov_buffer_offset = 0;
+                }
+            }
+
+            // A record of a type other than application data is received alone
+            if iov_buffer_index == bufs.len() || this_type != TLS_RECORD_TYPE_DATA {
+                break;
+            }
+        }
+        Ok((total_consumed, iov_buffer_index, iov_buffer_offset))
+    }
+
+    /// Decrypts the next record if it is complete in the recv buffer. Returns whether
+    /// a record is decrypted.
+    fn decrypt_next_record(&mut self) -> Result<bool> {
+        let tls_rx = self.tls_rx.as_mut().unwrap();
+        if tls_rx.is_broken() {
+            return_errno!(EBADMSG, "the connection is broken by a bad record");
+        }
+
+        // The header is peeked, since the record may be incomplete
+        let mut header = [0; TLS_HEADER_LEN];
+        if self.recv_buf.peek(&mut header) < TLS_HEADER_LEN {
+            return Ok(false);
+        }
+        let record_len = tls_rx.record_len(&header)?;
+        if record_len > self.recv_buf.capacity() {
+            return_errno!(EMSGSIZE, "the record is larger than the recv buffer");
+        }
+        if self.recv_buf.consumable() < record_len {
+            return Ok(false);
+        }
+
+        // The record is copied into the enclave before decryption, so that it can not be
+        // modified by the host any more
+        let mut record = vec![0; record_len];
+        self.recv_buf.consume(&mut record);
+        tls_rx.decrypt_record(&record)?;
+        Ok(true)
+    }
+
     /// Constructs a new recv request according to the receiver's internal state.
     ///
     /// The new `RecvReq` will be put into `self.recv_req`, which is a location that is
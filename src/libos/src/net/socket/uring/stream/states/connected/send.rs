@@ -9,6 +9,7 @@ use io_uring_callback::{Fd, IoHandle};
 use log::error;
 use sgx_untrusted_alloc::{MaybeUntrusted, UntrustedBox};
 
+use super::tls::{record_type_of_control, TlsTx, TLS_MAX_PLAINTEXT_LEN};
 use super::ConnectedStream;
 use crate::net::socket::uring::runtime::Runtime;
 use crate::net::socket::uring::stream::SEND_BUF_SIZE;
@@ -22,7 +23,12 @@ use crate::fs::IoEvents as Events;
 
 impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
     // We make sure the all the buffer contents are buffered in kernel and then return.
-    pub fn sendmsg(self: &Arc<Self>, bufs: &[&[u8]], flags: SendFlags) -> Result<usize> {
+    pub fn sendmsg(
+        self: &Arc<Self>,
+        bufs: &[&[u8]],
+        flags: SendFlags,
+        control: Option<&[u8]>,
+    ) -> Result<usize> {
         let total_len: usize = bufs.iter().map(|buf| buf.len()).sum();
         if total_len == 0 {
             return Ok(0);
@@ -33,13 +39,22 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
         let mut iov_buf_id = 0; // user buffer id tracker
         let mut iov_buf_index = 0; // user buffer index tracker
 
+        // The content type of the TLS records, only used when the TLS keys are set
+        let record_type = record_type_of_control(control);
+
         let mask = Events::OUT;
         // Initialize the poller only when needed
         let mut poller = None;
         let mut timeout = self.common.send_timeout();
         loop {
             // Attempt to write
-            let res = self.try_sendmsg(bufs, flags, &mut iov_buf_id, &mut iov_buf_index);
+            let res = self.try_sendmsg(
+                bufs,
+                flags,
+                record_type,
+                &mut iov_buf_id,
+                &mut iov_buf_index,
+            );
             if let Ok(len) = res {
                 send_len += len;
                 // Sent all or sent partial but it is nonblocking, return bytes sent
@@ -89,6 +104,7 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
         self: &Arc<Self>,
         bufs: &[&[u8]],
         flags: SendFlags,
+        record_type: u8,
         iov_buf_id: &mut usize,
         iov_buf_index: &mut usize,
     ) -> Result<usize> {
@@ -120,7 +136,9 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
         // Copy data from the bufs to the send buffer
         // If the send buffer is full, update the user buffer tracker, return error to wait for events
         // And once there is free space, continue from the user buffer tracker
-        let nbytes = {
+        let nbytes = if inner.tls_tx.is_some() {
+            inner.produce_records(bufs, record_type, iov_buf_id, iov_buf_index)?
+        } else {
             let mut total_produced = 0;
             let last_time_buf_id = iov_buf_id.clone();
             let mut last_time_buf_idx = iov_buf_index.clone();
@@ -141,7 +159,7 @@ impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
             total_produced
         };
 
-        if inner.send_buf.is_full() {
+        if inner.is_send_buf_full() {
             // Mark the socket as non-writable
             self.common.pollee().del_events(Events::OUT);
         }
@@ -336,6 +354,23 @@ impl Sender {
     pub fn need_update(&self) -> bool {
         self.need_update.load(Ordering::Relaxed)
     }
+
+    pub fn set_tls_tx(&self, tls_tx: TlsTx) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if inner.tls_tx.is_some() {
+            return_errno!(EBUSY, "the TLS keys are already set");
+        }
+        inner.tls_tx = Some(tls_tx);
+        Ok(())
+    }
+
+    pub fn tls_crypto_info(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.lock();
+        inner
+            .tls_tx
+            .as_ref()
+            .map(|tls_tx| tls_tx.crypto_info().as_bytes().to_vec())
+    }
 }
 
 impl std::fmt::Debug for Sender {
@@ -352,6 +387,8 @@ struct Inner {
     io_handle: Option<IoHandle>,
     is_shutdown: ShutdownStatus,
     fatal: Option<Errno>,
+    // The data are encrypted into TLS records if set
+    tls_tx: Option<TlsTx>,
 }
 
 // Safety. `SendReq` does not implement `Send`. But since all pointers in `SengReq`
@@ -368,6 +405,7 @@ impl Inner {
             io_handle: None,
             is_shutdown: ShutdownStatus::Running,
             fatal: None,
+            tls_tx: None,
         }
     }
 
@@ -377,6 +415,48 @@ impl Inner {
         self.send_buf = new_send_buf;
     }
 
+    fn is_send_buf_full(&self) -> bool {
+        match &self.tls_tx {
+            // Not even a record of one byte fits
+            Some(tls_tx) => self.send_buf.producible() <= tls_tx.overhead(),
+            None => self.send_buf.is_full(),
+        }
+    }
+
+    /// Encrypts the data from the bufs into TLS records, and produces them to the send
+    /// buffer. A record is produced only if it fits entirely, so the length returned is
+    /// that of the plaintext.
+    fn produce_records(
+        &mut self,
+        bufs: &[&[u8]],
+        record_type: u8,
+        iov_buf_id: &mut usize,
+        iov_buf_index: &mut usize,
+    ) -> Result<usize> {
+        let tls_tx = self.tls_tx.as_mut().unwrap();
+        let mut total_produced = 0;
+        loop {
+            let max_len = self
+                .send_buf
+                .producible()
+                .saturating_sub(tls_tx.overhead())
+                .min(TLS_MAX_PLAINTEXT_LEN);
+            if max_len == 0 {
+                break;
+            }
+            let plaintext = gather_bufs(bufs, iov_buf_id, iov_buf_index, max_len);
+            if plaintext.is_empty() {
+                break;
+            }
+
+            let record = tls_tx.encrypt_record(record_type, &plaintext)?;
+            let this_produced = self.send_buf.produce(&record);
+            debug_assert!(this_produced == record.len());
+            total_produced += plaintext.len();
+        }
+        Ok(total_produced)
+    }
+
     pub fn is_shutdown(&self) -> bool {
         self.is_shutdown == ShutdownStatus::PreShutdown
             || self.is_shutdown == ShutdownStatus::PostShutdown
@@ -447,6 +527,29 @@ impl std::fmt::Debug for Inner {
     }
 }
 
+// Gather at most `max_len` bytes from the bufs, starting from the user buffer tracker,
+// which is then advanced.
+fn gather_bufs(
+    bufs: &[&[u8]],
+    iov_buf_id: &mut usize,
+    iov_buf_index: &mut usize,
+    max_len: usize,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    while *iov_buf_id < bufs.len() && data.len() < max_len {
+        let buf = &bufs[*iov_buf_id][*iov_buf_index..];
+        let len = buf.len().min(max_len - data.len());
+        data.extend_from_slice(&buf[..len]);
+        if len < buf.len() {
+            *iov_buf_index += len;
+        } else {
+            *iov_buf_id += 1;
+            *iov_buf_index = 0;
+        }
+    }
+    data
+}
+
 #[repr(C)]
 struct SendReq {
     msg: libc::msghdr,
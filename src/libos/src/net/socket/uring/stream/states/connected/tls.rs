@@ -0,0 +1,441 @@
+//! In-enclave TLS record protection, i.e., kTLS.
+//!
+//! Once the TLS upper layer protocol is attached to a connected socket by
+//! `setsockopt(TCP_ULP, "tls")`, the user may hand over the keys negotiated in the TLS
+//! handshake by `setsockopt(SOL_TLS, TLS_TX / TLS_RX)`. From then on, the data sent is
+//! encrypted into TLS records, and the records received are decrypted, inside the enclave,
+//! so the host only sees the ciphertext. The ABI is compatible with Linux kTLS, so that
+//! the TLS libraries supporting kTLS offload work unchanged.
+//!
+//! Only AES-128-GCM of TLS 1.2 and TLS 1.3 is supported, which is provided by the SGX
+//! crypto library.
+
+use sgx_tcrypto::{rsgx_rijndael128GCM_decrypt, rsgx_rijndael128GCM_encrypt};
+
+use atomic::Ordering;
+
+use super::ConnectedStream;
+use crate::fs::IoEvents as Events;
+use crate::net::socket::uring::runtime::Runtime;
+use crate::net::socket::{CMessages, CmsgData};
+use crate::prelude::*;
+
+pub const SOL_TCP: i32 = 6;
+pub const TCP_ULP: i32 = 31;
+pub const TLS_TX: i32 = 1;
+pub const TLS_RX: i32 = 2;
+
+const TLS_ULP_NAME: &[u8] = b"tls";
+// The max length of the name of an upper layer protocol, including the trailing NUL
+const TCP_ULP_NAME_MAX: usize = 16;
+
+const TLS_1_2_VERSION: u16 = 0x0303;
+const TLS_1_3_VERSION: u16 = 0x0304;
+const TLS_CIPHER_AES_GCM_128: u16 = 51;
+
+pub const TLS_RECORD_TYPE_DATA: u8 = 23;
+
+pub const TLS_HEADER_LEN: usize = 5;
+const TLS_TAG_LEN: usize = 16;
+// The explicit nonce of a TLS 1.2 record
+const TLS_1_2_NONCE_LEN: usize = 8;
+// The max length of the plaintext of a record, i.e., 2^14
+pub const TLS_MAX_PLAINTEXT_LEN: usize = 16384;
+
+/// `struct tls12_crypto_info_aes_gcm_128` in C, which is used by both TLS 1.2 and 1.3.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(non_camel_case_types)]
+struct tls12_crypto_info_aes_gcm_128 {
+    version: u16,
+    cipher_type: u16,
+    iv: [u8; 8],
+    key: [u8; 16],
+    salt: [u8; 4],
+    rec_seq: [u8; 8],
+}
+
+/// The crypto state of one direction of a TLS connection.
+pub struct TlsCryptoInfo {
+    info: tls12_crypto_info_aes_gcm_128,
+}
+
+impl TlsCryptoInfo {
+    pub fn from_bytes(optval: &[u8]) -> Result<Self> {
+        // The common header of all the cipher types, i.e., `struct tls_crypto_info`
+        if optval.len() < 4 {
+            return_errno!(EINVAL, "the crypto info is too short");
+        }
+        let version = u16::from_ne_bytes([optval[0], optval[1]]);
+        let cipher_type = u16::from_ne_bytes([optval[2], optval[3]]);
+        if version != TLS_1_2_VERSION && version != TLS_1_3_VERSION {
+            return_errno!(EINVAL, "the TLS version is not supported");
+        }
+        if cipher_type != TLS_CIPHER_AES_GCM_128 {
+            return_errno!(EINVAL, "only AES-128-GCM is supported");
+        }
+        if optval.len() != std::mem::size_of::<tls12_crypto_info_aes_gcm_128>() {
+            return_errno!(EINVAL, "invalid length of the crypto info");
+        }
+
+        let info =
+            unsafe { (optval.as_ptr() as *const tls12_crypto_info_aes_gcm_128).read_unaligned() };
+        Ok(Self { info })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                &self.info as *const _ as *const u8,
+                std::mem::size_of::<tls12_crypto_info_aes_gcm_128>(),
+            )
+        }
+    }
+
+    fn is_tls13(&self) -> bool {
+        self.info.version == TLS_1_3_VERSION
+    }
+
+    // The nonce of the current record
+    fn nonce(&self) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[..4].copy_from_slice(&self.info.salt);
+        nonce[4..].copy_from_slice(&self.info.iv);
+        if self.is_tls13() {
+            for (byte, seq_byte) in nonce[4..].iter_mut().zip(self.info.rec_seq.iter()) {
+                *byte ^= seq_byte;
+            }
+        }
+        nonce
+    }
+
+    // Advance to the next record. The explicit nonce of TLS 1.2 grows with the sequence
+    // number, like Linux.
+    fn advance(&mut self) {
+        increase_be_bytes(&mut self.info.rec_seq);
+        if !self.is_tls13() {
+            increase_be_bytes(&mut self.info.iv);
+        }
+    }
+
+    // The additional data authenticated along with a record, i.e., the record header for
+    // TLS 1.3, or the sequence number followed by the header of the plaintext for TLS 1.2
+    fn aad(&self, record_type: u8, len: usize) -> Vec<u8> {
+        let mut aad = if self.is_tls13() {
+            Vec::new()
+        } else {
+            self.info.rec_seq.to_vec()
+        };
+        aad.extend_from_slice(&[record_type, 0x03, 0x03]);
+        aad.extend_from_slice(&(len as u16).to_be_bytes());
+        aad
+    }
+}
+
+impl Drop for TlsCryptoInfo {
+    fn drop(&mut self) {
+        // Clear the key material
+        unsafe {
+            std::ptr::write_volatile(&mut self.info.key, [0; 16]);
+        }
+    }
+}
+
+fn increase_be_bytes(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+/// The sending half of a TLS connection.
+pub struct TlsTx {
+    crypto_info: TlsCryptoInfo,
+}
+
+impl TlsTx {
+    pub fn new(crypto_info: TlsCryptoInfo) -> Self {
+        Self { crypto_info }
+    }
+
+    pub fn crypto_info(&self) -> &TlsCryptoInfo {
+        &self.crypto_info
+    }
+
+    /// The length added to the plaintext by a record.
+    pub fn overhead(&self) -> usize {
+        if self.crypto_info.is_tls13() {
+            // The inner content type
+            TLS_HEADER_LEN + 1 + TLS_TAG_LEN
+        } else {
+            TLS_HEADER_LEN + TLS_1_2_NONCE_LEN + TLS_TAG_LEN
+        }
+    }
+
+    /// Encrypt the plaintext into a record.
+    pub fn encrypt_record(&mut self, record_type: u8, plaintext: &[u8]) -> Result<Vec<u8>> {
+        debug_assert!(plaintext.len() <= TLS_MAX_PLAINTEXT_LEN);
+        let record_len = plaintext.len() + self.overhead();
+        let payload_len = record_len - TLS_HEADER_LEN;
+        let crypto_info = &mut self.crypto_info;
+
+        let (outer_type, inner_plaintext) = if crypto_info.is_tls13() {
+            let mut inner_plaintext = plaintext.to_vec();
+            inner_plaintext.push(record_type);
+            (TLS_RECORD_TYPE_DATA, inner_plaintext)
+        } else {
+            (record_type, plaintext.to_vec())
+        };
+
+        let mut record = Vec::with_capacity(record_len);
+        record.extend_from_slice(&[outer_type, 0x03, 0x03]);
+        record.extend_from_slice(&(payload_len as u16).to_be_bytes());
+        if !crypto_info.is_tls13() {
+            record.extend_from_slice(&crypto_info.info.iv);
+        }
+
+        let aad_len = if crypto_info.is_tls13() {
+            payload_len
+        } else {
+            plaintext.len()
+        };
+        let aad = crypto_info.aad(outer_type, aad_len);
+        let mut ciphertext = vec![0; inner_plaintext.len()];
+        let mut tag = [0; TLS_TAG_LEN];
+        rsgx_rijndael128GCM_encrypt(
+            &crypto_info.info.key,
+            &inner_plaintext,
+            &crypto_info.nonce(),
+            &aad,
+            &mut ciphertext,
+            &mut tag,
+        )
+        .map_err(|_| errno!(EIO, "failed to encrypt the record"))?;
+        record.extend_from_slice(&ciphertext);
+        record.extend_from_slice(&tag);
+        debug_assert!(record.len() == record_len);
+
+        crypto_info.advance();
+        Ok(record)
+    }
+}
+
+/// The receiving half of a TLS connection.
+pub struct TlsRx {
+    crypto_info: TlsCryptoInfo,
+    // The decrypted data of the current record, which is not received yet
+    plaintext: Vec<u8>,
+    offset: usize,
+    record_type: u8,
+    is_broken: bool,
+}
+
+impl TlsRx {
+    pub fn new(crypto_info: TlsCryptoInfo) -> Self {
+        Self {
+            crypto_info,
+            plaintext: Vec::new(),
+            offset: 0,
+            record_type: TLS_RECORD_TYPE_DATA,
+            is_broken: false,
+        }
+    }
+
+    pub fn crypto_info(&self) -> &TlsCryptoInfo {
+        &self.crypto_info
+    }
+
+    /// The content type of the current record.
+    pub fn record_type(&self) -> u8 {
+        self.record_type
+    }
+
+    /// The decrypted data of the current record, which is not received yet.
+    pub fn plaintext(&self) -> &[u8] {
+        &self.plaintext[self.offset..]
+    }
+
+    pub fn consume_plaintext(&mut self, len: usize) {
+        self.offset += len;
+        debug_assert!(self.offset <= self.plaintext.len());
+    }
+
+    /// Whether a bad record is received, after which the connection is unusable.
+    pub fn is_broken(&self) -> bool {
+        self.is_broken
+    }
+
+    /// The length of the record whose header is given, or an error if the header is
+    /// invalid.
+    pub fn record_len(&mut self, header: &[u8]) -> Result<usize> {
+        let res = self.do_record_len(header);
+        if res.is_err() {
+            // Like Linux, the connection is unusable after a bad record
+            self.is_broken = true;
+        }
+        res
+    }
+
+    fn do_record_len(&self, header: &[u8]) -> Result<usize> {
+        if header[1..3] != [0x03, 0x03] {
+            return_errno!(EINVAL, "invalid version of the record");
+        }
+
+        let payload_len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let min_payload_len = if self.crypto_info.is_tls13() {
+            TLS_TAG_LEN + 1
+        } else {
+            TLS_1_2_NONCE_LEN + TLS_TAG_LEN
+        };
+        // Like Linux, allow some padding of TLS 1.3
+        let max_payload_len = TLS_MAX_PLAINTEXT_LEN + min_payload_len + 256;
+        if payload_len > max_payload_len {
+            return_errno!(EMSGSIZE, "the record is too long");
+        }
+        if payload_len < min_payload_len {
+            return_errno!(EBADMSG, "the record is too short");
+        }
+        Ok(TLS_HEADER_LEN + payload_len)
+    }
+
+    /// Decrypt a complete record, whose data becomes the current plaintext.
+    pub fn decrypt_record(&mut self, record: &[u8]) -> Result<()> {
+        debug_assert!(self.plaintext().is_empty());
+        let res = self.do_decrypt_record(record);
+        if res.is_err() {
+            self.is_broken = true;
+        }
+        res
+    }
+
+    fn do_decrypt_record(&mut self, record: &[u8]) -> Result<()> {
+        let crypto_info = &mut self.crypto_info;
+        let outer_type = record[0];
+        let payload = &record[TLS_HEADER_LEN..];
+
+        let (ciphertext, aad, nonce) = if crypto_info.is_tls13() {
+            let ciphertext = &payload[..payload.len() - TLS_TAG_LEN];
+            let aad = crypto_info.aad(outer_type, payload.len());
+            (ciphertext, aad, crypto_info.nonce())
+        } else {
+            let ciphertext = &payload[TLS_1_2_NONCE_LEN..payload.len() - TLS_TAG_LEN];
+            let aad = crypto_info.aad(outer_type, ciphertext.len());
+            // The explicit nonce is carried by the record
+            let mut nonce = [0; 12];
+            nonce[..4].copy_from_slice(&crypto_info.info.salt);
+            nonce[4..].copy_from_slice(&payload[..TLS_1_2_NONCE_LEN]);
+            (ciphertext, aad, nonce)
+        };
+        let mut tag = [0; TLS_TAG_LEN];
+        tag.copy_from_slice(&payload[payload.len() - TLS_TAG_LEN..]);
+
+        let mut plaintext = vec![0; ciphertext.len()];
+        rsgx_rijndael128GCM_decrypt(
+            &crypto_info.info.key,
+            ciphertext,
+            &nonce,
+            &aad,
+            &tag,
+            &mut plaintext,
+        )
+        .map_err(|_| errno!(EBADMSG, "failed to decrypt the record"))?;
+        crypto_info.advance();
+
+        let record_type = if crypto_info.is_tls13() {
+            // The real content type is the last non-zero byte, followed by the padding
+            let type_pos = match plaintext.iter().rposition(|byte| *byte != 0) {
+                Some(type_pos) => type_pos,
+                None => return_errno!(EBADMSG, "no content type in the record"),
+            };
+            let record_type = plaintext[type_pos];
+            plaintext.truncate(type_pos);
+            record_type
+        } else {
+            outer_type
+        };
+        if plaintext.len() > TLS_MAX_PLAINTEXT_LEN {
+            return_errno!(EMSGSIZE, "the record is too long");
+        }
+
+        self.plaintext = plaintext;
+        self.offset = 0;
+        self.record_type = record_type;
+        Ok(())
+    }
+}
+
+/// The content type of the record to send, which is given by the `TLS_SET_RECORD_TYPE`
+/// control message.
+pub fn record_type_of_control(control: Option<&[u8]>) -> u8 {
+    let mut control = match control {
+        Some(control) => control.to_vec(),
+        None => return TLS_RECORD_TYPE_DATA,
+    };
+    for cmsg in CMessages::from_bytes(&mut control) {
+        if let CmsgData::TlsRecordType(record_type) = cmsg {
+            return record_type;
+        }
+    }
+    TLS_RECORD_TYPE_DATA
+}
+
+pub fn is_tls_option(level: i32, optname: i32) -> bool {
+    (level == SOL_TCP && optname == TCP_ULP) || level == crate::net::socket::SOL_TLS
+}
+
+impl<A: Addr + 'static, R: Runtime> ConnectedStream<A, R> {
+    pub fn set_tls_option(&self, level: i32, optname: i32, optval: &[u8]) -> Result<()> {
+        if level == SOL_TCP {
+            let name_len = optval
+                .iter()
+                .take(TCP_ULP_NAME_MAX)
+                .position(|byte| *byte == 0)
+                .unwrap_or(optval.len().min(TCP_ULP_NAME_MAX));
+            if &optval[..name_len] != TLS_ULP_NAME {
+                return_errno!(ENOENT, "the upper layer protocol is not supported");
+            }
+            if self.is_tls_ulp.swap(true, Ordering::Relaxed) {
+                return_errno!(EEXIST, "the upper layer protocol is already set");
+            }
+            return Ok(());
+        }
+
+        if !self.is_tls_ulp.load(Ordering::Relaxed) {
+            return_errno!(ENOPROTOOPT, "the TLS upper layer protocol is not set");
+        }
+        match optname {
+            TLS_TX => {
+                let crypto_info = TlsCryptoInfo::from_bytes(optval)?;
+                self.sender.set_tls_tx(TlsTx::new(crypto_info))
+            }
+            TLS_RX => {
+                let crypto_info = TlsCryptoInfo::from_bytes(optval)?;
+                self.receiver.set_tls_rx(TlsRx::new(crypto_info))?;
+                // The data received may be decrypted now
+                self.common.pollee().add_events(Events::IN);
+                Ok(())
+            }
+            _ => return_errno!(ENOPROTOOPT, "the TLS option is not supported"),
+        }
+    }
+
+    pub fn get_tls_option(&self, level: i32, optname: i32) -> Result<Vec<u8>> {
+        let is_tls_ulp = self.is_tls_ulp.load(Ordering::Relaxed);
+        if level == SOL_TCP {
+            let name = if is_tls_ulp { TLS_ULP_NAME } else { b"" };
+            return Ok(name.to_vec());
+        }
+
+        if !is_tls_ulp {
+            return_errno!(ENOPROTOOPT, "the TLS upper layer protocol is not set");
+        }
+        let crypto_info = match optname {
+            TLS_TX => self.sender.tls_crypto_info(),
+            TLS_RX => self.receiver.tls_crypto_info(),
+            _ => return_errno!(ENOPROTOOPT, "the TLS option is not supported"),
+        };
+        crypto_info.ok_or_else(|| errno!(EBUSY, "the crypto info is not set"))
+    }
+}
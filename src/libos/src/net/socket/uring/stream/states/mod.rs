@@ -4,6 +4,6 @@ mod init;
 mod listen;
 
 pub use self::connect::ConnectingStream;
-pub use self::connected::ConnectedStream;
+pub use self::connected::{is_tls_option, ConnectedStream};
 pub use self::init::InitStream;
 pub use self::listen::ListenerStream;
@@ -24,6 +24,8 @@ bitflags! {
         const MSG_DONTWAIT     = 0x40;          // Nonblocking io
         const MSG_WAITALL      = 0x0100;        // Wait for a full request
         const MSG_ERRQUEUE     = 0x2000;        // Fetch message from error queue
+        // recvmmsg only
+        const MSG_WAITFORONE   = 0x10000;       // Turn on MSG_DONTWAIT after the first message has been received
         // recvmsg only
         const MSG_CMSG_CLOEXEC = 0x40000000;    // Set close_on_exec for file descriptor received through SCM_RIGHTS
     }
@@ -21,7 +21,10 @@ pub use self::any_addr::AnyAddr;
 pub use self::domain::Domain;
 pub use self::flags::{mmsghdr, MsgFlags, RecvFlags, SendFlags, SocketFlags};
 pub use self::iovs::{Iovs, IovsMut, SliceAsLibcIovec};
-pub use self::msg::{CMessages, CmsgData};
+pub use self::msg::{
+    ucred_t, CMessages, CMessagesWriter, CmsgData, SOL_TLS, TLS_GET_RECORD_TYPE,
+    TLS_SET_RECORD_TYPE,
+};
 pub use self::protocol::SocketProtocol;
 pub use self::r#type::SocketType;
 pub use self::shutdown::Shutdown;
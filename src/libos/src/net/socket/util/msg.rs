@@ -1,5 +1,6 @@
 /// Socket message and its flags.
 use super::*;
+use crate::process::gid_t;
 
 /// This struct is used to iterate through the control messages.
 ///
@@ -40,10 +41,90 @@ impl<'a> CMessages<'a> {
     }
 }
 
+/// This struct is used to fill control messages into a buffer, e.g., the `msg_control`
+/// buffer of recvmsg.
+///
+/// Like Linux, a control message that does not fit in the buffer is truncated and
+/// `MSG_CTRUNC` should be reported to the user.
+pub struct CMessagesWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    is_truncated: bool,
+}
+
+impl<'a> CMessagesWriter<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            len: 0,
+            is_truncated: false,
+        }
+    }
+
+    /// The max length of the data of the next control message without truncation.
+    pub fn data_space(&self) -> usize {
+        let hdr_len = unsafe { libc::CMSG_LEN(0) as usize };
+        (self.buffer.len() - self.len).saturating_sub(hdr_len)
+    }
+
+    /// Push a control message, whose data may be truncated.
+    pub fn push(&mut self, level: i32, type_: i32, data: &[u8]) {
+        let hdr_len = unsafe { libc::CMSG_LEN(0) as usize };
+        let remaining = self.buffer.len() - self.len;
+        if remaining < hdr_len {
+            self.is_truncated = true;
+            return;
+        }
+
+        let cmsg_len = unsafe { libc::CMSG_LEN(data.len() as _) as usize };
+        let copy_len = if cmsg_len > remaining {
+            self.is_truncated = true;
+            remaining - hdr_len
+        } else {
+            data.len()
+        };
+
+        let buf = &mut self.buffer[self.len..];
+        // The user buffer may be unaligned
+        unsafe {
+            let cmsg = buf.as_mut_ptr() as *mut libc::cmsghdr;
+            std::ptr::addr_of_mut!((*cmsg).cmsg_len).write_unaligned((hdr_len + copy_len) as _);
+            std::ptr::addr_of_mut!((*cmsg).cmsg_level).write_unaligned(level);
+            std::ptr::addr_of_mut!((*cmsg).cmsg_type).write_unaligned(type_);
+        }
+        buf[hdr_len..hdr_len + copy_len].copy_from_slice(&data[..copy_len]);
+
+        let cmsg_space = unsafe { libc::CMSG_SPACE(data.len() as _) as usize };
+        self.len = (self.len + cmsg_space).min(self.buffer.len());
+    }
+
+    /// Mark the control messages as truncated, e.g., some file descriptors are discarded.
+    pub fn set_truncated(&mut self) {
+        self.is_truncated = true;
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    /// The length of the control messages in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// The level of the control messages of a kTLS socket.
+pub const SOL_TLS: i32 = 282;
+/// The type of the control message to send a TLS record of the given content type.
+pub const TLS_SET_RECORD_TYPE: i32 = 1;
+/// The type of the control message reporting the content type of the received record.
+pub const TLS_GET_RECORD_TYPE: i32 = 2;
+
 /// Control message data of variable type. The data resides next to `cmsghdr`.
 pub enum CmsgData<'a> {
     ScmRights(ScmRights<'a>),
-    ScmCredentials,
+    ScmCredentials(ucred_t),
+    TlsRecordType(u8),
 }
 
 impl<'a> CmsgData<'a> {
@@ -64,8 +145,12 @@ impl<'a> CmsgData<'a> {
     ///
     /// `data` must contain a valid control message and the control message must be type of
     /// `SOL_SOCKET` and level of `SCM_CREDENTIALS`.
-    unsafe fn as_credentials(_data: &'a [u8]) -> Self {
-        CmsgData::ScmCredentials
+    unsafe fn as_credentials(data: &'a [u8]) -> Option<Self> {
+        if data.len() != core::mem::size_of::<ucred_t>() {
+            return None;
+        }
+        let ucred = (data.as_ptr() as *const ucred_t).read_unaligned();
+        Some(CmsgData::ScmCredentials(ucred))
     }
 
     fn try_from_cmsghdr(cmsg: &'a libc::cmsghdr) -> Option<Self> {
@@ -78,7 +163,13 @@ impl<'a> CmsgData<'a> {
             match (*cmsg).cmsg_level {
                 libc::SOL_SOCKET => match (*cmsg).cmsg_type {
                     libc::SCM_RIGHTS => Some(CmsgData::as_rights(data)),
-                    libc::SCM_CREDENTIALS => Some(CmsgData::as_credentials(data)),
+                    libc::SCM_CREDENTIALS => CmsgData::as_credentials(data),
+                    _ => None,
+                },
+                SOL_TLS => match (*cmsg).cmsg_type {
+                    TLS_SET_RECORD_TYPE => {
+                        data.first().map(|type_| CmsgData::TlsRecordType(*type_))
+                    }
                     _ => None,
                 },
                 _ => None,
@@ -113,3 +204,43 @@ impl<'a> ScmRights<'a> {
             .map(|fd_bytes| FileDesc::from_ne_bytes(fd_bytes.try_into().unwrap()))
     }
 }
+
+/// The credentials of a process carried by `SCM_CREDENTIALS`, i.e., `struct ucred` in C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub struct ucred_t {
+    pub pid: pid_t,
+    pub uid: uid_t,
+    pub gid: gid_t,
+}
+
+impl ucred_t {
+    /// The credentials of the current process. All the processes run as root in Occlum.
+    pub fn current() -> Self {
+        Self {
+            pid: current!().process().pid(),
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    /// The credentials reported when the sender is unknown, same with Linux.
+    pub fn unknown() -> Self {
+        const OVERFLOW_ID: u32 = 65534;
+        Self {
+            pid: 0,
+            uid: OVERFLOW_ID,
+            gid: OVERFLOW_ID,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                core::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
@@ -1,13 +1,15 @@
-use super::socket::{mmsghdr, MsgFlags, SocketFlags, SocketProtocol};
+use super::socket::{mmsghdr, MsgFlags, SocketFlags, SocketProtocol, NETLINK_ROUTE};
 
 use atomic::Ordering;
 use core::f32::consts::E;
 use num_enum::TryFromPrimitive;
 use std::mem::MaybeUninit;
 use std::ptr;
+use std::sync::Weak;
 use std::time::Duration;
 
 use super::io_multiplexing::{AsEpollFile, EpollCtl, EpollFile, EpollFlags, FdSetExt, PollFd};
+use super::policy::{check_network_policy, is_datagram_allowed, Direction};
 use fs::{CreationFlags, File, FileDesc, FileRef, IoctlCmd};
 use misc::resource_t;
 use process::Process;
@@ -18,13 +20,21 @@ use util::mem_util::from_user;
 
 use super::*;
 
+use crate::config::LIBOS_CONFIG;
 use crate::fs::StatusFlags;
 use crate::io_uring::ENABLE_URING;
 use crate::prelude::*;
 
-// 4096 is default max socket connection value in Ubuntu 20.04
-const SOMAXCONN: u32 = 4096;
 const SOCONN_DEFAULT: u32 = 16;
+// The max num of the messages received by a recvmmsg call, like Linux
+const UIO_MAXIOV: c_uint = 1024;
+
+lazy_static! {
+    // The errors of the sockets to be reported by the next receive, like `sk_err` in
+    // Linux, indexed by the identities of the sockets
+    static ref PENDING_RECV_ERRORS: SgxMutex<HashMap<usize, (Weak<dyn File>, Errno)>> =
+        SgxMutex::new(HashMap::new());
+}
 
 pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<isize> {
     let domain = Domain::try_from(domain as u16)?;
@@ -68,8 +78,10 @@ pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<i
     if file_ref.is_none() {
         match domain {
             Domain::LOCAL => {
-                let unix_socket = unix_socket(socket_type, flags, protocol)?;
-                file_ref = Some(Arc::new(unix_socket));
+                file_ref = Some(unix_socket(socket_type, flags, protocol)?);
+            }
+            Domain::NETLINK if protocol == NETLINK_ROUTE => {
+                file_ref = Some(Arc::new(NetlinkSocket::new(socket_type, flags)?));
             }
             _ => {
                 let socket = HostSocket::new(domain, socket_type, flags, protocol)?;
@@ -78,8 +90,16 @@ pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<i
         }
     };
 
+    // A TCP or UDP socket may be taken over by a loopback socket later
+    let mut file_ref = file_ref.unwrap();
+    if LIBOS_CONFIG.feature.enable_loopback_in_enclave
+        && InetSocket::is_supported(domain, socket_type, protocol)
+    {
+        file_ref = Arc::new(InetSocket::new(file_ref, domain, socket_type));
+    }
+
     let close_on_spawn = flags.contains(SocketFlags::SOCK_CLOEXEC);
-    let fd = current!().add_file(file_ref.unwrap(), close_on_spawn);
+    let fd = current!().add_file(file_ref, close_on_spawn);
     Ok(fd as isize)
 }
 
@@ -94,14 +114,29 @@ pub fn do_bind(fd: c_int, addr: *const libc::sockaddr, addr_len: libc::socklen_t
     trace!("bind to addr: {:?}", addr);
 
     let file_ref = current!().file(fd as FileDesc)?;
+    check_network_policy(&file_ref, Direction::Ingress, &addr, "bind")?;
+
+    // A TCP or UDP socket bound to a loopback address talks inside the enclave
+    if let Ok(inet_socket) = file_ref.as_inet_socket() {
+        if inet_socket.take_over_to_bind(&addr)? {
+            return Ok(0);
+        }
+    }
+
     if let Ok(socket) = file_ref.as_host_socket() {
         let raw_addr = addr.to_raw();
         socket.bind(&raw_addr)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let unix_addr = addr.to_unix()?;
         unix_socket.bind(unix_addr)?;
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        unix_datagram.bind(addr.to_unix()?)?;
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket.bind(&addr)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.bind(&addr)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket.bind(&addr)?;
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -110,23 +145,38 @@ pub fn do_bind(fd: c_int, addr: *const libc::sockaddr, addr_len: libc::socklen_t
 }
 
 pub fn do_listen(fd: c_int, backlog: c_int) -> Result<isize> {
+    // Like Linux, the backlog is silently truncated to net.core.somaxconn
+    let somaxconn = misc::sysctl::somaxconn();
+    let backlog = if backlog as u32 > somaxconn {
+        somaxconn as c_int
+    } else {
+        backlog
+    };
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
         socket.listen(backlog)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.listen(backlog)?;
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        unix_datagram.listen(backlog)?;
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
-        let backlog: u32 = if backlog as u32 > SOMAXCONN {
-            SOMAXCONN
-        } else if backlog == 0 {
+        let backlog: u32 = if backlog == 0 {
             SOCONN_DEFAULT
         } else {
             backlog as u32
         };
         uring_socket.listen(backlog)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.listen(backlog)?;
+    } else if let Ok(_) = file_ref.as_netlink_socket() {
+        return_errno!(EOPNOTSUPP, "The socket is not of a listen supported type");
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
+    // The connections from the loopback sockets in the enclave are accepted as well
+    if let Ok(inet_socket) = file_ref.as_inet_socket() {
+        inet_socket.listen_shadow(backlog)?;
+    }
 
     Ok(0)
 }
@@ -145,6 +195,27 @@ pub fn do_connect(
     }
 
     let file_ref = current!().file(fd as FileDesc)?;
+    // The address is parsed and checked by the network policy once, before any socket
+    // connects to it
+    let any_addr = if addr_set {
+        let addr_len = addr_len as usize;
+        let sockaddr_storage = copy_sock_addr_from_user(addr, addr_len)?;
+        let any_addr = AnyAddr::from_c_storage(&sockaddr_storage, addr_len)?;
+        check_network_policy(&file_ref, Direction::Egress, &any_addr, "connect")?;
+        Some(any_addr)
+    } else {
+        None
+    };
+
+    // A connection to a loopback address bound in the enclave never leaves the enclave
+    if let (Ok(inet_socket), Some(any_addr)) = (file_ref.as_inet_socket(), any_addr.as_ref()) {
+        let socket_types = [SocketType::STREAM, SocketType::DGRAM];
+        if inet_socket.take_over_to_reach(any_addr, &socket_types)? {
+            file_ref.as_loopback_socket()?.connect(any_addr)?;
+            return Ok(0);
+        }
+    }
+
     if let Ok(socket) = file_ref.as_host_socket() {
         let addr_option = if addr_set {
             Some(unsafe { SockAddr::try_from_raw(addr, addr_len as u32)? })
@@ -153,21 +224,31 @@ pub fn do_connect(
         };
 
         socket.connect(addr_option.as_ref())?;
+        connect_shadow(&file_ref, any_addr.as_ref().unwrap_or(&AnyAddr::Unspec))?;
         return Ok(0);
     };
 
-    let addr = {
-        let addr_len = addr_len as usize;
-        let sockaddr_storage = copy_sock_addr_from_user(addr, addr_len)?;
-        let addr = AnyAddr::from_c_storage(&sockaddr_storage, addr_len)?;
-        addr
+    let addr = match any_addr {
+        Some(any_addr) => any_addr,
+        None => return_errno!(EINVAL, "no address is specified"),
     };
 
     if let Ok(unix_socket) = file_ref.as_unix_socket() {
         // TODO: support AF_UNSPEC address for datagram socket use
         unix_socket.connect(addr.to_unix()?)?;
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        if addr.is_unspec() {
+            unix_datagram.disconnect()?;
+        } else {
+            unix_datagram.connect(addr.to_unix()?)?;
+        }
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket.connect(&addr)?;
+        connect_shadow(&file_ref, &addr)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.connect(&addr)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket.connect(&addr)?;
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -197,27 +278,56 @@ pub fn do_accept4(
     let file_ref = current!().file(fd as FileDesc)?;
 
     // Accept the socket
-    let (new_file_ref, sock_addr_option): (Arc<dyn File>, Option<AnyAddr>) =
-        if let Ok(socket) = file_ref.as_host_socket() {
-            let (new_socket_file, sock_addr_option) = socket.accept(sock_flags)?;
-            (
-                Arc::new(new_socket_file),
-                sock_addr_option.map(|raw_addr| AnyAddr::Raw(raw_addr)),
-            )
-        } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-            let (new_socket_file, sock_addr_option) = unix_socket.accept(sock_flags)?;
-            (
-                Arc::new(new_socket_file),
-                sock_addr_option.map(|unix_addr| AnyAddr::Unix(unix_addr)),
-            )
-        } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
-            let nonblocking = sock_flags.contains(SocketFlags::SOCK_NONBLOCK);
-            let accepted_socket = uring_socket.accept(nonblocking)?;
-            let sock_addr = accepted_socket.peer_addr()?;
-            (Arc::new(accepted_socket), Some(sock_addr))
-        } else {
-            return_errno!(ENOTSOCK, "not a socket");
-        };
+    let nonblocking = sock_flags.contains(SocketFlags::SOCK_NONBLOCK);
+    let (new_file_ref, sock_addr_option) = recv_on_either(
+        &file_ref,
+        false,
+        |shadow| -> Result<(FileRef, Option<AnyAddr>)> {
+            Ok(if let Some(shadow) = shadow {
+                let (accepted_socket, sock_addr) = shadow.accept(nonblocking)?;
+                (Arc::new(accepted_socket), Some(sock_addr))
+            } else if let Ok(socket) = file_ref.as_host_socket() {
+                let (new_socket_file, sock_addr_option) = socket.accept(sock_flags)?;
+                (
+                    Arc::new(new_socket_file),
+                    sock_addr_option.map(|raw_addr| AnyAddr::Raw(raw_addr)),
+                )
+            } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
+                let (new_socket_file, sock_addr_option) = unix_socket.accept(sock_flags)?;
+                (
+                    Arc::new(new_socket_file),
+                    sock_addr_option.map(|unix_addr| AnyAddr::Unix(unix_addr)),
+                )
+            } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+                let (new_socket_file, sock_addr_option) = unix_datagram.accept(sock_flags)?;
+                (
+                    Arc::new(new_socket_file),
+                    sock_addr_option.map(|unix_addr| AnyAddr::Unix(unix_addr)),
+                )
+            } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
+                let accepted_socket = uring_socket.accept(nonblocking)?;
+                let sock_addr = accepted_socket.peer_addr()?;
+                (Arc::new(accepted_socket), Some(sock_addr))
+            } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+                let (accepted_socket, sock_addr) = loopback_socket.accept(nonblocking)?;
+                (Arc::new(accepted_socket), Some(sock_addr))
+            } else if let Ok(_) = file_ref.as_netlink_socket() {
+                return_errno!(EOPNOTSUPP, "The socket is not of a accept supported type");
+            } else {
+                return_errno!(ENOTSOCK, "not a socket");
+            })
+        },
+    )?;
+
+    // The connection from a denied address is aborted, and closed by dropping the socket
+    if let Some(sock_addr) = sock_addr_option.as_ref() {
+        if check_network_policy(&new_file_ref, Direction::Ingress, sock_addr, "accept").is_err() {
+            return_errno!(
+                ECONNABORTED,
+                "the connection is denied by the network policy"
+            );
+        }
+    }
 
     let new_fd = current!().add_file(new_file_ref, close_on_spawn);
 
@@ -243,8 +353,14 @@ pub fn do_shutdown(fd: c_int, how: c_int) -> Result<isize> {
         socket.shutdown(how)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.shutdown(how)?;
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        unix_datagram.shutdown(how)?;
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket.shutdown(how)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.shutdown(how)?;
+    } else if let Ok(_) = file_ref.as_netlink_socket() {
+        return_errno!(EOPNOTSUPP, "shutdown is not supported");
     } else {
         return_errno!(EBADF, "not a host socket")
     }
@@ -271,10 +387,22 @@ pub fn do_setsockopt(
         let mut cmd = new_host_setsockopt_cmd(level, optname, optval)?;
         host_socket.ioctl(cmd.as_mut())?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("setsockopt for unix socket is unimplemented");
+        if let Some(mut cmd) = new_unix_setsockopt_cmd(level, optname, optval)? {
+            unix_socket.ioctl(cmd.as_mut())?;
+        }
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        if let Some(mut cmd) = new_unix_datagram_setsockopt_cmd(level, optname, optval)? {
+            unix_datagram.ioctl(cmd.as_mut())?;
+        }
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         let mut cmd = new_uring_setsockopt_cmd(level, optname, optval, uring_socket.get_type())?;
         uring_socket.ioctl(cmd.as_mut())?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let mut cmd = new_uring_setsockopt_cmd(level, optname, optval, loopback_socket.get_type())?;
+        loopback_socket.ioctl(cmd.as_mut())?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let mut cmd = new_uring_setsockopt_cmd(level, optname, optval, netlink_socket.get_type())?;
+        netlink_socket.ioctl(cmd.as_mut())?;
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -311,12 +439,34 @@ pub fn do_getsockopt(
         let src_optval = get_optval(cmd.as_ref())?;
         copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("getsockopt for unix socket is unimplemented");
+        if let Some(mut cmd) = new_unix_getsockopt_cmd(level, optname)? {
+            unix_socket.ioctl(cmd.as_mut())?;
+            let src_optval = get_optval(cmd.as_ref())?;
+            copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
+        }
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        if let Some(mut cmd) = new_unix_datagram_getsockopt_cmd(level, optname)? {
+            unix_datagram.ioctl(cmd.as_mut())?;
+            let src_optval = get_optval(cmd.as_ref())?;
+            copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
+        }
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         let mut cmd = new_uring_getsockopt_cmd(level, optname, optlen, uring_socket.get_type())?;
         uring_socket.ioctl(cmd.as_mut())?;
         let src_optval = get_optval(cmd.as_ref())?;
         copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let socket_type = loopback_socket.get_type();
+        let mut cmd = new_uring_getsockopt_cmd(level, optname, optlen, socket_type)?;
+        loopback_socket.ioctl(cmd.as_mut())?;
+        let src_optval = get_optval(cmd.as_ref())?;
+        copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let socket_type = netlink_socket.get_type();
+        let mut cmd = new_uring_getsockopt_cmd(level, optname, optlen, socket_type)?;
+        netlink_socket.ioctl(cmd.as_mut())?;
+        let src_optval = get_optval(cmd.as_ref())?;
+        copy_bytes_to_user(src_optval, optval_mut, optlen_mut);
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -338,8 +488,14 @@ pub fn do_getpeername(
         host_socket.peer_addr()?.to_c_storage()
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.peer_addr()?.to_c_storage()
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        unix_datagram.peer_addr()?.to_c_storage()
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket.peer_addr()?.to_c_storage()
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.peer_addr()?.to_c_storage()
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket.peer_addr().to_c_storage()
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     };
@@ -366,8 +522,14 @@ pub fn do_getsockname(
         host_socket.addr()?.to_c_storage()
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.addr().to_c_storage()
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        unix_datagram.addr().to_c_storage()
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket.addr()?.to_c_storage()
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.addr()?.to_c_storage()
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket.addr().to_c_storage()
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     };
@@ -405,7 +567,15 @@ pub fn do_sendto(
     let send_flags = SendFlags::from_bits_truncate(flags);
 
     let file_ref = current!().file(fd as FileDesc)?;
-    if let Ok(host_socket) = file_ref.as_host_socket() {
+    if let Some(addr) = addr.as_ref() {
+        check_network_policy(&file_ref, Direction::Egress, addr, "sendto")?;
+    }
+    take_over_to_send(&file_ref, addr.as_ref())?;
+    if let Some(shadow) = shadow_to_send(&file_ref, addr.as_ref()) {
+        shadow
+            .sendto(buf, addr, send_flags)
+            .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(host_socket) = file_ref.as_host_socket() {
         host_socket
             .sendto(buf, send_flags, addr)
             .map(|u| u as isize)
@@ -418,10 +588,27 @@ pub fn do_sendto(
         unix_socket
             .sendto(buf, send_flags, addr)
             .map(|u| u as isize)
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        let addr = match addr {
+            Some(ref any_addr) => Some(any_addr.to_unix()?),
+            None => None,
+        };
+
+        unix_datagram
+            .sendto(buf, send_flags, addr)
+            .map(|u| u as isize)
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket
             .sendto(&buf, addr, send_flags)
             .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket
+            .sendto(buf, addr, send_flags)
+            .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket
+            .sendto(buf, addr, send_flags)
+            .map(|bytes_send| bytes_send as isize)
     } else {
         return_errno!(EBADF, "unsupported file type");
     }
@@ -446,17 +633,47 @@ pub fn do_recvfrom(
         .ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
 
     let file_ref = current!().file(fd as FileDesc)?;
-    let (data_len, addr_recv) = if let Ok(socket) = file_ref.as_host_socket() {
-        socket.recvfrom(buf, recv_flags)?
-    } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        unix_socket
-            .recvfrom(buf, recv_flags)
-            .map(|(len, addr_recv)| (len, addr_recv.map(|unix_addr| AnyAddr::Unix(unix_addr))))?
-    } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
-        uring_socket.recvfrom(&mut buf, recv_flags)?
-    } else {
-        return_errno!(ENOTSOCK, "not a socket");
+    take_pending_recv_error(&file_ref)?;
+    let mut recvfrom = |shadow: Option<&LoopbackSocket>,
+                        recv_flags: RecvFlags|
+     -> Result<(usize, Option<AnyAddr>)> {
+        Ok(if let Some(shadow) = shadow {
+            shadow.recvfrom(buf, recv_flags)?
+        } else if let Ok(socket) = file_ref.as_host_socket() {
+            socket.recvfrom(buf, recv_flags)?
+        } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
+            unix_socket
+                .recvfrom(buf, recv_flags)
+                .map(|(len, addr_recv)| {
+                    (len, addr_recv.map(|unix_addr| AnyAddr::Unix(unix_addr)))
+                })?
+        } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+            unix_datagram
+                .recvfrom(buf, recv_flags)
+                .map(|(len, addr_recv)| {
+                    (len, addr_recv.map(|unix_addr| AnyAddr::Unix(unix_addr)))
+                })?
+        } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
+            uring_socket.recvfrom(&mut buf, recv_flags)?
+        } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+            loopback_socket.recvfrom(buf, recv_flags)?
+        } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+            netlink_socket.recvfrom(buf, recv_flags)?
+        } else {
+            return_errno!(ENOTSOCK, "not a socket");
+        })
     };
+    // The datagrams from the sources denied by the network policy are dropped
+    let dontwait = recv_flags.contains(RecvFlags::MSG_DONTWAIT);
+    let (data_len, addr_recv) = recv_on_either(&file_ref, dontwait, |shadow| loop {
+        let (data_len, addr_recv) = recvfrom(shadow, recv_flags)?;
+        if is_datagram_allowed(&file_ref, addr_recv.as_ref(), "recvfrom") {
+            break Ok((data_len, addr_recv));
+        }
+        if recv_flags.contains(RecvFlags::MSG_PEEK) {
+            recvfrom(shadow, recv_flags - RecvFlags::MSG_PEEK)?;
+        }
+    })?;
 
     if let Some((addr_mut, addr_len_mut)) = addr_and_addr_len {
         if let Some(addr_recv) = addr_recv {
@@ -492,8 +709,8 @@ pub fn do_socketpair(
 
         let current = current!();
         let mut files = current.files().lock();
-        sock_pair[0] = files.put(Arc::new(client_socket), close_on_spawn);
-        sock_pair[1] = files.put(Arc::new(server_socket), close_on_spawn);
+        sock_pair[0] = files.put(client_socket, close_on_spawn);
+        sock_pair[1] = files.put(server_socket, close_on_spawn);
 
         debug!("socketpair: ({}, {})", sock_pair[0], sock_pair[1]);
         Ok(0)
@@ -512,7 +729,15 @@ pub fn do_sendmsg(fd: c_int, msg_ptr: *const libc::msghdr, flags_c: c_int) -> Re
     let flags = SendFlags::from_bits_truncate(flags_c);
 
     let file_ref = current!().file(fd as FileDesc)?;
-    if let Ok(host_socket) = file_ref.as_host_socket() {
+    if let Some(addr) = addr.as_ref() {
+        check_network_policy(&file_ref, Direction::Egress, addr, "sendmsg")?;
+    }
+    take_over_to_send(&file_ref, addr.as_ref())?;
+    if let Some(shadow) = shadow_to_send(&file_ref, addr.as_ref()) {
+        shadow
+            .sendmsg(&bufs[..], addr, flags, control)
+            .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(host_socket) = file_ref.as_host_socket() {
         host_socket
             .sendmsg(&bufs[..], flags, addr, control)
             .map(|bytes_send| bytes_send as isize)
@@ -520,10 +745,27 @@ pub fn do_sendmsg(fd: c_int, msg_ptr: *const libc::msghdr, flags_c: c_int) -> Re
         socket
             .sendmsg(&bufs[..], flags, control)
             .map(|bytes_sent| bytes_sent as isize)
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        let addr = match addr {
+            Some(ref any_addr) => Some(any_addr.to_unix()?),
+            None => None,
+        };
+
+        unix_datagram
+            .sendmsg(&bufs[..], flags, addr, control)
+            .map(|bytes_sent| bytes_sent as isize)
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         uring_socket
             .sendmsg(&bufs[..], addr, flags, control)
             .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket
+            .sendmsg(&bufs[..], addr, flags, control)
+            .map(|bytes_send| bytes_send as isize)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        netlink_socket
+            .sendmsg(&bufs[..], addr, flags, control)
+            .map(|bytes_send| bytes_send as isize)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -534,35 +776,79 @@ pub fn do_recvmsg(fd: c_int, msg_mut_ptr: *mut libc::msghdr, flags_c: c_int) ->
         "recvmsg: fd: {}, msg: {:?}, flags: 0x{:x}",
         fd, msg_mut_ptr, flags_c
     );
-    let (mut msg, mut addr, mut control, mut bufs) = extract_msghdr_mut_from_user(msg_mut_ptr)?;
+    let flags = RecvFlags::from_bits_truncate(flags_c) - RecvFlags::MSG_WAITFORONE;
+
+    let file_ref = current!().file(fd as FileDesc)?;
+    take_pending_recv_error(&file_ref)?;
+    recvmsg_to_user(&file_ref, msg_mut_ptr, flags).map(|bytes_recv| bytes_recv as isize)
+}
+
+pub fn do_recvmmsg(
+    fd: c_int,
+    msgvec_ptr: *mut mmsghdr,
+    vlen: c_uint,
+    flags_c: c_int,
+    timeout_ptr: *const timespec_t,
+) -> Result<isize> {
+    debug!(
+        "recvmmsg: fd: {}, msg: {:?}, vlen: {}, flags: 0x{:x}",
+        fd, msgvec_ptr, vlen, flags_c
+    );
+
+    let vlen = vlen.min(UIO_MAXIOV);
+    from_user::check_mut_array(msgvec_ptr, vlen as usize)?;
+    let msgvec = unsafe { std::slice::from_raw_parts_mut(msgvec_ptr, vlen as usize) };
+
+    // Like Linux, the timeout is only checked after a message is received, so
+    // recvmmsg may still block forever on the first message.
+    let deadline = if timeout_ptr.is_null() {
+        None
+    } else {
+        from_user::check_ptr(timeout_ptr)?;
+        let timeout = timespec_t::from_raw_ptr(timeout_ptr)?.as_duration();
+        Some(time::up_time::get().unwrap() + timeout)
+    };
+
     let flags = RecvFlags::from_bits_truncate(flags_c);
+    let wait_for_one = flags.contains(RecvFlags::MSG_WAITFORONE);
+    let mut flags = flags - RecvFlags::MSG_WAITFORONE;
 
+    // Only uring sockets receive the messages in a batch, which keep as many recv
+    // requests in flight as the messages of the vector. The other sockets still receive
+    // the messages one by one, e.g., a host socket takes an OCall for each message.
     let file_ref = current!().file(fd as FileDesc)?;
-    let (bytes_recv, recv_addr, msg_flags, msg_controllen) =
-        if let Ok(host_socket) = file_ref.as_host_socket() {
-            host_socket.recvmsg(&mut bufs[..], flags, control)?
-        } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-            unix_socket.recvmsg(&mut bufs[..], flags, control)?
-        } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
-            uring_socket.recvmsg(&mut bufs[..], flags, control)?
-        } else {
-            return_errno!(ENOTSOCK, "not a socket")
-        };
+    take_pending_recv_error(&file_ref)?;
+    if let Ok(uring_socket) = file_ref.as_uring_socket() {
+        uring_socket.reserve_recv_batch(msgvec.len());
+    }
 
-    if let Some(addr) = addr {
-        if let Some(recv_addr) = recv_addr {
-            let (c_addr_storage, c_addr_len) = recv_addr.to_c_storage();
-            copy_sock_addr_to_user(c_addr_storage, c_addr_len, addr, &mut msg.msg_namelen);
+    let mut recv_count = 0;
+    for mmsg in msgvec.iter_mut() {
+        match recvmsg_to_user(&file_ref, &mut mmsg.msg_hdr, flags) {
+            Ok(bytes_recv) => mmsg.msg_len = bytes_recv as c_uint,
+            // Return the messages received so far, e.g., upon EAGAIN of MSG_WAITFORONE.
+            // Like Linux, any other error is reported by the next receive.
+            Err(e) if recv_count > 0 => {
+                if e.errno() != EAGAIN {
+                    set_pending_recv_error(&file_ref, e.errno());
+                }
+                break;
+            }
+            Err(e) => return Err(e),
         }
-    }
+        recv_count += 1;
 
-    msg.msg_flags = msg_flags.bits();
-    msg.msg_controllen = msg_controllen;
-    if msg_controllen == 0 {
-        msg.msg_control = ptr::null_mut();
+        if wait_for_one {
+            flags |= RecvFlags::MSG_DONTWAIT;
+        }
+        if let Some(deadline) = deadline {
+            if time::up_time::get().unwrap() >= deadline {
+                break;
+            }
+        }
     }
 
-    Ok(bytes_recv as isize)
+    Ok(recv_count as isize)
 }
 
 pub fn do_sendmmsg(
@@ -581,6 +867,28 @@ pub fn do_sendmmsg(
 
     let flags = SendFlags::from_bits_truncate(flags_c);
     let file_ref = current!().file(fd as FileDesc)?;
+
+    // Only the messages before the first one to a denied address are sent
+    let mut allowed_count = 0;
+    for mmsg in msgvec.iter() {
+        let (addr, _, _) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
+        if let Some(addr) = addr.as_ref() {
+            if let Err(e) = check_network_policy(&file_ref, Direction::Egress, addr, "sendmmsg") {
+                if allowed_count == 0 {
+                    return Err(e);
+                }
+                break;
+            }
+        }
+        allowed_count += 1;
+    }
+    let msgvec = &mut msgvec[..allowed_count];
+
+    // The first destination decides whether the socket is taken over
+    if let Some(mmsg) = msgvec.first() {
+        let (addr, _, _) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
+        take_over_to_send(&file_ref, addr.as_ref())?;
+    }
     let mut send_count = 0;
 
     if let Ok(host_socket) = file_ref.as_host_socket() {
@@ -602,6 +910,27 @@ pub fn do_sendmmsg(
         }
     } else if let Ok(socket) = file_ref.as_unix_socket() {
         return_errno!(EOPNOTSUPP, "does not support unix socket")
+    } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+        for mmsg in (msgvec) {
+            let (addr, bufs, control) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
+            let addr = match addr {
+                Some(ref any_addr) => Some(any_addr.to_unix()?),
+                None => None,
+            };
+
+            if unix_datagram
+                .sendmsg(&bufs[..], flags, addr, control)
+                .map(|bytes_send| {
+                    mmsg.msg_len = bytes_send as c_uint;
+                    bytes_send as isize
+                })
+                .is_ok()
+            {
+                send_count += 1;
+            } else {
+                break;
+            }
+        }
     } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
         for mmsg in (msgvec) {
             let (addr, bufs, control) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
@@ -619,6 +948,40 @@ pub fn do_sendmmsg(
                 break;
             }
         }
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        for mmsg in (msgvec) {
+            let (addr, bufs, control) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
+
+            if loopback_socket
+                .sendmsg(&bufs[..], addr, flags, control)
+                .map(|bytes_send| {
+                    mmsg.msg_len = bytes_send as c_uint;
+                    bytes_send as isize
+                })
+                .is_ok()
+            {
+                send_count += 1;
+            } else {
+                break;
+            }
+        }
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        for mmsg in (msgvec) {
+            let (addr, bufs, control) = extract_msghdr_from_user(&mmsg.msg_hdr)?;
+
+            if netlink_socket
+                .sendmsg(&bufs[..], addr, flags, control)
+                .map(|bytes_send| {
+                    mmsg.msg_len = bytes_send as c_uint;
+                    bytes_send as isize
+                })
+                .is_ok()
+            {
+                send_count += 1;
+            } else {
+                break;
+            }
+        }
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -1009,6 +1372,69 @@ fn copy_sock_addr_from_user(
     Ok(sockaddr_storage)
 }
 
+// An unbound UDP socket sending to a loopback address bound in the enclave is taken over
+// by a loopback socket
+// Connect the shadow socket of a socket bound to a wildcard address as well
+fn connect_shadow(file_ref: &FileRef, addr: &AnyAddr) -> Result<()> {
+    match file_ref.as_inet_socket() {
+        Ok(inet_socket) => inet_socket.connect_shadow(addr),
+        Err(_) => Ok(()),
+    }
+}
+
+// Report the error left by a previous recvmmsg, which has returned the messages received
+// before the error
+fn take_pending_recv_error(file_ref: &FileRef) -> Result<()> {
+    let mut pending_errors = PENDING_RECV_ERRORS.lock().unwrap();
+    if pending_errors.is_empty() {
+        return Ok(());
+    }
+    if let Some((file, errno)) = pending_errors.remove(&file_id(file_ref)) {
+        // The identity may be reused by a new socket once the socket is closed
+        if file.strong_count() > 0 {
+            return_errno!(errno, "the error is left by the previous recvmmsg");
+        }
+    }
+    Ok(())
+}
+
+fn set_pending_recv_error(file_ref: &FileRef, errno: Errno) {
+    let mut pending_errors = PENDING_RECV_ERRORS.lock().unwrap();
+    // Drop the errors of the closed sockets
+    pending_errors.retain(|_, (file, _)| file.strong_count() > 0);
+    pending_errors.insert(file_id(file_ref), (Arc::downgrade(file_ref), errno));
+}
+
+fn file_id(file_ref: &FileRef) -> usize {
+    Arc::as_ptr(file_ref) as *const u8 as usize
+}
+
+fn take_over_to_send(file_ref: &FileRef, addr: Option<&AnyAddr>) -> Result<()> {
+    if let (Ok(inet_socket), Some(addr)) = (file_ref.as_inet_socket(), addr) {
+        inet_socket.take_over_to_reach(addr, &[SocketType::DGRAM])?;
+    }
+    Ok(())
+}
+
+// The shadow socket of a socket bound to a wildcard address, through which the datagrams
+// to the loopback sockets in the enclave are sent
+fn shadow_to_send<'a>(file_ref: &'a FileRef, addr: Option<&AnyAddr>) -> Option<&'a LoopbackSocket> {
+    file_ref.as_inet_socket().ok()?.shadow_to_send(addr)
+}
+
+// Accept or receive from either the shadow socket, if any, or the socket itself. See
+// `InetSocket::recv_on_either`.
+fn recv_on_either<T>(
+    file_ref: &FileRef,
+    dontwait: bool,
+    mut op: impl FnMut(Option<&LoopbackSocket>) -> Result<T>,
+) -> Result<T> {
+    match file_ref.as_inet_socket() {
+        Ok(inet_socket) => inet_socket.recv_on_either(dontwait, op),
+        Err(_) => op(None),
+    }
+}
+
 fn get_slice_from_sock_addr_ptr_mut<'a>(
     addr_ptr: *mut libc::sockaddr,
     addr_len_ptr: *mut libc::socklen_t,
@@ -1086,6 +1512,87 @@ fn new_uring_getsockopt_cmd(
     })
 }
 
+/// Create a new ioctl command for unix socket getsockopt syscall. Returns `None` if the
+/// option is not supported yet.
+fn new_unix_getsockopt_cmd(level: i32, optname: i32) -> Result<Option<Box<dyn IoctlCmd>>> {
+    if level == libc::SOL_SOCKET && optname == SockOptName::SO_PASSCRED as i32 {
+        return Ok(Some(Box::new(GetPassCredCmd::new(()))));
+    }
+
+    warn!("getsockopt for unix socket is unimplemented");
+    Ok(None)
+}
+
+/// Create a new ioctl command for unix datagram socket getsockopt syscall. Besides the
+/// options of all unix sockets, the timeouts are supported.
+fn new_unix_datagram_getsockopt_cmd(
+    level: i32,
+    optname: i32,
+) -> Result<Option<Box<dyn IoctlCmd>>> {
+    if level == libc::SOL_SOCKET {
+        if optname == SockOptName::SO_RCVTIMEO_OLD as i32 {
+            return Ok(Some(Box::new(GetRecvTimeoutCmd::new(()))));
+        }
+        if optname == SockOptName::SO_SNDTIMEO_OLD as i32 {
+            return Ok(Some(Box::new(GetSendTimeoutCmd::new(()))));
+        }
+    }
+    new_unix_getsockopt_cmd(level, optname)
+}
+
+/// Create a new ioctl command for unix socket setsockopt syscall. Returns `None` if the
+/// option is not supported yet.
+fn new_unix_setsockopt_cmd(
+    level: i32,
+    optname: i32,
+    optval: &[u8],
+) -> Result<Option<Box<dyn IoctlCmd>>> {
+    if level == libc::SOL_SOCKET && optname == SockOptName::SO_PASSCRED as i32 {
+        if optval.len() < std::mem::size_of::<i32>() {
+            return_errno!(EINVAL, "optlen is too small");
+        }
+        let passcred = i32::from_ne_bytes(optval[..4].try_into().unwrap()) != 0;
+        return Ok(Some(Box::new(SetPassCredCmd::new(passcred))));
+    }
+
+    warn!("setsockopt for unix socket is unimplemented");
+    Ok(None)
+}
+
+/// Create a new ioctl command for unix datagram socket setsockopt syscall. Besides the
+/// options of all unix sockets, the timeouts are supported.
+fn new_unix_datagram_setsockopt_cmd(
+    level: i32,
+    optname: i32,
+    optval: &[u8],
+) -> Result<Option<Box<dyn IoctlCmd>>> {
+    if level == libc::SOL_SOCKET {
+        if optname == SockOptName::SO_RCVTIMEO_OLD as i32 {
+            let timeout = timeout_from_optval(optval)?;
+            return Ok(Some(Box::new(SetRecvTimeoutCmd::new(timeout))));
+        }
+        if optname == SockOptName::SO_SNDTIMEO_OLD as i32 {
+            let timeout = timeout_from_optval(optval)?;
+            return Ok(Some(Box::new(SetSendTimeoutCmd::new(timeout))));
+        }
+    }
+    new_unix_setsockopt_cmd(level, optname, optval)
+}
+
+/// Parse the `struct timeval` of SO_RCVTIMEO or SO_SNDTIMEO.
+fn timeout_from_optval(optval: &[u8]) -> Result<Duration> {
+    if optval.len() < std::mem::size_of::<libc::timeval>() {
+        return_errno!(EINVAL, "invalid timeout option");
+    }
+    let timeout = unsafe { &*(optval.as_ptr() as *const libc::timeval) };
+    let secs = if timeout.tv_sec < 0 { 0 } else { timeout.tv_sec };
+    let usec = timeout.tv_usec;
+    if usec < 0 || usec > 1000000 || (usec as u32).checked_mul(1000).is_none() {
+        return_errno!(EDOM, "time struct value is invalid");
+    }
+    Ok(Duration::new(secs as u64, usec as u32 * 1000))
+}
+
 /// Create a new ioctl command for host socket setsockopt syscall
 fn new_host_setsockopt_cmd(
     level: i32,
@@ -1150,48 +1657,12 @@ fn new_uring_setsockopt_cmd(
         | SockOptName::SO_COOKIE
         | SockOptName::SO_PEERGROUPS => return_errno!(ENOPROTOOPT, "it's a read-only option"),
         SockOptName::SO_RCVTIMEO_OLD => {
-            let mut timeout: *const libc::timeval = std::ptr::null();
-            if optval.len() >= std::mem::size_of::<libc::timeval>() {
-                timeout = optval as *const _ as *const libc::timeval;
-            } else {
-                return_errno!(EINVAL, "invalid timeout option");
-            }
-            let timeout = unsafe {
-                let secs = if (*timeout).tv_sec < 0 {
-                    0
-                } else {
-                    (*timeout).tv_sec
-                };
-
-                let usec = (*timeout).tv_usec;
-                if usec < 0 || usec > 1000000 || (usec as u32).checked_mul(1000).is_none() {
-                    return_errno!(EDOM, "time struct value is invalid");
-                }
-                Duration::new(secs as u64, (*timeout).tv_usec as u32 * 1000)
-            };
+            let timeout = timeout_from_optval(optval)?;
             trace!("recv timeout = {:?}", timeout);
             Box::new(SetRecvTimeoutCmd::new(timeout))
         }
         SockOptName::SO_SNDTIMEO_OLD => {
-            let mut timeout: *const libc::timeval = std::ptr::null();
-            if optval.len() >= std::mem::size_of::<libc::timeval>() {
-                timeout = optval as *const _ as *const libc::timeval;
-            } else {
-                return_errno!(EINVAL, "invalid timeout option");
-            }
-            let timeout = unsafe {
-                let secs = if (*timeout).tv_sec < 0 {
-                    0
-                } else {
-                    (*timeout).tv_sec
-                };
-
-                let usec = (*timeout).tv_usec;
-                if usec < 0 || usec > 1000000 || (usec as u32).checked_mul(1000).is_none() {
-                    return_errno!(EDOM, "time struct value is invalid");
-                }
-                Duration::new(secs as u64, usec as u32 * 1000)
-            };
+            let timeout = timeout_from_optval(optval)?;
             trace!("send timeout = {:?}", timeout);
             Box::new(SetSendTimeoutCmd::new(timeout))
         }
@@ -1288,6 +1759,9 @@ fn get_optval(cmd: &dyn IoctlCmd) -> Result<&[u8]> {
         cmd : GetRecvBufSizeCmd => {
             cmd.get_output_as_bytes()
         },
+        cmd : GetPassCredCmd => {
+            cmd.get_output_as_bytes()
+        },
         _ => {
             return_errno!(EINVAL, "invalid sockopt command");
         }
@@ -1301,6 +1775,65 @@ fn copy_bytes_to_user(src_buf: &[u8], dst_buf: &mut [u8], dst_len: &mut u32) {
     *dst_len = copy_len as _;
 }
 
+// Receive a message of the socket into the msghdr of the user
+fn recvmsg_to_user(
+    file_ref: &FileRef,
+    msg_mut_ptr: *mut libc::msghdr,
+    flags: RecvFlags,
+) -> Result<usize> {
+    let (mut msg, mut addr, mut control, mut bufs) = extract_msghdr_mut_from_user(msg_mut_ptr)?;
+
+    let mut recvmsg = |shadow: Option<&LoopbackSocket>,
+                       flags: RecvFlags|
+     -> Result<(usize, Option<AnyAddr>, MsgFlags, usize)> {
+        let control = control.as_deref_mut();
+        Ok(if let Some(shadow) = shadow {
+            shadow.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(host_socket) = file_ref.as_host_socket() {
+            host_socket.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
+            unix_socket.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(unix_datagram) = file_ref.as_unix_datagram() {
+            unix_datagram.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(uring_socket) = file_ref.as_uring_socket() {
+            uring_socket.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+            loopback_socket.recvmsg(&mut bufs[..], flags, control)?
+        } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+            netlink_socket.recvmsg(&mut bufs[..], flags, control)?
+        } else {
+            return_errno!(ENOTSOCK, "not a socket")
+        })
+    };
+    // The datagrams from the sources denied by the network policy are dropped
+    let dontwait = flags.contains(RecvFlags::MSG_DONTWAIT);
+    let (bytes_recv, recv_addr, msg_flags, msg_controllen) =
+        recv_on_either(file_ref, dontwait, |shadow| loop {
+            let received = recvmsg(shadow, flags)?;
+            if is_datagram_allowed(file_ref, received.1.as_ref(), "recvmsg") {
+                break Ok(received);
+            }
+            if flags.contains(RecvFlags::MSG_PEEK) {
+                recvmsg(shadow, flags - RecvFlags::MSG_PEEK)?;
+            }
+        })?;
+
+    if let Some(addr) = addr {
+        if let Some(recv_addr) = recv_addr {
+            let (c_addr_storage, c_addr_len) = recv_addr.to_c_storage();
+            copy_sock_addr_to_user(c_addr_storage, c_addr_len, addr, &mut msg.msg_namelen);
+        }
+    }
+
+    msg.msg_flags = msg_flags.bits();
+    msg.msg_controllen = msg_controllen;
+    if msg_controllen == 0 {
+        msg.msg_control = ptr::null_mut();
+    }
+
+    Ok(bytes_recv)
+}
+
 fn extract_msghdr_from_user<'a>(
     msg_ptr: *const libc::msghdr,
 ) -> Result<(Option<AnyAddr>, Vec<&'a [u8]>, Option<&'a [u8]>)> {
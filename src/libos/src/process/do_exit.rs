@@ -7,7 +7,7 @@ use super::do_vfork::{is_vforked_child_process, vfork_return_to_parent};
 use super::pgrp::clean_pgrp_when_exit;
 use super::process::{Process, ProcessFilter};
 use super::{table, ProcessRef, TermStatus, ThreadRef, ThreadStatus};
-use crate::ipc::SYSTEM_V_SHM_MANAGER;
+use crate::ipc::{SYSTEM_V_SEM_MANAGER, SYSTEM_V_SHM_MANAGER};
 use crate::prelude::*;
 use crate::signal::{KernelSignal, SigNum};
 use crate::syscall::CpuContext;
@@ -56,6 +56,8 @@ fn exit_thread(term_status: TermStatus) {
         return;
     }
 
+    // The CPU time of the exiting thread would be lost otherwise
+    crate::time::timer::report_cpu_time(true);
     let num_remaining_threads = thread.exit(term_status);
 
     // Notify a thread, if any, that waits on ctid. See set_tid_address(2) for more info.
@@ -121,6 +123,10 @@ fn exit_process(thread: &ThreadRef, term_status: TermStatus) {
     // Clean used VM
     USER_SPACE_VM_MANAGER.free_chunks_when_exit(thread);
     SYSTEM_V_SHM_MANAGER.detach_shm_when_process_exit(thread);
+    SYSTEM_V_SEM_MANAGER.undo_when_process_exit(process.pid());
+    // Delete POSIX timers and interval timers
+    process.posix_timers().lock().unwrap().clear();
+    process.itimers().lock().unwrap().clear();
 
     // The parent is the idle process
     if parent_inner.is_none() {
@@ -180,6 +186,7 @@ pub fn exit_old_process_for_execve(term_status: TermStatus, new_parent_ref: Proc
     let thread = current!();
 
     // Exit current thread
+    crate::time::timer::report_cpu_time(true);
     let num_remaining_threads = thread.exit(term_status);
     if thread.tid() != thread.process().pid() {
         // Keep the main thread's tid available as long as the process is not destroyed.
@@ -227,6 +234,15 @@ fn exit_process_for_execve(
     let mut process_inner = process.inner();
     // Clean used VM
     USER_SPACE_VM_MANAGER.free_chunks_when_exit(thread);
+    // POSIX timers are not preserved across execve, but interval timers and the
+    // CPU time consumed are
+    process.posix_timers().lock().unwrap().clear();
+    new_parent_ref.cpu_clock().add(process.cpu_clock().now());
+    new_parent_ref
+        .itimers()
+        .lock()
+        .unwrap()
+        .inherit_from(&mut process.itimers().lock().unwrap(), &new_parent_ref);
 
     let mut new_parent_inner = new_parent_ref.inner();
     let pid = process.pid();
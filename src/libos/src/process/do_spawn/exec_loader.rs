@@ -1,6 +1,6 @@
 use super::super::elf_file::*;
 use super::ThreadRef;
-use crate::fs::{AsINodeFile, FileMode, INodeExt};
+use crate::fs::{AsINodeFile, FileMode, INodeExt, MountFlags};
 use crate::prelude::*;
 use rcore_fs::vfs::{FileType, INode, Metadata};
 use std::ffi::CString;
@@ -93,6 +93,9 @@ pub fn load_file_hdr_to_vec(
     if !file_mode.is_executable() {
         return_errno!(EACCES, "file is not executable");
     }
+    if inode.mount_flags().contains(MountFlags::MS_NOEXEC) {
+        return_errno!(EACCES, "the file system is mounted with noexec");
+    }
     if file_mode.has_set_uid() || file_mode.has_set_gid() {
         warn!(
             "set-user-ID and set-group-ID are not supportted, FileMode:{:?}",
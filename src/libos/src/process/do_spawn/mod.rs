@@ -308,6 +308,7 @@ fn new_process_common(
         let new_process = process_builder
             .vm(vm_ref)
             .exec_path(&elf_path)
+            .environ(envp)
             .umask(parent.umask())
             .parent(parent)
             .task(task)
@@ -0,0 +1,50 @@
+//! The I/O statistics of a process, which are shown in /proc/[pid]/io.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The counters are only for statistics, so the relaxed memory ordering is enough.
+#[derive(Debug, Default)]
+pub struct IoStats {
+    rchar: AtomicU64,
+    wchar: AtomicU64,
+    syscr: AtomicU64,
+    syscw: AtomicU64,
+}
+
+impl IoStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account a read system call which has read `bytes` bytes.
+    pub fn add_read(&self, bytes: usize) {
+        self.rchar.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.syscr.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Account a write system call which has written `bytes` bytes.
+    pub fn add_write(&self, bytes: usize) {
+        self.wchar.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.syscw.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of bytes read.
+    pub fn rchar(&self) -> u64 {
+        self.rchar.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes written.
+    pub fn wchar(&self) -> u64 {
+        self.wchar.load(Ordering::Relaxed)
+    }
+
+    /// The number of read system calls.
+    pub fn syscr(&self) -> u64 {
+        self.syscr.load(Ordering::Relaxed)
+    }
+
+    /// The number of write system calls.
+    pub fn syscw(&self) -> u64 {
+        self.syscw.load(Ordering::Relaxed)
+    }
+}
@@ -18,7 +18,7 @@ use crate::vm::ProcessVM;
 
 use self::pgrp::ProcessGrp;
 use self::process::{ProcessBuilder, ProcessInner};
-use self::thread::{ThreadBuilder, ThreadId, ThreadInner};
+use self::thread::{ThreadBuilder, ThreadInner};
 use self::wait::{WaitQueue, Waiter};
 
 pub use self::do_exit::handle_force_exit;
@@ -27,13 +27,14 @@ pub use self::do_robust_list::RobustListHead;
 pub use self::do_spawn::{do_spawn, do_spawn_without_exec};
 pub use self::do_vfork::{do_vfork, handle_force_stop};
 pub use self::do_wait4::idle_reap_zombie_children;
+pub use self::io_stats::IoStats;
 pub use self::process::{Process, ProcessFilter, ProcessStatus, IDLE};
 pub use self::spawn_attribute::posix_spawnattr_t;
 pub use self::spawn_attribute::SpawnAttr;
 pub use self::syscalls::*;
 pub use self::task::Task;
 pub use self::term_status::{ForcedExitStatus, TermStatus};
-pub use self::thread::{Thread, ThreadStatus, IO_BUF_SIZE};
+pub use self::thread::{Thread, ThreadId, ThreadName, ThreadStatus, IO_BUF_SIZE};
 
 mod do_arch_prctl;
 mod do_clone;
@@ -46,6 +47,7 @@ mod do_set_tid_address;
 mod do_spawn;
 mod do_vfork;
 mod do_wait4;
+mod io_stats;
 mod pgrp;
 mod prctl;
 mod process;
@@ -2,13 +2,16 @@ use super::super::table;
 use super::super::task::Task;
 use super::super::thread::{ThreadBuilder, ThreadId, ThreadName};
 use super::super::{
-    FileTableRef, ForcedExitStatus, FsViewRef, NiceValueRef, ProcessGrpRef, ProcessRef,
+    FileTableRef, ForcedExitStatus, FsViewRef, IoStats, NiceValueRef, ProcessGrpRef, ProcessRef,
     ProcessVMRef, ResourceLimitsRef, SchedAgentRef,
 };
 use super::{Process, ProcessInner};
 use crate::fs::FileMode;
 use crate::prelude::*;
 use crate::signal::{SigDispositions, SigQueues, SigSet};
+use crate::time::timer::CpuClock;
+use crate::time::{ITimers, PosixTimers};
+use std::ffi::CString;
 
 #[derive(Debug)]
 pub struct ProcessBuilder {
@@ -19,6 +22,7 @@ pub struct ProcessBuilder {
     pgrp: Option<ProcessGrpRef>,
     // Optional fields, which have reasonable default values
     exec_path: Option<String>,
+    environ: Option<Vec<CString>>,
     umask: Option<FileMode>,
     parent: Option<ProcessRef>,
     no_parent: bool,
@@ -34,6 +38,7 @@ impl ProcessBuilder {
             vm: None,
             pgrp: None,
             exec_path: None,
+            environ: None,
             umask: None,
             parent: None,
             no_parent: false,
@@ -51,6 +56,11 @@ impl ProcessBuilder {
         self
     }
 
+    pub fn environ(mut self, environ: &[CString]) -> Self {
+        self.environ = Some(environ.to_vec());
+        self
+    }
+
     pub fn umask(mut self, umask: FileMode) -> Self {
         self.umask = Some(umask);
         self
@@ -114,7 +124,10 @@ impl ProcessBuilder {
 
     pub fn build(mut self) -> Result<ProcessRef> {
         // Process's pid == Main thread's tid
-        let tid = self.tid.take().unwrap_or_else(|| ThreadId::new());
+        let tid = match self.tid.take() {
+            Some(tid) => tid,
+            None => ThreadId::new()?,
+        };
         let pid = tid.as_u32() as pid_t;
 
         // Check whether parent is given as expected
@@ -128,6 +141,7 @@ impl ProcessBuilder {
         // Build a new process
         let new_process = {
             let exec_path = self.exec_path.take().unwrap_or_default();
+            let environ = self.environ.take().unwrap_or_default();
             let umask = RwLock::new(self.umask.unwrap_or(FileMode::default_umask()));
             let parent = self.parent.take().map(|parent| RwLock::new(parent));
             let pgrp = RwLock::new(self.pgrp.clone());
@@ -135,10 +149,15 @@ impl ProcessBuilder {
             let sig_dispositions = RwLock::new(self.sig_dispositions.unwrap_or_default());
             let sig_queues = RwLock::new(SigQueues::new());
             let forced_exit_status = ForcedExitStatus::new();
+            let posix_timers = SgxMutex::new(PosixTimers::new());
+            let itimers = SgxMutex::new(ITimers::new());
+            let cpu_clock = CpuClock::new();
+            let io_stats = IoStats::new();
             let start_time = crate::time::up_time::get().unwrap();
             Arc::new(Process {
                 pid,
                 exec_path,
+                environ,
                 start_time,
                 umask,
                 parent,
@@ -147,6 +166,10 @@ impl ProcessBuilder {
                 sig_dispositions,
                 sig_queues,
                 forced_exit_status,
+                posix_timers,
+                itimers,
+                cpu_clock,
+                io_stats,
             })
         };
 
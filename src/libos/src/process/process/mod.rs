@@ -1,11 +1,14 @@
+use std::ffi::CString;
 use std::fmt;
 use std::time::Duration;
 
 use super::wait::WaitQueue;
-use super::{ForcedExitStatus, ProcessGrpRef, ProcessRef, TermStatus, ThreadRef};
+use super::{ForcedExitStatus, IoStats, ProcessGrpRef, ProcessRef, TermStatus, ThreadRef};
 use crate::fs::FileMode;
 use crate::prelude::*;
 use crate::signal::{SigDispositions, SigNum, SigQueues};
+use crate::time::timer::CpuClock;
+use crate::time::{ITimers, PosixTimers};
 
 pub use self::builder::ProcessBuilder;
 pub use self::idle::IDLE;
@@ -17,6 +20,7 @@ pub struct Process {
     // Immutable info
     pid: pid_t,
     exec_path: String,
+    environ: Vec<CString>,
     start_time: Duration,
     // Mutable info
     parent: Option<RwLock<ProcessRef>>,
@@ -27,6 +31,12 @@ pub struct Process {
     sig_dispositions: RwLock<SigDispositions>,
     sig_queues: RwLock<SigQueues>,
     forced_exit_status: ForcedExitStatus,
+    // Timer
+    posix_timers: SgxMutex<PosixTimers>,
+    itimers: SgxMutex<ITimers>,
+    cpu_clock: Arc<CpuClock>,
+    // Statistics
+    io_stats: IoStats,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -135,6 +145,11 @@ impl Process {
         &self.exec_path
     }
 
+    /// Get the initial environment variables
+    pub fn environ(&self) -> &[CString] {
+        &self.environ
+    }
+
     /// Get the time the process started after system boot
     ///
     /// The value is expressed in clock ticks
@@ -165,6 +180,26 @@ impl Process {
         &self.sig_dispositions
     }
 
+    /// Get the POSIX timers created by timer_create.
+    pub fn posix_timers(&self) -> &SgxMutex<PosixTimers> {
+        &self.posix_timers
+    }
+
+    /// Get the interval timers set by setitimer and alarm.
+    pub fn itimers(&self) -> &SgxMutex<ITimers> {
+        &self.itimers
+    }
+
+    /// Get the CPU-time clock of the process.
+    pub fn cpu_clock(&self) -> &Arc<CpuClock> {
+        &self.cpu_clock
+    }
+
+    /// Get the statistics of the read and write system calls.
+    pub fn io_stats(&self) -> &IoStats {
+        &self.io_stats
+    }
+
     pub fn term_status(&self) -> Option<TermStatus> {
         self.forced_exit_status.term_status()
     }
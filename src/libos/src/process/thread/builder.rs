@@ -1,5 +1,6 @@
 use spin::Once;
 use std::ptr::NonNull;
+use std::sync::atomic::AtomicU64;
 
 use super::{
     FileTableRef, FsViewRef, NiceValueRef, ProcessRef, ProcessVM, ProcessVMRef, ResourceLimitsRef,
@@ -117,7 +118,10 @@ impl ThreadBuilder {
         let task = self
             .task
             .ok_or_else(|| errno!(EINVAL, "task is mandatory"))?;
-        let tid = self.tid.unwrap_or_else(|| ThreadId::new());
+        let tid = match self.tid {
+            Some(tid) => tid,
+            None => ThreadId::new()?,
+        };
         let clear_ctid = RwLock::new(self.clear_ctid);
         let robust_list = RwLock::new(self.robust_list);
         let inner = SgxMutex::new(ThreadInner::new());
@@ -142,6 +146,9 @@ impl ThreadBuilder {
         } else {
             SgxMutex::new(None)
         };
+        // The host thread of the new thread is yet to be created, whose CPU
+        // time starts from zero
+        let reported_cpu_time = AtomicU64::new(0);
         let host_eventfd = Arc::new(HostEventFd::new()?);
         let raw_ptr = RwLock::new(0);
         let io_buffer = Once::new();
@@ -165,6 +172,7 @@ impl ThreadBuilder {
             sig_tmp_mask,
             sig_stack,
             profiler,
+            reported_cpu_time,
             host_eventfd,
             raw_ptr,
             io_buffer,
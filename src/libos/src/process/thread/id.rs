@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+use crate::misc::sysctl;
 use crate::prelude::*;
 
 /// ThreadId implements self-managed thread IDs.
@@ -15,10 +16,11 @@ impl ThreadId {
     /// Create a new thread ID.
     ///
     /// The thread ID returned is guaranteed to have a value greater than zero.
-    pub fn new() -> ThreadId {
+    /// EAGAIN is returned if all the IDs below kernel.pid_max are in use.
+    pub fn new() -> Result<ThreadId> {
         let mut alloc = THREAD_ID_ALLOC.lock().unwrap();
-        let tid = alloc.alloc();
-        Self { tid }
+        let tid = alloc.alloc()?;
+        Ok(Self { tid })
     }
 
     /// Create a "zero" thread ID.
@@ -32,6 +34,11 @@ impl ThreadId {
     pub fn as_u32(&self) -> u32 {
         self.tid
     }
+
+    /// Return the most recently allocated thread ID.
+    pub fn last_allocated() -> u32 {
+        THREAD_ID_ALLOC.lock().unwrap().next_id
+    }
 }
 
 impl Drop for ThreadId {
@@ -52,9 +59,10 @@ lazy_static! {
 /// PID/TID allocator.
 ///
 /// The allocation strategy is to start from the minimal value (here, 1) and increments
-/// each returned ID, until a maximum value (e.g., 2^32-1) is reached. After that, recycle
-/// from the minimal value and see if it is still in use. If not, use the value; otherwise,
-/// increments again.
+/// each returned ID, until a maximum value (i.e., kernel.pid_max - 1) is reached. After that,
+/// recycle from the minimal value and see if it is still in use. If not, use the value;
+/// otherwise, increments again. If all the values up to the maximum are in use, the
+/// allocation fails with EAGAIN like Linux.
 ///
 /// The allocation strategy above follows the *nix tradition.
 ///
@@ -73,20 +81,29 @@ impl IdAlloc {
         }
     }
 
-    pub fn alloc(&mut self) -> u32 {
+    pub fn alloc(&mut self) -> Result<u32> {
+        let max_id = sysctl::pid_max() - 1;
+        // Only count the IDs up to the maximum, since some IDs in use may be beyond
+        // it if pid_max has been lowered
+        if self.used_ids.len() as u32 >= max_id
+            && self.used_ids.iter().filter(|id| **id <= max_id).count() as u32 >= max_id
+        {
+            return_errno!(EAGAIN, "no more PIDs below pid_max");
+        }
         let new_id = loop {
             // Increments the ID and wrap around if necessary
-            self.next_id = self.next_id.wrapping_add(1);
-            if self.next_id == 0 {
-                self.next_id = 1;
-            }
+            self.next_id = if self.next_id >= max_id {
+                1
+            } else {
+                self.next_id + 1
+            };
 
             if !self.used_ids.contains(&self.next_id) {
                 break self.next_id;
             }
         };
         self.used_ids.insert(new_id);
-        new_id
+        Ok(new_id)
     }
 
     pub fn free(&mut self, id: u32) -> Option<u32> {
@@ -1,6 +1,7 @@
 use spin::Once;
 use std::fmt;
 use std::ptr::NonNull;
+use std::sync::atomic::AtomicU64;
 
 use super::task::Task;
 use super::{
@@ -9,8 +10,8 @@ use super::{
 };
 use crate::events::HostEventFd;
 use crate::fs::{EventCreationFlags, EventFile};
-use crate::net::AsEpollFile;
 use crate::net::THREAD_NOTIFIERS;
+use crate::net::{unix_gc, AsEpollFile, AsUnixDatagram, AsUnixSocket};
 use crate::prelude::*;
 use crate::signal::{SigQueues, SigSet, SigStack};
 use crate::time::ThreadProfiler;
@@ -55,6 +56,9 @@ pub struct Thread {
     sig_stack: SgxMutex<Option<SigStack>>,
     // System call timing
     profiler: SgxMutex<Option<ThreadProfiler>>,
+    // The CPU time of the host thread that is reported to the CPU-time clock of
+    // the process, in nanoseconds
+    reported_cpu_time: AtomicU64,
     // Misc
     host_eventfd: Arc<HostEventFd>,
     raw_ptr: RwLock<usize>,
@@ -123,6 +127,11 @@ impl Thread {
         &self.sig_stack
     }
 
+    /// Get the CPU time of the thread that is reported to the process.
+    pub fn reported_cpu_time(&self) -> &AtomicU64 {
+        &self.reported_cpu_time
+    }
+
     /// Get the alternate thread performance profiler
     pub fn profiler(&self) -> &SgxMutex<Option<ThreadProfiler>> {
         &self.profiler
@@ -154,6 +163,12 @@ impl Thread {
         }
 
         file.release_advisory_locks();
+
+        // The unix sockets in flight may become unreachable once a unix socket is closed
+        if is_unix_socket(&file) {
+            drop(file);
+            unix_gc();
+        }
         Ok(())
     }
 
@@ -161,6 +176,7 @@ impl Thread {
     /// by current process.
     pub fn close_all_files(&self) {
         let files = self.files().lock().del_all();
+        let has_unix_sockets = files.iter().any(is_unix_socket);
         for file in files {
             if let Ok(epoll_file) = file.as_epoll_file() {
                 // Unregister epoll file to avoid deadlock in file table
@@ -169,6 +185,10 @@ impl Thread {
 
             file.release_advisory_locks();
         }
+
+        if has_unix_sockets {
+            unix_gc();
+        }
     }
 
     pub fn fs(&self) -> &FsViewRef {
@@ -333,6 +353,10 @@ impl Thread {
     }
 }
 
+fn is_unix_socket(file: &FileRef) -> bool {
+    file.as_unix_socket().is_ok() || file.as_unix_datagram().is_ok()
+}
+
 impl PartialEq for Thread {
     fn eq(&self, other: &Self) -> bool {
         self.tid() == other.tid()
@@ -36,6 +36,16 @@ impl fmt::Debug for sigval_t {
     }
 }
 
+impl sigval_t {
+    pub fn as_int(&self) -> i32 {
+        unsafe { self.sigval_int }
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        unsafe { self.sigval_ptr }
+    }
+}
+
 impl From<i32> for sigval_t {
     fn from(val: i32) -> sigval_t {
         sigval_t { sigval_int: val }
@@ -58,6 +68,34 @@ impl<T> From<*const T> for sigval_t {
     }
 }
 
+/// The notification method of a sigevent (see sigevent(7)).
+pub const SIGEV_SIGNAL: i32 = 0;
+pub const SIGEV_NONE: i32 = 1;
+pub const SIGEV_THREAD: i32 = 2;
+pub const SIGEV_THREAD_ID: i32 = 4;
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct sigevent_t {
+    pub sigev_value: sigval_t,
+    pub sigev_signo: i32,
+    pub sigev_notify: i32,
+    // The union of sigev_notify_thread_id and the SIGEV_THREAD fields. Only the
+    // thread id is interpreted by the LibOS, the rest is padding.
+    pub sigev_notify_thread_id: i32,
+    _padding: [i32; 11],
+}
+
+impl sigevent_t {
+    /// The signal to notify with. The signal number is validated before it is
+    /// narrowed, so that, e.g., 257 is not taken as SIGHUP.
+    pub fn signum(&self) -> Result<SigNum> {
+        let signo = u8::try_from(self.sigev_signo)
+            .map_err(|_| errno!(EINVAL, "invalid signal number of the notification"))?;
+        SigNum::from_u8(signo)
+    }
+}
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct siginfo_t {
@@ -199,6 +237,34 @@ impl siginfo_t {
     }
 }
 
+/// The signal info read from a signalfd, which is 128 bytes in size.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct signalfd_siginfo_t {
+    pub ssi_signo: u32,
+    pub ssi_errno: i32,
+    pub ssi_code: i32,
+    pub ssi_pid: u32,
+    pub ssi_uid: u32,
+    pub ssi_fd: i32,
+    pub ssi_tid: u32,
+    pub ssi_band: u32,
+    pub ssi_overrun: u32,
+    pub ssi_trapno: u32,
+    pub ssi_status: i32,
+    pub ssi_int: i32,
+    pub ssi_ptr: u64,
+    pub ssi_utime: u64,
+    pub ssi_stime: u64,
+    pub ssi_addr: u64,
+    pub ssi_addr_lsb: u16,
+    _pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    _pad: [u8; 28],
+}
+
 #[derive(Clone)]
 #[repr(C)]
 pub struct ucontext_t {
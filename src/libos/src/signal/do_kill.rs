@@ -48,6 +48,20 @@ pub fn do_kill_from_outside_enclave(filter: ProcessFilter, signum: SigNum) -> Re
     Ok(())
 }
 
+/// Send a signal generated by the LibOS itself, e.g., upon the expiration of an interval timer.
+///
+/// Like `do_kill_from_outside_enclave`, this function does not rely on current!(),
+/// as it may be called by the interrupt thread.
+pub fn do_kill_from_kernel(process: &ProcessRef, signum: SigNum) {
+    if process.status() == ProcessStatus::Zombie {
+        return;
+    }
+
+    let signal = Box::new(KernelSignal::new(signum));
+    let mut sig_queues = process.sig_queues().write().unwrap();
+    sig_queues.enqueue(signal);
+}
+
 fn get_processes(filter: &ProcessFilter) -> Result<Vec<ProcessRef>> {
     let processes = match filter {
         ProcessFilter::WithAnyPid => table::get_all_processes(),
@@ -0,0 +1,26 @@
+use super::constants::*;
+use super::signal_file::{AsSignalFile, SignalFile, SignalFileFlags};
+use super::SigSet;
+use crate::prelude::*;
+
+/// Create a signalfd if the fd is -1, or change the mask of an existing one.
+pub fn do_signalfd(fd: i32, mask: SigSet, flags: SignalFileFlags) -> Result<FileDesc> {
+    debug!(
+        "do_signalfd: fd: {}, mask: {:?}, flags: {:?}",
+        fd, mask, flags
+    );
+
+    // Like sigprocmask, SIGKILL and SIGSTOP are silently ignored
+    let mask = mask - SIGKILL - SIGSTOP;
+
+    let current = current!();
+    if fd != -1 {
+        let file_ref = current.file(fd as FileDesc)?;
+        file_ref.as_signal_file()?.set_mask(mask);
+        return Ok(fd as FileDesc);
+    }
+
+    let file_ref: Arc<dyn File> = Arc::new(SignalFile::new(mask, flags));
+    let fd = current.add_file(file_ref, flags.contains(SignalFileFlags::SFD_CLOEXEC));
+    Ok(fd)
+}
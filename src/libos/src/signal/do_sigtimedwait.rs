@@ -162,7 +162,11 @@ impl Drop for PendingSigWaiter {
     }
 }
 
-fn has_interest_signal(interest: &SigSet, thread: &ThreadRef, process: &ProcessRef) -> bool {
+pub(super) fn has_interest_signal(
+    interest: &SigSet,
+    thread: &ThreadRef,
+    process: &ProcessRef,
+) -> bool {
     let pending = (process.sig_queues().read().unwrap().pending()
         | thread.sig_queues().read().unwrap().pending())
         & *interest;
@@ -170,7 +174,7 @@ fn has_interest_signal(interest: &SigSet, thread: &ThreadRef, process: &ProcessR
     !pending.empty()
 }
 
-fn dequeue_pending_signal(
+pub(super) fn dequeue_pending_signal(
     interest: &SigSet,
     thread: &ThreadRef,
     process: &ProcessRef,
@@ -2,25 +2,33 @@
 
 use crate::prelude::*;
 
-use sig_action::{SigAction, SigActionFlags, SigDefaultAction};
+use sig_action::{SigActionFlags, SigDefaultAction};
 
-pub use self::c_types::{sigaction_t, siginfo_t, sigset_t, stack_t};
+pub use self::c_types::{
+    sigaction_t, sigevent_t, siginfo_t, sigset_t, sigval_t, stack_t, SIGEV_NONE, SIGEV_SIGNAL,
+    SIGEV_THREAD, SIGEV_THREAD_ID,
+};
 pub use self::constants::*;
-pub use self::do_kill::do_kill_from_outside_enclave;
+pub use self::do_kill::{do_kill_from_kernel, do_kill_from_outside_enclave};
 pub use self::do_sigprocmask::MaskOp;
 pub use self::do_sigreturn::{deliver_signal, force_signal};
+pub use self::sig_action::SigAction;
 pub use self::sig_dispositions::SigDispositions;
 pub use self::sig_num::SigNum;
 pub use self::sig_queues::SigQueues;
 pub use self::sig_set::SigSet;
 pub use self::sig_stack::SigStack;
-pub use self::signals::{FaultSignal, KernelSignal, Signal, UserSignal, UserSignalKind};
+pub use self::signal_file::{AsSignalFile, SignalFile, SignalFileFlags};
+pub use self::signals::{
+    FaultSignal, KernelSignal, Signal, TimerOverrun, TimerSignal, UserSignal, UserSignalKind,
+};
 pub use self::syscalls::*;
 
 mod c_types;
 mod do_kill;
 mod do_sigaction;
 mod do_sigaltstack;
+mod do_signalfd;
 mod do_sigpending;
 mod do_sigprocmask;
 mod do_sigreturn;
@@ -32,6 +40,7 @@ mod sig_num;
 mod sig_queues;
 mod sig_set;
 mod sig_stack;
+mod signal_file;
 mod signals;
 mod syscalls;
 
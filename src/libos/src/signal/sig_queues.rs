@@ -38,6 +38,10 @@ impl SigQueues {
         self.count == 0
     }
 
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
     pub fn enqueue(&mut self, signal: Box<dyn Signal>) {
         let signum = signal.num();
         if signum.is_std() {
@@ -0,0 +1,285 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Weak;
+
+use super::c_types::{signalfd_siginfo_t, SI_MESGQ, SI_QUEUE, SI_TIMER};
+use super::constants::*;
+use super::do_sigtimedwait::{dequeue_pending_signal, has_interest_signal, PendingSigWaiter};
+use super::{siginfo_t, SigNum, SigSet};
+use crate::events::Observer;
+use crate::fs::{AccessMode, File, FileRef, IoEvents, IoNotifier, StatusFlags};
+use crate::prelude::*;
+use crate::process::{ProcessRef, ThreadRef};
+
+bitflags! {
+    pub struct SignalFileFlags: i32 {
+        /// Non-blocking
+        const SFD_NONBLOCK = 1 << 11;
+        /// Close on exec
+        const SFD_CLOEXEC = 1 << 19;
+    }
+}
+
+/// The file of a signalfd, through which the pending signals of the mask are read.
+///
+/// Like Linux, the signals are dequeued from the thread which reads the file and its
+/// process. The readiness of the file is reported for the thread which creates it.
+pub struct SignalFile {
+    mask: RwLock<SigSet>,
+    status_flags: RwLock<StatusFlags>,
+    thread: ThreadRef,
+    process: ProcessRef,
+    observer: Arc<SignalFileObserver>,
+}
+
+impl SignalFile {
+    pub fn new(mask: SigSet, flags: SignalFileFlags) -> Self {
+        let status_flags = if flags.contains(SignalFileFlags::SFD_NONBLOCK) {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        };
+        let thread = current!();
+        let process = thread.process().clone();
+        let observer = Arc::new(SignalFileObserver {
+            notifier: IoNotifier::new(),
+        });
+
+        let file = Self {
+            mask: RwLock::new(mask),
+            status_flags: RwLock::new(status_flags),
+            thread,
+            process,
+            observer,
+        };
+        file.register_observer(mask);
+        file
+    }
+
+    pub fn mask(&self) -> SigSet {
+        *self.mask.read().unwrap()
+    }
+
+    pub fn set_mask(&self, new_mask: SigSet) {
+        let mut mask = self.mask.write().unwrap();
+        self.unregister_observer();
+        self.register_observer(new_mask);
+        *mask = new_mask;
+        drop(mask);
+
+        // The signals that are newly interesting may be pending already
+        if has_interest_signal(&new_mask, &self.thread, &self.process) {
+            self.observer.notifier.broadcast(&IoEvents::IN);
+        }
+    }
+
+    fn register_observer(&self, mask: SigSet) {
+        let weak_observer = self.weak_observer();
+        self.thread
+            .sig_queues()
+            .read()
+            .unwrap()
+            .notifier()
+            .register(weak_observer.clone(), Some(mask), None);
+        self.process
+            .sig_queues()
+            .read()
+            .unwrap()
+            .notifier()
+            .register(weak_observer, Some(mask), None);
+    }
+
+    fn unregister_observer(&self) {
+        let weak_observer = self.weak_observer();
+        self.thread
+            .sig_queues()
+            .read()
+            .unwrap()
+            .notifier()
+            .unregister(&weak_observer);
+        self.process
+            .sig_queues()
+            .read()
+            .unwrap()
+            .notifier()
+            .unregister(&weak_observer);
+    }
+
+    fn weak_observer(&self) -> Weak<dyn Observer<SigNum>> {
+        Arc::downgrade(&self.observer) as Weak<dyn Observer<_>>
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+}
+
+impl File for SignalFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        const SIGINFO_SIZE: usize = std::mem::size_of::<signalfd_siginfo_t>();
+
+        let max_count = buf.len() / SIGINFO_SIZE;
+        if max_count == 0 {
+            return_errno!(EINVAL, "the buffer is too small for a signalfd_siginfo");
+        }
+
+        let mask = self.mask();
+        let thread = current!();
+        let process = thread.process().clone();
+        let mut count = 0;
+        while count < max_count {
+            let signal = match dequeue_pending_signal(&mask, &thread, &process) {
+                Some(signal) => signal,
+                // Only block for the first signal
+                None if count > 0 => break,
+                None if self.is_nonblocking() => {
+                    return_errno!(EAGAIN, "no pending signal of the mask");
+                }
+                None => {
+                    let pending_sig_waiter =
+                        PendingSigWaiter::new(thread.clone(), process.clone(), mask);
+                    pending_sig_waiter.wait(None)?
+                }
+            };
+
+            let siginfo = signalfd_siginfo_t::from(&signal.to_info());
+            let siginfo_buf = &mut buf[count * SIGINFO_SIZE..(count + 1) * SIGINFO_SIZE];
+            siginfo_buf.copy_from_slice(unsafe {
+                std::slice::from_raw_parts(
+                    &siginfo as *const signalfd_siginfo_t as *const u8,
+                    SIGINFO_SIZE,
+                )
+            });
+            count += 1;
+        }
+        Ok(count * SIGINFO_SIZE)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        // A signalfd_siginfo cannot be split into multiple buffers
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDONLY)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(*self.status_flags.read().unwrap())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        // Only O_NONBLOCK is meaningful for a signalfd
+        status_flags.set(
+            StatusFlags::O_NONBLOCK,
+            new_status_flags.contains(StatusFlags::O_NONBLOCK),
+        );
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        if has_interest_signal(&self.mask(), &self.thread, &self.process) {
+            IoEvents::IN
+        } else {
+            IoEvents::empty()
+        }
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.observer.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Drop for SignalFile {
+    fn drop(&mut self) {
+        self.unregister_observer();
+    }
+}
+
+impl Debug for SignalFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SignalFile")
+            .field("mask", &self.mask)
+            .field("status_flags", &self.status_flags)
+            .finish()
+    }
+}
+
+/// Turn the arrival of the interesting signals into the readiness of the file.
+struct SignalFileObserver {
+    notifier: IoNotifier,
+}
+
+impl Observer<SigNum> for SignalFileObserver {
+    fn on_event(&self, _signum: &SigNum, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
+        // The lock of the signal queue is held by the caller, so the observers of the
+        // file, e.g., epoll, must not poll the file here.
+        self.notifier.broadcast(&IoEvents::IN);
+    }
+}
+
+impl From<&siginfo_t> for signalfd_siginfo_t {
+    fn from(info: &siginfo_t) -> Self {
+        let mut siginfo = Self::default();
+        siginfo.ssi_signo = info.si_signo as u32;
+        siginfo.ssi_errno = info.si_errno;
+        siginfo.ssi_code = info.si_code;
+
+        // Like Linux, only the fields meaningful to the kind of the signal are copied
+        let signum = SigNum::from_u8(info.si_signo as u8).unwrap();
+        let is_fault = [SIGSEGV, SIGBUS, SIGILL, SIGFPE, SIGTRAP].contains(&signum);
+        match info.si_code {
+            SI_TIMER => {
+                siginfo.ssi_tid = info.si_timerid() as u32;
+                siginfo.ssi_overrun = info.si_overrune() as u32;
+                siginfo.ssi_int = info.si_value().as_int();
+                siginfo.ssi_ptr = info.si_value().as_ptr() as u64;
+            }
+            SI_QUEUE | SI_MESGQ => {
+                siginfo.ssi_pid = info.si_pid();
+                siginfo.ssi_uid = info.si_uid();
+                siginfo.ssi_int = info.si_value().as_int();
+                siginfo.ssi_ptr = info.si_value().as_ptr() as u64;
+            }
+            code if code > 0 && is_fault => {
+                siginfo.ssi_addr = info.si_addr() as u64;
+                siginfo.ssi_addr_lsb = info.si_addr_lsb() as u16;
+            }
+            code if code > 0 && signum == SIGCHLD => {
+                siginfo.ssi_pid = info.si_pid();
+                siginfo.ssi_uid = info.si_uid();
+                siginfo.ssi_status = info.si_status();
+                siginfo.ssi_utime = info.si_utime() as u64;
+                siginfo.ssi_stime = info.si_stime() as u64;
+            }
+            _ => {
+                siginfo.ssi_pid = info.si_pid();
+                siginfo.ssi_uid = info.si_uid();
+            }
+        }
+        siginfo
+    }
+}
+
+pub trait AsSignalFile {
+    fn as_signal_file(&self) -> Result<&SignalFile>;
+}
+
+impl AsSignalFile for FileRef {
+    fn as_signal_file(&self) -> Result<&SignalFile> {
+        self.as_any()
+            .downcast_ref::<SignalFile>()
+            .ok_or_else(|| errno!(EINVAL, "not a signalfd"))
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use super::super::c_types::*;
+use super::super::{SigNum, Signal};
+use crate::prelude::*;
+
+/// A signal generated by the expiration of a POSIX timer.
+#[derive(Debug)]
+pub struct TimerSignal {
+    num: SigNum,
+    timerid: i32,
+    value: sigval_t,
+    overrun: Arc<TimerOverrun>,
+    is_delivered: AtomicBool,
+}
+
+unsafe impl Sync for TimerSignal {}
+unsafe impl Send for TimerSignal {}
+
+impl TimerSignal {
+    pub fn new(num: SigNum, timerid: i32, value: sigval_t, overrun: Arc<TimerOverrun>) -> Self {
+        Self {
+            num,
+            timerid,
+            value,
+            overrun,
+            is_delivered: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Signal for TimerSignal {
+    fn num(&self) -> SigNum {
+        self.num
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        self.is_delivered.store(true, Ordering::Release);
+        let mut info = siginfo_t::new(self.num, SI_TIMER);
+        info.set_si_timerid(self.timerid);
+        info.set_si_overrune(self.overrun.on_deliver());
+        info.set_si_value(self.value);
+        info
+    }
+}
+
+impl Drop for TimerSignal {
+    fn drop(&mut self) {
+        // The signal may be discarded without being delivered, e.g., when the same
+        // standard signal is already pending. Let the timer queue a new one next time.
+        if !self.is_delivered.load(Ordering::Acquire) {
+            self.overrun.on_discard();
+        }
+    }
+}
+
+/// The overrun accounting of a POSIX timer.
+///
+/// According to timer_getoverrun(2), only a single signal is queued for a
+/// timer at any point in time. If the timer expires again while the signal
+/// is still pending, no new signal is queued; the extra expirations are
+/// counted as overruns instead, which are reported to the user upon the
+/// delivery of the signal.
+#[derive(Debug, Default)]
+pub struct TimerOverrun {
+    is_pending: AtomicBool,
+    count: AtomicI32,
+    last_count: AtomicI32,
+}
+
+impl TimerOverrun {
+    /// The max value of an overrun counter, a.k.a. DELAYTIMER_MAX.
+    const MAX_COUNT: i32 = i32::MAX;
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Account for `expirations` expirations of the timer.
+    ///
+    /// Returns whether a new signal should be queued.
+    pub fn on_expire(&self, expirations: u64) -> bool {
+        debug_assert!(expirations > 0);
+        let should_queue = !self.is_pending.swap(true, Ordering::AcqRel);
+        let overruns = if should_queue {
+            expirations - 1
+        } else {
+            expirations
+        };
+        self.add_count(overruns);
+        should_queue
+    }
+
+    /// Called when the pending signal is delivered to the user.
+    ///
+    /// Returns the overrun count associated with the delivered signal.
+    pub fn on_deliver(&self) -> i32 {
+        let count = self.count.swap(0, Ordering::AcqRel);
+        self.last_count.store(count, Ordering::Release);
+        self.is_pending.store(false, Ordering::Release);
+        count
+    }
+
+    /// Called when the pending signal is discarded without being delivered.
+    pub fn on_discard(&self) {
+        self.is_pending.store(false, Ordering::Release);
+    }
+
+    /// Get the overrun count of the last delivered signal.
+    pub fn last_count(&self) -> i32 {
+        self.last_count.load(Ordering::Acquire)
+    }
+
+    /// Reset the overrun accounting, e.g., when the timer is rearmed.
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Release);
+        self.last_count.store(0, Ordering::Release);
+    }
+
+    fn add_count(&self, overruns: u64) {
+        let overruns = overruns.min(Self::MAX_COUNT as u64) as i32;
+        let _ = self
+            .count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                Some(count.saturating_add(overruns))
+            });
+    }
+}
@@ -16,6 +16,8 @@ pub enum UserSignalKind {
     Kill,
     Tkill,
     Sigqueue(sigval_t),
+    // Sent by a POSIX message queue on the arrival of a message
+    Mesgq(sigval_t),
 }
 
 unsafe impl Sync for UserSignalKind {}
@@ -54,13 +56,15 @@ impl Signal for UserSignal {
             UserSignalKind::Kill => SI_USER,
             UserSignalKind::Tkill => SI_TKILL,
             UserSignalKind::Sigqueue(_) => SI_QUEUE,
+            UserSignalKind::Mesgq(_) => SI_MESGQ,
         };
 
         let mut info = siginfo_t::new(self.num, code);
         info.set_si_pid(self.pid);
         info.set_si_uid(self.uid);
-        if let UserSignalKind::Sigqueue(val) = self.kind {
-            info.set_si_value(val);
+        match self.kind {
+            UserSignalKind::Sigqueue(val) | UserSignalKind::Mesgq(val) => info.set_si_value(val),
+            _ => {}
         }
 
         info
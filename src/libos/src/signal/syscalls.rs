@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use super::constants::*;
 use super::do_sigprocmask::MaskOp;
+use super::signal_file::SignalFileFlags;
 use super::signals::FaultSignal;
 use super::{sigaction_t, siginfo_t, sigset_t, stack_t, SigAction, SigNum, SigSet, SigStack};
 use crate::prelude::*;
@@ -215,3 +216,29 @@ pub fn do_rt_sigsuspend(mask_ptr: *const sigset_t) -> Result<isize> {
     super::do_sigsuspend::do_sigsuspend(&mask)?;
     Ok(0)
 }
+
+pub fn do_signalfd(fd: i32, mask_ptr: *const sigset_t, mask_size: usize) -> Result<isize> {
+    do_signalfd4(fd, mask_ptr, mask_size, 0)
+}
+
+pub fn do_signalfd4(
+    fd: i32,
+    mask_ptr: *const sigset_t,
+    mask_size: usize,
+    flags: i32,
+) -> Result<isize> {
+    if mask_size != std::mem::size_of::<sigset_t>() {
+        return_errno!(EINVAL, "unexpected mask size");
+    }
+    let mask = {
+        if mask_ptr.is_null() {
+            return_errno!(EFAULT, "ptr must not be null");
+        }
+        from_user::check_ptr(mask_ptr)?;
+        SigSet::from_c(unsafe { *mask_ptr })
+    };
+    let flags = SignalFileFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+
+    let fd = super::do_signalfd::do_signalfd(fd, mask, flags)?;
+    Ok(fd as isize)
+}
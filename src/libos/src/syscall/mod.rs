@@ -18,33 +18,41 @@ use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem::{ManuallyDrop, MaybeUninit};
 use std::ptr;
 use std::ptr::NonNull;
-use time::{clockid_t, itimerspec_t, timespec_t, timeval_t};
+use time::{clockid_t, itimerspec_t, itimerval_t, timer_t, timespec_t, timeval_t};
 use util::log::{self, LevelFilter};
 use util::mem_util::from_user::*;
 
 use crate::config::user_rootfs_config;
 use crate::exception::do_handle_exception;
 use crate::fs::{
-    do_access, do_chdir, do_chmod, do_chown, do_close, do_creat, do_dup, do_dup2, do_dup3,
-    do_eventfd, do_eventfd2, do_faccessat, do_fallocate, do_fchdir, do_fchmod, do_fchmodat,
-    do_fchown, do_fchownat, do_fcntl, do_fdatasync, do_flock, do_fstat, do_fstatat, do_fstatfs,
-    do_fsync, do_ftruncate, do_futimesat, do_getcwd, do_getdents, do_getdents64, do_ioctl,
-    do_lchown, do_link, do_linkat, do_lseek, do_lstat, do_mkdir, do_mkdirat, do_mount,
-    do_mount_rootfs, do_open, do_openat, do_pipe, do_pipe2, do_pread, do_preadv, do_pwrite,
-    do_pwritev, do_read, do_readlink, do_readlinkat, do_readv, do_rename, do_renameat, do_rmdir,
-    do_sendfile, do_stat, do_statfs, do_symlink, do_symlinkat, do_sync, do_timerfd_create,
-    do_timerfd_gettime, do_timerfd_settime, do_truncate, do_umask, do_umount, do_unlink,
-    do_unlinkat, do_utime, do_utimensat, do_utimes, do_write, do_writev, iovec_t, utimbuf_t,
-    AsTimer, File, FileDesc, FileRef, HostStdioFds, Stat, Statfs,
+    do_access, do_chdir, do_chmod, do_chown, do_chroot, do_close, do_creat, do_dup, do_dup2,
+    do_dup3, do_eventfd, do_eventfd2, do_faccessat, do_fallocate, do_fchdir, do_fchmod,
+    do_fchmodat, do_fchown, do_fchownat, do_fcntl, do_fdatasync, do_fgetxattr, do_flistxattr,
+    do_flock, do_fremovexattr, do_fsetxattr, do_fstat, do_fstatat, do_fstatfs, do_fsync,
+    do_ftruncate, do_futimesat, do_getcwd, do_getdents, do_getdents64, do_getxattr,
+    do_inotify_add_watch, do_inotify_init, do_inotify_init1, do_inotify_rm_watch, do_ioctl,
+    do_lchown, do_lgetxattr, do_link, do_linkat, do_listxattr, do_llistxattr, do_lremovexattr,
+    do_lseek, do_lsetxattr, do_lstat, do_mkdir, do_mkdirat, do_mount, do_mount_rootfs, do_open,
+    do_openat, do_pipe, do_pipe2, do_pivot_root, do_pread, do_preadv, do_pwrite, do_pwritev,
+    do_read, do_readlink, do_readlinkat, do_readv, do_removexattr, do_rename, do_renameat,
+    do_rmdir, do_sendfile, do_setxattr, do_stat, do_statfs, do_symlink, do_symlinkat, do_sync,
+    do_timerfd_create, do_timerfd_gettime, do_timerfd_settime, do_truncate, do_umask, do_umount,
+    do_unlink, do_unlinkat, do_utime, do_utimensat, do_utimes, do_write, do_writev, iovec_t,
+    utimbuf_t, AsTimer, File, FileDesc, FileRef, HostStdioFds, Stat, Statfs,
 };
 use crate::interrupt::{do_handle_interrupt, sgx_interrupt_info_t};
-use crate::ipc::{do_shmat, do_shmctl, do_shmdt, do_shmget, key_t, shmids_t};
+use crate::ipc::{
+    do_mq_getsetattr, do_mq_notify, do_mq_open, do_mq_timedreceive, do_mq_timedsend, do_mq_unlink,
+    do_msgctl, do_msgget, do_msgrcv, do_msgsnd, do_semctl, do_semget, do_semop, do_semtimedop,
+    do_shmat, do_shmctl, do_shmdt, do_shmget, key_t, mq_attr_t, msqid_ds_t, sembuf_t, shmids_t,
+};
 use crate::misc::{resource_t, rlimit_t, sysinfo_t, utsname_t, RandFlags};
 use crate::net::{
     do_accept, do_accept4, do_bind, do_connect, do_epoll_create, do_epoll_create1, do_epoll_ctl,
     do_epoll_pwait, do_epoll_wait, do_getpeername, do_getsockname, do_getsockopt, do_listen,
-    do_poll, do_ppoll, do_pselect6, do_recvfrom, do_recvmsg, do_select, do_sendmmsg, do_sendmsg,
-    do_sendto, do_setsockopt, do_shutdown, do_socket, do_socketpair, mmsghdr, sigset_argpack,
+    do_poll, do_ppoll, do_pselect6, do_recvfrom, do_recvmmsg, do_recvmsg, do_select, do_sendmmsg,
+    do_sendmsg, do_sendto, do_setsockopt, do_shutdown, do_socket, do_socketpair, mmsghdr,
+    sigset_argpack,
 };
 use crate::process::{
     do_arch_prctl, do_clone, do_execve, do_exit, do_exit_group, do_futex, do_get_robust_list,
@@ -59,8 +67,8 @@ use crate::sched::{
 };
 use crate::signal::{
     do_kill, do_rt_sigaction, do_rt_sigpending, do_rt_sigprocmask, do_rt_sigreturn,
-    do_rt_sigsuspend, do_rt_sigtimedwait, do_sigaltstack, do_tgkill, do_tkill, sigaction_t,
-    siginfo_t, sigset_t, stack_t,
+    do_rt_sigsuspend, do_rt_sigtimedwait, do_sigaltstack, do_signalfd, do_signalfd4, do_tgkill,
+    do_tkill, sigaction_t, sigevent_t, siginfo_t, sigset_t, stack_t,
 };
 use crate::vm::{MMapFlags, MRemapFlags, MSyncFlags, MadviceFlags, VMPerms};
 use crate::{fs, process, std, vm};
@@ -132,9 +140,9 @@ macro_rules! process_syscall_table_with_callback {
             (Dup2 = 33) => do_dup2(old_fd: FileDesc, new_fd: FileDesc),
             (Pause = 34) => handle_unsupported(),
             (Nanosleep = 35) => do_nanosleep(req_u: *const timespec_t, rem_u: *mut timespec_t),
-            (Getitimer = 36) => handle_unsupported(),
-            (Alarm = 37) => handle_unsupported(),
-            (Setitimer = 38) => handle_unsupported(),
+            (Getitimer = 36) => do_getitimer(which: i32, curr_value: *mut itimerval_t),
+            (Alarm = 37) => do_alarm(seconds: u32),
+            (Setitimer = 38) => do_setitimer(which: i32, new_value: *const itimerval_t, old_value: *mut itimerval_t),
             (Getpid = 39) => do_getpid(),
             (Sendfile = 40) => do_sendfile(out_fd: FileDesc, in_fd: FileDesc, offset_ptr: *mut off_t, count: usize),
             (Socket = 41) => do_socket(domain: c_int, socket_type: c_int, protocol: c_int),
@@ -160,14 +168,14 @@ macro_rules! process_syscall_table_with_callback {
             (Wait4 = 61) => do_wait4(pid: i32, _exit_status: *mut i32, options: u32),
             (Kill = 62) => do_kill(pid: i32, sig: c_int),
             (Uname = 63) => do_uname(name: *mut utsname_t),
-            (Semget = 64) => handle_unsupported(),
-            (Semop = 65) => handle_unsupported(),
-            (Semctl = 66) => handle_unsupported(),
+            (Semget = 64) => do_semget(key: key_t, nsems: i32, semflg: i32),
+            (Semop = 65) => do_semop(semid: i32, sops: *const sembuf_t, nsops: size_t),
+            (Semctl = 66) => do_semctl(semid: i32, semnum: i32, cmd: i32, arg: u64),
             (Shmdt = 67) => do_shmdt(shmaddr: usize),
-            (Msgget = 68) => handle_unsupported(),
-            (Msgsnd = 69) => handle_unsupported(),
-            (Msgrcv = 70) => handle_unsupported(),
-            (Msgctl = 71) => handle_unsupported(),
+            (Msgget = 68) => do_msgget(key: key_t, msgflg: i32),
+            (Msgsnd = 69) => do_msgsnd(msqid: i32, msgp: *const u8, msgsz: size_t, msgflg: i32),
+            (Msgrcv = 70) => do_msgrcv(msqid: i32, msgp: *mut u8, msgsz: size_t, msgtyp: i64, msgflg: i32),
+            (Msgctl = 71) => do_msgctl(msqid: i32, cmd: i32, buf: *mut msqid_ds_t),
             (Fcntl = 72) => do_fcntl(fd: FileDesc, cmd: u32, arg: u64),
             (Flock = 73) => do_flock(fd: FileDesc, operation: i32),
             (Fsync = 74) => do_fsync(fd: FileDesc),
@@ -251,13 +259,13 @@ macro_rules! process_syscall_table_with_callback {
             (Munlockall = 152) => handle_unsupported(),
             (Vhangup = 153) => handle_unsupported(),
             (ModifyLdt = 154) => handle_unsupported(),
-            (PivotRoot = 155) => handle_unsupported(),
+            (PivotRoot = 155) => do_pivot_root(new_root: *const i8, put_old: *const i8),
             (SysCtl = 156) => handle_unsupported(),
             (Prctl = 157) => do_prctl(option: i32, arg2: u64, arg3: u64, arg4: u64, arg5: u64),
             (ArchPrctl = 158) => do_arch_prctl(code: u32, addr: *mut usize),
             (Adjtimex = 159) => handle_unsupported(),
             (Setrlimit = 160) => do_settrlimit(resource: u32, rlim: *const rlimit_t),
-            (Chroot = 161) => handle_unsupported(),
+            (Chroot = 161) => do_chroot(path: *const i8),
             (Sync = 162) => do_sync(),
             (Acct = 163) => handle_unsupported(),
             (Settimeofday = 164) => handle_unsupported(),
@@ -284,18 +292,18 @@ macro_rules! process_syscall_table_with_callback {
             (Security = 185) => handle_unsupported(),
             (Gettid = 186) => do_gettid(),
             (Readahead = 187) => handle_unsupported(),
-            (Setxattr = 188) => handle_unsupported(),
-            (Lsetxattr = 189) => handle_unsupported(),
-            (Fsetxattr = 190) => handle_unsupported(),
-            (Getxattr = 191) => handle_unsupported(),
-            (Lgetxattr = 192) => handle_unsupported(),
-            (Fgetxattr = 193) => handle_unsupported(),
-            (Listxattr = 194) => handle_unsupported(),
-            (Llistxattr = 195) => handle_unsupported(),
-            (Flistxattr = 196) => handle_unsupported(),
-            (Removexattr = 197) => handle_unsupported(),
-            (Lremovexattr = 198) => handle_unsupported(),
-            (Fremovexattr = 199) => handle_unsupported(),
+            (Setxattr = 188) => do_setxattr(path: *const i8, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Lsetxattr = 189) => do_lsetxattr(path: *const i8, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Fsetxattr = 190) => do_fsetxattr(fd: FileDesc, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Getxattr = 191) => do_getxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize),
+            (Lgetxattr = 192) => do_lgetxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize),
+            (Fgetxattr = 193) => do_fgetxattr(fd: FileDesc, name: *const i8, value: *mut u8, size: usize),
+            (Listxattr = 194) => do_listxattr(path: *const i8, list: *mut u8, size: usize),
+            (Llistxattr = 195) => do_llistxattr(path: *const i8, list: *mut u8, size: usize),
+            (Flistxattr = 196) => do_flistxattr(fd: FileDesc, list: *mut u8, size: usize),
+            (Removexattr = 197) => do_removexattr(path: *const i8, name: *const i8),
+            (Lremovexattr = 198) => do_lremovexattr(path: *const i8, name: *const i8),
+            (Fremovexattr = 199) => do_fremovexattr(fd: FileDesc, name: *const i8),
             (Tkill = 200) => do_tkill(tid: pid_t, sig: c_int),
             (Time = 201) => do_time(tloc_u: *mut time_t),
             (Futex = 202) => do_futex(futex_addr: *const i32, futex_op: u32, futex_val: i32, timeout: u64, futex_new_addr: *const i32, bitset: u32),
@@ -316,13 +324,13 @@ macro_rules! process_syscall_table_with_callback {
             (Getdents64 = 217) => do_getdents64(fd: FileDesc, buf: *mut u8, buf_size: usize),
             (SetTidAddress = 218) => do_set_tid_address(tidptr: *mut pid_t),
             (RestartSysCall = 219) => handle_unsupported(),
-            (Semtimedop = 220) => handle_unsupported(),
+            (Semtimedop = 220) => do_semtimedop(semid: i32, sops: *const sembuf_t, nsops: size_t, timeout: *const timespec_t),
             (Fadvise64 = 221) => handle_unsupported(),
-            (TimerCreate = 222) => handle_unsupported(),
-            (TimerSettime = 223) => handle_unsupported(),
-            (TimerGettime = 224) => handle_unsupported(),
-            (TimerGetoverrun = 225) => handle_unsupported(),
-            (TimerDelete = 226) => handle_unsupported(),
+            (TimerCreate = 222) => do_timer_create(clockid: clockid_t, sevp: *const sigevent_t, timerid_ptr: *mut timer_t),
+            (TimerSettime = 223) => do_timer_settime(timerid: timer_t, flags: i32, new_value: *const itimerspec_t, old_value: *mut itimerspec_t),
+            (TimerGettime = 224) => do_timer_gettime(timerid: timer_t, curr_value: *mut itimerspec_t),
+            (TimerGetoverrun = 225) => do_timer_getoverrun(timerid: timer_t),
+            (TimerDelete = 226) => do_timer_delete(timerid: timer_t),
             (ClockSettime = 227) => handle_unsupported(),
             (ClockGettime = 228) => do_clock_gettime(clockid: clockid_t, ts_u: *mut timespec_t),
             (ClockGetres = 229) => do_clock_getres(clockid: clockid_t, res_u: *mut timespec_t),
@@ -336,12 +344,12 @@ macro_rules! process_syscall_table_with_callback {
             (Mbind = 237) => handle_unsupported(),
             (SetMempolicy = 238) => handle_unsupported(),
             (GetMempolicy = 239) => handle_unsupported(),
-            (MqOpen = 240) => handle_unsupported(),
-            (MqUnlink = 241) => handle_unsupported(),
-            (MqTimedsend = 242) => handle_unsupported(),
-            (MqTimedreceive = 243) => handle_unsupported(),
-            (MqNotify = 244) => handle_unsupported(),
-            (MqGetsetattr = 245) => handle_unsupported(),
+            (MqOpen = 240) => do_mq_open(name: *const i8, oflag: u32, mode: u16, attr: *const mq_attr_t),
+            (MqUnlink = 241) => do_mq_unlink(name: *const i8),
+            (MqTimedsend = 242) => do_mq_timedsend(mqdes: FileDesc, msg_ptr: *const u8, msg_len: size_t, msg_prio: u32, abs_timeout: *const timespec_t),
+            (MqTimedreceive = 243) => do_mq_timedreceive(mqdes: FileDesc, msg_ptr: *mut u8, msg_len: size_t, msg_prio: *mut u32, abs_timeout: *const timespec_t),
+            (MqNotify = 244) => do_mq_notify(mqdes: FileDesc, sevp: *const sigevent_t),
+            (MqGetsetattr = 245) => do_mq_getsetattr(mqdes: FileDesc, newattr: *const mq_attr_t, oldattr: *mut mq_attr_t),
             (KexecLoad = 246) => handle_unsupported(),
             (Waitid = 247) => handle_unsupported(),
             (AddKey = 248) => handle_unsupported(),
@@ -349,9 +357,9 @@ macro_rules! process_syscall_table_with_callback {
             (Keyctl = 250) => handle_unsupported(),
             (IoprioSet = 251) => handle_unsupported(),
             (IoprioGet = 252) => handle_unsupported(),
-            (InotifyInit = 253) => handle_unsupported(),
-            (InotifyAddWatch = 254) => handle_unsupported(),
-            (InotifyRmWatch = 255) => handle_unsupported(),
+            (InotifyInit = 253) => do_inotify_init(),
+            (InotifyAddWatch = 254) => do_inotify_add_watch(fd: FileDesc, path: *const i8, mask: u32),
+            (InotifyRmWatch = 255) => do_inotify_rm_watch(fd: FileDesc, wd: i32),
             (MigratePages = 256) => handle_unsupported(),
             (Openat = 257) => do_openat(dirfd: i32, path: *const i8, flags: u32, mode: u16),
             (Mkdirat = 258) => do_mkdirat(dirfd: i32, path: *const i8, mode: u16),
@@ -378,24 +386,24 @@ macro_rules! process_syscall_table_with_callback {
             (MovePages = 279) => handle_unsupported(),
             (Utimensat = 280) => do_utimensat(dirfd: i32, path: *const i8, times: *const timespec_t, flags: i32),
             (EpollPwait = 281) => do_epoll_pwait(epfd: c_int, events: *mut libc::epoll_event, maxevents: c_int, timeout: c_int, sigmask: *const usize),
-            (Signalfd = 282) => handle_unsupported(),
+            (Signalfd = 282) => do_signalfd(fd: i32, mask_ptr: *const sigset_t, mask_size: usize),
             (TimerfdCreate = 283) => do_timerfd_create(clockid: clockid_t, flags: i32 ),
             (Eventfd = 284) => do_eventfd(init_val: u32),
             (Fallocate = 285) => do_fallocate(fd: FileDesc, mode: u32, offset: off_t, len: off_t),
             (TimerfdSettime = 286) => do_timerfd_settime(fd: FileDesc, flags: i32, new_value: *const itimerspec_t, old_value: *mut itimerspec_t),
             (TimerfdGettime = 287) => do_timerfd_gettime(fd: FileDesc, curr_value: *mut itimerspec_t),
             (Accept4 = 288) => do_accept4(fd: c_int, addr: *mut libc::sockaddr, addr_len: *mut libc::socklen_t, flags: c_int),
-            (Signalfd4 = 289) => handle_unsupported(),
+            (Signalfd4 = 289) => do_signalfd4(fd: i32, mask_ptr: *const sigset_t, mask_size: usize, flags: i32),
             (Eventfd2 = 290) => do_eventfd2(init_val: u32, flags: i32),
             (EpollCreate1 = 291) => do_epoll_create1(flags: c_int),
             (Dup3 = 292) => do_dup3(old_fd: FileDesc, new_fd: FileDesc, flags: u32),
             (Pipe2 = 293) => do_pipe2(fds_u: *mut i32, flags: u32),
-            (InotifyInit1 = 294) => handle_unsupported(),
+            (InotifyInit1 = 294) => do_inotify_init1(flags: i32),
             (Preadv = 295) => do_preadv(fd: FileDesc, iov: *mut iovec_t, count: i32, offset: off_t),
             (Pwritev = 296) => do_pwritev(fd: FileDesc, iov: *const iovec_t, count: i32, offset: off_t),
             (RtTgsigqueueinfo = 297) => handle_unsupported(),
             (PerfEventOpen = 298) => handle_unsupported(),
-            (Recvmmsg = 299) => handle_unsupported(),
+            (Recvmmsg = 299) => do_recvmmsg(fd: c_int, msg_ptr: *mut mmsghdr, vlen: c_uint, flags_c: c_int, timeout: *const timespec_t),
             (FanotifyInit = 300) => handle_unsupported(),
             (FanotifyMark = 301) => handle_unsupported(),
             (Prlimit64 = 302) => do_prlimit(pid: pid_t, resource: u32, new_limit: *const rlimit_t, old_limit: *mut rlimit_t),
@@ -759,6 +767,9 @@ fn do_syscall(user_context: &mut CpuContext) {
         user_context.rax = retval as u64;
     }
 
+    crate::time::timer::report_cpu_time(false);
+    crate::time::timer::handle_expired_timers();
+
     crate::signal::deliver_signal(user_context);
 
     crate::process::handle_force_stop();
@@ -1001,6 +1012,112 @@ fn do_nanosleep(req_u: *const timespec_t, rem_u: *mut timespec_t) -> Result<isiz
     Ok(0)
 }
 
+fn do_setitimer(
+    which: i32,
+    new_value: *const itimerval_t,
+    old_value: *mut itimerval_t,
+) -> Result<isize> {
+    let kind = time::ITimerKind::from_i32(which)?;
+    // A NULL new_value is treated as a zero timer value, which disarms the timer
+    let new_value = if !new_value.is_null() {
+        check_ptr(new_value)?;
+        itimerval_t::from_raw_ptr(new_value)?
+    } else {
+        itimerval_t::default()
+    };
+    let old_value = if !old_value.is_null() {
+        check_mut_ptr(old_value)?;
+        Some(unsafe { &mut *old_value })
+    } else {
+        None
+    };
+
+    let ret = time::do_setitimer(kind, &new_value)?;
+    if let Some(old_value) = old_value {
+        *old_value = ret;
+    }
+    Ok(0)
+}
+
+fn do_getitimer(which: i32, curr_value: *mut itimerval_t) -> Result<isize> {
+    let kind = time::ITimerKind::from_i32(which)?;
+    check_mut_ptr(curr_value)?;
+    let ret = time::do_getitimer(kind)?;
+    unsafe {
+        *curr_value = ret;
+    }
+    Ok(0)
+}
+
+fn do_alarm(seconds: u32) -> Result<isize> {
+    let remaining = time::do_alarm(seconds)?;
+    Ok(remaining as isize)
+}
+
+fn do_timer_create(
+    clockid: clockid_t,
+    sevp: *const sigevent_t,
+    timerid_ptr: *mut timer_t,
+) -> Result<isize> {
+    let clockid = time::ClockId::try_from(clockid)?;
+    let sigevent = if !sevp.is_null() {
+        check_ptr(sevp)?;
+        Some(unsafe { &*sevp })
+    } else {
+        None
+    };
+    check_mut_ptr(timerid_ptr)?;
+
+    let timerid = time::do_timer_create(clockid, sigevent)?;
+    unsafe {
+        *timerid_ptr = timerid;
+    }
+    Ok(0)
+}
+
+fn do_timer_settime(
+    timerid: timer_t,
+    flags: i32,
+    new_value: *const itimerspec_t,
+    old_value: *mut itimerspec_t,
+) -> Result<isize> {
+    let new_value = {
+        check_ptr(new_value)?;
+        itimerspec_t::from_raw_ptr(new_value)?
+    };
+    let old_value = if !old_value.is_null() {
+        check_mut_ptr(old_value)?;
+        Some(unsafe { &mut *old_value })
+    } else {
+        None
+    };
+
+    let ret = time::do_timer_settime(timerid, flags, &new_value)?;
+    if let Some(old_value) = old_value {
+        *old_value = ret;
+    }
+    Ok(0)
+}
+
+fn do_timer_gettime(timerid: timer_t, curr_value: *mut itimerspec_t) -> Result<isize> {
+    check_mut_ptr(curr_value)?;
+    let ret = time::do_timer_gettime(timerid)?;
+    unsafe {
+        *curr_value = ret;
+    }
+    Ok(0)
+}
+
+fn do_timer_getoverrun(timerid: timer_t) -> Result<isize> {
+    let overrun = time::do_timer_getoverrun(timerid)?;
+    Ok(overrun as isize)
+}
+
+fn do_timer_delete(timerid: timer_t) -> Result<isize> {
+    time::do_timer_delete(timerid)?;
+    Ok(0)
+}
+
 fn do_uname(name: *mut utsname_t) -> Result<isize> {
     check_mut_ptr(name)?;
     let name = unsafe { &mut *name };
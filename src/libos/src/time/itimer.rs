@@ -0,0 +1,175 @@
+//! Per-process interval timers, i.e., setitimer(2), getitimer(2) and alarm(2).
+//!
+//! ITIMER_REAL counts down in real time and delivers SIGALRM. ITIMER_VIRTUAL
+//! and ITIMER_PROF count down against the CPU time of a process, which is
+//! accounted by its `CpuClock`. The LibOS can't tell the time spent in the
+//! user space from that in the system calls, so both timers count down against
+//! the total CPU time, which is exact for ITIMER_PROF only.
+
+use super::timer::Timer;
+use super::*;
+use crate::process::{Process, ProcessRef};
+use crate::signal::{do_kill_from_kernel, SigNum, SIGALRM, SIGPROF, SIGVTALRM};
+use std::sync::Weak;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i32)]
+pub enum ITimerKind {
+    Real = 0,
+    Virtual = 1,
+    Prof = 2,
+}
+
+impl ITimerKind {
+    pub fn from_i32(which: i32) -> Result<Self> {
+        let kind = match which {
+            0 => Self::Real,
+            1 => Self::Virtual,
+            2 => Self::Prof,
+            _ => return_errno!(EINVAL, "invalid which for itimer"),
+        };
+        Ok(kind)
+    }
+
+    fn signum(&self) -> SigNum {
+        match self {
+            Self::Real => SIGALRM,
+            Self::Virtual => SIGVTALRM,
+            Self::Prof => SIGPROF,
+        }
+    }
+}
+
+/// The interval timers of a process.
+///
+/// A timer is created the first time it is armed.
+#[derive(Debug, Default)]
+pub struct ITimers {
+    timers: [Option<Arc<Timer>>; 3],
+}
+
+impl ITimers {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn set(
+        &mut self,
+        kind: ITimerKind,
+        process: &ProcessRef,
+        value: Duration,
+        interval: Duration,
+    ) -> Result<(Duration, Duration)> {
+        let timer = &mut self.timers[kind as usize];
+        if timer.is_none() {
+            if value == Duration::ZERO {
+                // Disarming a timer that was never armed
+                return Ok((Duration::ZERO, Duration::ZERO));
+            }
+            *timer = Some(new_timer(kind, process)?);
+        }
+        timer.as_ref().unwrap().set(value, interval, false)
+    }
+
+    fn get(&self, kind: ITimerKind) -> Result<(Duration, Duration)> {
+        match &self.timers[kind as usize] {
+            Some(timer) => timer.get(),
+            None => Ok((Duration::ZERO, Duration::ZERO)),
+        }
+    }
+
+    /// Take over the armed timers of `old`, which belong to the process image
+    /// replaced by execve. According to execve(2), interval timers are preserved.
+    pub fn inherit_from(&mut self, old: &mut ITimers, process: &ProcessRef) {
+        for kind in [ITimerKind::Real, ITimerKind::Virtual, ITimerKind::Prof] {
+            let old_timer = match old.timers[kind as usize].take() {
+                Some(old_timer) => old_timer,
+                None => continue,
+            };
+            let (value, interval) = match old_timer.get() {
+                Ok(old) => old,
+                Err(e) => {
+                    warn!("failed to get the old itimer: {:?}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.set(kind, process, value, interval) {
+                warn!("failed to inherit the old itimer: {:?}", e);
+            }
+        }
+    }
+
+    /// Disarm and delete all timers, e.g., when the process exits.
+    pub fn clear(&mut self) {
+        self.timers = Default::default();
+    }
+}
+
+fn new_timer(kind: ITimerKind, process: &ProcessRef) -> Result<Arc<Timer>> {
+    let weak_process: Weak<Process> = Arc::downgrade(process);
+    let signum = kind.signum();
+    let callback = Box::new(move |_expirations: u64| {
+        // Like standard signals, multiple expirations are merged into one signal
+        if let Some(process) = weak_process.upgrade() {
+            do_kill_from_kernel(&process, signum);
+        }
+    });
+    match kind {
+        ITimerKind::Real => Timer::new(ClockId::CLOCK_MONOTONIC, callback),
+        ITimerKind::Virtual | ITimerKind::Prof => {
+            Ok(Timer::new_on_cpu_clock(process.cpu_clock(), callback))
+        }
+    }
+}
+
+pub fn do_setitimer(kind: ITimerKind, new_value: &itimerval_t) -> Result<itimerval_t> {
+    debug!("setitimer: kind: {:?}, new_value: {:?}", kind, new_value);
+    let current = current!();
+    let process = current.process();
+    let mut itimers = process.itimers().lock().unwrap();
+    let (old_value, old_interval) = itimers.set(
+        kind,
+        process,
+        new_value.it_value.as_duration(),
+        new_value.it_interval.as_duration(),
+    )?;
+    Ok(to_itimerval(old_value, old_interval))
+}
+
+pub fn do_getitimer(kind: ITimerKind) -> Result<itimerval_t> {
+    let current = current!();
+    let itimers = current.process().itimers().lock().unwrap();
+    let (value, interval) = itimers.get(kind)?;
+    Ok(to_itimerval(value, interval))
+}
+
+pub fn do_alarm(seconds: u32) -> Result<u32> {
+    debug!("alarm: seconds: {}", seconds);
+    let current = current!();
+    let process = current.process();
+    let mut itimers = process.itimers().lock().unwrap();
+    let (old_value, _) = itimers.set(
+        ITimerKind::Real,
+        process,
+        Duration::from_secs(seconds as u64),
+        Duration::ZERO,
+    )?;
+
+    // Round to the nearest second, but never return zero if a previous alarm
+    // is still pending
+    let mut remaining_secs = old_value.as_secs();
+    if old_value.subsec_micros() >= 500_000 || (remaining_secs == 0 && old_value > Duration::ZERO) {
+        remaining_secs += 1;
+    }
+    Ok(remaining_secs.min(u32::MAX as u64) as u32)
+}
+
+// An armed timer with less than one microsecond remaining must not be reported as disarmed
+fn to_itimerval(value: Duration, interval: Duration) -> itimerval_t {
+    let value = if value > Duration::ZERO && value < Duration::from_micros(1) {
+        Duration::from_micros(1)
+    } else {
+        value
+    };
+    itimerval_t::from_durations(value, interval)
+}
@@ -11,10 +11,18 @@ use std::time::Duration;
 use std::{fmt, u64};
 use syscall::SyscallNum;
 
+mod itimer;
+mod posix_timer;
 mod profiler;
+pub mod timer;
 pub mod timer_slack;
 pub mod up_time;
 
+pub use itimer::{do_alarm, do_getitimer, do_setitimer, ITimerKind, ITimers};
+pub use posix_timer::{
+    do_timer_create, do_timer_delete, do_timer_getoverrun, do_timer_gettime, do_timer_settime,
+    timer_t, PosixTimers,
+};
 pub use profiler::ThreadProfiler;
 pub use timer_slack::TIMERSLACK;
 pub use vdso_time::ClockId;
@@ -175,6 +183,15 @@ impl timespec_t {
 pub type clockid_t = i32;
 
 pub fn do_clock_gettime(clockid: ClockId) -> Result<timespec_t> {
+    // The host CPU time of the enclave is shared by all the processes, so the
+    // CPU time of a process is accounted by the LibOS, consistently with the
+    // CPU-time timers
+    if clockid == ClockId::CLOCK_PROCESS_CPUTIME_ID {
+        timer::report_cpu_time(true);
+        let cpu_time = current!().process().cpu_clock().now();
+        return Ok(timespec_t::from(cpu_time));
+    }
+
     let duration = if is_enable_vdso() {
         vdso_time::clock_gettime(clockid).unwrap()
     } else {
@@ -336,4 +353,41 @@ impl itimerspec_t {
         self.it_value.validate()?;
         Ok(())
     }
+
+    pub fn from_durations(value: Duration, interval: Duration) -> Self {
+        Self {
+            it_interval: timespec_t::from(interval),
+            it_value: timespec_t::from(value),
+        }
+    }
+}
+
+// For setitimer and getitimer
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct itimerval_t {
+    it_interval: timeval_t,
+    it_value: timeval_t,
+}
+
+impl itimerval_t {
+    pub fn from_raw_ptr(ptr: *const itimerval_t) -> Result<itimerval_t> {
+        let itv = unsafe { *ptr };
+        itv.validate()?;
+        Ok(itv)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.it_interval.validate()?;
+        self.it_value.validate()?;
+        Ok(())
+    }
+
+    pub fn from_durations(value: Duration, interval: Duration) -> Self {
+        Self {
+            it_interval: timeval_t::from(interval),
+            it_value: timeval_t::from(value),
+        }
+    }
 }
@@ -0,0 +1,230 @@
+//! Per-process POSIX interval timers, i.e., timer_create(2) and friends.
+
+use super::timer::Timer;
+use super::*;
+use crate::process::{table, Process, ProcessRef};
+use crate::signal::{
+    sigevent_t, sigval_t, SigNum, TimerOverrun, TimerSignal, SIGALRM, SIGEV_NONE, SIGEV_SIGNAL,
+    SIGEV_THREAD_ID,
+};
+use std::sync::Weak;
+
+#[allow(non_camel_case_types)]
+pub type timer_t = i32;
+
+/// The POSIX timers owned by a process.
+#[derive(Debug, Default)]
+pub struct PosixTimers {
+    timers: HashMap<timer_t, PosixTimer>,
+    next_id: timer_t,
+}
+
+impl PosixTimers {
+    /// The max number of timers that a process can create.
+    const MAX_NUM_TIMERS: usize = 4096;
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn alloc_id(&mut self) -> Result<timer_t> {
+        if self.timers.len() >= Self::MAX_NUM_TIMERS {
+            return_errno!(EAGAIN, "too many timers");
+        }
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.checked_add(1).unwrap_or(0);
+            if !self.timers.contains_key(&id) {
+                return Ok(id);
+            }
+        }
+    }
+
+    fn get(&self, id: timer_t) -> Result<&PosixTimer> {
+        self.timers
+            .get(&id)
+            .ok_or_else(|| errno!(EINVAL, "invalid timer id"))
+    }
+
+    /// Delete all timers, e.g., when the process exits or executes a new program.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+}
+
+#[derive(Debug)]
+struct PosixTimer {
+    timer: Arc<Timer>,
+    overrun: Arc<TimerOverrun>,
+}
+
+/// Where and how to notify the expiration of a timer.
+#[derive(Debug, Clone, Copy)]
+enum TimerNotify {
+    None,
+    Process(SigNum),
+    Thread(SigNum, pid_t),
+}
+
+impl TimerNotify {
+    fn from_sigevent(sigevent: &sigevent_t, process: &ProcessRef) -> Result<Self> {
+        let notify = match sigevent.sigev_notify {
+            SIGEV_NONE => Self::None,
+            SIGEV_SIGNAL => {
+                let signum = sigevent.signum()?;
+                Self::Process(signum)
+            }
+            SIGEV_THREAD_ID => {
+                let signum = sigevent.signum()?;
+                let tid = sigevent.sigev_notify_thread_id as pid_t;
+                let thread = table::get_thread(tid)
+                    .map_err(|_| errno!(EINVAL, "invalid target thread of the timer"))?;
+                if thread.process().pid() != process.pid() {
+                    return_errno!(EINVAL, "the target thread is not in the calling process");
+                }
+                Self::Thread(signum, tid)
+            }
+            // SIGEV_THREAD is implemented by libc with SIGEV_THREAD_ID
+            _ => return_errno!(EINVAL, "unsupported notification method of the timer"),
+        };
+        Ok(notify)
+    }
+}
+
+pub fn do_timer_create(clockid: ClockId, sigevent: Option<&sigevent_t>) -> Result<timer_t> {
+    debug!(
+        "timer_create: clockid: {:?}, sigevent: {:?}",
+        clockid, sigevent
+    );
+
+    let current = current!();
+    let process = current.process();
+    let mut posix_timers = process.posix_timers().lock().unwrap();
+    let id = posix_timers.alloc_id()?;
+
+    // If sevp is NULL, then the timer is notified with SIGALRM and the timer ID
+    // as the signal value
+    let (notify, value) = match sigevent {
+        Some(sigevent) => (
+            TimerNotify::from_sigevent(sigevent, process)?,
+            sigevent.sigev_value,
+        ),
+        None => (TimerNotify::Process(SIGALRM), sigval_t::from(id)),
+    };
+
+    let overrun = Arc::new(TimerOverrun::new());
+    let notifier = TimerNotifier {
+        process: Arc::downgrade(process),
+        notify,
+        id,
+        value,
+        overrun: overrun.clone(),
+    };
+    let callback = Box::new(move |expirations: u64| notifier.notify(expirations));
+    let timer = if clockid == ClockId::CLOCK_PROCESS_CPUTIME_ID {
+        Timer::new_on_cpu_clock(process.cpu_clock(), callback)
+    } else {
+        Timer::new(clockid, callback)?
+    };
+    posix_timers
+        .timers
+        .insert(id, PosixTimer { timer, overrun });
+    Ok(id)
+}
+
+struct TimerNotifier {
+    process: Weak<Process>,
+    notify: TimerNotify,
+    id: timer_t,
+    value: sigval_t,
+    overrun: Arc<TimerOverrun>,
+}
+
+// Safety. The signal value is an opaque value that is never dereferenced by the LibOS.
+unsafe impl Send for TimerNotifier {}
+unsafe impl Sync for TimerNotifier {}
+
+impl TimerNotifier {
+    fn notify(&self, expirations: u64) {
+        let (signum, tid) = match self.notify {
+            TimerNotify::None => return,
+            TimerNotify::Process(signum) => (signum, None),
+            TimerNotify::Thread(signum, tid) => (signum, Some(tid)),
+        };
+        let process = match self.process.upgrade() {
+            Some(process) if process.status().is_alive() => process,
+            _ => return,
+        };
+        if !self.overrun.on_expire(expirations) {
+            return;
+        }
+
+        let signal = Box::new(TimerSignal::new(
+            signum,
+            self.id,
+            self.value,
+            self.overrun.clone(),
+        ));
+        match tid {
+            None => process.sig_queues().write().unwrap().enqueue(signal),
+            Some(tid) => {
+                // The target thread may have exited
+                if let Ok(thread) = table::get_thread(tid) {
+                    if thread.process().pid() == process.pid() {
+                        thread.sig_queues().write().unwrap().enqueue(signal);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn do_timer_settime(id: timer_t, flags: i32, new_value: &itimerspec_t) -> Result<itimerspec_t> {
+    debug!(
+        "timer_settime: id: {}, flags: {:#x}, new_value: {:?}",
+        id, flags, new_value
+    );
+    if flags & !TIMER_ABSTIME != 0 {
+        return_errno!(EINVAL, "invalid flags");
+    }
+    new_value.validate()?;
+
+    let current = current!();
+    let posix_timers = current.process().posix_timers().lock().unwrap();
+    let posix_timer = posix_timers.get(id)?;
+
+    posix_timer.overrun.reset();
+    let (old_value, old_interval) = posix_timer.timer.set(
+        new_value.it_value.as_duration(),
+        new_value.it_interval.as_duration(),
+        flags & TIMER_ABSTIME != 0,
+    )?;
+    Ok(itimerspec_t::from_durations(old_value, old_interval))
+}
+
+pub fn do_timer_gettime(id: timer_t) -> Result<itimerspec_t> {
+    let current = current!();
+    let posix_timers = current.process().posix_timers().lock().unwrap();
+    let posix_timer = posix_timers.get(id)?;
+
+    let (value, interval) = posix_timer.timer.get()?;
+    Ok(itimerspec_t::from_durations(value, interval))
+}
+
+pub fn do_timer_getoverrun(id: timer_t) -> Result<i32> {
+    let current = current!();
+    let posix_timers = current.process().posix_timers().lock().unwrap();
+    let posix_timer = posix_timers.get(id)?;
+    Ok(posix_timer.overrun.last_count())
+}
+
+pub fn do_timer_delete(id: timer_t) -> Result<()> {
+    debug!("timer_delete: id: {}", id);
+    let current = current!();
+    let mut posix_timers = current.process().posix_timers().lock().unwrap();
+    posix_timers
+        .timers
+        .remove(&id)
+        .ok_or_else(|| errno!(EINVAL, "invalid timer id"))?;
+    Ok(())
+}
@@ -0,0 +1,468 @@
+//! In-enclave timers.
+//!
+//! A `Timer` expires against one of the supported clocks and invokes a
+//! callback upon expiration. Unlike timerfd, which is backed by a host
+//! timerfd whose expirations can only be observed by reading the fd, the
+//! expiration of these timers must be observed by the LibOS itself so that
+//! the resulting events (e.g., signals) can be generated inside the enclave.
+//!
+//! Armed timers are kept in a min-heap per clock, ordered by deadline. The
+//! earliest deadline of each clock is mirrored in an atomic variable, so
+//! `handle_expired_timers`, which runs on the return path of every system
+//! call and whenever the interrupt thread broadcasts interrupts, only takes
+//! the lock of a heap when one of its timers is due.
+//!
+//! The timers against the CPU time of a process are kept in the heap of the
+//! `CpuClock` of the process instead, which is checked whenever a thread of
+//! the process reports its CPU time.
+
+use super::*;
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Weak;
+
+pub type TimerCallback = dyn Fn(u64) + Send + Sync;
+
+pub struct Timer {
+    clockid: ClockId,
+    // The clock of a timer against the CPU time of a process
+    cpu_clock: Option<Arc<CpuClock>>,
+    inner: SgxMutex<TimerInner>,
+    callback: Box<TimerCallback>,
+}
+
+#[derive(Debug, Default)]
+struct TimerInner {
+    // The next expiration time measured against the clock of the timer.
+    // `None` means that the timer is disarmed.
+    expire_at: Option<Duration>,
+    interval: Duration,
+    // Bumped every time the timer is set, so that the heap entries of the
+    // previous settings can be recognized as stale.
+    generation: u64,
+}
+
+impl Timer {
+    /// Create a disarmed timer, which calls `callback` with the number of
+    /// expirations every time the timer expires.
+    pub fn new(clockid: ClockId, callback: Box<TimerCallback>) -> Result<Arc<Self>> {
+        if clock_index(clockid).is_none() {
+            return_errno!(EINVAL, "unsupported clockid for timers");
+        }
+
+        let timer = Arc::new(Self {
+            clockid,
+            cpu_clock: None,
+            inner: SgxMutex::new(TimerInner::default()),
+            callback,
+        });
+        Ok(timer)
+    }
+
+    /// Create a disarmed timer against the CPU time of a process, i.e.,
+    /// CLOCK_PROCESS_CPUTIME_ID.
+    pub fn new_on_cpu_clock(cpu_clock: &Arc<CpuClock>, callback: Box<TimerCallback>) -> Arc<Self> {
+        Arc::new(Self {
+            clockid: ClockId::CLOCK_PROCESS_CPUTIME_ID,
+            cpu_clock: Some(cpu_clock.clone()),
+            inner: SgxMutex::new(TimerInner::default()),
+            callback,
+        })
+    }
+
+    pub fn clockid(&self) -> ClockId {
+        self.clockid
+    }
+
+    /// Arm or disarm the timer.
+    ///
+    /// The timer expires after `value` (or at `value` if `is_abs` is true)
+    /// for the first time and then every `interval`. A zero `value` disarms
+    /// the timer. Returns the remaining time and the interval before the
+    /// timer is reset.
+    pub fn set(
+        self: &Arc<Self>,
+        value: Duration,
+        interval: Duration,
+        is_abs: bool,
+    ) -> Result<(Duration, Duration)> {
+        let now = self.now()?;
+        let (old, expire_at, generation) = {
+            let mut inner = self.inner.lock().unwrap();
+            let old = inner.remaining(now);
+
+            inner.expire_at = if value == Duration::ZERO {
+                None
+            } else if is_abs {
+                Some(value)
+            } else {
+                Some(now + value)
+            };
+            inner.interval = interval;
+            inner.generation += 1;
+            (old, inner.expire_at, inner.generation)
+        };
+
+        // The heap entry of the previous setting, if any, becomes stale and is
+        // dropped lazily
+        if let Some(expire_at) = expire_at {
+            enqueue(self, expire_at, generation);
+        }
+        Ok(old)
+    }
+
+    /// Get the remaining time until the next expiration and the interval.
+    pub fn get(&self) -> Result<(Duration, Duration)> {
+        let now = self.now()?;
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.remaining(now))
+    }
+
+    // Update the timer and return the number of expirations since the last
+    // check, together with the next expiration time if the timer is periodic.
+    //
+    // Nothing happens if the heap entry of `generation` is stale.
+    fn check_expirations(&self, now: Duration, generation: u64) -> (u64, Option<Duration>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return (0, None);
+        }
+        let expire_at = match inner.expire_at {
+            Some(expire_at) if expire_at <= now => expire_at,
+            _ => return (0, None),
+        };
+
+        if inner.interval == Duration::ZERO {
+            inner.expire_at = None;
+            return (1, None);
+        }
+
+        let interval_ns = inner.interval.as_nanos();
+        let num_extra = (now - expire_at).as_nanos() / interval_ns;
+        let num_expirations = (num_extra + 1).min(u64::MAX as u128) as u64;
+        let next_expire_at =
+            duration_from_nanos(expire_at.as_nanos() + (num_extra + 1).saturating_mul(interval_ns));
+        inner.expire_at = Some(next_expire_at);
+        (num_expirations, Some(next_expire_at))
+    }
+
+    fn now(&self) -> Result<Duration> {
+        match self.cpu_clock.as_ref() {
+            Some(cpu_clock) => Ok(cpu_clock.now()),
+            None => clock_now(self.clockid),
+        }
+    }
+
+    // The heap of the clock of the timer and the mirror of its earliest deadline
+    fn heap(&self) -> (&SgxMutex<TimerHeap>, &AtomicU64) {
+        match self.cpu_clock.as_ref() {
+            Some(cpu_clock) => (&cpu_clock.heap, &cpu_clock.next_deadline),
+            None => {
+                let idx = clock_index(self.clockid).unwrap();
+                (&TIMER_HEAPS[idx], &NEXT_DEADLINES[idx])
+            }
+        }
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.generation == generation && inner.expire_at.is_some()
+    }
+}
+
+impl TimerInner {
+    fn remaining(&self, now: Duration) -> (Duration, Duration) {
+        let remaining = match self.expire_at {
+            // An expired, but not yet handled timer is about to fire. Report
+            // the smallest non-zero value so that the timer is still considered
+            // armed by the user.
+            Some(expire_at) => expire_at
+                .checked_sub(now)
+                .filter(|remaining| *remaining != Duration::ZERO)
+                .unwrap_or(Duration::from_nanos(1)),
+            None => Duration::ZERO,
+        };
+        (remaining, self.interval)
+    }
+}
+
+impl std::fmt::Debug for Timer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timer")
+            .field("clockid", &self.clockid)
+            .field("inner", &*self.inner.lock().unwrap())
+            .finish()
+    }
+}
+
+/// The clocks that timers can be created against by `Timer::new`.
+const TIMER_CLOCKS: [ClockId; 3] = [
+    ClockId::CLOCK_REALTIME,
+    ClockId::CLOCK_MONOTONIC,
+    ClockId::CLOCK_BOOTTIME,
+];
+
+fn clock_index(clockid: ClockId) -> Option<usize> {
+    TIMER_CLOCKS.iter().position(|id| *id == clockid)
+}
+
+// An armed timer in the heap of its clock. Only the entry whose generation
+// matches that of the timer is valid.
+struct TimerEntry {
+    expire_at: Duration,
+    generation: u64,
+    timer: Weak<Timer>,
+}
+
+impl TimerEntry {
+    fn is_valid(&self) -> bool {
+        self.timer
+            .upgrade()
+            .map_or(false, |timer| timer.is_current(self.generation))
+    }
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.expire_at.cmp(&other.expire_at)
+    }
+}
+
+struct TimerHeap {
+    heap: BinaryHeap<Reverse<TimerEntry>>,
+    // The heap is compacted when its length reaches this value
+    compact_threshold: usize,
+}
+
+impl TimerHeap {
+    const MIN_COMPACT_THRESHOLD: usize = 64;
+
+    fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            compact_threshold: Self::MIN_COMPACT_THRESHOLD,
+        }
+    }
+
+    fn push(&mut self, entry: TimerEntry) {
+        self.heap.push(Reverse(entry));
+
+        // A timer that is re-armed before it expires, e.g., a watchdog alarm,
+        // leaves a stale entry behind. Drop them once they pile up.
+        if self.heap.len() >= self.compact_threshold {
+            let heap = std::mem::take(&mut self.heap);
+            self.heap = heap
+                .into_vec()
+                .into_iter()
+                .filter(|entry| entry.0.is_valid())
+                .collect();
+            self.compact_threshold = (self.heap.len() * 2).max(Self::MIN_COMPACT_THRESHOLD);
+        }
+    }
+
+    fn pop_expired(&mut self, now: Duration) -> Option<TimerEntry> {
+        match self.heap.peek() {
+            Some(Reverse(entry)) if entry.expire_at <= now => self.heap.pop().map(|e| e.0),
+            _ => None,
+        }
+    }
+
+    fn next_deadline(&self) -> u64 {
+        self.heap
+            .peek()
+            .map_or(NO_DEADLINE, |entry| as_nanos_u64(entry.0.expire_at))
+    }
+}
+
+const NO_DEADLINE: u64 = u64::MAX;
+
+lazy_static! {
+    static ref TIMER_HEAPS: [SgxMutex<TimerHeap>; 3] = [
+        SgxMutex::new(TimerHeap::new()),
+        SgxMutex::new(TimerHeap::new()),
+        SgxMutex::new(TimerHeap::new()),
+    ];
+}
+
+// The earliest deadline in each heap in nanoseconds. A stale deadline that is
+// earlier than the real one is harmless: it only costs a look into the heap.
+static NEXT_DEADLINES: [AtomicU64; 3] = [
+    AtomicU64::new(NO_DEADLINE),
+    AtomicU64::new(NO_DEADLINE),
+    AtomicU64::new(NO_DEADLINE),
+];
+
+fn enqueue(timer: &Arc<Timer>, expire_at: Duration, generation: u64) {
+    let (heap, next_deadline) = timer.heap();
+    let mut heap = heap.lock().unwrap();
+    heap.push(TimerEntry {
+        expire_at,
+        generation,
+        timer: Arc::downgrade(timer),
+    });
+    next_deadline.store(heap.next_deadline(), Ordering::Release);
+}
+
+/// Invoke the callbacks of the expired timers.
+///
+/// The cost is a few atomic loads when no timer is due. This function does
+/// not rely on `current!()`, so it can be called by the interrupt thread,
+/// which is not a LibOS thread.
+pub fn handle_expired_timers() {
+    for (idx, clockid) in TIMER_CLOCKS.iter().enumerate() {
+        let next_deadline = NEXT_DEADLINES[idx].load(Ordering::Acquire);
+        if next_deadline == NO_DEADLINE {
+            continue;
+        }
+        let now = match clock_now(*clockid) {
+            Ok(now) => now,
+            Err(_) => continue,
+        };
+        if as_nanos_u64(now) < next_deadline {
+            continue;
+        }
+        fire_expired_timers(&TIMER_HEAPS[idx], &NEXT_DEADLINES[idx], now);
+    }
+}
+
+fn fire_expired_timers(heap: &SgxMutex<TimerHeap>, next_deadline: &AtomicU64, now: Duration) {
+    loop {
+        let entry = {
+            let mut heap = heap.lock().unwrap();
+            let entry = heap.pop_expired(now);
+            next_deadline.store(heap.next_deadline(), Ordering::Release);
+            match entry {
+                Some(entry) => entry,
+                None => break,
+            }
+        };
+        let timer = match entry.timer.upgrade() {
+            Some(timer) => timer,
+            None => continue,
+        };
+
+        let (num_expirations, next_expire_at) = timer.check_expirations(now, entry.generation);
+        if let Some(next_expire_at) = next_expire_at {
+            enqueue(&timer, next_expire_at, entry.generation);
+        }
+        // The callback is invoked without holding any lock of the timers
+        if num_expirations > 0 {
+            (timer.callback)(num_expirations);
+        }
+    }
+}
+
+/// The CPU-time clock of a process, i.e., CLOCK_PROCESS_CPUTIME_ID, together
+/// with the timers armed against it.
+///
+/// Every LibOS thread runs on a host thread of its own, so the CPU time of a
+/// process is approximated by the sum of the CPU time of the host threads of
+/// its threads. A thread reports the CPU time consumed since its last report
+/// when it reads the clock or exits, and also when it returns from a system
+/// call or an interrupt while the process has armed CPU-time timers. Each
+/// report takes an OCall, so the time of the other threads may lag behind
+/// until they report.
+pub struct CpuClock {
+    // The CPU time reported by the threads in nanoseconds
+    nanos: AtomicU64,
+    heap: SgxMutex<TimerHeap>,
+    next_deadline: AtomicU64,
+}
+
+impl CpuClock {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            nanos: AtomicU64::new(0),
+            heap: SgxMutex::new(TimerHeap::new()),
+            next_deadline: AtomicU64::new(NO_DEADLINE),
+        })
+    }
+
+    /// The CPU time reported so far.
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Acquire))
+    }
+
+    /// Add the CPU time consumed by another clock, e.g., the process image
+    /// replaced by execve.
+    pub fn add(&self, cpu_time: Duration) {
+        self.nanos
+            .fetch_add(as_nanos_u64(cpu_time), Ordering::AcqRel);
+    }
+
+    /// Whether any timer is armed against the clock.
+    pub fn has_armed_timers(&self) -> bool {
+        self.next_deadline.load(Ordering::Acquire) != NO_DEADLINE
+    }
+}
+
+impl std::fmt::Debug for CpuClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CpuClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
+
+/// Report the CPU time consumed by the current thread to the clock of its
+/// process, and fire the CPU-time timers that are due.
+///
+/// Unless `force` is true, nothing is reported if the process has no armed
+/// CPU-time timers, which saves an OCall on the return path of system calls.
+pub fn report_cpu_time(force: bool) {
+    let current = current!();
+    let cpu_clock = current.process().cpu_clock();
+    if !force && !cpu_clock.has_armed_timers() {
+        return;
+    }
+
+    let thread_cpu_time = match do_thread_getcpuclock() {
+        Ok(thread_cpu_time) => as_nanos_u64(thread_cpu_time.as_duration()),
+        Err(e) => {
+            warn!("failed to get the CPU time of the thread: {:?}", e);
+            return;
+        }
+    };
+    let reported = current
+        .reported_cpu_time()
+        .swap(thread_cpu_time, Ordering::AcqRel);
+    let delta = thread_cpu_time.saturating_sub(reported);
+    let now = cpu_clock.nanos.fetch_add(delta, Ordering::AcqRel) + delta;
+
+    if now >= cpu_clock.next_deadline.load(Ordering::Acquire) {
+        fire_expired_timers(
+            &cpu_clock.heap,
+            &cpu_clock.next_deadline,
+            Duration::from_nanos(now),
+        );
+    }
+}
+
+fn clock_now(clockid: ClockId) -> Result<Duration> {
+    do_clock_gettime(clockid).map(|ts| ts.as_duration())
+}
+
+fn as_nanos_u64(duration: Duration) -> u64 {
+    duration.as_nanos().min((NO_DEADLINE - 1) as u128) as u64
+}
+
+fn duration_from_nanos(nanos: u128) -> Duration {
+    const NANOS_PER_SEC: u128 = 1_000_000_000;
+    let secs = (nanos / NANOS_PER_SEC).min(u64::MAX as u128) as u64;
+    Duration::new(secs, (nanos % NANOS_PER_SEC) as u32)
+}
@@ -116,23 +116,17 @@ impl UntrustedCircularBuf {
     }
 
     pub fn consume(&mut self, buf: &mut [u8]) -> usize {
-        self.with_consumer_view(|part0, part1| {
-            if buf.len() <= part0.len() {
-                buf.copy_from_slice(&part0[..buf.len()]);
-                return buf.len();
-            }
-
-            buf[..part0.len()].copy_from_slice(part0);
+        self.with_consumer_view(|part0, part1| copy_from_parts(part0, part1, buf))
+    }
 
-            let buf = &mut buf[part0.len()..];
-            if buf.len() <= part1.len() {
-                buf.copy_from_slice(&part1[..buf.len()]);
-                return part0.len() + buf.len();
-            } else {
-                buf[..part1.len()].copy_from_slice(part1);
-                return part0.len() + part1.len();
-            }
-        })
+    /// Copies the data to the buffer without consuming it.
+    pub fn peek(&mut self, buf: &mut [u8]) -> usize {
+        let mut bytes_copied = 0;
+        self.with_consumer_view(|part0, part1| {
+            bytes_copied = copy_from_parts(part0, part1, buf);
+            0
+        });
+        bytes_copied
     }
 
     pub fn consume_without_copy(&mut self, len: usize) -> usize {
@@ -197,6 +191,24 @@ impl UntrustedCircularBuf {
     }
 }
 
+fn copy_from_parts(part0: &[u8], part1: &[u8], buf: &mut [u8]) -> usize {
+    if buf.len() <= part0.len() {
+        buf.copy_from_slice(&part0[..buf.len()]);
+        return buf.len();
+    }
+
+    buf[..part0.len()].copy_from_slice(part0);
+
+    let buf = &mut buf[part0.len()..];
+    if buf.len() <= part1.len() {
+        buf.copy_from_slice(&part1[..buf.len()]);
+        return part0.len() + buf.len();
+    } else {
+        buf[..part1.len()].copy_from_slice(part1);
+        return part0.len() + part1.len();
+    }
+}
+
 impl std::fmt::Debug for UntrustedCircularBuf {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("UntrustedCircularBuf")
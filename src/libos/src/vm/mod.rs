@@ -80,7 +80,7 @@ use self::vm_layout::VMLayout;
 
 pub use self::chunk::{ChunkRef, ChunkType};
 pub use self::process_vm::{
-    MMapFlags, MRemapFlags, MSyncFlags, MadviceFlags, ProcessVM, ProcessVMBuilder,
+    MMapFlags, MRemapFlags, MSyncFlags, MadviceFlags, ProcessVM, ProcessVMBuilder, ProcessVMUsage,
 };
 pub use self::user_space_vm::USER_SPACE_VM_MANAGER;
 pub use self::vm_area::VMArea;
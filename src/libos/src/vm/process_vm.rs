@@ -277,6 +277,24 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
     }
 }
 
+/// The memory usage of a process, in bytes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcessVMUsage {
+    /// The size of all the memory mappings
+    pub total: usize,
+    /// The size of the committed memory
+    pub resident: usize,
+    /// The size of the memory shared with other processes or backed by files
+    pub shared: usize,
+    /// The size of the private and writable memory, excluding the stack
+    pub data: usize,
+    pub stack: usize,
+    /// The size of the executable
+    pub exe: usize,
+    /// The size of the other executable memory, e.g., ld.so and the shared libraries
+    pub lib: usize,
+}
+
 // MemChunks is the structure to track all the chunks which are used by this process.
 type MemChunks = Arc<RwLock<HashSet<ChunkRef>>>;
 
@@ -431,6 +449,54 @@ impl ProcessVM {
         Ok(merged_vmas)
     }
 
+    // Get the memory usage by going through all the VMAs of the process.
+    // This is an expensive operation, which is used by procfs only.
+    pub fn get_mem_usage(&self, pid: pid_t) -> ProcessVMUsage {
+        let mut usage = ProcessVMUsage::default();
+        let mut add_vma = |vma: &VMArea| {
+            let size = vma.size();
+            usage.total += size;
+            usage.resident += vma.committed_size();
+            if vma.is_shared() || vma.backed_file().is_some() {
+                usage.shared += size;
+            }
+            if vma.range() == &self.stack_range {
+                usage.stack += size;
+            } else if vma.perms().can_write() && !vma.is_shared() {
+                usage.data += size;
+            }
+            if vma.perms().can_execute() {
+                let is_exe = self
+                    .elf_ranges
+                    .first()
+                    .map_or(false, |exe_range| exe_range.is_superset_of(vma.range()));
+                if is_exe {
+                    usage.exe += size;
+                } else {
+                    usage.lib += size;
+                }
+            }
+        };
+
+        let mem_chunks = self.mem_chunks.read().unwrap();
+        for chunk in mem_chunks.iter() {
+            match chunk.internal() {
+                ChunkType::SingleVMA(vma) => add_vma(&vma.lock().unwrap()),
+                ChunkType::MultiVMA(internal_manager) => {
+                    let internal = internal_manager.lock().unwrap();
+                    internal
+                        .chunk_manager()
+                        .vmas()
+                        .iter()
+                        .map(|obj| obj.vma())
+                        .filter(|vma| vma.belong_to(pid))
+                        .for_each(|vma| add_vma(vma));
+                }
+            }
+        }
+        usage
+    }
+
     pub fn get_process_range(&self) -> &VMRange {
         USER_SPACE_VM_MANAGER.range()
     }
@@ -208,6 +208,18 @@ impl VMArea {
         }
     }
 
+    // Get the size of the committed pages, i.e., the memory that is resident in EPC
+    pub fn committed_size(&self) -> usize {
+        match &self.pages {
+            None => self.size(),
+            Some(pages) => pages
+                .get_ranges(true)
+                .iter()
+                .map(|range| range.size())
+                .sum(),
+        }
+    }
+
     pub fn init_memory(mut self, options: &VMMapOptions) -> Result<Self> {
         let mut vm_area = self;
         let page_policy = options.page_policy();
@@ -0,0 +1,348 @@
+#define _GNU_SOURCE
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <spawn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include "test_fs.h"
+
+// ============================================================================
+// Helper function
+// ============================================================================
+
+#define JAIL_DIR            "/chroot_jail"
+#define JAIL_FILE           "/jail_file"
+#define NEW_ROOT_DIR        "/pivot_new_root"
+#define NEW_ROOT_FILE       "/new_root_file"
+#define PUT_OLD_DIR         "old_root"
+#define FILE_CONTENT        "Hello World\n"
+
+static const char prog_name[] = "/bin/chroot";
+
+static int create_file(const char *file_path) {
+    int fd = open(file_path, O_WRONLY | O_CREAT | O_TRUNC, 00666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create %s", file_path);
+    }
+    if (write(fd, FILE_CONTENT, strlen(FILE_CONTENT)) != strlen(FILE_CONTENT)) {
+        close(fd);
+        THROW_ERROR("failed to write %s", file_path);
+    }
+    close(fd);
+    return 0;
+}
+
+static int check_readlink(const char *path, const char *expected) {
+    char buf[PATH_MAX] = { 0 };
+
+    if (readlink(path, buf, sizeof(buf) - 1) < 0) {
+        THROW_ERROR("failed to readlink %s", path);
+    }
+    if (strcmp(buf, expected) != 0) {
+        THROW_ERROR("%s is %s, but %s is expected", path, buf, expected);
+    }
+    return 0;
+}
+
+// The programs and /proc are made visible in the jail by bind mounts
+static int setup_jail() {
+    const char *dirs[] = { "/bin", "/lib", "/proc" };
+    char path[PATH_MAX];
+
+    if (mkdir(JAIL_DIR, 00775) < 0 && errno != EEXIST) {
+        THROW_ERROR("failed to mkdir %s", JAIL_DIR);
+    }
+    for (int i = 0; i < ARRAY_SIZE(dirs); i++) {
+        snprintf(path, sizeof(path), "%s%s", JAIL_DIR, dirs[i]);
+        if (mkdir(path, 00775) < 0 && errno != EEXIST) {
+            THROW_ERROR("failed to mkdir %s", path);
+        }
+        if (mount(dirs[i], path, NULL, MS_BIND | MS_REC, NULL) < 0) {
+            THROW_ERROR("failed to bind %s to %s", dirs[i], path);
+        }
+    }
+    if (create_file(JAIL_DIR JAIL_FILE) < 0) {
+        THROW_ERROR("failed to create the file in the jail");
+    }
+    return 0;
+}
+
+static int run_child(char *mode, int *child_pid) {
+    char *child_argv[] = { "chroot", mode, NULL };
+    int pid;
+
+    if (posix_spawn(&pid, prog_name, NULL, NULL, child_argv, NULL) < 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    if (child_pid != NULL) {
+        *child_pid = pid;
+        return 0;
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) < 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        THROW_ERROR("the test in the child process failed");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases in the child processes
+// ============================================================================
+
+static int read_file(const char *path, char *buf, size_t size) {
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open %s", path);
+    }
+    int len = read(fd, buf, size - 1);
+    close(fd);
+    if (len < 0) {
+        THROW_ERROR("failed to read %s", path);
+    }
+    buf[len] = '\0';
+    return 0;
+}
+
+// Only the mounts in the jail are shown, at the paths in the jail
+static int check_mounts_in_jail() {
+    char buf[4096];
+    int mount_id, parent_id, major, minor;
+    char root[PATH_MAX] = { 0 };
+    char target[PATH_MAX] = { 0 };
+
+    if (read_file("/proc/self/mounts", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read the mounts");
+    }
+    if (strstr(buf, JAIL_DIR) != NULL || strstr(buf, " /host ") != NULL) {
+        THROW_ERROR("the mounts out of the jail are visible:\n%s", buf);
+    }
+    if (strstr(buf, " / unionfs ") == NULL || strstr(buf, " /proc ") == NULL) {
+        THROW_ERROR("the mounts in the jail are not visible:\n%s", buf);
+    }
+
+    if (read_file("/proc/self/mountinfo", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read the mountinfo");
+    }
+    // The first line is the mount where the jail is in
+    int ret = sscanf(buf, "%d %d %d:%d %s %s", &mount_id, &parent_id, &major, &minor,
+                     root, target);
+    if (ret != 6 || strcmp(root, JAIL_DIR) != 0 || strcmp(target, "/") != 0) {
+        THROW_ERROR("the root of the jail is not shown as the root mount:\n%s", buf);
+    }
+    return 0;
+}
+
+static int child_check_in_jail() {
+    struct stat root_stat, parent_stat;
+    char cwd[PATH_MAX];
+
+    if (fs_check_file_content(JAIL_FILE, FILE_CONTENT) < 0) {
+        THROW_ERROR("failed to read the file in the jail");
+    }
+    if (access(JAIL_DIR, F_OK) == 0 || errno != ENOENT) {
+        THROW_ERROR("the files out of the jail are visible");
+    }
+    // ".." of the root is the root itself
+    if (stat("/", &root_stat) < 0 || stat("/../..", &parent_stat) < 0) {
+        THROW_ERROR("failed to stat the root");
+    }
+    if (root_stat.st_ino != parent_stat.st_ino) {
+        THROW_ERROR("\"..\" goes beyond the root");
+    }
+    if (fs_check_file_content("../.." JAIL_FILE, FILE_CONTENT) < 0) {
+        THROW_ERROR("failed to read the file by the relative path");
+    }
+    if (getcwd(cwd, sizeof(cwd)) == NULL || strcmp(cwd, "/") != 0) {
+        THROW_ERROR("the cwd is not in the jail");
+    }
+    if (check_readlink("/proc/self/root", "/") < 0) {
+        THROW_ERROR("failed to check the root in the jail");
+    }
+    if (check_mounts_in_jail() < 0) {
+        THROW_ERROR("failed to check the mounts in the jail");
+    }
+    return 0;
+}
+
+static int child_chroot_then_check() {
+    if (chroot(JAIL_DIR) < 0) {
+        THROW_ERROR("failed to chroot");
+    }
+    if (child_check_in_jail() < 0) {
+        THROW_ERROR("failed to check in the jail");
+    }
+    // The root is inherited by the child process
+    if (run_child("check_in_jail", NULL) < 0) {
+        THROW_ERROR("failed to check in the jail of the child process");
+    }
+    return 0;
+}
+
+static int child_chroot_then_wait() {
+    char buf;
+
+    if (chroot(JAIL_DIR) < 0) {
+        THROW_ERROR("failed to chroot");
+    }
+    // Wait until the parent closes the stdin
+    while (read(STDIN_FILENO, &buf, 1) > 0) {
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for chroot and pivot_root
+// ============================================================================
+
+static int test_chroot() {
+    struct stat stat_buf;
+
+    if (run_child("chroot_then_check", NULL) < 0) {
+        THROW_ERROR("failed to check in the child process");
+    }
+    // The root of the parent is unchanged
+    if (stat(JAIL_DIR JAIL_FILE, &stat_buf) < 0) {
+        THROW_ERROR("the root of the parent is changed");
+    }
+    return 0;
+}
+
+static int test_chroot_with_invalid_path() {
+    if (chroot(JAIL_DIR JAIL_FILE) == 0 || errno != ENOTDIR) {
+        THROW_ERROR("chroot to a file should fail");
+    }
+    if (chroot("/non_existent_dir") == 0 || errno != ENOENT) {
+        THROW_ERROR("chroot to a non-existent dir should fail");
+    }
+    return 0;
+}
+
+static int test_proc_pid_root() {
+    char path[PATH_MAX];
+    int pipe_fds[2];
+    int saved_stdin;
+    int child_pid;
+    int status;
+    int ret = 0;
+
+    // The read end of the pipe is the stdin of the child, while the write end is
+    // not inherited by the child
+    if (pipe2(pipe_fds, O_CLOEXEC) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    saved_stdin = fcntl(STDIN_FILENO, F_DUPFD_CLOEXEC, 0);
+    dup2(pipe_fds[0], STDIN_FILENO);
+    close(pipe_fds[0]);
+    if (run_child("chroot_then_wait", &child_pid) < 0) {
+        ret = -1;
+    }
+    dup2(saved_stdin, STDIN_FILENO);
+    close(saved_stdin);
+    if (ret < 0) {
+        close(pipe_fds[1]);
+        THROW_ERROR("failed to run the child process");
+    }
+
+    // Wait until the child has changed the root
+    snprintf(path, sizeof(path), "/proc/%d/root", child_pid);
+    for (int i = 0; i < 100; i++) {
+        char buf[PATH_MAX] = { 0 };
+        if (readlink(path, buf, sizeof(buf) - 1) > 0 && strcmp(buf, "/") != 0) {
+            break;
+        }
+        usleep(10 * 1000);
+    }
+    ret = check_readlink(path, JAIL_DIR);
+    close(pipe_fds[1]);
+    if (waitpid(child_pid, &status, 0) < 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (ret < 0) {
+        THROW_ERROR("failed to check the root of the child process");
+    }
+    return 0;
+}
+
+static int test_pivot_root() {
+    char cwd[PATH_MAX];
+    struct stat stat_buf;
+
+    if (mkdir(NEW_ROOT_DIR, 00775) < 0) {
+        THROW_ERROR("failed to mkdir the new root");
+    }
+    if (mount("ramfs", NEW_ROOT_DIR, "ramfs", 0, NULL) < 0) {
+        THROW_ERROR("failed to mount ramfs");
+    }
+    if (mkdir(NEW_ROOT_DIR "/" PUT_OLD_DIR, 00775) < 0) {
+        THROW_ERROR("failed to mkdir put_old");
+    }
+    if (create_file(NEW_ROOT_DIR NEW_ROOT_FILE) < 0) {
+        THROW_ERROR("failed to create the file in the new root");
+    }
+
+    // put_old must be under new_root, and new_root must be a mount point
+    if (syscall(SYS_pivot_root, NEW_ROOT_DIR, "/bin") == 0 || errno != EINVAL) {
+        THROW_ERROR("pivot_root should fail if put_old is not under new_root");
+    }
+    if (syscall(SYS_pivot_root, JAIL_DIR, JAIL_DIR "/bin") == 0 || errno != EINVAL) {
+        THROW_ERROR("pivot_root should fail if new_root is not a mount point");
+    }
+
+    if (chdir(NEW_ROOT_DIR) < 0) {
+        THROW_ERROR("failed to chdir to the new root");
+    }
+    if (syscall(SYS_pivot_root, ".", PUT_OLD_DIR) < 0) {
+        THROW_ERROR("failed to pivot_root");
+    }
+    // The cwd is still the new root, which is "/" now
+    if (getcwd(cwd, sizeof(cwd)) == NULL || strcmp(cwd, "/") != 0) {
+        THROW_ERROR("the cwd is not the new root");
+    }
+    if (fs_check_file_content(NEW_ROOT_FILE, FILE_CONTENT) < 0) {
+        THROW_ERROR("failed to read the file in the new root");
+    }
+    if (stat("/" PUT_OLD_DIR JAIL_DIR JAIL_FILE, &stat_buf) < 0) {
+        THROW_ERROR("the old root is not at put_old");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_chroot),
+    TEST_CASE(test_chroot_with_invalid_path),
+    TEST_CASE(test_proc_pid_root),
+    // The root is changed for all the processes, so it must be the last one
+    TEST_CASE(test_pivot_root),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        if (strcmp(argv[1], "check_in_jail") == 0) {
+            return child_check_in_jail() < 0;
+        } else if (strcmp(argv[1], "chroot_then_check") == 0) {
+            return child_chroot_then_check() < 0;
+        } else if (strcmp(argv[1], "chroot_then_wait") == 0) {
+            return child_chroot_then_wait() < 0;
+        }
+        return 1;
+    }
+
+    if (setup_jail() < 0) {
+        THROW_ERROR("failed to setup the jail");
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
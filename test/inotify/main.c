@@ -0,0 +1,316 @@
+#define _GNU_SOURCE
+#include <sys/inotify.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test_fs.h"
+
+#define TEST_DIR "/root/test_inotify_dir"
+#define EVENT_BUF_SIZE 4096
+
+// ============================================================================
+// Helper function
+// ============================================================================
+
+struct expected_event {
+    uint32_t mask;
+    const char *name;
+};
+
+// Read the events and check them against the expected ones in order
+static int check_events(int inotify_fd, int wd, struct expected_event *expected,
+                        int num_expected, uint32_t *cookie) {
+    char buf[EVENT_BUF_SIZE] __attribute__((aligned(__alignof__(struct inotify_event))));
+    int num_events = 0;
+
+    while (num_events < num_expected) {
+        ssize_t len = read(inotify_fd, buf, sizeof(buf));
+        if (len <= 0) {
+            THROW_ERROR("failed to read the events");
+        }
+        for (char *ptr = buf; ptr < buf + len; num_events++) {
+            struct inotify_event *event = (struct inotify_event *)ptr;
+            ptr += sizeof(struct inotify_event) + event->len;
+            if (num_events >= num_expected) {
+                THROW_ERROR("too many events");
+            }
+
+            struct expected_event *expected_event = &expected[num_events];
+            if (event->wd != wd || event->mask != expected_event->mask) {
+                printf("event %d: mask %#x, expected mask %#x\n", num_events, event->mask,
+                       expected_event->mask);
+                THROW_ERROR("unexpected event");
+            }
+            if (expected_event->name == NULL) {
+                if (event->len != 0) {
+                    THROW_ERROR("the event should have no name");
+                }
+            } else if (event->len == 0 || strcmp(event->name, expected_event->name) != 0) {
+                THROW_ERROR("the name of the event is wrong");
+            }
+            if (cookie != NULL && event->cookie != 0) {
+                if (*cookie != 0 && *cookie != event->cookie) {
+                    THROW_ERROR("the cookies of the events do not match");
+                }
+                *cookie = event->cookie;
+            }
+        }
+    }
+
+    // No more events
+    if (read(inotify_fd, buf, sizeof(buf)) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("there should be no more events");
+    }
+    return 0;
+}
+
+static int write_file(const char *path, const char *msg) {
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 00666);
+    if (fd < 0) {
+        THROW_ERROR("failed to open the file");
+    }
+    if (write(fd, msg, strlen(msg)) != strlen(msg)) {
+        close(fd);
+        THROW_ERROR("failed to write the file");
+    }
+    close(fd);
+    return 0;
+}
+
+// ============================================================================
+// Test cases for inotify
+// ============================================================================
+
+static int __test_file_events_in_dir(int inotify_fd) {
+    const char *file_path = TEST_DIR "/file";
+    int wd = inotify_add_watch(inotify_fd, TEST_DIR, IN_ALL_EVENTS);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+
+    if (write_file(file_path, "hello") < 0 || unlink(file_path) < 0) {
+        THROW_ERROR("failed to create and remove the file");
+    }
+
+    struct expected_event expected[] = {
+        { IN_CREATE, "file" },
+        { IN_OPEN, "file" },
+        { IN_MODIFY, "file" },
+        { IN_CLOSE_WRITE, "file" },
+        { IN_DELETE, "file" },
+    };
+    return check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), NULL);
+}
+
+static int __test_dir_events_in_dir(int inotify_fd) {
+    const char *dir_path = TEST_DIR "/dir";
+    const char *new_dir_path = TEST_DIR "/new_dir";
+    int wd = inotify_add_watch(inotify_fd, TEST_DIR,
+                               IN_CREATE | IN_DELETE | IN_MOVED_FROM | IN_MOVED_TO);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+
+    if (mkdir(dir_path, 00775) < 0 || rename(dir_path, new_dir_path) < 0 ||
+            rmdir(new_dir_path) < 0) {
+        THROW_ERROR("failed to create, rename and remove the dir");
+    }
+
+    struct expected_event expected[] = {
+        { IN_CREATE | IN_ISDIR, "dir" },
+        { IN_MOVED_FROM | IN_ISDIR, "dir" },
+        { IN_MOVED_TO | IN_ISDIR, "new_dir" },
+        { IN_DELETE | IN_ISDIR, "new_dir" },
+    };
+    uint32_t cookie = 0;
+    if (check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), &cookie) < 0) {
+        return -1;
+    }
+    if (cookie == 0) {
+        THROW_ERROR("the move events should have a cookie");
+    }
+    return 0;
+}
+
+static int __test_file_self_events(int inotify_fd) {
+    const char *file_path = TEST_DIR "/file";
+    const char *new_file_path = TEST_DIR "/new_file";
+    if (write_file(file_path, "hello") < 0) {
+        return -1;
+    }
+    int wd = inotify_add_watch(inotify_fd, file_path, IN_MODIFY | IN_MOVE_SELF | IN_DELETE_SELF);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+
+    if (write_file(file_path, "world") < 0 || rename(file_path, new_file_path) < 0 ||
+            unlink(new_file_path) < 0) {
+        THROW_ERROR("failed to modify, rename and remove the file");
+    }
+
+    // The watch is removed after the file is deleted
+    struct expected_event expected[] = {
+        { IN_MODIFY, NULL },
+        { IN_MOVE_SELF, NULL },
+        { IN_DELETE_SELF, NULL },
+        { IN_IGNORED, NULL },
+    };
+    if (check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), NULL) < 0) {
+        return -1;
+    }
+    if (inotify_rm_watch(inotify_fd, wd) >= 0 || errno != EINVAL) {
+        THROW_ERROR("the watch should have been removed");
+    }
+    return 0;
+}
+
+static int __test_oneshot(int inotify_fd) {
+    const char *file_path = TEST_DIR "/file";
+    int wd = inotify_add_watch(inotify_fd, TEST_DIR, IN_CREATE | IN_ONESHOT);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+
+    if (write_file(file_path, "hello") < 0 || unlink(file_path) < 0 ||
+            write_file(file_path, "hello") < 0 || unlink(file_path) < 0) {
+        THROW_ERROR("failed to create and remove the file");
+    }
+
+    struct expected_event expected[] = {
+        { IN_CREATE, "file" },
+        { IN_IGNORED, NULL },
+    };
+    return check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), NULL);
+}
+
+static int __test_rm_watch(int inotify_fd) {
+    int wd = inotify_add_watch(inotify_fd, TEST_DIR, IN_CREATE);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+    // The same watch descriptor is returned for the same inode
+    if (inotify_add_watch(inotify_fd, TEST_DIR, IN_DELETE | IN_MASK_ADD) != wd) {
+        THROW_ERROR("failed to modify the watch");
+    }
+    if (inotify_add_watch(inotify_fd, TEST_DIR, IN_DELETE | IN_MASK_CREATE) >= 0 ||
+            errno != EEXIST) {
+        THROW_ERROR("IN_MASK_CREATE on an existing watch should return EEXIST");
+    }
+
+    if (inotify_rm_watch(inotify_fd, wd) < 0) {
+        THROW_ERROR("failed to remove the watch");
+    }
+    if (inotify_rm_watch(inotify_fd, wd) >= 0 || errno != EINVAL) {
+        THROW_ERROR("removing a nonexistent watch should return EINVAL");
+    }
+
+    // No more events after the watch is removed
+    if (mkdir(TEST_DIR "/dir", 00775) < 0 || rmdir(TEST_DIR "/dir") < 0) {
+        THROW_ERROR("failed to create and remove the dir");
+    }
+    struct expected_event expected[] = {
+        { IN_IGNORED, NULL },
+    };
+    return check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), NULL);
+}
+
+static int __test_poll_and_read(int inotify_fd) {
+    int wd = inotify_add_watch(inotify_fd, TEST_DIR, IN_CREATE);
+    if (wd < 0) {
+        THROW_ERROR("failed to add the watch");
+    }
+
+    struct pollfd pfd = { .fd = inotify_fd, .events = POLLIN };
+    if (poll(&pfd, 1, 0) != 0) {
+        THROW_ERROR("there should be no event to read");
+    }
+
+    if (mkdir(TEST_DIR "/dir", 00775) < 0 || rmdir(TEST_DIR "/dir") < 0) {
+        THROW_ERROR("failed to create and remove the dir");
+    }
+    if (poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLIN)) {
+        THROW_ERROR("the event should be polled");
+    }
+
+    // The name "dir" is padded to 16 bytes
+    int bytes_to_read = 0;
+    if (ioctl(inotify_fd, FIONREAD, &bytes_to_read) < 0 ||
+            bytes_to_read != sizeof(struct inotify_event) + 16) {
+        THROW_ERROR("failed to get the length of the events");
+    }
+    char small_buf[sizeof(struct inotify_event)];
+    if (read(inotify_fd, small_buf, sizeof(small_buf)) >= 0 || errno != EINVAL) {
+        THROW_ERROR("reading with a small buffer should return EINVAL");
+    }
+
+    struct expected_event expected[] = {
+        { IN_CREATE | IN_ISDIR, "dir" },
+    };
+    return check_events(inotify_fd, wd, expected, ARRAY_SIZE(expected), NULL);
+}
+
+typedef int(*test_inotify_func_t)(int);
+
+static int test_inotify_framework(test_inotify_func_t fn) {
+    if (mkdir(TEST_DIR, 00775) < 0) {
+        THROW_ERROR("failed to create the test dir");
+    }
+    int inotify_fd = inotify_init1(IN_NONBLOCK | IN_CLOEXEC);
+    if (inotify_fd < 0) {
+        THROW_ERROR("failed to create the inotify instance");
+    }
+
+    int ret = fn(inotify_fd);
+
+    close(inotify_fd);
+    if (rmdir(TEST_DIR) < 0) {
+        THROW_ERROR("failed to remove the test dir");
+    }
+    return ret;
+}
+
+static int test_file_events_in_dir() {
+    return test_inotify_framework(__test_file_events_in_dir);
+}
+
+static int test_dir_events_in_dir() {
+    return test_inotify_framework(__test_dir_events_in_dir);
+}
+
+static int test_file_self_events() {
+    return test_inotify_framework(__test_file_self_events);
+}
+
+static int test_oneshot() {
+    return test_inotify_framework(__test_oneshot);
+}
+
+static int test_rm_watch() {
+    return test_inotify_framework(__test_rm_watch);
+}
+
+static int test_poll_and_read() {
+    return test_inotify_framework(__test_poll_and_read);
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_file_events_in_dir),
+    TEST_CASE(test_dir_events_in_dir),
+    TEST_CASE(test_file_self_events),
+    TEST_CASE(test_oneshot),
+    TEST_CASE(test_rm_watch),
+    TEST_CASE(test_poll_and_read),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,252 @@
+#include <sys/time.h>
+#include <sys/wait.h>
+#include <signal.h>
+#include <spawn.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int wait_signal(int signum, int timeout_sec) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    struct timespec timeout = { .tv_sec = timeout_sec, .tv_nsec = 0 };
+    return sigtimedwait(&set, NULL, &timeout);
+}
+
+static void block_signal(int signum) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+}
+
+static void unblock_signal(int signum) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    sigprocmask(SIG_UNBLOCK, &set, NULL);
+}
+
+static int is_itimer_armed(int which) {
+    struct itimerval curr;
+    if (getitimer(which, &curr) < 0) {
+        return -1;
+    }
+    return curr.it_value.tv_sec != 0 || curr.it_value.tv_usec != 0;
+}
+
+static int spawn_and_wait(char *const argv[]) {
+    int child_pid, status;
+    if (posix_spawn(&child_pid, "/bin/itimer", NULL, NULL, argv, NULL) != 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    if (waitpid(child_pid, &status, 0) < 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        THROW_ERROR("the child process failed");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Child processes
+// ============================================================================
+
+// The interval timers of a newly spawned process are all disarmed
+static int child_check_cleared(void) {
+    if (is_itimer_armed(ITIMER_REAL) != 0 || alarm(0) != 0) {
+        return -1;
+    }
+    return 0;
+}
+
+// The interval timers are preserved across execve
+static int child_exec(void) {
+    alarm(100);
+    char *const argv[] = {"itimer", "check-inherited", NULL};
+    execve("/bin/itimer", argv, NULL);
+    return -1;
+}
+
+static int child_check_inherited(void) {
+    unsigned int remaining = alarm(0);
+    if (remaining == 0 || remaining > 100) {
+        return -1;
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for interval timers
+// ============================================================================
+
+int test_alarm() {
+    block_signal(SIGALRM);
+    if (alarm(1) != 0) {
+        THROW_ERROR("no alarm should be pending");
+    }
+    if (wait_signal(SIGALRM, 3) != SIGALRM) {
+        THROW_ERROR("failed to wait for SIGALRM");
+    }
+    unblock_signal(SIGALRM);
+    return 0;
+}
+
+int test_alarm_remaining() {
+    alarm(10);
+    unsigned int remaining = alarm(5);
+    if (remaining < 9 || remaining > 10) {
+        THROW_ERROR("unexpected remaining seconds of the alarm: %u", remaining);
+    }
+    remaining = alarm(0);
+    if (remaining < 4 || remaining > 5) {
+        THROW_ERROR("unexpected remaining seconds of the alarm: %u", remaining);
+    }
+    if (is_itimer_armed(ITIMER_REAL) != 0) {
+        THROW_ERROR("the cancelled alarm is still armed");
+    }
+    return 0;
+}
+
+int test_itimer_real_periodic() {
+    struct itimerval new_value = {
+        .it_interval = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+        .it_value = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+    };
+    struct itimerval curr;
+
+    block_signal(SIGALRM);
+    if (setitimer(ITIMER_REAL, &new_value, NULL) < 0) {
+        THROW_ERROR("setitimer failed");
+    }
+    for (int i = 0; i < 3; i++) {
+        if (wait_signal(SIGALRM, 3) != SIGALRM) {
+            THROW_ERROR("failed to wait for SIGALRM");
+        }
+    }
+
+    if (getitimer(ITIMER_REAL, &curr) < 0) {
+        THROW_ERROR("getitimer failed");
+    }
+    if (curr.it_interval.tv_sec != 0 || curr.it_interval.tv_usec != 50 * 1000 ||
+            curr.it_value.tv_sec != 0 || curr.it_value.tv_usec > 50 * 1000) {
+        THROW_ERROR("unexpected value from getitimer");
+    }
+
+    struct itimerval zero = { 0 };
+    struct itimerval old;
+    if (setitimer(ITIMER_REAL, &zero, &old) < 0) {
+        THROW_ERROR("setitimer failed");
+    }
+    if (old.it_interval.tv_usec != 50 * 1000) {
+        THROW_ERROR("unexpected old value from setitimer");
+    }
+    // Discard the signal that may have been generated before the timer is disarmed
+    wait_signal(SIGALRM, 0);
+    unblock_signal(SIGALRM);
+    return 0;
+}
+
+// Burn the CPU until the CPU-time timer expires
+static int test_itimer_cpu_time(int which, int signum) {
+    struct itimerval new_value = {
+        .it_interval = { .tv_sec = 0, .tv_usec = 0 },
+        .it_value = { .tv_sec = 0, .tv_usec = 20 * 1000 },
+    };
+    block_signal(signum);
+    if (setitimer(which, &new_value, NULL) < 0) {
+        THROW_ERROR("setitimer failed");
+    }
+    if (is_itimer_armed(which) != 1) {
+        THROW_ERROR("the CPU-time timer is not armed");
+    }
+
+    time_t start = time(NULL);
+    sigset_t pending;
+    do {
+        sigpending(&pending);
+    } while (!sigismember(&pending, signum) && time(NULL) - start < 3);
+    if (!sigismember(&pending, signum)) {
+        THROW_ERROR("the CPU-time timer does not expire");
+    }
+    wait_signal(signum, 0);
+    unblock_signal(signum);
+
+    if (is_itimer_armed(which) != 0) {
+        THROW_ERROR("the expired one-shot timer is still armed");
+    }
+    return 0;
+}
+
+int test_itimer_prof() {
+    return test_itimer_cpu_time(ITIMER_PROF, SIGPROF);
+}
+
+int test_itimer_virtual() {
+    return test_itimer_cpu_time(ITIMER_VIRTUAL, SIGVTALRM);
+}
+
+int test_itimer_cleared_on_spawn() {
+    alarm(100);
+    char *const argv[] = {"itimer", "check-cleared", NULL};
+    int ret = spawn_and_wait(argv);
+    alarm(0);
+    return ret;
+}
+
+int test_itimer_inherited_on_exec() {
+    char *const argv[] = {"itimer", "exec", NULL};
+    return spawn_and_wait(argv);
+}
+
+int test_invalid_arguments() {
+    struct itimerval curr;
+    if (getitimer(3, &curr) == 0 || errno != EINVAL) {
+        THROW_ERROR("getitimer with an invalid timer should fail");
+    }
+
+    struct itimerval new_value = {
+        .it_interval = { .tv_sec = 0, .tv_usec = 0 },
+        .it_value = { .tv_sec = 0, .tv_usec = 1000 * 1000 },
+    };
+    if (setitimer(ITIMER_REAL, &new_value, NULL) == 0 || errno != EINVAL) {
+        THROW_ERROR("setitimer with an invalid value should fail");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_alarm),
+    TEST_CASE(test_alarm_remaining),
+    TEST_CASE(test_itimer_real_periodic),
+    TEST_CASE(test_itimer_prof),
+    TEST_CASE(test_itimer_virtual),
+    TEST_CASE(test_itimer_cleared_on_spawn),
+    TEST_CASE(test_itimer_inherited_on_exec),
+    TEST_CASE(test_invalid_arguments),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        if (strcmp(argv[1], "check-cleared") == 0) {
+            return child_check_cleared();
+        } else if (strcmp(argv[1], "exec") == 0) {
+            return child_exec();
+        } else if (strcmp(argv[1], "check-inherited") == 0) {
+            return child_check_inherited();
+        }
+        return -1;
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
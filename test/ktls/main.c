@@ -0,0 +1,333 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+#define TCP_PORT                8813
+#define MSG_DATA                "Hello, kTLS!"
+
+// The ABI of kTLS, i.e., <linux/tls.h>
+#ifndef TCP_ULP
+#define TCP_ULP                 31
+#endif
+#ifndef SOL_TLS
+#define SOL_TLS                 282
+#endif
+#define TLS_TX                  1
+#define TLS_RX                  2
+#define TLS_SET_RECORD_TYPE     1
+#define TLS_GET_RECORD_TYPE     2
+#define TLS_1_2_VERSION         0x0303
+#define TLS_CIPHER_AES_GCM_128  51
+#define TLS_CIPHER_AES_GCM_256  52
+
+#define TLS_RECORD_TYPE_ALERT   21
+#define TLS_RECORD_TYPE_DATA    23
+// The header, explicit nonce and tag of a TLS 1.2 record of AES-GCM
+#define TLS_1_2_OVERHEAD        (5 + 8 + 16)
+
+struct tls12_crypto_info_aes_gcm_128 {
+    uint16_t version;
+    uint16_t cipher_type;
+    unsigned char iv[8];
+    unsigned char key[16];
+    unsigned char salt[4];
+    unsigned char rec_seq[8];
+};
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+// Connect a pair of TCP sockets. The listening socket is bound to the wildcard address
+// so that the connection is not short-circuited in the enclave.
+static int connect_pair(int *client_fd, int *server_fd) {
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(TCP_PORT);
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    int reuse = 1;
+    setsockopt(listen_fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse));
+    if (bind(listen_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+            listen(listen_fd, 1) < 0) {
+        close(listen_fd);
+        return -1;
+    }
+
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    *client_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (*client_fd < 0) {
+        close(listen_fd);
+        return -1;
+    }
+    if (connect(*client_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(*client_fd);
+        close(listen_fd);
+        return -1;
+    }
+
+    *server_fd = accept(listen_fd, NULL, NULL);
+    close(listen_fd);
+    if (*server_fd < 0) {
+        close(*client_fd);
+        return -1;
+    }
+    return 0;
+}
+
+static void fill_crypto_info(struct tls12_crypto_info_aes_gcm_128 *info) {
+    memset(info, 0, sizeof(*info));
+    info->version = TLS_1_2_VERSION;
+    info->cipher_type = TLS_CIPHER_AES_GCM_128;
+    memset(info->iv, 0x11, sizeof(info->iv));
+    memset(info->key, 0x22, sizeof(info->key));
+    memset(info->salt, 0x33, sizeof(info->salt));
+}
+
+static int set_tls_ulp(int fd) {
+    return setsockopt(fd, SOL_TCP, TCP_ULP, "tls", sizeof("tls"));
+}
+
+static int set_tls_keys(int fd, int optname) {
+    struct tls12_crypto_info_aes_gcm_128 info;
+    fill_crypto_info(&info);
+    return setsockopt(fd, SOL_TLS, optname, &info, sizeof(info));
+}
+
+// Connect a pair of TCP sockets, where the data sent by the client is protected by TLS
+// and those received by the server are not, unless `server_rx` is set.
+static int connect_tls_pair(int *client_fd, int *server_fd, int server_rx) {
+    if (connect_pair(client_fd, server_fd) < 0) {
+        THROW_ERROR("failed to connect the sockets");
+    }
+    if (set_tls_ulp(*client_fd) < 0 || set_tls_ulp(*server_fd) < 0 ||
+            set_tls_keys(*client_fd, TLS_TX) < 0 ||
+            (server_rx && set_tls_keys(*server_fd, TLS_RX) < 0)) {
+        close(*client_fd);
+        close(*server_fd);
+        THROW_ERROR("failed to set up kTLS");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_set_and_get_ulp() {
+    int client_fd, server_fd;
+    if (connect_pair(&client_fd, &server_fd) < 0) {
+        THROW_ERROR("failed to connect the sockets");
+    }
+
+    int ret = -1;
+    if (setsockopt(client_fd, SOL_TCP, TCP_ULP, "foo", sizeof("foo")) == 0 ||
+            errno != ENOENT) {
+        printf("unknown upper layer protocol is attached\n");
+        goto out;
+    }
+    if (setsockopt(client_fd, SOL_TLS, TLS_TX, "", 0) == 0 || errno != ENOPROTOOPT) {
+        printf("TLS option is set without the upper layer protocol\n");
+        goto out;
+    }
+    if (set_tls_ulp(client_fd) < 0) {
+        printf("failed to attach the TLS upper layer protocol\n");
+        goto out;
+    }
+
+    char name[16] = {0};
+    socklen_t len = sizeof(name);
+    if (getsockopt(client_fd, SOL_TCP, TCP_ULP, name, &len) < 0 ||
+            strncmp(name, "tls", sizeof(name)) != 0) {
+        printf("the upper layer protocol is wrong\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+static int test_unsupported_cipher() {
+    int client_fd, server_fd;
+    if (connect_pair(&client_fd, &server_fd) < 0) {
+        THROW_ERROR("failed to connect the sockets");
+    }
+
+    int ret = -1;
+    if (set_tls_ulp(client_fd) < 0) {
+        printf("failed to attach the TLS upper layer protocol\n");
+        goto out;
+    }
+
+    struct tls12_crypto_info_aes_gcm_128 info;
+    fill_crypto_info(&info);
+    info.cipher_type = TLS_CIPHER_AES_GCM_256;
+    if (setsockopt(client_fd, SOL_TLS, TLS_TX, &info, sizeof(info)) == 0 ||
+            errno != EINVAL) {
+        printf("unsupported cipher is accepted\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+static int test_ciphertext_on_wire() {
+    int client_fd, server_fd;
+    if (connect_tls_pair(&client_fd, &server_fd, 0) < 0) {
+        THROW_ERROR("failed to set up the connection");
+    }
+
+    int ret = -1;
+    if (send(client_fd, MSG_DATA, strlen(MSG_DATA), 0) != strlen(MSG_DATA)) {
+        printf("failed to send the data\n");
+        goto out;
+    }
+
+    // The server without the TLS keys receives the record as is
+    unsigned char record[64] = {0};
+    size_t record_len = strlen(MSG_DATA) + TLS_1_2_OVERHEAD;
+    if (recv(server_fd, record, record_len, MSG_WAITALL) != record_len) {
+        printf("the length of the record is wrong\n");
+        goto out;
+    }
+    if (record[0] != TLS_RECORD_TYPE_DATA || record[1] != 0x03 || record[2] != 0x03 ||
+            ((record[3] << 8) | record[4]) != record_len - 5) {
+        printf("the header of the record is wrong\n");
+        goto out;
+    }
+    if (memmem(record, record_len, MSG_DATA, strlen(MSG_DATA)) != NULL) {
+        printf("the data are not encrypted\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+static int test_send_and_recv() {
+    int client_fd, server_fd;
+    if (connect_tls_pair(&client_fd, &server_fd, 1) < 0) {
+        THROW_ERROR("failed to set up the connection");
+    }
+
+    int ret = -1;
+    // Two records are sent, and received at once
+    for (int i = 0; i < 2; i++) {
+        if (send(client_fd, MSG_DATA, strlen(MSG_DATA), 0) != strlen(MSG_DATA)) {
+            printf("failed to send the data\n");
+            goto out;
+        }
+    }
+
+    char buf[64] = {0};
+    size_t len = strlen(MSG_DATA) * 2;
+    if (recv(server_fd, buf, len, MSG_WAITALL) != len ||
+            strncmp(buf, MSG_DATA MSG_DATA, len) != 0) {
+        printf("the data received are wrong\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+static int test_record_type() {
+    int client_fd, server_fd;
+    if (connect_tls_pair(&client_fd, &server_fd, 1) < 0) {
+        THROW_ERROR("failed to set up the connection");
+    }
+
+    int ret = -1;
+    // Send an alert record, whose type is given by the control message
+    char alert[2] = {1, 0};
+    char control[CMSG_SPACE(sizeof(unsigned char))] = {0};
+    struct iovec iov = { .iov_base = alert, .iov_len = sizeof(alert) };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_TLS;
+    cmsg->cmsg_type = TLS_SET_RECORD_TYPE;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(unsigned char));
+    *CMSG_DATA(cmsg) = TLS_RECORD_TYPE_ALERT;
+    if (sendmsg(client_fd, &msg, 0) != sizeof(alert)) {
+        printf("failed to send the alert\n");
+        goto out;
+    }
+    if (send(client_fd, MSG_DATA, strlen(MSG_DATA), 0) != strlen(MSG_DATA)) {
+        printf("failed to send the data\n");
+        goto out;
+    }
+
+    // The alert is received alone, along with its type
+    char buf[64] = {0};
+    iov.iov_base = buf;
+    iov.iov_len = sizeof(buf);
+    memset(control, 0, sizeof(control));
+    msg.msg_controllen = sizeof(control);
+    if (recvmsg(server_fd, &msg, 0) != sizeof(alert) ||
+            memcmp(buf, alert, sizeof(alert)) != 0) {
+        printf("the alert received is wrong\n");
+        goto out;
+    }
+    cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_TLS ||
+            cmsg->cmsg_type != TLS_GET_RECORD_TYPE ||
+            *CMSG_DATA(cmsg) != TLS_RECORD_TYPE_ALERT) {
+        printf("the type of the record received is wrong\n");
+        goto out;
+    }
+
+    memset(buf, 0, sizeof(buf));
+    if (recv(server_fd, buf, sizeof(buf), 0) != strlen(MSG_DATA) ||
+            strcmp(buf, MSG_DATA) != 0) {
+        printf("the data received are wrong\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_set_and_get_ulp),
+    TEST_CASE(test_unsupported_cipher),
+    TEST_CASE(test_ciphertext_on_wire),
+    TEST_CASE(test_send_and_recv),
+    TEST_CASE(test_record_type),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
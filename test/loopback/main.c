@@ -0,0 +1,673 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <sys/epoll.h>
+#include <sys/time.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+#define TCP_PORT            8811
+#define UDP_PORT            8812
+#define MSG_PING            "ping"
+#define MSG_PONG            "pong"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static void fill_addr(struct sockaddr_in *addr, int port) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sin_family = AF_INET;
+    addr->sin_port = htons(port);
+    addr->sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+}
+
+static int bind_socket(int type, int port) {
+    struct sockaddr_in addr;
+    fill_addr(&addr, port);
+
+    int fd = socket(AF_INET, type, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(fd);
+        return -1;
+    }
+    return fd;
+}
+
+static int listen_socket(int port) {
+    int fd = bind_socket(SOCK_STREAM, port);
+    if (fd < 0) {
+        return -1;
+    }
+    if (listen(fd, 5) < 0) {
+        close(fd);
+        return -1;
+    }
+    return fd;
+}
+
+static int connect_socket(int port) {
+    struct sockaddr_in addr;
+    fill_addr(&addr, port);
+
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(fd);
+        return -1;
+    }
+    return fd;
+}
+
+static int is_same_addr(struct sockaddr_in *addr, struct sockaddr_in *other) {
+    return addr->sin_family == other->sin_family &&
+           addr->sin_port == other->sin_port &&
+           addr->sin_addr.s_addr == other->sin_addr.s_addr;
+}
+
+static int check_recv(int fd, const char *expected) {
+    char buf[64] = {0};
+    ssize_t len = recv(fd, buf, sizeof(buf), 0);
+    if (len != strlen(expected) || strncmp(buf, expected, len) != 0) {
+        return -1;
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for TCP
+// ============================================================================
+
+static int test_tcp_connect_and_accept() {
+    int ret = -1;
+    int client_fd = -1, accepted_fd = -1;
+    int listen_fd = listen_socket(TCP_PORT);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the listening socket");
+    }
+
+    // Connecting never blocks, so the connection can be accepted afterwards
+    client_fd = connect_socket(TCP_PORT);
+    if (client_fd < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect to the loopback address");
+    }
+
+    struct sockaddr_in peer_addr, client_addr, server_addr;
+    socklen_t addr_len = sizeof(peer_addr);
+    accepted_fd = accept(listen_fd, (struct sockaddr *)&peer_addr, &addr_len);
+    if (accepted_fd < 0) {
+        printf("failed to accept the connection\n");
+        goto out;
+    }
+
+    addr_len = sizeof(client_addr);
+    if (getsockname(client_fd, (struct sockaddr *)&client_addr, &addr_len) < 0 ||
+            !is_same_addr(&peer_addr, &client_addr) || client_addr.sin_port == 0) {
+        printf("the address of the connecting socket is wrong\n");
+        goto out;
+    }
+    fill_addr(&server_addr, TCP_PORT);
+    addr_len = sizeof(peer_addr);
+    if (getpeername(client_fd, (struct sockaddr *)&peer_addr, &addr_len) < 0 ||
+            !is_same_addr(&peer_addr, &server_addr)) {
+        printf("the peer address of the connecting socket is wrong\n");
+        goto out;
+    }
+
+    if (send(client_fd, MSG_PING, strlen(MSG_PING), 0) != strlen(MSG_PING) ||
+            check_recv(accepted_fd, MSG_PING) < 0) {
+        printf("failed to send the message to the accepted socket\n");
+        goto out;
+    }
+    if (send(accepted_fd, MSG_PONG, strlen(MSG_PONG), 0) != strlen(MSG_PONG) ||
+            check_recv(client_fd, MSG_PONG) < 0) {
+        printf("failed to send the message to the connecting socket\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (accepted_fd >= 0) {
+        close(accepted_fd);
+    }
+    close(client_fd);
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_shutdown() {
+    int ret = -1;
+    int listen_fd = listen_socket(TCP_PORT);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the listening socket");
+    }
+    int client_fd = connect_socket(TCP_PORT);
+    int accepted_fd = client_fd < 0 ? -1 : accept(listen_fd, NULL, NULL);
+    if (client_fd < 0 || accepted_fd < 0) {
+        printf("failed to establish the connection\n");
+        goto out;
+    }
+
+    if (shutdown(client_fd, SHUT_WR) < 0) {
+        printf("failed to shutdown the connecting socket\n");
+        goto out;
+    }
+    char buf[16];
+    if (recv(accepted_fd, buf, sizeof(buf), 0) != 0) {
+        printf("EOF is expected after shutdown\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (accepted_fd >= 0) {
+        close(accepted_fd);
+    }
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_recv_timeout() {
+    int ret = -1;
+    int client_fd = -1;
+    int listen_fd = listen_socket(TCP_PORT);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the listening socket");
+    }
+
+    // Both accepting and receiving give up once the timeout expires
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 100 * 1000 };
+    if (setsockopt(listen_fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0) {
+        printf("failed to set the receiving timeout\n");
+        goto out;
+    }
+    if (accept(listen_fd, NULL, NULL) >= 0 || errno != EAGAIN) {
+        printf("accepting should time out with EAGAIN\n");
+        goto out;
+    }
+
+    client_fd = connect_socket(TCP_PORT);
+    if (client_fd < 0) {
+        printf("failed to connect to the loopback address\n");
+        goto out;
+    }
+    if (setsockopt(client_fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0) {
+        printf("failed to set the receiving timeout\n");
+        goto out;
+    }
+    char buf[16];
+    if (recv(client_fd, buf, sizeof(buf), 0) >= 0 || errno != EAGAIN) {
+        printf("receiving should time out with EAGAIN\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_epoll() {
+    int ret = -1;
+    int client_fd = -1;
+    int listen_fd = listen_socket(TCP_PORT);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the listening socket");
+    }
+    int epfd = epoll_create1(0);
+    if (epfd < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to create the epoll file");
+    }
+
+    struct epoll_event event = { .events = EPOLLIN, .data.fd = listen_fd };
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, listen_fd, &event) < 0) {
+        printf("failed to add the listening socket to epoll\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        printf("no connection is expected\n");
+        goto out;
+    }
+
+    client_fd = connect_socket(TCP_PORT);
+    if (client_fd < 0) {
+        printf("failed to connect to the loopback address\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || !(event.events & EPOLLIN) ||
+            event.data.fd != listen_fd) {
+        printf("the incoming connection is not reported\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    close(epfd);
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_epoll_before_take_over() {
+    int ret = -1;
+    int client_fd = -1;
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+    int epfd = epoll_create1(0);
+    if (epfd < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to create the epoll file");
+    }
+
+    // The socket is monitored before it is taken over by binding a loopback address
+    struct epoll_event event = { .events = EPOLLIN, .data.fd = listen_fd };
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, listen_fd, &event) < 0) {
+        printf("failed to add the socket to epoll\n");
+        goto out;
+    }
+    struct sockaddr_in addr;
+    fill_addr(&addr, TCP_PORT);
+    if (bind(listen_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        printf("failed to listen on the loopback address\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        printf("no connection is expected\n");
+        goto out;
+    }
+
+    client_fd = connect_socket(TCP_PORT);
+    if (client_fd < 0) {
+        printf("failed to connect to the loopback address\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || !(event.events & EPOLLIN) ||
+            event.data.fd != listen_fd) {
+        printf("the incoming connection is not reported\n");
+        goto out;
+    }
+    if (epoll_ctl(epfd, EPOLL_CTL_DEL, listen_fd, NULL) < 0) {
+        printf("failed to delete the socket from epoll\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    close(epfd);
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_connect_not_listening() {
+    int ret = -1;
+    int bound_fd = bind_socket(SOCK_STREAM, TCP_PORT);
+    if (bound_fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+
+    if (connect_socket(TCP_PORT) >= 0 || errno != ECONNREFUSED) {
+        printf("connecting to a socket not listening should be refused\n");
+        goto out;
+    }
+    if (bind_socket(SOCK_STREAM, TCP_PORT) >= 0 || errno != EADDRINUSE) {
+        printf("binding the same address twice should fail\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(bound_fd);
+    return ret;
+}
+
+static int test_tcp_nonblocking_connect_error() {
+    int ret = -1;
+    int bound_fd = bind_socket(SOCK_STREAM, TCP_PORT);
+    if (bound_fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+    int fd = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (fd < 0) {
+        close(bound_fd);
+        THROW_ERROR("failed to create the socket");
+    }
+
+    // The refused connection is reported by SO_ERROR, which clears the error
+    struct sockaddr_in addr;
+    fill_addr(&addr, TCP_PORT);
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EINPROGRESS) {
+        printf("the non-blocking connect should be in progress\n");
+        goto out;
+    }
+    int error = 0;
+    socklen_t optlen = sizeof(error);
+    if (getsockopt(fd, SOL_SOCKET, SO_ERROR, &error, &optlen) < 0 ||
+            error != ECONNREFUSED) {
+        printf("the refused connection is not reported by SO_ERROR\n");
+        goto out;
+    }
+    if (getsockopt(fd, SOL_SOCKET, SO_ERROR, &error, &optlen) < 0 || error != 0) {
+        printf("the error is not cleared by SO_ERROR\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    close(bound_fd);
+    return ret;
+}
+
+static int test_tcp_dup_shares_socket() {
+    int ret = -1;
+    int client_fd = -1, accepted_fd = -1;
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+    int dup_fd = dup(fd);
+    if (dup_fd < 0) {
+        close(fd);
+        THROW_ERROR("failed to dup the socket");
+    }
+
+    // The socket taken over through one fd is seen through the other
+    struct sockaddr_in addr, bound_addr;
+    fill_addr(&addr, TCP_PORT);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 || listen(dup_fd, 5) < 0) {
+        printf("failed to listen through the duplicated fd\n");
+        goto out;
+    }
+    socklen_t addr_len = sizeof(bound_addr);
+    if (getsockname(dup_fd, (struct sockaddr *)&bound_addr, &addr_len) < 0 ||
+            !is_same_addr(&addr, &bound_addr)) {
+        printf("the address of the duplicated fd is wrong\n");
+        goto out;
+    }
+
+    client_fd = connect_socket(TCP_PORT);
+    accepted_fd = client_fd < 0 ? -1 : accept(fd, NULL, NULL);
+    if (client_fd < 0 || accepted_fd < 0) {
+        printf("failed to establish the connection\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (accepted_fd >= 0) {
+        close(accepted_fd);
+    }
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    close(dup_fd);
+    close(fd);
+    return ret;
+}
+
+static int test_tcp_options_kept() {
+    int ret = -1;
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    int reuse_addr = 1;
+    struct timeval timeout = { .tv_sec = 3, .tv_usec = 0 };
+    if (setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &reuse_addr, sizeof(reuse_addr)) < 0 ||
+            setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0) {
+        close(fd);
+        THROW_ERROR("failed to set the options");
+    }
+
+    // The options set before the socket is taken over are kept
+    struct sockaddr_in addr;
+    fill_addr(&addr, TCP_PORT);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        printf("failed to bind the socket\n");
+        goto out;
+    }
+    reuse_addr = 0;
+    socklen_t optlen = sizeof(reuse_addr);
+    if (getsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &reuse_addr, &optlen) < 0 ||
+            reuse_addr == 0) {
+        printf("SO_REUSEADDR is lost\n");
+        goto out;
+    }
+    memset(&timeout, 0, sizeof(timeout));
+    optlen = sizeof(timeout);
+    if (getsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, &optlen) < 0 ||
+            timeout.tv_sec != 3) {
+        printf("SO_RCVTIMEO is lost\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_tcp_port_reserved_on_host() {
+    int ret = -1;
+    int bound_fd = bind_socket(SOCK_STREAM, TCP_PORT);
+    if (bound_fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+
+    // A socket bound to the wildcard address talks through the host, where the port
+    // of the loopback socket is taken
+    struct sockaddr_in addr;
+    fill_addr(&addr, TCP_PORT);
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        close(bound_fd);
+        THROW_ERROR("failed to create the socket");
+    }
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EADDRINUSE) {
+        printf("the port of the loopback socket is not reserved on the host\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    close(bound_fd);
+    return ret;
+}
+
+static int test_tcp_wildcard_bind() {
+    int ret = -1;
+    int client_fd = -1, accepted_fd = -1;
+    struct sockaddr_in addr;
+    fill_addr(&addr, TCP_PORT);
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+            listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen on the wildcard address");
+    }
+
+    // A socket bound to the wildcard address accepts the connections from the enclave
+    client_fd = connect_socket(TCP_PORT);
+    if (client_fd < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect to the loopback address");
+    }
+    accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        printf("failed to accept the connection\n");
+        goto out;
+    }
+
+    if (send(client_fd, MSG_PING, strlen(MSG_PING), 0) != strlen(MSG_PING) ||
+            check_recv(accepted_fd, MSG_PING) < 0) {
+        printf("failed to send the message to the accepted socket\n");
+        goto out;
+    }
+    if (send(accepted_fd, MSG_PONG, strlen(MSG_PONG), 0) != strlen(MSG_PONG) ||
+            check_recv(client_fd, MSG_PONG) < 0) {
+        printf("failed to send the message to the connecting socket\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (accepted_fd >= 0) {
+        close(accepted_fd);
+    }
+    close(client_fd);
+    close(listen_fd);
+    return ret;
+}
+
+// ============================================================================
+// Test cases for UDP
+// ============================================================================
+
+static int test_udp_sendto_and_recvfrom() {
+    int ret = -1;
+    int server_fd = bind_socket(SOCK_DGRAM, UDP_PORT);
+    if (server_fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+    int client_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (client_fd < 0) {
+        close(server_fd);
+        THROW_ERROR("failed to create the unbound socket");
+    }
+
+    struct sockaddr_in server_addr, client_addr, src_addr;
+    fill_addr(&server_addr, UDP_PORT);
+    if (sendto(client_fd, MSG_PING, strlen(MSG_PING), 0, (struct sockaddr *)&server_addr,
+               sizeof(server_addr)) != strlen(MSG_PING)) {
+        printf("failed to send the datagram\n");
+        goto out;
+    }
+
+    char buf[64] = {0};
+    socklen_t addr_len = sizeof(src_addr);
+    ssize_t len = recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                           &addr_len);
+    if (len != strlen(MSG_PING) || strncmp(buf, MSG_PING, len) != 0) {
+        printf("failed to receive the datagram\n");
+        goto out;
+    }
+
+    // The unbound socket is bound to an ephemeral port when sending
+    addr_len = sizeof(client_addr);
+    if (getsockname(client_fd, (struct sockaddr *)&client_addr, &addr_len) < 0 ||
+            client_addr.sin_port == 0 || !is_same_addr(&src_addr, &client_addr)) {
+        printf("the source address of the datagram is wrong\n");
+        goto out;
+    }
+
+    if (sendto(server_fd, MSG_PONG, strlen(MSG_PONG), 0, (struct sockaddr *)&src_addr,
+               addr_len) != strlen(MSG_PONG) || check_recv(client_fd, MSG_PONG) < 0) {
+        printf("failed to reply to the source address\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+static int test_udp_wildcard_bind() {
+    int ret = -1;
+    struct sockaddr_in addr;
+    fill_addr(&addr, UDP_PORT);
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    int server_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (server_fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+    if (bind(server_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(server_fd);
+        THROW_ERROR("failed to bind the wildcard address");
+    }
+    int client_fd = bind_socket(SOCK_DGRAM, 0);
+    if (client_fd < 0) {
+        close(server_fd);
+        THROW_ERROR("failed to create the bound socket");
+    }
+
+    struct sockaddr_in server_addr, src_addr;
+    fill_addr(&server_addr, UDP_PORT);
+    if (sendto(client_fd, MSG_PING, strlen(MSG_PING), 0, (struct sockaddr *)&server_addr,
+               sizeof(server_addr)) != strlen(MSG_PING)) {
+        printf("failed to send the datagram\n");
+        goto out;
+    }
+
+    // The reply is from the loopback address, rather than from the wildcard address
+    char buf[64] = {0};
+    socklen_t addr_len = sizeof(src_addr);
+    ssize_t len = recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                           &addr_len);
+    if (len != strlen(MSG_PING) || strncmp(buf, MSG_PING, len) != 0) {
+        printf("failed to receive the datagram\n");
+        goto out;
+    }
+    if (sendto(server_fd, MSG_PONG, strlen(MSG_PONG), 0, (struct sockaddr *)&src_addr,
+               addr_len) != strlen(MSG_PONG)) {
+        printf("failed to reply to the source address\n");
+        goto out;
+    }
+    addr_len = sizeof(src_addr);
+    len = recvfrom(client_fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr, &addr_len);
+    if (len != strlen(MSG_PONG) || strncmp(buf, MSG_PONG, len) != 0 ||
+            !is_same_addr(&src_addr, &server_addr)) {
+        printf("failed to receive the reply from the loopback address\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_tcp_connect_and_accept),
+    TEST_CASE(test_tcp_shutdown),
+    TEST_CASE(test_tcp_recv_timeout),
+    TEST_CASE(test_tcp_epoll),
+    TEST_CASE(test_tcp_epoll_before_take_over),
+    TEST_CASE(test_tcp_connect_not_listening),
+    TEST_CASE(test_tcp_nonblocking_connect_error),
+    TEST_CASE(test_tcp_dup_shares_socket),
+    TEST_CASE(test_tcp_options_kept),
+    TEST_CASE(test_tcp_port_reserved_on_host),
+    TEST_CASE(test_tcp_wildcard_bind),
+    TEST_CASE(test_udp_sendto_and_recvfrom),
+    TEST_CASE(test_udp_wildcard_bind),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -1,6 +1,10 @@
 #include <sys/stat.h>
 #include <sys/mount.h>
+#include <sys/mman.h>
+#include <sys/xattr.h>
+#include <sys/inotify.h>
 #include <errno.h>
+#include <stdio.h>
 #include <fcntl.h>
 #include "test_fs.h"
 
@@ -213,6 +217,366 @@ static int test_umount_ramfs() {
     return test_mount_framework(__test_umount_fs, target, false);
 }
 
+// ============================================================================
+// Test cases for bind mount, remount and move
+// ============================================================================
+
+#define BIND_SRC_DIR    "/mnt_bind_src"
+#define BIND_DIR        "/mnt_bind"
+#define MOVED_DIR       "/mnt_moved"
+
+static int test_bind_mount() {
+    if (__test_mount_ramfs(BIND_SRC_DIR) < 0) {
+        THROW_ERROR("failed to mount the source ramfs");
+    }
+    if (write_read_file(BIND_SRC_DIR "/file.txt") < 0) {
+        THROW_ERROR("failed to RW files on the source");
+    }
+    if (create_dir(BIND_DIR) < 0) {
+        THROW_ERROR("failed to create the bind mnt dir");
+    }
+
+    if (mount(BIND_SRC_DIR, BIND_DIR, NULL, MS_BIND, NULL) < 0) {
+        THROW_ERROR("failed to bind mount");
+    }
+    if (fs_check_file_content(BIND_DIR "/file.txt", "Hello World\n") < 0) {
+        THROW_ERROR("failed to read the file through the bind mount");
+    }
+    if (write_read_file(BIND_DIR "/new_file.txt") < 0) {
+        THROW_ERROR("failed to RW files through the bind mount");
+    }
+    if (fs_check_file_content(BIND_SRC_DIR "/new_file.txt", "Hello World\n") < 0) {
+        THROW_ERROR("failed to read the file written through the bind mount");
+    }
+    return 0;
+}
+
+// A file is the same file no matter through which mount it is looked up
+static int test_bind_mount_same_file() {
+    const char *value = "bind";
+    char buf[16] = {0};
+
+    if (setxattr(BIND_SRC_DIR "/file.txt", "user.test", value, strlen(value), 0) < 0) {
+        THROW_ERROR("failed to set the xattr on the source");
+    }
+    if (getxattr(BIND_DIR "/file.txt", "user.test", buf, sizeof(buf)) != strlen(value) ||
+            strcmp(buf, value) != 0) {
+        THROW_ERROR("the xattr should be visible through the bind mount");
+    }
+    if (removexattr(BIND_DIR "/file.txt", "user.test") < 0) {
+        THROW_ERROR("failed to remove the xattr through the bind mount");
+    }
+
+    int inotify_fd = inotify_init1(IN_NONBLOCK);
+    if (inotify_fd < 0) {
+        THROW_ERROR("failed to create an inotify");
+    }
+    if (inotify_add_watch(inotify_fd, BIND_SRC_DIR "/file.txt", IN_MODIFY) < 0) {
+        close(inotify_fd);
+        THROW_ERROR("failed to watch the file on the source");
+    }
+    if (write_read_file(BIND_DIR "/file.txt") < 0) {
+        close(inotify_fd);
+        THROW_ERROR("failed to RW files through the bind mount");
+    }
+    char event_buf[sizeof(struct inotify_event) + NAME_MAX + 1];
+    int len = read(inotify_fd, event_buf, sizeof(event_buf));
+    close(inotify_fd);
+    if (len < (int)sizeof(struct inotify_event) ||
+            !(((struct inotify_event *)event_buf)->mask & IN_MODIFY)) {
+        THROW_ERROR("the write through the bind mount should be reported to the watch");
+    }
+    return 0;
+}
+
+// The mount and its file system are shown read-only in the mountinfo
+static int check_mountinfo_readonly(const char *target) {
+    char line[1024];
+    char pattern[256];
+    int found = 0;
+
+    FILE *fp = fopen("/proc/self/mountinfo", "r");
+    if (fp == NULL) {
+        THROW_ERROR("failed to open the mountinfo");
+    }
+    snprintf(pattern, sizeof(pattern), " %s ro", target);
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        if (strstr(line, pattern) != NULL) {
+            size_t len = strlen(line);
+            found = len >= 4 && strcmp(line + len - 4, " ro\n") == 0;
+            break;
+        }
+    }
+    fclose(fp);
+    if (!found) {
+        THROW_ERROR("the read-only mount is not shown read-only");
+    }
+    return 0;
+}
+
+static int test_remount_readonly() {
+    int flags = MS_REMOUNT | MS_BIND | MS_RDONLY;
+
+    // The mount cannot become read-only while files are opened for writing
+    int fd = open(BIND_DIR "/file.txt", O_RDWR);
+    if (fd < 0) {
+        THROW_ERROR("failed to open a file to write");
+    }
+    if (!(mount(NULL, BIND_DIR, NULL, flags, NULL) < 0 && errno == EBUSY)) {
+        close(fd);
+        THROW_ERROR("remount read-only with an open writer should return EBUSY");
+    }
+    void *addr = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    close(fd);
+    if (addr == MAP_FAILED) {
+        THROW_ERROR("failed to mmap the file");
+    }
+    if (!(mount(NULL, BIND_DIR, NULL, flags, NULL) < 0 && errno == EBUSY)) {
+        munmap(addr, 4096);
+        THROW_ERROR("remount read-only with a shared mapping should return EBUSY");
+    }
+    munmap(addr, 4096);
+
+    if (mount(NULL, BIND_DIR, NULL, flags, NULL) < 0) {
+        THROW_ERROR("failed to remount read-only");
+    }
+
+    fd = open(BIND_DIR "/file.txt", O_WRONLY);
+    if (!(fd < 0 && errno == EROFS)) {
+        THROW_ERROR("open to write should return EROFS");
+    }
+    fd = open(BIND_DIR "/created.txt", O_RDWR | O_CREAT, 00666);
+    if (!(fd < 0 && errno == EROFS)) {
+        THROW_ERROR("open to create should return EROFS");
+    }
+    if (!(mkdir(BIND_DIR "/dir", 00775) < 0 && errno == EROFS)) {
+        THROW_ERROR("mkdir should return EROFS");
+    }
+    if (!(unlink(BIND_DIR "/file.txt") < 0 && errno == EROFS)) {
+        THROW_ERROR("unlink should return EROFS");
+    }
+    if (!(chmod(BIND_DIR "/file.txt", 00644) < 0 && errno == EROFS)) {
+        THROW_ERROR("chmod should return EROFS");
+    }
+    if (fs_check_file_content(BIND_DIR "/file.txt", "Hello World\n") < 0) {
+        THROW_ERROR("failed to read the file on the read-only mount");
+    }
+    if (check_mountinfo_readonly(BIND_DIR) < 0) {
+        THROW_ERROR("failed to check the read-only mount in the mountinfo");
+    }
+    // The source mount is still writable
+    if (write_read_file(BIND_SRC_DIR "/file.txt") < 0) {
+        THROW_ERROR("failed to RW files on the source");
+    }
+
+    if (mount(NULL, BIND_DIR, NULL, MS_REMOUNT | MS_BIND, NULL) < 0) {
+        THROW_ERROR("failed to remount writable");
+    }
+    if (write_read_file(BIND_DIR "/file.txt") < 0) {
+        THROW_ERROR("failed to RW files after remounting writable");
+    }
+
+    if (create_dir(BIND_SRC_DIR "/dir") < 0) {
+        THROW_ERROR("failed to create a dir");
+    }
+    if (!(mount(NULL, BIND_SRC_DIR "/dir", NULL, flags, NULL) < 0 && errno == EINVAL)) {
+        THROW_ERROR("remount on a non-mountpoint should return EINVAL");
+    }
+    if (umount(BIND_DIR) < 0) {
+        THROW_ERROR("failed to umount the bind mount");
+    }
+    return 0;
+}
+
+static int test_bind_mount_recursive() {
+    const char *submount_dir = BIND_SRC_DIR "/sub";
+    if (__test_mount_ramfs(submount_dir) < 0) {
+        THROW_ERROR("failed to mount the ramfs under the source");
+    }
+    if (write_read_file(BIND_SRC_DIR "/sub/file.txt") < 0) {
+        THROW_ERROR("failed to RW files on the submount");
+    }
+
+    if (mount(BIND_SRC_DIR, BIND_DIR, NULL, MS_BIND, NULL) < 0) {
+        THROW_ERROR("failed to bind mount");
+    }
+    if (check_file_no_exists(BIND_DIR "/sub/file.txt") < 0) {
+        THROW_ERROR("the submount should be invisible through a non-recursive bind mount");
+    }
+    if (umount(BIND_DIR) < 0) {
+        THROW_ERROR("failed to umount the bind mount");
+    }
+
+    if (mount(BIND_SRC_DIR, BIND_DIR, NULL, MS_BIND | MS_REC, NULL) < 0) {
+        THROW_ERROR("failed to bind mount recursively");
+    }
+    if (fs_check_file_content(BIND_DIR "/sub/file.txt", "Hello World\n") < 0) {
+        THROW_ERROR("the submount should be visible through a recursive bind mount");
+    }
+    if (umount(BIND_DIR) < 0) {
+        THROW_ERROR("failed to umount the bind mount");
+    }
+
+    if (umount(submount_dir) < 0) {
+        THROW_ERROR("failed to umount the submount");
+    }
+    return 0;
+}
+
+static int test_move_mount() {
+    if (create_dir(MOVED_DIR) < 0) {
+        THROW_ERROR("failed to create the dir to move to");
+    }
+    int ret = mount(BIND_SRC_DIR, BIND_SRC_DIR "/sub", NULL, MS_MOVE, NULL);
+    if (!(ret < 0 && errno == EINVAL)) {
+        THROW_ERROR("moving a mount under itself should return EINVAL");
+    }
+
+    if (mount(BIND_SRC_DIR, MOVED_DIR, NULL, MS_MOVE, NULL) < 0) {
+        THROW_ERROR("failed to move the mount");
+    }
+    if (fs_check_file_content(MOVED_DIR "/file.txt", "Hello World\n") < 0) {
+        THROW_ERROR("failed to read the file on the moved mount");
+    }
+    if (check_file_no_exists(BIND_SRC_DIR "/file.txt") < 0) {
+        THROW_ERROR("the mount should be moved away");
+    }
+    if (umount(MOVED_DIR) < 0) {
+        THROW_ERROR("failed to umount the moved mount");
+    }
+    return 0;
+}
+
+static int test_mount_propagation() {
+    if (__test_mount_ramfs(BIND_DIR) < 0) {
+        THROW_ERROR("failed to mount ramfs");
+    }
+    if (mount(NULL, BIND_DIR, NULL, MS_PRIVATE, NULL) < 0) {
+        THROW_ERROR("failed to make the mount private");
+    }
+    int ret = mount(NULL, BIND_DIR, NULL, MS_PRIVATE | MS_SHARED, NULL);
+    if (!(ret < 0 && errno == EINVAL)) {
+        THROW_ERROR("multiple propagation types should return EINVAL");
+    }
+    if (umount(BIND_DIR) < 0) {
+        THROW_ERROR("failed to umount ramfs");
+    }
+    return 0;
+}
+
+#define EXT2_BAD_DISK_DIR "/host/mnt_test/ext2_bad_disk"
+#define EXT2_BAD_DISK_IMAGE EXT2_BAD_DISK_DIR "/sworndisk.image"
+#define EXT2_BAD_DISK_MNT "/mnt_ext2_bad_disk"
+
+// An existing image that cannot be opened must not be replaced by a new disk
+static int test_mount_ext2_bad_image() {
+    char image_content[4096];
+    char read_buf[sizeof(image_content)];
+    memset(image_content, 0xa5, sizeof(image_content));
+
+    if (create_dir("/host/mnt_test") < 0 || create_dir(EXT2_BAD_DISK_DIR) < 0 ||
+            create_dir(EXT2_BAD_DISK_MNT) < 0) {
+        return -1;
+    }
+    int fd = open(EXT2_BAD_DISK_IMAGE, O_RDWR | O_CREAT | O_TRUNC, 00666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the image");
+    }
+    if (write(fd, image_content, sizeof(image_content)) != sizeof(image_content)) {
+        close(fd);
+        THROW_ERROR("failed to write the image");
+    }
+    close(fd);
+
+    int ret = mount("ext2", EXT2_BAD_DISK_MNT, "ext2", 0,
+                    "dir=./mnt_test/ext2_bad_disk,disk_size=5GB,"
+                    "key=c7-32-b3-ed-44-df-ec-7b-25-2d-9a-32-38-8d-58-61");
+    if (!(ret < 0 && errno == EACCES)) {
+        THROW_ERROR("mounting a disk whose image cannot be opened should return EACCES");
+    }
+
+    fd = open(EXT2_BAD_DISK_IMAGE, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("the image should be kept");
+    }
+    ret = read(fd, read_buf, sizeof(read_buf));
+    close(fd);
+    if (ret != sizeof(read_buf) || memcmp(read_buf, image_content, sizeof(read_buf)) != 0) {
+        THROW_ERROR("the image should not be modified");
+    }
+
+    if (unlink(EXT2_BAD_DISK_IMAGE) < 0 || rmdir(EXT2_BAD_DISK_MNT) < 0) {
+        THROW_ERROR("failed to clean up");
+    }
+    return 0;
+}
+
+#define EXT2_MKFS_DISK_DIR "/host/mnt_test/ext2_mkfs_disk"
+#define EXT2_MKFS_DISK_IMAGE EXT2_MKFS_DISK_DIR "/sworndisk.image"
+#define EXT2_MKFS_DISK_MNT "/mnt_ext2_mkfs_disk"
+#define EXT2_MKFS_DISK_OPTIONS "dir=./mnt_test/ext2_mkfs_disk,disk_size=5GB"
+
+static int test_mount_ext2_with_mkfs_options() {
+    const char *invalid_options[] = {
+        EXT2_MKFS_DISK_OPTIONS ",block_size=1024",
+        EXT2_MKFS_DISK_OPTIONS ",block_size=2048",
+        EXT2_MKFS_DISK_OPTIONS ",block_size=8192",
+        EXT2_MKFS_DISK_OPTIONS ",inodes_count=5",
+        EXT2_MKFS_DISK_OPTIONS ",inodes_count=4000000000",
+    };
+    const char *file_path = EXT2_MKFS_DISK_MNT "/test_mkfs.txt";
+    const char *msg = "Hello Ext2\n";
+
+    if (create_dir("/host/mnt_test") < 0 || create_dir(EXT2_MKFS_DISK_DIR) < 0 ||
+            create_dir(EXT2_MKFS_DISK_MNT) < 0) {
+        return -1;
+    }
+    // The disk is not formatted if the options are invalid, so it is still
+    // formatted by the next mount
+    for (int i = 0; i < ARRAY_SIZE(invalid_options); i++) {
+        int ret = mount("ext2", EXT2_MKFS_DISK_MNT, "ext2", 0, invalid_options[i]);
+        if (!(ret < 0 && errno == EINVAL)) {
+            THROW_ERROR("mounting with invalid mkfs options should return EINVAL");
+        }
+    }
+
+    if (mount("ext2", EXT2_MKFS_DISK_MNT, "ext2", 0,
+              EXT2_MKFS_DISK_OPTIONS ",block_size=4096,inodes_count=4096") < 0) {
+        THROW_ERROR("failed to mount ext2 with mkfs options");
+    }
+    int fd = open(file_path, O_WRONLY | O_CREAT | O_TRUNC, 00666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a file in ext2");
+    }
+    if (write(fd, msg, strlen(msg)) != strlen(msg)) {
+        close(fd);
+        THROW_ERROR("failed to write a file in ext2");
+    }
+    close(fd);
+    if (umount(EXT2_MKFS_DISK_MNT) < 0) {
+        THROW_ERROR("failed to umount ext2");
+    }
+
+    // The formatted disk is opened as it is
+    if (mount("ext2", EXT2_MKFS_DISK_MNT, "ext2", 0, EXT2_MKFS_DISK_OPTIONS) < 0) {
+        THROW_ERROR("failed to mount the formatted ext2");
+    }
+    if (fs_check_file_content(file_path, msg) < 0) {
+        THROW_ERROR("failed to read the file back from ext2");
+    }
+    if (unlink(file_path) < 0) {
+        THROW_ERROR("failed to remove a file in ext2");
+    }
+    if (umount(EXT2_MKFS_DISK_MNT) < 0) {
+        THROW_ERROR("failed to umount ext2");
+    }
+
+    if (unlink(EXT2_MKFS_DISK_IMAGE) < 0 || rmdir(EXT2_MKFS_DISK_MNT) < 0) {
+        THROW_ERROR("failed to clean up");
+    }
+    return 0;
+}
+
 // ============================================================================
 // Test suite main
 // ============================================================================
@@ -227,6 +591,14 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_umount_hostfs),
     TEST_CASE(test_mount_ramfs),
     TEST_CASE(test_umount_ramfs),
+    TEST_CASE(test_bind_mount),
+    TEST_CASE(test_bind_mount_same_file),
+    TEST_CASE(test_remount_readonly),
+    TEST_CASE(test_bind_mount_recursive),
+    TEST_CASE(test_move_mount),
+    TEST_CASE(test_mount_propagation),
+    TEST_CASE(test_mount_ext2_bad_image),
+    TEST_CASE(test_mount_ext2_with_mkfs_options),
 };
 
 int main(int argc, const char *argv[]) {
@@ -0,0 +1,277 @@
+#define _GNU_SOURCE
+#include <mqueue.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+#define MQ_NAME         "/occlum_test_mq"
+#define MQ_MAXMSG       4
+#define MQ_MSGSIZE      64
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static mqd_t create_mq(int flags) {
+    struct mq_attr attr = {
+        .mq_maxmsg = MQ_MAXMSG,
+        .mq_msgsize = MQ_MSGSIZE,
+    };
+    mq_unlink(MQ_NAME);
+    return mq_open(MQ_NAME, O_CREAT | O_EXCL | O_RDWR | flags, 0600, &attr);
+}
+
+static void destroy_mq(mqd_t mqd) {
+    mq_close(mqd);
+    mq_unlink(MQ_NAME);
+}
+
+static volatile sig_atomic_t notified = 0;
+static volatile int notified_value = 0;
+
+static void notify_handler(int signum, siginfo_t *info, void *ucontext) {
+    if (info->si_code == SI_MESGQ) {
+        notified = 1;
+        notified_value = info->si_value.sival_int;
+    }
+}
+
+// ============================================================================
+// Test cases for POSIX message queues
+// ============================================================================
+
+int test_open_and_unlink() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_open(MQ_NAME, O_CREAT | O_EXCL | O_RDWR, 0600, NULL) >= 0 || errno != EEXIST) {
+        THROW_ERROR("mq_open with O_EXCL should fail");
+    }
+    mqd_t mqd2 = mq_open(MQ_NAME, O_RDONLY);
+    if (mqd2 < 0) {
+        THROW_ERROR("mq_open of an existing queue failed");
+    }
+    mq_close(mqd2);
+
+    struct mq_attr attr;
+    if (mq_getattr(mqd, &attr) < 0) {
+        THROW_ERROR("mq_getattr failed");
+    }
+    if (attr.mq_maxmsg != MQ_MAXMSG || attr.mq_msgsize != MQ_MSGSIZE || attr.mq_curmsgs != 0) {
+        THROW_ERROR("incorrect attributes");
+    }
+
+    destroy_mq(mqd);
+    if (mq_open(MQ_NAME, O_RDWR) >= 0 || errno != ENOENT) {
+        THROW_ERROR("mq_open of an unlinked queue should fail");
+    }
+    return 0;
+}
+
+int test_send_recv_by_priority() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_send(mqd, "low", 4, 1) < 0 ||
+            mq_send(mqd, "high", 5, 10) < 0 ||
+            mq_send(mqd, "low2", 5, 1) < 0) {
+        THROW_ERROR("mq_send failed");
+    }
+
+    const char *expected_msgs[] = { "high", "low", "low2" };
+    unsigned int expected_prios[] = { 10, 1, 1 };
+    char buf[MQ_MSGSIZE];
+    for (int i = 0; i < 3; i++) {
+        unsigned int prio;
+        ssize_t len = mq_receive(mqd, buf, sizeof(buf), &prio);
+        if (len != strlen(expected_msgs[i]) + 1) {
+            THROW_ERROR("mq_receive returned an incorrect length");
+        }
+        if (strcmp(buf, expected_msgs[i]) != 0 || prio != expected_prios[i]) {
+            THROW_ERROR("messages are received in an incorrect order");
+        }
+    }
+    destroy_mq(mqd);
+    return 0;
+}
+
+int test_nonblocking_and_timeout() {
+    mqd_t mqd = create_mq(O_NONBLOCK);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    char buf[MQ_MSGSIZE];
+    if (mq_receive(mqd, buf, sizeof(buf), NULL) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("mq_receive on an empty queue should fail with EAGAIN");
+    }
+    for (int i = 0; i < MQ_MAXMSG; i++) {
+        if (mq_send(mqd, "msg", 4, 0) < 0) {
+            THROW_ERROR("mq_send failed");
+        }
+    }
+    if (mq_send(mqd, "msg", 4, 0) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("mq_send on a full queue should fail with EAGAIN");
+    }
+
+    // Switch to blocking mode and wait until timeout
+    struct mq_attr attr = { .mq_flags = 0 };
+    if (mq_setattr(mqd, &attr, NULL) < 0) {
+        THROW_ERROR("mq_setattr failed");
+    }
+    struct timespec abs_timeout;
+    clock_gettime(CLOCK_REALTIME, &abs_timeout);
+    abs_timeout.tv_nsec += 100 * 1000 * 1000;
+    if (abs_timeout.tv_nsec >= 1000 * 1000 * 1000) {
+        abs_timeout.tv_sec += 1;
+        abs_timeout.tv_nsec -= 1000 * 1000 * 1000;
+    }
+    if (mq_timedsend(mqd, "msg", 4, 0, &abs_timeout) >= 0 || errno != ETIMEDOUT) {
+        THROW_ERROR("mq_timedsend on a full queue should time out");
+    }
+    destroy_mq(mqd);
+    return 0;
+}
+
+int test_invalid_arguments() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    char buf[MQ_MSGSIZE];
+    if (mq_send(mqd, buf, MQ_MSGSIZE + 1, 0) >= 0 || errno != EMSGSIZE) {
+        THROW_ERROR("mq_send of a too long message should fail");
+    }
+    if (mq_receive(mqd, buf, MQ_MSGSIZE - 1, NULL) >= 0 || errno != EMSGSIZE) {
+        THROW_ERROR("mq_receive with a too small buffer should fail");
+    }
+    if (mq_send(mqd, buf, 1, 32768) >= 0 || errno != EINVAL) {
+        THROW_ERROR("mq_send with an invalid priority should fail");
+    }
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = 256 + SIGHUP,
+    };
+    if (mq_notify(mqd, &sev) >= 0 || errno != EINVAL) {
+        THROW_ERROR("mq_notify with an invalid signal should fail");
+    }
+    destroy_mq(mqd);
+
+    // The permissions are not checked when the queue is created
+    mqd = mq_open(MQ_NAME, O_CREAT | O_RDWR, 0400, NULL);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_open(MQ_NAME, O_WRONLY) >= 0 || errno != EACCES) {
+        THROW_ERROR("mq_open of a read-only queue for writing should fail");
+    }
+    destroy_mq(mqd);
+
+    mqd = mq_open(MQ_NAME, O_CREAT | O_WRONLY, 0600, NULL);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_receive(mqd, buf, sizeof(buf), NULL) >= 0 || errno != EBADF) {
+        THROW_ERROR("mq_receive on a write-only queue should fail");
+    }
+    destroy_mq(mqd);
+    return 0;
+}
+
+int test_poll() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    struct pollfd pfd = { .fd = mqd, .events = POLLIN | POLLOUT };
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLOUT) {
+        THROW_ERROR("an empty queue should only be writable");
+    }
+    if (mq_send(mqd, "msg", 4, 0) < 0) {
+        THROW_ERROR("mq_send failed");
+    }
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != (POLLIN | POLLOUT)) {
+        THROW_ERROR("a non-empty queue should be readable");
+    }
+    destroy_mq(mqd);
+    return 0;
+}
+
+int test_notify() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    struct sigaction sa = { .sa_sigaction = notify_handler, .sa_flags = SA_SIGINFO };
+    if (sigaction(SIGUSR1, &sa, NULL) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR1,
+        .sigev_value.sival_int = 42,
+    };
+    if (mq_notify(mqd, &sev) < 0) {
+        THROW_ERROR("mq_notify failed");
+    }
+    if (mq_send(mqd, "msg", 4, 0) < 0) {
+        THROW_ERROR("mq_send failed");
+    }
+    if (!notified || notified_value != 42) {
+        THROW_ERROR("the notification is not delivered");
+    }
+
+    // The registration is removed after the notification
+    notified = 0;
+    char buf[MQ_MSGSIZE];
+    mq_receive(mqd, buf, sizeof(buf), NULL);
+    mq_send(mqd, "msg", 4, 0);
+    if (notified) {
+        THROW_ERROR("the notification should be delivered only once");
+    }
+    destroy_mq(mqd);
+    return 0;
+}
+
+int test_dev_mqueue() {
+    mqd_t mqd = create_mq(0);
+    if (mqd < 0) {
+        THROW_ERROR("mq_open failed");
+    }
+    struct stat stat_buf;
+    if (stat("/dev/mqueue" MQ_NAME, &stat_buf) < 0) {
+        THROW_ERROR("the queue is not visible under /dev/mqueue");
+    }
+    if (unlink("/dev/mqueue" MQ_NAME) < 0) {
+        THROW_ERROR("failed to unlink the queue from /dev/mqueue");
+    }
+    if (mq_unlink(MQ_NAME) >= 0 || errno != ENOENT) {
+        THROW_ERROR("the queue should have been unlinked");
+    }
+    mq_close(mqd);
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_open_and_unlink),
+    TEST_CASE(test_send_recv_by_priority),
+    TEST_CASE(test_nonblocking_and_timeout),
+    TEST_CASE(test_invalid_arguments),
+    TEST_CASE(test_poll),
+    TEST_CASE(test_notify),
+    TEST_CASE(test_dev_mqueue),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
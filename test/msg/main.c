@@ -0,0 +1,217 @@
+#define _GNU_SOURCE
+#include <sys/ipc.h>
+#include <sys/msg.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <unistd.h>
+#include "test.h"
+
+#define MAX_TEXT_LEN 64
+
+struct msg_buf {
+    long mtype;
+    char mtext[MAX_TEXT_LEN];
+};
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int create_msg_queue() {
+    int msqid = msgget(IPC_PRIVATE, IPC_CREAT | 0600);
+    if (msqid < 0) {
+        THROW_ERROR("msgget failed");
+    }
+    return msqid;
+}
+
+static int send_msg(int msqid, long mtype, const char *text, int flags) {
+    struct msg_buf msg = { .mtype = mtype };
+    strncpy(msg.mtext, text, MAX_TEXT_LEN);
+    return msgsnd(msqid, &msg, strlen(text) + 1, flags);
+}
+
+static void *send_after_sleep(void *arg) {
+    int msqid = *(int *)arg;
+    usleep(100 * 1000);
+    send_msg(msqid, 1, "hello", 0);
+    return NULL;
+}
+
+static void *remove_after_sleep(void *arg) {
+    int msqid = *(int *)arg;
+    usleep(100 * 1000);
+    msgctl(msqid, IPC_RMID, NULL);
+    return NULL;
+}
+
+// ============================================================================
+// Test cases for System V message queues
+// ============================================================================
+
+int test_msgget() {
+    key_t key = 0x3e5a;
+    int msqid = msgget(key, IPC_CREAT | IPC_EXCL | 0600);
+    if (msqid < 0) {
+        THROW_ERROR("msgget failed");
+    }
+    if (msgget(key, IPC_CREAT | IPC_EXCL | 0600) >= 0 || errno != EEXIST) {
+        THROW_ERROR("msgget with IPC_EXCL should fail");
+    }
+    if (msgget(key, 0) != msqid) {
+        THROW_ERROR("msgget with an existing key should return the same queue");
+    }
+    if (msgctl(msqid, IPC_RMID, NULL) < 0) {
+        THROW_ERROR("msgctl IPC_RMID failed");
+    }
+    if (msgget(key, 0) >= 0 || errno != ENOENT) {
+        THROW_ERROR("the removed queue should not be found");
+    }
+    return 0;
+}
+
+int test_send_recv() {
+    int msqid = create_msg_queue();
+    struct msg_buf msg;
+    if (send_msg(msqid, 1, "hello", 0) < 0) {
+        THROW_ERROR("msgsnd failed");
+    }
+    ssize_t len = msgrcv(msqid, &msg, MAX_TEXT_LEN, 0, 0);
+    if (len != strlen("hello") + 1 || msg.mtype != 1 || strcmp(msg.mtext, "hello") != 0) {
+        THROW_ERROR("msgrcv returns a wrong message");
+    }
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 0, IPC_NOWAIT) >= 0 || errno != ENOMSG) {
+        THROW_ERROR("msgrcv on an empty queue with IPC_NOWAIT should fail");
+    }
+    msgctl(msqid, IPC_RMID, NULL);
+    return 0;
+}
+
+int test_recv_by_type() {
+    int msqid = create_msg_queue();
+    struct msg_buf msg;
+    send_msg(msqid, 3, "three", 0);
+    send_msg(msqid, 1, "one", 0);
+    send_msg(msqid, 2, "two", 0);
+    send_msg(msqid, 5, "five", 0);
+
+    // The first message of the type
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 2, 0) < 0 || msg.mtype != 2) {
+        THROW_ERROR("msgrcv with a positive msgtyp failed");
+    }
+    // The first message not of the type
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 3, MSG_EXCEPT) < 0 || msg.mtype != 1) {
+        THROW_ERROR("msgrcv with MSG_EXCEPT failed");
+    }
+    // The message of the lowest type that is less than or equal to |msgtyp|
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, -4, 0) < 0 || msg.mtype != 3) {
+        THROW_ERROR("msgrcv with a negative msgtyp failed");
+    }
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, -4, IPC_NOWAIT) >= 0 || errno != ENOMSG) {
+        THROW_ERROR("msgrcv should find no message");
+    }
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 0, 0) < 0 || msg.mtype != 5) {
+        THROW_ERROR("msgrcv with a zero msgtyp failed");
+    }
+    msgctl(msqid, IPC_RMID, NULL);
+    return 0;
+}
+
+int test_msg_noerror() {
+    int msqid = create_msg_queue();
+    struct msg_buf msg;
+    send_msg(msqid, 1, "a long message", 0);
+    if (msgrcv(msqid, &msg, 4, 0, 0) >= 0 || errno != E2BIG) {
+        THROW_ERROR("msgrcv with a small buffer should fail");
+    }
+    // The message is still in the queue
+    ssize_t len = msgrcv(msqid, &msg, 4, 0, MSG_NOERROR);
+    if (len != 4 || strncmp(msg.mtext, "a lo", 4) != 0) {
+        THROW_ERROR("msgrcv with MSG_NOERROR should truncate the message");
+    }
+    msgctl(msqid, IPC_RMID, NULL);
+    return 0;
+}
+
+int test_ipc_stat_set() {
+    int msqid = create_msg_queue();
+    struct msqid_ds ds;
+    send_msg(msqid, 1, "hello", 0);
+    if (msgctl(msqid, IPC_STAT, &ds) < 0) {
+        THROW_ERROR("msgctl IPC_STAT failed");
+    }
+    if (ds.msg_qnum != 1 || ds.msg_lspid != getpid() || ds.msg_qbytes == 0) {
+        THROW_ERROR("msgctl IPC_STAT returns wrong values");
+    }
+
+    // Shrink the queue so that it is full
+    ds.msg_qbytes = strlen("hello") + 1;
+    if (msgctl(msqid, IPC_SET, &ds) < 0) {
+        THROW_ERROR("msgctl IPC_SET failed");
+    }
+    if (send_msg(msqid, 1, "hello", IPC_NOWAIT) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("msgsnd to a full queue with IPC_NOWAIT should fail");
+    }
+    msgctl(msqid, IPC_RMID, NULL);
+    return 0;
+}
+
+int test_blocking_recv() {
+    int msqid = create_msg_queue();
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, send_after_sleep, &msqid) < 0) {
+        THROW_ERROR("pthread_create failed");
+    }
+    struct msg_buf msg;
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 0, 0) < 0 || strcmp(msg.mtext, "hello") != 0) {
+        THROW_ERROR("blocking msgrcv failed");
+    }
+    pthread_join(thread, NULL);
+    msgctl(msqid, IPC_RMID, NULL);
+    return 0;
+}
+
+int test_rmid_wakes_waiters() {
+    int msqid = create_msg_queue();
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, remove_after_sleep, &msqid) < 0) {
+        THROW_ERROR("pthread_create failed");
+    }
+    struct msg_buf msg;
+    if (msgrcv(msqid, &msg, MAX_TEXT_LEN, 0, 0) >= 0 || errno != EIDRM) {
+        THROW_ERROR("msgrcv on a removed queue should fail with EIDRM");
+    }
+    pthread_join(thread, NULL);
+    return 0;
+}
+
+int test_invalid_arguments() {
+    int msqid = create_msg_queue();
+    if (send_msg(msqid, 0, "hello", 0) >= 0 || errno != EINVAL) {
+        THROW_ERROR("msgsnd with a non-positive mtype should fail");
+    }
+    msgctl(msqid, IPC_RMID, NULL);
+    if (send_msg(msqid, 1, "hello", 0) >= 0 || errno != EINVAL) {
+        THROW_ERROR("msgsnd to a removed queue should fail");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_msgget),
+    TEST_CASE(test_send_recv),
+    TEST_CASE(test_recv_by_type),
+    TEST_CASE(test_msg_noerror),
+    TEST_CASE(test_ipc_stat_set),
+    TEST_CASE(test_blocking_recv),
+    TEST_CASE(test_rmid_wakes_waiters),
+    TEST_CASE(test_invalid_arguments),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
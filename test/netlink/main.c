@@ -0,0 +1,308 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <linux/netlink.h>
+#include <linux/rtnetlink.h>
+#include <arpa/inet.h>
+#include <net/if.h>
+#include <ifaddrs.h>
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+#define RECV_BUF_SIZE   8192
+#define REQUEST_SEQ     1234
+
+struct link_request {
+    struct nlmsghdr hdr;
+    struct ifinfomsg ifi;
+};
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int create_netlink_socket(unsigned int *port_id) {
+    int fd = socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the netlink socket");
+    }
+
+    struct sockaddr_nl addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.nl_family = AF_NETLINK;
+    socklen_t addr_len = sizeof(addr);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+            getsockname(fd, (struct sockaddr *)&addr, &addr_len) < 0) {
+        close(fd);
+        THROW_ERROR("failed to bind the netlink socket");
+    }
+    if (addr.nl_family != AF_NETLINK || addr.nl_pid == 0) {
+        close(fd);
+        THROW_ERROR("the port ID is not assigned");
+    }
+    *port_id = addr.nl_pid;
+    return fd;
+}
+
+static int send_link_request(int fd, int flags, int index) {
+    struct link_request req;
+    memset(&req, 0, sizeof(req));
+    req.hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    req.hdr.nlmsg_type = RTM_GETLINK;
+    req.hdr.nlmsg_flags = NLM_F_REQUEST | flags;
+    req.hdr.nlmsg_seq = REQUEST_SEQ;
+    req.ifi.ifi_family = AF_UNSPEC;
+    req.ifi.ifi_index = index;
+
+    struct sockaddr_nl kernel_addr;
+    memset(&kernel_addr, 0, sizeof(kernel_addr));
+    kernel_addr.nl_family = AF_NETLINK;
+    if (sendto(fd, &req, req.hdr.nlmsg_len, 0, (struct sockaddr *)&kernel_addr,
+               sizeof(kernel_addr)) != req.hdr.nlmsg_len) {
+        THROW_ERROR("failed to send the request");
+    }
+    return 0;
+}
+
+static const char *link_name(struct nlmsghdr *hdr) {
+    struct ifinfomsg *ifi = NLMSG_DATA(hdr);
+    int attrs_len = IFLA_PAYLOAD(hdr);
+    for (struct rtattr *attr = IFLA_RTA(ifi); RTA_OK(attr, attrs_len);
+            attr = RTA_NEXT(attr, attrs_len)) {
+        if (attr->rta_type == IFLA_IFNAME) {
+            return RTA_DATA(attr);
+        }
+    }
+    return NULL;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_getifaddrs() {
+    struct ifaddrs *ifaddrs;
+    if (getifaddrs(&ifaddrs) < 0) {
+        THROW_ERROR("getifaddrs failed");
+    }
+
+    int has_loopback_addr = 0;
+    for (struct ifaddrs *ifa = ifaddrs; ifa != NULL; ifa = ifa->ifa_next) {
+        if (ifa->ifa_addr == NULL || ifa->ifa_addr->sa_family != AF_INET) {
+            continue;
+        }
+        struct sockaddr_in *addr = (struct sockaddr_in *)ifa->ifa_addr;
+        if (addr->sin_addr.s_addr == htonl(INADDR_LOOPBACK) &&
+                strcmp(ifa->ifa_name, "lo") == 0 && (ifa->ifa_flags & IFF_LOOPBACK)) {
+            has_loopback_addr = 1;
+        }
+    }
+    freeifaddrs(ifaddrs);
+
+    if (!has_loopback_addr) {
+        THROW_ERROR("the address of the loopback interface is not found");
+    }
+    return 0;
+}
+
+static int test_getifaddrs_ipv6() {
+    struct ifaddrs *ifaddrs;
+    if (getifaddrs(&ifaddrs) < 0) {
+        THROW_ERROR("getifaddrs failed");
+    }
+
+    // The host may have IPv6 disabled, so only the reported addresses are checked
+    int ret = 0;
+    for (struct ifaddrs *ifa = ifaddrs; ifa != NULL; ifa = ifa->ifa_next) {
+        if (ifa->ifa_addr == NULL || ifa->ifa_addr->sa_family != AF_INET6) {
+            continue;
+        }
+        struct sockaddr_in6 *addr = (struct sockaddr_in6 *)ifa->ifa_addr;
+        if (ifa->ifa_netmask == NULL || if_nametoindex(ifa->ifa_name) == 0) {
+            printf("the IPv6 address of %s is incomplete\n", ifa->ifa_name);
+            ret = -1;
+        }
+        if (IN6_IS_ADDR_LOOPBACK(&addr->sin6_addr) && !(ifa->ifa_flags & IFF_LOOPBACK)) {
+            printf("the IPv6 loopback address is not on the loopback interface\n");
+            ret = -1;
+        }
+    }
+    freeifaddrs(ifaddrs);
+    return ret;
+}
+
+static int test_dump_links() {
+    unsigned int port_id;
+    int fd = create_netlink_socket(&port_id);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the netlink socket");
+    }
+
+    int ret = -1;
+    if (send_link_request(fd, NLM_F_DUMP, 0) < 0) {
+        goto out;
+    }
+
+    int has_loopback = 0, is_done = 0;
+    char buf[RECV_BUF_SIZE];
+    while (!is_done) {
+        struct sockaddr_nl from;
+        socklen_t from_len = sizeof(from);
+        int len = recvfrom(fd, buf, sizeof(buf), MSG_DONTWAIT, (struct sockaddr *)&from,
+                           &from_len);
+        if (len <= 0) {
+            printf("failed to receive the answers\n");
+            goto out;
+        }
+        if (from.nl_family != AF_NETLINK || from.nl_pid != 0) {
+            printf("the answers are not from the kernel\n");
+            goto out;
+        }
+
+        for (struct nlmsghdr *hdr = (struct nlmsghdr *)buf; NLMSG_OK(hdr, len);
+                hdr = NLMSG_NEXT(hdr, len)) {
+            if (hdr->nlmsg_seq != REQUEST_SEQ || hdr->nlmsg_pid != port_id) {
+                printf("the answer is not to the request\n");
+                goto out;
+            }
+            if (hdr->nlmsg_type == NLMSG_DONE) {
+                is_done = 1;
+                break;
+            }
+            if (hdr->nlmsg_type != RTM_NEWLINK || !(hdr->nlmsg_flags & NLM_F_MULTI)) {
+                printf("the answer is not a link\n");
+                goto out;
+            }
+            const char *name = link_name(hdr);
+            if (name != NULL && strcmp(name, "lo") == 0) {
+                has_loopback = 1;
+            }
+        }
+    }
+    if (!has_loopback) {
+        printf("the loopback interface is not found\n");
+        goto out;
+    }
+
+    // No more answers
+    if (recv(fd, buf, sizeof(buf), MSG_DONTWAIT) >= 0 || errno != EAGAIN) {
+        printf("there are unexpected answers\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_get_link_by_index() {
+    unsigned int port_id;
+    int fd = create_netlink_socket(&port_id);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the netlink socket");
+    }
+
+    int ret = -1;
+    int index = if_nametoindex("lo");
+    if (index == 0) {
+        printf("failed to get the index of the loopback interface\n");
+        goto out;
+    }
+    if (send_link_request(fd, NLM_F_ACK, index) < 0) {
+        goto out;
+    }
+
+    // The link is followed by the acknowledgement
+    char buf[RECV_BUF_SIZE];
+    int len = recv(fd, buf, sizeof(buf), MSG_DONTWAIT);
+    struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+    if (!NLMSG_OK(hdr, len) || hdr->nlmsg_type != RTM_NEWLINK) {
+        printf("the link is not received\n");
+        goto out;
+    }
+    struct ifinfomsg *ifi = NLMSG_DATA(hdr);
+    const char *name = link_name(hdr);
+    if (ifi->ifi_index != index || name == NULL || strcmp(name, "lo") != 0) {
+        printf("the link is wrong\n");
+        goto out;
+    }
+
+    len = recv(fd, buf, sizeof(buf), MSG_DONTWAIT);
+    if (!NLMSG_OK(hdr, len) || hdr->nlmsg_type != NLMSG_ERROR ||
+            ((struct nlmsgerr *)NLMSG_DATA(hdr))->error != 0) {
+        printf("the acknowledgement is not received\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_get_link_of_invalid_index() {
+    unsigned int port_id;
+    int fd = create_netlink_socket(&port_id);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the netlink socket");
+    }
+
+    int ret = -1;
+    if (send_link_request(fd, 0, 0x7fffffff) < 0) {
+        goto out;
+    }
+
+    char buf[RECV_BUF_SIZE];
+    int len = recv(fd, buf, sizeof(buf), MSG_DONTWAIT);
+    struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+    if (!NLMSG_OK(hdr, len) || hdr->nlmsg_type != NLMSG_ERROR ||
+            ((struct nlmsgerr *)NLMSG_DATA(hdr))->error != -ENODEV) {
+        printf("the error is not received\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_talk_to_non_kernel() {
+    unsigned int port_id;
+    int fd = create_netlink_socket(&port_id);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the netlink socket");
+    }
+
+    int ret = -1;
+    struct sockaddr_nl addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.nl_family = AF_NETLINK;
+    addr.nl_pid = port_id;
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 ||
+            errno != ECONNREFUSED) {
+        printf("connected to a non-kernel port\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_getifaddrs),
+    TEST_CASE(test_getifaddrs_ipv6),
+    TEST_CASE(test_dump_links),
+    TEST_CASE(test_get_link_by_index),
+    TEST_CASE(test_get_link_of_invalid_index),
+    TEST_CASE(test_talk_to_non_kernel),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,383 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <spawn.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// The network policy in config.jq of this test:
+// 1. Sending UDP datagrams to 127.0.0.1:8898 is allowed;
+// 2. Any other egress to port 8898 is denied;
+// 3. Any egress to 192.0.2.0/24 (TEST-NET-1) is denied;
+// 4. Binding or accepting TCP connections on port 8899 is denied;
+// 5. Receiving UDP datagrams from 127.0.0.1:8903 is denied;
+// 6. Unix sockets on the paths with the prefix /tmp/network_policy_denied are denied.
+#define ALLOWED_UDP_PORT        8898
+#define DENIED_INGRESS_PORT     8899
+#define ALLOWED_INGRESS_PORT    8900
+#define UDP_SERVER_PORT         8902
+#define DENIED_UDP_SOURCE_PORT  8903
+#define DENIED_IP               "192.0.2.1"
+#define DENIED_UNIX_PATH        "/tmp/network_policy_denied.sock"
+#define ALLOWED_UNIX_PATH       "/tmp/network_policy_allowed.sock"
+#define UNIX_DIR_LINK           "/tmp/network_policy_link"
+#define MSG_DATA                "Hello, network policy!"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static void init_inet_addr(struct sockaddr_in *addr, const char *ip, int port) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sin_family = AF_INET;
+    addr->sin_port = htons(port);
+    inet_pton(AF_INET, ip, &addr->sin_addr);
+}
+
+static void init_unix_addr(struct sockaddr_un *addr, const char *path) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+    strncpy(addr->sun_path, path, sizeof(addr->sun_path) - 1);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_connect_denied() {
+    struct sockaddr_in addr;
+    int ret = -1;
+
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    init_inet_addr(&addr, DENIED_IP, 80);
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("the connection to the denied network is not denied\n");
+        goto out;
+    }
+    // The rule of the allowed UDP port doesn't apply to TCP
+    init_inet_addr(&addr, "127.0.0.1", ALLOWED_UDP_PORT);
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("the connection to the denied port is not denied\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_connect_short_addr_denied() {
+    struct sockaddr_in6 addr;
+    int ret = -1;
+
+    int fd = socket(AF_INET6, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    // An address shorter than sockaddr_in6, i.e., without the scope id, never reaches
+    // the host unchecked
+    memset(&addr, 0, sizeof(addr));
+    addr.sin6_family = AF_INET6;
+    addr.sin6_port = htons(80);
+    inet_pton(AF_INET6, "::ffff:" DENIED_IP, &addr.sin6_addr);
+    socklen_t short_len = offsetof(struct sockaddr_in6, sin6_scope_id);
+    if (connect(fd, (struct sockaddr *)&addr, short_len) == 0 ||
+            (errno != EPERM && errno != EINVAL)) {
+        printf("the connection by the short address is not denied\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_sendto_denied() {
+    struct sockaddr_in addr;
+    int ret = -1;
+
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    init_inet_addr(&addr, DENIED_IP, 80);
+    if (sendto(fd, MSG_DATA, strlen(MSG_DATA), 0, (struct sockaddr *)&addr,
+               sizeof(addr)) >= 0 || errno != EPERM) {
+        printf("the datagram to the denied network is not denied\n");
+        goto out;
+    }
+    // The first matched rule wins
+    init_inet_addr(&addr, "127.0.0.1", ALLOWED_UDP_PORT);
+    if (sendto(fd, MSG_DATA, strlen(MSG_DATA), 0, (struct sockaddr *)&addr,
+               sizeof(addr)) != strlen(MSG_DATA)) {
+        printf("the datagram to the allowed address is denied\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_sendmmsg_partially_denied() {
+    struct sockaddr_in allowed_addr, denied_addr;
+    struct iovec iov = { .iov_base = MSG_DATA, .iov_len = strlen(MSG_DATA) };
+    struct mmsghdr msgs[2];
+    int ret = -1;
+
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    init_inet_addr(&allowed_addr, "127.0.0.1", ALLOWED_UDP_PORT);
+    init_inet_addr(&denied_addr, DENIED_IP, 80);
+    memset(msgs, 0, sizeof(msgs));
+    for (int i = 0; i < 2; i++) {
+        msgs[i].msg_hdr.msg_iov = &iov;
+        msgs[i].msg_hdr.msg_iovlen = 1;
+        msgs[i].msg_hdr.msg_namelen = sizeof(struct sockaddr_in);
+    }
+    msgs[0].msg_hdr.msg_name = &allowed_addr;
+    msgs[1].msg_hdr.msg_name = &denied_addr;
+
+    // Only the messages before the denied one are sent
+    if (sendmmsg(fd, msgs, 2, 0) != 1) {
+        printf("the messages before the denied one are not sent\n");
+        goto out;
+    }
+    if (sendmmsg(fd, &msgs[1], 1, 0) >= 0 || errno != EPERM) {
+        printf("the denied message is not denied\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_bind_denied() {
+    struct sockaddr_in addr;
+    int ret = -1;
+
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    init_inet_addr(&addr, "0.0.0.0", DENIED_INGRESS_PORT);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("binding to the denied port is not denied\n");
+        goto out;
+    }
+    init_inet_addr(&addr, "0.0.0.0", ALLOWED_INGRESS_PORT);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        printf("binding to the allowed port is denied\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int test_datagram_from_denied_source_dropped() {
+    struct sockaddr_in server_addr, denied_addr, src_addr;
+    int server_fd = -1, denied_fd = -1, allowed_fd = -1;
+    char buf[64];
+    int ret = -1;
+
+    init_inet_addr(&server_addr, "127.0.0.1", UDP_SERVER_PORT);
+    // The wildcard address is not denied, while the source address 127.0.0.1 is
+    init_inet_addr(&denied_addr, "0.0.0.0", DENIED_UDP_SOURCE_PORT);
+    server_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    denied_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    allowed_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (server_fd < 0 || denied_fd < 0 || allowed_fd < 0 ||
+            bind(server_fd, (struct sockaddr *)&server_addr, sizeof(server_addr)) < 0 ||
+            bind(denied_fd, (struct sockaddr *)&denied_addr, sizeof(denied_addr)) < 0) {
+        printf("failed to create the sockets\n");
+        goto out;
+    }
+
+    // The datagrams from the denied source are dropped, either received or peeked
+    int flags[] = { 0, MSG_PEEK };
+    for (int i = 0; i < ARRAY_SIZE(flags); i++) {
+        if (sendto(denied_fd, MSG_DATA, strlen(MSG_DATA), 0, (struct sockaddr *)&server_addr,
+                   sizeof(server_addr)) != strlen(MSG_DATA) ||
+                sendto(allowed_fd, MSG_DATA, strlen(MSG_DATA), 0,
+                       (struct sockaddr *)&server_addr, sizeof(server_addr)) != strlen(MSG_DATA)) {
+            printf("failed to send the datagrams\n");
+            goto out;
+        }
+        socklen_t addr_len = sizeof(src_addr);
+        if (recvfrom(server_fd, buf, sizeof(buf), flags[i], (struct sockaddr *)&src_addr,
+                     &addr_len) != strlen(MSG_DATA) ||
+                ntohs(src_addr.sin_port) == DENIED_UDP_SOURCE_PORT) {
+            printf("the datagram from the denied source is not dropped\n");
+            goto out;
+        }
+        if (flags[i] == MSG_PEEK && recv(server_fd, buf, sizeof(buf), 0) != strlen(MSG_DATA)) {
+            printf("failed to receive the peeked datagram\n");
+            goto out;
+        }
+    }
+    ret = 0;
+out:
+    if (allowed_fd >= 0) {
+        close(allowed_fd);
+    }
+    if (denied_fd >= 0) {
+        close(denied_fd);
+    }
+    if (server_fd >= 0) {
+        close(server_fd);
+    }
+    return ret;
+}
+
+static int test_unix_denied() {
+    struct sockaddr_un addr;
+    int ret = -1;
+
+    int fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+
+    init_unix_addr(&addr, DENIED_UNIX_PATH);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("binding to the denied path is not denied\n");
+        goto out;
+    }
+    if (connect(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("connecting to the denied path is not denied\n");
+        goto out;
+    }
+    unlink(ALLOWED_UNIX_PATH);
+    init_unix_addr(&addr, ALLOWED_UNIX_PATH);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        printf("binding to the allowed path is denied\n");
+        goto out;
+    }
+    unlink(ALLOWED_UNIX_PATH);
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+static int bind_unix_denied(const char *path) {
+    struct sockaddr_un addr;
+    int ret = 0;
+
+    int fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the socket");
+    }
+    init_unix_addr(&addr, path);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) == 0 || errno != EPERM) {
+        printf("binding to %s is not denied\n", path);
+        unlink(path);
+        ret = -1;
+    }
+    close(fd);
+    return ret;
+}
+
+static int test_unix_path_bypass_denied() {
+    const char *paths[] = {
+        "//tmp/network_policy_denied.sock",
+        "/tmp/./network_policy_denied.sock",
+        "/tmp/../tmp/network_policy_denied.sock",
+        UNIX_DIR_LINK "/network_policy_denied.sock",
+    };
+    int ret = -1;
+
+    unlink(UNIX_DIR_LINK);
+    if (symlink("/tmp", UNIX_DIR_LINK) < 0) {
+        THROW_ERROR("failed to create the symlink");
+    }
+    for (int i = 0; i < ARRAY_SIZE(paths); i++) {
+        if (bind_unix_denied(paths[i]) < 0) {
+            goto out;
+        }
+    }
+
+    // A path relative to the cwd
+    if (chdir("/tmp") < 0) {
+        printf("failed to change the cwd\n");
+        goto out;
+    }
+    int res = bind_unix_denied("network_policy_denied.sock");
+    if (chdir("/") < 0 || res < 0) {
+        goto out;
+    }
+    ret = 0;
+out:
+    unlink(UNIX_DIR_LINK);
+    return ret;
+}
+
+static int test_unix_path_in_chroot_denied() {
+    char *child_argv[] = { "network_policy", "chroot_then_bind", NULL };
+    int pid, status;
+
+    if (posix_spawn(&pid, "/bin/network_policy", NULL, NULL, child_argv, NULL) < 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    if (waitpid(pid, &status, 0) < 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        THROW_ERROR("the path in the chroot jail is not denied");
+    }
+    return 0;
+}
+
+// The rules apply to the path in the mount tree, rather than the one in the jail
+static int child_chroot_then_bind() {
+    if (chroot("/tmp") < 0) {
+        THROW_ERROR("failed to chroot");
+    }
+    return bind_unix_denied("/network_policy_denied.sock");
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_connect_denied),
+    TEST_CASE(test_connect_short_addr_denied),
+    TEST_CASE(test_sendto_denied),
+    TEST_CASE(test_sendmmsg_partially_denied),
+    TEST_CASE(test_bind_denied),
+    TEST_CASE(test_datagram_from_denied_source_dropped),
+    TEST_CASE(test_unix_denied),
+    TEST_CASE(test_unix_path_bypass_denied),
+    TEST_CASE(test_unix_path_in_chroot_denied),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        if (strcmp(argv[1], "chroot_then_bind") == 0) {
+            return child_chroot_then_bind() < 0;
+        }
+        return 1;
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,411 @@
+#define _GNU_SOURCE
+#include <sys/syscall.h>
+#include <signal.h>
+#include <stddef.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+#ifndef SIGEV_THREAD_ID
+#define SIGEV_THREAD_ID 4
+#endif
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+// The field name of the target thread id in struct sigevent differs between
+// libc implementations. But it is always the first int after sigev_notify.
+static void set_sigev_thread_id(struct sigevent *sev, pid_t tid) {
+    char *ptr = (char *)sev + offsetof(struct sigevent, sigev_notify) + sizeof(int);
+    *(pid_t *)ptr = tid;
+}
+
+static int block_signal(int signum) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    return sigprocmask(SIG_BLOCK, &set, NULL);
+}
+
+static int unblock_signal(int signum) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    return sigprocmask(SIG_UNBLOCK, &set, NULL);
+}
+
+static int wait_signal(int signum, siginfo_t *info) {
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, signum);
+    struct timespec timeout = { .tv_sec = 3, .tv_nsec = 0 };
+    return sigtimedwait(&set, info, &timeout);
+}
+
+// ============================================================================
+// Test cases for POSIX timers
+// ============================================================================
+
+int test_timer_signal() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGUSR1;
+    sev.sigev_value.sival_int = 0xabcd;
+
+    if (block_signal(SIGUSR1) < 0) {
+        THROW_ERROR("failed to block SIGUSR1");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { 0, 100 * 1000 * 1000 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    siginfo_t info;
+    if (wait_signal(SIGUSR1, &info) != SIGUSR1) {
+        THROW_ERROR("failed to wait for the timer signal");
+    }
+    if (info.si_code != SI_TIMER || info.si_value.sival_int != 0xabcd) {
+        THROW_ERROR("unexpected siginfo of the timer signal");
+    }
+
+    // A one-shot timer is disarmed after expiration
+    struct itimerspec curr;
+    if (timer_gettime(timerid, &curr) < 0) {
+        THROW_ERROR("timer_gettime failed");
+    }
+    if (curr.it_value.tv_sec != 0 || curr.it_value.tv_nsec != 0) {
+        THROW_ERROR("the expired timer is still armed");
+    }
+
+    if (timer_delete(timerid) < 0) {
+        THROW_ERROR("timer_delete failed");
+    }
+    unblock_signal(SIGUSR1);
+    return 0;
+}
+
+int test_timer_gettime() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_NONE;
+
+    if (timer_create(CLOCK_REALTIME, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    struct itimerspec its = {
+        .it_interval = { 1, 0 },
+        .it_value = { 10, 0 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    struct itimerspec curr;
+    if (timer_gettime(timerid, &curr) < 0) {
+        THROW_ERROR("timer_gettime failed");
+    }
+    if (curr.it_value.tv_sec > 10 || curr.it_value.tv_sec < 9 ||
+            curr.it_interval.tv_sec != 1 || curr.it_interval.tv_nsec != 0) {
+        THROW_ERROR("unexpected value from timer_gettime");
+    }
+
+    // Disarm the timer and get the old value
+    struct itimerspec disarm = { 0 };
+    struct itimerspec old;
+    if (timer_settime(timerid, 0, &disarm, &old) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+    if (old.it_value.tv_sec > 10 || old.it_value.tv_sec < 9) {
+        THROW_ERROR("unexpected old value from timer_settime");
+    }
+    if (timer_gettime(timerid, &curr) < 0) {
+        THROW_ERROR("timer_gettime failed");
+    }
+    if (curr.it_value.tv_sec != 0 || curr.it_value.tv_nsec != 0) {
+        THROW_ERROR("the disarmed timer is still armed");
+    }
+
+    timer_delete(timerid);
+    return 0;
+}
+
+int test_timer_abstime() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGUSR2;
+
+    if (block_signal(SIGUSR2) < 0) {
+        THROW_ERROR("failed to block SIGUSR2");
+    }
+    if (timer_create(CLOCK_REALTIME, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    struct timespec now;
+    clock_gettime(CLOCK_REALTIME, &now);
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { now.tv_sec + 1, now.tv_nsec },
+    };
+    if (timer_settime(timerid, TIMER_ABSTIME, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    siginfo_t info;
+    if (wait_signal(SIGUSR2, &info) != SIGUSR2) {
+        THROW_ERROR("failed to wait for the timer signal");
+    }
+    struct timespec end;
+    clock_gettime(CLOCK_REALTIME, &end);
+    if (end.tv_sec < its.it_value.tv_sec ||
+            (end.tv_sec == its.it_value.tv_sec && end.tv_nsec < its.it_value.tv_nsec)) {
+        THROW_ERROR("the timer expired too early");
+    }
+
+    timer_delete(timerid);
+    unblock_signal(SIGUSR2);
+    return 0;
+}
+
+int test_timer_overrun() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGRTMIN + 1;
+
+    if (block_signal(SIGRTMIN + 1) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    struct itimerspec its = {
+        .it_interval = { 0, 10 * 1000 * 1000 },
+        .it_value = { 0, 10 * 1000 * 1000 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    // Let the timer expire many times while its signal is blocked
+    struct timespec duration = { .tv_sec = 0, .tv_nsec = 300 * 1000 * 1000 };
+    nanosleep(&duration, NULL);
+
+    siginfo_t info;
+    if (wait_signal(SIGRTMIN + 1, &info) != SIGRTMIN + 1) {
+        THROW_ERROR("failed to wait for the timer signal");
+    }
+    int overrun = timer_getoverrun(timerid);
+    if (overrun <= 0) {
+        THROW_ERROR("expect some overruns, but got %d", overrun);
+    }
+
+    timer_delete(timerid);
+    // Only one signal is queued for the timer
+    struct timespec zero = { 0 };
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGRTMIN + 1);
+    sigtimedwait(&set, &info, &zero);
+    unblock_signal(SIGRTMIN + 1);
+    return 0;
+}
+
+int test_timer_thread_id() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_THREAD_ID;
+    sev.sigev_signo = SIGUSR1;
+    set_sigev_thread_id(&sev, syscall(SYS_gettid));
+
+    if (block_signal(SIGUSR1) < 0) {
+        THROW_ERROR("failed to block SIGUSR1");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { 0, 50 * 1000 * 1000 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    siginfo_t info;
+    if (wait_signal(SIGUSR1, &info) != SIGUSR1) {
+        THROW_ERROR("failed to wait for the timer signal");
+    }
+    if (info.si_code != SI_TIMER) {
+        THROW_ERROR("unexpected siginfo of the timer signal");
+    }
+
+    timer_delete(timerid);
+    unblock_signal(SIGUSR1);
+    return 0;
+}
+
+int test_timer_rearm() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGUSR2;
+
+    if (block_signal(SIGUSR2) < 0) {
+        THROW_ERROR("failed to block SIGUSR2");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+
+    // Re-arm the timer again and again like a watchdog, which must not
+    // leave the stale settings in effect
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { 10, 0 },
+    };
+    for (int i = 0; i < 1000; i++) {
+        if (timer_settime(timerid, 0, &its, NULL) < 0) {
+            THROW_ERROR("timer_settime failed");
+        }
+    }
+    its.it_value.tv_sec = 0;
+    its.it_value.tv_nsec = 50 * 1000 * 1000;
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    siginfo_t info;
+    if (wait_signal(SIGUSR2, &info) != SIGUSR2) {
+        THROW_ERROR("failed to wait for the timer signal");
+    }
+    struct itimerspec curr;
+    if (timer_gettime(timerid, &curr) < 0) {
+        THROW_ERROR("timer_gettime failed");
+    }
+    if (curr.it_value.tv_sec != 0 || curr.it_value.tv_nsec != 0) {
+        THROW_ERROR("the timer should be disarmed after firing");
+    }
+
+    timer_delete(timerid);
+    unblock_signal(SIGUSR2);
+    return 0;
+}
+
+static long elapsed_ms(clockid_t clockid, const struct timespec *start) {
+    struct timespec now;
+    clock_gettime(clockid, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+int test_timer_cpu_time() {
+    timer_t timerid;
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGUSR2;
+
+    if (block_signal(SIGUSR2) < 0) {
+        THROW_ERROR("failed to block SIGUSR2");
+    }
+    if (timer_create(CLOCK_PROCESS_CPUTIME_ID, &sev, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+    struct timespec cpu_start, real_start;
+    if (clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &cpu_start) < 0 ||
+            clock_gettime(CLOCK_MONOTONIC, &real_start) < 0) {
+        THROW_ERROR("clock_gettime failed");
+    }
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { 0, 50 * 1000 * 1000 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) < 0) {
+        THROW_ERROR("timer_settime failed");
+    }
+
+    // Burn the CPU until the timer expires
+    sigset_t pending;
+    do {
+        sigpending(&pending);
+    } while (!sigismember(&pending, SIGUSR2) && elapsed_ms(CLOCK_MONOTONIC, &real_start) < 3000);
+    if (!sigismember(&pending, SIGUSR2)) {
+        THROW_ERROR("the CPU-time timer does not expire");
+    }
+    if (elapsed_ms(CLOCK_PROCESS_CPUTIME_ID, &cpu_start) < 50) {
+        THROW_ERROR("the CPU-time timer expires too early");
+    }
+
+    siginfo_t info;
+    if (wait_signal(SIGUSR2, &info) != SIGUSR2 || info.si_code != SI_TIMER) {
+        THROW_ERROR("failed to consume the timer signal");
+    }
+    if (timer_delete(timerid) < 0) {
+        THROW_ERROR("timer_delete failed");
+    }
+    unblock_signal(SIGUSR2);
+    return 0;
+}
+
+int test_invalid_arguments() {
+    timer_t timerid;
+    if (timer_create(CLOCK_THREAD_CPUTIME_ID + 100, NULL, &timerid) == 0 || errno != EINVAL) {
+        THROW_ERROR("timer_create with an invalid clockid should fail");
+    }
+
+    struct sigevent sev = { 0 };
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = 256 + SIGHUP;
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timerid) == 0 || errno != EINVAL) {
+        THROW_ERROR("timer_create with an out-of-range signal should fail");
+    }
+
+    if (timer_create(CLOCK_MONOTONIC, NULL, &timerid) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+    struct itimerspec its = {
+        .it_interval = { 0, 0 },
+        .it_value = { 0, 1000 * 1000 * 1000 },
+    };
+    if (timer_settime(timerid, 0, &its, NULL) == 0 || errno != EINVAL) {
+        THROW_ERROR("timer_settime with an invalid value should fail");
+    }
+    if (timer_delete(timerid) < 0) {
+        THROW_ERROR("timer_delete failed");
+    }
+    if (syscall(SYS_timer_delete, 1000) == 0 || errno != EINVAL) {
+        THROW_ERROR("timer_delete with an invalid timer should fail");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_timer_signal),
+    TEST_CASE(test_timer_gettime),
+    TEST_CASE(test_timer_abstime),
+    TEST_CASE(test_timer_overrun),
+    TEST_CASE(test_timer_thread_id),
+    TEST_CASE(test_timer_rearm),
+    TEST_CASE(test_timer_cpu_time),
+    TEST_CASE(test_invalid_arguments),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -1,8 +1,12 @@
 #define _GNU_SOURCE
 #include <sys/types.h>
 #include <sys/vfs.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/utsname.h>
 #include <fcntl.h>
 #include <limits.h>
+#include <pthread.h>
 #include <stdlib.h>
 #include <errno.h>
 #include "test_fs.h"
@@ -48,6 +52,28 @@ static int test_read_from_procfs(const char *proc_inode) {
     return 0;
 }
 
+static int read_whole_procfs_file(const char *proc_inode, char *buf, int buf_size) {
+    int fd = open(proc_inode, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open file: %s", proc_inode);
+    }
+    int len = 0;
+    while (len < buf_size - 1) {
+        int n = read(fd, buf + len, buf_size - 1 - len);
+        if (n < 0) {
+            close(fd);
+            THROW_ERROR("failed to read: %s", proc_inode);
+        }
+        if (n == 0) {
+            break;
+        }
+        len += n;
+    }
+    buf[len] = '\0';
+    close(fd);
+    return len;
+}
+
 // ============================================================================
 // Test cases for procfs
 // ============================================================================
@@ -188,6 +214,214 @@ static int test_read_from_proc_self_stat() {
     return 0;
 }
 
+static int test_read_from_proc_self_status() {
+    char buf[4096];
+    char expected_pid[64] = { 0 };
+
+    if (read_whole_procfs_file("/proc/self/status", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/self/status");
+    }
+    printf("test read from /proc/self/status:\n%s\n", buf);
+    snprintf(expected_pid, sizeof(expected_pid), "\nPid:\t%d\n", getpid());
+    if (strstr(buf, expected_pid) == NULL) {
+        THROW_ERROR("failed to check the pid");
+    }
+    if (strstr(buf, "\nThreads:\t1\n") == NULL || strstr(buf, "\nVmRSS:") == NULL) {
+        THROW_ERROR("failed to check the status");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_statm() {
+    char buf[256];
+    unsigned long size, resident, shared, text, lib, data, dt;
+
+    if (read_whole_procfs_file("/proc/self/statm", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/self/statm");
+    }
+    int ret = sscanf(buf, "%lu %lu %lu %lu %lu %lu %lu", &size, &resident, &shared, &text,
+                     &lib, &data, &dt);
+    if (ret != 7) {
+        THROW_ERROR("failed to parse /proc/self/statm");
+    }
+    if (size == 0 || resident > size || text == 0 || data == 0) {
+        THROW_ERROR("failed to check the memory usage");
+    }
+    return 0;
+}
+
+extern char **environ;
+
+static int test_read_from_proc_self_environ() {
+    char buf[4096];
+
+    int len = read_whole_procfs_file("/proc/self/environ", buf, sizeof(buf));
+    if (len < 0) {
+        THROW_ERROR("failed to read /proc/self/environ");
+    }
+    // The environment has not been changed, so it is the same with the initial one
+    int offset = 0;
+    for (char **env = environ; *env != NULL; env++) {
+        if (offset >= len || strcmp(buf + offset, *env) != 0) {
+            THROW_ERROR("failed to check the environment variable: %s", *env);
+        }
+        offset += strlen(*env) + 1;
+    }
+    if (offset != len) {
+        THROW_ERROR("failed to check the length of /proc/self/environ");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_limits() {
+    char buf[4096];
+    struct rlimit rlim;
+
+    if (read_whole_procfs_file("/proc/self/limits", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/self/limits");
+    }
+    printf("test read from /proc/self/limits:\n%s\n", buf);
+    if (strncmp(buf, "Limit", strlen("Limit")) != 0) {
+        THROW_ERROR("failed to check the header");
+    }
+    if (getrlimit(RLIMIT_NOFILE, &rlim) < 0) {
+        THROW_ERROR("failed to getrlimit");
+    }
+    char *line = strstr(buf, "Max open files");
+    unsigned long soft_limit;
+    if (line == NULL || sscanf(line, "Max open files %lu", &soft_limit) != 1) {
+        THROW_ERROR("failed to find the limit of open files");
+    }
+    if (soft_limit != rlim.rlim_cur) {
+        THROW_ERROR("failed to check the limit of open files");
+    }
+    return 0;
+}
+
+static int read_proc_self_io(unsigned long *wchar, unsigned long *syscw) {
+    char buf[1024];
+
+    if (read_whole_procfs_file("/proc/self/io", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/self/io");
+    }
+    char *line = strstr(buf, "wchar: ");
+    if (line == NULL || sscanf(line, "wchar: %lu", wchar) != 1) {
+        THROW_ERROR("failed to parse wchar");
+    }
+    line = strstr(buf, "syscw: ");
+    if (line == NULL || sscanf(line, "syscw: %lu", syscw) != 1) {
+        THROW_ERROR("failed to parse syscw");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_io() {
+    unsigned long wchar_before, syscw_before, wchar_after, syscw_after;
+    char data[100] = { 0 };
+
+    if (read_proc_self_io(&wchar_before, &syscw_before) < 0) {
+        THROW_ERROR("failed to read the io statistics");
+    }
+    int fd = open("/dev/null", O_WRONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open /dev/null");
+    }
+    if (write(fd, data, sizeof(data)) != sizeof(data)) {
+        close(fd);
+        THROW_ERROR("failed to write /dev/null");
+    }
+    close(fd);
+    if (read_proc_self_io(&wchar_after, &syscw_after) < 0) {
+        THROW_ERROR("failed to read the io statistics");
+    }
+    if (wchar_after - wchar_before != sizeof(data) || syscw_after - syscw_before != 1) {
+        THROW_ERROR("failed to check the io statistics");
+    }
+    return 0;
+}
+
+struct thread_info {
+    pid_t tid;
+    pthread_barrier_t started;
+    pthread_barrier_t checked;
+};
+
+static void *thread_func(void *arg) {
+    struct thread_info *info = (struct thread_info *)arg;
+    info->tid = syscall(SYS_gettid);
+    pthread_barrier_wait(&info->started);
+    pthread_barrier_wait(&info->checked);
+    return NULL;
+}
+
+static int test_read_and_write_proc_self_task() {
+    struct thread_info info;
+    pthread_t thread;
+    char path[PATH_MAX] = { 0 };
+    char buf[1024];
+    char name[16] = { 0 };
+    int ret = 0;
+
+    pthread_barrier_init(&info.started, NULL, 2);
+    pthread_barrier_init(&info.checked, NULL, 2);
+    if (pthread_create(&thread, NULL, thread_func, &info) != 0) {
+        THROW_ERROR("failed to create the thread");
+    }
+    pthread_barrier_wait(&info.started);
+
+    char expected_entries[2][NAME_MAX] = { 0 };
+    snprintf(expected_entries[0], NAME_MAX, "%d", getpid());
+    snprintf(expected_entries[1], NAME_MAX, "%d", info.tid);
+    if (check_readdir_with_expected_entries("/proc/self/task", expected_entries, 2) < 0) {
+        ret = -1;
+        printf("failed to readdir /proc/self/task\n");
+        goto out;
+    }
+
+    snprintf(path, sizeof(path), "/proc/self/task/%d/stat", info.tid);
+    int tid = 0;
+    if (read_whole_procfs_file(path, buf, sizeof(buf)) < 0 || sscanf(buf, "%d", &tid) != 1 ||
+            tid != info.tid) {
+        ret = -1;
+        printf("failed to check %s\n", path);
+        goto out;
+    }
+
+    // Set the name of the thread through the comm file
+    snprintf(path, sizeof(path), "/proc/self/task/%d/comm", info.tid);
+    int fd = open(path, O_WRONLY | O_TRUNC);
+    if (fd < 0) {
+        ret = -1;
+        printf("failed to open %s to write\n", path);
+        goto out;
+    }
+    const char *new_name = "worker\n";
+    if (write(fd, new_name, strlen(new_name)) != strlen(new_name)) {
+        close(fd);
+        ret = -1;
+        printf("failed to write %s\n", path);
+        goto out;
+    }
+    close(fd);
+    if (pthread_getname_np(thread, name, sizeof(name)) != 0 || strcmp(name, "worker") != 0) {
+        ret = -1;
+        printf("failed to check the name of the thread\n");
+        goto out;
+    }
+    if (fs_check_file_content(path, "worker\n") < 0) {
+        ret = -1;
+        printf("failed to check the content of %s\n", path);
+        goto out;
+    }
+
+out:
+    pthread_barrier_wait(&info.checked);
+    pthread_join(thread, NULL);
+    pthread_barrier_destroy(&info.started);
+    pthread_barrier_destroy(&info.checked);
+    return ret;
+}
+
 static int test_read_from_proc_meminfo() {
     const char *proc_meminfo = "/proc/meminfo";
 
@@ -215,6 +449,150 @@ static int test_read_from_proc_stat() {
     return 0;
 }
 
+static int test_read_from_proc_mounts() {
+    char buf[4096];
+    char link_buf[PATH_MAX] = { 0 };
+
+    if (test_readlink_from_procfs("/proc/mounts", link_buf, sizeof(link_buf),
+                                  "self/mounts") < 0) {
+        THROW_ERROR("failed to readlink /proc/mounts");
+    }
+    if (read_whole_procfs_file("/proc/mounts", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/mounts");
+    }
+    printf("test read from /proc/mounts:\n%s\n", buf);
+    if (strstr(buf, "unionfs / unionfs rw 0 0\n") == NULL) {
+        THROW_ERROR("failed to find the root mount");
+    }
+    if (strstr(buf, " /proc procfs ") == NULL) {
+        THROW_ERROR("failed to find the procfs mount");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_mountinfo() {
+    char buf[4096];
+    int mount_id, parent_id, major, minor;
+    char root[PATH_MAX] = { 0 };
+    char target[PATH_MAX] = { 0 };
+
+    if (read_whole_procfs_file("/proc/self/mountinfo", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/self/mountinfo");
+    }
+    printf("test read from /proc/self/mountinfo:\n%s\n", buf);
+    // The first line is the root mount, which is the parent of itself
+    int ret = sscanf(buf, "%d %d %d:%d %s %s", &mount_id, &parent_id, &major, &minor,
+                     root, target);
+    if (ret != 6) {
+        THROW_ERROR("failed to parse the first line");
+    }
+    if (mount_id != parent_id || strcmp(root, "/") != 0 || strcmp(target, "/") != 0) {
+        THROW_ERROR("failed to check the root mount");
+    }
+    if (strstr(buf, " - procfs procfs rw\n") == NULL) {
+        THROW_ERROR("failed to find the procfs mount");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_filesystems() {
+    char buf[1024];
+
+    if (read_whole_procfs_file("/proc/filesystems", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/filesystems");
+    }
+    if (strstr(buf, "nodev\tprocfs\n") == NULL || strstr(buf, "\text2\n") == NULL) {
+        THROW_ERROR("failed to check the result in /proc/filesystems");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_uptime() {
+    char buf[128];
+    double uptime, idle;
+
+    if (read_whole_procfs_file("/proc/uptime", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/uptime");
+    }
+    if (sscanf(buf, "%lf %lf", &uptime, &idle) != 2 || uptime <= 0) {
+        THROW_ERROR("failed to check the uptime");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_loadavg() {
+    char buf[128];
+    double loads[3];
+    int runnable, total, last_pid;
+
+    if (read_whole_procfs_file("/proc/loadavg", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/loadavg");
+    }
+    int ret = sscanf(buf, "%lf %lf %lf %d/%d %d", &loads[0], &loads[1], &loads[2],
+                     &runnable, &total, &last_pid);
+    if (ret != 6 || runnable < 1 || runnable > total || last_pid < getpid()) {
+        THROW_ERROR("failed to check the loadavg");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_version() {
+    char buf[256];
+    struct utsname name;
+
+    if (read_whole_procfs_file("/proc/version", buf, sizeof(buf)) < 0) {
+        THROW_ERROR("failed to read /proc/version");
+    }
+    if (uname(&name) < 0) {
+        THROW_ERROR("failed to call uname");
+    }
+    if (strncmp(buf, name.sysname, strlen(name.sysname)) != 0 ||
+            strstr(buf, name.release) == NULL) {
+        THROW_ERROR("failed to check the version");
+    }
+    return 0;
+}
+
+static int write_proc_file(const char *path, const char *value) {
+    int fd = open(path, O_WRONLY);
+    if (fd < 0) {
+        return -1;
+    }
+    int ret = write(fd, value, strlen(value));
+    close(fd);
+    return ret < 0 ? -1 : 0;
+}
+
+static int test_read_and_write_proc_sys() {
+    char old_value[64];
+    const char *pid_max = "/proc/sys/kernel/pid_max";
+
+    if (fs_check_file_content("/proc/sys/vm/overcommit_memory", "0\n") < 0) {
+        THROW_ERROR("failed to check overcommit_memory");
+    }
+    if (read_whole_procfs_file(pid_max, old_value, sizeof(old_value)) < 0) {
+        THROW_ERROR("failed to read %s", pid_max);
+    }
+    if (write_proc_file(pid_max, "65536\n") < 0) {
+        THROW_ERROR("failed to write %s", pid_max);
+    }
+    if (fs_check_file_content(pid_max, "65536\n") < 0) {
+        THROW_ERROR("failed to check %s", pid_max);
+    }
+    // The value is out of range
+    if (write_proc_file(pid_max, "1") == 0 || errno != EINVAL) {
+        THROW_ERROR("failed to check writing an invalid value");
+    }
+    if (write_proc_file(pid_max, old_value) < 0) {
+        THROW_ERROR("failed to restore %s", pid_max);
+    }
+    // A read-only parameter
+    if (write_proc_file("/proc/sys/kernel/ostype", "Linux") == 0) {
+        THROW_ERROR("failed to check writing a read-only parameter");
+    }
+    return 0;
+}
+
 #define PROC_SUPER_MAGIC 0x9fa0
 static int test_statfs() {
     const char *file_path = "/proc/cpuinfo";
@@ -294,9 +672,22 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_read_from_proc_self_cmdline),
     TEST_CASE(test_read_from_proc_self_comm),
     TEST_CASE(test_read_from_proc_self_stat),
+    TEST_CASE(test_read_from_proc_self_status),
+    TEST_CASE(test_read_from_proc_self_statm),
+    TEST_CASE(test_read_from_proc_self_environ),
+    TEST_CASE(test_read_from_proc_self_limits),
+    TEST_CASE(test_read_from_proc_self_io),
+    TEST_CASE(test_read_and_write_proc_self_task),
     TEST_CASE(test_read_from_proc_meminfo),
     TEST_CASE(test_read_from_proc_cpuinfo),
     TEST_CASE(test_read_from_proc_stat),
+    TEST_CASE(test_read_from_proc_mounts),
+    TEST_CASE(test_read_from_proc_self_mountinfo),
+    TEST_CASE(test_read_from_proc_filesystems),
+    TEST_CASE(test_read_from_proc_uptime),
+    TEST_CASE(test_read_from_proc_loadavg),
+    TEST_CASE(test_read_from_proc_version),
+    TEST_CASE(test_read_and_write_proc_sys),
     TEST_CASE(test_statfs),
     TEST_CASE(test_readdir_root),
     TEST_CASE(test_readdir_self),
@@ -0,0 +1,284 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+#define UDP_PORT            8821
+#define NUM_MSGS            4
+#define MSG_BUF_SIZE        64
+
+// The receiving socket is bound to the wildcard address, so that it is never taken over
+// by a loopback socket, and is a uring socket as io_uring is enabled by config.jq
+struct udp_pair {
+    int recv_fd;
+    int send_fd;
+    // The address that the messages are sent to
+    struct sockaddr_in recv_addr;
+};
+
+struct msg_vec {
+    struct mmsghdr msgs[NUM_MSGS];
+    struct iovec iovs[NUM_MSGS];
+    struct sockaddr_in addrs[NUM_MSGS];
+    char bufs[NUM_MSGS][MSG_BUF_SIZE];
+};
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int create_udp_pair(struct udp_pair *pair) {
+    memset(&pair->recv_addr, 0, sizeof(pair->recv_addr));
+    pair->recv_addr.sin_family = AF_INET;
+    pair->recv_addr.sin_port = htons(UDP_PORT);
+    pair->recv_addr.sin_addr.s_addr = htonl(INADDR_ANY);
+
+    pair->recv_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (pair->recv_fd < 0) {
+        THROW_ERROR("failed to create the receiving socket");
+    }
+    if (bind(pair->recv_fd, (struct sockaddr *)&pair->recv_addr,
+             sizeof(pair->recv_addr)) < 0) {
+        close(pair->recv_fd);
+        THROW_ERROR("failed to bind the receiving socket");
+    }
+    pair->recv_addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+    pair->send_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (pair->send_fd < 0) {
+        close(pair->recv_fd);
+        THROW_ERROR("failed to create the sending socket");
+    }
+    return 0;
+}
+
+static void close_udp_pair(struct udp_pair *pair) {
+    close(pair->recv_fd);
+    close(pair->send_fd);
+}
+
+static int send_msgs(struct udp_pair *pair, int num_msgs) {
+    for (int i = 0; i < num_msgs; i++) {
+        char msg[MSG_BUF_SIZE];
+        snprintf(msg, sizeof(msg), "message %d", i);
+        if (sendto(pair->send_fd, msg, strlen(msg), 0, (struct sockaddr *)&pair->recv_addr,
+                   sizeof(pair->recv_addr)) != strlen(msg)) {
+            THROW_ERROR("failed to send the message");
+        }
+    }
+    return 0;
+}
+
+static void init_msg_vec(struct msg_vec *vec, size_t buf_size) {
+    memset(vec, 0, sizeof(*vec));
+    for (int i = 0; i < NUM_MSGS; i++) {
+        vec->iovs[i].iov_base = vec->bufs[i];
+        vec->iovs[i].iov_len = buf_size;
+        vec->msgs[i].msg_hdr.msg_iov = &vec->iovs[i];
+        vec->msgs[i].msg_hdr.msg_iovlen = 1;
+        vec->msgs[i].msg_hdr.msg_name = &vec->addrs[i];
+        vec->msgs[i].msg_hdr.msg_namelen = sizeof(vec->addrs[i]);
+    }
+}
+
+// Check the messages received are the first num_msgs ones sent
+static int check_msgs(struct msg_vec *vec, int num_msgs) {
+    for (int i = 0; i < num_msgs; i++) {
+        char expected[MSG_BUF_SIZE];
+        snprintf(expected, sizeof(expected), "message %d", i);
+        if (vec->msgs[i].msg_len != strlen(expected) ||
+                strncmp(vec->bufs[i], expected, vec->msgs[i].msg_len) != 0) {
+            THROW_ERROR("the message %d is wrong", i);
+        }
+        if (vec->msgs[i].msg_hdr.msg_namelen != sizeof(struct sockaddr_in) ||
+                vec->addrs[i].sin_family != AF_INET ||
+                vec->addrs[i].sin_addr.s_addr != htonl(INADDR_LOOPBACK)) {
+            THROW_ERROR("the source address of the message %d is wrong", i);
+        }
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_recvmmsg() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    struct msg_vec vec;
+    init_msg_vec(&vec, MSG_BUF_SIZE);
+    if (send_msgs(&pair, NUM_MSGS) < 0) {
+        goto out;
+    }
+    // Block until all the messages are received
+    if (recvmmsg(pair.recv_fd, vec.msgs, NUM_MSGS, 0, NULL) != NUM_MSGS) {
+        printf("failed to receive all the messages\n");
+        goto out;
+    }
+    ret = check_msgs(&vec, NUM_MSGS);
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+static int test_recvmmsg_with_waitforone() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    struct msg_vec vec;
+    init_msg_vec(&vec, MSG_BUF_SIZE);
+    if (send_msgs(&pair, 2) < 0) {
+        goto out;
+    }
+    // Wait for the messages to arrive
+    usleep(100 * 1000);
+    // Only the messages that are present are received
+    if (recvmmsg(pair.recv_fd, vec.msgs, NUM_MSGS, MSG_WAITFORONE, NULL) != 2) {
+        printf("failed to receive the present messages\n");
+        goto out;
+    }
+    ret = check_msgs(&vec, 2);
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+static int test_recvmmsg_with_timeout() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    struct msg_vec vec;
+    init_msg_vec(&vec, MSG_BUF_SIZE);
+    if (send_msgs(&pair, 1) < 0) {
+        goto out;
+    }
+    // The timeout expires once the first message is received
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 0 };
+    if (recvmmsg(pair.recv_fd, vec.msgs, NUM_MSGS, 0, &timeout) != 1) {
+        printf("failed to return after the timeout\n");
+        goto out;
+    }
+    ret = check_msgs(&vec, 1);
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+static int test_recvmmsg_nonblocking() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    struct msg_vec vec;
+    init_msg_vec(&vec, MSG_BUF_SIZE);
+    if (recvmmsg(pair.recv_fd, vec.msgs, NUM_MSGS, MSG_DONTWAIT, NULL) >= 0 ||
+            errno != EAGAIN) {
+        printf("failed to return EAGAIN without messages\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+static int test_recvmmsg_truncated() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    size_t buf_size = 4;
+    struct msg_vec vec;
+    init_msg_vec(&vec, buf_size);
+    if (send_msgs(&pair, 2) < 0) {
+        goto out;
+    }
+    if (recvmmsg(pair.recv_fd, vec.msgs, 2, 0, NULL) != 2) {
+        printf("failed to receive the messages\n");
+        goto out;
+    }
+    for (int i = 0; i < 2; i++) {
+        if (vec.msgs[i].msg_len != buf_size ||
+                !(vec.msgs[i].msg_hdr.msg_flags & MSG_TRUNC) ||
+                strncmp(vec.bufs[i], "mess", buf_size) != 0) {
+            printf("the message %d is not truncated\n", i);
+            goto out;
+        }
+    }
+    ret = 0;
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+static int test_recvmmsg_error_reported_later() {
+    struct udp_pair pair;
+    if (create_udp_pair(&pair) < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    int ret = -1;
+    struct msg_vec vec;
+    init_msg_vec(&vec, MSG_BUF_SIZE);
+    if (send_msgs(&pair, 2) < 0) {
+        goto out;
+    }
+    // Wait for the messages to arrive
+    usleep(100 * 1000);
+    // The error of the second message is reported by the next receive, after the first
+    // message is returned
+    vec.msgs[1].msg_hdr.msg_iov = (struct iovec *)1;
+    if (recvmmsg(pair.recv_fd, vec.msgs, 2, MSG_DONTWAIT, NULL) != 1) {
+        printf("failed to return the message received before the error\n");
+        goto out;
+    }
+    if (check_msgs(&vec, 1) < 0) {
+        goto out;
+    }
+    if (recvmmsg(pair.recv_fd, vec.msgs, 1, MSG_DONTWAIT, NULL) >= 0 || errno != EFAULT) {
+        printf("failed to report the error of the previous recvmmsg\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_udp_pair(&pair);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_recvmmsg),
+    TEST_CASE(test_recvmmsg_with_waitforone),
+    TEST_CASE(test_recvmmsg_with_timeout),
+    TEST_CASE(test_recvmmsg_nonblocking),
+    TEST_CASE(test_recvmmsg_truncated),
+    TEST_CASE(test_recvmmsg_error_reported_later),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,286 @@
+#define _GNU_SOURCE
+#include <sys/ipc.h>
+#include <sys/sem.h>
+#include <sys/wait.h>
+#include <pthread.h>
+#include <spawn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+#define NSEMS 4
+
+union semun {
+    int val;
+    struct semid_ds *buf;
+    unsigned short *array;
+};
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int create_sem_set(int nsems) {
+    int semid = semget(IPC_PRIVATE, nsems, IPC_CREAT | 0600);
+    if (semid < 0) {
+        THROW_ERROR("semget failed");
+    }
+    return semid;
+}
+
+static void remove_sem_set(int semid) {
+    semctl(semid, 0, IPC_RMID);
+}
+
+static void *post_after_sleep(void *arg) {
+    int semid = *(int *)arg;
+    struct sembuf sop = { .sem_num = 0, .sem_op = 1, .sem_flg = 0 };
+    usleep(100 * 1000);
+    semop(semid, &sop, 1);
+    return NULL;
+}
+
+static void *remove_after_sleep(void *arg) {
+    int semid = *(int *)arg;
+    usleep(100 * 1000);
+    remove_sem_set(semid);
+    return NULL;
+}
+
+// ============================================================================
+// Child process
+// ============================================================================
+
+// Increase the semaphore with SEM_UNDO and exit without decreasing it
+static int child_undo(int semid) {
+    struct sembuf sop = { .sem_num = 0, .sem_op = 2, .sem_flg = SEM_UNDO };
+    if (semop(semid, &sop, 1) < 0) {
+        return -1;
+    }
+    return semctl(semid, 0, GETVAL) == 2 ? 0 : -1;
+}
+
+// ============================================================================
+// Test cases for System V semaphores
+// ============================================================================
+
+int test_semget() {
+    key_t key = 0x5e4a;
+    int semid = semget(key, NSEMS, IPC_CREAT | IPC_EXCL | 0600);
+    if (semid < 0) {
+        THROW_ERROR("semget failed");
+    }
+    if (semget(key, NSEMS, IPC_CREAT | IPC_EXCL | 0600) >= 0 || errno != EEXIST) {
+        THROW_ERROR("semget with IPC_EXCL should fail");
+    }
+    if (semget(key, 0, 0) != semid) {
+        THROW_ERROR("semget with an existing key should return the same set");
+    }
+    if (semget(key, NSEMS + 1, 0) >= 0 || errno != EINVAL) {
+        THROW_ERROR("semget with a larger nsems should fail");
+    }
+    remove_sem_set(semid);
+    if (semget(key, NSEMS, 0) >= 0 || errno != ENOENT) {
+        THROW_ERROR("the removed set should not be found");
+    }
+    return 0;
+}
+
+int test_setval_getval() {
+    int semid = create_sem_set(NSEMS);
+    union semun arg = { .val = 5 };
+    if (semctl(semid, 1, SETVAL, arg) < 0) {
+        THROW_ERROR("semctl SETVAL failed");
+    }
+    if (semctl(semid, 1, GETVAL) != 5 || semctl(semid, 0, GETVAL) != 0) {
+        THROW_ERROR("semctl GETVAL returns a wrong value");
+    }
+    if (semctl(semid, 1, GETPID) != getpid()) {
+        THROW_ERROR("semctl GETPID returns a wrong pid");
+    }
+    if (semctl(semid, NSEMS, GETVAL) >= 0 || errno != EINVAL) {
+        THROW_ERROR("semctl with an invalid semnum should fail");
+    }
+    arg.val = 40000;
+    if (semctl(semid, 1, SETVAL, arg) >= 0 || errno != ERANGE) {
+        THROW_ERROR("semctl SETVAL with a too large value should fail");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_setall_getall() {
+    int semid = create_sem_set(NSEMS);
+    unsigned short vals[NSEMS] = {1, 2, 3, 4};
+    unsigned short out[NSEMS] = {0};
+    union semun arg = { .array = vals };
+    if (semctl(semid, 0, SETALL, arg) < 0) {
+        THROW_ERROR("semctl SETALL failed");
+    }
+    arg.array = out;
+    if (semctl(semid, 0, GETALL, arg) < 0) {
+        THROW_ERROR("semctl GETALL failed");
+    }
+    for (int i = 0; i < NSEMS; i++) {
+        if (out[i] != vals[i]) {
+            THROW_ERROR("semctl GETALL returns wrong values");
+        }
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_ipc_stat_set() {
+    int semid = create_sem_set(NSEMS);
+    struct semid_ds ds;
+    union semun arg = { .buf = &ds };
+    if (semctl(semid, 0, IPC_STAT, arg) < 0) {
+        THROW_ERROR("semctl IPC_STAT failed");
+    }
+    if (ds.sem_nsems != NSEMS || (ds.sem_perm.mode & 0777) != 0600) {
+        THROW_ERROR("semctl IPC_STAT returns wrong values");
+    }
+
+    ds.sem_perm.mode = 0644;
+    if (semctl(semid, 0, IPC_SET, arg) < 0) {
+        THROW_ERROR("semctl IPC_SET failed");
+    }
+    memset(&ds, 0, sizeof(ds));
+    if (semctl(semid, 0, IPC_STAT, arg) < 0) {
+        THROW_ERROR("semctl IPC_STAT failed");
+    }
+    if ((ds.sem_perm.mode & 0777) != 0644) {
+        THROW_ERROR("semctl IPC_SET does not take effect");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_semop() {
+    int semid = create_sem_set(NSEMS);
+    struct sembuf sops[2] = {
+        { .sem_num = 0, .sem_op = 2, .sem_flg = 0 },
+        { .sem_num = 1, .sem_op = 1, .sem_flg = 0 },
+    };
+    if (semop(semid, sops, 2) < 0) {
+        THROW_ERROR("semop failed");
+    }
+
+    // The operations are performed atomically: none of them is performed
+    // if any of them would block
+    sops[0].sem_op = -1;
+    sops[1].sem_op = -2;
+    sops[1].sem_flg = IPC_NOWAIT;
+    if (semop(semid, sops, 2) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("semop with IPC_NOWAIT should fail");
+    }
+    if (semctl(semid, 0, GETVAL) != 2 || semctl(semid, 1, GETVAL) != 1) {
+        THROW_ERROR("semop is not atomic");
+    }
+
+    // Wait for zero
+    struct sembuf sop = { .sem_num = 2, .sem_op = 0, .sem_flg = 0 };
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop failed to wait for zero");
+    }
+
+    sop.sem_num = NSEMS;
+    if (semop(semid, &sop, 1) >= 0 || errno != EFBIG) {
+        THROW_ERROR("semop with an invalid sem_num should fail");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_semtimedop_timeout() {
+    int semid = create_sem_set(1);
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = 0 };
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 100 * 1000 * 1000 };
+    if (semtimedop(semid, &sop, 1, &timeout) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("semtimedop should time out");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_semop_blocking() {
+    int semid = create_sem_set(1);
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, post_after_sleep, &semid) < 0) {
+        THROW_ERROR("pthread_create failed");
+    }
+
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = 0 };
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("blocking semop failed");
+    }
+    pthread_join(thread, NULL);
+    if (semctl(semid, 0, GETVAL) != 0) {
+        THROW_ERROR("the semaphore value is wrong");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+int test_rmid_wakes_waiters() {
+    int semid = create_sem_set(1);
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, remove_after_sleep, &semid) < 0) {
+        THROW_ERROR("pthread_create failed");
+    }
+
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = 0 };
+    if (semop(semid, &sop, 1) >= 0 || errno != EIDRM) {
+        THROW_ERROR("semop on a removed set should fail with EIDRM");
+    }
+    pthread_join(thread, NULL);
+    return 0;
+}
+
+int test_sem_undo() {
+    int semid = create_sem_set(1);
+    char semid_str[16];
+    snprintf(semid_str, sizeof(semid_str), "%d", semid);
+    char *child_argv[] = {"sem", semid_str, NULL};
+
+    int child_pid, status;
+    if (posix_spawn(&child_pid, "/bin/sem", NULL, NULL, child_argv, NULL) != 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    if (waitpid(child_pid, &status, 0) < 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        THROW_ERROR("the child process failed");
+    }
+    if (semctl(semid, 0, GETVAL) != 0) {
+        THROW_ERROR("the operation of the child is not undone");
+    }
+    remove_sem_set(semid);
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_semget),
+    TEST_CASE(test_setval_getval),
+    TEST_CASE(test_setall_getall),
+    TEST_CASE(test_ipc_stat_set),
+    TEST_CASE(test_semop),
+    TEST_CASE(test_semtimedop_timeout),
+    TEST_CASE(test_semop_blocking),
+    TEST_CASE(test_rmid_wakes_waiters),
+    TEST_CASE(test_sem_undo),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        return child_undo(atoi(argv[1]));
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
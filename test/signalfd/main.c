@@ -0,0 +1,205 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/signalfd.h>
+#include <sys/syscall.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper function
+// ============================================================================
+
+static int block_signals(int sig1, int sig2) {
+    sigset_t mask;
+    sigemptyset(&mask);
+    sigaddset(&mask, sig1);
+    sigaddset(&mask, sig2);
+    if (sigprocmask(SIG_BLOCK, &mask, NULL) < 0) {
+        THROW_ERROR("failed to block the signals");
+    }
+    return 0;
+}
+
+static int create_signalfd(int sig, int flags) {
+    sigset_t mask;
+    sigemptyset(&mask);
+    sigaddset(&mask, sig);
+    int fd = signalfd(-1, &mask, flags);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a signalfd");
+    }
+    return fd;
+}
+
+// ============================================================================
+// Test cases for signalfd
+// ============================================================================
+
+static int test_read_signal() {
+    if (block_signals(SIGUSR1, SIGUSR2) < 0) {
+        return -1;
+    }
+    int fd = create_signalfd(SIGUSR1, 0);
+    if (fd < 0) {
+        return -1;
+    }
+
+    if (kill(getpid(), SIGUSR1) < 0) {
+        THROW_ERROR("failed to send the signal");
+    }
+    struct signalfd_siginfo info;
+    if (read(fd, &info, sizeof(info)) != sizeof(info)) {
+        THROW_ERROR("failed to read the signalfd");
+    }
+    if (info.ssi_signo != SIGUSR1 || info.ssi_code != SI_USER ||
+            info.ssi_pid != getpid()) {
+        THROW_ERROR("the signal info is wrong");
+    }
+
+    // The signal has been dequeued
+    sigset_t pending;
+    if (sigpending(&pending) < 0 || sigismember(&pending, SIGUSR1)) {
+        THROW_ERROR("the signal should not be pending");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_read_queued_signals() {
+    int fd = create_signalfd(SIGRTMIN, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    if (block_signals(SIGRTMIN, SIGRTMIN) < 0) {
+        return -1;
+    }
+
+    // The real-time signals are queued, and read at once
+    for (int i = 0; i < 2; i++) {
+        if (kill(getpid(), SIGRTMIN) < 0) {
+            THROW_ERROR("failed to send the signal");
+        }
+    }
+    struct signalfd_siginfo infos[4];
+    if (read(fd, infos, sizeof(infos)) != 2 * sizeof(infos[0])) {
+        THROW_ERROR("failed to read the queued signals");
+    }
+    for (int i = 0; i < 2; i++) {
+        if (infos[i].ssi_signo != SIGRTMIN || infos[i].ssi_code != SI_USER) {
+            THROW_ERROR("the signal info of the queued signal is wrong");
+        }
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_nonblocking_and_mask() {
+    int fd = create_signalfd(SIGUSR1, SFD_NONBLOCK | SFD_CLOEXEC);
+    if (fd < 0) {
+        return -1;
+    }
+    if ((fcntl(fd, F_GETFD) & FD_CLOEXEC) == 0) {
+        THROW_ERROR("SFD_CLOEXEC is not set");
+    }
+
+    struct signalfd_siginfo info;
+    if (read(fd, &info, sizeof(info)) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("reading an empty nonblocking signalfd should return EAGAIN");
+    }
+    if (read(fd, &info, sizeof(info) - 1) >= 0 || errno != EINVAL) {
+        THROW_ERROR("reading with a small buffer should return EINVAL");
+    }
+
+    // Signals out of the mask are not read
+    if (kill(getpid(), SIGUSR2) < 0) {
+        THROW_ERROR("failed to send the signal");
+    }
+    if (read(fd, &info, sizeof(info)) >= 0 || errno != EAGAIN) {
+        THROW_ERROR("signals out of the mask should not be read");
+    }
+
+    // Change the mask of the signalfd
+    sigset_t mask;
+    sigemptyset(&mask);
+    sigaddset(&mask, SIGUSR2);
+    if (signalfd(fd, &mask, 0) != fd) {
+        THROW_ERROR("failed to change the mask");
+    }
+    if (read(fd, &info, sizeof(info)) != sizeof(info) || info.ssi_signo != SIGUSR2) {
+        THROW_ERROR("failed to read the signal of the new mask");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_epoll() {
+    int fd = create_signalfd(SIGUSR1, SFD_NONBLOCK);
+    if (fd < 0) {
+        return -1;
+    }
+    int ep_fd = epoll_create1(0);
+    if (ep_fd < 0) {
+        THROW_ERROR("failed to create an epoll");
+    }
+    struct epoll_event event = { .events = EPOLLIN, .data.fd = fd };
+    if (epoll_ctl(ep_fd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        THROW_ERROR("failed to add the signalfd to the epoll");
+    }
+
+    if (epoll_wait(ep_fd, &event, 1, 0) != 0) {
+        THROW_ERROR("the signalfd should not be ready");
+    }
+    if (kill(getpid(), SIGUSR1) < 0) {
+        THROW_ERROR("failed to send the signal");
+    }
+    if (epoll_wait(ep_fd, &event, 1, 1000) != 1 || event.data.fd != fd ||
+            (event.events & EPOLLIN) == 0) {
+        THROW_ERROR("the signalfd should be ready");
+    }
+
+    struct signalfd_siginfo info;
+    if (read(fd, &info, sizeof(info)) != sizeof(info) || info.ssi_signo != SIGUSR1) {
+        THROW_ERROR("failed to read the signalfd");
+    }
+    if (epoll_wait(ep_fd, &event, 1, 0) != 0) {
+        THROW_ERROR("the signalfd should not be ready after read");
+    }
+    close(ep_fd);
+    close(fd);
+    return 0;
+}
+
+static int test_invalid_args() {
+    sigset_t mask;
+    sigemptyset(&mask);
+    if (syscall(SYS_signalfd4, -1, &mask, sizeof(mask) - 1, 0) >= 0 || errno != EINVAL) {
+        THROW_ERROR("signalfd with a wrong mask size should return EINVAL");
+    }
+    if (signalfd(-1, &mask, 0x1) >= 0 || errno != EINVAL) {
+        THROW_ERROR("signalfd with invalid flags should return EINVAL");
+    }
+    if (signalfd(STDOUT_FILENO, &mask, 0) >= 0 || errno != EINVAL) {
+        THROW_ERROR("signalfd on a non-signalfd should return EINVAL");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_read_signal),
+    TEST_CASE(test_read_queued_signals),
+    TEST_CASE(test_nonblocking_and_mask),
+    TEST_CASE(test_epoll),
+    TEST_CASE(test_invalid_args),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -10,6 +10,11 @@ TEST_NAME := $(shell basename $(CUR_DIR))
 IMAGE_DIR := $(BUILD_DIR)/test/image
 BIN := $(IMAGE_DIR)/bin/$(TEST_NAME)
 
+# A test with a jq filter of its own runs in a separate instance, whose Occlum.json is
+# the one shared by the tests patched by the filter
+CONFIG_FILTER := $(wildcard $(CUR_DIR)/config.jq)
+INSTANCE_DIR := $(BUILD_DIR)/test_$(TEST_NAME)
+
 # Occlum bin path
 OCCLUM_BIN_PATH ?= $(BUILD_DIR)/bin
 
@@ -77,8 +82,18 @@ $(BUILD_DIR)/test/obj/$(TEST_NAME)/%.o: %.cc
 #############################################################################
 
 test:
+ifeq ($(CONFIG_FILTER),)
 	@cd $(BUILD_DIR)/test && \
 		$(EXTRA_ENV) $(OCCLUM_BIN_PATH)/occlum exec /bin/$(TEST_NAME) $(BIN_ARGS)
+else
+	@$(RM) -rf $(INSTANCE_DIR) && mkdir -p $(INSTANCE_DIR)
+	@cd $(INSTANCE_DIR) && \
+		$(OCCLUM_BIN_PATH)/occlum init > /dev/null && \
+		$(RM) -rf image && cp -a $(IMAGE_DIR) image && \
+		jq -f $(CONFIG_FILTER) $(BUILD_DIR)/test/Occlum.json > Occlum.json && \
+		$(OCCLUM_BIN_PATH)/occlum build -f > /dev/null && \
+		$(EXTRA_ENV) $(OCCLUM_BIN_PATH)/occlum run /bin/$(TEST_NAME) $(BIN_ARGS)
+endif
 
 test-native:
 	@LD_LIBRARY_PATH=/usr/local/occlum/lib cd $(IMAGE_DIR) && ./bin/$(TEST_NAME) $(BIN_ARGS)
@@ -89,3 +104,4 @@ test-native:
 
 clean:
 	@-$(RM) -f $(BIN) $(DEPS_FILE) $(C_OBJS) $(CXX_OBJS)
+	@-$(RM) -rf $(INSTANCE_DIR)
@@ -0,0 +1,577 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <sys/ioctl.h>
+#include <sys/time.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+#define DGRAM_PATH          "/tmp/unix_dgram_test.sock"
+#define SEQPACKET_PATH      "/tmp/unix_seqpacket_test.sock"
+#define MSG_A               "first message"
+#define MSG_B               "second message, which is longer"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static socklen_t fill_addr(struct sockaddr_un *addr, const char *path) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+    strcpy(addr->sun_path, path);
+    return strlen(addr->sun_path) + sizeof(addr->sun_family) + 1;
+}
+
+static int bind_socket(int type, const char *path) {
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_addr(&addr, path);
+
+    unlink(path);
+    int fd = socket(AF_UNIX, type, 0);
+    if (fd < 0) {
+        return -1;
+    }
+    if (bind(fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        close(fd);
+        return -1;
+    }
+    return fd;
+}
+
+static int send_fd(int sock, int fd_to_send) {
+    char data = 'x';
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(int))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &fd_to_send, sizeof(int));
+
+    return sendmsg(sock, &msg, 0) == 1 ? 0 : -1;
+}
+
+// ============================================================================
+// Test cases for SOCK_DGRAM
+// ============================================================================
+
+static int test_dgram_sendto_unbound() {
+    int ret = -1;
+    int server_fd = bind_socket(SOCK_DGRAM, DGRAM_PATH);
+    if (server_fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+    int client_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (client_fd < 0) {
+        close(server_fd);
+        THROW_ERROR("failed to create the unbound socket");
+    }
+
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_addr(&addr, DGRAM_PATH);
+    if (sendto(client_fd, MSG_A, sizeof(MSG_A), 0, (struct sockaddr *)&addr,
+               addr_len) != sizeof(MSG_A) ||
+            sendto(client_fd, MSG_B, sizeof(MSG_B), 0, (struct sockaddr *)&addr,
+                   addr_len) != sizeof(MSG_B)) {
+        printf("failed to send the messages\n");
+        goto out;
+    }
+
+    // The boundaries of messages are preserved
+    char buf[128] = {0};
+    struct sockaddr_un from;
+    socklen_t from_len = sizeof(from);
+    if (recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&from,
+                 &from_len) != sizeof(MSG_A) || strcmp(buf, MSG_A) != 0) {
+        printf("failed to receive the first message\n");
+        goto out;
+    }
+    // The sender is unnamed
+    if (from_len != sizeof(sa_family_t) || from.sun_family != AF_UNIX) {
+        printf("the address of an unbound sender is incorrect\n");
+        goto out;
+    }
+    if (recv(server_fd, buf, sizeof(buf), 0) != sizeof(MSG_B) || strcmp(buf, MSG_B) != 0) {
+        printf("failed to receive the second message\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    close(server_fd);
+    unlink(DGRAM_PATH);
+    return ret;
+}
+
+static int test_dgram_connect_and_sender_addr() {
+    const char *client_path = "/tmp/unix_dgram_client.sock";
+    int ret = -1;
+    int server_fd = bind_socket(SOCK_DGRAM, DGRAM_PATH);
+    int client_fd = bind_socket(SOCK_DGRAM, client_path);
+    if (server_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create the bound sockets");
+    }
+
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_addr(&addr, DGRAM_PATH);
+    if (connect(client_fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            write(client_fd, MSG_A, sizeof(MSG_A)) != sizeof(MSG_A)) {
+        printf("failed to write to the connected socket\n");
+        goto out;
+    }
+
+    char buf[128] = {0};
+    struct sockaddr_un from;
+    socklen_t from_len = sizeof(from);
+    if (recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&from,
+                 &from_len) != sizeof(MSG_A) || strcmp(from.sun_path, client_path) != 0) {
+        printf("the address of a bound sender is incorrect\n");
+        goto out;
+    }
+
+    // Reply to the sender by its address
+    if (sendto(server_fd, MSG_B, sizeof(MSG_B), 0, (struct sockaddr *)&from,
+               from_len) != sizeof(MSG_B) ||
+            read(client_fd, buf, sizeof(buf)) != sizeof(MSG_B)) {
+        printf("failed to reply to the sender\n");
+        goto out;
+    }
+
+    // The destination socket is gone
+    close(server_fd);
+    server_fd = -1;
+    if (write(client_fd, MSG_A, sizeof(MSG_A)) >= 0 || errno != ECONNREFUSED) {
+        printf("sending to a closed socket should fail\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    if (server_fd >= 0) {
+        close(server_fd);
+    }
+    unlink(DGRAM_PATH);
+    unlink(client_path);
+    return ret;
+}
+
+static int test_dgram_connected_peer() {
+    const char *client_path = "/tmp/unix_dgram_client.sock";
+    int ret = -1;
+    int server_fd = bind_socket(SOCK_DGRAM, DGRAM_PATH);
+    int client_fd = bind_socket(SOCK_DGRAM, client_path);
+    int other_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (server_fd < 0 || client_fd < 0 || other_fd < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    struct sockaddr_un addr, client_addr;
+    socklen_t addr_len = fill_addr(&addr, DGRAM_PATH);
+    socklen_t client_addr_len = fill_addr(&client_addr, client_path);
+    if (connect(client_fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to connect the socket\n");
+        goto out;
+    }
+
+    // A connected socket only receives messages from its peer
+    if (sendto(other_fd, MSG_A, sizeof(MSG_A), 0, (struct sockaddr *)&client_addr,
+               client_addr_len) >= 0 || errno != EPERM) {
+        printf("sending to a socket connected to another socket should fail\n");
+        goto out;
+    }
+
+    // Connecting to AF_UNSPEC address dissolves the association
+    struct sockaddr unspec = { .sa_family = AF_UNSPEC };
+    if (connect(client_fd, &unspec, sizeof(unspec)) < 0) {
+        printf("failed to disconnect the socket\n");
+        goto out;
+    }
+    if (write(client_fd, MSG_A, sizeof(MSG_A)) >= 0 || errno != ENOTCONN) {
+        printf("writing to a disconnected socket should fail\n");
+        goto out;
+    }
+    char buf[128] = {0};
+    if (sendto(other_fd, MSG_A, sizeof(MSG_A), 0, (struct sockaddr *)&client_addr,
+               client_addr_len) != sizeof(MSG_A) ||
+            recv(client_fd, buf, sizeof(buf), 0) != sizeof(MSG_A)) {
+        printf("failed to send to the disconnected socket\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(other_fd);
+    close(client_fd);
+    close(server_fd);
+    unlink(DGRAM_PATH);
+    unlink(client_path);
+    return ret;
+}
+
+static int test_dgram_truncate_and_peek() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+
+    int ret = -1;
+    if (write(socks[0], MSG_B, sizeof(MSG_B)) != sizeof(MSG_B)) {
+        printf("failed to write the message\n");
+        goto out;
+    }
+
+    int len = 0;
+    if (ioctl(socks[1], FIONREAD, &len) < 0 || len != sizeof(MSG_B)) {
+        printf("FIONREAD should return the length of the next message\n");
+        goto out;
+    }
+
+    char buf[8];
+    if (recv(socks[1], buf, sizeof(buf), MSG_PEEK) != sizeof(buf)) {
+        printf("failed to peek the message\n");
+        goto out;
+    }
+
+    // The rest of a truncated message is discarded
+    struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+    struct msghdr msg = { .msg_iov = &iov, .msg_iovlen = 1 };
+    if (recvmsg(socks[1], &msg, MSG_TRUNC) != sizeof(MSG_B) ||
+            !(msg.msg_flags & MSG_TRUNC)) {
+        printf("the message should be truncated\n");
+        goto out;
+    }
+    if (recv(socks[1], buf, sizeof(buf), MSG_DONTWAIT) >= 0 || errno != EAGAIN) {
+        printf("the queue should be empty\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(socks[0]);
+    close(socks[1]);
+    return ret;
+}
+
+static int test_dgram_listen() {
+    int fd = bind_socket(SOCK_DGRAM, DGRAM_PATH);
+    if (fd < 0) {
+        THROW_ERROR("failed to create the bound socket");
+    }
+    int ret = listen(fd, 5);
+    int err = errno;
+    close(fd);
+    unlink(DGRAM_PATH);
+    if (ret >= 0 || err != EOPNOTSUPP) {
+        THROW_ERROR("listen on a datagram socket should fail");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for SOCK_SEQPACKET
+// ============================================================================
+
+static int test_seqpacket_accept() {
+    int ret = -1;
+    int listen_fd = bind_socket(SOCK_SEQPACKET, SEQPACKET_PATH);
+    if (listen_fd < 0 || listen(listen_fd, 5) < 0) {
+        THROW_ERROR("failed to create the listening socket");
+    }
+    int client_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    int accepted_fd = -1;
+
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_addr(&addr, SEQPACKET_PATH);
+    if (connect(client_fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to connect\n");
+        goto out;
+    }
+
+    struct pollfd pfd = { .fd = listen_fd, .events = POLLIN };
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLIN)) {
+        printf("the listening socket should be readable\n");
+        goto out;
+    }
+    accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        printf("failed to accept\n");
+        goto out;
+    }
+
+    char buf[128] = {0};
+    if (write(client_fd, MSG_A, sizeof(MSG_A)) != sizeof(MSG_A) ||
+            write(client_fd, MSG_B, sizeof(MSG_B)) != sizeof(MSG_B) ||
+            read(accepted_fd, buf, sizeof(buf)) != sizeof(MSG_A) ||
+            read(accepted_fd, buf, sizeof(buf)) != sizeof(MSG_B)) {
+        printf("the boundaries of the messages are not preserved\n");
+        goto out;
+    }
+
+    // The peer gets EOF after the socket is closed
+    close(client_fd);
+    client_fd = -1;
+    if (read(accepted_fd, buf, sizeof(buf)) != 0) {
+        printf("the peer should get EOF\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (client_fd >= 0) {
+        close(client_fd);
+    }
+    if (accepted_fd >= 0) {
+        close(accepted_fd);
+    }
+    close(listen_fd);
+    unlink(SEQPACKET_PATH);
+    return ret;
+}
+
+static int test_seqpacket_socketpair_shutdown() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_SEQPACKET, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+
+    int ret = -1;
+    char buf[128];
+    if (write(socks[0], MSG_A, sizeof(MSG_A)) != sizeof(MSG_A) ||
+            shutdown(socks[0], SHUT_WR) < 0) {
+        printf("failed to write and shutdown\n");
+        goto out;
+    }
+    // The queued message is still received before EOF
+    if (read(socks[1], buf, sizeof(buf)) != sizeof(MSG_A) ||
+            read(socks[1], buf, sizeof(buf)) != 0) {
+        printf("the peer should get the message and then EOF\n");
+        goto out;
+    }
+    if (write(socks[0], MSG_A, sizeof(MSG_A)) >= 0 || errno != EPIPE) {
+        printf("writing after shutdown should fail\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(socks[0]);
+    close(socks[1]);
+    return ret;
+}
+
+static int test_dgram_poll_out() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+    int flags = fcntl(socks[0], F_GETFL);
+    fcntl(socks[0], F_SETFL, flags | O_NONBLOCK);
+
+    int ret = -1;
+    char buf[128] = {0};
+    int num_sent = 0;
+    while (send(socks[0], buf, sizeof(buf), 0) == sizeof(buf)) {
+        num_sent++;
+    }
+    if (errno != EAGAIN || num_sent == 0) {
+        printf("the peer queue should become full\n");
+        goto out;
+    }
+
+    // The sender is not writable while the peer queue is full
+    struct pollfd pfd = { .fd = socks[0], .events = POLLOUT };
+    if (poll(&pfd, 1, 0) != 0) {
+        printf("POLLOUT should not be reported when the peer queue is full\n");
+        goto out;
+    }
+
+    // The sender waiting in poll is woken up once the peer receives a message
+    if (recv(socks[1], buf, sizeof(buf), 0) != sizeof(buf)) {
+        printf("failed to receive a message\n");
+        goto out;
+    }
+    if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLOUT)) {
+        printf("POLLOUT should be reported when the peer queue has room\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(socks[0]);
+    close(socks[1]);
+    return ret;
+}
+
+static int test_dgram_timeouts() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+
+    int ret = -1;
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 100 * 1000 };
+    struct timeval actual = { 0 };
+    socklen_t len = sizeof(actual);
+    if (setsockopt(socks[1], SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof(timeout)) < 0 ||
+            getsockopt(socks[1], SOL_SOCKET, SO_RCVTIMEO, &actual, &len) < 0 ||
+            actual.tv_sec != timeout.tv_sec || actual.tv_usec != timeout.tv_usec) {
+        printf("failed to set SO_RCVTIMEO\n");
+        goto out;
+    }
+    char buf[128] = {0};
+    if (recv(socks[1], buf, sizeof(buf), 0) >= 0 || errno != EAGAIN) {
+        printf("recv should time out with EAGAIN\n");
+        goto out;
+    }
+
+    if (setsockopt(socks[0], SOL_SOCKET, SO_SNDTIMEO, &timeout, sizeof(timeout)) < 0) {
+        printf("failed to set SO_SNDTIMEO\n");
+        goto out;
+    }
+    while (send(socks[0], buf, sizeof(buf), 0) == sizeof(buf)) {
+    }
+    if (errno != EAGAIN) {
+        printf("send should time out with EAGAIN\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(socks[0]);
+    close(socks[1]);
+    return ret;
+}
+
+// ============================================================================
+// Test cases for SCM_RIGHTS
+// ============================================================================
+
+static int test_scm_rights() {
+    int socks[2];
+    int pipe_fds[2];
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, socks) < 0 || pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create the socket pair and the pipe");
+    }
+
+    int ret = -1;
+    if (send_fd(socks[0], pipe_fds[1]) < 0) {
+        printf("failed to send the fd\n");
+        goto out;
+    }
+
+    char data;
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(int))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(socks[1], &msg, MSG_CMSG_CLOEXEC) != 1 || (msg.msg_flags & MSG_CTRUNC)) {
+        printf("failed to receive the fd\n");
+        goto out;
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS) {
+        printf("the control message is incorrect\n");
+        goto out;
+    }
+    int received_fd;
+    memcpy(&received_fd, CMSG_DATA(cmsg), sizeof(int));
+    if (!(fcntl(received_fd, F_GETFD) & FD_CLOEXEC)) {
+        printf("the received fd should be close-on-exec\n");
+        close(received_fd);
+        goto out;
+    }
+
+    // The received fd refers to the same pipe
+    char buf[sizeof(MSG_A)];
+    if (write(received_fd, MSG_A, sizeof(MSG_A)) != sizeof(MSG_A) ||
+            read(pipe_fds[0], buf, sizeof(buf)) != sizeof(MSG_A)) {
+        printf("the received fd does not work\n");
+        close(received_fd);
+        goto out;
+    }
+    close(received_fd);
+
+    // The fd is discarded without the control buffer
+    if (send_fd(socks[0], pipe_fds[1]) < 0) {
+        printf("failed to send the fd\n");
+        goto out;
+    }
+    msg.msg_control = NULL;
+    msg.msg_controllen = 0;
+    if (recvmsg(socks[1], &msg, 0) != 1 || !(msg.msg_flags & MSG_CTRUNC)) {
+        printf("MSG_CTRUNC should be set\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(socks[0]);
+    close(socks[1]);
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return ret;
+}
+
+// A socket passed to itself is released by the garbage collector once it is closed
+static int test_scm_rights_to_itself() {
+    int ret = -1;
+    int server_fd = bind_socket(SOCK_DGRAM, DGRAM_PATH);
+    int client_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (server_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_addr(&addr, DGRAM_PATH);
+    if (connect(client_fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            send_fd(client_fd, server_fd) < 0) {
+        printf("failed to pass the socket to itself\n");
+        goto out;
+    }
+    close(server_fd);
+    server_fd = -1;
+
+    // The destination socket is gone
+    if (write(client_fd, MSG_A, sizeof(MSG_A)) >= 0 || errno != ECONNREFUSED) {
+        printf("the socket passed to itself is not released\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(client_fd);
+    if (server_fd >= 0) {
+        close(server_fd);
+    }
+    unlink(DGRAM_PATH);
+    return ret;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_dgram_sendto_unbound),
+    TEST_CASE(test_dgram_connect_and_sender_addr),
+    TEST_CASE(test_dgram_connected_peer),
+    TEST_CASE(test_dgram_truncate_and_peek),
+    TEST_CASE(test_dgram_listen),
+    TEST_CASE(test_seqpacket_accept),
+    TEST_CASE(test_seqpacket_socketpair_shutdown),
+    TEST_CASE(test_dgram_poll_out),
+    TEST_CASE(test_dgram_timeouts),
+    TEST_CASE(test_scm_rights),
+    TEST_CASE(test_scm_rights_to_itself),
+};
+
+int main() {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -1,3 +1,4 @@
+#define _GNU_SOURCE
 #include <sys/syscall.h>
 #include <sys/wait.h>
 #include <sys/socket.h>
@@ -11,6 +12,8 @@
 #include <string.h>
 #include <sys/epoll.h>
 #include <pthread.h>
+#include <fcntl.h>
+#include <errno.h>
 
 #include "test.h"
 
@@ -558,6 +561,291 @@ int test_sendmsg_recvmsg() {
     return ret;
 }
 
+// ============================================================================
+// Test cases for ancillary data
+// ============================================================================
+
+static int send_fds(int sock, int *fds, int num_fds) {
+    char data = 'x';
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(int) * 4)] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = CMSG_SPACE(sizeof(int) * num_fds),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int) * num_fds);
+    memcpy(CMSG_DATA(cmsg), fds, sizeof(int) * num_fds);
+
+    return sendmsg(sock, &msg, 0) == 1 ? 0 : -1;
+}
+
+static int recv_fd(int sock) {
+    char data;
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(int))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(sock, &msg, 0) != 1) {
+        return -1;
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_type != SCM_RIGHTS) {
+        return -1;
+    }
+    int fd;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    return fd;
+}
+
+// Run by the child process: receive a fd and write the echo message into it
+static int child_recv_fd_and_echo(int sock) {
+    int fd = recv_fd(sock);
+    if (fd < 0) {
+        return -1;
+    }
+    if (write(fd, ECHO_MSG, sizeof(ECHO_MSG)) != sizeof(ECHO_MSG)) {
+        return -1;
+    }
+    close(fd);
+    return 0;
+}
+
+int test_scm_rights_inter_process() {
+    int socks[2], pipe_fds[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0 || pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create the socket pair and the pipe");
+    }
+
+    char sock_str[16];
+    snprintf(sock_str, sizeof(sock_str), "%d", socks[1]);
+    const char *child_argv[3] = {"/bin/unix_socket", sock_str, NULL};
+    int child_pid;
+    if (posix_spawn(&child_pid, child_argv[0], NULL, NULL, (char *const *)child_argv,
+                    NULL) != 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    close(socks[1]);
+
+    // The file is held by the message after the sender closes it
+    if (send_fds(socks[0], &pipe_fds[1], 1) < 0) {
+        THROW_ERROR("failed to send the fd");
+    }
+    close(pipe_fds[1]);
+
+    char buf[sizeof(ECHO_MSG)] = {0};
+    if (read(pipe_fds[0], buf, sizeof(buf)) != sizeof(ECHO_MSG) ||
+            strcmp(buf, ECHO_MSG) != 0) {
+        THROW_ERROR("failed to read the message written by the child");
+    }
+    // All the write ends are closed
+    if (read(pipe_fds[0], buf, sizeof(buf)) != 0) {
+        THROW_ERROR("the pipe should get EOF");
+    }
+
+    int status = 0;
+    if (wait4(child_pid, &status, 0, NULL) < 0 || status != 0) {
+        THROW_ERROR("the child process failed");
+    }
+    close(socks[0]);
+    close(pipe_fds[0]);
+    return 0;
+}
+
+int test_scm_rights_truncated() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+
+    int fds[2] = {STDIN_FILENO, STDOUT_FILENO};
+    if (send_fds(socks[0], fds, 2) < 0) {
+        THROW_ERROR("failed to send the fds");
+    }
+
+    // There is only room for one fd
+    char data;
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(int))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(socks[1], &msg, 0) != 1 || !(msg.msg_flags & MSG_CTRUNC)) {
+        THROW_ERROR("MSG_CTRUNC should be set");
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_len != CMSG_LEN(sizeof(int))) {
+        THROW_ERROR("one fd should be received");
+    }
+    int fd;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    close(fd);
+
+    close(socks[0]);
+    close(socks[1]);
+    return 0;
+}
+
+// The unix sockets in flight that are only reachable from each other are released
+// once they are closed
+int test_scm_rights_cycle() {
+    int socks[2], watched_socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0 ||
+            socketpair(AF_UNIX, SOCK_STREAM, 0, watched_socks) < 0) {
+        THROW_ERROR("failed to create the socket pairs");
+    }
+
+    // Each socket of the pair is queued to the other, along with watched_socks[1]
+    if (send_fds(socks[0], &socks[0], 1) < 0 ||
+            send_fds(socks[1], &socks[1], 1) < 0 ||
+            send_fds(socks[1], &watched_socks[1], 1) < 0) {
+        THROW_ERROR("failed to send the fds");
+    }
+    close(socks[0]);
+    close(socks[1]);
+    close(watched_socks[1]);
+
+    // watched_socks[1] is released along with the cycle, so its peer gets EOF
+    char buf[1];
+    fcntl(watched_socks[0], F_SETFL, O_NONBLOCK);
+    if (read(watched_socks[0], buf, sizeof(buf)) != 0) {
+        THROW_ERROR("the sockets in the cycle are not released");
+    }
+    close(watched_socks[0]);
+    return 0;
+}
+
+// The listener can be passed to the connection that is not accepted yet
+int test_scm_rights_to_pending_connection() {
+    const char *sock_path = "/tmp/unix_socket_pending_path";
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    int client_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create the unix sockets");
+    }
+
+    struct sockaddr_un addr;
+    memset(&addr, 0, sizeof(struct sockaddr_un));
+    addr.sun_family = AF_UNIX;
+    strcpy(addr.sun_path, sock_path);
+    socklen_t addr_len = strlen(addr.sun_path) + sizeof(addr.sun_family) + 1;
+    unlink(sock_path);
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            listen(listen_fd, 5) < 0 ||
+            connect(client_fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        THROW_ERROR("failed to connect");
+    }
+
+    if (send_fds(client_fd, &listen_fd, 1) < 0) {
+        THROW_ERROR("failed to pass the listener to the pending connection");
+    }
+    int accepted_fd = accept(listen_fd, NULL, NULL);
+    if (accepted_fd < 0) {
+        THROW_ERROR("failed to accept");
+    }
+    int fd = recv_fd(accepted_fd);
+    if (fd < 0) {
+        THROW_ERROR("failed to receive the listener");
+    }
+
+    close(fd);
+    close(accepted_fd);
+    close(client_fd);
+    close(listen_fd);
+    unlink(sock_path);
+    return 0;
+}
+
+static int recv_cred(int sock, struct ucred *cred) {
+    char data;
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(struct ucred))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(sock, &msg, 0) != 1) {
+        return -1;
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET ||
+            cmsg->cmsg_type != SCM_CREDENTIALS) {
+        return -1;
+    }
+    memcpy(cred, CMSG_DATA(cmsg), sizeof(struct ucred));
+    return 0;
+}
+
+int test_scm_credentials() {
+    int socks[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create the socket pair");
+    }
+
+    int enable = 1;
+    if (setsockopt(socks[1], SOL_SOCKET, SO_PASSCRED, &enable, sizeof(enable)) < 0) {
+        THROW_ERROR("failed to set SO_PASSCRED");
+    }
+    int passcred = 0;
+    socklen_t optlen = sizeof(passcred);
+    if (getsockopt(socks[1], SOL_SOCKET, SO_PASSCRED, &passcred, &optlen) < 0 ||
+            passcred != 1) {
+        THROW_ERROR("failed to get SO_PASSCRED");
+    }
+
+    // The credentials of the sender are reported without being sent explicitly
+    struct ucred cred;
+    if (write(socks[0], "x", 1) != 1 || recv_cred(socks[1], &cred) < 0 ||
+            cred.pid != getpid() || cred.uid != getuid() || cred.gid != getgid()) {
+        THROW_ERROR("failed to receive the implicit credentials");
+    }
+
+    // Send the credentials explicitly
+    char data = 'x';
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    char control[CMSG_SPACE(sizeof(struct ucred))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_CREDENTIALS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+    struct ucred sent_cred = { .pid = getpid(), .uid = getuid(), .gid = getgid() };
+    memcpy(CMSG_DATA(cmsg), &sent_cred, sizeof(sent_cred));
+    if (sendmsg(socks[0], &msg, 0) != 1 || recv_cred(socks[1], &cred) < 0 ||
+            cred.pid != sent_cred.pid) {
+        THROW_ERROR("failed to receive the explicit credentials");
+    }
+
+    // The pid must be valid
+    sent_cred.pid = 0x7fffffff;
+    memcpy(CMSG_DATA(cmsg), &sent_cred, sizeof(sent_cred));
+    if (sendmsg(socks[0], &msg, 0) >= 0 || errno != ESRCH) {
+        THROW_ERROR("sending the credentials of an invalid pid should fail");
+    }
+
+    close(socks[0]);
+    close(socks[1]);
+    return 0;
+}
+
 static test_case_t test_cases[] = {
     TEST_CASE(test_unix_socket_inter_process),
     TEST_CASE(test_socketpair_inter_process),
@@ -568,8 +856,16 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_unix_socket_rename),
     TEST_CASE(test_epoll_wait),
     TEST_CASE(test_sendmsg_recvmsg),
+    TEST_CASE(test_scm_rights_inter_process),
+    TEST_CASE(test_scm_rights_truncated),
+    TEST_CASE(test_scm_rights_cycle),
+    TEST_CASE(test_scm_rights_to_pending_connection),
+    TEST_CASE(test_scm_credentials),
 };
 
 int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        return child_recv_fd_and_echo(atoi(argv[1]));
+    }
     return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
 }
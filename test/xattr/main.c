@@ -0,0 +1,361 @@
+#define _GNU_SOURCE
+#include <sys/stat.h>
+#include <sys/xattr.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include "test_fs.h"
+
+#define XATTR_NAME "user.test_name"
+#define XATTR_VALUE "test_value"
+
+// ============================================================================
+// Helper function
+// ============================================================================
+
+static int create_file(const char *file_path) {
+    int fd = open(file_path, O_RDONLY | O_CREAT | O_TRUNC, 00666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a file");
+    }
+    close(fd);
+    return 0;
+}
+
+static int remove_file(const char *file_path) {
+    if (unlink(file_path) < 0) {
+        THROW_ERROR("failed to unlink the created file");
+    }
+    return 0;
+}
+
+static int check_xattr_value(const char *file_path, const char *name,
+                             const char *expected_value) {
+    char buf[64] = { 0 };
+    ssize_t len = getxattr(file_path, name, buf, sizeof(buf));
+    if (len != strlen(expected_value) || memcmp(buf, expected_value, len) != 0) {
+        THROW_ERROR("the value of the attribute is wrong");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for xattr
+// ============================================================================
+
+static int __test_set_and_get(const char *file_path) {
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+        THROW_ERROR("failed to set the attribute");
+    }
+    if (check_xattr_value(file_path, XATTR_NAME, XATTR_VALUE) < 0) {
+        return -1;
+    }
+
+    // Get the size of the value only
+    if (getxattr(file_path, XATTR_NAME, NULL, 0) != strlen(XATTR_VALUE)) {
+        THROW_ERROR("failed to get the size of the value");
+    }
+    char small_buf[1];
+    if (getxattr(file_path, XATTR_NAME, small_buf, sizeof(small_buf)) >= 0 ||
+            errno != ERANGE) {
+        THROW_ERROR("getxattr with a small buffer should return ERANGE");
+    }
+    if (getxattr(file_path, "user.nonexistent", NULL, 0) >= 0 || errno != ENODATA) {
+        THROW_ERROR("getxattr of a nonexistent attribute should return ENODATA");
+    }
+    return 0;
+}
+
+static int __test_create_and_replace(const char *file_path) {
+    const char *new_value = "new_value";
+
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE),
+                 XATTR_REPLACE) >= 0 || errno != ENODATA) {
+        THROW_ERROR("XATTR_REPLACE of a nonexistent attribute should return ENODATA");
+    }
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE),
+                 XATTR_CREATE) < 0) {
+        THROW_ERROR("failed to create the attribute");
+    }
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE),
+                 XATTR_CREATE) >= 0 || errno != EEXIST) {
+        THROW_ERROR("XATTR_CREATE of an existing attribute should return EEXIST");
+    }
+    if (setxattr(file_path, XATTR_NAME, new_value, strlen(new_value), XATTR_REPLACE) < 0) {
+        THROW_ERROR("failed to replace the attribute");
+    }
+    return check_xattr_value(file_path, XATTR_NAME, new_value);
+}
+
+static int __test_list_and_remove(const char *file_path) {
+    const char *names[] = { "user.a", "trusted.b", "security.c" };
+    const char expected_list[] = "security.c\0trusted.b\0user.a";
+
+    for (int i = 0; i < ARRAY_SIZE(names); i++) {
+        if (setxattr(file_path, names[i], XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+            THROW_ERROR("failed to set the attribute");
+        }
+    }
+
+    char list[64] = { 0 };
+    if (listxattr(file_path, NULL, 0) != sizeof(expected_list)) {
+        THROW_ERROR("failed to get the size of the name list");
+    }
+    if (listxattr(file_path, list, 1) >= 0 || errno != ERANGE) {
+        THROW_ERROR("listxattr with a small buffer should return ERANGE");
+    }
+    if (listxattr(file_path, list, sizeof(list)) != sizeof(expected_list) ||
+            memcmp(list, expected_list, sizeof(expected_list)) != 0) {
+        THROW_ERROR("the name list is wrong");
+    }
+
+    for (int i = 0; i < ARRAY_SIZE(names); i++) {
+        if (removexattr(file_path, names[i]) < 0) {
+            THROW_ERROR("failed to remove the attribute");
+        }
+    }
+    if (removexattr(file_path, names[0]) >= 0 || errno != ENODATA) {
+        THROW_ERROR("removexattr of a nonexistent attribute should return ENODATA");
+    }
+    if (listxattr(file_path, list, sizeof(list)) != 0) {
+        THROW_ERROR("the name list should be empty");
+    }
+    return 0;
+}
+
+static int __test_fd_variants(const char *file_path) {
+    char buf[64] = { 0 };
+    int fd = open(file_path, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open the file");
+    }
+    if (fsetxattr(fd, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+        THROW_ERROR("failed to set the attribute by fd");
+    }
+    if (fgetxattr(fd, XATTR_NAME, buf, sizeof(buf)) != strlen(XATTR_VALUE)) {
+        THROW_ERROR("failed to get the attribute by fd");
+    }
+    if (flistxattr(fd, buf, sizeof(buf)) != sizeof(XATTR_NAME)) {
+        THROW_ERROR("failed to list the attributes by fd");
+    }
+    if (fremovexattr(fd, XATTR_NAME) < 0) {
+        THROW_ERROR("failed to remove the attribute by fd");
+    }
+    close(fd);
+    return 0;
+}
+
+static int __test_symlink(const char *file_path) {
+    const char *link_path = "/root/test_xattr_link";
+    if (symlink(file_path, link_path) < 0) {
+        THROW_ERROR("failed to create the symlink");
+    }
+
+    // The attribute is set on the target
+    if (setxattr(link_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+        THROW_ERROR("failed to set the attribute through the symlink");
+    }
+    if (check_xattr_value(file_path, XATTR_NAME, XATTR_VALUE) < 0) {
+        return -1;
+    }
+    // The user attributes are not allowed on the symlink itself
+    if (lsetxattr(link_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) >= 0 ||
+            errno != EPERM) {
+        THROW_ERROR("lsetxattr of a user attribute on a symlink should return EPERM");
+    }
+    if (lgetxattr(link_path, XATTR_NAME, NULL, 0) >= 0 || errno != ENODATA) {
+        THROW_ERROR("lgetxattr of a user attribute on a symlink should return ENODATA");
+    }
+
+    if (unlink(link_path) < 0) {
+        THROW_ERROR("failed to remove the symlink");
+    }
+    return 0;
+}
+
+static int __test_invalid_args(const char *file_path) {
+    char long_name[300] = "user.";
+    memset(long_name + 5, 'a', sizeof(long_name) - 6);
+
+    if (setxattr(file_path, "system.posix_acl_access", XATTR_VALUE, strlen(XATTR_VALUE),
+                 0) >= 0 || errno != EOPNOTSUPP) {
+        THROW_ERROR("setxattr in an unsupported namespace should return EOPNOTSUPP");
+    }
+    if (setxattr(file_path, "user.", XATTR_VALUE, strlen(XATTR_VALUE), 0) >= 0 ||
+            errno != EINVAL) {
+        THROW_ERROR("setxattr with an empty name in a namespace should return EINVAL");
+    }
+    if (setxattr(file_path, long_name, XATTR_VALUE, strlen(XATTR_VALUE), 0) >= 0 ||
+            errno != ERANGE) {
+        THROW_ERROR("setxattr with a too long name should return ERANGE");
+    }
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE),
+                 0x4) >= 0 || errno != EINVAL) {
+        THROW_ERROR("setxattr with invalid flags should return EINVAL");
+    }
+
+    size_t large_size = 65536 + 1;
+    char *large_value = calloc(1, large_size);
+    if (large_value == NULL) {
+        THROW_ERROR("failed to allocate the value");
+    }
+    int ret = setxattr(file_path, XATTR_NAME, large_value, large_size, 0);
+    free(large_value);
+    if (ret >= 0 || errno != E2BIG) {
+        THROW_ERROR("setxattr with a too large value should return E2BIG");
+    }
+    return 0;
+}
+
+static int __test_removed_with_file(const char *file_path) {
+    if (setxattr(file_path, XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+        THROW_ERROR("failed to set the attribute");
+    }
+    if (remove_file(file_path) < 0 || create_file(file_path) < 0) {
+        return -1;
+    }
+    if (getxattr(file_path, XATTR_NAME, NULL, 0) >= 0 || errno != ENODATA) {
+        THROW_ERROR("the attributes of the removed file should be released");
+    }
+    return 0;
+}
+
+// A file of the read-only image layer of unionfs is copied up once its attributes
+// are set, which are kept by the copy
+static int test_image_file() {
+    const char *file_paths[] = {
+        "/bin/xattr",
+        "/bin",
+    };
+
+    for (int i = 0; i < ARRAY_SIZE(file_paths); i++) {
+        if (getxattr(file_paths[i], XATTR_NAME, NULL, 0) >= 0 || errno != ENODATA) {
+            THROW_ERROR("the file of the image should have no attributes");
+        }
+        if (setxattr(file_paths[i], XATTR_NAME, XATTR_VALUE, strlen(XATTR_VALUE), 0) < 0) {
+            THROW_ERROR("failed to set the attribute of the file of the image");
+        }
+        if (check_xattr_value(file_paths[i], XATTR_NAME, XATTR_VALUE) < 0) {
+            return -1;
+        }
+        if (removexattr(file_paths[i], XATTR_NAME) < 0) {
+            THROW_ERROR("failed to remove the attribute of the file of the image");
+        }
+    }
+    return 0;
+}
+
+// The attributes of a file system are limited to 1MB in total
+static int test_fs_size_limit() {
+    const int nfiles = 20;
+    const size_t value_size = 60000;
+    char file_path[64];
+    int ret = 0;
+
+    char *value = calloc(1, value_size);
+    if (value == NULL) {
+        THROW_ERROR("failed to allocate the value");
+    }
+    int i;
+    for (i = 0; i < nfiles; i++) {
+        snprintf(file_path, sizeof(file_path), "/dev/shm/test_xattr_limit_%d", i);
+        if (create_file(file_path) < 0) {
+            ret = -1;
+            break;
+        }
+        if (setxattr(file_path, XATTR_NAME, value, value_size, 0) < 0) {
+            if (errno != ENOSPC) {
+                printf("setxattr should fail with ENOSPC once the limit is reached\n");
+                ret = -1;
+            }
+            break;
+        }
+    }
+    if (ret == 0 && i == nfiles) {
+        printf("the attributes of a file system should be limited\n");
+        ret = -1;
+    }
+    free(value);
+
+    for (int j = 0; j <= i && j < nfiles; j++) {
+        snprintf(file_path, sizeof(file_path), "/dev/shm/test_xattr_limit_%d", j);
+        unlink(file_path);
+    }
+    if (ret < 0) {
+        THROW_ERROR("test_fs_size_limit failed");
+    }
+    return 0;
+}
+
+typedef int(*test_xattr_func_t)(const char *);
+
+static int test_xattr_framework(test_xattr_func_t fn) {
+    const char *file_paths[] = {
+        "/root/test_filesystem_xattr.txt",
+        "/dev/shm/test_filesystem_xattr.txt",
+    };
+
+    for (int i = 0; i < ARRAY_SIZE(file_paths); i++) {
+        if (create_file(file_paths[i]) < 0) {
+            return -1;
+        }
+        if (fn(file_paths[i]) < 0) {
+            return -1;
+        }
+        if (remove_file(file_paths[i]) < 0) {
+            return -1;
+        }
+    }
+    return 0;
+}
+
+static int test_set_and_get() {
+    return test_xattr_framework(__test_set_and_get);
+}
+
+static int test_create_and_replace() {
+    return test_xattr_framework(__test_create_and_replace);
+}
+
+static int test_list_and_remove() {
+    return test_xattr_framework(__test_list_and_remove);
+}
+
+static int test_fd_variants() {
+    return test_xattr_framework(__test_fd_variants);
+}
+
+static int test_symlink() {
+    return test_xattr_framework(__test_symlink);
+}
+
+static int test_invalid_args() {
+    return test_xattr_framework(__test_invalid_args);
+}
+
+static int test_removed_with_file() {
+    return test_xattr_framework(__test_removed_with_file);
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_set_and_get),
+    TEST_CASE(test_create_and_replace),
+    TEST_CASE(test_list_and_remove),
+    TEST_CASE(test_fd_variants),
+    TEST_CASE(test_symlink),
+    TEST_CASE(test_invalid_args),
+    TEST_CASE(test_removed_with_file),
+    TEST_CASE(test_image_file),
+    TEST_CASE(test_fs_size_limit),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -480,6 +480,39 @@ fn main() {
             }
         }
 
+        // Only the SwornDisk of an Ext2 can be given a key
+        for mount in &occlum_config.mount {
+            if let Some(key_str) = mount.options.key.as_ref() {
+                if mount.type_ != String::from("ext2") {
+                    println!("The key can only be specified for Ext2.");
+                    return;
+                }
+                let is_valid_key = {
+                    let bytes: Vec<&str> = key_str.split('-').collect();
+                    bytes.len() == 16
+                        && bytes
+                            .iter()
+                            .all(|byte| u8::from_str_radix(byte, 16).is_ok())
+                };
+                if !is_valid_key {
+                    println!("The key \"{}\" is not correct.", key_str);
+                    return;
+                }
+            }
+        }
+
+        // Each Ext2 is backed by its own disk, whose image is put in the source dir
+        let ext2_sources: Vec<Option<&String>> = occlum_config
+            .mount
+            .iter()
+            .filter(|mount| mount.type_ == String::from("ext2"))
+            .map(|mount| mount.source.as_ref())
+            .collect();
+        if (1..ext2_sources.len()).any(|i| ext2_sources[..i].contains(&ext2_sources[i])) {
+            println!("The source of each Ext2 must be different.");
+            return;
+        }
+
         let kss_tuple = parse_kss_conf(&occlum_config);
 
         let (misc_select, misc_mask) = if instance_is_for_edmm_platform {
@@ -572,6 +605,7 @@ fn main() {
             disable_log: disable_log,
             app: app_config,
             feature: occlum_config.feature.clone(),
+            network: occlum_config.network,
         };
 
         let occlum_json_str = serde_json::to_string_pretty(&occlum_json_config).unwrap();
@@ -764,6 +798,8 @@ struct OcclumConfiguration {
     metadata: OcclumMetadata,
     feature: OcclumFeature,
     mount: Vec<OcclumMount>,
+    #[serde(default)]
+    network: serde_json::Value,
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -819,6 +855,8 @@ struct OcclumFeature {
     enable_edmm: bool,
     #[serde(default)]
     enable_posix_shm: bool,
+    #[serde(default)]
+    enable_loopback_in_enclave: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -845,6 +883,24 @@ struct OcclumMountOptions {
     pub cache_size: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disk_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mkfs: Option<OcclumMkfsOptions>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct OcclumMkfsOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inodes_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved_ratio: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
 }
 
 #[inline]
@@ -906,4 +962,7 @@ struct InternalOcclumJson {
     disable_log: bool,
     app: serde_json::Value,
     feature: OcclumFeature,
+    // The network policy is checked by the LibOS
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    network: serde_json::Value,
 }